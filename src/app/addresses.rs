@@ -0,0 +1,160 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{
+    BytesAddress, ContentType, DataAddress, Error, RegisterAddress, Result, Scope, Url, XorName,
+    XorUrlBase,
+};
+
+/// Conversions between network addresses and `Url`s
+pub trait UrlAddressExt: Sized {
+    /// Create a XOR-URL for the content at a data address, with the given content type
+    fn from_data_address(
+        address: DataAddress,
+        content_type: ContentType,
+        base: XorUrlBase,
+    ) -> Result<Self>;
+
+    /// Create a XOR-URL for a Register, with raw content type
+    fn from_register_address(address: RegisterAddress, base: XorUrlBase) -> Result<Self>;
+
+    /// Create a XOR-URL for a Register from its XorName, type tag and scope
+    fn from_register_parts(
+        xorname: XorName,
+        type_tag: u64,
+        scope: Scope,
+        base: XorUrlBase,
+    ) -> Result<Self>;
+
+    /// Create a XOR-URL for a Blob, with raw content type
+    fn from_bytes_address(address: BytesAddress, base: XorUrlBase) -> Result<Self>;
+
+    /// The address of the Register the URL targets, failing if it targets another data type
+    fn register_address(&self) -> Result<RegisterAddress>;
+
+    /// The XorName, type tag and scope of the Register the URL targets
+    fn register_parts(&self) -> Result<(XorName, u64, Scope)>;
+
+    /// The address of the Blob the URL targets, failing if it targets another data type
+    fn bytes_address(&self) -> Result<BytesAddress>;
+}
+
+impl UrlAddressExt for Url {
+    fn from_data_address(
+        address: DataAddress,
+        content_type: ContentType,
+        base: XorUrlBase,
+    ) -> Result<Self> {
+        let type_tag = match &address {
+            DataAddress::Register(register_address) => register_address.tag(),
+            _ => 0,
+        };
+        let xorurl = Url::encode(
+            address,
+            None,
+            type_tag,
+            content_type,
+            None,
+            None,
+            None,
+            None,
+            None,
+            base,
+        )?;
+
+        Ok(Url::from_xorurl(&xorurl)?)
+    }
+
+    fn from_register_address(address: RegisterAddress, base: XorUrlBase) -> Result<Self> {
+        Self::from_data_address(DataAddress::Register(address), ContentType::Raw, base)
+    }
+
+    fn from_register_parts(
+        xorname: XorName,
+        type_tag: u64,
+        scope: Scope,
+        base: XorUrlBase,
+    ) -> Result<Self> {
+        Self::from_data_address(
+            DataAddress::register(xorname, scope, type_tag),
+            ContentType::Raw,
+            base,
+        )
+    }
+
+    fn from_bytes_address(address: BytesAddress, base: XorUrlBase) -> Result<Self> {
+        Self::from_data_address(DataAddress::Bytes(address), ContentType::Raw, base)
+    }
+
+    fn register_address(&self) -> Result<RegisterAddress> {
+        match self.address() {
+            DataAddress::Register(register_address) => Ok(register_address),
+            other => Err(Error::ContentError(format!(
+                "The url {} has an {:?} address. \
+                To fetch register entries, this url must refer to a register.",
+                self, other
+            ))),
+        }
+    }
+
+    fn register_parts(&self) -> Result<(XorName, u64, Scope)> {
+        self.register_address()
+            .map(|address| register_address_parts(&address))
+    }
+
+    fn bytes_address(&self) -> Result<BytesAddress> {
+        match self.address() {
+            DataAddress::Bytes(bytes_address) => Ok(bytes_address),
+            other => Err(Error::ContentError(format!(
+                "The url {} has an {:?} address, it must refer to a Blob.",
+                self, other
+            ))),
+        }
+    }
+}
+
+fn register_address_parts(address: &RegisterAddress) -> (XorName, u64, Scope) {
+    let scope = if address.is_public() {
+        Scope::Public
+    } else {
+        Scope::Private
+    };
+    (*address.name(), address.tag(), scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_XORURL_BASE;
+    use anyhow::Result;
+
+    #[test]
+    fn test_url_register_address_roundtrip() -> Result<()> {
+        let xorname: XorName = rand::random();
+        let url = Url::from_register_parts(xorname, 25_000, Scope::Private, DEFAULT_XORURL_BASE)?;
+        assert_eq!(url.register_parts()?, (xorname, 25_000, Scope::Private));
+
+        let address = url.register_address()?;
+        let url_from_address = Url::from_register_address(address, DEFAULT_XORURL_BASE)?;
+        assert_eq!(url_from_address, url);
+        assert!(url.bytes_address().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_url_bytes_address_roundtrip() -> Result<()> {
+        let address = BytesAddress::Public(rand::random());
+        let url = Url::from_bytes_address(address, DEFAULT_XORURL_BASE)?;
+        assert_eq!(url.bytes_address()?, address);
+        assert!(url.register_address().is_err());
+
+        Ok(())
+    }
+}
@@ -101,3 +101,26 @@ async fn send_app_auth_req(auth_req_str: &str, endpoint: Option<&str>) -> Result
     info!("SAFE authorisation response received!");
     Ok(authd_response)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Result};
+    use std::collections::BTreeSet;
+
+    #[tokio::test]
+    async fn test_connect_untrusted_network() -> Result<()> {
+        let trusted_key = bls::SecretKey::random().public_key();
+        let impostor_key = bls::SecretKey::random().public_key();
+        let mut safe = Safe::default();
+        safe.set_trusted_genesis_keys(vec![trusted_key].into_iter().collect());
+
+        match safe
+            .connect(None, None, (impostor_key, BTreeSet::new()))
+            .await
+        {
+            Err(Error::UntrustedNetwork(_)) => Ok(()),
+            other => Err(anyhow!("Unexpected result: {:?}", other)),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    encryption::{
+        decrypt_payload, derive_symmetric_key, encrypt_payload, keyed_hash, SymmetricKey,
+    },
+    helpers::gen_timestamp_secs,
+    multimap::MultimapKeyValues,
+    register::EntryHash,
+    search::IndexedKind,
+};
+use crate::{ContentType, Error, Result, Safe, Scope, Url, XorName, XorUrl};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Type tag to use for the bookmarks collection stored on a private Multimap
+const BOOKMARKS_TYPE_TAG: u64 = 1_600;
+
+// Context used to derive the location and encryption key of the bookmarks collection
+const BOOKMARKS_CONTEXT: &[u8] = b"sn_api-bookmarks";
+
+/// A bookmark stored in the user's private bookmarks collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub title: String,
+    pub url: String,
+    pub tags: BTreeSet<String>,
+    pub added_at: String,
+}
+
+impl Bookmark {
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.title.to_lowercase().contains(&query)
+            || self.url.to_lowercase().contains(&query)
+            || self.tags.iter().any(|tag| tag.to_lowercase() == query)
+    }
+}
+
+impl Safe {
+    /// # Add a bookmark to the user's private bookmarks collection.
+    ///
+    /// The collection is stored, encrypted, at a location derived from the keypair
+    /// this instance is connected with, thus it's resolvable from any of the user's devices.
+    /// Adding a URL which is already bookmarked replaces the existing bookmark.
+    pub async fn bookmarks_add(
+        &self,
+        title: &str,
+        url: &str,
+        tags: BTreeSet<String>,
+    ) -> Result<Bookmark> {
+        info!("Adding bookmark for {}", url);
+        let _ = Safe::parse_url(url)?;
+        let (xorurl, key, entries) = self.fetch_bookmarks_collection().await?;
+
+        let bookmark = Bookmark {
+            title: title.to_string(),
+            url: url.to_string(),
+            tags,
+            added_at: gen_timestamp_secs(),
+        };
+        let serialised_bookmark = rmp_serde::to_vec_named(&bookmark).map_err(|err| {
+            Error::Serialisation(format!(
+                "Couldn't serialise bookmark '{:?}': {:?}",
+                bookmark, err
+            ))
+        })?;
+
+        let lookup_key = keyed_hash(&key, url.as_bytes()).to_vec();
+        let replace = entries_for_key(&entries, &lookup_key);
+        let value = encrypt_payload(&self.encryption_policy, &key, &serialised_bookmark)?;
+        let _ = self
+            .multimap_insert(&xorurl, (lookup_key, value), replace)
+            .await?;
+        self.local_index
+            .insert(IndexedKind::Bookmark, &bookmark.title, &bookmark.url);
+
+        Ok(bookmark)
+    }
+
+    /// Remove the bookmark for the given URL from the user's bookmarks collection
+    pub async fn bookmarks_remove(&self, url: &str) -> Result<()> {
+        info!("Removing bookmark for {}", url);
+        let (xorurl, key, entries) = self.fetch_bookmarks_collection().await?;
+
+        let lookup_key = keyed_hash(&key, url.as_bytes()).to_vec();
+        let replace = entries_for_key(&entries, &lookup_key);
+        if replace.is_empty() {
+            return Err(Error::EntryNotFound(format!(
+                "No bookmark found for \"{}\"",
+                url
+            )));
+        }
+
+        // An empty value is used as a tombstone for a removed bookmark
+        let _ = self
+            .multimap_insert(&xorurl, (lookup_key, vec![]), replace)
+            .await?;
+        self.local_index.remove(IndexedKind::Bookmark, url);
+
+        Ok(())
+    }
+
+    /// List all the bookmarks in the user's bookmarks collection
+    pub async fn bookmarks_list(&self) -> Result<Vec<Bookmark>> {
+        let (_, key, entries) = self.fetch_bookmarks_collection().await?;
+
+        // If there are concurrent entries for the same URL, e.g. written from
+        // different devices, we keep the most recently added one
+        let mut bookmarks = BTreeMap::<String, Bookmark>::new();
+        for (_, (_, value)) in entries.iter().filter(|(_, (_, value))| !value.is_empty()) {
+            let serialised_bookmark = decrypt_payload(&self.encryption_policy, &key, value)?;
+            let bookmark: Bookmark =
+                rmp_serde::from_slice(&serialised_bookmark).map_err(|err| {
+                    Error::ContentError(format!("Couldn't parse bookmark: {:?}", err))
+                })?;
+            match bookmarks.get(&bookmark.url) {
+                Some(existing) if existing.added_at >= bookmark.added_at => {}
+                _ => {
+                    let _ = bookmarks.insert(bookmark.url.clone(), bookmark);
+                }
+            }
+        }
+
+        Ok(bookmarks.into_values().collect())
+    }
+
+    /// Search the user's bookmarks whose title or URL contain the query,
+    /// or which are tagged with it, ignoring case
+    pub async fn bookmarks_search(&self, query: &str) -> Result<Vec<Bookmark>> {
+        let bookmarks = self.bookmarks_list().await?;
+        Ok(bookmarks
+            .into_iter()
+            .filter(|bookmark| bookmark.matches(query))
+            .collect())
+    }
+
+    /// Export the user's bookmarks collection as a JSON document
+    pub async fn bookmarks_export(&self) -> Result<String> {
+        let bookmarks = self.bookmarks_list().await?;
+        serde_json::to_string_pretty(&bookmarks)
+            .map_err(|err| Error::Serialisation(format!("Couldn't serialise bookmarks: {:?}", err)))
+    }
+
+    // Private helper to fetch the bookmarks collection, creating it upon first use,
+    // returning its XOR-URL, its encryption key, and its current entries
+    async fn fetch_bookmarks_collection(
+        &self,
+    ) -> Result<(XorUrl, SymmetricKey, MultimapKeyValues)> {
+        let keypair = self.get_my_keypair()?;
+        let key = derive_symmetric_key(&keypair, BOOKMARKS_CONTEXT)?;
+        let xorname = XorName(keyed_hash(&key, BOOKMARKS_CONTEXT));
+        let xorurl = Url::encode_register(
+            xorname,
+            BOOKMARKS_TYPE_TAG,
+            Scope::Private,
+            ContentType::Multimap,
+            self.xorurl_base,
+        )?;
+        let safe_url = Url::from_xorurl(&xorurl)?;
+
+        let entries = match self.fetch_multimap_values(&safe_url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => MultimapKeyValues::new(),
+            Err(Error::ContentNotFound(_)) => {
+                debug!("Bookmarks collection not found, creating it");
+                let _ = self
+                    .multimap_create(Some(xorname), BOOKMARKS_TYPE_TAG, true)
+                    .await?;
+                MultimapKeyValues::new()
+            }
+            Err(err) => return Err(err),
+        };
+
+        Ok((xorurl, key, entries))
+    }
+}
+
+// Hashes of the entries in the collection which correspond to the given lookup key
+fn entries_for_key(entries: &MultimapKeyValues, lookup_key: &[u8]) -> BTreeSet<EntryHash> {
+    entries
+        .iter()
+        .filter(|(_, (key, _))| key == lookup_key)
+        .map(|(hash, _)| *hash)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app::test_helpers::{new_safe_instance, random_nrs_name},
+        retry_loop_for_pattern,
+    };
+    use anyhow::Result;
+    use std::collections::BTreeSet;
+
+    #[tokio::test]
+    async fn test_bookmarks_add_search_remove() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let site_url = format!("safe://{}", random_nrs_name());
+        let blog_url = format!("safe://{}", random_nrs_name());
+        let tag = random_nrs_name();
+
+        let tags: BTreeSet<String> = vec![tag.clone()].into_iter().collect();
+        let bookmark = safe.bookmarks_add("My Site", &site_url, tags).await?;
+        let _ = safe
+            .bookmarks_add("My Blog", &blog_url, BTreeSet::new())
+            .await?;
+
+        let _ = retry_loop_for_pattern!(safe.bookmarks_list(), Ok(v) if v.iter().any(|b| b.url == blog_url))?;
+        let bookmarks = safe.bookmarks_list().await?;
+        assert!(bookmarks.contains(&bookmark));
+
+        let found = safe.bookmarks_search(&tag.to_uppercase()).await?;
+        assert_eq!(found, vec![bookmark.clone()]);
+
+        safe.bookmarks_remove(&site_url).await?;
+        let _ = retry_loop_for_pattern!(safe.bookmarks_list(), Ok(v) if !v.contains(&bookmark))?;
+
+        let exported = safe.bookmarks_export().await?;
+        assert!(exported.contains(&blog_url));
+        assert!(!exported.contains(&site_url));
+
+        Ok(())
+    }
+}
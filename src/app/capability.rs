@@ -0,0 +1,239 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::whois::RegisterWriters;
+use crate::{DataAddress, Error, PublicKey, Result, Safe, Url, XorUrl};
+use bytes::Bytes;
+use chrono::Utc;
+use log::{debug, info};
+use safe_network::types::Signature;
+use serde::{Deserialize, Serialize};
+
+/// Access a capability grants over its target. Only read access can be granted, since
+/// the network only lets the keys in the policy of a Register write to it, see
+/// `Safe::register_grant_write`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CapabilityAccess {
+    Read,
+}
+
+// The signed part of a capability as it's stored on the network
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CapabilityGrant {
+    pub(crate) target: String,
+    pub(crate) paths: Vec<String>,
+    pub(crate) access: CapabilityAccess,
+    pub(crate) expires_at: Option<i64>,
+    pub(crate) issuer: PublicKey,
+    // Key needed to decrypt the target, if it's private content, wrapped for the grant
+    pub(crate) wrapped_key: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CapabilityRecord {
+    grant: CapabilityGrant,
+    signature: Signature,
+}
+
+/// What a capability URL grants, as reported by `Safe::preview_capability`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapabilityPreview {
+    /// URL of the content the capability grants access to
+    pub target: String,
+    /// Paths of the target the access is restricted to, empty if it's not restricted
+    pub paths: Vec<String>,
+    pub access: CapabilityAccess,
+    /// Expiry time, as seconds since the Unix epoch, if the capability expires
+    pub expires_at: Option<i64>,
+    /// Whether the capability has already expired
+    pub expired: bool,
+    /// Public key of whom issued, and signed, the capability
+    pub issuer: PublicKey,
+    /// Whether the issuer owns the target, or is allowed to write to it, thus it's entitled
+    /// to grant access over it. None if the target has no owner, e.g. it's a Blob.
+    pub issuer_authorised: Option<bool>,
+    /// Whether the capability carries the key to decrypt private content
+    pub grants_decryption: bool,
+}
+
+impl Safe {
+    /// # Create a capability URL
+    ///
+    /// The capability grants the given access over the target URL, optionally restricted to
+    /// some of its paths and until an expiry time (as seconds since the Unix epoch).
+    /// It's signed with the signer of this instance, see `set_signer`, and can be shared
+    /// with other users for them to review it with `preview_capability` before using it.
+    /// Capabilities are advisory: they record what the issuer grants, but the network
+    /// doesn't enforce them, thus it's up to the applications to honour their paths and
+    /// expiry, except for share links, which carry the key to decrypt the content.
+    pub async fn capability_create(
+        &self,
+        target: &str,
+        paths: &[&str],
+        access: CapabilityAccess,
+        expires_at: Option<i64>,
+    ) -> Result<XorUrl> {
+        info!("Creating {:?} capability over {}", access, target);
+        let _ = Safe::parse_url(target)?;
+        let grant = CapabilityGrant {
+            target: target.to_string(),
+            paths: paths.iter().map(|path| path.to_string()).collect(),
+            access,
+            expires_at,
+            issuer: self.signer_public_key()?,
+            wrapped_key: None,
+        };
+
+        self.store_capability(grant).await
+    }
+
+    /// # Preview a capability URL
+    ///
+    /// Report exactly what a capability URL, e.g. one shared by another user, grants:
+    /// its target, the paths and access it's restricted to, its expiry, and who issued it,
+    /// so the user can review it prior to using it. The target's owner and permissions are
+    /// looked up to report whether the issuer is entitled to grant access over it, but its
+    /// content is not accessed.
+    /// It fails if the URL is not a capability, or if its signature is not valid.
+    pub async fn preview_capability(&self, url: &str) -> Result<CapabilityPreview> {
+        debug!("Previewing capability: {}", url);
+        let grant = self.fetch_capability(url).await?;
+
+        // The signature only proves who issued the capability, not that the issuer has
+        // any control over the target, thus check the issuer against the target's policy
+        let (target_url, _) = self.parse_and_resolve_url(&grant.target).await?;
+        let issuer_authorised = match target_url.address() {
+            DataAddress::Register(address) => {
+                let (owner, writers) = self.safe_client.get_register_policy(address).await?;
+                Some(
+                    owner == grant.issuer
+                        || match writers {
+                            RegisterWriters::Anyone => true,
+                            RegisterWriters::Keys(keys) => keys.contains(&grant.issuer),
+                        },
+                )
+            }
+            _ => None,
+        };
+
+        Ok(CapabilityPreview {
+            expired: grant
+                .expires_at
+                .map_or(false, |expires_at| expires_at <= Utc::now().timestamp()),
+            target: grant.target,
+            paths: grant.paths,
+            access: grant.access,
+            expires_at: grant.expires_at,
+            issuer: grant.issuer,
+            issuer_authorised,
+            grants_decryption: grant.wrapped_key.is_some(),
+        })
+    }
+
+    // Sign and store a capability grant, returning the capability URL
+    pub(crate) async fn store_capability(&self, grant: CapabilityGrant) -> Result<XorUrl> {
+        let signature = self.signer()?.sign(&serialise_grant(&grant)?).await?;
+        let serialised_record = rmp_serde::to_vec_named(&CapabilityRecord { grant, signature })
+            .map_err(|err| {
+                Error::Serialisation(format!("Couldn't serialise capability: {:?}", err))
+            })?;
+
+        self.store_public_bytes(Bytes::from(serialised_record), None, false)
+            .await
+    }
+
+    // Fetch a capability grant, verifying it was signed by its issuer
+    pub(crate) async fn fetch_capability(&self, url: &str) -> Result<CapabilityGrant> {
+        let safe_url = Url::from_url(url)?;
+        let serialised_record = self.fetch_public_data(&safe_url, None).await?;
+        let record: CapabilityRecord = rmp_serde::from_slice(&serialised_record).map_err(|_| {
+            Error::ContentError(format!("Content at \"{}\" is not a capability", url))
+        })?;
+
+        let signed_bytes = serialise_grant(&record.grant)?;
+        record
+            .grant
+            .issuer
+            .verify(&record.signature, &signed_bytes)
+            .map_err(|err| {
+                Error::ContentError(format!(
+                    "Invalid signature found on capability at \"{}\": {:?}",
+                    url, err
+                ))
+            })?;
+
+        Ok(record.grant)
+    }
+}
+
+fn serialise_grant(grant: &CapabilityGrant) -> Result<Vec<u8>> {
+    rmp_serde::to_vec_named(grant)
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise capability: {:?}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_preview_capability() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let target = safe.register_create(None, 25_000, false).await?;
+        let expires_at = Utc::now().timestamp() + 3600;
+
+        let capability_url = safe
+            .capability_create(
+                &target,
+                &["/docs/"],
+                CapabilityAccess::Read,
+                Some(expires_at),
+            )
+            .await?;
+        let preview = retry_loop!(safe.preview_capability(&capability_url));
+        assert_eq!(
+            preview,
+            CapabilityPreview {
+                target: target.clone(),
+                paths: vec!["/docs/".to_string()],
+                access: CapabilityAccess::Read,
+                expires_at: Some(expires_at),
+                expired: false,
+                issuer: safe.get_my_keypair()?.public_key(),
+                issuer_authorised: Some(true),
+                grants_decryption: false,
+            }
+        );
+
+        // a capability issued over someone else's content is reported as not authorised
+        let other = new_safe_instance().await?;
+        let forged_url = other
+            .capability_create(&target, &[], CapabilityAccess::Read, None)
+            .await?;
+        let preview = retry_loop!(safe.preview_capability(&forged_url));
+        assert_eq!(preview.issuer, other.get_my_keypair()?.public_key());
+        assert_eq!(preview.issuer_authorised, Some(false));
+
+        let expired_url = safe
+            .capability_create(&target, &[], CapabilityAccess::Read, Some(0))
+            .await?;
+        let preview = retry_loop!(safe.preview_capability(&expired_url));
+        assert!(preview.expired);
+
+        // content which is not a capability cannot be previewed
+        let not_a_capability = safe
+            .store_public_bytes(Bytes::from("hello"), None, false)
+            .await?;
+        let _ = retry_loop!(safe.fetch(&not_a_capability, None));
+        assert!(safe.preview_capability(&not_a_capability).await.is_err());
+
+        Ok(())
+    }
+}
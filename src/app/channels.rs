@@ -0,0 +1,359 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{encryption::keyed_hash, helpers::gen_timestamp_secs, register::EntryHash};
+use crate::{ContentType, Error, PublicKey, Result, Safe, Scope, Url, XorName, XorUrl};
+use bytes::Bytes;
+use log::{debug, info};
+use safe_network::types::Signature;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Type tag to use for the Registers backing the update channels
+const CHANNEL_TYPE_TAG: u64 = 1_800;
+
+/// An update published on a channel, already verified to be signed by the channel's owner
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelUpdate {
+    pub channel: String,
+    /// Sequence number of the update within the channel, starting at 1
+    pub seq: u64,
+    pub payload: Bytes,
+    /// Time the update was published, in RFC3339 format
+    pub published_at: String,
+}
+
+// The signed part of an update as it's stored on the network, each
+// update links to the previous one so followers can catch up
+#[derive(Debug, Serialize, Deserialize)]
+struct UpdateContent {
+    channel: String,
+    seq: u64,
+    payload: Vec<u8>,
+    published_at: String,
+    previous: Option<XorUrl>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct UpdateRecord {
+    content: UpdateContent,
+    signature: Signature,
+}
+
+/// A subscription to an owner's channel, keeping a cursor with the
+/// sequence number of the last update received
+#[derive(Clone)]
+pub struct ChannelSubscription {
+    safe: Safe,
+    owner: PublicKey,
+    channel: String,
+    url: Url,
+    cursor: u64,
+}
+
+impl ChannelSubscription {
+    /// Sequence number of the last update received
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    /// Set the cursor, e.g. to resume from a previously persisted cursor,
+    /// or to zero to receive all the updates from the start of the channel
+    pub fn set_cursor(&mut self, cursor: u64) {
+        self.cursor = cursor;
+    }
+
+    /// Retrieve the updates published since the cursor, oldest first, advancing the cursor.
+    /// Updates whose signature is not valid for the channel's owner are rejected.
+    pub async fn next_updates(&mut self) -> Result<Vec<ChannelUpdate>> {
+        let tips = match self.safe.fetch_register_entries(&self.url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => return Ok(vec![]),
+            Err(err) => return Err(err),
+        };
+
+        // The owner normally appends to the latest update, thus there is a single tip,
+        // unless updates were published concurrently, so we walk back from all the tips
+        // and merge the updates found ordering them by sequence number and hash
+        let mut pending: Vec<Url> = tips.into_iter().map(|(_, entry)| entry).collect();
+        let mut visited = BTreeSet::new();
+        let mut found = BTreeMap::new();
+        while let Some(url) = pending.pop() {
+            if !visited.insert(url.xorname()) {
+                continue;
+            }
+            let content = self
+                .safe
+                .fetch_channel_update(&self.owner, &self.channel, &url)
+                .await?;
+            if content.seq <= self.cursor {
+                continue;
+            }
+            if let Some(previous) = &content.previous {
+                pending.push(Url::from_xorurl(previous)?);
+            }
+            let _ = found.insert((content.seq, url.xorname()), content);
+        }
+
+        let updates: Vec<ChannelUpdate> = found
+            .into_values()
+            .map(|content| ChannelUpdate {
+                channel: content.channel,
+                seq: content.seq,
+                payload: Bytes::from(content.payload),
+                published_at: content.published_at,
+            })
+            .collect();
+
+        if let Some(last) = updates.last() {
+            self.cursor = last.seq;
+        }
+        debug!(
+            "{} new updates received on channel '{}'",
+            updates.len(),
+            self.channel
+        );
+        Ok(updates)
+    }
+}
+
+impl Safe {
+    /// # Publish an update on one of the user's channels
+    ///
+    /// Channels are stored on Registers, only writable by their owner, at a location
+    /// derived from the owner's public key and the channel name, thus any follower
+    /// can subscribe to them knowing only such public key and name.
+    /// Each update is signed with the signer of this instance, see `set_signer`.
+    pub async fn publish_update(&self, channel: &str, payload: Bytes) -> Result<u64> {
+        info!("Publishing update on channel '{}'", channel);
+        let signer = self.signer()?;
+        let owner = signer.public_key();
+        let (xorname, url) = self.channel_location(&owner, channel)?;
+
+        let (parents, previous) = match self.fetch_register_entries(&url).await {
+            Ok(entries) => {
+                let parents: BTreeSet<EntryHash> = entries.iter().map(|(hash, _)| *hash).collect();
+                let mut previous: Option<(u64, Url)> = None;
+                for (_, entry) in entries.iter() {
+                    let content = self.fetch_channel_update(&owner, channel, entry).await?;
+                    if previous
+                        .as_ref()
+                        .map_or(true, |(seq, _)| content.seq > *seq)
+                    {
+                        previous = Some((content.seq, entry.clone()));
+                    }
+                }
+                (parents, previous)
+            }
+            Err(Error::EmptyContent(_)) => (BTreeSet::new(), None),
+            Err(Error::ContentNotFound(_)) => {
+                debug!("Channel '{}' not found, creating it", channel);
+                let _ = self
+                    .register_create(Some(xorname), CHANNEL_TYPE_TAG, false)
+                    .await?;
+                (BTreeSet::new(), None)
+            }
+            Err(err) => return Err(err),
+        };
+
+        let content = UpdateContent {
+            channel: channel.to_string(),
+            seq: previous.as_ref().map_or(1, |(seq, _)| seq + 1),
+            payload: payload.to_vec(),
+            published_at: gen_timestamp_secs(),
+            previous: previous.map(|(_, url)| url.to_xorurl_string()),
+        };
+        let signature = signer.sign(&serialise_content(&content)?).await?;
+        let seq = content.seq;
+        let record = UpdateRecord { content, signature };
+        let serialised_record = rmp_serde::to_vec_named(&record).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise channel update: {:?}", err))
+        })?;
+        let record_xorurl = self
+            .store_public_bytes(Bytes::from(serialised_record), None, false)
+            .await?;
+
+        let _ = self
+            .write_to_register(&url.to_string(), Url::from_xorurl(&record_xorurl)?, parents)
+            .await?;
+
+        Ok(seq)
+    }
+
+    /// Subscribe to the channel with the given name published by the owner of the public key.
+    /// The subscription starts at the beginning of the channel, use `set_cursor` to resume
+    /// from a previous subscription.
+    pub fn subscribe_channel(
+        &self,
+        owner: PublicKey,
+        channel: &str,
+    ) -> Result<ChannelSubscription> {
+        let (_, url) = self.channel_location(&owner, channel)?;
+        Ok(ChannelSubscription {
+            safe: self.clone(),
+            owner,
+            channel: channel.to_string(),
+            url,
+            cursor: 0,
+        })
+    }
+
+    // Fetch an update record, verifying it was signed by the channel's owner
+    async fn fetch_channel_update(
+        &self,
+        owner: &PublicKey,
+        channel: &str,
+        url: &Url,
+    ) -> Result<UpdateContent> {
+        let serialised_record = self.fetch_public_data(url, None).await?;
+        let record: UpdateRecord = rmp_serde::from_slice(&serialised_record)
+            .map_err(|err| Error::ContentError(format!("Couldn't parse update: {:?}", err)))?;
+
+        let signed_bytes = serialise_content(&record.content)?;
+        owner
+            .verify(&record.signature, &signed_bytes)
+            .map_err(|err| {
+                Error::ContentError(format!(
+                    "Invalid signature found on update #{} of channel '{}': {:?}",
+                    record.content.seq, channel, err
+                ))
+            })?;
+        if record.content.channel != channel {
+            return Err(Error::ContentError(format!(
+                "Update found on channel '{}' belongs to channel '{}'",
+                channel, record.content.channel
+            )));
+        }
+
+        Ok(record.content)
+    }
+
+    // Private helper to obtain the location of a channel
+    fn channel_location(&self, owner: &PublicKey, channel: &str) -> Result<(XorName, Url)> {
+        let serialised_owner = rmp_serde::to_vec(owner).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise public key: {:?}", err))
+        })?;
+        let xorname = XorName(keyed_hash(&serialised_owner, channel.as_bytes()));
+        let xorurl = Url::encode_register(
+            xorname,
+            CHANNEL_TYPE_TAG,
+            Scope::Public,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?;
+
+        Ok((xorname, Url::from_xorurl(&xorurl)?))
+    }
+}
+
+fn serialise_content(content: &UpdateContent) -> Result<Vec<u8>> {
+    rmp_serde::to_vec_named(content).map_err(|err| {
+        Error::Serialisation(format!("Couldn't serialise channel update: {:?}", err))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::test_helpers::{new_safe_instance, random_nrs_name},
+        retry_loop_for_pattern,
+    };
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_channel_publish_and_subscribe() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let channel = random_nrs_name();
+
+        assert_eq!(
+            safe.publish_update(&channel, Bytes::from("first")).await?,
+            1
+        );
+        let owner = safe.get_my_keypair()?.public_key();
+        let mut subscription = safe.subscribe_channel(owner, &channel)?;
+        let updates = retry_loop_for_pattern!(subscription.next_updates(), Ok(v) if !v.is_empty())?;
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].payload, Bytes::from("first"));
+        assert_eq!(subscription.cursor(), 1);
+
+        assert_eq!(
+            safe.publish_update(&channel, Bytes::from("second")).await?,
+            2
+        );
+        let updates = retry_loop_for_pattern!(subscription.next_updates(), Ok(v) if !v.is_empty())?;
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].payload, Bytes::from("second"));
+
+        assert_eq!(
+            safe.publish_update(&channel, Bytes::from("third")).await?,
+            3
+        );
+        let updates = retry_loop_for_pattern!(subscription.next_updates(), Ok(v) if !v.is_empty())?;
+        assert_eq!(updates[0].seq, 3);
+        assert_eq!(subscription.cursor(), 3);
+
+        // a new subscriber catches up from the start of the channel
+        let mut late_subscription = safe.subscribe_channel(owner, &channel)?;
+        let updates = late_subscription.next_updates().await?;
+        assert_eq!(updates.len(), 3);
+        assert_eq!(updates[0].seq, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_channel_concurrent_updates() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let channel = random_nrs_name();
+        let owner = safe.get_my_keypair()?.public_key();
+        let (_, url) = safe.channel_location(&owner, &channel)?;
+
+        let _ = safe.publish_update(&channel, Bytes::from("first")).await?;
+        let first =
+            retry_loop_for_pattern!(safe.fetch_register_entries(&url), Ok(e) if e.len() == 1)?;
+
+        // publish a second update, and then a concurrent one also following the first update
+        let _ = safe.publish_update(&channel, Bytes::from("second")).await?;
+        let content = UpdateContent {
+            channel: channel.clone(),
+            seq: 2,
+            payload: b"concurrent".to_vec(),
+            published_at: gen_timestamp_secs(),
+            previous: first.iter().next().map(|(_, url)| url.to_xorurl_string()),
+        };
+        let signature = safe.signer()?.sign(&serialise_content(&content)?).await?;
+        let record = rmp_serde::to_vec_named(&UpdateRecord { content, signature })?;
+        let record_xorurl = safe
+            .store_public_bytes(Bytes::from(record), None, false)
+            .await?;
+        let _ = safe
+            .write_to_register(
+                &url.to_string(),
+                Url::from_xorurl(&record_xorurl)?,
+                first.iter().map(|(hash, _)| *hash).collect(),
+            )
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.fetch_register_entries(&url), Ok(e) if e.len() == 2)?;
+
+        // updates from both tips are received
+        let mut subscription = safe.subscribe_channel(owner, &channel)?;
+        let updates = subscription.next_updates().await?;
+        assert_eq!(updates.len(), 3);
+        assert_eq!(updates[0].payload, Bytes::from("first"));
+        assert_eq!(updates[1].seq, 2);
+        assert_eq!(updates[2].seq, 2);
+        let payloads: BTreeSet<Bytes> = updates.into_iter().map(|u| u.payload).collect();
+        assert!(payloads.contains(&Bytes::from("second")));
+        assert!(payloads.contains(&Bytes::from("concurrent")));
+        assert_eq!(subscription.cursor(), 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{Result, Safe, XorName};
+use bytes::Bytes;
+use log::debug;
+use safe_network::types::BytesAddress;
+
+impl Safe {
+    /// # Store a single immutable chunk
+    ///
+    /// Low-level API for tooling working at the chunk level, e.g. explorers or custom data
+    /// structures. As opposed to `store_public_bytes`, the bytes are not self-encrypted but
+    /// stored in a single public chunk, returning the chunk's XorName, thus they need to be
+    /// too few to be self-encrypted, failing with `Error::InvalidInput` otherwise.
+    pub async fn store_chunk(&self, bytes: Bytes) -> Result<XorName> {
+        debug!("Storing raw chunk of {} bytes", bytes.len());
+        self.safe_client.store_chunk(bytes).await
+    }
+
+    /// # Get a single immutable chunk
+    ///
+    /// Low-level API to retrieve the content of a public chunk stored with `store_chunk`
+    /// by its XorName. Note the client doesn't expose the chunks a Blob was self-encrypted
+    /// into, thus those can't be retrieved individually.
+    pub async fn get_chunk(&self, xorname: XorName) -> Result<Bytes> {
+        self.safe_client
+            .get_bytes(BytesAddress::Public(xorname), None)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_store_and_get_chunk() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let bytes = Bytes::from("a raw chunk");
+
+        let xorname = safe.store_chunk(bytes.clone()).await?;
+        let chunk = retry_loop!(safe.get_chunk(xorname));
+        assert_eq!(chunk, bytes);
+
+        // too many bytes for a single chunk
+        assert!(safe
+            .store_chunk(Bytes::from(vec![0; 10_000]))
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
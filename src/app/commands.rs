@@ -0,0 +1,456 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    fetch::{Range, SafeData},
+    files::{FilesMap, ProcessedFiles},
+    nrs::{NrsMap, ProcessedEntries},
+};
+use crate::{Result, Safe, Url, VersionHash, XorName, XorUrl};
+use bytes::Bytes;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A call to the API, which can be serialised to be sent by frontends (e.g. a CLI,
+/// an RPC server or a desktop app) and executed with `Safe::execute`.
+/// Each command maps to the `Safe` method of the same name, with the same arguments.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    Fetch {
+        url: String,
+        range: Range,
+    },
+    Inspect {
+        url: String,
+    },
+    FilesContainerCreate {
+        location: Option<String>,
+        dest: Option<String>,
+        recursive: bool,
+        follow_links: bool,
+        dry_run: bool,
+    },
+    FilesContainerGet {
+        url: String,
+    },
+    FilesContainerSync {
+        location: String,
+        url: String,
+        recursive: bool,
+        follow_links: bool,
+        delete: bool,
+        update_nrs: bool,
+        dry_run: bool,
+    },
+    FilesContainerAdd {
+        source_file: String,
+        url: String,
+        force: bool,
+        update_nrs: bool,
+        follow_links: bool,
+        dry_run: bool,
+    },
+    FilesContainerRemovePath {
+        url: String,
+        recursive: bool,
+        update_nrs: bool,
+        dry_run: bool,
+    },
+    StorePublicBytes {
+        data: Vec<u8>,
+        media_type: Option<String>,
+        dry_run: bool,
+    },
+    FilesGetPublicData {
+        url: String,
+        range: Range,
+    },
+    NrsMapContainerCreate {
+        name: String,
+        link: String,
+        default: bool,
+        hard_link: bool,
+        dry_run: bool,
+    },
+    NrsMapContainerAdd {
+        name: String,
+        link: String,
+        default: bool,
+        hard_link: bool,
+        dry_run: bool,
+    },
+    NrsMapContainerRemove {
+        name: String,
+        dry_run: bool,
+    },
+    NrsMapContainerGet {
+        url: String,
+    },
+    RegisterCreate {
+        name: Option<XorName>,
+        type_tag: u64,
+        private: bool,
+    },
+    RegisterRead {
+        url: String,
+    },
+    RegisterReadEntry {
+        url: String,
+        hash: VersionHash,
+    },
+    WriteToRegister {
+        url: String,
+        entry: String,
+        parents: Vec<VersionHash>,
+    },
+    JsonCreate {
+        value: Value,
+        private: Option<bool>,
+    },
+    JsonGet {
+        url: String,
+        json_pointer: String,
+    },
+    JsonPatch {
+        url: String,
+        patch: Value,
+    },
+}
+
+/// The outcome of a `Command` executed, which can be serialised to be sent back to frontends
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "response", rename_all = "snake_case")]
+pub enum Response {
+    XorUrl {
+        xorurl: XorUrl,
+    },
+    Data {
+        data: SafeData,
+    },
+    DataChain {
+        chain: Vec<SafeData>,
+    },
+    Bytes {
+        data: Vec<u8>,
+    },
+    Files {
+        xorurl: Option<XorUrl>,
+        version: Option<VersionHash>,
+        processed_files: ProcessedFiles,
+        files_map: FilesMap,
+    },
+    Nrs {
+        xorurl: XorUrl,
+        version: Option<VersionHash>,
+        processed_entries: ProcessedEntries,
+        nrs_map: NrsMap,
+    },
+    RegisterEntries {
+        entries: Vec<(VersionHash, String)>,
+    },
+    RegisterEntry {
+        entry: String,
+    },
+    EntryHash {
+        hash: VersionHash,
+    },
+    Json {
+        version: Option<VersionHash>,
+        value: Value,
+    },
+}
+
+impl Safe {
+    /// # Execute a command
+    ///
+    /// Calls the API method the command maps to, returning its outcome as a `Response`.
+    pub async fn execute(&mut self, command: Command) -> Result<Response> {
+        debug!("Executing command: {:?}", command);
+        let response = match command {
+            Command::Fetch { url, range } => Response::Data {
+                data: self.fetch(&url, range).await?,
+            },
+            Command::Inspect { url } => Response::DataChain {
+                chain: self.inspect(&url).await?,
+            },
+            Command::FilesContainerCreate {
+                location,
+                dest,
+                recursive,
+                follow_links,
+                dry_run,
+            } => {
+                let (xorurl, processed_files, files_map) = self
+                    .files_container_create(
+                        location.as_deref(),
+                        dest.as_deref(),
+                        recursive,
+                        follow_links,
+                        dry_run,
+                    )
+                    .await?;
+                Response::Files {
+                    xorurl: Some(xorurl),
+                    version: None,
+                    processed_files,
+                    files_map,
+                }
+            }
+            Command::FilesContainerGet { url } => {
+                let (version, files_map) = self.files_container_get(&url).await?;
+                Response::Files {
+                    xorurl: None,
+                    version: Some(version),
+                    processed_files: ProcessedFiles::default(),
+                    files_map,
+                }
+            }
+            Command::FilesContainerSync {
+                location,
+                url,
+                recursive,
+                follow_links,
+                delete,
+                update_nrs,
+                dry_run,
+            } => {
+                let (version, processed_files, files_map) = self
+                    .files_container_sync(
+                        &location,
+                        &url,
+                        recursive,
+                        follow_links,
+                        delete,
+                        update_nrs,
+                        dry_run,
+                    )
+                    .await?;
+                Response::Files {
+                    xorurl: None,
+                    version: Some(version),
+                    processed_files,
+                    files_map,
+                }
+            }
+            Command::FilesContainerAdd {
+                source_file,
+                url,
+                force,
+                update_nrs,
+                follow_links,
+                dry_run,
+            } => {
+                let (version, processed_files, files_map) = self
+                    .files_container_add(
+                        &source_file,
+                        &url,
+                        force,
+                        update_nrs,
+                        follow_links,
+                        dry_run,
+                    )
+                    .await?;
+                Response::Files {
+                    xorurl: None,
+                    version: Some(version),
+                    processed_files,
+                    files_map,
+                }
+            }
+            Command::FilesContainerRemovePath {
+                url,
+                recursive,
+                update_nrs,
+                dry_run,
+            } => {
+                let (version, processed_files, files_map) = self
+                    .files_container_remove_path(&url, recursive, update_nrs, dry_run)
+                    .await?;
+                Response::Files {
+                    xorurl: None,
+                    version: Some(version),
+                    processed_files,
+                    files_map,
+                }
+            }
+            Command::StorePublicBytes {
+                data,
+                media_type,
+                dry_run,
+            } => Response::XorUrl {
+                xorurl: self
+                    .store_public_bytes(Bytes::from(data), media_type.as_deref(), dry_run)
+                    .await?,
+            },
+            Command::FilesGetPublicData { url, range } => Response::Bytes {
+                data: self.files_get_public_data(&url, range).await?.to_vec(),
+            },
+            Command::NrsMapContainerCreate {
+                name,
+                link,
+                default,
+                hard_link,
+                dry_run,
+            } => {
+                let (xorurl, processed_entries, nrs_map) = self
+                    .nrs_map_container_create(&name, &link, default, hard_link, dry_run)
+                    .await?;
+                Response::Nrs {
+                    xorurl,
+                    version: None,
+                    processed_entries,
+                    nrs_map,
+                }
+            }
+            Command::NrsMapContainerAdd {
+                name,
+                link,
+                default,
+                hard_link,
+                dry_run,
+            } => {
+                let (version, xorurl, processed_entries, nrs_map) = self
+                    .nrs_map_container_add(&name, &link, default, hard_link, dry_run)
+                    .await?;
+                Response::Nrs {
+                    xorurl,
+                    version: Some(version),
+                    processed_entries,
+                    nrs_map,
+                }
+            }
+            Command::NrsMapContainerRemove { name, dry_run } => {
+                let (version, xorurl, processed_entries, nrs_map) =
+                    self.nrs_map_container_remove(&name, dry_run).await?;
+                Response::Nrs {
+                    xorurl,
+                    version: Some(version),
+                    processed_entries,
+                    nrs_map,
+                }
+            }
+            Command::NrsMapContainerGet { url } => {
+                let (version, nrs_map) = self.nrs_map_container_get(&url).await?;
+                Response::Nrs {
+                    xorurl: url,
+                    version: Some(version),
+                    processed_entries: ProcessedEntries::default(),
+                    nrs_map,
+                }
+            }
+            Command::RegisterCreate {
+                name,
+                type_tag,
+                private,
+            } => Response::XorUrl {
+                xorurl: self.register_create(name, type_tag, private).await?,
+            },
+            Command::RegisterRead { url } => Response::RegisterEntries {
+                entries: self
+                    .register_read(&url)
+                    .await?
+                    .into_iter()
+                    .map(|(hash, entry)| (VersionHash::from(&hash), entry.to_string()))
+                    .collect(),
+            },
+            Command::RegisterReadEntry { url, hash } => Response::RegisterEntry {
+                entry: self
+                    .register_read_entry(&url, hash.entry_hash())
+                    .await?
+                    .to_string(),
+            },
+            Command::WriteToRegister {
+                url,
+                entry,
+                parents,
+            } => {
+                let parents = parents.iter().map(|version| version.entry_hash()).collect();
+                let hash = self
+                    .write_to_register(&url, Url::from_url(&entry)?, parents)
+                    .await?;
+                Response::EntryHash {
+                    hash: VersionHash::from(&hash),
+                }
+            }
+            Command::JsonCreate { value, private } => Response::XorUrl {
+                xorurl: self.json_create(&value, private).await?,
+            },
+            Command::JsonGet { url, json_pointer } => {
+                let (version, value) = self.json_get(&url, &json_pointer).await?;
+                Response::Json {
+                    version: Some(version),
+                    value,
+                }
+            }
+            Command::JsonPatch { url, patch } => Response::Json {
+                version: Some(self.json_patch(&url, &patch).await?),
+                value: patch,
+            },
+        };
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop};
+    use anyhow::{anyhow, Result};
+
+    #[test]
+    fn test_command_serialisation() -> Result<()> {
+        let command: Command = serde_json::from_str(
+            r#"{ "command": "files_get_public_data", "url": "safe://name", "range": [null, 10] }"#,
+        )?;
+        assert_eq!(
+            command,
+            Command::FilesGetPublicData {
+                url: "safe://name".to_string(),
+                range: Some((None, Some(10))),
+            }
+        );
+
+        let serialised = serde_json::to_value(&Response::XorUrl {
+            xorurl: "safe://xorurl".to_string(),
+        })?;
+        assert_eq!(
+            serialised,
+            serde_json::json!({ "response": "xor_url", "xorurl": "safe://xorurl" })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_store_and_get_public_data() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let data = b"Something super good".to_vec();
+
+        let xorurl = match safe
+            .execute(Command::StorePublicBytes {
+                data: data.clone(),
+                media_type: None,
+                dry_run: false,
+            })
+            .await?
+        {
+            Response::XorUrl { xorurl } => xorurl,
+            other => return Err(anyhow!("Unexpected response: {:?}", other)),
+        };
+
+        let response = retry_loop!(safe.execute(Command::FilesGetPublicData {
+            url: xorurl.clone(),
+            range: None,
+        }));
+        assert_eq!(response, Response::Bytes { data });
+        Ok(())
+    }
+}
@@ -26,6 +26,13 @@ pub const PREDICATE_ORIGINAL_MODIFIED: &str = "o_modified";
 pub const PREDICATE_ORIGINAL_CREATED: &str = "o_created";
 pub const PREDICATE_READONLY: &str = "readonly";
 pub const PREDICATE_MODE_BITS: &str = "mode_bits";
+pub const PREDICATE_LICENSE: &str = "license";
+pub const PREDICATE_SOURCE: &str = "source";
+pub const PREDICATE_AUTHOR: &str = "author";
+pub const PREDICATE_CREATION_TOOL: &str = "creation_tool";
+pub const PREDICATE_VARIANTS: &str = "variants";
+pub const PREDICATE_ENCODING: &str = "encoding";
+pub const PREDICATE_FOLLOW_LATEST: &str = "follow_latest";
 
 // see: https://stackoverflow.com/questions/18869772/mime-type-for-a-directory
 // We will use the FreeDesktop standard for directories and symlinks.
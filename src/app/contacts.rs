@@ -0,0 +1,755 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    encryption::{
+        decrypt_payload, derive_symmetric_key, encrypt_payload, keyed_hash, EncryptionPolicy,
+        SymmetricKey,
+    },
+    helpers::gen_timestamp_secs,
+    multimap::MultimapKeyValues,
+    register::EntryHash,
+};
+use crate::{ContentType, Error, Result, Safe, Scope, Url, XorName, XorUrl};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Type tag to use for the contacts collection stored on a private Multimap
+const CONTACTS_TYPE_TAG: u64 = 2_900;
+
+// Context used to derive the location and encryption key of the contacts collection
+const CONTACTS_CONTEXT: &[u8] = b"sn_api-contacts";
+
+// Lookup key of the user's own profile within the contacts collection
+const PROFILE_LOOKUP: &[u8] = b"profile";
+
+// Name of the vCard property holding the SAFE public key of a contact
+const VCARD_PUBLIC_KEY: &str = "X-SAFE-PUBLIC-KEY";
+
+// Name of the schema.org additional property holding the SAFE public key of a contact
+const JSONLD_PUBLIC_KEY: &str = "safePublicKey";
+
+// Maximum length in octets of a vCard line before it's folded
+const VCARD_LINE_LEN: usize = 75;
+
+/// A contact card, either stored in the user's private contacts collection,
+/// or being the user's own profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Contact {
+    /// Unique identifier of the contact, kept across imports and exports
+    pub uid: String,
+    pub name: String,
+    pub emails: Vec<String>,
+    pub phones: Vec<String>,
+    /// A URL for the contact, e.g. the safe:// URL of their site
+    pub url: Option<String>,
+    /// Hex encoded SAFE public key of the contact, e.g. to send them mail
+    pub public_key: Option<String>,
+    pub note: Option<String>,
+    pub updated_at: String,
+}
+
+impl Contact {
+    /// Create a contact card with the given name and a new unique identifier
+    pub fn new(name: &str) -> Self {
+        Self {
+            uid: gen_uid(),
+            name: name.to_string(),
+            emails: vec![],
+            phones: vec![],
+            url: None,
+            public_key: None,
+            note: None,
+            updated_at: gen_timestamp_secs(),
+        }
+    }
+}
+
+impl Safe {
+    /// # Add a contact to the user's private contacts collection.
+    ///
+    /// The collection is stored, encrypted, at a location derived from the keypair
+    /// this instance is connected with, thus it's resolvable from any of the user's devices.
+    /// Adding a contact with the same `uid` as an existing one replaces it.
+    pub async fn contacts_add(&self, contact: Contact) -> Result<Contact> {
+        info!("Adding contact '{}'", contact.name);
+        let (xorurl, key, entries) = self.fetch_contacts_collection().await?;
+        let lookup_key = contact_lookup_key(&key, &contact.uid);
+        self.store_contact(&xorurl, &key, &entries, lookup_key, contact)
+            .await
+    }
+
+    /// Remove the contact with the given `uid` from the user's contacts collection
+    pub async fn contacts_remove(&self, uid: &str) -> Result<()> {
+        info!("Removing contact with uid '{}'", uid);
+        let (xorurl, key, entries) = self.fetch_contacts_collection().await?;
+
+        let lookup_key = contact_lookup_key(&key, uid);
+        let replace = entries_for_key(&entries, &lookup_key);
+        if replace.is_empty() {
+            return Err(Error::EntryNotFound(format!(
+                "No contact found with uid \"{}\"",
+                uid
+            )));
+        }
+
+        // An empty value is used as a tombstone for a removed contact
+        let _ = self
+            .multimap_insert(&xorurl, (lookup_key, vec![]), replace)
+            .await?;
+
+        Ok(())
+    }
+
+    /// List all the contacts in the user's contacts collection, sorted by name
+    pub async fn contacts_list(&self) -> Result<Vec<Contact>> {
+        let (_, key, entries) = self.fetch_contacts_collection().await?;
+        let profile_key = keyed_hash(&key, PROFILE_LOOKUP).to_vec();
+        let mut contacts: Vec<Contact> =
+            latest_contacts(&self.encryption_policy, &key, &entries, |lookup_key| {
+                lookup_key != profile_key.as_slice()
+            })?
+            .into_values()
+            .collect();
+        contacts.sort_by_key(|contact| contact.name.to_lowercase());
+
+        Ok(contacts)
+    }
+
+    /// # Import the contacts of a vCard document into the user's contacts collection
+    ///
+    /// All the vCards found in the document are imported, replacing the existing contacts
+    /// with the same `UID`, thus an address book exported from another application, or
+    /// with `contacts_export_vcard`, can be imported more than once. The imported
+    /// contacts are returned.
+    pub async fn contacts_import_vcard(&self, vcard: &str) -> Result<Vec<Contact>> {
+        let contacts = contacts_from_vcard(vcard)?;
+        self.import_contacts(contacts).await
+    }
+
+    /// # Import the contacts of a JSON-LD document into the user's contacts collection
+    ///
+    /// The document can be a single schema.org `Person`, an array of them, or a graph of
+    /// them, as exported by `contacts_export_jsonld`. Existing contacts with the same
+    /// `identifier` are replaced. The imported contacts are returned.
+    pub async fn contacts_import_jsonld(&self, jsonld: &str) -> Result<Vec<Contact>> {
+        let contacts = contacts_from_jsonld(jsonld)?;
+        self.import_contacts(contacts).await
+    }
+
+    /// Export the user's contacts collection as a vCard document
+    pub async fn contacts_export_vcard(&self) -> Result<String> {
+        let contacts = self.contacts_list().await?;
+        Ok(contacts_to_vcard(&contacts))
+    }
+
+    /// Export the user's contacts collection as a JSON-LD document of schema.org `Person`s
+    pub async fn contacts_export_jsonld(&self) -> Result<String> {
+        let contacts = self.contacts_list().await?;
+        contacts_to_jsonld(&contacts)
+    }
+
+    /// # Set the user's own profile.
+    ///
+    /// The profile is a contact card stored, encrypted, along with the user's contacts,
+    /// thus it can be shared with others exporting it, e.g. with `contacts_to_vcard`.
+    pub async fn profile_set(&self, profile: Contact) -> Result<Contact> {
+        info!("Setting profile '{}'", profile.name);
+        let (xorurl, key, entries) = self.fetch_contacts_collection().await?;
+        let lookup_key = keyed_hash(&key, PROFILE_LOOKUP).to_vec();
+        self.store_contact(&xorurl, &key, &entries, lookup_key, profile)
+            .await
+    }
+
+    /// Get the user's own profile, if it was set
+    pub async fn profile_get(&self) -> Result<Option<Contact>> {
+        let (_, key, entries) = self.fetch_contacts_collection().await?;
+        let profile_key = keyed_hash(&key, PROFILE_LOOKUP).to_vec();
+        let profile = latest_contacts(&self.encryption_policy, &key, &entries, |lookup_key| {
+            lookup_key == profile_key.as_slice()
+        })?
+        .into_values()
+        .next();
+
+        Ok(profile)
+    }
+
+    // Private helper to import contacts into the collection, fetching it only once
+    async fn import_contacts(&self, contacts: Vec<Contact>) -> Result<Vec<Contact>> {
+        info!("Importing {} contacts", contacts.len());
+        let (xorurl, key, entries) = self.fetch_contacts_collection().await?;
+        let mut imported = Vec::with_capacity(contacts.len());
+        for contact in contacts {
+            let lookup_key = contact_lookup_key(&key, &contact.uid);
+            imported.push(
+                self.store_contact(&xorurl, &key, &entries, lookup_key, contact)
+                    .await?,
+            );
+        }
+
+        Ok(imported)
+    }
+
+    // Private helper to store a contact in the collection, replacing
+    // any existing entries for the same lookup key
+    async fn store_contact(
+        &self,
+        xorurl: &str,
+        key: &SymmetricKey,
+        entries: &MultimapKeyValues,
+        lookup_key: Vec<u8>,
+        mut contact: Contact,
+    ) -> Result<Contact> {
+        contact.updated_at = gen_timestamp_secs();
+        let serialised_contact = rmp_serde::to_vec_named(&contact).map_err(|err| {
+            Error::Serialisation(format!(
+                "Couldn't serialise contact '{:?}': {:?}",
+                contact, err
+            ))
+        })?;
+
+        let replace = entries_for_key(entries, &lookup_key);
+        let value = encrypt_payload(&self.encryption_policy, key, &serialised_contact)?;
+        let _ = self
+            .multimap_insert(xorurl, (lookup_key, value), replace)
+            .await?;
+
+        Ok(contact)
+    }
+
+    // Private helper to fetch the contacts collection, creating it upon first use,
+    // returning its XOR-URL, its encryption key, and its current entries
+    async fn fetch_contacts_collection(&self) -> Result<(XorUrl, SymmetricKey, MultimapKeyValues)> {
+        let keypair = self.get_my_keypair()?;
+        let key = derive_symmetric_key(&keypair, CONTACTS_CONTEXT)?;
+        let xorname = XorName(keyed_hash(&key, CONTACTS_CONTEXT));
+        let xorurl = Url::encode_register(
+            xorname,
+            CONTACTS_TYPE_TAG,
+            Scope::Private,
+            ContentType::Multimap,
+            self.xorurl_base,
+        )?;
+        let safe_url = Url::from_xorurl(&xorurl)?;
+
+        let entries = match self.fetch_multimap_values(&safe_url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => MultimapKeyValues::new(),
+            Err(Error::ContentNotFound(_)) => {
+                debug!("Contacts collection not found, attempting to create it");
+                let _ = self
+                    .multimap_create(Some(xorname), CONTACTS_TYPE_TAG, true)
+                    .await?;
+                MultimapKeyValues::new()
+            }
+            Err(err) => return Err(err),
+        };
+
+        Ok((xorurl, key, entries))
+    }
+}
+
+/// # Render contact cards as a vCard (version 4.0) document
+///
+/// The SAFE public key of a contact, if any, is rendered as an `X-SAFE-PUBLIC-KEY`
+/// property, which other applications preserve but ignore.
+pub fn contacts_to_vcard(contacts: &[Contact]) -> String {
+    let mut vcard = String::new();
+    for contact in contacts {
+        let mut lines = vec![
+            "BEGIN:VCARD".to_string(),
+            "VERSION:4.0".to_string(),
+            format!("UID:{}", escape_vcard(&contact.uid)),
+            format!("FN:{}", escape_vcard(&contact.name)),
+        ];
+        lines.extend(
+            contact
+                .emails
+                .iter()
+                .map(|email| format!("EMAIL:{}", escape_vcard(email))),
+        );
+        lines.extend(
+            contact
+                .phones
+                .iter()
+                .map(|phone| format!("TEL;VALUE=text:{}", escape_vcard(phone))),
+        );
+        if let Some(url) = &contact.url {
+            lines.push(format!("URL:{}", escape_vcard(url)));
+        }
+        if let Some(public_key) = &contact.public_key {
+            lines.push(format!("{}:{}", VCARD_PUBLIC_KEY, escape_vcard(public_key)));
+        }
+        if let Some(note) = &contact.note {
+            lines.push(format!("NOTE:{}", escape_vcard(note)));
+        }
+        lines.push("END:VCARD".to_string());
+
+        for line in lines {
+            vcard.push_str(&fold_vcard_line(&line));
+            vcard.push_str("\r\n");
+        }
+    }
+
+    vcard
+}
+
+/// # Parse the contact cards of a vCard document
+///
+/// Versions 2.1, 3.0 and 4.0 are supported, as long as their values are not encoded,
+/// e.g. quoted-printable values of vCard 2.1 are taken verbatim. Cards without a `UID`
+/// are given a new one, and the structured name (`N`) is used for cards without a
+/// formatted name (`FN`). Properties other than the ones of `Contact` are ignored.
+pub fn contacts_from_vcard(vcard: &str) -> Result<Vec<Contact>> {
+    // Lines starting with whitespace continue the previous line
+    let mut lines = Vec::<String>::new();
+    for line in vcard.lines() {
+        match line.strip_prefix(|c: char| c == ' ' || c == '\t') {
+            Some(continuation) if !lines.is_empty() => {
+                if let Some(last) = lines.last_mut() {
+                    last.push_str(continuation);
+                }
+            }
+            _ => {
+                if !line.trim().is_empty() {
+                    lines.push(line.to_string());
+                }
+            }
+        }
+    }
+
+    let mut contacts = vec![];
+    let mut current: Option<(Contact, Option<String>)> = None;
+    for line in lines {
+        let (name, value) = match line.find(':') {
+            Some(index) => (&line[..index], &line[index + 1..]),
+            None => {
+                return Err(Error::InvalidInput(format!(
+                    "Invalid vCard line, missing ':' separator: {}",
+                    line
+                )))
+            }
+        };
+        // Drop the parameters and the group of the property, e.g. 'item1.EMAIL;TYPE=work'
+        let name = name.split(';').next().unwrap_or_default();
+        let name = name.rsplit('.').next().unwrap_or_default().to_uppercase();
+
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value.trim().eq_ignore_ascii_case("VCARD") => {
+                let mut contact = Contact::new("");
+                contact.uid = String::new();
+                current = Some((contact, None));
+            }
+            ("END", Some(_)) if value.trim().eq_ignore_ascii_case("VCARD") => {
+                if let Some((mut contact, structured_name)) = current.take() {
+                    if contact.name.is_empty() {
+                        contact.name = structured_name.ok_or_else(|| {
+                            Error::InvalidInput(
+                                "Invalid vCard, it has neither a FN nor a N property".to_string(),
+                            )
+                        })?;
+                    }
+                    if contact.uid.is_empty() {
+                        contact.uid = gen_uid();
+                    }
+                    contacts.push(contact);
+                }
+            }
+            ("FN", Some((contact, _))) => contact.name = unescape_vcard(value),
+            ("N", Some((_, structured_name))) => {
+                // Family; Given; Additional; Prefixes; Suffixes
+                let parts: Vec<String> = split_vcard_value(value);
+                let order = [3, 1, 2, 0, 4];
+                let name = order
+                    .iter()
+                    .filter_map(|index| parts.get(*index))
+                    .filter(|part| !part.is_empty())
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if !name.is_empty() {
+                    *structured_name = Some(name);
+                }
+            }
+            ("UID", Some((contact, _))) => {
+                contact.uid = unescape_vcard(value)
+                    .trim_start_matches("urn:uuid:")
+                    .to_string()
+            }
+            ("EMAIL", Some((contact, _))) => contact.emails.push(unescape_vcard(value)),
+            ("TEL", Some((contact, _))) => contact
+                .phones
+                .push(unescape_vcard(value).trim_start_matches("tel:").to_string()),
+            ("URL", Some((contact, _))) => contact.url = Some(unescape_vcard(value)),
+            ("NOTE", Some((contact, _))) => contact.note = Some(unescape_vcard(value)),
+            (property, Some((contact, _))) if property == VCARD_PUBLIC_KEY => {
+                contact.public_key = Some(unescape_vcard(value))
+            }
+            ("BEGIN", _) | ("END", _) => {
+                return Err(Error::InvalidInput(format!(
+                    "Invalid vCard, unexpected '{}' line",
+                    line
+                )))
+            }
+            _ => {}
+        }
+    }
+
+    if current.is_some() {
+        return Err(Error::InvalidInput(
+            "Invalid vCard, missing 'END:VCARD' line".to_string(),
+        ));
+    }
+
+    Ok(contacts)
+}
+
+/// # Render contact cards as a JSON-LD document
+///
+/// Each contact is rendered as a schema.org `Person` of a graph, with its SAFE public key,
+/// if any, as an additional property named `safePublicKey`.
+pub fn contacts_to_jsonld(contacts: &[Contact]) -> Result<String> {
+    let graph: Vec<Value> = contacts
+        .iter()
+        .map(|contact| {
+            let mut person = json!({
+                "@type": "Person",
+                "identifier": contact.uid,
+                "name": contact.name,
+                "email": contact.emails,
+                "telephone": contact.phones,
+            });
+            if let Some(url) = &contact.url {
+                person["url"] = json!(url);
+            }
+            if let Some(note) = &contact.note {
+                person["description"] = json!(note);
+            }
+            if let Some(public_key) = &contact.public_key {
+                person["additionalProperty"] = json!([{
+                    "@type": "PropertyValue",
+                    "name": JSONLD_PUBLIC_KEY,
+                    "value": public_key,
+                }]);
+            }
+            person
+        })
+        .collect();
+
+    let document = json!({
+        "@context": "https://schema.org",
+        "@graph": graph,
+    });
+    serde_json::to_string_pretty(&document)
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise contacts: {:?}", err)))
+}
+
+/// # Parse the contact cards of a JSON-LD document
+///
+/// The document can be a single schema.org `Person`, an array of them, or an object with
+/// a `@graph` of them. Nodes of other types are ignored, and persons without an
+/// `identifier` are given a new one.
+pub fn contacts_from_jsonld(jsonld: &str) -> Result<Vec<Contact>> {
+    let document: Value = serde_json::from_str(jsonld)
+        .map_err(|err| Error::InvalidInput(format!("Invalid JSON-LD document: {}", err)))?;
+    let nodes = match document {
+        Value::Array(nodes) => nodes,
+        Value::Object(mut object) => match object.remove("@graph") {
+            Some(Value::Array(nodes)) => nodes,
+            Some(_) => {
+                return Err(Error::InvalidInput(
+                    "Invalid JSON-LD document, '@graph' is not an array".to_string(),
+                ))
+            }
+            None => vec![Value::Object(object)],
+        },
+        _ => {
+            return Err(Error::InvalidInput(
+                "Invalid JSON-LD document, it's neither an object nor an array".to_string(),
+            ))
+        }
+    };
+
+    let mut contacts = vec![];
+    for node in nodes {
+        let is_person = match &node["@type"] {
+            Value::String(kind) => is_person_type(kind),
+            Value::Array(kinds) => kinds
+                .iter()
+                .any(|kind| kind.as_str().map(is_person_type).unwrap_or(false)),
+            _ => false,
+        };
+        if !is_person {
+            continue;
+        }
+
+        let name = match node["name"].as_str() {
+            Some(name) => name.to_string(),
+            None => {
+                let name = ["givenName", "familyName"]
+                    .iter()
+                    .filter_map(|field| node[*field].as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if name.is_empty() {
+                    return Err(Error::InvalidInput(
+                        "Invalid JSON-LD Person, it has no name".to_string(),
+                    ));
+                }
+                name
+            }
+        };
+
+        let mut contact = Contact::new(&name);
+        if let Some(uid) = node["identifier"].as_str() {
+            contact.uid = uid.to_string();
+        }
+        contact.emails = jsonld_strings(&node["email"])
+            .into_iter()
+            .map(|email| email.trim_start_matches("mailto:").to_string())
+            .collect();
+        contact.phones = jsonld_strings(&node["telephone"])
+            .into_iter()
+            .map(|phone| phone.trim_start_matches("tel:").to_string())
+            .collect();
+        contact.url = jsonld_strings(&node["url"]).into_iter().next();
+        contact.note = node["description"].as_str().map(|note| note.to_string());
+        contact.public_key = match &node["additionalProperty"] {
+            Value::Array(properties) => properties.iter().find_map(jsonld_public_key),
+            property => jsonld_public_key(property),
+        };
+        contacts.push(contact);
+    }
+
+    Ok(contacts)
+}
+
+// Contacts of the collection whose lookup key is accepted by the filter, keeping
+// the most recently updated one where there are concurrent entries for the same key
+fn latest_contacts(
+    policy: &EncryptionPolicy,
+    key: &SymmetricKey,
+    entries: &MultimapKeyValues,
+    filter: impl Fn(&[u8]) -> bool,
+) -> Result<BTreeMap<Vec<u8>, Contact>> {
+    let mut contacts = BTreeMap::<Vec<u8>, Contact>::new();
+    for (_, (lookup_key, value)) in entries
+        .iter()
+        .filter(|(_, (lookup_key, value))| !value.is_empty() && filter(lookup_key))
+    {
+        let serialised_contact = decrypt_payload(policy, key, value)?;
+        let contact: Contact = rmp_serde::from_slice(&serialised_contact)
+            .map_err(|err| Error::ContentError(format!("Couldn't parse contact: {:?}", err)))?;
+        match contacts.get(lookup_key) {
+            Some(existing) if existing.updated_at >= contact.updated_at => {}
+            _ => {
+                let _ = contacts.insert(lookup_key.clone(), contact);
+            }
+        }
+    }
+
+    Ok(contacts)
+}
+
+// Lookup key of a contact within the collection
+fn contact_lookup_key(key: &SymmetricKey, uid: &str) -> Vec<u8> {
+    let mut lookup = b"contact:".to_vec();
+    lookup.extend_from_slice(uid.as_bytes());
+    keyed_hash(key, &lookup).to_vec()
+}
+
+// Hashes of the entries in the collection which correspond to the given lookup key
+fn entries_for_key(entries: &MultimapKeyValues, lookup_key: &[u8]) -> BTreeSet<EntryHash> {
+    entries
+        .iter()
+        .filter(|(_, (key, _))| key == lookup_key)
+        .map(|(hash, _)| *hash)
+        .collect()
+}
+
+fn gen_uid() -> String {
+    hex::encode(rand::random::<[u8; 16]>())
+}
+
+fn escape_vcard(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+}
+
+fn unescape_vcard(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+// Split a structured vCard value on the ';' separators which aren't escaped
+fn split_vcard_value(value: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut escaped = false;
+    for c in value.chars() {
+        if c == ';' && !escaped {
+            parts.push(unescape_vcard(&std::mem::take(&mut part)));
+        } else {
+            part.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    parts.push(unescape_vcard(&part));
+    parts
+}
+
+// Fold a vCard line into lines of at most 75 octets, without splitting characters
+fn fold_vcard_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut line_len = 0;
+    for c in line.chars() {
+        if line_len + c.len_utf8() > VCARD_LINE_LEN {
+            folded.push_str("\r\n ");
+            line_len = 1;
+        }
+        folded.push(c);
+        line_len += c.len_utf8();
+    }
+    folded
+}
+
+fn is_person_type(kind: &str) -> bool {
+    kind == "Person" || kind.ends_with("schema.org/Person")
+}
+
+// Values of a JSON-LD property which can be either a string or an array of them
+fn jsonld_strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(value) => vec![value.clone()],
+        Value::Array(values) => values
+            .iter()
+            .filter_map(|value| value.as_str().map(|value| value.to_string()))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn jsonld_public_key(property: &Value) -> Option<String> {
+    if property["name"].as_str() == Some(JSONLD_PUBLIC_KEY) {
+        property["value"].as_str().map(|value| value.to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop_for_pattern};
+    use anyhow::Result;
+
+    fn sample_contact() -> Contact {
+        let mut contact = Contact::new("Ada Lovelace, Countess");
+        contact.emails = vec!["ada@example.com".to_string()];
+        contact.phones = vec!["+44 20 7946 0000".to_string()];
+        contact.url = Some("safe://ada".to_string());
+        contact.public_key = Some("a".repeat(96));
+        contact.note = Some("Analytical Engine;\nnotes".to_string());
+        contact
+    }
+
+    #[test]
+    fn test_contacts_vcard_roundtrip() -> Result<()> {
+        let contact = sample_contact();
+        let vcard = contacts_to_vcard(&[contact.clone()]);
+        assert!(vcard.lines().all(|line| line.len() <= VCARD_LINE_LEN));
+
+        let parsed = contacts_from_vcard(&vcard)?;
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].uid, contact.uid);
+        assert_eq!(parsed[0].name, contact.name);
+        assert_eq!(parsed[0].emails, contact.emails);
+        assert_eq!(parsed[0].phones, contact.phones);
+        assert_eq!(parsed[0].url, contact.url);
+        assert_eq!(parsed[0].public_key, contact.public_key);
+        assert_eq!(parsed[0].note, contact.note);
+        Ok(())
+    }
+
+    #[test]
+    fn test_contacts_from_vcard_3() -> Result<()> {
+        let vcard = "BEGIN:VCARD\r\nVERSION:3.0\r\nN:Hopper;Grace;Brewster;Rear Adm.;\r\n\
+            item1.EMAIL;TYPE=INTERNET,WORK:grace@example.com\r\n\
+            TEL;TYPE=CELL:+1 555\r\n 0100\r\nX-UNKNOWN:ignored\r\nEND:VCARD\r\n";
+        let parsed = contacts_from_vcard(vcard)?;
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].name, "Rear Adm. Grace Brewster Hopper");
+        assert_eq!(parsed[0].emails, vec!["grace@example.com".to_string()]);
+        assert_eq!(parsed[0].phones, vec!["+1 5550100".to_string()]);
+        assert!(!parsed[0].uid.is_empty());
+
+        assert!(contacts_from_vcard("BEGIN:VCARD\r\nFN:Unterminated\r\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_contacts_jsonld_roundtrip() -> Result<()> {
+        let contact = sample_contact();
+        let jsonld = contacts_to_jsonld(&[contact.clone()])?;
+        let parsed = contacts_from_jsonld(&jsonld)?;
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].uid, contact.uid);
+        assert_eq!(parsed[0].name, contact.name);
+        assert_eq!(parsed[0].public_key, contact.public_key);
+        assert_eq!(parsed[0].note, contact.note);
+
+        let single = r#"{"@context":"https://schema.org","@type":"Person",
+            "givenName":"Alan","familyName":"Turing","email":"mailto:alan@example.com"}"#;
+        let parsed = contacts_from_jsonld(single)?;
+        assert_eq!(parsed[0].name, "Alan Turing");
+        assert_eq!(parsed[0].emails, vec!["alan@example.com".to_string()]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_contacts_import_export() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let contact = sample_contact();
+        let imported = safe
+            .contacts_import_vcard(&contacts_to_vcard(&[contact.clone()]))
+            .await?;
+        assert_eq!(imported.len(), 1);
+
+        let _ = retry_loop_for_pattern!(safe.contacts_list(), Ok(v) if v.iter().any(|c| c.uid == contact.uid))?;
+        let exported = safe.contacts_export_jsonld().await?;
+        assert!(exported.contains(&contact.uid));
+
+        let profile = safe.profile_set(Contact::new("Me")).await?;
+        let _ = retry_loop_for_pattern!(safe.profile_get(), Ok(Some(_)))?;
+        assert_eq!(safe.profile_get().await?, Some(profile));
+        // the profile is not listed among the contacts
+        assert!(safe.contacts_list().await?.iter().all(|c| c.name != "Me"));
+
+        safe.contacts_remove(&contact.uid).await?;
+        let _ = retry_loop_for_pattern!(safe.contacts_list(), Ok(v) if v.is_empty())?;
+        Ok(())
+    }
+}
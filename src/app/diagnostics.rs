@@ -0,0 +1,151 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::Safe;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Counts of the network operations sent by an instance
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Diagnostics {
+    /// Queries sent to read data from the network
+    pub queries: u64,
+    /// Commands sent to store or write data on the network
+    pub commands: u64,
+    /// Operations retried by the API after failing
+    pub retries: u64,
+    /// Bytes of content sent with commands
+    pub bytes_sent: u64,
+    /// Bytes of content received from queries
+    pub bytes_received: u64,
+}
+
+impl Diagnostics {
+    /// Counts of the operations sent since an earlier snapshot was taken
+    pub fn since(&self, earlier: &Diagnostics) -> Diagnostics {
+        Diagnostics {
+            queries: self.queries.saturating_sub(earlier.queries),
+            commands: self.commands.saturating_sub(earlier.commands),
+            retries: self.retries.saturating_sub(earlier.retries),
+            bytes_sent: self.bytes_sent.saturating_sub(earlier.bytes_sent),
+            bytes_received: self.bytes_received.saturating_sub(earlier.bytes_received),
+        }
+    }
+}
+
+// Counters updated by the client upon each operation sent to the network.
+// Clones of an instance share the same counters.
+#[derive(Clone, Default)]
+pub(crate) struct DiagnosticsCounters {
+    queries: Arc<AtomicU64>,
+    commands: Arc<AtomicU64>,
+    retries: Arc<AtomicU64>,
+    bytes_sent: Arc<AtomicU64>,
+    bytes_received: Arc<AtomicU64>,
+}
+
+impl DiagnosticsCounters {
+    pub(crate) fn query(&self) {
+        let _ = self.queries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn command(&self, bytes_sent: usize) {
+        let _ = self.commands.fetch_add(1, Ordering::Relaxed);
+        let _ = self
+            .bytes_sent
+            .fetch_add(bytes_sent as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn received(&self, bytes_received: usize) {
+        let _ = self
+            .bytes_received
+            .fetch_add(bytes_received as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn retry(&self) {
+        let _ = self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> Diagnostics {
+        Diagnostics {
+            queries: self.queries.load(Ordering::Relaxed),
+            commands: self.commands.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Safe {
+    /// Counts of all the network operations sent by this instance, and its clones, so far
+    pub fn diagnostics(&self) -> Diagnostics {
+        self.safe_client.diagnostics().snapshot()
+    }
+
+    /// # Run an API call reporting the network operations it sent
+    ///
+    /// Returns the outcome of the call together with the counts of the operations sent
+    /// while it ran. Operations sent concurrently by clones of this instance are counted
+    /// as well, thus calls to diagnose should not run concurrently with others.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use sn_api::Safe;
+    /// # let rt = tokio::runtime::Runtime::new().unwrap();
+    /// # rt.block_on(async {
+    /// #   let mut safe = Safe::default();
+    /// #   safe.connect(None, None, None).await.unwrap();
+    ///     let (result, diagnostics) = safe.with_diagnostics(safe.fetch("safe://mysite", None)).await;
+    ///     println!("Queries sent to fetch safe://mysite: {}", diagnostics.queries);
+    /// # });
+    /// ```
+    pub async fn with_diagnostics<F: Future>(&self, call: F) -> (F::Output, Diagnostics) {
+        let counters = self.safe_client.diagnostics();
+        let start = counters.snapshot();
+        let output = call.await;
+        (output, counters.snapshot().since(&start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{app::test_helpers::new_safe_instance, retry_loop};
+    use anyhow::Result;
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn test_with_diagnostics() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let data = Bytes::from("diagnosed content");
+
+        let (xorurl, diagnostics) = safe
+            .with_diagnostics(safe.store_public_bytes(data.clone(), None, false))
+            .await;
+        let xorurl = xorurl?;
+        assert_eq!(diagnostics.commands, 1);
+        assert_eq!(diagnostics.queries, 0);
+        assert_eq!(diagnostics.bytes_sent, data.len() as u64);
+
+        let _ = retry_loop!(safe.fetch(&xorurl, None));
+        let (fetched, diagnostics) = safe.with_diagnostics(safe.fetch(&xorurl, None)).await;
+        let _ = fetched?;
+        assert_eq!(diagnostics.commands, 0);
+        assert!(diagnostics.queries >= 1);
+        assert!(diagnostics.bytes_received >= data.len() as u64);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::Safe;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// Content types which can be fetched and resolved by this build
+const SUPPORTED_CONTENT_TYPES: &[&str] = &[
+    "Raw",
+    "MediaType",
+    "FilesContainer",
+    "NrsMapContainer",
+    "Multimap",
+];
+
+// Features supported by the network this build is meant to connect to
+const NETWORK_FEATURES: &[&str] = &[
+    "public_scope",
+    "private_scope",
+    "registers",
+    "open_registers",
+    "private_blobs",
+    "multimap",
+    "nrs",
+];
+
+/// Report of the optional subsystems, content types and network features supported,
+/// for applications to adapt to them at runtime
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Version of this crate
+    pub api_version: String,
+    /// Optional subsystems this build was compiled with, e.g. `advanced` or `http_import`
+    pub build_features: BTreeSet<String>,
+    /// Content types which can be fetched, e.g. `FilesContainer`
+    pub content_types: BTreeSet<String>,
+    /// Whether this instance is connected to a network, or to a simulated one
+    pub connected: bool,
+    /// Features supported with the network connected to, empty if not connected.
+    /// Features which are temporarily disabled, e.g. `wallet`, are not reported.
+    pub network_features: BTreeSet<String>,
+}
+
+impl Capabilities {
+    /// Check if a build feature, content type, or network feature is supported
+    pub fn supports(&self, feature: &str) -> bool {
+        self.build_features.contains(feature)
+            || self.content_types.contains(feature)
+            || self.network_features.contains(feature)
+    }
+}
+
+impl Safe {
+    /// # Report the capabilities of this build and the network it's connected to
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use sn_api::Safe;
+    ///     let safe = Safe::default();
+    ///     let capabilities = safe.capabilities();
+    ///     assert!(capabilities.supports("FilesContainer"));
+    ///     assert!(!capabilities.connected);
+    /// ```
+    pub fn capabilities(&self) -> Capabilities {
+        let connected = self.safe_client.is_connected();
+        let network_features = if connected {
+            NETWORK_FEATURES.iter().map(|f| f.to_string()).collect()
+        } else {
+            BTreeSet::new()
+        };
+
+        Capabilities {
+            api_version: env!("CARGO_PKG_VERSION").to_string(),
+            build_features: build_features(),
+            content_types: SUPPORTED_CONTENT_TYPES
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            connected,
+            network_features,
+        }
+    }
+}
+
+// Optional subsystems enabled with Cargo features for this build
+fn build_features() -> BTreeSet<String> {
+    let mut features = vec!["app"];
+    if cfg!(feature = "advanced") {
+        features.push("advanced");
+    }
+    if cfg!(feature = "sim") {
+        features.push("sim");
+    }
+    if cfg!(feature = "http_import") {
+        features.push("http_import");
+    }
+    if cfg!(feature = "authd_client") {
+        features.push("authd_client");
+    }
+    if cfg!(feature = "authenticator") {
+        features.push("authenticator");
+    }
+
+    features.into_iter().map(|f| f.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_helpers::new_safe_instance;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_capabilities() -> Result<()> {
+        let disconnected = Safe::default().capabilities();
+        assert!(!disconnected.connected);
+        assert!(disconnected.network_features.is_empty());
+        assert!(disconnected.supports("app"));
+        assert!(disconnected.supports("Multimap"));
+        assert!(!disconnected.supports("wallet"));
+        assert_eq!(
+            disconnected.build_features.contains("advanced"),
+            cfg!(feature = "advanced")
+        );
+
+        let safe = new_safe_instance().await?;
+        let capabilities = safe.capabilities();
+        assert!(capabilities.connected);
+        assert!(capabilities.supports("private_scope"));
+
+        Ok(())
+    }
+}
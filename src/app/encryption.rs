@@ -0,0 +1,303 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{common::sk_to_hex, Error, Result};
+use aes_gcm::Aes256Gcm;
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    XChaCha20Poly1305,
+};
+use hmac::{Hmac, Mac, NewMac};
+use safe_network::types::Keypair;
+use sha3::Sha3_256;
+use std::collections::BTreeSet;
+use tiny_keccak::{Hasher, Sha3};
+
+// Length of the symmetric keys used to encrypt private payloads
+pub(crate) const SYMMETRIC_KEY_LEN: usize = 32;
+
+pub(crate) type SymmetricKey = [u8; SYMMETRIC_KEY_LEN];
+
+/// Symmetric encryption algorithms supported to encrypt private payloads. The identifier
+/// of the algorithm is stored with each encrypted payload, thus content encrypted with
+/// any supported algorithm can still be decrypted after the default one is changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EncryptionAlgorithm {
+    XChaCha20Poly1305,
+    Aes256Gcm,
+}
+
+impl EncryptionAlgorithm {
+    /// All the algorithms supported
+    pub fn supported() -> BTreeSet<Self> {
+        vec![Self::XChaCha20Poly1305, Self::Aes256Gcm]
+            .into_iter()
+            .collect()
+    }
+
+    /// Identifier of the algorithm as stored with the encrypted payloads
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::XChaCha20Poly1305 => 1,
+            Self::Aes256Gcm => 2,
+        }
+    }
+
+    /// Algorithm corresponding to an identifier, if it's supported
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::supported().into_iter().find(|alg| alg.id() == id)
+    }
+
+    /// Whether the algorithm is approved by FIPS 140
+    pub fn is_fips_approved(&self) -> bool {
+        match self {
+            Self::XChaCha20Poly1305 => false,
+            Self::Aes256Gcm => true,
+        }
+    }
+
+    fn nonce_len(&self) -> usize {
+        match self {
+            Self::XChaCha20Poly1305 => 24,
+            Self::Aes256Gcm => 12,
+        }
+    }
+
+    fn encrypt(&self, key: &SymmetricKey, nonce: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::XChaCha20Poly1305 => {
+                XChaCha20Poly1305::new(key[..].into()).encrypt(nonce.into(), plaintext)
+            }
+            Self::Aes256Gcm => Aes256Gcm::new(key[..].into()).encrypt(nonce.into(), plaintext),
+        }
+        .map_err(|err| Error::Serialisation(format!("Failed to encrypt payload: {:?}", err)))
+    }
+
+    fn decrypt(&self, key: &SymmetricKey, nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::XChaCha20Poly1305 => {
+                XChaCha20Poly1305::new(key[..].into()).decrypt(nonce.into(), ciphertext)
+            }
+            Self::Aes256Gcm => Aes256Gcm::new(key[..].into()).decrypt(nonce.into(), ciphertext),
+        }
+        .map_err(|_| Error::AccessDenied("Failed to decrypt payload".to_string()))
+    }
+}
+
+/// Policy of the algorithms to use to encrypt and decrypt private payloads
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncryptionPolicy {
+    /// Algorithm used to encrypt new payloads
+    pub default_algorithm: EncryptionAlgorithm,
+    /// Algorithms accepted when decrypting payloads, any payload
+    /// encrypted with a different algorithm is rejected
+    pub allowed_algorithms: BTreeSet<EncryptionAlgorithm>,
+}
+
+impl Default for EncryptionPolicy {
+    fn default() -> Self {
+        Self {
+            default_algorithm: EncryptionAlgorithm::XChaCha20Poly1305,
+            allowed_algorithms: EncryptionAlgorithm::supported(),
+        }
+    }
+}
+
+impl EncryptionPolicy {
+    /// Policy which only allows FIPS-approved algorithms
+    pub fn fips() -> Self {
+        Self {
+            default_algorithm: EncryptionAlgorithm::Aes256Gcm,
+            allowed_algorithms: EncryptionAlgorithm::supported()
+                .into_iter()
+                .filter(|alg| alg.is_fips_approved())
+                .collect(),
+        }
+    }
+
+    /// Check the policy is consistent, i.e. that the default algorithm is an allowed one,
+    /// otherwise new payloads would be encrypted with an algorithm the policy rejects
+    pub fn validate(&self) -> Result<()> {
+        if self.allowed_algorithms.contains(&self.default_algorithm) {
+            Ok(())
+        } else {
+            Err(Error::InvalidInput(format!(
+                "The default encryption algorithm {:?} is not one of the allowed algorithms: {:?}",
+                self.default_algorithm, self.allowed_algorithms
+            )))
+        }
+    }
+}
+
+// Derive a symmetric key from the keypair's secret key with HMAC-SHA3-256, the context
+// allows to derive different keys for each type of content from the same keypair
+pub(crate) fn derive_symmetric_key(keypair: &Keypair, context: &[u8]) -> Result<SymmetricKey> {
+    let secret_key = keypair.secret_key().map_err(|err| {
+        Error::InvalidInput(format!(
+            "Failed to obtain secret key to derive encryption key from: {:?}",
+            err
+        ))
+    })?;
+
+    let mut mac =
+        Hmac::<Sha3_256>::new_varkey(sk_to_hex(secret_key).as_bytes()).map_err(|err| {
+            Error::InvalidInput(format!("Failed to initialise key derivation: {:?}", err))
+        })?;
+    mac.update(context);
+
+    let mut key = SymmetricKey::default();
+    key.copy_from_slice(&mac.finalize().into_bytes());
+    Ok(key)
+}
+
+// Hash some content with the given key, e.g. to obtain lookup keys which don't reveal the content
+pub(crate) fn keyed_hash(key: &[u8], content: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha3::v256();
+    let mut hash = [0; 32];
+    hasher.update(key);
+    hasher.update(content);
+    hasher.finalize(&mut hash);
+    hash
+}
+
+// Encrypt a payload with the policy's default algorithm, as long as it's allowed by the policy.
+// The identifier of the algorithm and the random nonce used are prepended to the returned
+// ciphertext.
+pub(crate) fn encrypt_payload(
+    policy: &EncryptionPolicy,
+    key: &SymmetricKey,
+    plaintext: &[u8],
+) -> Result<Vec<u8>> {
+    policy.validate()?;
+    let algorithm = policy.default_algorithm;
+    let nonce: Vec<u8> = (0..algorithm.nonce_len())
+        .map(|_| rand::random::<u8>())
+        .collect();
+    let ciphertext = algorithm.encrypt(key, &nonce, plaintext)?;
+
+    let mut payload = vec![algorithm.id()];
+    payload.extend(nonce);
+    payload.extend(ciphertext);
+    Ok(payload)
+}
+
+// Whether the algorithm a payload was encrypted with is allowed by the policy
+pub(crate) fn payload_allowed(policy: &EncryptionPolicy, payload: &[u8]) -> bool {
+    payload
+        .first()
+        .and_then(|id| EncryptionAlgorithm::from_id(*id))
+        .map_or(false, |algorithm| {
+            policy.allowed_algorithms.contains(&algorithm)
+        })
+}
+
+// Decrypt a payload previously encrypted with `encrypt_payload`,
+// as long as the algorithm it was encrypted with is allowed by the policy
+pub(crate) fn decrypt_payload(
+    policy: &EncryptionPolicy,
+    key: &SymmetricKey,
+    payload: &[u8],
+) -> Result<Vec<u8>> {
+    let (id, payload) = payload
+        .split_first()
+        .ok_or_else(|| Error::ContentError("Encrypted payload is empty".to_string()))?;
+    let algorithm = EncryptionAlgorithm::from_id(*id).ok_or_else(|| {
+        Error::ContentError(format!(
+            "Payload is encrypted with an unsupported algorithm (id: {})",
+            id
+        ))
+    })?;
+    if !policy.allowed_algorithms.contains(&algorithm) {
+        return Err(Error::AccessDenied(format!(
+            "Payload is encrypted with {:?} which is not allowed by the encryption policy",
+            algorithm
+        )));
+    }
+    if payload.len() < algorithm.nonce_len() {
+        return Err(Error::ContentError(
+            "Encrypted payload is too short to contain a nonce".to_string(),
+        ));
+    }
+
+    let (nonce, ciphertext) = payload.split_at(algorithm.nonce_len());
+    algorithm.decrypt(key, nonce, ciphertext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Result};
+
+    #[test]
+    fn test_encryption_roundtrip() -> Result<()> {
+        let plaintext = b"something super secret";
+        for algorithm in EncryptionAlgorithm::supported() {
+            let policy = EncryptionPolicy {
+                default_algorithm: algorithm,
+                ..EncryptionPolicy::default()
+            };
+            let key: SymmetricKey = rand::random();
+
+            let payload = encrypt_payload(&policy, &key, plaintext)?;
+            assert_eq!(payload[0], algorithm.id());
+            assert_ne!(&payload[1 + algorithm.nonce_len()..], &plaintext[..]);
+            assert_eq!(
+                decrypt_payload(&policy, &key, &payload)?,
+                plaintext.to_vec()
+            );
+
+            let wrong_key: SymmetricKey = rand::random();
+            match decrypt_payload(&policy, &wrong_key, &payload) {
+                Err(Error::AccessDenied(_)) => {}
+                other => return Err(anyhow!("Unexpected result: {:?}", other)),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_encryption_policy_rejects_disallowed_algorithm() -> Result<()> {
+        let key: SymmetricKey = rand::random();
+        let payload = encrypt_payload(&EncryptionPolicy::default(), &key, b"secret")?;
+
+        let fips_policy = EncryptionPolicy::fips();
+        assert!(!fips_policy
+            .allowed_algorithms
+            .contains(&EncryptionAlgorithm::XChaCha20Poly1305));
+        match decrypt_payload(&fips_policy, &key, &payload) {
+            Err(Error::AccessDenied(_)) => Ok(()),
+            other => Err(anyhow!("Unexpected result: {:?}", other)),
+        }
+    }
+
+    #[test]
+    fn test_encryption_policy_validate() -> Result<()> {
+        let key: SymmetricKey = rand::random();
+        let policy = EncryptionPolicy {
+            default_algorithm: EncryptionAlgorithm::XChaCha20Poly1305,
+            ..EncryptionPolicy::fips()
+        };
+
+        assert!(matches!(policy.validate(), Err(Error::InvalidInput(_))));
+        match encrypt_payload(&policy, &key, b"secret") {
+            Err(Error::InvalidInput(_)) => Ok(()),
+            other => Err(anyhow!("Unexpected result: {:?}", other)),
+        }
+    }
+
+    #[test]
+    fn test_derive_symmetric_key() -> Result<()> {
+        let keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        let key = derive_symmetric_key(&keypair, b"context")?;
+
+        assert_eq!(key, derive_symmetric_key(&keypair, b"context")?);
+        assert_ne!(key, derive_symmetric_key(&keypair, b"other context")?);
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::Safe;
+use crate::{Error, Result};
+use log::debug;
+use rand::{
+    rngs::{OsRng, StdRng},
+    RngCore, SeedableRng,
+};
+use safe_network::types::Keypair;
+use std::sync::Arc;
+
+// Number of bytes sampled from the entropy source by the health tests
+const HEALTH_TEST_SAMPLES: usize = 4_096;
+
+// Cutoff of the repetition count test, i.e. the number of consecutive identical bytes
+// considered a failure, as per NIST SP 800-90B section 4.4.1, for a false positive rate
+// of 2^-40 per byte assuming 8 bits of min-entropy per byte. The rate recommended by the
+// standard, 2^-20, would make a healthy source fail too often over a whole sample.
+const REPETITION_COUNT_CUTOFF: usize = 6;
+
+// Window and cutoff of the adaptive proportion test, i.e. the number of occurrences of
+// the first byte of a window within it considered a failure, as per NIST SP 800-90B
+// section 4.4.2, for the same false positive rate and min-entropy
+const ADAPTIVE_PROPORTION_WINDOW: usize = 512;
+const ADAPTIVE_PROPORTION_CUTOFF: usize = 20;
+
+/// Source of the randomness keys are generated from, see `Safe::set_entropy_source`,
+/// e.g. a hardware token, or the OS CSPRNG which is the default one
+pub trait EntropySource: Send + Sync {
+    /// Fill the buffer with random bytes, failing if the source can't provide them
+    fn fill_bytes(&self, dest: &mut [u8]) -> Result<()>;
+}
+
+/// Entropy source reading from the OS CSPRNG
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsEntropy;
+
+impl EntropySource for OsEntropy {
+    fn fill_bytes(&self, dest: &mut [u8]) -> Result<()> {
+        OsRng.try_fill_bytes(dest).map_err(|err| {
+            Error::InsufficientEntropy(format!("Failed to read from the OS CSPRNG: {}", err))
+        })
+    }
+}
+
+impl Safe {
+    /// Set the source of the randomness keys are generated from by `generate_keypair`
+    pub fn set_entropy_source(&mut self, source: Arc<dyn EntropySource>) {
+        self.entropy = source;
+    }
+
+    /// # Check the health of the entropy source
+    ///
+    /// A sample of bytes is read from the entropy source and checked with the repetition
+    /// count and adaptive proportion tests of NIST SP 800-90B, which detect sources which
+    /// are stuck or have become heavily biased. Passing the tests doesn't prove the source
+    /// is random, but failing them means it must not be used to generate keys.
+    pub fn entropy_self_test(&self) -> Result<()> {
+        let mut samples = vec![0; HEALTH_TEST_SAMPLES];
+        self.entropy.fill_bytes(&mut samples)?;
+        check_repetition_count(&samples)?;
+        check_adaptive_proportion(&samples)?;
+        debug!("Entropy source passed the health tests");
+        Ok(())
+    }
+
+    /// # Generate an Ed25519 keypair from the entropy source
+    ///
+    /// The entropy source is checked with `entropy_self_test` before generating the keypair,
+    /// which makes it suitable for long-lived identity keys, failing with
+    /// `Error::InsufficientEntropy` if the source is unhealthy.
+    pub fn generate_keypair(&self) -> Result<Keypair> {
+        self.entropy_self_test()?;
+        let mut seed = <StdRng as SeedableRng>::Seed::default();
+        self.entropy.fill_bytes(&mut seed)?;
+        let mut rng = StdRng::from_seed(seed);
+        Ok(Keypair::new_ed25519(&mut rng))
+    }
+}
+
+// Fail if a byte is repeated too many times in a row
+fn check_repetition_count(samples: &[u8]) -> Result<()> {
+    let mut run = 1;
+    for pair in samples.windows(2) {
+        if pair[0] == pair[1] {
+            run += 1;
+            if run >= REPETITION_COUNT_CUTOFF {
+                return Err(Error::InsufficientEntropy(format!(
+                    "Entropy source failed the repetition count test, byte {:#04x} was \
+                    repeated {} times in a row",
+                    pair[0], run
+                )));
+            }
+        } else {
+            run = 1;
+        }
+    }
+    Ok(())
+}
+
+// Fail if the first byte of a window occurs too many times within it
+fn check_adaptive_proportion(samples: &[u8]) -> Result<()> {
+    for window in samples.chunks_exact(ADAPTIVE_PROPORTION_WINDOW) {
+        let count = window.iter().filter(|byte| **byte == window[0]).count();
+        if count >= ADAPTIVE_PROPORTION_CUTOFF {
+            return Err(Error::InsufficientEntropy(format!(
+                "Entropy source failed the adaptive proportion test, byte {:#04x} occurred \
+                {} times within {} bytes",
+                window[0], count, ADAPTIVE_PROPORTION_WINDOW
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Result};
+    use std::sync::Mutex;
+
+    // Deterministic source, so keys generated from it can be compared
+    struct SeededEntropy(Mutex<StdRng>);
+
+    impl EntropySource for SeededEntropy {
+        fn fill_bytes(&self, dest: &mut [u8]) -> crate::Result<()> {
+            self.0
+                .lock()
+                .map_err(|_| Error::InsufficientEntropy("Poisoned lock".to_string()))?
+                .fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    struct StuckEntropy;
+
+    impl EntropySource for StuckEntropy {
+        fn fill_bytes(&self, dest: &mut [u8]) -> crate::Result<()> {
+            dest.iter_mut().for_each(|byte| *byte = 0xaa);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_entropy_health_tests() -> Result<()> {
+        let mut samples = vec![0; HEALTH_TEST_SAMPLES];
+        OsEntropy.fill_bytes(&mut samples)?;
+        check_repetition_count(&samples)?;
+        check_adaptive_proportion(&samples)?;
+
+        let repeated = [1, 2, 3, 3, 3, 3, 3, 3, 4];
+        assert!(check_repetition_count(&repeated).is_err());
+
+        // no repetitions in a row, but biased towards the first byte of the window
+        let biased: Vec<u8> = (0..ADAPTIVE_PROPORTION_WINDOW)
+            .map(|i| if i % 2 == 0 { 7 } else { i as u8 })
+            .collect();
+        check_repetition_count(&biased)?;
+        assert!(check_adaptive_proportion(&biased).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_keypair_from_entropy_source() -> Result<()> {
+        let keypair_from_seed = |seed: u64| {
+            let mut safe = Safe::default();
+            safe.set_entropy_source(Arc::new(SeededEntropy(Mutex::new(StdRng::seed_from_u64(
+                seed,
+            )))));
+            safe.generate_keypair()
+        };
+        assert_eq!(
+            keypair_from_seed(1)?.public_key(),
+            keypair_from_seed(1)?.public_key()
+        );
+        assert_ne!(
+            keypair_from_seed(1)?.public_key(),
+            keypair_from_seed(2)?.public_key()
+        );
+
+        let mut safe = Safe::default();
+        safe.set_entropy_source(Arc::new(StuckEntropy));
+        match safe.generate_keypair() {
+            Err(Error::InsufficientEntropy(_)) => Ok(()),
+            other => Err(anyhow!("Unexpected result: {:?}", other.map(|_| ()))),
+        }
+    }
+}
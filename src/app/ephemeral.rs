@@ -0,0 +1,160 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{Error, Result, Safe};
+use log::info;
+
+/// Writes an ephemeral identity is allowed to make, see `Safe::ephemeral_with_policy`.
+/// By default it can only read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EphemeralPolicy {
+    /// Whether it can write entries to existing Registers it has permission to write
+    /// to, e.g. to deposit in relays or to post to Registers writable by anyone
+    pub register_writes: bool,
+    /// Whether it can create Registers
+    pub register_creation: bool,
+    /// Maximum size of the Blobs it can store, none can be stored if zero
+    pub max_blob_size: usize,
+}
+
+// Kind of write checked against the policy of an ephemeral identity
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum EphemeralWrite {
+    Blob(usize),
+    RegisterCreation,
+    RegisterEntry,
+    RegisterDeletion,
+}
+
+impl EphemeralPolicy {
+    // Fail if the write is not allowed by the policy
+    pub(crate) fn check(&self, write: EphemeralWrite) -> Result<()> {
+        let allowed = match write {
+            EphemeralWrite::Blob(size) => size <= self.max_blob_size,
+            EphemeralWrite::RegisterCreation => self.register_creation,
+            EphemeralWrite::RegisterEntry => self.register_writes,
+            // they can't own any Register they can't create
+            EphemeralWrite::RegisterDeletion => self.register_creation,
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(Error::AccessDenied(format!(
+                "{:?} is not allowed by the policy of this ephemeral identity",
+                write
+            )))
+        }
+    }
+}
+
+impl Safe {
+    /// # Create a read-only ephemeral identity
+    ///
+    /// See `ephemeral_with_policy`, the identity returned can't write anything.
+    pub async fn ephemeral(&self) -> Result<Safe> {
+        self.ephemeral_with_policy(EphemeralPolicy::default()).await
+    }
+
+    /// # Create an ephemeral identity
+    ///
+    /// A new instance is returned, connected to the same network as this one but with a
+    /// new random keypair, which is never stored, thus what it does can't be linked to the
+    /// identity of this instance, e.g. for browsing privately. It doesn't share any state
+    /// with this instance either, e.g. the fetch history, the signer, or the local caches.
+    /// It can only make the writes allowed by the policy, any other write fails with
+    /// `Error::AccessDenied` without being sent to the network.
+    pub async fn ephemeral_with_policy(&self, policy: EphemeralPolicy) -> Result<Safe> {
+        info!("Creating ephemeral identity with policy: {:?}", policy);
+        let mut ephemeral = Safe::new(Some(self.xorurl_base), self.safe_client.timeout());
+        ephemeral.set_encryption_policy(self.encryption_policy.clone())?;
+        ephemeral.set_trusted_genesis_keys(self.trusted_genesis_keys().clone());
+        ephemeral
+            .safe_client
+            .set_runtime(self.safe_client.runtime());
+        ephemeral.safe_client.set_ephemeral_policy(policy);
+
+        #[cfg(feature = "sim")]
+        if let Some(sim) = self.safe_client.sim() {
+            ephemeral.connect_sim(&sim, None);
+            return Ok(ephemeral);
+        }
+
+        let node_config = self.safe_client.node_config()?;
+        let keypair = ephemeral.keypair();
+        ephemeral.connect(Some(keypair), None, node_config).await?;
+        Ok(ephemeral)
+    }
+
+    /// Policy of the writes allowed to this instance, if it's an ephemeral identity
+    pub fn ephemeral_policy(&self) -> Option<EphemeralPolicy> {
+        self.safe_client.ephemeral_policy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, Url};
+    use anyhow::{anyhow, Result};
+    use bytes::Bytes;
+
+    #[test]
+    fn test_ephemeral_policy_check() {
+        let read_only = EphemeralPolicy::default();
+        assert!(read_only.check(EphemeralWrite::Blob(1)).is_err());
+        assert!(read_only.check(EphemeralWrite::RegisterEntry).is_err());
+        assert!(read_only.check(EphemeralWrite::RegisterCreation).is_err());
+
+        let policy = EphemeralPolicy {
+            register_writes: true,
+            register_creation: false,
+            max_blob_size: 1_024,
+        };
+        assert!(policy.check(EphemeralWrite::Blob(1_024)).is_ok());
+        assert!(policy.check(EphemeralWrite::Blob(1_025)).is_err());
+        assert!(policy.check(EphemeralWrite::RegisterEntry).is_ok());
+        assert!(policy.check(EphemeralWrite::RegisterCreation).is_err());
+        assert!(policy.check(EphemeralWrite::RegisterDeletion).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ephemeral_identity() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe
+            .store_public_bytes(Bytes::from("public content"), None, false)
+            .await?;
+        assert_eq!(safe.ephemeral_policy(), None);
+
+        let ephemeral = safe.ephemeral().await?;
+        assert_ne!(
+            ephemeral.get_my_keypair()?.public_key(),
+            safe.get_my_keypair()?.public_key()
+        );
+        assert_eq!(
+            ephemeral.ephemeral_policy(),
+            Some(EphemeralPolicy::default())
+        );
+
+        // it can read, but not write
+        let url = Url::from_url(&xorurl)?;
+        let data = retry_loop!(ephemeral.fetch_public_data(&url, None));
+        assert_eq!(data, Bytes::from("public content"));
+        match ephemeral
+            .store_public_bytes(Bytes::from("linkable"), None, false)
+            .await
+        {
+            Err(Error::AccessDenied(_)) => {}
+            other => return Err(anyhow!("Unexpected result: {:?}", other)),
+        }
+        match ephemeral.register_create(None, 25_000, false).await {
+            Err(Error::AccessDenied(_)) => Ok(()),
+            other => Err(anyhow!("Unexpected result: {:?}", other)),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::helpers::gen_timestamp_secs;
+#[cfg(feature = "http_import")]
+use crate::Error;
+use crate::{Result, Safe};
+use futures::channel::mpsc;
+#[cfg(feature = "http_import")]
+use log::warn;
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// Event emitted on the event bus of an instance, see `Safe::on_event`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SafeEvent {
+    /// An operation which stores content on the network completed, e.g. a Register write
+    OperationCompleted {
+        operation: String,
+        url: String,
+        /// Time of the event, in RFC3339 format
+        timestamp: String,
+    },
+    /// Content was synced up to a new version, e.g. a FilesContainer with a
+    /// local folder, or a mirror with its source content
+    SyncFinished {
+        url: String,
+        version: String,
+        timestamp: String,
+    },
+    /// A new entry was received on a Register subscription, see `Safe::register_subscribe`
+    SubscriptionUpdate {
+        url: String,
+        /// Hash of the entry, hex encoded
+        entry_hash: String,
+        timestamp: String,
+    },
+    /// An operation failed
+    Error {
+        operation: String,
+        url: String,
+        error: String,
+        timestamp: String,
+    },
+}
+
+type EventSink = Arc<dyn Fn(&SafeEvent) + Send + Sync>;
+
+#[derive(Default)]
+struct EventSinks {
+    callbacks: Vec<EventSink>,
+    #[cfg(feature = "http_import")]
+    webhooks: Vec<String>,
+}
+
+// Sinks the events are delivered to, shared by an instance and all its clones
+#[derive(Clone, Default)]
+pub(crate) struct EventBus {
+    sinks: Arc<Mutex<EventSinks>>,
+}
+
+impl Safe {
+    /// Call the callback with each event emitted by this instance or any of its clones
+    pub fn on_event(&self, callback: impl Fn(&SafeEvent) + Send + Sync + 'static) {
+        if let Ok(mut sinks) = self.events.sinks.lock() {
+            sinks.callbacks.push(Arc::new(callback));
+        }
+    }
+
+    /// Receive the events emitted by this instance or any of its clones on a channel
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<SafeEvent> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.on_event(move |event| {
+            let _ = sender.unbounded_send(event.clone());
+        });
+        receiver
+    }
+
+    /// POST each event emitted, serialised as JSON, to the webhook URL.
+    /// The events are delivered on background tasks, failures are only logged.
+    #[cfg(feature = "http_import")]
+    pub fn add_event_webhook(&self, url: &str) {
+        if let Ok(mut sinks) = self.events.sinks.lock() {
+            sinks.webhooks.push(url.to_string());
+        }
+    }
+
+    /// Remove all the sinks events are delivered to, including webhooks
+    pub fn clear_event_sinks(&self) {
+        if let Ok(mut sinks) = self.events.sinks.lock() {
+            *sinks = EventSinks::default();
+        }
+    }
+
+    // Deliver an event to all the sinks. The sinks are called without holding
+    // the lock, so callbacks can add sinks themselves.
+    pub(crate) fn emit_event(&self, event: SafeEvent) {
+        trace!("Emitting event: {:?}", event);
+        let callbacks = match self.events.sinks.lock() {
+            Ok(sinks) => {
+                #[cfg(feature = "http_import")]
+                for webhook in sinks.webhooks.iter().cloned() {
+                    let event = event.clone();
+                    self.runtime().spawn(Box::pin(async move {
+                        if let Err(err) = post_event(&webhook, &event).await {
+                            warn!("Failed to deliver event to webhook {}: {}", webhook, err);
+                        }
+                    }));
+                }
+                sinks.callbacks.clone()
+            }
+            Err(err) => {
+                debug!("Event sinks are not available: {}", err);
+                return;
+            }
+        };
+
+        callbacks.iter().for_each(|callback| callback(&event));
+    }
+
+    // Emit the event corresponding to the outcome of an operation on the given URL
+    pub(crate) fn emit_outcome<T>(&self, operation: &str, url: &str, result: &Result<T>) {
+        let event = match result {
+            Ok(_) => SafeEvent::OperationCompleted {
+                operation: operation.to_string(),
+                url: url.to_string(),
+                timestamp: gen_timestamp_secs(),
+            },
+            Err(err) => SafeEvent::Error {
+                operation: operation.to_string(),
+                url: url.to_string(),
+                error: err.to_string(),
+                timestamp: gen_timestamp_secs(),
+            },
+        };
+        self.emit_event(event);
+    }
+}
+
+// POST an event serialised as JSON to a webhook
+#[cfg(feature = "http_import")]
+pub(crate) async fn post_event<T: Serialize>(webhook: &str, event: &T) -> Result<()> {
+    let body = serde_json::to_vec(event)
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise event: {:?}", err)))?;
+    let _ = reqwest::Client::new()
+        .post(webhook)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| Error::NetDataError(format!("Failed to POST to {}: {}", webhook, err)))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, Error, Url};
+    use anyhow::{anyhow, Result};
+    use bytes::Bytes;
+    use std::{
+        collections::BTreeSet,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    #[test]
+    fn test_events_delivered_to_all_sinks() -> Result<()> {
+        let safe = Safe::default();
+        let mut receiver = safe.subscribe_events();
+        let delivered = Arc::new(AtomicUsize::new(0));
+        let counter = delivered.clone();
+        safe.clone().on_event(move |_| {
+            let _ = counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let ok: crate::Result<()> = Ok(());
+        safe.emit_outcome("an_operation", "safe://url", &ok);
+        let failed: crate::Result<()> = Err(Error::EmptyContent("nothing".to_string()));
+        safe.emit_outcome("an_operation", "safe://url", &failed);
+
+        assert_eq!(delivered.load(Ordering::SeqCst), 2);
+        assert!(matches!(
+            receiver.try_recv()?,
+            SafeEvent::OperationCompleted { operation, .. } if operation == "an_operation"
+        ));
+        assert!(matches!(
+            receiver.try_recv()?,
+            SafeEvent::Error { url, .. } if url == "safe://url"
+        ));
+
+        safe.clear_event_sinks();
+        safe.emit_outcome("an_operation", "safe://url", &ok);
+        assert_eq!(delivered.load(Ordering::SeqCst), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_events_register_write() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let mut receiver = safe.subscribe_events();
+        let entry = Url::from_xorurl(
+            &safe
+                .store_public_bytes(Bytes::from("an entry"), None, false)
+                .await?,
+        )?;
+        let _ = safe
+            .write_to_register(&xorurl, entry, BTreeSet::new())
+            .await?;
+
+        match receiver.try_recv()? {
+            SafeEvent::OperationCompleted { operation, url, .. } => {
+                assert_eq!(operation, "write_to_register");
+                assert_eq!(
+                    Url::from_url(&url)?.xorname(),
+                    Url::from_url(&xorurl)?.xorname()
+                );
+            }
+            other => return Err(anyhow!("Unexpected event: {:?}", other)),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,359 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    consts::{PREDICATE_MODIFIED, PREDICATE_TYPE},
+    files::FileMeta,
+    helpers::gen_timestamp_secs,
+};
+use crate::{PublicKey, Result, Safe, VersionHash};
+use bytes::Bytes;
+use chrono::{DateTime, FixedOffset};
+use log::debug;
+use std::cmp::Reverse;
+
+/// Format of the feeds rendered, see `Safe::files_container_feed`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeedFormat {
+    /// RSS 2.0
+    Rss,
+    /// Atom, RFC 4287
+    Atom,
+}
+
+/// Information about a feed, and how many of the most recent changes it includes
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedOptions {
+    pub title: String,
+    pub description: String,
+    /// Link the feed is published at, e.g. through a gateway, which the links of its items
+    /// are made relative to. The URL of the content the feed is rendered from if not set.
+    pub link: Option<String>,
+    /// Maximum number of items in the feed, the most recent ones
+    pub max_items: usize,
+}
+
+impl Default for FeedOptions {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            description: String::new(),
+            link: None,
+            max_items: 20,
+        }
+    }
+}
+
+/// An item of a feed, i.e. a change of the content it's rendered from
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedItem {
+    pub title: String,
+    pub link: String,
+    /// Time of the change, in RFC3339 format
+    pub updated: String,
+    pub content: Option<String>,
+}
+
+impl Safe {
+    /// # Render the recent changes of a FilesContainer as a feed
+    ///
+    /// Each file of the FilesContainer is an item of the feed, the most recently modified
+    /// first, thus followers using conventional feed readers, e.g. through a gateway, can
+    /// track a blog published on a FilesContainer. Folders and symlinks are not included.
+    pub async fn files_container_feed(
+        &mut self,
+        url: &str,
+        format: FeedFormat,
+        options: &FeedOptions,
+    ) -> Result<Bytes> {
+        let (link, items) = self.files_container_feed_items(url, options).await?;
+        Ok(Bytes::from(render_feed(format, options, &link, items)))
+    }
+
+    /// # Render the recent changes of a FilesContainer as a feed and store it in it
+    ///
+    /// The feed is stored at the given path of the FilesContainer, overwriting any
+    /// previous version of it, and the new version of the FilesContainer is returned.
+    /// The feed itself is not included as an item of the feed.
+    pub async fn files_container_publish_feed(
+        &mut self,
+        url: &str,
+        path: &str,
+        format: FeedFormat,
+        options: &FeedOptions,
+    ) -> Result<VersionHash> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        safe_url.set_path("");
+        let feed_path = format!("/{}", path.trim_start_matches('/'));
+
+        let (link, items) = self
+            .files_container_feed_items(&safe_url.to_string(), options)
+            .await?;
+        let feed_link = item_link(&link, &feed_path);
+        let items = items
+            .into_iter()
+            .filter(|item| item.link != feed_link)
+            .collect();
+        let feed = render_feed(format, options, &link, items);
+
+        safe_url.set_path(&feed_path);
+        let (version, _, _) = self
+            .files_container_add_from_raw(
+                Bytes::from(feed),
+                &safe_url.to_string(),
+                true,
+                false,
+                false,
+            )
+            .await?;
+        debug!("Feed published at {}", safe_url);
+
+        Ok(version)
+    }
+
+    /// # Render the updates of a channel as a feed
+    ///
+    /// Each update of the channel published by the owner of the public key is an item of the
+    /// feed, the most recent first, with the payload of the update as the item's content.
+    pub async fn channel_feed(
+        &self,
+        owner: PublicKey,
+        channel: &str,
+        format: FeedFormat,
+        options: &FeedOptions,
+    ) -> Result<Bytes> {
+        let mut subscription = self.subscribe_channel(owner, channel)?;
+        let link = options
+            .link
+            .clone()
+            .unwrap_or_else(|| format!("safe-channel:{}", channel));
+        let items = subscription
+            .next_updates()
+            .await?
+            .into_iter()
+            .map(|update| FeedItem {
+                title: format!("{} #{}", update.channel, update.seq),
+                link: format!("{}#{}", link, update.seq),
+                updated: update.published_at,
+                content: Some(String::from_utf8_lossy(&update.payload).to_string()),
+            })
+            .collect();
+
+        Ok(Bytes::from(render_feed(format, options, &link, items)))
+    }
+
+    // Private helper to obtain the items of the feed of a FilesContainer, and the feed's link
+    async fn files_container_feed_items(
+        &mut self,
+        url: &str,
+        options: &FeedOptions,
+    ) -> Result<(String, Vec<FeedItem>)> {
+        let (_, files_map) = self.files_container_get(url).await?;
+        let link = options.link.clone().unwrap_or_else(|| url.to_string());
+        let items = files_map
+            .iter()
+            .filter(|(_, file_item)| {
+                file_item
+                    .get(PREDICATE_TYPE)
+                    .map_or(false, |file_type| FileMeta::filetype_is_file(file_type))
+            })
+            .map(|(path, file_item)| FeedItem {
+                title: path.trim_start_matches('/').to_string(),
+                link: item_link(&link, path),
+                updated: file_item
+                    .get(PREDICATE_MODIFIED)
+                    .cloned()
+                    .unwrap_or_default(),
+                content: None,
+            })
+            .collect();
+
+        Ok((link, items))
+    }
+}
+
+fn item_link(link: &str, path: &str) -> String {
+    format!("{}{}", link.trim_end_matches('/'), path)
+}
+
+/// Render the items as a feed in the given format, the most recent first
+pub fn render_feed(
+    format: FeedFormat,
+    options: &FeedOptions,
+    link: &str,
+    mut items: Vec<FeedItem>,
+) -> String {
+    items.sort_by_key(|item| Reverse(parse_time(&item.updated)));
+    items.truncate(options.max_items);
+    let updated = items
+        .first()
+        .map(|item| item.updated.clone())
+        .unwrap_or_else(gen_timestamp_secs);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    match format {
+        FeedFormat::Rss => {
+            xml.push_str("<rss version=\"2.0\">\n<channel>\n");
+            xml.push_str(&format!("<title>{}</title>\n", escape_xml(&options.title)));
+            xml.push_str(&format!("<link>{}</link>\n", escape_xml(link)));
+            xml.push_str(&format!(
+                "<description>{}</description>\n",
+                escape_xml(&options.description)
+            ));
+            xml.push_str(&format!(
+                "<lastBuildDate>{}</lastBuildDate>\n",
+                rfc2822(&updated)
+            ));
+            for item in items.iter() {
+                xml.push_str("<item>");
+                xml.push_str(&format!("<title>{}</title>", escape_xml(&item.title)));
+                xml.push_str(&format!("<link>{}</link>", escape_xml(&item.link)));
+                xml.push_str(&format!(
+                    "<guid isPermaLink=\"false\">{}</guid>",
+                    escape_xml(&item.link)
+                ));
+                xml.push_str(&format!("<pubDate>{}</pubDate>", rfc2822(&item.updated)));
+                if let Some(content) = &item.content {
+                    xml.push_str(&format!(
+                        "<description>{}</description>",
+                        escape_xml(content)
+                    ));
+                }
+                xml.push_str("</item>\n");
+            }
+            xml.push_str("</channel>\n</rss>\n");
+        }
+        FeedFormat::Atom => {
+            xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+            xml.push_str(&format!("<title>{}</title>\n", escape_xml(&options.title)));
+            xml.push_str(&format!(
+                "<subtitle>{}</subtitle>\n",
+                escape_xml(&options.description)
+            ));
+            xml.push_str(&format!("<link href=\"{}\"/>\n", escape_xml(link)));
+            xml.push_str(&format!("<id>{}</id>\n", escape_xml(link)));
+            xml.push_str(&format!("<updated>{}</updated>\n", escape_xml(&updated)));
+            for item in items.iter() {
+                xml.push_str("<entry>");
+                xml.push_str(&format!("<title>{}</title>", escape_xml(&item.title)));
+                xml.push_str(&format!("<link href=\"{}\"/>", escape_xml(&item.link)));
+                xml.push_str(&format!("<id>{}</id>", escape_xml(&item.link)));
+                xml.push_str(&format!("<updated>{}</updated>", escape_xml(&item.updated)));
+                if let Some(content) = &item.content {
+                    xml.push_str(&format!(
+                        "<content type=\"text\">{}</content>",
+                        escape_xml(content)
+                    ));
+                }
+                xml.push_str("</entry>\n");
+            }
+            xml.push_str("</feed>\n");
+        }
+    }
+
+    xml
+}
+
+fn parse_time(time: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(time).ok()
+}
+
+// RSS dates are in RFC2822 format, with a two digits day as most readers expect,
+// while times which can't be parsed are kept as they are
+fn rfc2822(time: &str) -> String {
+    parse_time(time).map_or_else(
+        || escape_xml(time),
+        |time| time.format("%a, %d %b %Y %H:%M:%S %z").to_string(),
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern, Url};
+    use anyhow::Result;
+
+    fn item(title: &str, updated: &str) -> FeedItem {
+        FeedItem {
+            title: title.to_string(),
+            link: format!("safe://blog/{}", title),
+            updated: updated.to_string(),
+            content: Some(format!("<p>{}</p>", title)),
+        }
+    }
+
+    #[test]
+    fn test_render_feed() {
+        let options = FeedOptions {
+            title: "Tom & Jerry's blog".to_string(),
+            max_items: 2,
+            ..FeedOptions::default()
+        };
+        let items = vec![
+            item("first", "2021-01-01T00:00:00Z"),
+            item("third", "2021-03-01T00:00:00Z"),
+            item("second", "2021-02-01T00:00:00Z"),
+        ];
+
+        let rss = render_feed(FeedFormat::Rss, &options, "safe://blog", items.clone());
+        assert!(rss.contains("<rss version=\"2.0\">"));
+        assert!(rss.contains("<title>Tom &amp; Jerry&apos;s blog</title>"));
+        assert!(rss.contains("<pubDate>Mon, 01 Mar 2021 00:00:00 +0000</pubDate>"));
+        assert!(rss.contains("<description>&lt;p&gt;third&lt;/p&gt;</description>"));
+        assert!(rss.find("third").unwrap_or_default() < rss.find("second").unwrap_or_default());
+        assert!(!rss.contains("first"));
+
+        let atom = render_feed(FeedFormat::Atom, &options, "safe://blog", items);
+        assert!(atom.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+        assert!(atom.contains("<updated>2021-03-01T00:00:00Z</updated>\n"));
+        assert!(atom.contains("<link href=\"safe://blog/second\"/>"));
+        assert_eq!(atom.matches("<entry>").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_files_container_publish_feed() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let (xorurl, _, _) = safe
+            .files_container_create(Some("./testdata/"), None, true, false, false)
+            .await?;
+        let mut safe_url = Url::from_url(&xorurl)?;
+        safe_url.set_content_version(None);
+        let xorurl = safe_url.to_string();
+        let _ = retry_loop!(safe.files_container_get(&xorurl));
+
+        let options = FeedOptions {
+            title: "testdata".to_string(),
+            max_items: 100,
+            ..FeedOptions::default()
+        };
+        let version = safe
+            .files_container_publish_feed(&xorurl, "feed.xml", FeedFormat::Atom, &options)
+            .await?;
+        let (_, files_map) = retry_loop_for_pattern!(safe.files_container_get(&xorurl), Ok((v, _)) if *v == version)?;
+        assert!(files_map.contains_key("/feed.xml"));
+
+        let feed = safe
+            .files_container_feed(&xorurl, FeedFormat::Atom, &options)
+            .await?;
+        let feed = String::from_utf8_lossy(&feed);
+        assert!(feed.contains("/test.md\"/>"));
+        assert!(feed.contains("/feed.xml\"/>"));
+
+        Ok(())
+    }
+}
@@ -12,21 +12,30 @@ use super::{
     multimap::MultimapKeyValues,
     nrs::NrsMap,
     register::{Entry, EntryHash},
-    Safe, XorName,
+    Safe, UrlAddressExt, XorName,
 };
 pub use super::{ContentType, DataType, Scope, Url, VersionHash, XorUrlBase};
 use crate::{Error, Result};
 use bytes::Bytes;
 use log::{debug, info};
-use safe_network::types::BytesAddress;
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeSet, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
 
 pub type Range = Option<(Option<u64>, Option<u64>)>;
 
 // Maximum number of indirections allowed when resolving a safe:// URL following links
 const INDIRECTION_LIMIT: u8 = 10;
 
+// Maximum number of attempts to read a set of URLs consistently before giving up
+const CONSISTENT_READ_MAX_ATTEMPTS: usize = 5;
+
+// Versions of the containers (NRS Map and Files containers) found when resolving URLs
+type ContainerVersions = BTreeMap<(XorName, u64), VersionHash>;
+
+/// Data found resolving a safe:// URL, serialised as described by `schemas::INSPECT_SCHEMA`
 #[derive(Debug, PartialEq, Deserialize, Serialize, Clone)]
 pub enum SafeData {
     SafeKey {
@@ -60,6 +69,10 @@ pub enum SafeData {
         nrs_map: NrsMap,
         data_type: DataType,
         resolved_from: String,
+        // Set if the link the URL resolved to doesn't specify a version of versionable
+        // content, thus its latest version was resolved, see `NrsVersionRequirement`
+        #[serde(default)]
+        follows_latest: bool,
     },
     Multimap {
         xorurl: String,
@@ -73,6 +86,10 @@ pub enum SafeData {
         xorname: XorName,
         type_tag: u64,
         data: BTreeSet<(EntryHash, Entry)>,
+        // Media type the Register was created with, if any,
+        // see `Safe::register_create_with_metadata`
+        #[serde(default)]
+        media_type: Option<String>,
         resolved_from: String,
     },
     PrivateRegister {
@@ -80,6 +97,10 @@ pub enum SafeData {
         xorname: XorName,
         type_tag: u64,
         data: BTreeSet<(EntryHash, Entry)>,
+        // Media type the Register was created with, if any,
+        // see `Safe::register_create_with_metadata`
+        #[serde(default)]
+        media_type: Option<String>,
         resolved_from: String,
     },
 }
@@ -147,9 +168,51 @@ impl Safe {
     pub async fn fetch(&self, url: &str, range: Range) -> Result<SafeData> {
         let mut resolution_chain = self.retrieve_from_url(url, true, range, true).await?;
         // Construct return data using the last and first items from the resolution chain
-        resolution_chain
+        let safe_data = resolution_chain
             .pop()
-            .ok_or_else(|| Error::ContentNotFound(format!("Failed to resolve {}", url)))
+            .ok_or_else(|| Error::ContentNotFound(format!("Failed to resolve {}", url)))?;
+
+        self.history.record(url, &safe_data.xorurl());
+        Ok(safe_data)
+    }
+
+    /// # Retrieve data from a set of interdependent safe:// URLs
+    /// The content is fetched for each of the URLs, making sure the versions of the NRS Map
+    /// and Files containers they are resolved through are the same for all of them, i.e. all
+    /// the content returned is mutually consistent. If any of the containers is updated
+    /// while reading, e.g. by a concurrent publish, the URLs are all read again.
+    /// Containers which are resolved at a version explicitly set in the URL or NRS link
+    /// are not required to match, since such URLs deliberately pin that version.
+    pub async fn read_consistent(&self, urls: &[&str]) -> Result<Vec<SafeData>> {
+        for attempt in 1..=CONSISTENT_READ_MAX_ATTEMPTS {
+            // Read the content, checking all URLs were resolved through the same
+            // versions of the containers they have in common
+            let mut versions = ContainerVersions::new();
+            let mut results = Vec::with_capacity(urls.len());
+            for url in urls {
+                let mut chain = self.retrieve_from_url(url, true, None, true).await?;
+                if !track_container_versions(&chain, &mut versions)? {
+                    break;
+                }
+                let safe_data = chain
+                    .pop()
+                    .ok_or_else(|| Error::ContentNotFound(format!("Failed to resolve {}", url)))?;
+                results.push(safe_data);
+            }
+
+            if results.len() == urls.len() {
+                return Ok(results);
+            }
+            debug!(
+                "Concurrent update detected on attempt #{} to read URLs consistently, retrying...",
+                attempt
+            );
+        }
+
+        Err(Error::ContentError(format!(
+            "Failed to read a consistent version of the URLs after {} attempts, content is being updated concurrently",
+            CONSISTENT_READ_MAX_ATTEMPTS
+        )))
     }
 
     /// # Inspect a safe:// URL and retrieve metadata information but the actual target content
@@ -347,8 +410,18 @@ impl Safe {
                     nrs_map
                 );
 
-                let target_url = nrs_map.resolve_for_subnames(the_xor.sub_names_vec())?;
-                debug!("Resolved target: {}", target_url);
+                let (target_url, follows_latest) = self
+                    .nrs_resolve_link(&nrs_map, the_xor.sub_names_vec())
+                    .await?;
+                debug!(
+                    "Resolved target: {}{}",
+                    target_url,
+                    if follows_latest {
+                        " (following its latest version)"
+                    } else {
+                        ""
+                    }
+                );
 
                 let mut target_safe_url = Safe::parse_url(&target_url)?;
                 // Let's concatenate the path corresponding to the URL we are processing
@@ -382,6 +455,7 @@ impl Safe {
                     nrs_map,
                     data_type: the_xor.data_type(),
                     resolved_from: url,
+                    follows_latest,
                 };
 
                 Ok((nrs_map_container, Some((target_safe_url, None))))
@@ -428,22 +502,8 @@ impl Safe {
                             .await
                     }
                     DataType::Register => {
-                        let data = if retrieve_data {
-                            // TODO: use the content hash in the URL to grab a single element if it exists
-                            self.fetch_register_entries(&the_xor).await?
-                        } else {
-                            BTreeSet::new()
-                        };
-
-                        let safe_data = SafeData::PublicRegister {
-                            xorurl,
-                            xorname: the_xor.xorname(),
-                            type_tag: the_xor.type_tag(),
-                            data,
-                            resolved_from: url.to_string(),
-                        };
-
-                        Ok((safe_data, None))
+                        self.retrieve_register(&the_xor, retrieve_data, None, url)
+                            .await
                     }
                 }
             }
@@ -468,6 +528,10 @@ impl Safe {
                         )
                         .await
                     }
+                    DataType::Register => {
+                        self.retrieve_register(&the_xor, retrieve_data, Some(media_type_str), url)
+                            .await
+                    }
                     other => Err(Error::ContentError(format!(
                         "Data type '{:?}' not supported yet",
                         other
@@ -480,6 +544,32 @@ impl Safe {
         }
     }
 
+    async fn retrieve_register(
+        &self,
+        the_xor: &Url,
+        retrieve_data: bool,
+        media_type: Option<String>,
+        url: String,
+    ) -> Result<(SafeData, Option<NextStepInfo>)> {
+        let data = if retrieve_data {
+            // TODO: use the content hash in the URL to grab a single element if it exists
+            self.fetch_register_entries(the_xor).await?
+        } else {
+            BTreeSet::new()
+        };
+
+        let safe_data = SafeData::PublicRegister {
+            xorurl: the_xor.to_xorurl_string(),
+            xorname: the_xor.xorname(),
+            type_tag: the_xor.type_tag(),
+            data,
+            media_type,
+            resolved_from: url,
+        };
+
+        Ok((safe_data, None))
+    }
+
     async fn retrieve_data(
         &self,
         the_xor: &Url,
@@ -497,7 +587,7 @@ impl Safe {
 
         let data = if retrieve_data {
             self.safe_client
-                .get_bytes(BytesAddress::Public(the_xor.xorname()), range)
+                .get_bytes(the_xor.bytes_address()?, range)
                 .await?
         } else {
             Bytes::new()
@@ -540,6 +630,41 @@ fn gen_filtered_filesmap(urlpath: &str, files_map: &FilesMap, xorurl: &str) -> R
         Ok(filtered_filesmap)
     }
 }
+
+// Record the version of each container found in a resolution chain, returning false
+// if any of them has a different version than the one previously recorded for it.
+// Containers resolved from a URL which explicitly sets the version are not tracked.
+fn track_container_versions(chain: &[SafeData], versions: &mut ContainerVersions) -> Result<bool> {
+    for safe_data in chain {
+        match safe_data {
+            SafeData::FilesContainer {
+                xorname,
+                type_tag,
+                version,
+                resolved_from,
+                ..
+            }
+            | SafeData::NrsMapContainer {
+                xorname,
+                type_tag,
+                version,
+                resolved_from,
+                ..
+            } => {
+                if Safe::parse_url(resolved_from)?.content_version().is_some() {
+                    continue;
+                }
+                if *versions.entry((*xorname, *type_tag)).or_insert(*version) != *version {
+                    return Ok(false);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(true)
+}
+
 // // This contains information for the next step to be made
 // // in each iteration of the resolution process
 type NextStepInfo = (Url, Option<FileItem>);
@@ -547,10 +672,9 @@ type NextStepInfo = (Url, Option<FileItem>);
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{app::test_helpers::new_safe_instance, retry_loop, Scope, Url};
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern, Url};
     use anyhow::{anyhow, bail, Context, Result};
     use rand::{distributions::Alphanumeric, thread_rng, Rng};
-    use safe_network::types::DataAddress;
     use std::io::Read;
 
     #[tokio::test]
@@ -818,43 +942,31 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_fetch_unsupported_with_media_type() -> Result<()> {
+    async fn test_fetch_register_with_media_type() -> Result<()> {
         let mut safe = new_safe_instance().await?;
-        let xorname = rand::random();
-        let type_tag = 575_756_443;
-        let xorurl = Url::encode(
-            DataAddress::register(xorname, Scope::Public, type_tag),
-            None,
-            type_tag,
-            ContentType::MediaType("text/html".to_string()),
-            None,
-            None,
-            None,
-            None,
-            None,
-            XorUrlBase::Base32z,
-        )?;
-
-        match safe.fetch(&xorurl, None).await {
-            Ok(c) => {
-                bail!("Unxpected fetched content: {:?}", c)
-            }
-            Err(Error::ContentError(msg)) => {
-                assert_eq!(msg, "Data type 'Register' not supported yet".to_string())
-            }
-            other => bail!("Error returned is not the expected one: {:?}", other),
-        };
-
-        match safe.inspect(&xorurl).await {
-            Ok(c) => Err(anyhow!("Unxpected fetched content: {:?}", c)),
-            Err(Error::ContentError(msg)) => {
-                assert_eq!(msg, "Data type 'Register' not supported yet".to_string());
+        let metadata = Bytes::from("an html feed");
+        let xorurl = safe
+            .register_create_with_metadata(
+                None,
+                575_756_443,
+                false,
+                ContentType::MediaType("text/html".to_string()),
+                Some(metadata.clone()),
+            )
+            .await?;
+        let fetched_metadata =
+            retry_loop_for_pattern!(safe.register_metadata(&xorurl), Ok(Some(_)))?;
+        assert_eq!(fetched_metadata, Some(metadata));
+
+        match safe.inspect(&xorurl).await?.first() {
+            Some(SafeData::PublicRegister {
+                data, media_type, ..
+            }) => {
+                assert!(data.is_empty());
+                assert_eq!(media_type.as_deref(), Some("text/html"));
                 Ok(())
             }
-            other => Err(anyhow!(
-                "Error returned is not the expected one: {:?}",
-                other
-            )),
+            other => Err(anyhow!("Unexpected inspected content: {:?}", other)),
         }
     }
 
@@ -901,4 +1013,73 @@ mod tests {
             )),
         }
     }
+
+    #[tokio::test]
+    async fn test_read_consistent() -> Result<()> {
+        let site_name: String = thread_rng().sample_iter(&Alphanumeric).take(15).collect();
+
+        let mut safe = new_safe_instance().await?;
+        let (xorurl, _, _) = safe
+            .files_container_create(Some("./testdata/"), None, true, false, false)
+            .await?;
+        let _ = retry_loop!(safe.fetch(&xorurl, None));
+        let (version0, _) = retry_loop!(safe.files_container_get(&xorurl));
+
+        let mut safe_url = Url::from_url(&xorurl)?;
+        safe_url.set_content_version(Some(version0));
+        let _ = safe
+            .nrs_map_container_create(&site_name, &safe_url.to_string(), true, true, false)
+            .await?;
+
+        let nrs_url = format!("safe://{}", site_name);
+        let _ = retry_loop!(safe.fetch(&nrs_url, None));
+
+        let index_url = format!("{}/test.md", nrs_url);
+        let sub_url = format!("{}/subfolder/subexists.md", nrs_url);
+        let results = safe
+            .read_consistent(&[&nrs_url, &index_url, &sub_url])
+            .await?;
+
+        assert_eq!(results.len(), 3);
+        match &results[0] {
+            SafeData::FilesContainer { version, .. } => assert_eq!(*version, version0),
+            other => bail!("Unexpected content found: {:?}", other),
+        }
+        match &results[1] {
+            SafeData::PublicBlob { data, .. } => {
+                assert!(String::from_utf8(data.to_vec())?.starts_with("hello tests!"))
+            }
+            other => bail!("Unexpected content found: {:?}", other),
+        }
+        assert!(matches!(results[2], SafeData::PublicBlob { .. }));
+
+        // URLs pinning different versions of the same container are read as requested
+        let (version1, _, _) = safe
+            .files_container_sync(
+                "./testdata/subfolder/",
+                &xorurl,
+                false,
+                false,
+                false,
+                false,
+                false,
+            )
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.files_container_get(&xorurl), Ok((version, _)) if *version == version1)?;
+        let mut latest_url = Url::from_url(&xorurl)?;
+        latest_url.set_content_version(None);
+        let results = safe
+            .read_consistent(&[&safe_url.to_string(), &latest_url.to_string()])
+            .await?;
+        match &results[..] {
+            [SafeData::FilesContainer { version: v0, .. }, SafeData::FilesContainer { version: v1, .. }] =>
+            {
+                assert_eq!(*v0, version0);
+                assert_eq!(*v1, version1);
+            }
+            other => bail!("Unexpected content found: {:?}", other),
+        }
+
+        Ok(())
+    }
 }
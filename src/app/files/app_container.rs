@@ -0,0 +1,96 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{FilesMap, FILES_CONTAINER_TYPE_TAG};
+use crate::{
+    app::encryption::{derive_symmetric_key, keyed_hash},
+    ContentType, Error, Result, Safe, Scope, Url, XorName, XorUrl,
+};
+use log::debug;
+
+// Context used to derive the location of app containers from the keypair
+const APP_CONTAINER_CONTEXT: &[u8] = b"sn_api-app-container";
+
+impl Safe {
+    /// # Get the private FilesContainer dedicated to an app
+    ///
+    /// The container is at a location derived from this instance's keypair and the
+    /// app id, so the same one is returned each time, it's created upon first use.
+    /// Each app can use it as the root for its private data.
+    pub async fn app_container(&self, app_id: &str) -> Result<XorUrl> {
+        if app_id.is_empty() {
+            return Err(Error::InvalidInput(
+                "The app id of an app container cannot be empty".to_string(),
+            ));
+        }
+
+        let keypair = self.get_my_keypair()?;
+        let key = derive_symmetric_key(&keypair, APP_CONTAINER_CONTEXT)?;
+        let xorname = XorName(keyed_hash(&key, app_id.as_bytes()));
+        let xorurl = Url::encode_register(
+            xorname,
+            FILES_CONTAINER_TYPE_TAG,
+            Scope::Private,
+            ContentType::FilesContainer,
+            self.xorurl_base,
+        )?;
+        let safe_url = Url::from_xorurl(&xorurl)?;
+
+        match self.fetch_register_entries(&safe_url).await {
+            Ok(_) => return Ok(xorurl),
+            Err(Error::EmptyContent(_)) => {}
+            Err(Error::ContentNotFound(_)) => {
+                debug!(
+                    "App container for '{}' not found, attempting to create it",
+                    app_id
+                );
+                let _ = self
+                    .safe_client
+                    .store_register(Some(xorname), FILES_CONTAINER_TYPE_TAG, None, true)
+                    .await?;
+            }
+            Err(err) => return Err(err),
+        }
+
+        // The first version of the container is an empty FilesMap
+        let files_map_xorurl = self
+            .store_files_map(&FilesMap::default(), Scope::Public)
+            .await?;
+        let _ = self
+            .write_to_register(
+                &xorurl,
+                Url::from_xorurl(&files_map_xorurl)?,
+                Default::default(),
+            )
+            .await?;
+
+        Ok(xorurl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{app::test_helpers::new_safe_instance, retry_loop};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_app_container() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+
+        let xorurl = safe.app_container("net.maidsafe.test").await?;
+        let (_, files_map) = retry_loop!(safe.files_container_get(&xorurl));
+        assert!(files_map.is_empty());
+
+        assert_eq!(safe.app_container("net.maidsafe.test").await?, xorurl);
+        assert_ne!(safe.app_container("net.maidsafe.other").await?, xorurl);
+        assert!(safe.app_container("").await.is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::FilesMap;
+use crate::{
+    app::encryption::{decrypt_payload, derive_symmetric_key, encrypt_payload},
+    Error, Result, Safe, Scope, Url, UrlAddressExt, VersionHash,
+};
+use bytes::Bytes;
+use log::info;
+
+// Context used to derive the key the FilesMaps of private FilesContainers are encrypted with
+const FILES_MAP_CONTEXT: &[u8] = b"sn_api-files-map";
+
+// Prefix of encrypted FilesMaps, which tells them apart from the plain FilesMaps
+// stored before their encryption was supported, which are JSON objects
+const ENCRYPTED_FILES_MAP_PREFIX: &[u8] = b"sn_api-encrypted-files-map:";
+
+impl Safe {
+    /// # Encrypt the metadata of an existing private FilesContainer
+    ///
+    /// The FilesMap of private FilesContainers, i.e. the names, paths and metadata of
+    /// their files, is encrypted with a key derived from the keypair this instance is
+    /// connected with, see `set_encrypt_private_metadata`. FilesContainers created before
+    /// that was supported can be migrated with this function, which stores a new version
+    /// of the FilesContainer with its current FilesMap encrypted, and returns it. The
+    /// current version is returned if its FilesMap is encrypted already.
+    pub async fn files_container_encrypt_metadata(&self, url: &str) -> Result<VersionHash> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let (_, _, scope) = safe_url.register_parts()?;
+        if scope != Scope::Private {
+            return Err(Error::InvalidInput(format!(
+                "Only the metadata of private FilesContainers can be encrypted: {}",
+                url
+            )));
+        }
+
+        let entries = self.fetch_register_entries(&safe_url).await?;
+        if entries.len() > 1 {
+            return Err(Error::NotImplementedError(
+                "Multiple file container entries not managed, the FilesContainer needs to be \
+                 updated before its metadata can be encrypted"
+                    .to_string(),
+            ));
+        }
+        let (current_hash, files_map_xorurl) = entries.into_iter().next().ok_or_else(|| {
+            Error::EmptyContent(format!("FilesContainer found at \"{}\" was empty", url))
+        })?;
+
+        let serialised_files_map = self.fetch_public_data(&files_map_xorurl, None).await?;
+        if serialised_files_map.starts_with(ENCRYPTED_FILES_MAP_PREFIX) {
+            return Ok(VersionHash::from(&current_hash));
+        }
+
+        info!("Encrypting the metadata of FilesContainer at {}", url);
+        let files_map = self.decode_files_map(&serialised_files_map)?;
+        let encrypted_files_map = self.encrypt_files_map(&files_map)?;
+        let files_map_xorurl = self
+            .store_private_bytes(Bytes::from(encrypted_files_map), None)
+            .await?;
+        let entry_hash = self
+            .write_to_register(
+                &safe_url.to_string(),
+                Url::from_xorurl(&files_map_xorurl)?,
+                vec![current_hash].into_iter().collect(),
+            )
+            .await?;
+
+        Ok(VersionHash::from(&entry_hash))
+    }
+
+    // Serialise a FilesMap to be stored with the given scope, encrypting it
+    // if the scope is private and the encryption of metadata is enabled
+    pub(crate) fn encode_files_map(&self, files_map: &FilesMap, scope: Scope) -> Result<Vec<u8>> {
+        if scope == Scope::Private && self.encrypt_private_metadata {
+            self.encrypt_files_map(files_map)
+        } else {
+            serialise_files_map(files_map)
+        }
+    }
+
+    // Deserialise a FilesMap, decrypting it if it was stored encrypted
+    pub(crate) fn decode_files_map(&self, serialised_files_map: &[u8]) -> Result<FilesMap> {
+        match serialised_files_map.strip_prefix(ENCRYPTED_FILES_MAP_PREFIX) {
+            Some(encrypted_files_map) => {
+                let serialised_files_map = decrypt_payload(
+                    &self.encryption_policy,
+                    &self.files_map_key()?,
+                    encrypted_files_map,
+                )?;
+                deserialise_files_map(&serialised_files_map)
+            }
+            None => deserialise_files_map(serialised_files_map),
+        }
+    }
+
+    fn encrypt_files_map(&self, files_map: &FilesMap) -> Result<Vec<u8>> {
+        let serialised_files_map = serialise_files_map(files_map)?;
+        let mut encrypted_files_map = ENCRYPTED_FILES_MAP_PREFIX.to_vec();
+        encrypted_files_map.extend(encrypt_payload(
+            &self.encryption_policy,
+            &self.files_map_key()?,
+            &serialised_files_map,
+        )?);
+        Ok(encrypted_files_map)
+    }
+
+    fn files_map_key(&self) -> Result<[u8; 32]> {
+        derive_symmetric_key(&self.get_my_keypair()?, FILES_MAP_CONTEXT)
+    }
+}
+
+fn serialise_files_map(files_map: &FilesMap) -> Result<Vec<u8>> {
+    // TODO: use RDF format
+    serde_json::to_vec(files_map).map_err(|err| {
+        Error::Serialisation(format!(
+            "Couldn't serialise the FilesMap generated: {:?}",
+            err
+        ))
+    })
+}
+
+fn deserialise_files_map(serialised_files_map: &[u8]) -> Result<FilesMap> {
+    serde_json::from_slice(serialised_files_map).map_err(|err| {
+        Error::ContentError(format!(
+            "Couldn't deserialise the FilesMap stored in the FilesContainer: {:?}",
+            err
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop};
+    use anyhow::{anyhow, Result};
+
+    #[tokio::test]
+    async fn test_files_container_private_metadata_encrypted() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        safe.set_private_by_default(true);
+        let (xorurl, _, files_map) = safe
+            .files_container_create(Some("./testdata/test.md"), None, false, false, false)
+            .await?;
+
+        let safe_url = Url::from_url(&xorurl)?;
+        let entries = retry_loop!(safe.fetch_register_entries(&safe_url));
+        let (_, files_map_xorurl) = entries
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("FilesContainer has no entries"))?;
+        let stored = safe.fetch_public_data(&files_map_xorurl, None).await?;
+        assert!(stored.starts_with(ENCRYPTED_FILES_MAP_PREFIX));
+        assert!(!String::from_utf8_lossy(&stored).contains("test.md"));
+
+        let (_, fetched_files_map) = safe.files_container_get(&xorurl).await?;
+        assert_eq!(fetched_files_map, files_map);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_files_container_encrypt_metadata() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        safe.set_private_by_default(true);
+        safe.set_encrypt_private_metadata(false);
+        let (xorurl, _, files_map) = safe
+            .files_container_create(Some("./testdata/test.md"), None, false, false, false)
+            .await?;
+        let (plain_version, _) = retry_loop!(safe.files_container_get(&xorurl));
+
+        let mut unversioned_url = Url::from_url(&xorurl)?;
+        unversioned_url.set_content_version(None);
+        let version = safe
+            .files_container_encrypt_metadata(&unversioned_url.to_string())
+            .await?;
+        assert_ne!(version, plain_version);
+        let (current_version, fetched_files_map) =
+            retry_loop!(safe.files_container_get(&unversioned_url.to_string()));
+        assert_eq!(current_version, version);
+        assert_eq!(fetched_files_map, files_map);
+
+        // encrypting it again doesn't create a new version
+        let same_version = safe
+            .files_container_encrypt_metadata(&unversioned_url.to_string())
+            .await?;
+        assert_eq!(same_version, version);
+
+        Ok(())
+    }
+}
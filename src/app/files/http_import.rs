@@ -0,0 +1,235 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{metadata::FileMeta, FilesMap, ProcessedFiles, Provenance};
+use crate::{
+    app::{consts::*, helpers::sha3_256_hex},
+    Error, Result, Safe, VersionHash,
+};
+use bytes::Bytes;
+use log::{debug, info};
+use std::{
+    collections::{BTreeMap, HashSet},
+    iter::FromIterator,
+};
+
+// Default maximum size of each resource to import
+const DEFAULT_HTTP_IMPORT_MAX_SIZE: u64 = 100 * 1024 * 1024;
+
+// File name used for resources whose URL has no path, e.g. https://example.com/
+const DEFAULT_HTTP_IMPORT_FILE_NAME: &str = "index.html";
+
+/// Options for importing HTTP resources into a FilesContainer
+#[derive(Debug, Clone)]
+pub struct HttpImportOptions {
+    /// Maximum size in bytes of each resource, the import fails if any is larger
+    pub max_size: u64,
+    /// Expected SHA3-256 checksums, hex encoded, of some of the resources keyed by their URL.
+    /// The import fails if any of them doesn't match.
+    pub checksums: BTreeMap<String, String>,
+    /// Overwrite the files which already exist at the paths the resources are imported to
+    pub force: bool,
+}
+
+impl Default for HttpImportOptions {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_HTTP_IMPORT_MAX_SIZE,
+            checksums: BTreeMap::new(),
+            force: false,
+        }
+    }
+}
+
+impl Safe {
+    /// # Import HTTP resources into a FilesContainer
+    ///
+    /// Each resource is downloaded and published as a file named after the last segment
+    /// of its URL, in the folder the container URL's path targets. All files are added
+    /// in a single new version of the FilesContainer, recording the URL they were
+    /// downloaded from as their source. Nothing is added if any of the downloads fail.
+    pub async fn files_import_http(
+        &mut self,
+        urls: &[&str],
+        container_url: &str,
+        options: &HttpImportOptions,
+    ) -> Result<(VersionHash, ProcessedFiles, FilesMap)> {
+        let safe_url = Safe::parse_url(container_url)?;
+        if safe_url.content_version().is_some() {
+            return Err(Error::InvalidInput(format!(
+                "The target URL cannot contain a version: {}",
+                container_url
+            )));
+        };
+
+        let (mut safe_url, _) = self.parse_and_resolve_url(container_url).await?;
+        safe_url.set_content_version(None);
+        let (current_version, mut files_map) = self.fetch_files_container(&safe_url).await?;
+        let dest_folder = safe_url.path().trim_end_matches('/').to_string();
+
+        let mut processed_files = ProcessedFiles::new();
+        for url in urls.iter() {
+            let dest_path = format!("{}/{}", dest_folder, file_name_from_url(url)?);
+            let exists = files_map.contains_key(&dest_path);
+            if exists && !options.force {
+                return Err(Error::InvalidInput(format!(
+                    "A file already exists at \"{}\" to import {} into, use the 'force' option to overwrite it",
+                    dest_path, url
+                )));
+            }
+
+            let (data, media_type) = download(url, options.max_size).await?;
+            if let Some(expected) = options.checksums.get(*url) {
+                let checksum = sha3_256_hex(&data);
+                if !checksum.eq_ignore_ascii_case(expected) {
+                    return Err(Error::ContentError(format!(
+                        "Checksum of {} is {}, but {} was expected",
+                        url, checksum, expected
+                    )));
+                }
+            }
+
+            let size = data.len().to_string();
+            let xorurl = match self
+                .store_public_bytes(data.clone(), media_type.as_deref(), false)
+                .await
+            {
+                Ok(xorurl) => xorurl,
+                Err(Error::InvalidMediaType(_)) => {
+                    debug!("Unsupported media type of {}, storing it as raw", url);
+                    self.store_public_bytes(data, None, false).await?
+                }
+                Err(err) => return Err(err),
+            };
+
+            let file_type = media_type.unwrap_or_else(|| "Raw".to_string());
+            let mut file_item = FileMeta::from_type_and_size(&file_type, &size).to_file_item();
+            let _ = file_item.insert(PREDICATE_LINK.to_string(), xorurl.clone());
+            let provenance = Provenance {
+                source: Some(url.to_string()),
+                ..Default::default()
+            };
+            provenance.apply_to(&mut file_item);
+
+            let sign = if exists {
+                CONTENT_UPDATED_SIGN
+            } else {
+                CONTENT_ADDED_SIGN
+            };
+            let _ = processed_files.insert(dest_path.clone(), (sign.to_string(), xorurl));
+            let _ = files_map.insert(dest_path, file_item);
+        }
+
+        let version = if processed_files.is_empty() {
+            current_version
+        } else {
+            self.append_version_to_files_container(
+                HashSet::from_iter([current_version]),
+                &files_map,
+                container_url,
+                safe_url,
+                false,
+                false,
+            )
+            .await?
+        };
+
+        Ok((version, processed_files, files_map))
+    }
+}
+
+// Download a resource, failing as soon as it's known to be larger than the maximum size,
+// returning its content and media type
+async fn download(url: &str, max_size: u64) -> Result<(Bytes, Option<String>)> {
+    info!("Downloading {}", url);
+    let download_error =
+        |err: reqwest::Error| Error::ContentError(format!("Failed to download {}: {}", url, err));
+    let too_large = || {
+        Error::InvalidInput(format!(
+            "Resource at {} is larger than the maximum size of {} bytes",
+            url, max_size
+        ))
+    };
+
+    let mut response = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(download_error)?;
+    if response
+        .content_length()
+        .map_or(false, |len| len > max_size)
+    {
+        return Err(too_large());
+    }
+
+    let media_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|media_type| media_type.trim().to_string())
+        .or_else(|| {
+            mime_guess::from_path(url)
+                .first_raw()
+                .map(|media_type| media_type.to_string())
+        });
+
+    let mut data = vec![];
+    while let Some(chunk) = response.chunk().await.map_err(download_error)? {
+        if data.len() as u64 + chunk.len() as u64 > max_size {
+            return Err(too_large());
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    Ok((Bytes::from(data), media_type))
+}
+
+// Name of the file a resource is imported as, i.e. the last segment of its URL's path
+fn file_name_from_url(url: &str) -> Result<String> {
+    let parsed = url::Url::parse(url)
+        .map_err(|err| Error::InvalidInput(format!("Invalid URL {}: {}", url, err)))?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err(Error::InvalidInput(format!(
+            "Only HTTP and HTTPS resources can be imported: {}",
+            url
+        )));
+    }
+
+    let name = parsed
+        .path_segments()
+        .and_then(|segments| segments.filter(|segment| !segment.is_empty()).last())
+        .unwrap_or(DEFAULT_HTTP_IMPORT_FILE_NAME);
+    Ok(urlencoding::decode(name)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|_| name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name_from_url() {
+        assert_eq!(
+            file_name_from_url("https://example.com/docs/report%20final.pdf").ok(),
+            Some("report final.pdf".to_string())
+        );
+        assert_eq!(
+            file_name_from_url("https://example.com/docs/").ok(),
+            Some("docs".to_string())
+        );
+        assert_eq!(
+            file_name_from_url("http://example.com").ok(),
+            Some(DEFAULT_HTTP_IMPORT_FILE_NAME.to_string())
+        );
+        assert!(file_name_from_url("ftp://example.com/file").is_err());
+        assert!(file_name_from_url("not a url").is_err());
+    }
+}
@@ -0,0 +1,201 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{
+    app::{encryption::keyed_hash, lease::Lease},
+    ContentType, Error, PublicKey, Result, Safe, Scope, Url, UrlAddressExt, XorName,
+};
+use log::{debug, info, warn};
+use std::{
+    collections::BTreeSet,
+    time::{Duration, Instant},
+};
+
+// Type tag of the Registers the publish locks are leased on
+const PUBLISH_LOCK_TYPE_TAG: u64 = 3_000;
+
+// Context of the hash the location of the lock of a FilesContainer is derived with
+const PUBLISH_LOCK_CONTEXT: &[u8] = b"sn_api-publish-lock";
+
+/// How the updates of FilesContainers are serialised among publishers, e.g. concurrent CI
+/// jobs publishing to the same FilesContainer, see `Safe::set_publish_lock`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublishLock {
+    /// Keys of the publishers, which are allowed to write to the lock Registers created
+    /// by this instance, besides its own key
+    pub publishers: BTreeSet<PublicKey>,
+    /// TTL of the lease held while updating a FilesContainer, it should exceed the time
+    /// an update takes, since another publisher can take over once the lease expires
+    pub ttl: Duration,
+    /// How long to wait for the lock while another publisher holds it, before failing with
+    /// `Error::LeaseUnavailable`, it fails right away if zero
+    pub wait_timeout: Duration,
+    /// Interval at which the lock is tried again while waiting for it
+    pub retry_interval: Duration,
+}
+
+impl Default for PublishLock {
+    fn default() -> Self {
+        Self {
+            publishers: BTreeSet::new(),
+            ttl: Duration::from_secs(600),
+            wait_timeout: Duration::from_secs(300),
+            retry_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Safe {
+    /// # Serialise the updates of FilesContainers among publishers
+    ///
+    /// With a publish lock set, each update of a FilesContainer, e.g. `files_container_sync`
+    /// or `files_container_add`, including the update of the NRS name if requested, is made
+    /// while holding a lease on a lock Register derived from the FilesContainer's address,
+    /// see `lease_acquire`, so publishers sharing the same lock settings update it one after
+    /// the other, rather than forking it. Publishers wait for the lock, up to the timeout set,
+    /// in no particular order. The lock Register is created, writable by all the publishers,
+    /// by the first one which needs it. Dry runs don't take the lock. No lock is taken by
+    /// default, and `None` disables it.
+    pub fn set_publish_lock(&mut self, lock: Option<PublishLock>) {
+        self.publish_lock = lock;
+    }
+
+    /// The publish lock settings, if there are any
+    pub fn publish_lock(&self) -> Option<&PublishLock> {
+        self.publish_lock.as_ref()
+    }
+
+    // Acquire the publish lock of the FilesContainer the URL targets, waiting for it as per
+    // the settings, if a publish lock is set, returning the lease to release once updated
+    pub(crate) async fn publish_lock_acquire(&self, url: &str) -> Result<Option<Lease>> {
+        let lock = match &self.publish_lock {
+            Some(lock) => lock.clone(),
+            None => return Ok(None),
+        };
+        let lock_url = self.publish_lock_url(url, &lock).await?;
+
+        let started = Instant::now();
+        loop {
+            match self.lease_acquire(&lock_url, lock.ttl).await {
+                Ok(lease) => {
+                    info!("Publish lock acquired for {}", url);
+                    return Ok(Some(lease));
+                }
+                Err(Error::LeaseUnavailable(msg))
+                    if started.elapsed() + lock.retry_interval <= lock.wait_timeout =>
+                {
+                    debug!("Waiting for the publish lock of {}: {}", url, msg);
+                    self.runtime().sleep(lock.retry_interval).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    // Release the publish lock if it was acquired. Failing to release it is not an error,
+    // as the update it was held for is done, and the lease expires anyway.
+    pub(crate) async fn publish_lock_release(&self, lease: Option<Lease>) {
+        if let Some(lease) = lease {
+            let url = lease.url.clone();
+            if let Err(err) = self.lease_release(lease).await {
+                warn!("Failed to release the publish lock at {}: {}", url, err);
+            }
+        }
+    }
+
+    // Location of the lock Register of the FilesContainer the URL targets,
+    // creating the Register if it doesn't exist yet
+    async fn publish_lock_url(&self, url: &str, lock: &PublishLock) -> Result<String> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let address = safe_url.register_address()?;
+        let mut content = address.name().0.to_vec();
+        content.extend(&address.tag().to_le_bytes());
+        let lock_xorname = XorName(keyed_hash(PUBLISH_LOCK_CONTEXT, &content));
+        let lock_url = Url::encode_register(
+            lock_xorname,
+            PUBLISH_LOCK_TYPE_TAG,
+            Scope::Public,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?;
+
+        match self.register_read(&lock_url).await {
+            Ok(_) | Err(Error::EmptyContent(_)) => {}
+            Err(Error::ContentNotFound(_)) => {
+                debug!("Publish lock Register not found, creating it");
+                let _ = self
+                    .register_create_with_writers(
+                        Some(lock_xorname),
+                        PUBLISH_LOCK_TYPE_TAG,
+                        false,
+                        lock.publishers.clone(),
+                    )
+                    .await?;
+            }
+            Err(err) => return Err(err),
+        }
+
+        Ok(lock_url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop};
+    use anyhow::{anyhow, Result};
+
+    #[tokio::test]
+    async fn test_publish_lock() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let (xorurl, _, _) = retry_loop!(safe.files_container_create(
+            Some("./testdata/test.md"),
+            None,
+            false,
+            false,
+            false
+        ));
+
+        safe.set_publish_lock(Some(PublishLock {
+            wait_timeout: Duration::from_secs(0),
+            ..PublishLock::default()
+        }));
+        let (_, _, files_map) = safe
+            .files_container_add_from_raw(
+                bytes::Bytes::from("published"),
+                &format!("{}/published.txt", xorurl),
+                false,
+                false,
+                false,
+            )
+            .await?;
+        assert!(files_map.contains_key("/published.txt"));
+
+        // another publisher holding the lock makes the update fail once the wait times out
+        let lease = retry_loop!(safe.publish_lock_acquire(&xorurl))
+            .ok_or_else(|| anyhow!("No lease acquired"))?;
+        match safe
+            .files_container_add_from_raw(
+                bytes::Bytes::from("blocked"),
+                &format!("{}/blocked.txt", xorurl),
+                false,
+                false,
+                false,
+            )
+            .await
+        {
+            Err(Error::LeaseUnavailable(_)) => {}
+            other => return Err(anyhow!("Unexpected result: {:?}", other)),
+        }
+        safe.publish_lock_release(Some(lease)).await;
+
+        Ok(())
+    }
+}
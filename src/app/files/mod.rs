@@ -7,16 +7,26 @@
 // specific language governing permissions and limitations relating to use of the SAFE Network
 // Software.
 
+mod app_container;
+mod encrypted_map;
 mod file_system;
 mod files_map;
+#[cfg(feature = "http_import")]
+mod http_import;
+mod lock;
 mod metadata;
+mod provenance;
 mod realpath;
+mod stream;
+mod variants;
 
+use super::register::{is_tombstone, QuarantineKind};
 use crate::{
-    app::consts::*, app::nrs::VersionHash, fetch::Range, ContentType, DataType, Error, Result,
-    Safe, Scope, Url, XorUrl,
+    app::consts::*, app::events::SafeEvent, app::helpers::gen_timestamp_secs,
+    app::nrs::VersionHash, fetch::Range, ContentType, DataType, Error, IndexedKind, Result, Safe,
+    Scope, Url, UrlAddressExt, XorUrl,
 };
-use bytes::{Buf, Bytes};
+use bytes::Bytes;
 use file_system::{file_system_dir_walk, file_system_single_file, normalise_path_separator};
 use files_map::add_or_update_file_item;
 use log::{debug, info, warn};
@@ -30,13 +40,19 @@ pub(crate) use metadata::FileMeta;
 pub(crate) use realpath::RealPath;
 
 pub use files_map::{FileItem, FilesMap, GetAttr};
+#[cfg(feature = "http_import")]
+pub use http_import::HttpImportOptions;
+pub use lock::PublishLock;
+pub use provenance::Provenance;
+pub use stream::{FetchOptions, FetchOrder, StreamedFileInfo};
+pub use variants::{Accept, ContentVariant};
 
 // List of files uploaded with details if they were added, updated or deleted from FilesContainer
 pub type ProcessedFiles = BTreeMap<String, (String, String)>;
 
 const ERROR_MSG_NO_FILES_CONTAINER_FOUND: &str = "No FilesContainer found at this address";
 // Type tag to use for the FilesContainer stored on Register
-const FILES_CONTAINER_TYPE_TAG: u64 = 1_100;
+pub(crate) const FILES_CONTAINER_TYPE_TAG: u64 = 1_100;
 
 impl Safe {
     /// # Create a FilesContainer.
@@ -90,19 +106,25 @@ impl Safe {
         let xorurl = if dry_run {
             "".to_string()
         } else {
-            // Store the serialised FilesMap in a Public Blob
-            let files_map_xorurl = self.store_files_map(&files_map).await?;
+            // Store the serialised FilesMap in a Blob, with the default scope
+            let scope = self.default_scope();
+            let files_map_xorurl = self.store_files_map(&files_map, scope).await?;
 
             // Store the serialised FilesMap XOR-URL as the first entry value in the Register
             let xorname = self
                 .safe_client
-                .store_register(None, FILES_CONTAINER_TYPE_TAG, None, false)
+                .store_register(
+                    None,
+                    FILES_CONTAINER_TYPE_TAG,
+                    None,
+                    matches!(scope, Scope::Private),
+                )
                 .await?;
 
             let xor_url = Url::encode_register(
                 xorname,
                 FILES_CONTAINER_TYPE_TAG,
-                Scope::Public,
+                scope,
                 ContentType::FilesContainer,
                 self.xorurl_base,
             )?;
@@ -114,6 +136,11 @@ impl Safe {
 
             let mut tmp_url = Url::from_xorurl(&xor_url)?;
             tmp_url.set_content_version(Some(VersionHash::from(entry_hash)));
+            self.local_index.insert(
+                IndexedKind::FilesContainer,
+                location.unwrap_or(&xor_url),
+                &xor_url,
+            );
             format!("{}", &tmp_url)
         };
 
@@ -167,35 +194,51 @@ impl Safe {
                 err => Error::NetDataError(format!("Failed to get current version: {}", err)),
             })?;
 
-        // take the 1st entry (TODO Multiple entries)
         debug!(
             "Retrieved {} entries for register at {}",
             entries.len(),
             safe_url.to_string()
         );
-        if entries.len() > 1 {
-            return Err(Error::NotImplementedError("Multiple file container entries not managed, this happends when 2 clients write concurrently to a file container".to_string()));
-        }
-        let first_entry = entries.iter().next();
-        let (version, files_map_xorurl) = if let Some((v, m)) = first_entry {
-            (v.into(), m.to_owned())
-        } else {
-            warn!("FilesContainer found at \"{:?}\" was empty", safe_url);
-            return Ok((VersionHash::default(), FilesMap::default()));
-        };
 
-        debug!("Files map retrieved.... v{:?}", &version);
-        // TODO: use RDF format and deserialise it
-        // Using the FilesMap XOR-URL we can now fetch the FilesMap and deserialise it
-        let serialised_files_map = self.fetch_public_data(&files_map_xorurl, None).await?;
-        let files_map = serde_json::from_slice(serialised_files_map.chunk()).map_err(|err| {
-            Error::ContentError(format!(
-                "Couldn't deserialise the FilesMap stored in the FilesContainer: {:?}",
-                err
-            ))
-        })?;
+        // decode the FilesMap of each entry, quarantining those which can't be,
+        // so a corrupt entry doesn't make the FilesContainer unreadable
+        let mut files_maps = Vec::with_capacity(entries.len());
+        let mut quarantined = 0;
+        for (hash, files_map_xorurl) in entries.iter() {
+            if is_tombstone(files_map_xorurl) {
+                continue;
+            }
+            let decoded = match self.fetch_public_data(files_map_xorurl, None).await {
+                Ok(serialised_files_map) => self.decode_files_map(&serialised_files_map),
+                Err(err) => Err(err),
+            };
+            match decoded {
+                Ok(files_map) => files_maps.push((VersionHash::from(hash), files_map)),
+                Err(err) => {
+                    self.quarantine_entry(safe_url, *hash, QuarantineKind::FilesMap, err)?;
+                    quarantined += 1;
+                }
+            }
+        }
 
-        Ok((version, files_map))
+        // take the 1st entry (TODO Multiple entries)
+        if files_maps.len() > 1 {
+            return Err(Error::NotImplementedError("Multiple file container entries not managed, this happends when 2 clients write concurrently to a file container".to_string()));
+        }
+        match files_maps.pop() {
+            Some((version, files_map)) => {
+                debug!("Files map retrieved.... v{:?}", &version);
+                Ok((version, files_map))
+            }
+            None if quarantined > 0 => Err(Error::ContentError(format!(
+                "None of the {} FilesMap entries found at \"{}\" could be decoded, they were quarantined",
+                quarantined, safe_url
+            ))),
+            None => {
+                warn!("FilesContainer found at \"{:?}\" was empty", safe_url);
+                Ok((VersionHash::default(), FilesMap::default()))
+            }
+        }
     }
 
     /// # Sync up local folder with the content on a FilesContainer.
@@ -225,6 +268,39 @@ impl Safe {
         delete: bool,
         update_nrs: bool,
         dry_run: bool,
+    ) -> Result<(VersionHash, ProcessedFiles, FilesMap)> {
+        // dry runs don't update the FilesContainer, thus they don't take the lock
+        let lease = if dry_run {
+            None
+        } else {
+            self.publish_lock_acquire(url).await?
+        };
+        let result = self
+            .files_container_sync_unlocked(
+                location,
+                url,
+                recursive,
+                follow_links,
+                delete,
+                update_nrs,
+                dry_run,
+            )
+            .await;
+        self.publish_lock_release(lease).await;
+        result
+    }
+
+    // Body of `files_container_sync`, run while holding the publish lock if one is set
+    #[allow(clippy::too_many_arguments)]
+    async fn files_container_sync_unlocked(
+        &mut self,
+        location: &str,
+        url: &str,
+        recursive: bool,
+        follow_links: bool,
+        delete: bool,
+        update_nrs: bool,
+        dry_run: bool,
     ) -> Result<(VersionHash, ProcessedFiles, FilesMap)> {
         if delete && !recursive {
             return Err(Error::InvalidInput(
@@ -291,6 +367,14 @@ impl Safe {
             .await?
         };
 
+        if !dry_run {
+            self.emit_event(SafeEvent::SyncFinished {
+                url: url.to_string(),
+                version: version.to_string(),
+                timestamp: gen_timestamp_secs(),
+            });
+        }
+
         Ok((version, processed_files, new_files_map))
     }
 
@@ -320,6 +404,36 @@ impl Safe {
         update_nrs: bool,
         follow_links: bool,
         dry_run: bool,
+    ) -> Result<(VersionHash, ProcessedFiles, FilesMap)> {
+        // dry runs don't update the FilesContainer, thus they don't take the lock
+        let lease = if dry_run {
+            None
+        } else {
+            self.publish_lock_acquire(url).await?
+        };
+        let result = self
+            .files_container_add_unlocked(
+                source_file,
+                url,
+                force,
+                update_nrs,
+                follow_links,
+                dry_run,
+            )
+            .await;
+        self.publish_lock_release(lease).await;
+        result
+    }
+
+    // Body of `files_container_add`, run while holding the publish lock if one is set
+    async fn files_container_add_unlocked(
+        &mut self,
+        source_file: &str,
+        url: &str,
+        force: bool,
+        update_nrs: bool,
+        follow_links: bool,
+        dry_run: bool,
     ) -> Result<(VersionHash, ProcessedFiles, FilesMap)> {
         let (safe_url, current_version, current_files_map) =
             validate_files_add_params(self, source_file, url, update_nrs).await?;
@@ -391,12 +505,34 @@ impl Safe {
         force: bool,
         update_nrs: bool,
         dry_run: bool,
+    ) -> Result<(VersionHash, ProcessedFiles, FilesMap)> {
+        // dry runs don't update the FilesContainer, thus they don't take the lock
+        let lease = if dry_run {
+            None
+        } else {
+            self.publish_lock_acquire(url).await?
+        };
+        let result = self
+            .files_container_add_from_raw_unlocked(data, url, force, update_nrs, dry_run)
+            .await;
+        self.publish_lock_release(lease).await;
+        result
+    }
+
+    // Body of `files_container_add_from_raw`, run while holding the publish lock if one is set
+    async fn files_container_add_from_raw_unlocked(
+        &mut self,
+        data: Bytes,
+        url: &str,
+        force: bool,
+        update_nrs: bool,
+        dry_run: bool,
     ) -> Result<(VersionHash, ProcessedFiles, FilesMap)> {
         let (safe_url, current_version, current_files_map) =
             validate_files_add_params(self, "", url, update_nrs).await?;
 
         let dest_path = safe_url.path();
-        let new_file_xorurl = self.store_public_bytes(data, None, false).await?;
+        let new_file_xorurl = self.store_bytes(data, None, false).await?;
 
         // Let's act according to if it's a local file path or a safe:// location
         let (processed_files, new_files_map, success_count) =
@@ -443,6 +579,27 @@ impl Safe {
         recursive: bool,
         update_nrs: bool,
         dry_run: bool,
+    ) -> Result<(VersionHash, ProcessedFiles, FilesMap)> {
+        // dry runs don't update the FilesContainer, thus they don't take the lock
+        let lease = if dry_run {
+            None
+        } else {
+            self.publish_lock_acquire(url).await?
+        };
+        let result = self
+            .files_container_remove_path_unlocked(url, recursive, update_nrs, dry_run)
+            .await;
+        self.publish_lock_release(lease).await;
+        result
+    }
+
+    // Body of `files_container_remove_path`, run while holding the publish lock if one is set
+    async fn files_container_remove_path_unlocked(
+        &mut self,
+        url: &str,
+        recursive: bool,
+        update_nrs: bool,
+        dry_run: bool,
     ) -> Result<(VersionHash, ProcessedFiles, FilesMap)> {
         let safe_url = Safe::parse_url(url)?;
         if safe_url.content_version().is_some() {
@@ -495,6 +652,198 @@ impl Safe {
         Ok((version, processed_files, new_files_map))
     }
 
+    /// # Remove many paths from a FilesContainer at once
+    ///
+    /// All the paths are removed from the FilesContainer the URL targets in a single
+    /// new version of it. If any of the paths cannot be removed nothing is removed.
+    /// If `dry_run` is set, nothing is written and the current version is returned
+    /// along with the FilesMap the removals would result in.
+    pub async fn files_container_remove_batch(
+        &mut self,
+        url: &str,
+        paths: &[&str],
+        recursive: bool,
+        update_nrs: bool,
+        dry_run: bool,
+    ) -> Result<(VersionHash, ProcessedFiles, FilesMap)> {
+        // dry runs don't update the FilesContainer, thus they don't take the lock
+        let lease = if dry_run {
+            None
+        } else {
+            self.publish_lock_acquire(url).await?
+        };
+        let result = self
+            .files_container_remove_batch_unlocked(url, paths, recursive, update_nrs, dry_run)
+            .await;
+        self.publish_lock_release(lease).await;
+        result
+    }
+
+    // Body of `files_container_remove_batch`, run while holding the publish lock if one is set
+    async fn files_container_remove_batch_unlocked(
+        &mut self,
+        url: &str,
+        paths: &[&str],
+        recursive: bool,
+        update_nrs: bool,
+        dry_run: bool,
+    ) -> Result<(VersionHash, ProcessedFiles, FilesMap)> {
+        let safe_url = Safe::parse_url(url)?;
+        if safe_url.content_version().is_some() {
+            return Err(Error::InvalidInput(format!(
+                "The target URL cannot contain a version: {}",
+                url
+            )));
+        };
+
+        // If NRS name shall be updated then the URL has to be an NRS-URL
+        if update_nrs && safe_url.content_type() != ContentType::NrsMapContainer {
+            return Err(Error::InvalidInput(
+                "'update-nrs' is not allowed since the URL provided is not an NRS URL".to_string(),
+            ));
+        }
+
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+
+        let (current_version, mut files_map): (VersionHash, FilesMap) =
+            self.fetch_files_container(&safe_url).await?;
+
+        let mut processed_files = ProcessedFiles::default();
+        let mut total_count = 0;
+        for path in paths.iter() {
+            if path.is_empty() {
+                return Err(Error::InvalidInput(
+                    "The paths to remove cannot be empty".to_string(),
+                ));
+            }
+            let (path_processed_files, new_files_map, success_count) =
+                files_map_remove_path(path, files_map, recursive)?;
+            processed_files.extend(path_processed_files);
+            files_map = new_files_map;
+            total_count += success_count;
+        }
+
+        let version = if dry_run || total_count == 0 {
+            current_version
+        } else {
+            self.append_version_to_files_container(
+                HashSet::from_iter([current_version]),
+                &files_map,
+                url,
+                safe_url,
+                false,
+                update_nrs,
+            )
+            .await?
+        };
+
+        Ok((version, processed_files, files_map))
+    }
+
+    /// # Fork a FilesContainer
+    ///
+    /// Creates a new FilesContainer, owned by this instance, with the files of the one
+    /// the URL targets, at the version it targets or its latest version otherwise. The new
+    /// FilesContainer references the same published files, thus nothing is re-uploaded,
+    /// and from then on it can diverge independently from the one it was forked from.
+    /// Returns the XOR-URL of the first version of the new FilesContainer.
+    pub async fn files_container_fork(&mut self, url: &str) -> Result<(XorUrl, FilesMap)> {
+        debug!("Forking FilesContainer from: {:?}", url);
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let (_, files_map) = self.fetch_files_container(&safe_url).await?;
+
+        // The FilesMap is content addressed, thus storing it again doesn't upload any new data
+        let scope = self.default_scope();
+        let files_map_xorurl = self.store_files_map(&files_map, scope).await?;
+        let xorname = self
+            .safe_client
+            .store_register(
+                None,
+                FILES_CONTAINER_TYPE_TAG,
+                None,
+                matches!(scope, Scope::Private),
+            )
+            .await?;
+        let xor_url = Url::encode_register(
+            xorname,
+            FILES_CONTAINER_TYPE_TAG,
+            scope,
+            ContentType::FilesContainer,
+            self.xorurl_base,
+        )?;
+
+        let entry = Url::from_xorurl(&files_map_xorurl)?;
+        let entry_hash = self
+            .write_to_register(&xor_url, entry, Default::default())
+            .await?;
+
+        let mut fork_url = Url::from_xorurl(&xor_url)?;
+        fork_url.set_content_version(Some(VersionHash::from(&entry_hash)));
+        self.local_index
+            .insert(IndexedKind::FilesContainer, url, &xor_url);
+
+        Ok((fork_url.to_string(), files_map))
+    }
+
+    /// # Move a path within a FilesContainer
+    ///
+    /// The file found at `from_path` in the FilesContainer the URL targets is moved to
+    /// `to_path`, keeping all its metadata, in a single new version of the FilesContainer.
+    /// If `recursive` is set, all the files within the `from_path` folder are moved under
+    /// the `to_path` folder. Nothing is overwritten, the move fails if any of the
+    /// destination paths already exists. If `dry_run` is set, nothing is written and the
+    /// current version is returned along with the FilesMap the move would result in.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn files_container_move(
+        &mut self,
+        url: &str,
+        from_path: &str,
+        to_path: &str,
+        recursive: bool,
+        update_nrs: bool,
+        dry_run: bool,
+    ) -> Result<(VersionHash, ProcessedFiles, FilesMap)> {
+        let safe_url = Safe::parse_url(url)?;
+        if safe_url.content_version().is_some() {
+            return Err(Error::InvalidInput(format!(
+                "The target URL cannot contain a version: {}",
+                url
+            )));
+        };
+
+        // If NRS name shall be updated then the URL has to be an NRS-URL
+        if update_nrs && safe_url.content_type() != ContentType::NrsMapContainer {
+            return Err(Error::InvalidInput(
+                "'update-nrs' is not allowed since the URL provided is not an NRS URL".to_string(),
+            ));
+        }
+
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+
+        let (current_version, files_map): (VersionHash, FilesMap) =
+            self.fetch_files_container(&safe_url).await?;
+        let (processed_files, new_files_map, success_count) =
+            files_map_move_path(from_path, to_path, files_map, recursive)?;
+
+        let version = if dry_run || success_count == 0 {
+            current_version
+        } else {
+            self.append_version_to_files_container(
+                HashSet::from_iter([current_version]),
+                &new_files_map,
+                url,
+                safe_url,
+                false,
+                update_nrs,
+            )
+            .await?
+        };
+
+        Ok((version, processed_files, new_files_map))
+    }
+
     // Private helper function to append new version of the FilesMap to the Files Container
     // It flagged with `update_nrs`, it will also update the link in the corresponding NRS Map Container
     #[allow(clippy::too_many_arguments)]
@@ -514,7 +863,8 @@ impl Safe {
         }
         // The FilesContainer is updated by adding an entry containing the link to
         // the Blob with the serialised new version of the FilesMap.
-        let files_map_xorurl = self.store_files_map(new_files_map).await?;
+        let (_, _, scope) = safe_url.register_parts()?;
+        let files_map_xorurl = self.store_files_map(new_files_map, scope).await?;
 
         // append entry to register
         let entry = Url::from_xorurl(&files_map_xorurl)?;
@@ -585,6 +935,34 @@ impl Safe {
         )?)
     }
 
+    /// # Store a Blob with the default scope
+    ///
+    /// The content is stored as a Private Blob if this instance is set to store new
+    /// content as private by default, see `set_private_by_default`, or as a Public Blob
+    /// otherwise. Dry runs return a XOR-URL with the default XorName as with
+    /// `store_public_bytes`.
+    pub async fn store_bytes(
+        &self,
+        bytes: Bytes,
+        media_type: Option<&str>,
+        dry_run: bool,
+    ) -> Result<XorUrl> {
+        if !self.private_by_default {
+            return self.store_public_bytes(bytes, media_type, dry_run).await;
+        }
+
+        if dry_run {
+            let xorurl = self.store_public_bytes(bytes, media_type, dry_run).await?;
+            let safe_url = Url::from_xorurl(&xorurl)?;
+            return Ok(Url::encode_bytes(
+                BytesAddress::Private(safe_url.xorname()),
+                safe_url.content_type(),
+                self.xorurl_base,
+            )?);
+        }
+        self.store_private_bytes(bytes, media_type).await
+    }
+
     /// # Get a Public Blob
     /// Get blob from the network.
     ///
@@ -607,12 +985,13 @@ impl Safe {
         self.fetch_public_data(&safe_url, range).await
     }
 
-    /// Fetch an Blob from a Url without performing any type of URL resolution
+    /// Fetch an Blob from a Url without performing any type of URL resolution.
+    /// Private Blobs can also be fetched, as long as they are owned by this instance.
     pub(crate) async fn fetch_public_data(&self, safe_url: &Url, range: Range) -> Result<Bytes> {
         let data = match safe_url.data_type() {
             DataType::Bytes => {
                 self.safe_client
-                    .get_bytes(BytesAddress::Public(safe_url.xorname()), range)
+                    .get_bytes(safe_url.bytes_address()?, range)
                     .await?
             }
             other => {
@@ -622,20 +1001,23 @@ impl Safe {
         Ok(data)
     }
 
-    // Private helper to serialise a FilesMap and store it in a Public Blob
-    async fn store_files_map(&mut self, files_map: &FilesMap) -> Result<String> {
+    // Private helper to serialise a FilesMap and store it in a Blob with
+    // the given scope, which should be the scope of its FilesContainer
+    pub(crate) async fn store_files_map(
+        &self,
+        files_map: &FilesMap,
+        scope: Scope,
+    ) -> Result<String> {
         // The FilesMapContainer is a Register where each NRS Map version is
         // an entry containing the XOR-URL of the Blob that contains the serialised NrsMap.
-        // TODO: use RDF format
-        let serialised_files_map = serde_json::to_string(&files_map).map_err(|err| {
-            Error::Serialisation(format!(
-                "Couldn't serialise the FilesMap generated: {:?}",
-                err
-            ))
-        })?;
-        let files_map_xorurl = self
-            .store_public_bytes(Bytes::from(serialised_files_map), None, false)
-            .await?;
+        let serialised_files_map = Bytes::from(self.encode_files_map(files_map, scope)?);
+        let files_map_xorurl = match scope {
+            Scope::Public => {
+                self.store_public_bytes(serialised_files_map, None, false)
+                    .await?
+            }
+            Scope::Private => self.store_private_bytes(serialised_files_map, None).await?,
+        };
         Ok(files_map_xorurl)
     }
 }
@@ -1031,6 +1413,79 @@ async fn files_map_add_link(
 }
 
 // Remove a path from the FilesMap provided
+// Helper to move a path, or all the paths within a folder if 'recursive' is set, to a new
+// path in a FilesMap, keeping the FileItems untouched
+fn files_map_move_path(
+    from_path: &str,
+    to_path: &str,
+    mut files_map: FilesMap,
+    recursive: bool,
+) -> Result<(ProcessedFiles, FilesMap, u64)> {
+    let from_path = from_path.trim_end_matches('/');
+    let to_path = to_path.trim_end_matches('/');
+    if from_path.is_empty() || to_path.is_empty() {
+        return Err(Error::InvalidInput(
+            "The paths to move from and to cannot be empty".to_string(),
+        ));
+    }
+
+    let from_folder = format!("{}/", from_path);
+    if to_path == from_path || to_path.starts_with(&from_folder) {
+        return Err(Error::InvalidInput(format!(
+            "Cannot move \"{}\" to \"{}\" as it's within itself",
+            from_path, to_path
+        )));
+    }
+
+    // Find all the paths to be moved along with their new path
+    let moves: Vec<(String, String)> = files_map
+        .keys()
+        .filter_map(|path| {
+            if path == from_path {
+                Some((path.clone(), to_path.to_string()))
+            } else if recursive && path.starts_with(&from_folder) {
+                Some((
+                    path.clone(),
+                    format!("{}/{}", to_path, &path[from_folder.len()..]),
+                ))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    if moves.is_empty() {
+        return Err(Error::ContentError(format!(
+            "No content found matching the \"{}\" path on the target FilesContainer. If you are trying to move a folder rather than a file, you need to pass the 'recursive' flag",
+            from_path
+        )));
+    }
+    if let Some((_, new_path)) = moves
+        .iter()
+        .find(|(_, new_path)| files_map.contains_key(new_path))
+    {
+        return Err(Error::InvalidInput(format!(
+            "Cannot move to \"{}\" since a file already exists at such path",
+            new_path
+        )));
+    }
+
+    let mut processed_files = ProcessedFiles::default();
+    for (path, new_path) in moves.iter() {
+        if let Some(file_item) = files_map.remove(path) {
+            let link = file_item.get(PREDICATE_LINK).cloned().unwrap_or_default();
+            processed_files.insert(
+                path.clone(),
+                (CONTENT_DELETED_SIGN.to_string(), link.clone()),
+            );
+            processed_files.insert(new_path.clone(), (CONTENT_ADDED_SIGN.to_string(), link));
+            files_map.insert(new_path.clone(), file_item);
+        }
+    }
+
+    Ok((processed_files, files_map, moves.len() as u64))
+}
+
 fn files_map_remove_path(
     dest_path: &str,
     mut files_map: FilesMap,
@@ -1092,7 +1547,7 @@ fn files_map_remove_path(
     Ok((processed_files, new_files_map, success_count))
 }
 
-// Upload a files to the Network as a Public Blob
+// Upload a files to the Network as a Blob, with the default scope
 async fn upload_file_to_net(safe: &mut Safe, path: &Path, dry_run: bool) -> Result<XorUrl> {
     let data = fs::read(path).map_err(|err| {
         Error::InvalidInput(format!("Failed to read file from local location: {}", err))
@@ -1101,14 +1556,14 @@ async fn upload_file_to_net(safe: &mut Safe, path: &Path, dry_run: bool) -> Resu
 
     let mime_type = mime_guess::from_path(&path);
     match safe
-        .store_public_bytes(data.to_owned(), mime_type.first_raw(), dry_run)
+        .store_bytes(data.to_owned(), mime_type.first_raw(), dry_run)
         .await
     {
         Ok(xorurl) => Ok(xorurl),
         Err(err) => {
             // Let's then upload it and set media-type to be simply raw content
             if let Error::InvalidMediaType(_) = err {
-                safe.store_public_bytes(data, None, dry_run).await
+                safe.store_bytes(data, None, dry_run).await
             } else {
                 Err(err)
             }
@@ -2812,4 +3267,141 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_files_container_remove_batch() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let (xorurl, _, files_map) =
+            retry_loop!(safe.files_container_create(Some("./testdata/"), None, true, true, false));
+        let _ = retry_loop!(safe.fetch(&xorurl, None));
+        let mut safe_url = Url::from_url(&xorurl)?;
+        safe_url.set_content_version(None);
+        let container_url = safe_url.to_string();
+        let (version0, _) = retry_loop!(safe.files_container_get(&container_url));
+
+        let paths = ["/test.md", "/subfolder"];
+
+        // a dry run doesn't create a new version
+        let (version, processed_files, new_files_map) = safe
+            .files_container_remove_batch(&container_url, &paths, true, false, true)
+            .await?;
+        assert_eq!(version, version0);
+        assert_eq!(processed_files.len(), SUBFOLDER_PUT_FILEITEM_COUNT + 1);
+        assert_eq!(
+            new_files_map.len(),
+            TESTDATA_PUT_FILEITEM_COUNT - SUBFOLDER_PUT_FILEITEM_COUNT - 1
+        );
+
+        let (version1, processed_files, _) = safe
+            .files_container_remove_batch(&container_url, &paths, true, false, false)
+            .await?;
+        assert_ne!(version1, version0);
+        assert_eq!(processed_files["/test.md"].0, CONTENT_DELETED_SIGN);
+        assert_eq!(
+            processed_files["/test.md"].1,
+            files_map["/test.md"][PREDICATE_LINK]
+        );
+
+        let (_, current_files_map) = retry_loop_for_pattern!(safe.files_container_get(&container_url), Ok((v, _)) if *v == version1)?;
+        assert_eq!(current_files_map, new_files_map);
+
+        // if any of the paths is not found nothing is removed
+        assert!(safe
+            .files_container_remove_batch(
+                &container_url,
+                &["/another.md", "/missing.md"],
+                false,
+                false,
+                false
+            )
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_files_container_move() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let (xorurl, _, files_map) =
+            retry_loop!(safe.files_container_create(Some("./testdata/"), None, true, true, false));
+        let _ = retry_loop!(safe.fetch(&xorurl, None));
+        let mut safe_url = Url::from_url(&xorurl)?;
+        safe_url.set_content_version(None);
+        let container_url = safe_url.to_string();
+        let (version0, _) = retry_loop!(safe.files_container_get(&container_url));
+
+        let (version1, processed_files, new_files_map) = safe
+            .files_container_move(&container_url, "/test.md", "/moved.md", false, false, false)
+            .await?;
+        assert_ne!(version1, version0);
+        assert_eq!(processed_files["/test.md"].0, CONTENT_DELETED_SIGN);
+        assert_eq!(processed_files["/moved.md"].0, CONTENT_ADDED_SIGN);
+        assert_eq!(new_files_map["/moved.md"], files_map["/test.md"]);
+        assert!(!new_files_map.contains_key("/test.md"));
+
+        let (_, current_files_map) = retry_loop_for_pattern!(safe.files_container_get(&container_url), Ok((v, _)) if *v == version1)?;
+        assert_eq!(current_files_map, new_files_map);
+
+        // a folder is moved with all its files, as a dry run
+        let (version, processed_files, moved_files_map) = safe
+            .files_container_move(&container_url, "/subfolder", "/renamed", true, false, true)
+            .await?;
+        assert_eq!(version, version1);
+        assert_eq!(
+            moved_files_map["/renamed/sub2.md"],
+            files_map["/subfolder/sub2.md"]
+        );
+        assert!(!moved_files_map
+            .keys()
+            .any(|path| path.starts_with("/subfolder/")));
+        assert_eq!(processed_files["/renamed/sub2.md"].0, CONTENT_ADDED_SIGN);
+
+        // existing files are not overwritten
+        assert!(safe
+            .files_container_move(
+                &container_url,
+                "/moved.md",
+                "/another.md",
+                false,
+                false,
+                false
+            )
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_files_container_fork() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let (xorurl, _, files_map) =
+            retry_loop!(safe.files_container_create(Some("./testdata/"), None, true, true, false));
+        let _ = retry_loop!(safe.fetch(&xorurl, None));
+
+        let (fork_xorurl, fork_files_map) = safe.files_container_fork(&xorurl).await?;
+        assert_ne!(fork_xorurl, xorurl);
+        assert_eq!(fork_files_map, files_map);
+
+        // the fork diverges independently from the original
+        let mut fork_url = Url::from_url(&fork_xorurl)?;
+        fork_url.set_content_version(None);
+        let fork_container_url = fork_url.to_string();
+        let _ = retry_loop!(safe.files_container_get(&fork_container_url));
+        let (_, _, new_files_map) = safe
+            .files_container_remove_path(
+                &format!("{}/test.md", fork_container_url),
+                false,
+                false,
+                false,
+            )
+            .await?;
+        assert!(!new_files_map.contains_key("/test.md"));
+
+        let (_, original_files_map) = retry_loop!(safe.files_container_get(&xorurl));
+        assert_eq!(original_files_map, files_map);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,264 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{FileItem, FilesMap};
+use crate::{app::consts::*, Error, Result, Safe, VersionHash};
+use log::info;
+use std::{
+    collections::{BTreeMap, HashSet},
+    iter::FromIterator,
+};
+
+/// License and provenance of a file, stored along with the rest of its metadata
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Provenance {
+    /// SPDX license identifier or expression, e.g. `MIT OR Apache-2.0`
+    pub license: Option<String>,
+    /// URL of the content the file was taken from
+    pub source: Option<String>,
+    /// URL of the author's identity, e.g. their NRS name or public profile
+    pub author: Option<String>,
+    /// Name and version of the tool used to create the file
+    pub creation_tool: Option<String>,
+}
+
+impl Provenance {
+    /// Read the provenance from a file's metadata
+    pub fn from_file_item(file_item: &FileItem) -> Self {
+        Self {
+            license: file_item.get(PREDICATE_LICENSE).cloned(),
+            source: file_item.get(PREDICATE_SOURCE).cloned(),
+            author: file_item.get(PREDICATE_AUTHOR).cloned(),
+            creation_tool: file_item.get(PREDICATE_CREATION_TOOL).cloned(),
+        }
+    }
+
+    /// Check if none of the fields are set
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // Set on the file's metadata the fields which are set, keeping the rest unchanged
+    pub(crate) fn apply_to(&self, file_item: &mut FileItem) {
+        let fields = [
+            (PREDICATE_LICENSE, &self.license),
+            (PREDICATE_SOURCE, &self.source),
+            (PREDICATE_AUTHOR, &self.author),
+            (PREDICATE_CREATION_TOOL, &self.creation_tool),
+        ];
+        for (predicate, value) in fields.iter() {
+            if let Some(value) = value {
+                let _ = file_item.insert(predicate.to_string(), value.clone());
+            }
+        }
+    }
+}
+
+impl Safe {
+    /// # Set the license and provenance of files in a FilesContainer
+    ///
+    /// The fields set on the provenance are stored in the metadata of the file the URL's
+    /// path targets, or of all the files in the folder if the path ends with '/' or is empty,
+    /// keeping any other field unchanged. A new version of the FilesContainer is created.
+    pub async fn files_container_set_provenance(
+        &mut self,
+        url: &str,
+        provenance: &Provenance,
+    ) -> Result<(VersionHash, FilesMap)> {
+        if let Some(license) = &provenance.license {
+            validate_spdx_expression(license)?;
+        }
+
+        let safe_url = Safe::parse_url(url)?;
+        if safe_url.content_version().is_some() {
+            return Err(Error::InvalidInput(format!(
+                "The target URL cannot contain a version: {}",
+                url
+            )));
+        };
+
+        info!("Setting provenance on {}", url);
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let (current_version, mut files_map) = self.fetch_files_container(&safe_url).await?;
+
+        let dest_path = safe_url.path().to_string();
+        let mut updated = 0;
+        for (file_path, file_item) in files_map.iter_mut() {
+            if path_matches(&dest_path, file_path) {
+                provenance.apply_to(file_item);
+                updated += 1;
+            }
+        }
+        if updated == 0 {
+            return Err(Error::ContentNotFound(format!(
+                "No content found matching the \"{}\" path on the target FilesContainer",
+                dest_path
+            )));
+        }
+
+        let version = self
+            .append_version_to_files_container(
+                HashSet::from_iter([current_version]),
+                &files_map,
+                url,
+                safe_url,
+                false,
+                false,
+            )
+            .await?;
+
+        Ok((version, files_map))
+    }
+
+    /// Get the license and provenance of the files in a FilesContainer the URL's path
+    /// targets, only including the files which have any of the fields set
+    pub async fn files_container_provenance(
+        &mut self,
+        url: &str,
+    ) -> Result<BTreeMap<String, Provenance>> {
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let (_, files_map) = self.fetch_files_container(&safe_url).await?;
+
+        let dest_path = safe_url.path();
+        Ok(files_map
+            .iter()
+            .filter(|(file_path, _)| path_matches(dest_path, file_path))
+            .map(|(file_path, file_item)| {
+                (file_path.clone(), Provenance::from_file_item(file_item))
+            })
+            .filter(|(_, provenance)| !provenance.is_empty())
+            .collect())
+    }
+}
+
+// A path targets a single file, or all files in a folder if it ends with '/' or is empty
+fn path_matches(dest_path: &str, file_path: &str) -> bool {
+    if dest_path.is_empty() || dest_path.ends_with('/') {
+        file_path.starts_with(dest_path)
+    } else {
+        file_path == dest_path
+    }
+}
+
+// Check a license is a valid SPDX identifier or expression, e.g. `MIT`,
+// `GPL-2.0+`, `LicenseRef-Custom`, or `(MIT OR Apache-2.0) AND BSD-3-Clause`
+pub(crate) fn validate_spdx_expression(expression: &str) -> Result<()> {
+    let invalid = |reason: &str| {
+        Err(Error::InvalidInput(format!(
+            "Invalid SPDX license expression '{}': {}",
+            expression, reason
+        )))
+    };
+
+    let spaced = expression.replace('(', " ( ").replace(')', " ) ");
+    let mut depth = 0;
+    let mut expect_license = true;
+    for token in spaced.split_whitespace() {
+        match token {
+            "(" if expect_license => depth += 1,
+            ")" if !expect_license && depth > 0 => depth -= 1,
+            "AND" | "OR" | "WITH" if !expect_license => expect_license = true,
+            id if expect_license => {
+                let id = id.strip_suffix('+').unwrap_or(id);
+                if id.is_empty()
+                    || !id
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                {
+                    return invalid(&format!("'{}' is not a valid license identifier", token));
+                }
+                expect_license = false;
+            }
+            other => return invalid(&format!("unexpected '{}'", other)),
+        }
+    }
+
+    if expect_license || depth != 0 {
+        return invalid("incomplete expression");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern, Url};
+    use anyhow::Result;
+
+    #[test]
+    fn test_validate_spdx_expression() {
+        assert!(validate_spdx_expression("MIT").is_ok());
+        assert!(validate_spdx_expression("GPL-2.0+").is_ok());
+        assert!(validate_spdx_expression("(MIT OR Apache-2.0) AND BSD-3-Clause").is_ok());
+        assert!(validate_spdx_expression("GPL-2.0 WITH Classpath-exception-2.0").is_ok());
+
+        assert!(validate_spdx_expression("").is_err());
+        assert!(validate_spdx_expression("MIT OR").is_err());
+        assert!(validate_spdx_expression("(MIT").is_err());
+        assert!(validate_spdx_expression("MIT Apache-2.0").is_err());
+        assert!(validate_spdx_expression("My License!").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_files_container_set_provenance() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let (xorurl, _, _) = safe
+            .files_container_create(Some("./testdata/"), None, true, false, false)
+            .await?;
+        let _ = retry_loop!(safe.fetch(&xorurl, None));
+        let mut safe_url = Url::from_url(&xorurl)?;
+        safe_url.set_content_version(None);
+        let xorurl = safe_url.to_string();
+
+        let provenance = Provenance {
+            license: Some("MIT OR Apache-2.0".to_string()),
+            author: Some("safe://alice".to_string()),
+            ..Default::default()
+        };
+        let (_, files_map) = safe
+            .files_container_set_provenance(&xorurl, &provenance)
+            .await?;
+        assert!(files_map
+            .values()
+            .all(|file_item| Provenance::from_file_item(file_item) == provenance));
+
+        let tool = Provenance {
+            creation_tool: Some("editor 1.0".to_string()),
+            ..Default::default()
+        };
+        let _ = safe
+            .files_container_set_provenance(&format!("{}/test.md", xorurl), &tool)
+            .await?;
+
+        let provenances = retry_loop_for_pattern!(
+            safe.files_container_provenance(&format!("{}/test.md", xorurl)),
+            Ok(p) if p.values().any(|p| p.creation_tool.is_some())
+        )?;
+        assert_eq!(provenances.len(), 1);
+        assert_eq!(
+            provenances["/test.md"],
+            Provenance {
+                creation_tool: Some("editor 1.0".to_string()),
+                ..provenance.clone()
+            }
+        );
+
+        let invalid = Provenance {
+            license: Some("MIT OR".to_string()),
+            ..Default::default()
+        };
+        assert!(safe
+            .files_container_set_provenance(&xorurl, &invalid)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
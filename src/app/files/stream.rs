@@ -0,0 +1,347 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{Error, Result, Safe, Url, XorUrl};
+use bytes::Bytes;
+use futures::{
+    io::{AsyncRead, AsyncReadExt},
+    stream::{self, Stream, StreamExt},
+};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+// Size of the segments the streamed content is split into, each stored as a Blob.
+// At most one segment is buffered in memory while uploading.
+const STREAM_SEGMENT_SIZE: usize = 4 * 1024 * 1024;
+
+// Size of the buffer used to read from readers
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+// Default number of segments fetched in parallel
+const DEFAULT_FETCH_PARALLELISM: usize = 4;
+
+// Streamed content is stored as a manifest Blob listing the Blobs of each segment
+#[derive(Debug, Serialize, Deserialize)]
+struct StreamManifest {
+    media_type: Option<String>,
+    size: u64,
+    segments: Vec<XorUrl>,
+}
+
+/// Order in which the segments of streamed content are fetched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FetchOrder {
+    /// Segments are returned in order, e.g. for media players to start playing as soon
+    /// as the first segments arrive, while the following ones are fetched in parallel
+    Sequential,
+    /// Segments are returned as soon as each of them arrives, in any order,
+    /// for bulk downloads to get the maximum throughput
+    Any,
+}
+
+/// Options to fetch streamed content with `files_read_stream_with`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FetchOptions {
+    /// Maximum number of segments fetched in parallel
+    pub parallelism: usize,
+    pub order: FetchOrder,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            parallelism: DEFAULT_FETCH_PARALLELISM,
+            order: FetchOrder::Sequential,
+        }
+    }
+}
+
+/// Information about content stored from a stream or reader
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamedFileInfo {
+    /// The media type hint the content was stored with
+    pub media_type: Option<String>,
+    /// Total size of the content in bytes
+    pub size: u64,
+}
+
+// Buffers the data received until a segment is complete, uploading it before reading
+// more data from the source, so slow uploads apply backpressure on the source.
+struct SegmentUploader<'a> {
+    safe: &'a Safe,
+    buffer: Vec<u8>,
+    size: u64,
+    segments: Vec<XorUrl>,
+}
+
+impl<'a> SegmentUploader<'a> {
+    fn new(safe: &'a Safe) -> Self {
+        Self {
+            safe,
+            buffer: Vec::with_capacity(STREAM_SEGMENT_SIZE),
+            size: 0,
+            segments: vec![],
+        }
+    }
+
+    async fn push(&mut self, data: &[u8]) -> Result<()> {
+        self.size += data.len() as u64;
+        let mut data = data;
+        while !data.is_empty() {
+            let available = STREAM_SEGMENT_SIZE - self.buffer.len();
+            let (head, tail) = data.split_at(std::cmp::min(available, data.len()));
+            self.buffer.extend_from_slice(head);
+            data = tail;
+
+            if self.buffer.len() == STREAM_SEGMENT_SIZE {
+                self.upload_segment().await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn upload_segment(&mut self) -> Result<()> {
+        let segment = std::mem::replace(&mut self.buffer, Vec::with_capacity(STREAM_SEGMENT_SIZE));
+        debug!(
+            "Uploading segment #{} of {} bytes",
+            self.segments.len(),
+            segment.len()
+        );
+        let xorurl = self
+            .safe
+            .store_bytes(Bytes::from(segment), None, false)
+            .await?;
+        self.segments.push(xorurl);
+        Ok(())
+    }
+
+    async fn finish(mut self, media_type: Option<&str>) -> Result<XorUrl> {
+        if !self.buffer.is_empty() {
+            self.upload_segment().await?;
+        }
+
+        let manifest = StreamManifest {
+            media_type: media_type.map(|media_type| media_type.to_string()),
+            size: self.size,
+            segments: self.segments,
+        };
+        let serialised_manifest = rmp_serde::to_vec_named(&manifest).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise stream manifest: {:?}", err))
+        })?;
+        self.safe
+            .store_bytes(Bytes::from(serialised_manifest), None, false)
+            .await
+    }
+}
+
+impl Safe {
+    /// # Store content from a stream
+    ///
+    /// The content is uploaded as it's received, without buffering all of it, in segments
+    /// which are stored as Blobs with the default scope, see `set_private_by_default`, as is
+    /// the manifest which lists them, returning its XOR-URL. Use `files_read_stream` to read it back. The media type is stored as a hint.
+    pub async fn files_store_stream<S>(
+        &self,
+        mut stream: S,
+        media_type: Option<&str>,
+    ) -> Result<XorUrl>
+    where
+        S: Stream<Item = Bytes> + Unpin,
+    {
+        let mut uploader = SegmentUploader::new(self);
+        while let Some(data) = stream.next().await {
+            uploader.push(&data).await?;
+        }
+        uploader.finish(media_type).await
+    }
+
+    /// # Store content from a reader
+    ///
+    /// Same as `files_store_stream` but reading the content from an `AsyncRead`.
+    pub async fn files_store_reader<R>(
+        &self,
+        mut reader: R,
+        media_type: Option<&str>,
+    ) -> Result<XorUrl>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut uploader = SegmentUploader::new(self);
+        let mut buffer = vec![0; READ_BUFFER_SIZE];
+        loop {
+            let read = reader.read(&mut buffer).await.map_err(|err| {
+                Error::ContentError(format!("Failed to read the content to store: {}", err))
+            })?;
+            if read == 0 {
+                break;
+            }
+            uploader.push(&buffer[..read]).await?;
+        }
+        uploader.finish(media_type).await
+    }
+
+    /// # Read content stored from a stream or reader
+    ///
+    /// Returns the information about the content, and a stream which fetches
+    /// each of its segments from the network, in order, as it's polled.
+    pub async fn files_read_stream(
+        &self,
+        url: &str,
+    ) -> Result<(StreamedFileInfo, impl Stream<Item = Result<Bytes>>)> {
+        let options = FetchOptions {
+            parallelism: 1,
+            order: FetchOrder::Sequential,
+        };
+        let (info, segments) = self.files_read_stream_with(url, options).await?;
+        Ok((info, segments.map(|segment| segment.map(|(_, data)| data))))
+    }
+
+    /// # Read content stored from a stream or reader, with the given fetch options
+    ///
+    /// Returns the information about the content, and a stream which fetches its
+    /// segments from the network, as many in parallel as set in the options, returning
+    /// each of them together with its offset within the content.
+    pub async fn files_read_stream_with(
+        &self,
+        url: &str,
+        options: FetchOptions,
+    ) -> Result<(StreamedFileInfo, impl Stream<Item = Result<(u64, Bytes)>>)> {
+        let safe_url = Url::from_url(url)?;
+        let serialised_manifest = self.fetch_public_data(&safe_url, None).await?;
+        let manifest: StreamManifest =
+            rmp_serde::from_slice(&serialised_manifest).map_err(|err| {
+                Error::ContentError(format!("Couldn't parse stream manifest: {:?}", err))
+            })?;
+
+        let info = StreamedFileInfo {
+            media_type: manifest.media_type,
+            size: manifest.size,
+        };
+        debug!(
+            "Fetching {} segments with {:?}",
+            manifest.segments.len(),
+            options
+        );
+
+        // All segments but the last one are full, thus each offset is known beforehand
+        let safe = self.clone();
+        let fetches = stream::iter(manifest.segments.into_iter().enumerate())
+            .map(move |(index, xorurl)| fetch_segment(safe.clone(), index, xorurl));
+
+        let parallelism = std::cmp::max(options.parallelism, 1);
+        let segments = match options.order {
+            FetchOrder::Sequential => fetches.buffered(parallelism).left_stream(),
+            FetchOrder::Any => fetches.buffer_unordered(parallelism).right_stream(),
+        };
+
+        Ok((info, segments))
+    }
+}
+
+// Fetch a segment of streamed content, returning it with its offset within the content
+async fn fetch_segment(safe: Safe, index: usize, xorurl: XorUrl) -> Result<(u64, Bytes)> {
+    let data = safe
+        .fetch_public_data(&Url::from_xorurl(&xorurl)?, None)
+        .await?;
+    Ok(((index * STREAM_SEGMENT_SIZE) as u64, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, UrlAddressExt};
+    use anyhow::Result;
+    use futures::io::Cursor;
+
+    async fn read_all(safe: &Safe, xorurl: &str) -> Result<(StreamedFileInfo, Vec<u8>)> {
+        let (info, segments) = safe.files_read_stream(xorurl).await?;
+        let segments: Vec<crate::Result<Bytes>> = segments.collect().await;
+        let mut content = vec![];
+        for segment in segments {
+            content.extend_from_slice(&segment?);
+        }
+        Ok((info, content))
+    }
+
+    #[tokio::test]
+    async fn test_files_store_stream_and_reader() -> Result<()> {
+        let safe = new_safe_instance().await?;
+
+        let items = vec![
+            Bytes::from("hello "),
+            Bytes::from("streamed "),
+            Bytes::from("world"),
+        ];
+        let xorurl = safe
+            .files_store_stream(stream::iter(items), Some("text/plain"))
+            .await?;
+        let (info, content) = retry_loop!(read_all(&safe, &xorurl));
+        assert_eq!(content, b"hello streamed world".to_vec());
+        assert_eq!(
+            info,
+            StreamedFileInfo {
+                media_type: Some("text/plain".to_string()),
+                size: 20,
+            }
+        );
+
+        let data: Vec<u8> = (0..READ_BUFFER_SIZE * 3).map(|i| i as u8).collect();
+        let xorurl = safe
+            .files_store_reader(Cursor::new(data.clone()), None)
+            .await?;
+        let (info, content) = retry_loop!(read_all(&safe, &xorurl));
+        assert_eq!(content, data);
+        assert_eq!(info.size, data.len() as u64);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_files_read_stream_with_any_order() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let data: Vec<u8> = (0..STREAM_SEGMENT_SIZE * 2 + 10).map(|i| i as u8).collect();
+        let xorurl = safe
+            .files_store_reader(Cursor::new(data.clone()), None)
+            .await?;
+        let _ = retry_loop!(read_all(&safe, &xorurl));
+
+        let options = FetchOptions {
+            parallelism: 3,
+            order: FetchOrder::Any,
+        };
+        let (info, segments) = safe.files_read_stream_with(&xorurl, options).await?;
+        let segments: Vec<crate::Result<(u64, Bytes)>> = segments.collect().await;
+        assert_eq!(segments.len(), 3);
+
+        let mut content = vec![0; info.size as usize];
+        for segment in segments {
+            let (offset, segment) = segment?;
+            let offset = offset as usize;
+            content[offset..offset + segment.len()].copy_from_slice(&segment);
+        }
+        assert_eq!(content, data);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_files_store_stream_private_by_default() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        safe.set_private_by_default(true);
+
+        let xorurl = safe
+            .files_store_stream(stream::iter(vec![Bytes::from("private")]), None)
+            .await?;
+        assert!(!Url::from_xorurl(&xorurl)?.bytes_address()?.is_public());
+        let (_, content) = retry_loop!(read_all(&safe, &xorurl));
+        assert_eq!(content, b"private".to_vec());
+
+        Ok(())
+    }
+}
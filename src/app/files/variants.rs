@@ -0,0 +1,436 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{FileItem, FileMeta, FilesMap};
+use crate::{
+    app::consts::*,
+    fetch::{Range, SafeData},
+    DataType, Error, Result, Safe, Url, VersionHash, XorUrl,
+};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::HashSet, iter::FromIterator};
+
+// Media type assumed for content stored without one
+const DEFAULT_MEDIA_TYPE: &str = "application/octet-stream";
+
+// Encoding of content stored as is
+const IDENTITY_ENCODING: &str = "identity";
+
+/// Alternative representation of a file, recorded in its metadata,
+/// e.g. a compressed copy, or an image at a different resolution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentVariant {
+    /// XOR-URL of the Blob holding this representation
+    pub link: XorUrl,
+    /// Media type of the representation, e.g. `image/webp`
+    pub media_type: Option<String>,
+    /// Encoding the representation is stored with, e.g. `gzip`, none if stored as is
+    pub encoding: Option<String>,
+    /// Width in pixels, for images
+    pub width: Option<u32>,
+}
+
+/// Media types and encodings accepted when fetching content, in the format of the HTTP
+/// `Accept` and `Accept-Encoding` headers, used to pick the best representation of a file
+/// which has alternative ones recorded, see `Safe::fetch_with_accept`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Accept {
+    media_types: Vec<(String, f32)>,
+    encodings: Vec<(String, f32)>,
+    max_width: Option<u32>,
+}
+
+impl Accept {
+    /// Parse the lists of accepted media types and encodings, e.g. `image/webp, image/*;q=0.8`
+    /// and `gzip, br;q=0.5`. An empty list of media types accepts any of them, while an
+    /// empty list of encodings only accepts content stored as is.
+    pub fn new(media_types: &str, encodings: &str) -> Self {
+        Self {
+            media_types: parse_quality_list(media_types),
+            encodings: parse_quality_list(encodings),
+            max_width: None,
+        }
+    }
+
+    /// Prefer the widest images not exceeding the given width, or the narrowest ones if
+    /// all of them exceed it, over the preferred media types and encodings
+    pub fn with_max_width(mut self, max_width: u32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// Pick the best of the representations given, preferring those with an explicitly
+    /// accepted encoding over those stored as is upon a tie, and otherwise the first one.
+    /// Returns none if none of them is acceptable.
+    pub fn select<'a>(&self, variants: &'a [ContentVariant]) -> Option<&'a ContentVariant> {
+        variants
+            .iter()
+            .map(|variant| (self.rank(variant), variant))
+            .filter(|((_, _, quality, _), _)| *quality > 0.0)
+            .rev()
+            .max_by(|(a, _), (b, _)| {
+                a.0.cmp(&b.0)
+                    .then(a.1.cmp(&b.1))
+                    .then(a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal))
+                    .then(a.3.cmp(&b.3))
+            })
+            .map(|(_, variant)| variant)
+    }
+
+    // Rank a representation by whether it fits the maximum width, how close its width
+    // is to such maximum, its quality, and whether its encoding was accepted explicitly
+    // rather than being content stored as is
+    fn rank(&self, variant: &ContentVariant) -> (bool, i64, f32, bool) {
+        let (encoding_quality, explicit) = self.encoding_quality(variant.encoding.as_deref());
+        let quality = self.media_type_quality(variant.media_type.as_deref()) * encoding_quality;
+        match (self.max_width, variant.width) {
+            (Some(max_width), Some(width)) if width > max_width => {
+                (false, -(width as i64), quality, explicit)
+            }
+            (Some(_), Some(width)) => (true, width as i64, quality, explicit),
+            _ => (true, 0, quality, explicit),
+        }
+    }
+
+    // Quality of the most specific accepted media type matching, i.e. 'type/subtype',
+    // then 'type/*', and then '*/*'
+    fn media_type_quality(&self, media_type: Option<&str>) -> f32 {
+        if self.media_types.is_empty() {
+            return 1.0;
+        }
+
+        let media_type = media_type.unwrap_or(DEFAULT_MEDIA_TYPE).to_lowercase();
+        let main_type = media_type.split('/').next().unwrap_or_default();
+        self.media_types
+            .iter()
+            .filter_map(|(accepted, quality)| {
+                if *accepted == media_type {
+                    Some((2, *quality))
+                } else if accepted.strip_suffix("/*") == Some(main_type) {
+                    Some((1, *quality))
+                } else if accepted == "*/*" {
+                    Some((0, *quality))
+                } else {
+                    None
+                }
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map_or(0.0, |(_, quality)| quality)
+    }
+
+    // Quality of the accepted encoding matching, and whether it was accepted explicitly.
+    // Content stored as is is acceptable unless explicitly excluded.
+    fn encoding_quality(&self, encoding: Option<&str>) -> (f32, bool) {
+        let encoding = encoding.unwrap_or(IDENTITY_ENCODING).to_lowercase();
+        let find = |name: &str| {
+            self.encodings
+                .iter()
+                .find(|(accepted, _)| accepted == name)
+                .map(|(_, quality)| *quality)
+        };
+
+        match find(&encoding).or_else(|| find("*")) {
+            Some(quality) => (quality, true),
+            None if encoding == IDENTITY_ENCODING => (1.0, false),
+            None => (0.0, false),
+        }
+    }
+}
+
+impl Safe {
+    /// # Retrieve the best representation of the content at a safe:// URL
+    ///
+    /// Like `fetch`, but if the URL resolves to a file which has alternative representations
+    /// recorded in its metadata (see `files_container_add_variant`), the one which best
+    /// matches the accepted media types and encodings is retrieved. The metadata returned
+    /// reflects the link, media type and encoding of the representation picked. It fails
+    /// with `Error::ContentNotFound` if none of the representations is acceptable.
+    pub async fn fetch_with_accept(
+        &self,
+        url: &str,
+        range: Range,
+        accept: &Accept,
+    ) -> Result<SafeData> {
+        let mut chain = self.retrieve_from_url(url, false, None, true).await?;
+        let inspected = chain
+            .pop()
+            .ok_or_else(|| Error::ContentNotFound(format!("Failed to resolve {}", url)))?;
+        let metadata = match &inspected {
+            SafeData::PublicBlob {
+                metadata: Some(metadata),
+                ..
+            } if metadata.contains_key(PREDICATE_VARIANTS) => metadata.clone(),
+            _ => return self.fetch(url, range).await,
+        };
+
+        let variants = file_representations(&metadata)?;
+        let chosen = accept.select(&variants).ok_or_else(|| {
+            Error::ContentNotFound(format!(
+                "None of the representations of the content at {} is acceptable",
+                url
+            ))
+        })?;
+        debug!("Representation picked for {}: {:?}", url, chosen);
+
+        let mut chosen_metadata = metadata.clone();
+        let _ = chosen_metadata.insert(PREDICATE_LINK.to_string(), chosen.link.clone());
+        if let Some(media_type) = &chosen.media_type {
+            let _ = chosen_metadata.insert(PREDICATE_TYPE.to_string(), media_type.clone());
+        }
+        match &chosen.encoding {
+            Some(encoding) => {
+                let _ = chosen_metadata.insert(PREDICATE_ENCODING.to_string(), encoding.clone());
+            }
+            None => {
+                let _ = chosen_metadata.remove(PREDICATE_ENCODING);
+            }
+        }
+
+        let chosen_url = Url::from_url(&chosen.link)?;
+        let data = self.fetch_public_data(&chosen_url, range).await?;
+        let safe_data = SafeData::PublicBlob {
+            xorurl: chosen_url.to_xorurl_string(),
+            xorname: chosen_url.xorname(),
+            data,
+            media_type: chosen.media_type.clone(),
+            metadata: Some(chosen_metadata),
+            resolved_from: inspected.resolved_from(),
+        };
+
+        self.history.record(url, &safe_data.xorurl());
+        Ok(safe_data)
+    }
+
+    /// # Record an alternative representation of a file in a FilesContainer
+    ///
+    /// The representation is recorded in the metadata of the file the URL's path targets,
+    /// replacing any representation previously recorded with the same link.
+    /// A new version of the FilesContainer is created.
+    pub async fn files_container_add_variant(
+        &mut self,
+        url: &str,
+        variant: ContentVariant,
+    ) -> Result<(VersionHash, FilesMap)> {
+        let safe_url = Safe::parse_url(url)?;
+        if safe_url.content_version().is_some() {
+            return Err(Error::InvalidInput(format!(
+                "The target URL cannot contain a version: {}",
+                url
+            )));
+        };
+        if !matches!(Url::from_url(&variant.link)?.data_type(), DataType::Bytes) {
+            return Err(Error::InvalidInput(format!(
+                "The representation must link to a Blob: {}",
+                variant.link
+            )));
+        }
+
+        info!("Adding representation {} to {}", variant.link, url);
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let (current_version, mut files_map) = self.fetch_files_container(&safe_url).await?;
+
+        let file_path = safe_url.path().to_string();
+        let file_item = match files_map.get_mut(&file_path) {
+            Some(file_item)
+                if file_item
+                    .get(PREDICATE_TYPE)
+                    .map_or(false, |file_type| FileMeta::filetype_is_file(file_type)) =>
+            {
+                file_item
+            }
+            _ => {
+                return Err(Error::ContentNotFound(format!(
+                    "No file found at the \"{}\" path on the target FilesContainer",
+                    file_path
+                )))
+            }
+        };
+
+        let mut variants = recorded_variants(file_item)?;
+        variants.retain(|recorded| recorded.link != variant.link);
+        variants.push(variant);
+        let serialised_variants = serde_json::to_string(&variants).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise representations: {:?}", err))
+        })?;
+        let _ = file_item.insert(PREDICATE_VARIANTS.to_string(), serialised_variants);
+
+        let version = self
+            .append_version_to_files_container(
+                HashSet::from_iter([current_version]),
+                &files_map,
+                url,
+                safe_url,
+                false,
+                false,
+            )
+            .await?;
+
+        Ok((version, files_map))
+    }
+}
+
+// Alternative representations recorded in a file's metadata
+fn recorded_variants(file_item: &FileItem) -> Result<Vec<ContentVariant>> {
+    match file_item.get(PREDICATE_VARIANTS) {
+        Some(serialised_variants) => serde_json::from_str(serialised_variants).map_err(|err| {
+            Error::ContentError(format!("Couldn't parse representations: {:?}", err))
+        }),
+        None => Ok(vec![]),
+    }
+}
+
+// All the representations of a file, starting with the original one
+fn file_representations(file_item: &FileItem) -> Result<Vec<ContentVariant>> {
+    let link = file_item.get(PREDICATE_LINK).ok_or_else(|| {
+        Error::ContentError("FileItem is corrupt. It is missing a \"link\" property".to_string())
+    })?;
+    let original = ContentVariant {
+        link: link.clone(),
+        media_type: file_item.get(PREDICATE_TYPE).cloned(),
+        encoding: file_item.get(PREDICATE_ENCODING).cloned(),
+        width: None,
+    };
+
+    let mut variants = vec![original];
+    variants.extend(recorded_variants(file_item)?);
+    Ok(variants)
+}
+
+// Parse a comma separated list of values with optional quality factors, e.g. `gzip, br;q=0.5`
+fn parse_quality_list(list: &str) -> Vec<(String, f32)> {
+    list.split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let value = parts.next().filter(|value| !value.is_empty())?;
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .map_or(1.0, |q| q.clamp(0.0, 1.0));
+            Some((value.to_lowercase(), quality))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern};
+    use anyhow::{anyhow, Result};
+    use bytes::Bytes;
+
+    fn variant(
+        link: &str,
+        media_type: &str,
+        encoding: Option<&str>,
+        width: Option<u32>,
+    ) -> ContentVariant {
+        ContentVariant {
+            link: link.to_string(),
+            media_type: Some(media_type.to_string()),
+            encoding: encoding.map(str::to_string),
+            width,
+        }
+    }
+
+    #[test]
+    fn test_accept_select() {
+        let variants = vec![
+            variant("original", "image/png", None, Some(2000)),
+            variant("webp", "image/webp", None, Some(2000)),
+            variant("thumbnail", "image/png", None, Some(200)),
+            variant("compressed", "image/png", Some("gzip"), Some(2000)),
+        ];
+        let picked = |accept: Accept| accept.select(&variants).map(|v| v.link.as_str());
+
+        assert_eq!(picked(Accept::default()), Some("original"));
+        assert_eq!(
+            picked(Accept::new("image/webp, image/*;q=0.8", "")),
+            Some("webp")
+        );
+        assert_eq!(picked(Accept::new("image/png", "gzip")), Some("compressed"));
+        assert_eq!(
+            picked(Accept::new("image/png", "gzip;q=0.5")),
+            Some("original")
+        );
+        assert_eq!(
+            picked(Accept::new("image/png", "").with_max_width(500)),
+            Some("thumbnail")
+        );
+        assert_eq!(
+            picked(Accept::new("image/webp;q=0.9, */*;q=0.1", "").with_max_width(100)),
+            Some("thumbnail")
+        );
+        assert_eq!(picked(Accept::new("text/html", "")), None);
+        assert_eq!(picked(Accept::new("*/*", "identity;q=0")), None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_with_accept() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let (xorurl, _, _) = safe
+            .files_container_create(Some("./testdata/test.md"), None, false, false, false)
+            .await?;
+        let _ = retry_loop!(safe.fetch(&xorurl, None));
+        let mut file_url = Url::from_url(&xorurl)?;
+        file_url.set_content_version(None);
+        file_url.set_path("/test.md");
+        let file_url = file_url.to_string();
+
+        let compressed = Bytes::from("compressed markdown");
+        let link = safe
+            .store_public_bytes(compressed.clone(), Some("text/markdown"), false)
+            .await?;
+        let _ = safe
+            .files_container_add_variant(
+                &file_url,
+                ContentVariant {
+                    link: link.clone(),
+                    media_type: Some("text/markdown".to_string()),
+                    encoding: Some("gzip".to_string()),
+                    width: None,
+                },
+            )
+            .await?;
+
+        let accept = Accept::new("text/*", "gzip, identity;q=0.5");
+        let negotiated = retry_loop_for_pattern!(
+            safe.fetch_with_accept(&file_url, None, &accept),
+            Ok(SafeData::PublicBlob { data, .. }) if *data == compressed
+        )?;
+        match negotiated {
+            SafeData::PublicBlob {
+                xorurl,
+                metadata: Some(metadata),
+                ..
+            } => {
+                assert_eq!(xorurl, link);
+                assert_eq!(metadata[PREDICATE_ENCODING], "gzip");
+            }
+            other => return Err(anyhow!("Unexpected content fetched: {:?}", other)),
+        }
+
+        // the original is picked when the encoding is not accepted
+        match safe
+            .fetch_with_accept(&file_url, None, &Accept::new("text/markdown", ""))
+            .await?
+        {
+            SafeData::PublicBlob { data, .. } => assert!(data.starts_with(b"hello tests!")),
+            other => return Err(anyhow!("Unexpected content fetched: {:?}", other)),
+        }
+
+        assert!(safe
+            .fetch_with_accept(&file_url, None, &Accept::new("image/*", ""))
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
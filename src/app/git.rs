@@ -0,0 +1,315 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{helpers::gen_timestamp_secs, register::EntryHash};
+use crate::{
+    ContentType, Error, Result, Safe, Scope, Url, UrlAddressExt, VersionHash, XorName, XorUrl,
+};
+use bytes::Bytes;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Type tag to use for the Registers holding the state of git repositories
+const GIT_REPO_TYPE_TAG: u64 = 2_700;
+
+/// State of a git repository stored on the network: its refs, and the packfiles holding
+/// its objects. Each push stores a new state, superseding the previous one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GitRepoState {
+    /// Object id, as hex, each ref points to, keyed by the ref's name, e.g. `refs/heads/main`
+    pub refs: BTreeMap<String, String>,
+    /// Ref HEAD points to, if set, e.g. `refs/heads/main`
+    pub head: Option<String>,
+    /// XOR-URLs of the packfiles, in the order they were pushed
+    pub packs: Vec<XorUrl>,
+    /// Time the state was pushed, in RFC3339 format
+    pub pushed_at: String,
+}
+
+/// Update of a ref pushed to a git repository
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitRefUpdate {
+    /// Name of the ref, e.g. `refs/heads/main`
+    pub name: String,
+    /// Object id the ref is expected to point to, `None` if it's expected not to exist
+    pub old: Option<String>,
+    /// Object id the ref is to point to, `None` to delete the ref
+    pub new: Option<String>,
+}
+
+impl Safe {
+    /// # Create a git repository
+    ///
+    /// The state of the repository is stored on a Register, each entry linking to a Blob
+    /// with its refs and the list of packfiles pushed, which are stored as Blobs as well.
+    /// These are the primitives a `git-remote-safe` helper is meant to be built upon.
+    pub async fn git_repo_create(&self, name: Option<XorName>, private: bool) -> Result<XorUrl> {
+        let xorname = self
+            .safe_client
+            .store_register(name, GIT_REPO_TYPE_TAG, None, private)
+            .await?;
+        let scope = if private {
+            Scope::Private
+        } else {
+            Scope::Public
+        };
+
+        Ok(Url::encode_register(
+            xorname,
+            GIT_REPO_TYPE_TAG,
+            scope,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?)
+    }
+
+    /// # Read the current state of a git repository
+    ///
+    /// It's what a helper lists upon git's `list` command. If there were concurrent
+    /// pushes, the packfiles of all of them are returned, and each ref is taken from
+    /// the latest push which has it, until a new push supersedes them.
+    pub async fn git_repo_state(&self, url: &str) -> Result<(VersionHash, GitRepoState)> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let (tips, state) = self.fetch_git_repo_state(&safe_url).await?;
+        let version = tips
+            .iter()
+            .next()
+            .map(VersionHash::from)
+            .unwrap_or_default();
+        Ok((version, state))
+    }
+
+    /// # Push a packfile and ref updates to a git repository
+    ///
+    /// The packfile, if any, is stored as a Blob with the same scope as the repository.
+    /// The refs are only updated if they point to the expected objects, unless forced,
+    /// otherwise it fails with `Error::ConcurrentWrite` and nothing is pushed, which is
+    /// reported to git as a rejected, non fast-forward, push. Returns the new version.
+    pub async fn git_push(
+        &self,
+        url: &str,
+        pack: Option<Bytes>,
+        updates: &[GitRefUpdate],
+        head: Option<&str>,
+        force: bool,
+    ) -> Result<VersionHash> {
+        info!(
+            "Pushing {} ref updates to git repository {}",
+            updates.len(),
+            url
+        );
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let scope = safe_url.register_parts()?.2;
+        let (tips, mut state) = self.fetch_git_repo_state(&safe_url).await?;
+
+        for update in updates.iter() {
+            let current = state.refs.get(&update.name);
+            if !force && current != update.old.as_ref() {
+                return Err(Error::ConcurrentWrite(format!(
+                    "Ref '{}' points to {:?} instead of the expected {:?}",
+                    update.name, current, update.old
+                )));
+            }
+        }
+
+        if let Some(pack) = pack {
+            let pack_xorurl = match scope {
+                Scope::Public => self.store_public_bytes(pack, None, false).await?,
+                Scope::Private => self.store_private_bytes(pack, None).await?,
+            };
+            if !state.packs.contains(&pack_xorurl) {
+                state.packs.push(pack_xorurl);
+            }
+        }
+        for update in updates.iter() {
+            let _ = match &update.new {
+                Some(new) => state.refs.insert(update.name.clone(), new.clone()),
+                None => state.refs.remove(&update.name),
+            };
+        }
+        if let Some(head) = head {
+            state.head = Some(head.to_string());
+        }
+        state.pushed_at = gen_timestamp_secs();
+
+        let serialised_state = rmp_serde::to_vec_named(&state).map_err(|err| {
+            Error::Serialisation(format!(
+                "Couldn't serialise git repository state: {:?}",
+                err
+            ))
+        })?;
+        let state_xorurl = match scope {
+            Scope::Public => {
+                self.store_public_bytes(Bytes::from(serialised_state), None, false)
+                    .await?
+            }
+            Scope::Private => {
+                self.store_private_bytes(Bytes::from(serialised_state), None)
+                    .await?
+            }
+        };
+        let entry_hash = self
+            .write_to_register(
+                &safe_url.to_string(),
+                Url::from_xorurl(&state_xorurl)?,
+                tips,
+            )
+            .await?;
+
+        Ok(VersionHash::from(&entry_hash))
+    }
+
+    /// # Fetch a packfile of a git repository
+    ///
+    /// A helper fetches the packfiles of the repository state it hasn't fetched before.
+    pub async fn git_fetch_pack(&self, pack_url: &str) -> Result<Bytes> {
+        let safe_url = Safe::parse_url(pack_url)?;
+        self.fetch_public_data(&safe_url, None).await
+    }
+
+    // Private helper to read the state of a repository, merging the states of concurrent pushes
+    async fn fetch_git_repo_state(&self, url: &Url) -> Result<(BTreeSet<EntryHash>, GitRepoState)> {
+        let entries = match self.fetch_register_entries(url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut states = vec![];
+        for (_, entry) in entries.iter() {
+            let serialised_state = self.fetch_public_data(entry, None).await?;
+            let state: GitRepoState = rmp_serde::from_slice(&serialised_state).map_err(|err| {
+                Error::ContentError(format!("Couldn't parse git repository state: {:?}", err))
+            })?;
+            states.push(state);
+        }
+        if states.len() > 1 {
+            debug!(
+                "Merging {} concurrent states of git repository {}",
+                states.len(),
+                url
+            );
+        }
+
+        let tips = entries.into_iter().map(|(hash, _)| hash).collect();
+        Ok((tips, merge_states(states)))
+    }
+}
+
+// Merge the states of concurrent pushes, taking the packfiles of all of them,
+// and the refs and HEAD from the latest push which has them
+fn merge_states(mut states: Vec<GitRepoState>) -> GitRepoState {
+    states.sort_by(|a, b| a.pushed_at.cmp(&b.pushed_at));
+    states
+        .into_iter()
+        .fold(GitRepoState::default(), |mut merged, state| {
+            for pack in state.packs {
+                if !merged.packs.contains(&pack) {
+                    merged.packs.push(pack);
+                }
+            }
+            merged.refs.extend(state.refs);
+            if state.head.is_some() {
+                merged.head = state.head;
+            }
+            merged.pushed_at = state.pushed_at;
+            merged
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern};
+    use anyhow::Result;
+
+    fn state(refs: &[(&str, &str)], packs: &[&str], pushed_at: &str) -> GitRepoState {
+        GitRepoState {
+            refs: refs
+                .iter()
+                .map(|(name, id)| (name.to_string(), id.to_string()))
+                .collect(),
+            head: None,
+            packs: packs.iter().map(|pack| pack.to_string()).collect(),
+            pushed_at: pushed_at.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_git_merge_states() {
+        let older = state(
+            &[("refs/heads/main", "aa"), ("refs/heads/dev", "bb")],
+            &["safe://pack1", "safe://pack2"],
+            "2021-01-01T00:00:00Z",
+        );
+        let newer = state(
+            &[("refs/heads/main", "cc")],
+            &["safe://pack1", "safe://pack3"],
+            "2021-01-02T00:00:00Z",
+        );
+
+        let merged = merge_states(vec![newer, older]);
+        assert_eq!(
+            merged.packs,
+            vec!["safe://pack1", "safe://pack2", "safe://pack3"]
+        );
+        assert_eq!(merged.refs["refs/heads/main"], "cc");
+        assert_eq!(merged.refs["refs/heads/dev"], "bb");
+        assert_eq!(merged.pushed_at, "2021-01-02T00:00:00Z");
+    }
+
+    #[tokio::test]
+    async fn test_git_push_and_fetch() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.git_repo_create(None, false).await?;
+        let (_, empty) = retry_loop!(safe.git_repo_state(&xorurl));
+        assert_eq!(empty, GitRepoState::default());
+
+        let main = |old: Option<&str>, new: &str| GitRefUpdate {
+            name: "refs/heads/main".to_string(),
+            old: old.map(str::to_string),
+            new: Some(new.to_string()),
+        };
+        let pack = Bytes::from("PACK first objects");
+        let version = safe
+            .git_push(
+                &xorurl,
+                Some(pack.clone()),
+                &[main(None, "aa")],
+                Some("refs/heads/main"),
+                false,
+            )
+            .await?;
+
+        let (current, state) = retry_loop_for_pattern!(safe.git_repo_state(&xorurl), Ok((current, _)) if *current == version)?;
+        assert_eq!(current, version);
+        assert_eq!(state.refs["refs/heads/main"], "aa");
+        assert_eq!(state.head.as_deref(), Some("refs/heads/main"));
+        assert_eq!(state.packs.len(), 1);
+        assert_eq!(safe.git_fetch_pack(&state.packs[0]).await?, pack);
+
+        // a push based on a stale ref is rejected, unless forced
+        assert!(matches!(
+            safe.git_push(&xorurl, None, &[main(None, "bb")], None, false)
+                .await,
+            Err(Error::ConcurrentWrite(_))
+        ));
+        let forced = safe
+            .git_push(&xorurl, None, &[main(None, "bb")], None, true)
+            .await?;
+        let (_, state) = retry_loop_for_pattern!(safe.git_repo_state(&xorurl), Ok((current, _)) if *current == forced)?;
+        assert_eq!(state.refs["refs/heads/main"], "bb");
+        assert_eq!(state.packs.len(), 1);
+
+        Ok(())
+    }
+}
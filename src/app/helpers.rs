@@ -16,6 +16,7 @@ use std::{
     str::{self, FromStr},
     time,
 };
+use tiny_keccak::{Hasher, Sha3};
 
 /// The conversion from token to raw value
 const TOKEN_TO_RAW_CONVERSION: u64 = 1_000_000_000;
@@ -60,3 +61,51 @@ pub fn systemtime_to_rfc3339(t: time::SystemTime) -> String {
 pub fn gen_timestamp_secs() -> String {
     Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
 }
+
+// Match a text against a pattern where '*' matches any sequence of characters
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let mut remaining = text;
+    for (i, part) in parts.iter().enumerate() {
+        if i == 0 {
+            match remaining.strip_prefix(part) {
+                Some(rest) => remaining = rest,
+                None => return false,
+            }
+        } else if i == parts.len() - 1 {
+            return remaining.ends_with(part);
+        } else {
+            match remaining.find(part) {
+                Some(index) => remaining = &remaining[index + part.len()..],
+                None => return false,
+            }
+        }
+    }
+    true
+}
+
+// SHA3-256 hash of some data, hex encoded
+pub(crate) fn sha3_256_hex(data: &[u8]) -> String {
+    let mut hasher = Sha3::v256();
+    let mut hash = [0; 32];
+    hasher.update(data);
+    hasher.finalize(&mut hash);
+    hex::encode(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha3_256_hex() {
+        assert_eq!(
+            sha3_256_hex(b""),
+            "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+        );
+    }
+}
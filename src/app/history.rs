@@ -0,0 +1,270 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::helpers::{gen_timestamp_secs, glob_match};
+use crate::{Error, Result, Safe};
+use chrono::{DateTime, FixedOffset};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// A record of a URL fetched while the history was enabled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// The URL as it was provided to `fetch`
+    pub url: String,
+    /// The XOR-URL of the content the URL was resolved to
+    pub resolved_xorurl: String,
+    /// Time of the fetch, in RFC3339 format
+    pub timestamp: String,
+}
+
+impl HistoryEntry {
+    // Whether the entry was recorded at or after the given time. Entries whose timestamp
+    // can't be parsed, e.g. edited by hand, are considered recent so they are not purged.
+    fn recorded_since(&self, time: DateTime<FixedOffset>) -> bool {
+        DateTime::parse_from_rfc3339(&self.timestamp).map_or(true, |timestamp| timestamp >= time)
+    }
+}
+
+#[derive(Default)]
+struct HistoryState {
+    path: Option<PathBuf>,
+    paused: bool,
+    exclude_patterns: Vec<String>,
+    entries: Vec<HistoryEntry>,
+}
+
+impl HistoryState {
+    fn is_excluded(&self, url: &str) -> bool {
+        self.exclude_patterns
+            .iter()
+            .any(|pattern| glob_match(pattern, url))
+    }
+
+    fn persist(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            let serialised_entries = serde_json::to_string(&self.entries).map_err(|err| {
+                Error::Serialisation(format!("Couldn't serialise fetch history: {:?}", err))
+            })?;
+            fs::write(path, serialised_entries).map_err(|err| {
+                Error::FileSystemError(format!(
+                    "Couldn't write fetch history to '{}': {}",
+                    path.display(),
+                    err
+                ))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+// Local, opt-in, store of the URLs fetched. It's disabled unless explicitly enabled.
+#[derive(Clone, Default)]
+pub(crate) struct FetchHistory {
+    state: Option<Arc<Mutex<HistoryState>>>,
+}
+
+impl FetchHistory {
+    // Record a fetch unless the history is disabled, paused, or the URL excluded
+    pub(crate) fn record(&self, url: &str, resolved_xorurl: &str) {
+        if let Some(state) = &self.state {
+            if let Ok(mut state) = state.lock() {
+                if state.paused || state.is_excluded(url) {
+                    return;
+                }
+
+                state.entries.push(HistoryEntry {
+                    url: url.to_string(),
+                    resolved_xorurl: resolved_xorurl.to_string(),
+                    timestamp: gen_timestamp_secs(),
+                });
+                if let Err(err) = state.persist() {
+                    warn!("Failed to persist fetch history: {}", err);
+                }
+            }
+        }
+    }
+
+    fn with_state<T>(&self, f: impl FnOnce(&mut HistoryState) -> Result<T>) -> Result<T> {
+        let state = self.state.as_ref().ok_or_else(|| {
+            Error::InvalidInput("Fetch history is not enabled on this instance".to_string())
+        })?;
+        let mut state = state
+            .lock()
+            .map_err(|_| Error::InvalidInput("Fetch history state is poisoned".to_string()))?;
+        f(&mut state)
+    }
+}
+
+impl Safe {
+    /// Enable the local fetch history. If a path is provided the history is persisted to
+    /// that file, loading any history previously stored in it, otherwise it's kept in memory.
+    pub fn history_enable(&mut self, path: Option<&Path>) -> Result<()> {
+        let entries = match path {
+            Some(path) if path.exists() => {
+                let serialised_entries = fs::read(path).map_err(|err| {
+                    Error::FileSystemError(format!(
+                        "Couldn't read fetch history from '{}': {}",
+                        path.display(),
+                        err
+                    ))
+                })?;
+                serde_json::from_slice(&serialised_entries).map_err(|err| {
+                    Error::ContentError(format!("Couldn't parse fetch history: {:?}", err))
+                })?
+            }
+            _ => Vec::new(),
+        };
+
+        debug!("Enabling fetch history with {} entries", entries.len());
+        self.history = FetchHistory {
+            state: Some(Arc::new(Mutex::new(HistoryState {
+                path: path.map(|p| p.to_path_buf()),
+                entries,
+                ..HistoryState::default()
+            }))),
+        };
+        Ok(())
+    }
+
+    /// Disable the local fetch history, any history persisted on disk is kept
+    pub fn history_disable(&mut self) {
+        self.history = FetchHistory::default();
+    }
+
+    /// Pause or resume recording fetches in the history
+    pub fn history_pause(&self, paused: bool) -> Result<()> {
+        self.history.with_state(|state| {
+            state.paused = paused;
+            Ok(())
+        })
+    }
+
+    /// Set the patterns of URLs which shall never be recorded in the history.
+    /// Patterns can use `*` as a wildcard, e.g. `safe://*.private-site/*`.
+    pub fn history_set_exclusions(&self, patterns: Vec<String>) -> Result<()> {
+        self.history.with_state(|state| {
+            state.exclude_patterns = patterns;
+            Ok(())
+        })
+    }
+
+    /// Query the history for the entries whose URL contains the text provided,
+    /// optionally only those recorded since the given RFC3339 time,
+    /// returning the most recent ones first.
+    pub fn history_query(
+        &self,
+        text: Option<&str>,
+        since: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<HistoryEntry>> {
+        let since = since.map(parse_time).transpose()?;
+        self.history.with_state(|state| {
+            Ok(state
+                .entries
+                .iter()
+                .rev()
+                .filter(|entry| text.map_or(true, |text| entry.url.contains(text)))
+                .filter(|entry| since.map_or(true, |since| entry.recorded_since(since)))
+                .take(limit.unwrap_or(usize::MAX))
+                .cloned()
+                .collect())
+        })
+    }
+
+    /// Purge the entries recorded before the given RFC3339 time, or the whole
+    /// history if no time is provided, returning the number of entries purged
+    pub fn history_purge(&self, before: Option<&str>) -> Result<usize> {
+        let before = before.map(parse_time).transpose()?;
+        self.history.with_state(|state| {
+            let initial_len = state.entries.len();
+            match before {
+                Some(before) => state.entries.retain(|entry| entry.recorded_since(before)),
+                None => state.entries.clear(),
+            }
+            state.persist()?;
+            Ok(initial_len - state.entries.len())
+        })
+    }
+}
+
+// Parse a time bound provided by the user, comparing instants rather than
+// strings so bounds with any offset are honoured
+fn parse_time(time: &str) -> Result<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(time).map_err(|err| {
+        Error::InvalidInput(format!(
+            "Invalid time '{}', it must be in RFC3339 format: {}",
+            time, err
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_history_glob_match() {
+        assert!(glob_match("safe://site", "safe://site"));
+        assert!(!glob_match("safe://site", "safe://site/path"));
+        assert!(glob_match(
+            "safe://*.private/*",
+            "safe://a.private/index.html"
+        ));
+        assert!(!glob_match(
+            "safe://*.private/*",
+            "safe://public/index.html"
+        ));
+        assert!(glob_match("*secret*", "safe://my-secret-site"));
+    }
+
+    #[test]
+    fn test_history_record_query_purge() -> Result<()> {
+        let mut safe = Safe::default();
+        safe.history.record("safe://not-enabled", "safe://xorurl0");
+        assert!(safe.history_query(None, None, None).is_err());
+
+        safe.history_enable(None)?;
+        safe.history_set_exclusions(vec!["safe://*.private*".to_string()])?;
+        safe.history.record("safe://site-one", "safe://xorurl1");
+        safe.history
+            .record("safe://my.private/file", "safe://xorurl2");
+        safe.history_pause(true)?;
+        safe.history.record("safe://paused", "safe://xorurl3");
+        safe.history_pause(false)?;
+        safe.history.record("safe://site-two", "safe://xorurl4");
+
+        let entries = safe.history_query(None, None, None)?;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].url, "safe://site-two");
+        assert_eq!(entries[1].url, "safe://site-one");
+
+        let entries = safe.history_query(Some("one"), None, Some(5))?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].resolved_xorurl, "safe://xorurl1");
+
+        // bounds are compared as instants whatever their offset
+        let entries = safe.history_query(None, Some("2000-01-01T02:00:00+02:00"), None)?;
+        assert_eq!(entries.len(), 2);
+        assert!(safe.history_query(None, Some("2000-01-01"), None).is_err());
+        assert!(safe.history_purge(Some("yesterday")).is_err());
+        assert_eq!(safe.history_purge(Some("2000-01-01T00:00:00Z"))?, 0);
+
+        assert_eq!(safe.history_purge(None)?, 2);
+        assert!(safe.history_query(None, None, None)?.is_empty());
+
+        Ok(())
+    }
+}
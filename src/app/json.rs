@@ -0,0 +1,290 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::register::EntryHash;
+use crate::{Error, Result, Safe, Scope, Url, UrlAddressExt, VersionHash, XorUrl};
+use bytes::Bytes;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+// Type tag to use for the Registers holding JSON documents
+const JSON_DOC_TYPE_TAG: u64 = 1_900;
+
+// Number of versions after which a full snapshot of the document is stored
+// instead of only the patch, bounding the number of patches to apply upon reads
+const JSON_SNAPSHOT_INTERVAL: u64 = 10;
+
+// Each version of a document is stored as a record in a Blob, holding either a full
+// snapshot of the document or the patch applied to the previous version.
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonRecord {
+    version: u64,
+    previous: Option<EntryHash>,
+    snapshot: Option<String>,
+    patch: Option<String>,
+}
+
+impl Safe {
+    /// # Create a JSON document
+    ///
+    /// The document is stored on a Register which keeps its version history,
+    /// returning the XOR-URL of its first version. The Register, and the Blobs holding
+    /// the versions, are private or public as requested, or with the default scope when
+    /// not specified, see `set_private_by_default`.
+    pub async fn json_create(&self, value: &Value, private: Option<bool>) -> Result<XorUrl> {
+        info!("Creating JSON document");
+        let private = private.unwrap_or_else(|| matches!(self.default_scope(), Scope::Private));
+        let xorurl = self
+            .register_create(None, JSON_DOC_TYPE_TAG, private)
+            .await?;
+
+        let record = JsonRecord {
+            version: 0,
+            previous: None,
+            snapshot: Some(serialise_json(value)?),
+            patch: None,
+        };
+        let hash = self
+            .write_json_record(&xorurl, &record, BTreeSet::new())
+            .await?;
+
+        let mut safe_url = Url::from_xorurl(&xorurl)?;
+        safe_url.set_content_version(Some(VersionHash::from(&hash)));
+        Ok(safe_url.to_string())
+    }
+
+    /// # Get a value from a JSON document
+    ///
+    /// The value is located with a JSON Pointer (RFC6901), e.g. `/address/city`,
+    /// an empty pointer returning the whole document. If the URL contains a version
+    /// the value is taken from that version, otherwise from the latest.
+    pub async fn json_get(&self, url: &str, json_pointer: &str) -> Result<(VersionHash, Value)> {
+        debug!("Getting JSON value at '{}' from: {}", json_pointer, url);
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let (hash, _, document) = self.fetch_json_document(&safe_url).await?;
+
+        let value = document.pointer(json_pointer).cloned().ok_or_else(|| {
+            Error::ContentNotFound(format!(
+                "No value found at '{}' in JSON document at \"{}\"",
+                json_pointer, url
+            ))
+        })?;
+
+        Ok((VersionHash::from(&hash), value))
+    }
+
+    /// # Patch a JSON document
+    ///
+    /// Apply a JSON Patch (RFC6902) to the latest version of the document, only the patch
+    /// being stored as the new version, returning the version hash of it.
+    pub async fn json_patch(&self, url: &str, patch: &Value) -> Result<VersionHash> {
+        info!("Patching JSON document at: {}", url);
+        let json_patch: json_patch::Patch = serde_json::from_value(patch.clone())
+            .map_err(|err| Error::InvalidInput(format!("Invalid JSON Patch: {}", err)))?;
+
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let (hash, record, mut document) = self.fetch_json_document(&safe_url).await?;
+
+        // Make sure the patch applies to the current version before storing it
+        json_patch::patch(&mut document, &json_patch)
+            .map_err(|err| Error::InvalidInput(format!("JSON Patch cannot be applied: {}", err)))?;
+
+        let version = record.version + 1;
+        let new_record = if version % JSON_SNAPSHOT_INTERVAL == 0 {
+            JsonRecord {
+                version,
+                previous: Some(hash),
+                snapshot: Some(serialise_json(&document)?),
+                patch: None,
+            }
+        } else {
+            JsonRecord {
+                version,
+                previous: Some(hash),
+                snapshot: None,
+                patch: Some(serialise_json(patch)?),
+            }
+        };
+
+        let parents = vec![hash].into_iter().collect();
+        let new_hash = self
+            .write_json_record(&safe_url.to_string(), &new_record, parents)
+            .await?;
+
+        Ok(VersionHash::from(&new_hash))
+    }
+
+    // Private helper to fetch a JSON document at the version set in the URL, or the latest,
+    // returning its version hash, its record, and the document rebuilt from the records
+    async fn fetch_json_document(&self, safe_url: &Url) -> Result<(EntryHash, JsonRecord, Value)> {
+        let entries = self.fetch_register_entries(safe_url).await?;
+
+        // If there were concurrent patches we take the one with the highest version
+        let mut latest: Option<(EntryHash, JsonRecord)> = None;
+        for (hash, entry) in entries.iter() {
+            let record = self.fetch_json_record(entry).await?;
+            if latest
+                .as_ref()
+                .map_or(true, |(_, l)| record.version > l.version)
+            {
+                latest = Some((*hash, record));
+            }
+        }
+        let (hash, record) = latest.ok_or_else(|| {
+            Error::EmptyContent(format!("No JSON document found at \"{}\"", safe_url))
+        })?;
+
+        // Walk back to the latest snapshot collecting the patches to apply to it
+        let mut patches = vec![];
+        let mut current_snapshot = record.snapshot.clone();
+        let mut current_patch = record.patch.clone();
+        let mut previous = record.previous;
+        let mut document = loop {
+            if let Some(snapshot) = current_snapshot {
+                break parse_json(&snapshot)?;
+            }
+            if let Some(patch) = current_patch {
+                patches.push(patch);
+            }
+
+            let previous_hash = previous.ok_or_else(|| {
+                Error::ContentError(format!(
+                    "JSON document at \"{}\" has no initial snapshot",
+                    safe_url
+                ))
+            })?;
+            let entry = self.fetch_register_entry(safe_url, previous_hash).await?;
+            let previous_record = self.fetch_json_record(&entry).await?;
+            current_snapshot = previous_record.snapshot;
+            current_patch = previous_record.patch;
+            previous = previous_record.previous;
+        };
+
+        for patch in patches.iter().rev() {
+            let json_patch: json_patch::Patch = serde_json::from_str(patch).map_err(|err| {
+                Error::ContentError(format!("Couldn't parse stored JSON Patch: {}", err))
+            })?;
+            json_patch::patch(&mut document, &json_patch).map_err(|err| {
+                Error::ContentError(format!("Couldn't apply stored JSON Patch: {}", err))
+            })?;
+        }
+
+        Ok((hash, record, document))
+    }
+
+    async fn fetch_json_record(&self, entry: &Url) -> Result<JsonRecord> {
+        let serialised_record = self.fetch_public_data(entry, None).await?;
+        rmp_serde::from_slice(&serialised_record)
+            .map_err(|err| Error::ContentError(format!("Couldn't parse JSON record: {:?}", err)))
+    }
+
+    async fn write_json_record(
+        &self,
+        url: &str,
+        record: &JsonRecord,
+        parents: BTreeSet<EntryHash>,
+    ) -> Result<EntryHash> {
+        let serialised_record = rmp_serde::to_vec_named(record).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise JSON record: {:?}", err))
+        })?;
+        let bytes = Bytes::from(serialised_record);
+        let record_xorurl = if Url::from_url(url)?.register_address()?.is_public() {
+            self.store_public_bytes(bytes, None, false).await?
+        } else {
+            self.store_private_bytes(bytes, None).await?
+        };
+
+        self.write_to_register(url, Url::from_xorurl(&record_xorurl)?, parents)
+            .await
+    }
+}
+
+fn serialise_json(value: &Value) -> Result<String> {
+    serde_json::to_string(value)
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise JSON: {:?}", err)))
+}
+
+fn parse_json(json: &str) -> Result<Value> {
+    serde_json::from_str(json)
+        .map_err(|err| Error::ContentError(format!("Couldn't parse JSON: {:?}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern};
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_json_create_get_patch() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe
+            .json_create(
+                &json!({ "name": "Alice", "address": { "city": "Paris" } }),
+                Some(false),
+            )
+            .await?;
+        assert!(Url::from_url(&xorurl)?.register_address()?.is_public());
+
+        let (version0, city) = retry_loop!(safe.json_get(&xorurl, "/address/city"));
+        assert_eq!(city, json!("Paris"));
+
+        let mut safe_url = Url::from_url(&xorurl)?;
+        safe_url.set_content_version(None);
+        let unversioned_url = safe_url.to_string();
+
+        let mut version = version0;
+        for i in 0..JSON_SNAPSHOT_INTERVAL + 1 {
+            let patch = json!([{ "op": "replace", "path": "/address/city", "value": format!("City {}", i) }]);
+            let new_version = safe.json_patch(&unversioned_url, &patch).await?;
+            let _ = retry_loop_for_pattern!(safe.json_get(&unversioned_url, ""), Ok((v, _)) if *v == new_version)?;
+            version = new_version;
+        }
+
+        let (latest_version, document) = safe.json_get(&unversioned_url, "").await?;
+        assert_eq!(latest_version, version);
+        assert_eq!(
+            document,
+            json!({ "name": "Alice", "address": { "city": format!("City {}", JSON_SNAPSHOT_INTERVAL) } })
+        );
+
+        // previous versions are still available
+        let (_, city) = safe.json_get(&xorurl, "/address/city").await?;
+        assert_eq!(city, json!("Paris"));
+
+        let invalid_patch = json!([{ "op": "remove", "path": "/missing" }]);
+        assert!(safe
+            .json_patch(&unversioned_url, &invalid_patch)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_create_default_scope() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        safe.set_private_by_default(true);
+        let xorurl = safe.json_create(&json!({ "name": "Bob" }), None).await?;
+        assert!(!Url::from_url(&xorurl)?.register_address()?.is_public());
+
+        let entries = retry_loop!(safe.register_read(&xorurl));
+        for (_, record_url) in entries.iter() {
+            assert!(!record_url.bytes_address()?.is_public());
+        }
+        let (_, name) = safe.json_get(&xorurl, "/name").await?;
+        assert_eq!(name, json!("Bob"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::register::EntryHash;
+use crate::{Error, Result, Safe, Url, UrlAddressExt, XorName, XorUrl};
+use bytes::Bytes;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Type tag to use for the Registers backing keyed Registers
+pub(crate) const KEYED_REGISTER_TYPE_TAG: u64 = 2_100;
+
+// Each entry of a keyed Register links to a Blob containing a serialised KeyedRecord,
+// which holds the new value of a key, a link to the previous value of the same key,
+// and an index with the entry of the latest value of each of the other keys.
+// Reading only the Register's current entries is thus enough to find any key.
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyedRecord {
+    key: String,
+    value: Vec<u8>,
+    previous: Option<EntryHash>,
+    index: BTreeMap<String, EntryHash>,
+}
+
+/// Keys of a keyed Register mapped to the entry of their latest value
+pub type KeyedRegisterIndex = BTreeMap<String, EntryHash>;
+
+impl Safe {
+    /// # Create a keyed Register
+    ///
+    /// A keyed Register maps string keys to their latest value,
+    /// keeping the history of the values of each key.
+    pub async fn kr_create(&self, name: Option<XorName>, private: bool) -> Result<XorUrl> {
+        self.register_create(name, KEYED_REGISTER_TYPE_TAG, private)
+            .await
+    }
+
+    /// Set the value of a key in a keyed Register, returning the hash of the entry written.
+    /// The record holding the value is stored in a Blob with the same scope as the Register.
+    pub async fn kr_put(&self, url: &str, key: &str, value: Bytes) -> Result<EntryHash> {
+        debug!("Setting key '{}' on keyed Register at {}", key, url);
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let (parents, mut index) = self.fetch_keyed_index(&safe_url).await?;
+
+        let record = KeyedRecord {
+            key: key.to_string(),
+            value: value.to_vec(),
+            previous: index.remove(key),
+            index,
+        };
+        let serialised_record = rmp_serde::to_vec_named(&record).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise keyed record: {:?}", err))
+        })?;
+        let bytes = Bytes::from(serialised_record);
+        let record_xorurl = if safe_url.register_address()?.is_public() {
+            self.store_public_bytes(bytes, None, false).await?
+        } else {
+            self.store_private_bytes(bytes, None).await?
+        };
+
+        self.write_to_register(url, Url::from_xorurl(&record_xorurl)?, parents)
+            .await
+    }
+
+    /// Get the latest value of a key in a keyed Register,
+    /// failing with `Error::EntryNotFound` if the key was never set
+    pub async fn kr_get(&self, url: &str, key: &str) -> Result<Bytes> {
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let (_, index) = self.fetch_keyed_index(&safe_url).await?;
+        let hash = index.get(key).ok_or_else(|| {
+            Error::EntryNotFound(format!("Key '{}' not found on keyed Register", key))
+        })?;
+
+        let (_, record) = self.fetch_keyed_record(&safe_url, *hash).await?;
+        Ok(Bytes::from(record.value))
+    }
+
+    /// Get all the values a key had in a keyed Register, most recent first
+    pub async fn kr_history(&self, url: &str, key: &str) -> Result<Vec<(EntryHash, Bytes)>> {
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let (_, index) = self.fetch_keyed_index(&safe_url).await?;
+
+        let mut history = vec![];
+        let mut next = index.get(key).copied();
+        while let Some(hash) = next {
+            let (_, record) = self.fetch_keyed_record(&safe_url, hash).await?;
+            next = record.previous;
+            history.push((hash, Bytes::from(record.value)));
+        }
+
+        Ok(history)
+    }
+
+    /// Get the keys set in a keyed Register, with the entry of their latest value
+    pub async fn kr_keys(&self, url: &str) -> Result<KeyedRegisterIndex> {
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let (_, index) = self.fetch_keyed_index(&safe_url).await?;
+        Ok(index)
+    }
+
+    // Build the index of latest values from the Register's current entries, also
+    // returning the hashes of such entries. If keys were concurrently set from different
+    // replicas, the value with the highest entry hash wins.
+    async fn fetch_keyed_index(
+        &self,
+        safe_url: &Url,
+    ) -> Result<(BTreeSet<EntryHash>, KeyedRegisterIndex)> {
+        let tips = match self.fetch_register_entries(safe_url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut index = KeyedRegisterIndex::new();
+        let mut tip_keys = KeyedRegisterIndex::new();
+        for (hash, entry) in tips.iter() {
+            let record = self.fetch_keyed_record_from_entry(entry).await?;
+            for (key, key_hash) in record.index.into_iter() {
+                let current = index.entry(key).or_insert(key_hash);
+                if key_hash > *current {
+                    *current = key_hash;
+                }
+            }
+            let current = tip_keys.entry(record.key).or_insert(*hash);
+            if *hash > *current {
+                *current = *hash;
+            }
+        }
+        // The current entries are always more recent than the values they index
+        index.extend(tip_keys);
+
+        let parents = tips.into_iter().map(|(hash, _)| hash).collect();
+        Ok((parents, index))
+    }
+
+    async fn fetch_keyed_record(
+        &self,
+        safe_url: &Url,
+        hash: EntryHash,
+    ) -> Result<(EntryHash, KeyedRecord)> {
+        let entry = self.fetch_register_entry(safe_url, hash).await?;
+        let record = self.fetch_keyed_record_from_entry(&entry).await?;
+        Ok((hash, record))
+    }
+
+    async fn fetch_keyed_record_from_entry(&self, entry: &Url) -> Result<KeyedRecord> {
+        let serialised_record = self.fetch_public_data(entry, None).await?;
+        rmp_serde::from_slice(&serialised_record)
+            .map_err(|err| Error::ContentError(format!("Couldn't parse keyed record: {:?}", err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_kr_put_get_history() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.kr_create(None, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let _ = safe.kr_put(&xorurl, "theme", Bytes::from("light")).await?;
+        let _ = retry_loop_for_pattern!(safe.kr_keys(&xorurl), Ok(keys) if keys.len() == 1)?;
+        let _ = safe.kr_put(&xorurl, "lang", Bytes::from("en")).await?;
+        let _ = retry_loop_for_pattern!(safe.kr_keys(&xorurl), Ok(keys) if keys.len() == 2)?;
+        let hash = safe.kr_put(&xorurl, "theme", Bytes::from("dark")).await?;
+        let _ = retry_loop_for_pattern!(safe.kr_keys(&xorurl), Ok(keys) if keys.get("theme") == Some(&hash))?;
+
+        assert_eq!(safe.kr_get(&xorurl, "theme").await?, Bytes::from("dark"));
+        assert_eq!(safe.kr_get(&xorurl, "lang").await?, Bytes::from("en"));
+        assert!(matches!(
+            safe.kr_get(&xorurl, "missing").await,
+            Err(Error::EntryNotFound(_))
+        ));
+
+        let history = safe.kr_history(&xorurl, "theme").await?;
+        let values: Vec<Bytes> = history.into_iter().map(|(_, value)| value).collect();
+        assert_eq!(values, vec![Bytes::from("dark"), Bytes::from("light")]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_kr_put_private() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.kr_create(None, true).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let _ = safe.kr_put(&xorurl, "secret", Bytes::from("value")).await?;
+        let entries = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if entries.len() == 1)?;
+        for (_, record_url) in entries.iter() {
+            assert!(!record_url.bytes_address()?.is_public());
+        }
+        assert_eq!(safe.kr_get(&xorurl, "secret").await?, Bytes::from("value"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    obligations::ObligationKind,
+    register::{Entry, EntryHash},
+};
+use crate::{Error, PublicKey, Result, Safe, Url, UrlAddressExt};
+use bytes::Bytes;
+use chrono::Utc;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, time::Duration};
+
+// Each entry of a lease Register links to a Blob containing a serialised LeaseRecord.
+// The current state of the lease is given by the record with the highest fencing
+// token among the Register's current entries, ties broken by the lowest entry hash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LeaseRecord {
+    holder: PublicKey,
+    fencing_token: u64,
+    expires_at: i64,
+    released: bool,
+}
+
+impl LeaseRecord {
+    fn is_live(&self, now: i64) -> bool {
+        !self.released && self.expires_at > now
+    }
+}
+
+/// An advisory lease acquired over a shared resource
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lease {
+    /// URL of the Register backing the lease
+    pub url: String,
+    /// Public key of the lease holder
+    pub holder: PublicKey,
+    /// Monotonically increasing token, to be presented to the shared resource with
+    /// each write so it can reject writers holding a stale lease
+    pub fencing_token: u64,
+    /// Expiry time of the lease, as seconds since the Unix epoch
+    pub expires_at: i64,
+    entry_hash: EntryHash,
+}
+
+impl Safe {
+    /// Acquire an advisory lease, for the given TTL, on the Register found at the provided URL.
+    /// The Register needs to be writable by all cooperating writers.
+    /// It fails with `Error::LeaseUnavailable` if there is a live lease held on it.
+    pub async fn lease_acquire(&self, url: &str, ttl: Duration) -> Result<Lease> {
+        info!("Acquiring lease on {}", url);
+        let holder = self.get_my_keypair()?.public_key();
+        let now = Utc::now().timestamp();
+
+        let (tips, current) = self.fetch_lease_state(url).await?;
+        let fencing_token = match &current {
+            Some((_, record)) if record.is_live(now) => {
+                return Err(Error::LeaseUnavailable(format!(
+                    "Lease on \"{}\" is currently held by {:?} (fencing token {}) until {}",
+                    url, record.holder, record.fencing_token, record.expires_at
+                )))
+            }
+            Some((_, record)) => record.fencing_token + 1,
+            None => 1,
+        };
+
+        let record = LeaseRecord {
+            holder,
+            fencing_token,
+            expires_at: now + ttl.as_secs() as i64,
+            released: false,
+        };
+        let entry_hash = self.write_lease_record(url, &record, tips).await?;
+
+        // Another writer may have concurrently acquired the lease, in which case
+        // the record elected by the convention is the one which holds it
+        let (_, elected) = self.fetch_lease_state(url).await?;
+        match elected {
+            Some((hash, _)) if hash != entry_hash => Err(Error::LeaseUnavailable(format!(
+                "Lease on \"{}\" was concurrently acquired by another writer",
+                url
+            ))),
+            _ => {
+                self.obligations
+                    .track(url, ObligationKind::Lease, record.expires_at);
+                Ok(Lease {
+                    url: url.to_string(),
+                    holder,
+                    fencing_token,
+                    expires_at: record.expires_at,
+                    entry_hash,
+                })
+            }
+        }
+    }
+
+    /// Extend the expiry of a lease currently held, keeping its fencing token
+    pub async fn lease_renew(&self, lease: &Lease, ttl: Duration) -> Result<Lease> {
+        debug!("Renewing lease on {}", lease.url);
+        let tips = self.check_lease_held(lease).await?;
+
+        let record = LeaseRecord {
+            holder: lease.holder,
+            fencing_token: lease.fencing_token,
+            expires_at: Utc::now().timestamp() + ttl.as_secs() as i64,
+            released: false,
+        };
+        let entry_hash = self.write_lease_record(&lease.url, &record, tips).await?;
+        self.obligations
+            .track(&lease.url, ObligationKind::Lease, record.expires_at);
+
+        Ok(Lease {
+            expires_at: record.expires_at,
+            entry_hash,
+            ..lease.clone()
+        })
+    }
+
+    /// Release a lease currently held so other writers can acquire it
+    pub async fn lease_release(&self, lease: Lease) -> Result<()> {
+        debug!("Releasing lease on {}", lease.url);
+        let tips = self.check_lease_held(&lease).await?;
+
+        let record = LeaseRecord {
+            holder: lease.holder,
+            fencing_token: lease.fencing_token,
+            expires_at: lease.expires_at,
+            released: true,
+        };
+        let _ = self.write_lease_record(&lease.url, &record, tips).await?;
+        self.obligations.forget(&lease.url, &ObligationKind::Lease);
+
+        Ok(())
+    }
+
+    // Private helper to make sure the lease is still the one in effect,
+    // returning the current entries of the lease Register
+    async fn check_lease_held(&self, lease: &Lease) -> Result<BTreeSet<EntryHash>> {
+        let (tips, current) = self.fetch_lease_state(&lease.url).await?;
+        match current {
+            Some((hash, record))
+                if hash == lease.entry_hash && record.is_live(Utc::now().timestamp()) =>
+            {
+                Ok(tips)
+            }
+            _ => Err(Error::LeaseUnavailable(format!(
+                "Lease on \"{}\" with fencing token {} is no longer held",
+                lease.url, lease.fencing_token
+            ))),
+        }
+    }
+
+    // Private helper to read the lease Register, returning its current entries
+    // as well as the record which is in effect according to the convention
+    async fn fetch_lease_state(
+        &self,
+        url: &str,
+    ) -> Result<(BTreeSet<EntryHash>, Option<(EntryHash, LeaseRecord)>)> {
+        let entries = self.register_read(url).await?;
+
+        let mut current: Option<(EntryHash, LeaseRecord)> = None;
+        for (hash, entry) in entries.iter() {
+            let record = self.fetch_lease_record(entry).await?;
+            let is_newer = match &current {
+                Some((_, curr)) => record.fencing_token > curr.fencing_token,
+                None => true,
+            };
+            if is_newer {
+                current = Some((*hash, record));
+            }
+        }
+
+        let tips = entries.into_iter().map(|(hash, _)| hash).collect();
+        Ok((tips, current))
+    }
+
+    async fn fetch_lease_record(&self, entry: &Entry) -> Result<LeaseRecord> {
+        let serialised_record = self.fetch_public_data(entry, None).await?;
+        rmp_serde::from_slice(&serialised_record)
+            .map_err(|err| Error::ContentError(format!("Couldn't parse lease record: {:?}", err)))
+    }
+
+    async fn write_lease_record(
+        &self,
+        url: &str,
+        record: &LeaseRecord,
+        parents: BTreeSet<EntryHash>,
+    ) -> Result<EntryHash> {
+        let serialised_record = rmp_serde::to_vec_named(record).map_err(|err| {
+            Error::Serialisation(format!(
+                "Couldn't serialise the lease record '{:?}': {:?}",
+                record, err
+            ))
+        })?;
+
+        // the record is stored with the same scope as the lease Register
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let bytes = Bytes::from(serialised_record);
+        let xorurl = if safe_url.register_address()?.is_public() {
+            self.store_public_bytes(bytes, None, false).await?
+        } else {
+            self.store_private_bytes(bytes, None).await?
+        };
+
+        self.write_to_register(url, Url::from_xorurl(&xorurl)?, parents)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern};
+    use anyhow::{anyhow, Result};
+
+    #[tokio::test]
+    async fn test_lease_acquire_and_release() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let lease = safe.lease_acquire(&xorurl, Duration::from_secs(60)).await?;
+        assert_eq!(lease.fencing_token, 1);
+        assert_eq!(lease.holder, safe.get_my_keypair()?.public_key());
+
+        let _ = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(v) if !v.is_empty())?;
+        match safe.lease_acquire(&xorurl, Duration::from_secs(60)).await {
+            Err(Error::LeaseUnavailable(_)) => {}
+            other => return Err(anyhow!("Unexpected result: {:?}", other)),
+        }
+
+        let renewed = safe.lease_renew(&lease, Duration::from_secs(120)).await?;
+        assert_eq!(renewed.fencing_token, lease.fencing_token);
+        assert!(renewed.expires_at >= lease.expires_at);
+
+        let _ = retry_loop_for_pattern!(safe.fetch_lease_state(&xorurl), Ok((_, Some((hash, _)))) if *hash == renewed.entry_hash)?;
+        safe.lease_release(renewed).await?;
+
+        let _ = retry_loop_for_pattern!(safe.fetch_lease_state(&xorurl), Ok((_, Some((_, record)))) if record.released)?;
+        let lease = safe.lease_acquire(&xorurl, Duration::from_secs(60)).await?;
+        assert_eq!(lease.fencing_token, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lease_private_register() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, true).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let lease = safe.lease_acquire(&xorurl, Duration::from_secs(60)).await?;
+        let entries = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(v) if !v.is_empty())?;
+        // the lease records of a private Register are not published
+        for (hash, entry) in entries.iter() {
+            assert_eq!(*hash, lease.entry_hash);
+            assert!(!entry.bytes_address()?.is_public());
+        }
+
+        Ok(())
+    }
+}
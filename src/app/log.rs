@@ -0,0 +1,316 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::register::EntryHash;
+use crate::{Error, Result, Safe, Url, UrlAddressExt, XorName};
+use bytes::Bytes;
+use futures::{stream, Stream};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+};
+
+// A record of a Log, stored in a Blob each entry of the Register links to
+#[derive(Debug, Serialize, Deserialize)]
+struct LogRecord {
+    seq: u64,
+    // Hash of the entry of the previous record, none for the first record
+    prev: Option<EntryHash>,
+    payload: Vec<u8>,
+}
+
+/// An append-only log of records over a Register.
+///
+/// Records are numbered from zero in the order they were appended, and each of them
+/// supersedes the previous one, so the Register's history is a single chain of entries.
+/// Each record is stored in a Blob, with the same scope as the Register's, which the
+/// Register entry links to, together with its sequence number and the hash of the
+/// previous record's entry. Appends fail with `Error::ConcurrentWrite` when the Register
+/// doesn't have a single current entry, e.g. when two clients appended concurrently, since
+/// the history would then no longer be linear.
+#[derive(Clone)]
+pub struct Log {
+    safe: Safe,
+    url: Url,
+    // Hashes of the entries of the records read so far, by their sequence number,
+    // shared by the clones of the Log
+    index: Arc<Mutex<Vec<EntryHash>>>,
+}
+
+impl Log {
+    /// Create a Register on the network to hold a Log
+    pub async fn create(
+        safe: &Safe,
+        name: Option<XorName>,
+        type_tag: u64,
+        private: bool,
+    ) -> Result<Self> {
+        let xorurl = safe.register_create(name, type_tag, private).await?;
+        Self::open(safe, &xorurl).await
+    }
+
+    /// Use the existing Register at the given URL as a Log
+    pub async fn open(safe: &Safe, url: &str) -> Result<Self> {
+        let (mut url, _) = safe.parse_and_resolve_url(url).await?;
+        url.set_content_version(None);
+        let _ = url.register_address()?;
+
+        Ok(Self {
+            safe: safe.clone(),
+            url,
+            index: Arc::new(Mutex::new(vec![])),
+        })
+    }
+
+    /// URL of the underlying Register
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Append a record to the Log, returning its sequence number
+    pub async fn append(&self, payload: Bytes) -> Result<u64> {
+        let tip = self.tip().await?;
+        let (seq, prev) = match &tip {
+            Some((hash, record)) => (record.seq + 1, Some(*hash)),
+            None => (0, None),
+        };
+        let serialised_record = rmp_serde::to_vec_named(&LogRecord {
+            seq,
+            prev,
+            payload: payload.to_vec(),
+        })
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise Log record: {:?}", err)))?;
+
+        let bytes = Bytes::from(serialised_record);
+        let xorurl = if self.url.register_address()?.is_public() {
+            self.safe.store_public_bytes(bytes, None, false).await?
+        } else {
+            self.safe.store_private_bytes(bytes, None).await?
+        };
+        let hash = self
+            .safe
+            .write_to_register_cas(
+                &self.url.to_string(),
+                Url::from_xorurl(&xorurl)?,
+                prev.into_iter().collect(),
+            )
+            .await?;
+
+        if let Ok(mut index) = self.index.lock() {
+            if index.len() as u64 == seq {
+                index.push(hash);
+            }
+        }
+        debug!("Record {} appended to Log at {}", seq, self.url);
+
+        Ok(seq)
+    }
+
+    /// Number of records of the Log
+    pub async fn len(&self) -> Result<u64> {
+        Ok(self.tip().await?.map_or(0, |(_, record)| record.seq + 1))
+    }
+
+    /// Whether the Log has no records
+    pub async fn is_empty(&self) -> Result<bool> {
+        Ok(self.len().await? == 0)
+    }
+
+    /// Read the payload of the record with the given sequence number
+    pub async fn get(&self, seq: u64) -> Result<Bytes> {
+        let hash = match self.indexed(seq) {
+            Some(hash) => hash,
+            None => {
+                let len = self.index_up_to_tip().await?;
+                if seq >= len {
+                    return Err(Error::EntryNotFound(format!(
+                        "Log at \"{}\" has {} records, there's no record {}",
+                        self.url, len, seq
+                    )));
+                }
+                self.indexed(seq).ok_or_else(|| {
+                    Error::EntryNotFound(format!("Record {} of Log at \"{}\"", seq, self.url))
+                })?
+            }
+        };
+
+        let record = self.fetch_record(hash).await?;
+        Ok(Bytes::from(record.payload))
+    }
+
+    /// # Read the records of the Log from the given sequence number
+    ///
+    /// Returns a stream yielding the sequence number and payload of each record, from the
+    /// given one up to the last record the Log had when this was called.
+    pub async fn iter_from(&self, seq: u64) -> Result<impl Stream<Item = Result<(u64, Bytes)>>> {
+        let len = self.index_up_to_tip().await?;
+        let log = self.clone();
+
+        Ok(stream::unfold(seq, move |seq| {
+            let log = log.clone();
+            async move {
+                if seq >= len {
+                    return None;
+                }
+                Some((log.get(seq).await.map(|payload| (seq, payload)), seq + 1))
+            }
+        }))
+    }
+
+    // Hash of the entry of a record if it was already indexed
+    fn indexed(&self, seq: u64) -> Option<EntryHash> {
+        self.index
+            .lock()
+            .ok()
+            .and_then(|index| index.get(seq as usize).copied())
+    }
+
+    // The current entry of the Register and its record, if the Log has any
+    async fn tip(&self) -> Result<Option<(EntryHash, LogRecord)>> {
+        let entries = match self.safe.fetch_register_entries(&self.url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+        if entries.len() > 1 {
+            return Err(Error::ConcurrentWrite(format!(
+                "Log at \"{}\" is not linear, it has {} current entries: {:?}",
+                self.url,
+                entries.len(),
+                entries
+                    .iter()
+                    .map(|(hash, _)| hash)
+                    .map(hex::encode)
+                    .collect::<Vec<_>>()
+            )));
+        }
+
+        match entries.into_iter().next() {
+            Some((hash, _)) => Ok(Some((hash, self.fetch_record(hash).await?))),
+            None => Ok(None),
+        }
+    }
+
+    // Index the hashes of all the records, walking the chain back from the current entry
+    // down to the records already indexed, returning the number of records
+    async fn index_up_to_tip(&self) -> Result<u64> {
+        let (tip_hash, tip) = match self.tip().await? {
+            Some(tip) => tip,
+            None => return Ok(0),
+        };
+        let len = tip.seq + 1;
+        let indexed = self.index.lock().map_or(0, |index| index.len()) as u64;
+
+        let mut hashes = vec![tip_hash];
+        let mut record = tip;
+        while record.seq > indexed {
+            let prev = record.prev.ok_or_else(|| {
+                Error::ContentError(format!(
+                    "Record {} of Log at \"{}\" doesn't link to the previous one",
+                    record.seq, self.url
+                ))
+            })?;
+            let prev_record = self.fetch_record(prev).await?;
+            if prev_record.seq + 1 != record.seq {
+                return Err(Error::ContentError(format!(
+                    "Record {} of Log at \"{}\" links to record {}",
+                    record.seq, self.url, prev_record.seq
+                )));
+            }
+            hashes.push(prev);
+            record = prev_record;
+        }
+
+        if let Ok(mut index) = self.index.lock() {
+            // the hashes collected go from the tip down to the first record not indexed
+            let first = len - hashes.len() as u64;
+            if index.len() as u64 == first {
+                index.extend(hashes.into_iter().rev());
+            }
+        }
+
+        Ok(len)
+    }
+
+    async fn fetch_record(&self, hash: EntryHash) -> Result<LogRecord> {
+        let entry = self.safe.fetch_register_entry(&self.url, hash).await?;
+        let serialised_record = self.safe.fetch_public_data(&entry, None).await?;
+        rmp_serde::from_slice(&serialised_record).map_err(|err| {
+            Error::DecodeError(format!(
+                "Couldn't decode Log record of Register entry {}: {:?}",
+                hex::encode(hash),
+                err
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop_for_pattern};
+    use anyhow::{anyhow, Result};
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_log_append_and_get() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let log = Log::create(&safe, None, 25_000, false).await?;
+        assert!(log.is_empty().await?);
+
+        for (expected_seq, payload) in ["first", "second", "third"].iter().enumerate() {
+            let seq = log.append(Bytes::from(*payload)).await?;
+            assert_eq!(seq, expected_seq as u64);
+            let _ = retry_loop_for_pattern!(log.len(), Ok(len) if *len == seq + 1)?;
+        }
+
+        assert_eq!(log.get(1).await?, Bytes::from("second"));
+        assert!(matches!(log.get(3).await, Err(Error::EntryNotFound(_))));
+
+        // a new handle on the same Log walks the chain of records
+        let other = Log::open(&safe, &log.url().to_string()).await?;
+        let records: Vec<(u64, Bytes)> = other
+            .iter_from(1)
+            .await?
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<crate::Result<_>>()?;
+        assert_eq!(
+            records,
+            vec![(1, Bytes::from("second")), (2, Bytes::from("third"))]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_log_rejects_fork() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let log = Log::create(&safe, None, 25_000, false).await?;
+        let _ = log.append(Bytes::from("first")).await?;
+
+        // a branch written concurrently makes the history no longer linear
+        let _ = safe
+            .write_to_register(
+                &log.url().to_string(),
+                Url::from_url("safe://branch")?,
+                BTreeSet::new(),
+            )
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_read(&log.url().to_string()), Ok(entries) if entries.len() == 2)?;
+
+        match log.append(Bytes::from("second")).await {
+            Err(Error::ConcurrentWrite(_)) => Ok(()),
+            other => Err(anyhow!("Unexpected result: {:?}", other)),
+        }
+    }
+}
@@ -0,0 +1,653 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    encryption::{decrypt_payload, encrypt_payload, SymmetricKey},
+    helpers::gen_timestamp_secs,
+};
+use crate::{Error, PublicKey, Result, Safe, Url, XorUrl};
+use bytes::Bytes;
+use chrono::DateTime;
+use log::{debug, info};
+use multibase::Base;
+use serde::{Deserialize, Serialize};
+
+// Prefix of the relay deposits which are mail messages, which tells
+// them apart from any other data deposited on the same inbox
+const MAIL_PAYLOAD_PREFIX: &[u8] = b"sn_api-mail:";
+
+// Maximum length of the lines of base64 encoded MIME content
+const MIME_LINE_LEN: usize = 76;
+
+/// A mail message, exchanged through the inboxes of the relay convention,
+/// see `Safe::mail_send`. Its headers follow those of RFC 5322 so it can be
+/// converted from and to MIME, see `mail_to_mime` and `mail_from_mime`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MailMessage {
+    /// Unique identifier of the message, without angle brackets
+    pub message_id: String,
+    /// Address of the sender as displayed, e.g. `Alice <safe://alice>`
+    pub from: String,
+    pub to: Vec<String>,
+    pub subject: String,
+    /// Time the message was written, in RFC3339 format
+    pub date: String,
+    /// Identifier of the message this one replies to
+    pub in_reply_to: Option<String>,
+    /// Identifiers of the messages of the thread, the oldest first
+    pub references: Vec<String>,
+    pub body: String,
+    /// Attachments, stored as Blobs linked from the message
+    pub attachments: Vec<MailAttachment>,
+}
+
+impl MailMessage {
+    /// A new message with a unique identifier, dated now
+    pub fn new(from: &str, to: Vec<String>, subject: &str, body: &str) -> Self {
+        Self {
+            message_id: gen_message_id(),
+            from: from.to_string(),
+            to,
+            subject: subject.to_string(),
+            date: gen_timestamp_secs(),
+            in_reply_to: None,
+            references: vec![],
+            body: body.to_string(),
+            attachments: vec![],
+        }
+    }
+
+    /// A reply to this message, addressed to its sender, on the same thread
+    pub fn reply(&self, from: &str, body: &str) -> Self {
+        let subject = if self.subject.to_lowercase().starts_with("re:") {
+            self.subject.clone()
+        } else {
+            format!("Re: {}", self.subject)
+        };
+        let mut references = self.references.clone();
+        references.push(self.message_id.clone());
+
+        Self {
+            in_reply_to: Some(self.message_id.clone()),
+            references,
+            ..Self::new(from, vec![self.from.clone()], &subject, body)
+        }
+    }
+}
+
+/// An attachment of a mail message, stored encrypted in a Blob with a key only
+/// known to the recipients of the message, see `Safe::mail_fetch_attachment`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MailAttachment {
+    pub filename: String,
+    pub media_type: String,
+    /// Size of the attachment, in bytes
+    pub size: u64,
+    /// XOR-URL of the Blob the attachment is stored in
+    pub link: XorUrl,
+    key: SymmetricKey,
+}
+
+/// Content of an attachment, to be attached to a message, or as fetched or imported
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentContent {
+    pub filename: String,
+    pub media_type: String,
+    pub data: Bytes,
+}
+
+/// A mail message received, with the public key of its sender,
+/// who signed the deposit of the message on the inbox
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedMail {
+    pub sender: PublicKey,
+    pub message: MailMessage,
+}
+
+impl Safe {
+    /// # Send a mail message
+    ///
+    /// The message is deposited on the inbox of the recipient, following the relay
+    /// convention, see `relay_deposit`, thus it's signed by the sender and encrypted to
+    /// the recipient's key. Attachments are stored in Blobs, each encrypted with a random
+    /// key included in the message. Returns the message sent, linking to its attachments.
+    pub async fn mail_send(
+        &self,
+        recipient: &bls::PublicKey,
+        mut message: MailMessage,
+        attachments: Vec<AttachmentContent>,
+    ) -> Result<MailMessage> {
+        info!(
+            "Sending mail message {} with {} attachments",
+            message.message_id,
+            attachments.len()
+        );
+        for attachment in attachments {
+            let key: SymmetricKey = rand::random();
+            let ciphertext = encrypt_payload(&self.encryption_policy, &key, &attachment.data)?;
+            let link = self
+                .store_public_bytes(Bytes::from(ciphertext), None, false)
+                .await?;
+            message.attachments.push(MailAttachment {
+                filename: attachment.filename,
+                media_type: attachment.media_type,
+                size: attachment.data.len() as u64,
+                link,
+                key,
+            });
+        }
+
+        let mut payload = MAIL_PAYLOAD_PREFIX.to_vec();
+        payload.extend(rmp_serde::to_vec_named(&message).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise mail message: {:?}", err))
+        })?);
+        let _ = self.relay_deposit(recipient, Bytes::from(payload)).await?;
+
+        Ok(message)
+    }
+
+    /// # Receive the mail messages sent to a recipient
+    ///
+    /// Drains the recipient's inbox, see `relay_drain`, returning the mail messages found,
+    /// oldest first. Since the inbox is drained, any other data deposited on it which is
+    /// not a mail message is skipped and lost, thus `relay_drain` is to be used instead
+    /// for inboxes shared with other formats.
+    pub async fn mail_receive(&self, recipient: &bls::SecretKey) -> Result<Vec<ReceivedMail>> {
+        let mut received = vec![];
+        for deposit in self.relay_drain(recipient).await? {
+            let message = match deposit.payload.strip_prefix(MAIL_PAYLOAD_PREFIX) {
+                Some(serialised_message) => {
+                    rmp_serde::from_slice::<MailMessage>(serialised_message)
+                }
+                None => {
+                    debug!("Skipping deposit which is not a mail message");
+                    continue;
+                }
+            };
+            match message {
+                Ok(message) => received.push(ReceivedMail {
+                    sender: deposit.sender,
+                    message,
+                }),
+                Err(err) => debug!("Skipping invalid mail message: {:?}", err),
+            }
+        }
+
+        Ok(received)
+    }
+
+    /// Fetch and decrypt an attachment of a mail message
+    pub async fn mail_fetch_attachment(&self, attachment: &MailAttachment) -> Result<Bytes> {
+        let ciphertext = self
+            .fetch_public_data(&Url::from_xorurl(&attachment.link)?, None)
+            .await?;
+        let data = decrypt_payload(&self.encryption_policy, &attachment.key, &ciphertext)?;
+        Ok(Bytes::from(data))
+    }
+
+    /// # Export a mail message as MIME
+    ///
+    /// Its attachments are fetched and included in the MIME message, see `mail_to_mime`.
+    pub async fn mail_export_mime(&self, message: &MailMessage) -> Result<String> {
+        let mut attachments = Vec::with_capacity(message.attachments.len());
+        for attachment in message.attachments.iter() {
+            attachments.push(AttachmentContent {
+                filename: attachment.filename.clone(),
+                media_type: attachment.media_type.clone(),
+                data: self.mail_fetch_attachment(attachment).await?,
+            });
+        }
+
+        Ok(mail_to_mime(message, &attachments))
+    }
+}
+
+/// Render a mail message, with the given attachments, as a MIME message (RFC 2045),
+/// the attachments of the message itself being ignored. Its text and attachments are
+/// base64 encoded, and a `multipart/mixed` message is rendered if there are attachments.
+pub fn mail_to_mime(message: &MailMessage, attachments: &[AttachmentContent]) -> String {
+    let date = DateTime::parse_from_rfc3339(&message.date)
+        .map(|date| date.to_rfc2822())
+        .unwrap_or_else(|_| message.date.clone());
+
+    let mut mime = format!("Message-ID: <{}>\r\n", message.message_id);
+    mime.push_str(&format!("Date: {}\r\n", date));
+    mime.push_str(&format!("From: {}\r\n", encode_header(&message.from)));
+    if !message.to.is_empty() {
+        let to: Vec<String> = message.to.iter().map(|to| encode_header(to)).collect();
+        mime.push_str(&format!("To: {}\r\n", to.join(", ")));
+    }
+    mime.push_str(&format!("Subject: {}\r\n", encode_header(&message.subject)));
+    if let Some(in_reply_to) = &message.in_reply_to {
+        mime.push_str(&format!("In-Reply-To: <{}>\r\n", in_reply_to));
+    }
+    if !message.references.is_empty() {
+        let references: Vec<String> = message
+            .references
+            .iter()
+            .map(|id| format!("<{}>", id))
+            .collect();
+        mime.push_str(&format!("References: {}\r\n", references.join(" ")));
+    }
+    mime.push_str("MIME-Version: 1.0\r\n");
+
+    let text_part = format!(
+        "Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}",
+        encode_base64_lines(message.body.as_bytes())
+    );
+    if attachments.is_empty() {
+        mime.push_str(&text_part);
+        return mime;
+    }
+
+    let boundary = format!("sn_api-{}", hex::encode(rand::random::<[u8; 12]>()));
+    mime.push_str(&format!(
+        "Content-Type: multipart/mixed; boundary=\"{}\"\r\n\r\n",
+        boundary
+    ));
+    mime.push_str(&format!("--{}\r\n{}", boundary, text_part));
+    for attachment in attachments.iter() {
+        let filename = encode_header(&attachment.filename).replace('"', "'");
+        mime.push_str(&format!("--{}\r\n", boundary));
+        mime.push_str(&format!(
+            "Content-Type: {}; name=\"{}\"\r\n",
+            attachment.media_type, filename
+        ));
+        mime.push_str(&format!(
+            "Content-Disposition: attachment; filename=\"{}\"\r\n",
+            filename
+        ));
+        mime.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+        mime.push_str(&encode_base64_lines(&attachment.data));
+    }
+    mime.push_str(&format!("--{}--\r\n", boundary));
+
+    mime
+}
+
+/// Parse a MIME message (RFC 2045) into a mail message and its attachments, which are
+/// to be attached to the message when sending it. The first text part which is not an
+/// attachment is taken as the body of the message. Parts encoded as base64 and
+/// quoted-printable are decoded, and headers encoded as RFC 2047 words as well.
+pub fn mail_from_mime(mime: &str) -> Result<(MailMessage, Vec<AttachmentContent>)> {
+    let mime = mime.replace("\r\n", "\n");
+    let (headers, body) = split_headers(&mime)?;
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    };
+    let ids = |value: &str| -> Vec<String> {
+        value
+            .split_whitespace()
+            .map(|id| id.trim_start_matches('<').trim_end_matches('>').to_string())
+            .filter(|id| !id.is_empty())
+            .collect()
+    };
+
+    let mut message = MailMessage::new(
+        &decode_header(header("from").unwrap_or_default()),
+        header("to")
+            .map(|to| {
+                decode_header(to)
+                    .split(',')
+                    .map(|to| to.trim().to_string())
+                    .filter(|to| !to.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        &decode_header(header("subject").unwrap_or_default()),
+        "",
+    );
+    if let Some(id) = header("message-id").and_then(|id| ids(id).into_iter().next()) {
+        message.message_id = id;
+    }
+    if let Some(date) = header("date").and_then(|date| DateTime::parse_from_rfc2822(date).ok()) {
+        message.date = date.to_rfc3339();
+    }
+    message.in_reply_to = header("in-reply-to").and_then(|id| ids(id).into_iter().next());
+    message.references = header("references").map(ids).unwrap_or_default();
+
+    let mut body_text = None;
+    let mut attachments = vec![];
+    let body = body.strip_suffix('\n').unwrap_or(body);
+    parse_part(&headers, body, &mut body_text, &mut attachments)?;
+    message.body = body_text.unwrap_or_default();
+
+    Ok((message, attachments))
+}
+
+// Parse a part of a MIME message, recursing into multipart parts
+fn parse_part(
+    headers: &[(String, String)],
+    body: &str,
+    body_text: &mut Option<String>,
+    attachments: &mut Vec<AttachmentContent>,
+) -> Result<()> {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    };
+    let content_type = header("content-type").unwrap_or("text/plain");
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    if media_type.starts_with("multipart/") {
+        let boundary = header_param(content_type, "boundary").ok_or_else(|| {
+            Error::InvalidInput("MIME multipart content has no boundary".to_string())
+        })?;
+        let delimiter = format!("--{}", boundary);
+        for part in body.split(&delimiter).skip(1) {
+            if part.starts_with("--") {
+                break;
+            }
+            // the line breaks around the delimiters belong to them
+            let part = part.strip_prefix('\n').unwrap_or(part);
+            let part = part.strip_suffix('\n').unwrap_or(part);
+            let (part_headers, part_body) = split_headers(part)?;
+            parse_part(&part_headers, part_body, body_text, attachments)?;
+        }
+        return Ok(());
+    }
+
+    let data = match header("content-transfer-encoding").map(|encoding| encoding.to_lowercase()) {
+        Some(encoding) if encoding == "base64" => decode_base64(body)?,
+        Some(encoding) if encoding == "quoted-printable" => decode_quoted_printable(body),
+        _ => body.as_bytes().to_vec(),
+    };
+    let disposition = header("content-disposition").unwrap_or_default();
+    let filename = header_param(disposition, "filename")
+        .or_else(|| header_param(content_type, "name"))
+        .map(|filename| decode_header(&filename));
+
+    match filename {
+        Some(filename) => attachments.push(AttachmentContent {
+            filename,
+            media_type,
+            data: Bytes::from(data),
+        }),
+        None if disposition.to_lowercase().starts_with("attachment") => {
+            attachments.push(AttachmentContent {
+                filename: "attachment".to_string(),
+                media_type,
+                data: Bytes::from(data),
+            })
+        }
+        None if media_type.starts_with("text/") && body_text.is_none() => {
+            *body_text = Some(String::from_utf8_lossy(&data).to_string());
+        }
+        None => debug!("Skipping MIME part of type {}", media_type),
+    }
+
+    Ok(())
+}
+
+// Split the headers, with their names lowercased, from the body of a MIME part
+fn split_headers(part: &str) -> Result<(Vec<(String, String)>, &str)> {
+    let (header_lines, body) = match part.find("\n\n") {
+        Some(end) => (&part[..end], &part[end + 2..]),
+        None => (part.trim_end_matches('\n'), ""),
+    };
+
+    let mut headers: Vec<(String, String)> = vec![];
+    for line in header_lines.lines() {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            // a folded line continues the previous header
+            match headers.last_mut() {
+                Some((_, value)) => {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+                None => {
+                    return Err(Error::InvalidInput(format!(
+                        "Invalid MIME header line: '{}'",
+                        line
+                    )))
+                }
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        } else {
+            return Err(Error::InvalidInput(format!(
+                "Invalid MIME header line: '{}'",
+                line
+            )));
+        }
+    }
+
+    Ok((headers, body))
+}
+
+// Value of a parameter of a header, e.g. the boundary of a `Content-Type`
+fn header_param(value: &str, param: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        if name.trim().eq_ignore_ascii_case(param) {
+            Some(value.trim().trim_matches('"').to_string())
+        } else {
+            None
+        }
+    })
+}
+
+// Encode a header value as an RFC 2047 word if it's not plain ASCII
+fn encode_header(value: &str) -> String {
+    if value.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+        value.to_string()
+    } else {
+        format!("=?utf-8?B?{}?=", encode_base64(value.as_bytes()))
+    }
+}
+
+// Decode the RFC 2047 words of a header value, only UTF-8 and ASCII charsets are supported
+fn decode_header(value: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("=?") {
+        let word = &rest[start + 2..];
+        let parts: Vec<&str> = word.splitn(3, '?').collect();
+        let end = parts.get(2).and_then(|text| text.find("?="));
+        let (charset, encoding, text, end) = match (parts.as_slice(), end) {
+            ([charset, encoding, text], Some(end)) => (*charset, *encoding, &text[..end], end),
+            _ => break,
+        };
+        let bytes = match encoding.to_lowercase().as_str() {
+            "b" => decode_base64(text).ok(),
+            "q" => Some(decode_quoted_printable(&text.replace('_', " "))),
+            _ => None,
+        };
+        let supported =
+            charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("us-ascii");
+        let consumed = start + 2 + charset.len() + encoding.len() + 2 + end + 2;
+        match bytes {
+            Some(bytes) if supported => {
+                // whitespace between adjacent encoded words is not part of the value
+                let before = &rest[..start];
+                if !before.trim().is_empty() || decoded.is_empty() {
+                    decoded.push_str(before);
+                }
+                decoded.push_str(&String::from_utf8_lossy(&bytes));
+            }
+            _ => decoded.push_str(&rest[..consumed]),
+        }
+        rest = &rest[consumed..];
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    // the first character is the multibase prefix of the encoding
+    multibase::encode(Base::Base64Pad, data)[1..].to_string()
+}
+
+fn encode_base64_lines(data: &[u8]) -> String {
+    let encoded = encode_base64(data);
+    let mut lines = String::with_capacity(encoded.len() + encoded.len() / MIME_LINE_LEN * 2 + 2);
+    for line in encoded.as_bytes().chunks(MIME_LINE_LEN) {
+        lines.push_str(&String::from_utf8_lossy(line));
+        lines.push_str("\r\n");
+    }
+    lines
+}
+
+fn decode_base64(encoded: &str) -> Result<Vec<u8>> {
+    let encoded: String = encoded.chars().filter(|c| !c.is_whitespace()).collect();
+    let (_, data) = multibase::decode(format!("M{}", encoded))
+        .map_err(|err| Error::InvalidInput(format!("Invalid base64 MIME content: {}", err)))?;
+    Ok(data)
+}
+
+fn decode_quoted_printable(encoded: &str) -> Vec<u8> {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'=' {
+            if bytes.get(i + 1) == Some(&b'\n') {
+                // soft line break
+                i += 2;
+                continue;
+            }
+            let hex_digits = bytes.get(i + 1..i + 3).and_then(|hex_digits| {
+                std::str::from_utf8(hex_digits)
+                    .ok()
+                    .and_then(|hex_digits| u8::from_str_radix(hex_digits, 16).ok())
+            });
+            if let Some(byte) = hex_digits {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    decoded
+}
+
+fn gen_message_id() -> String {
+    format!("{}@safe", hex::encode(rand::random::<[u8; 16]>()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop_for_pattern};
+    use anyhow::{anyhow, Result};
+
+    #[test]
+    fn test_mail_mime_roundtrip() -> Result<()> {
+        let original = MailMessage::new(
+            "Alice <safe://alice>",
+            vec!["Bob <safe://bob>".to_string()],
+            "Café meeting",
+            "See you there,\nAlice",
+        );
+        let message = original.reply("Bob <safe://bob>", "Sure!");
+        let attachments = vec![AttachmentContent {
+            filename: "map.png".to_string(),
+            media_type: "image/png".to_string(),
+            data: Bytes::from(vec![0u8, 1, 2, 255]),
+        }];
+
+        let mime = mail_to_mime(&message, &attachments);
+        assert!(mime.contains("Subject: =?utf-8?B?"));
+        assert!(mime.contains(&format!("In-Reply-To: <{}>", original.message_id)));
+
+        let (parsed, parsed_attachments) = mail_from_mime(&mime)?;
+        assert_eq!(parsed.message_id, message.message_id);
+        assert_eq!(parsed.subject, "Re: Café meeting");
+        assert_eq!(parsed.to, vec!["Alice <safe://alice>".to_string()]);
+        assert_eq!(parsed.in_reply_to, Some(original.message_id.clone()));
+        assert_eq!(parsed.references, vec![original.message_id]);
+        assert_eq!(parsed.body, "Sure!");
+        assert_eq!(parsed_attachments, attachments);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mail_from_mime_quoted_printable() -> Result<()> {
+        let mime = "From: Carol <carol@example.com>\n\
+            To: dave@example.com, erin@example.com\n\
+            Subject: =?utf-8?Q?Caf=C3=A9?=\n \
+            notes\n\
+            Date: Tue, 1 Jun 2021 10:00:00 +0000\n\
+            Content-Type: multipart/alternative; boundary=xyz\n\
+            \n\
+            --xyz\n\
+            Content-Type: text/plain; charset=utf-8\n\
+            Content-Transfer-Encoding: quoted-printable\n\
+            \n\
+            Caf=C3=A9 at noo=\n\
+            n\n\
+            --xyz\n\
+            Content-Type: text/html\n\
+            \n\
+            <p>Caf&eacute;</p>\n\
+            --xyz--\n";
+
+        let (message, attachments) = mail_from_mime(mime)?;
+        assert_eq!(message.from, "Carol <carol@example.com>");
+        assert_eq!(message.to.len(), 2);
+        assert_eq!(message.subject, "Café notes");
+        assert_eq!(message.date, "2021-06-01T10:00:00+00:00");
+        assert_eq!(message.body, "Café at noon");
+        assert!(attachments.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mail_send_and_receive() -> Result<()> {
+        let sender = new_safe_instance().await?;
+        let recipient = new_safe_instance().await?;
+        let recipient_sk = bls::SecretKey::random();
+
+        let message = MailMessage::new("sender", vec!["recipient".to_string()], "hi", "hello");
+        let attachment = AttachmentContent {
+            filename: "notes.txt".to_string(),
+            media_type: "text/plain".to_string(),
+            data: Bytes::from("some notes"),
+        };
+        let sent = sender
+            .mail_send(
+                &recipient_sk.public_key(),
+                message,
+                vec![attachment.clone()],
+            )
+            .await?;
+        assert_eq!(sent.attachments.len(), 1);
+
+        let received = retry_loop_for_pattern!(recipient.mail_receive(&recipient_sk), Ok(received) if !received.is_empty())?;
+        let mail = received
+            .first()
+            .ok_or_else(|| anyhow!("No mail received"))?;
+        assert_eq!(mail.sender, sender.get_my_keypair()?.public_key());
+        assert_eq!(mail.message, sent);
+
+        let data = recipient
+            .mail_fetch_attachment(&mail.message.attachments[0])
+            .await?;
+        assert_eq!(data, attachment.data);
+        let mime = recipient.mail_export_mime(&mail.message).await?;
+        let (_, attachments) = mail_from_mime(&mime)?;
+        assert_eq!(attachments, vec![attachment]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::Safe;
+use futures::channel::oneshot;
+use log::debug;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+// Memory a fetch is expected to take when its size is not known beforehand,
+// which is the maximum size of the chunks content is split into
+const DEFAULT_FETCH_ESTIMATE: usize = 1024 * 1024;
+
+/// Memory held by an instance, and its clones, see `Safe::memory_stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Budget for the buffers of in-flight fetches, if any
+    pub budget: Option<u64>,
+    /// Bytes currently held by in-flight fetches
+    pub in_flight_bytes: u64,
+    /// Maximum bytes held by in-flight fetches at any time
+    pub peak_in_flight_bytes: u64,
+    /// Fetches currently waiting for memory to be released
+    pub queued_fetches: u64,
+    /// Fetches which had to wait for memory to be released so far
+    pub total_queued_fetches: u64,
+    /// Approximate bytes held by the local caches, e.g. by the
+    /// envelopes indexed to read Registers sorted by time
+    pub cached_bytes: u64,
+}
+
+#[derive(Default)]
+struct BudgetState {
+    limit: Option<usize>,
+    in_flight: usize,
+    peak: usize,
+    total_queued: u64,
+    waiters: VecDeque<oneshot::Sender<()>>,
+}
+
+impl BudgetState {
+    // A fetch fits if there is room left for it, or if nothing else is in flight,
+    // so fetches larger than the whole budget can still run on their own
+    fn fits(&self, bytes: usize) -> bool {
+        self.limit.map_or(true, |limit| {
+            self.in_flight == 0 || self.in_flight + bytes <= limit
+        })
+    }
+
+    fn add(&mut self, bytes: usize) {
+        self.in_flight += bytes;
+        self.peak = self.peak.max(self.in_flight);
+    }
+
+    // Wake up the queued fetches so they check again if they fit
+    fn wake_all(&mut self) {
+        self.waiters.drain(..).for_each(|waiter| {
+            let _ = waiter.send(());
+        });
+    }
+}
+
+// Budget of the memory in-flight fetches can hold. Fetches which don't fit in the budget
+// are queued until enough memory is released. Clones of an instance share the same budget.
+#[derive(Clone, Default)]
+pub(crate) struct MemoryBudget {
+    state: Arc<Mutex<BudgetState>>,
+}
+
+impl MemoryBudget {
+    pub(crate) fn set_limit(&self, limit: Option<usize>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.limit = limit;
+            state.wake_all();
+        }
+    }
+
+    // Reserve memory for a fetch, waiting until it fits in the budget. The estimate is
+    // corrected with `MemoryReservation::resize` once the size of the data is known.
+    pub(crate) async fn reserve(&self, estimate: Option<usize>) -> MemoryReservation {
+        let bytes = estimate.unwrap_or(DEFAULT_FETCH_ESTIMATE);
+        let mut queued = false;
+        loop {
+            let waiter = match self.state.lock() {
+                Ok(mut state) => {
+                    if state.fits(bytes) {
+                        state.add(bytes);
+                        break;
+                    }
+                    if !queued {
+                        debug!("Fetch of {} bytes queued until memory is released", bytes);
+                        state.total_queued += 1;
+                        queued = true;
+                    }
+                    let (tx, rx) = oneshot::channel();
+                    state.waiters.push_back(tx);
+                    rx
+                }
+                // The budget can't be enforced, the fetch is not held back
+                Err(_) => break,
+            };
+            let _ = waiter.await;
+        }
+
+        MemoryReservation {
+            budget: self.clone(),
+            bytes,
+        }
+    }
+
+    fn release(&self, bytes: usize) {
+        if let Ok(mut state) = self.state.lock() {
+            state.in_flight = state.in_flight.saturating_sub(bytes);
+            state.wake_all();
+        }
+    }
+
+    pub(crate) fn stats(&self) -> MemoryStats {
+        self.state.lock().map_or_else(
+            |_| MemoryStats::default(),
+            |state| MemoryStats {
+                budget: state.limit.map(|limit| limit as u64),
+                in_flight_bytes: state.in_flight as u64,
+                peak_in_flight_bytes: state.peak as u64,
+                queued_fetches: state
+                    .waiters
+                    .iter()
+                    .filter(|waiter| !waiter.is_canceled())
+                    .count() as u64,
+                total_queued_fetches: state.total_queued,
+                cached_bytes: 0,
+            },
+        )
+    }
+}
+
+// Memory reserved by an in-flight fetch, released when dropped
+pub(crate) struct MemoryReservation {
+    budget: MemoryBudget,
+    bytes: usize,
+}
+
+impl MemoryReservation {
+    // Correct the memory reserved with the actual size of the data fetched
+    pub(crate) fn resize(&mut self, bytes: usize) {
+        if let Ok(mut state) = self.budget.state.lock() {
+            state.in_flight = state.in_flight.saturating_sub(self.bytes);
+            state.add(bytes);
+            if bytes < self.bytes {
+                state.wake_all();
+            }
+        }
+        self.bytes = bytes;
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.budget.release(self.bytes);
+    }
+}
+
+impl Safe {
+    /// Set the maximum memory the buffers of in-flight fetches can hold, in bytes, shared
+    /// by this instance and all its clones. Fetches which don't fit in the budget are
+    /// queued until enough memory is released, a fetch larger than the whole budget runs
+    /// on its own. Fetches of unknown size are expected to take a chunk's size until
+    /// fetched. There is no budget by default.
+    pub fn set_memory_budget(&mut self, budget: Option<usize>) {
+        self.safe_client.memory().set_limit(budget);
+    }
+
+    /// Memory held by this instance and all its clones, including the memory
+    /// held by the local caches, which is accounted but not limited by the budget
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            cached_bytes: self.envelope_index.bytes() as u64,
+            ..self.safe_client.memory().stats()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_memory_budget_queues_fetches() -> Result<()> {
+        let mut safe = Safe::default();
+        safe.set_memory_budget(Some(100));
+        let budget = safe.safe_client.memory();
+
+        futures::executor::block_on(async {
+            let first = budget.reserve(Some(60)).await;
+            let mut second = Box::pin(budget.reserve(Some(60)));
+            assert!(futures::poll!(&mut second).is_pending());
+            assert_eq!(safe.memory_stats().queued_fetches, 1);
+
+            drop(first);
+            let mut second = second.await;
+            second.resize(30);
+            let third = budget.reserve(Some(70)).await;
+            assert_eq!(safe.memory_stats().in_flight_bytes, 100);
+            drop(second);
+            drop(third);
+
+            // a fetch larger than the budget runs on its own
+            let _big = budget.reserve(Some(500)).await;
+            let stats = safe.memory_stats();
+            assert_eq!(stats.in_flight_bytes, 500);
+            assert_eq!(stats.peak_in_flight_bytes, 500);
+            assert_eq!(stats.queued_fetches, 0);
+            assert_eq!(stats.total_queued_fetches, 1);
+            assert_eq!(stats.budget, Some(100));
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    encryption::keyed_hash,
+    events::SafeEvent,
+    files::{Provenance, FILES_CONTAINER_TYPE_TAG},
+    helpers::gen_timestamp_secs,
+    register::EntryHash,
+};
+use crate::{ContentType, Error, Result, Safe, Scope, Url, VersionHash, XorName, XorUrl};
+use bytes::Bytes;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, time::Duration};
+
+// Type tag to use for the Registers holding the provenance of the mirrors
+const MIRROR_PROVENANCE_TYPE_TAG: u64 = 2_000;
+
+// Context used to derive the location of the provenance Register of a mirror
+const MIRROR_PROVENANCE_CONTEXT: &[u8] = b"sn_api-mirror-provenance";
+
+/// Record of a version of the source content re-published on a mirror
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MirrorSync {
+    /// The URL of the content mirrored, as it was provided when creating the mirror
+    pub source: String,
+    /// The versioned XOR-URL of the FilesContainer the source was resolved to
+    pub source_xorurl: String,
+    /// The versioned XOR-URL of the mirror where the source version was re-published
+    pub mirror_xorurl: String,
+    /// Time of the sync, in RFC3339 format
+    pub synced_at: String,
+    previous: Option<XorUrl>,
+}
+
+/// A read-only replica, owned by the user, of someone else's FilesContainer or NRS name.
+///
+/// Each new version of the source content is re-published on the mirror, a FilesContainer
+/// which can be fetched like any other, recording its provenance on a separate Register.
+pub struct Mirror {
+    safe: Safe,
+    source: String,
+    xorurl: XorUrl,
+    last_sync: Option<(XorUrl, MirrorSync)>,
+}
+
+impl Mirror {
+    /// The URL of the content mirrored
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The XOR-URL of the mirror's FilesContainer
+    pub fn xorurl(&self) -> &str {
+        &self.xorurl
+    }
+
+    /// Check if there is a new version of the source content and re-publish it on
+    /// the mirror, returning the new version of the mirror if there was one
+    pub async fn sync(&mut self) -> Result<Option<VersionHash>> {
+        let (source_url, _) = self.safe.parse_and_resolve_url(&self.source).await?;
+        let (source_version, mut files_map) = self.safe.fetch_files_container(&source_url).await?;
+        let mut source_versioned_url = source_url.clone();
+        source_versioned_url.set_content_version(Some(source_version));
+        let source_xorurl = source_versioned_url.to_xorurl_string();
+
+        if let Some((_, last_sync)) = &self.last_sync {
+            if last_sync.source_xorurl == source_xorurl {
+                debug!("Mirror of {} is up to date", self.source);
+                return Ok(None);
+            }
+        }
+
+        info!(
+            "Re-publishing version {} of {} on mirror",
+            source_version, self.source
+        );
+        let mirror_url = Url::from_xorurl(&self.xorurl)?;
+        let parents: BTreeSet<EntryHash> = match self.safe.fetch_register_entries(&mirror_url).await
+        {
+            Ok(entries) => entries.into_iter().map(|(hash, _)| hash).collect(),
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+        // Record where each file was mirrored from, unless it already carries its source
+        for (path, file_item) in files_map.iter_mut() {
+            if Provenance::from_file_item(file_item).source.is_none() {
+                let mut file_url = source_versioned_url.clone();
+                file_url.set_path(path);
+                let provenance = Provenance {
+                    source: Some(file_url.to_string()),
+                    ..Default::default()
+                };
+                provenance.apply_to(file_item);
+            }
+        }
+        let files_map_xorurl = self.safe.store_files_map(&files_map, Scope::Public).await?;
+        let hash = self
+            .safe
+            .write_to_register(&self.xorurl, Url::from_xorurl(&files_map_xorurl)?, parents)
+            .await?;
+        let version = VersionHash::from(&hash);
+
+        let mut mirror_versioned_url = mirror_url;
+        mirror_versioned_url.set_content_version(Some(version));
+        let sync = MirrorSync {
+            source: self.source.clone(),
+            source_xorurl,
+            mirror_xorurl: mirror_versioned_url.to_string(),
+            synced_at: gen_timestamp_secs(),
+            previous: self.last_sync.as_ref().map(|(xorurl, _)| xorurl.clone()),
+        };
+        let sync_xorurl = self.safe.write_mirror_sync(&self.xorurl, &sync).await?;
+        self.last_sync = Some((sync_xorurl, sync));
+        self.safe.emit_event(SafeEvent::SyncFinished {
+            url: self.xorurl.clone(),
+            version: version.to_string(),
+            timestamp: gen_timestamp_secs(),
+        });
+
+        Ok(Some(version))
+    }
+
+    /// Keep the mirror in sync, checking the source content for new versions at the given
+    /// interval. This never returns, thus it's meant to be spawned as a background task.
+    pub async fn run(&mut self, interval: Duration) {
+        loop {
+            if let Err(err) = self.sync().await {
+                warn!("Failed to sync mirror of {}: {}", self.source, err);
+                self.safe.emit_event(SafeEvent::Error {
+                    operation: "mirror_sync".to_string(),
+                    url: self.xorurl.clone(),
+                    error: err.to_string(),
+                    timestamp: gen_timestamp_secs(),
+                });
+            }
+            self.safe.runtime().sleep(interval).await;
+        }
+    }
+}
+
+impl Safe {
+    /// # Create a mirror of a FilesContainer or NRS name
+    ///
+    /// The mirror is a FilesContainer owned by the user where the current version of the
+    /// source content is re-published. Use `Mirror::run` to keep it in sync.
+    pub async fn mirror_create(&self, source: &str) -> Result<Mirror> {
+        info!("Creating mirror of {}", source);
+        let _ = Safe::parse_url(source)?;
+        let xorname = self
+            .safe_client
+            .store_register(None, FILES_CONTAINER_TYPE_TAG, None, false)
+            .await?;
+        let xorurl = Url::encode_register(
+            xorname,
+            FILES_CONTAINER_TYPE_TAG,
+            Scope::Public,
+            ContentType::FilesContainer,
+            self.xorurl_base,
+        )?;
+        let (provenance_xorname, _) = self.mirror_provenance_location(&xorurl)?;
+        let _ = self
+            .register_create(Some(provenance_xorname), MIRROR_PROVENANCE_TYPE_TAG, false)
+            .await?;
+
+        let mut mirror = Mirror {
+            safe: self.clone(),
+            source: source.to_string(),
+            xorurl,
+            last_sync: None,
+        };
+        let _ = mirror.sync().await?;
+
+        Ok(mirror)
+    }
+
+    /// Open a mirror previously created by the user, e.g. to resume keeping it in sync
+    pub async fn mirror_open(&self, mirror_url: &str) -> Result<Mirror> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(mirror_url).await?;
+        safe_url.set_content_version(None);
+        let xorurl = safe_url.to_string();
+        let last_sync = self.fetch_last_mirror_sync(&xorurl).await?.ok_or_else(|| {
+            Error::ContentNotFound(format!("No mirror provenance found for \"{}\"", mirror_url))
+        })?;
+
+        Ok(Mirror {
+            safe: self.clone(),
+            source: last_sync.1.source.clone(),
+            xorurl,
+            last_sync: Some(last_sync),
+        })
+    }
+
+    /// Retrieve the provenance of a mirror, i.e. the record of all the versions
+    /// of the source content re-published on it, most recent first
+    pub async fn mirror_provenance(&self, mirror_url: &str) -> Result<Vec<MirrorSync>> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(mirror_url).await?;
+        safe_url.set_content_version(None);
+
+        let mut provenance = vec![];
+        let mut next = self
+            .fetch_last_mirror_sync(&safe_url.to_string())
+            .await?
+            .map(|(_, sync)| sync);
+        while let Some(sync) = next {
+            next = match &sync.previous {
+                Some(previous) => Some(self.fetch_mirror_sync(previous).await?),
+                None => None,
+            };
+            provenance.push(sync);
+        }
+
+        Ok(provenance)
+    }
+
+    // Private helper to obtain the location of the provenance Register of a mirror
+    fn mirror_provenance_location(&self, mirror_xorurl: &str) -> Result<(XorName, XorUrl)> {
+        let mirror_xorname = Url::from_xorurl(mirror_xorurl)?.xorname();
+        let xorname = XorName(keyed_hash(MIRROR_PROVENANCE_CONTEXT, &mirror_xorname.0));
+        let xorurl = Url::encode_register(
+            xorname,
+            MIRROR_PROVENANCE_TYPE_TAG,
+            Scope::Public,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?;
+        Ok((xorname, xorurl))
+    }
+
+    async fn fetch_last_mirror_sync(
+        &self,
+        mirror_xorurl: &str,
+    ) -> Result<Option<(XorUrl, MirrorSync)>> {
+        let (_, provenance_xorurl) = self.mirror_provenance_location(mirror_xorurl)?;
+        let entries = match self
+            .fetch_register_entries(&Url::from_xorurl(&provenance_xorurl)?)
+            .await
+        {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        // Syncs are always appended to the latest one, thus there is a single entry
+        match entries.into_iter().next() {
+            Some((_, entry)) => {
+                let sync_xorurl = entry.to_string();
+                let sync = self.fetch_mirror_sync(&sync_xorurl).await?;
+                Ok(Some((sync_xorurl, sync)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn fetch_mirror_sync(&self, sync_xorurl: &str) -> Result<MirrorSync> {
+        let serialised_sync = self
+            .fetch_public_data(&Url::from_xorurl(sync_xorurl)?, None)
+            .await?;
+        rmp_serde::from_slice(&serialised_sync)
+            .map_err(|err| Error::ContentError(format!("Couldn't parse mirror sync: {:?}", err)))
+    }
+
+    async fn write_mirror_sync(&self, mirror_xorurl: &str, sync: &MirrorSync) -> Result<XorUrl> {
+        let (_, provenance_xorurl) = self.mirror_provenance_location(mirror_xorurl)?;
+        let parents = match self
+            .fetch_register_entries(&Url::from_xorurl(&provenance_xorurl)?)
+            .await
+        {
+            Ok(entries) => entries.into_iter().map(|(hash, _)| hash).collect(),
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        let serialised_sync = rmp_serde::to_vec_named(sync).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise mirror sync: {:?}", err))
+        })?;
+        let sync_xorurl = self
+            .store_public_bytes(Bytes::from(serialised_sync), None, false)
+            .await?;
+        let _ = self
+            .write_to_register(&provenance_xorurl, Url::from_xorurl(&sync_xorurl)?, parents)
+            .await?;
+
+        Ok(sync_xorurl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern, Url};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_mirror_create_and_sync() -> Result<()> {
+        let mut source_safe = new_safe_instance().await?;
+        let (source_xorurl, _, files_map) = source_safe
+            .files_container_create(Some("./testdata/test.md"), None, false, false, false)
+            .await?;
+        let _ = retry_loop!(source_safe.fetch(&source_xorurl, None));
+
+        let mut safe = new_safe_instance().await?;
+        let mut mirror = safe.mirror_create(&source_xorurl).await?;
+        assert_eq!(mirror.source(), source_xorurl);
+        let (_, mirrored_files_map) = retry_loop!(safe.files_container_get(mirror.xorurl()));
+        assert_eq!(
+            mirrored_files_map.keys().collect::<Vec<_>>(),
+            files_map.keys().collect::<Vec<_>>()
+        );
+        let provenances = safe.files_container_provenance(mirror.xorurl()).await?;
+        let file_source = Url::from_url(provenances["/test.md"].source.as_deref().unwrap_or(""))?;
+        assert_eq!(file_source.path(), "/test.md");
+        assert_eq!(
+            file_source.xorname(),
+            Url::from_url(&source_xorurl)?.xorname()
+        );
+
+        // nothing to sync while the source doesn't change
+        let _ = retry_loop_for_pattern!(safe.mirror_provenance(mirror.xorurl()), Ok(v) if v.len() == 1)?;
+        assert_eq!(mirror.sync().await?, None);
+
+        let provenance = safe.mirror_provenance(mirror.xorurl()).await?;
+        assert_eq!(provenance[0].source, source_xorurl);
+
+        let reopened = safe.mirror_open(mirror.xorurl()).await?;
+        assert_eq!(reopened.source(), source_xorurl);
+
+        Ok(())
+    }
+}
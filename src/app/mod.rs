@@ -7,37 +7,115 @@
 // specific language governing permissions and limitations relating to use of the SAFE Network
 // Software.
 
+mod addresses;
 mod auth;
 mod consts;
+mod diagnostics;
+mod encryption;
+mod entropy;
 mod helpers;
+mod history;
 mod keys;
+mod log;
 mod safe_client;
+mod search;
+mod signer;
 #[cfg(test)]
 mod test_helpers;
+mod workers;
 
 use super::{common, constants, Result};
+use events::EventBus;
+use files::PublishLock;
+use history::FetchHistory;
+use nrs::NrsVersionRequirement;
+use obligations::Obligations;
 use rand::rngs::OsRng;
+use register::{EnvelopeIndex, Quarantine, DEFAULT_MAX_REGISTER_ENTRY_LEN};
 use safe_client::SafeAppClient;
 use safe_network::client::DEFAULT_QUERY_TIMEOUT;
 use safe_network::types::Keypair;
+use search::LocalIndex;
 
-use std::time::Duration;
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
 
 // The following is what's meant to be the public API
 
+pub mod bookmarks;
+pub mod capability;
+pub mod channels;
+#[cfg(feature = "advanced")]
+pub mod chunks;
+pub mod commands;
+pub mod contacts;
+pub mod discovery;
+pub mod ephemeral;
+pub mod events;
+pub mod feeds;
 pub mod fetch;
 pub mod files;
+pub mod git;
+pub mod json;
+pub mod keyed_register;
+pub mod lease;
+pub mod mail;
+pub mod memory;
+pub mod mirror;
+pub mod monitor;
 pub mod multimap;
 pub mod nrs;
+pub mod obligations;
+pub mod privacy;
+pub mod private_data;
+pub mod proofs;
+pub mod publish;
 pub mod register;
+pub mod registry;
+pub mod relay;
+pub mod reports;
+pub mod runtime;
+pub mod schedule;
+pub mod schemas;
+pub mod share;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod transfers;
+pub mod vault;
+pub mod whois;
+pub use addresses::UrlAddressExt;
 pub use consts::DEFAULT_XORURL_BASE;
+pub use diagnostics::Diagnostics;
+pub use encryption::{EncryptionAlgorithm, EncryptionPolicy};
+pub use entropy::{EntropySource, OsEntropy};
 pub use helpers::parse_tokens_amount;
+pub use history::HistoryEntry;
+// `self::` tells the module apart from the log crate
+pub use self::log::Log;
 pub use safe_network::url::*;
+pub use search::{IndexedKind, SearchResult};
+pub use signer::{KeypairSigner, Signer};
 pub use xor_name::{XorName, XOR_NAME_LEN};
 
 #[derive(Clone)]
 pub struct Safe {
     safe_client: SafeAppClient,
+    history: FetchHistory,
+    local_index: LocalIndex,
+    envelope_index: EnvelopeIndex,
+    quarantine: Quarantine,
+    encryption_policy: EncryptionPolicy,
+    entropy: Arc<dyn EntropySource>,
+    signer: Option<Arc<dyn Signer>>,
+    publish_lock: Option<PublishLock>,
+    obligations: Obligations,
+    events: EventBus,
+    private_by_default: bool,
+    encrypt_private_metadata: bool,
+    encrypt_private_registers: bool,
+    large_register_entries: bool,
+    large_register_entry_max_len: usize,
+    verify_nrs_links: bool,
+    nrs_version_requirement: NrsVersionRequirement,
     pub xorurl_base: XorUrlBase,
 }
 
@@ -51,11 +129,29 @@ impl Safe {
     pub fn new(xorurl_base: Option<XorUrlBase>, timeout: Duration) -> Self {
         Self {
             safe_client: SafeAppClient::new(timeout),
+            history: FetchHistory::default(),
+            local_index: LocalIndex::default(),
+            envelope_index: EnvelopeIndex::default(),
+            quarantine: Quarantine::default(),
+            encryption_policy: EncryptionPolicy::default(),
+            entropy: Arc::new(OsEntropy),
+            signer: None,
+            publish_lock: None,
+            obligations: Obligations::default(),
+            events: EventBus::default(),
+            private_by_default: false,
+            encrypt_private_metadata: true,
+            encrypt_private_registers: false,
+            large_register_entries: false,
+            large_register_entry_max_len: DEFAULT_MAX_REGISTER_ENTRY_LEN,
+            verify_nrs_links: false,
+            nrs_version_requirement: NrsVersionRequirement::default(),
             xorurl_base: xorurl_base.unwrap_or(DEFAULT_XORURL_BASE),
         }
     }
 
-    /// Generate a new random Ed25519 keypair
+    /// Generate a new random Ed25519 keypair from the OS CSPRNG, regardless of the
+    /// entropy source set, see `generate_keypair` for long-lived identity keys
     pub fn keypair(&self) -> Keypair {
         let mut rng = OsRng;
         Keypair::new_ed25519(&mut rng)
@@ -66,4 +162,110 @@ impl Safe {
     pub fn get_my_keypair(&self) -> Result<Keypair> {
         self.safe_client.keypair()
     }
+
+    /// Set the policy of the algorithms used to encrypt and decrypt private payloads,
+    /// it fails if the default algorithm of the policy is not one of the allowed ones
+    pub fn set_encryption_policy(&mut self, policy: EncryptionPolicy) -> Result<()> {
+        policy.validate()?;
+        self.encryption_policy = policy;
+        Ok(())
+    }
+
+    /// Retrieve the policy of the algorithms used to encrypt and decrypt private payloads
+    pub fn encryption_policy(&self) -> &EncryptionPolicy {
+        &self.encryption_policy
+    }
+
+    /// Set the genesis keys of the networks this instance is allowed to connect to. When
+    /// set, `connect` fails with `Error::UntrustedNetwork` if the genesis key of the bootstrap
+    /// config provided isn't among them, thus an impostor network can't be connected to even
+    /// if the bootstrap config, e.g. obtained from the authenticator, is compromised. The
+    /// client verifies the sections of the network it connects to against such genesis key.
+    pub fn set_trusted_genesis_keys(&mut self, keys: BTreeSet<bls::PublicKey>) {
+        self.safe_client.set_trusted_genesis_keys(keys);
+    }
+
+    /// Genesis keys of the networks this instance is allowed to connect to, any if empty
+    pub fn trusted_genesis_keys(&self) -> &BTreeSet<bls::PublicKey> {
+        self.safe_client.trusted_genesis_keys()
+    }
+
+    /// Set the number of threads large payloads are self-encrypted on when uploaded, so
+    /// the async runtime driving other operations isn't stalled. With no threads they're
+    /// self-encrypted on the uploading task. The threads are only spawned upon first use.
+    pub fn set_encryption_workers(&mut self, workers: usize) {
+        self.safe_client.set_workers(workers);
+    }
+
+    /// Number of threads large payloads are self-encrypted on when uploaded
+    pub fn encryption_workers(&self) -> usize {
+        self.safe_client.workers()
+    }
+
+    /// Set whether new content is stored with private scope unless it's explicitly
+    /// requested to be public, i.e. the files uploaded to new FilesContainers, the
+    /// content stored with `store_bytes` or from streams, and the JSON documents created
+    /// without a scope. APIs which take the scope, e.g. `register_create` or `kr_create`,
+    /// aren't affected, the Blobs written to a Register, e.g. the records of keyed
+    /// Registers, have the scope of such Register, and content meant to be read by others,
+    /// e.g. NRS Maps, channel updates or mail, is always public. Private content can be
+    /// published afterwards with `make_public`.
+    pub fn set_private_by_default(&mut self, private_by_default: bool) {
+        self.private_by_default = private_by_default;
+    }
+
+    /// Whether new content is stored with private scope unless it's explicitly requested
+    pub fn is_private_by_default(&self) -> bool {
+        self.private_by_default
+    }
+
+    /// Set whether the FilesMaps of private FilesContainers, i.e. the names, paths and
+    /// metadata of their files, are encrypted with a key derived from the keypair this
+    /// instance is connected with, besides being stored as private content. It's enabled
+    /// by default. Plain FilesMaps can still be read when enabled, and existing
+    /// FilesContainers can be migrated with `files_container_encrypt_metadata`.
+    pub fn set_encrypt_private_metadata(&mut self, encrypt_private_metadata: bool) {
+        self.encrypt_private_metadata = encrypt_private_metadata;
+    }
+
+    /// Whether the FilesMaps of private FilesContainers are encrypted
+    pub fn is_encrypting_private_metadata(&self) -> bool {
+        self.encrypt_private_metadata
+    }
+
+    /// Set whether the targets of the links associated to NRS names are verified on the
+    /// network, i.e. that they exist, are of the type declared by the link, and that
+    /// the version the link specifies is valid for them. It's disabled by default
+    /// as it requires fetching the targets, see `verify_nrs_link`.
+    pub fn set_verify_nrs_links(&mut self, verify_nrs_links: bool) {
+        self.verify_nrs_links = verify_nrs_links;
+    }
+
+    /// Whether the targets of the links associated to NRS names are verified on the network
+    pub fn is_verifying_nrs_links(&self) -> bool {
+        self.verify_nrs_links
+    }
+
+    /// Set whether the links associated to NRS names must specify a version when they
+    /// target versionable content, e.g. FilesContainers. With `FollowLatest`, the names
+    /// associated to unversioned links follow the updates of the content, as its latest
+    /// version is resolved at fetch time, which is flagged in the `NrsMapContainer`
+    /// found in the resolution chain. It's `Strict` by default.
+    pub fn set_nrs_version_requirement(&mut self, requirement: NrsVersionRequirement) {
+        self.nrs_version_requirement = requirement;
+    }
+
+    /// Whether the links associated to NRS names must specify a version
+    pub fn nrs_version_requirement(&self) -> NrsVersionRequirement {
+        self.nrs_version_requirement
+    }
+
+    // Scope new content is stored with unless it's explicitly requested
+    pub(crate) fn default_scope(&self) -> Scope {
+        if self.private_by_default {
+            Scope::Private
+        } else {
+            Scope::Public
+        }
+    }
 }
@@ -0,0 +1,278 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+#[cfg(feature = "http_import")]
+use super::events::post_event;
+use super::{fetch::SafeData, helpers::gen_timestamp_secs};
+use crate::{Error, Result, Safe};
+use futures::channel::mpsc;
+#[cfg(feature = "http_import")]
+use log::warn;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+
+/// Event emitted by a `LinkMonitor` upon checking the URLs it monitors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MonitorEvent {
+    /// The URL couldn't be resolved, either for the first time or after being resolvable
+    Unresolvable {
+        url: String,
+        error: String,
+        /// Time of the check, in RFC3339 format
+        checked_at: String,
+    },
+    /// The URL can be resolved again after failing to
+    Recovered {
+        url: String,
+        fingerprint: String,
+        checked_at: String,
+    },
+    /// The content the URL resolves to is different from the last time it was checked
+    Changed {
+        url: String,
+        previous: String,
+        current: String,
+        checked_at: String,
+    },
+}
+
+// State of each URL monitored since it was last checked
+#[derive(Debug, Default)]
+struct MonitoredUrl {
+    fingerprint: Option<String>,
+    failing: bool,
+}
+
+type EventSink = Box<dyn Fn(&MonitorEvent) + Send + Sync>;
+
+/// Periodic check of a set of URLs, e.g. NRS names of published sites, for resolvability
+/// and for changes on the content they resolve to, to detect outages or defacements.
+///
+/// The content is identified by the XOR-URL it resolves to, together with its version
+/// for FilesContainers and NrsMapContainers, which is referred to as its fingerprint.
+/// The events emitted upon each check are delivered to the sinks set on the monitor.
+pub struct LinkMonitor {
+    safe: Safe,
+    urls: BTreeMap<String, MonitoredUrl>,
+    sinks: Vec<EventSink>,
+    #[cfg(feature = "http_import")]
+    webhooks: Vec<String>,
+}
+
+impl LinkMonitor {
+    /// Start monitoring a URL, the content it resolves to upon its first
+    /// check is taken as the one expected
+    pub fn watch(&mut self, url: &str) {
+        let _ = self.urls.entry(url.to_string()).or_default();
+    }
+
+    /// Stop monitoring a URL
+    pub fn unwatch(&mut self, url: &str) {
+        let _ = self.urls.remove(url);
+    }
+
+    /// The URLs being monitored, with the fingerprint of the content
+    /// they resolved to when last checked, if they could be resolved
+    pub fn urls(&self) -> BTreeMap<String, Option<String>> {
+        self.urls
+            .iter()
+            .map(|(url, state)| {
+                let fingerprint = if state.failing {
+                    None
+                } else {
+                    state.fingerprint.clone()
+                };
+                (url.clone(), fingerprint)
+            })
+            .collect()
+    }
+
+    /// Call the callback with each event emitted
+    pub fn on_event(&mut self, callback: impl Fn(&MonitorEvent) + Send + Sync + 'static) {
+        self.sinks.push(Box::new(callback));
+    }
+
+    /// Receive the events emitted on a channel
+    pub fn subscribe(&mut self) -> mpsc::UnboundedReceiver<MonitorEvent> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.on_event(move |event| {
+            let _ = sender.unbounded_send(event.clone());
+        });
+        receiver
+    }
+
+    /// POST each event emitted, serialised as JSON, to the webhook URL
+    #[cfg(feature = "http_import")]
+    pub fn add_webhook(&mut self, url: &str) {
+        self.webhooks.push(url.to_string());
+    }
+
+    /// Check all the URLs monitored, returning the events emitted
+    pub async fn check(&mut self) -> Vec<MonitorEvent> {
+        let mut events = vec![];
+        for (url, state) in self.urls.iter_mut() {
+            let checked_at = gen_timestamp_secs();
+            match self.safe.fingerprint(url).await {
+                Ok(fingerprint) => {
+                    if state.failing {
+                        events.push(MonitorEvent::Recovered {
+                            url: url.clone(),
+                            fingerprint: fingerprint.clone(),
+                            checked_at: checked_at.clone(),
+                        });
+                    }
+                    match &state.fingerprint {
+                        Some(previous) if *previous != fingerprint => {
+                            events.push(MonitorEvent::Changed {
+                                url: url.clone(),
+                                previous: previous.clone(),
+                                current: fingerprint.clone(),
+                                checked_at,
+                            })
+                        }
+                        _ => debug!("Content at {} is unchanged", url),
+                    }
+                    state.fingerprint = Some(fingerprint);
+                    state.failing = false;
+                }
+                Err(err) => {
+                    // A URL which is still failing is only reported once
+                    if !state.failing {
+                        events.push(MonitorEvent::Unresolvable {
+                            url: url.clone(),
+                            error: err.to_string(),
+                            checked_at,
+                        });
+                    }
+                    state.failing = true;
+                }
+            }
+        }
+
+        for event in events.iter() {
+            info!("Link monitor event: {:?}", event);
+            self.sinks.iter().for_each(|sink| sink(event));
+            #[cfg(feature = "http_import")]
+            for webhook in self.webhooks.iter() {
+                if let Err(err) = post_event(webhook, event).await {
+                    warn!("Failed to deliver event to webhook {}: {}", webhook, err);
+                }
+            }
+        }
+
+        events
+    }
+
+    /// Check the URLs monitored at the given interval. This never returns,
+    /// thus it's meant to be spawned as a background task.
+    pub async fn run(&mut self, interval: Duration) {
+        loop {
+            let _ = self.check().await;
+            self.safe.runtime().sleep(interval).await;
+        }
+    }
+}
+
+impl Safe {
+    /// # Create a monitor of the resolvability of a set of URLs and of the content they resolve to
+    ///
+    /// See `LinkMonitor`, more URLs can be monitored with `LinkMonitor::watch`.
+    pub fn link_monitor(&self, urls: &[&str]) -> LinkMonitor {
+        let mut monitor = LinkMonitor {
+            safe: self.clone(),
+            urls: BTreeMap::new(),
+            sinks: vec![],
+            #[cfg(feature = "http_import")]
+            webhooks: vec![],
+        };
+        urls.iter().for_each(|url| monitor.watch(url));
+        monitor
+    }
+
+    // Private helper to identify the content a URL resolves to, without fetching it
+    async fn fingerprint(&self, url: &str) -> Result<String> {
+        let mut resolution_chain = self.retrieve_from_url(url, false, None, true).await?;
+        let fingerprint = match resolution_chain.pop() {
+            Some(SafeData::FilesContainer {
+                xorurl, version, ..
+            })
+            | Some(SafeData::NrsMapContainer {
+                xorurl, version, ..
+            }) => format!("{}@{}", xorurl, version),
+            Some(safe_data) => safe_data.xorurl(),
+            None => return Err(Error::ContentNotFound(format!("Failed to resolve {}", url))),
+        };
+
+        Ok(fingerprint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::test_helpers::{new_safe_instance, random_nrs_name},
+        retry_loop,
+    };
+    use anyhow::{anyhow, Result};
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn test_link_monitor() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let site_name = random_nrs_name();
+        let original = safe
+            .store_public_bytes(Bytes::from("my site"), None, false)
+            .await?;
+        let _ =
+            retry_loop!(safe.nrs_map_container_create(&site_name, &original, true, false, false));
+        let _ = retry_loop!(safe.fetch(&format!("safe://{}", site_name), None));
+
+        let missing = format!("safe://{}", random_nrs_name());
+        let mut monitor = safe.link_monitor(&[&format!("safe://{}", site_name), &missing]);
+        let mut receiver = monitor.subscribe();
+
+        let events = monitor.check().await;
+        assert_eq!(events.len(), 1);
+        assert!(matches!(&events[0], MonitorEvent::Unresolvable { url, .. } if *url == missing));
+        assert!(monitor.check().await.is_empty());
+        assert_eq!(monitor.urls()[&missing], None);
+
+        // the site is defaced
+        let defaced = safe
+            .store_public_bytes(Bytes::from("defaced"), None, false)
+            .await?;
+        let _ = safe
+            .nrs_map_container_add(&site_name, &defaced, true, false, false)
+            .await?;
+        let events = retry_loop!(async {
+            let events = monitor.check().await;
+            if events.is_empty() {
+                Err(anyhow!("No change detected yet"))
+            } else {
+                Ok(events)
+            }
+        });
+        match &events[..] {
+            [MonitorEvent::Changed {
+                previous, current, ..
+            }] => {
+                assert_eq!(*previous, original);
+                assert_eq!(*current, defaced);
+            }
+            other => return Err(anyhow!("Unexpected events: {:?}", other)),
+        }
+
+        let delivered = receiver.try_recv()?;
+        assert!(matches!(delivered, MonitorEvent::Unresolvable { .. }));
+
+        Ok(())
+    }
+}
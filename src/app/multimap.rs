@@ -7,7 +7,7 @@
 // specific language governing permissions and limitations relating to use of the SAFE Network
 // Software.
 
-use super::register::EntryHash;
+use super::register::{is_tombstone, EntryHash, QuarantineKind};
 use crate::{Error, Result, Safe};
 use bytes::Bytes;
 use log::debug;
@@ -143,12 +143,25 @@ impl Safe {
             other => other,
         }?;
 
-        // We parse each entry in the Register as a 'MultimapKeyValue'
+        // We parse each entry in the Register as a 'MultimapKeyValue',
+        // quarantining those which are not, rather than failing the whole read
         let mut multimap_key_vals = MultimapKeyValues::new();
         for (hash, entry_ptr) in entries.iter() {
-            let entry = self.fetch_public_data(entry_ptr, None).await?;
-            let key_val = Self::decode_multimap_entry(&entry)?;
-            multimap_key_vals.insert((*hash, key_val));
+            if is_tombstone(entry_ptr) {
+                continue;
+            }
+            let decoded = match self.fetch_public_data(entry_ptr, None).await {
+                Ok(entry) => Self::decode_multimap_entry(&entry),
+                Err(err) => Err(err),
+            };
+            match decoded {
+                Ok(key_val) => {
+                    multimap_key_vals.insert((*hash, key_val));
+                }
+                Err(err) => {
+                    self.quarantine_entry(safeurl, *hash, QuarantineKind::MultimapEntry, err)?
+                }
+            }
         }
         Ok(multimap_key_vals)
     }
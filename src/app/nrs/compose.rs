@@ -0,0 +1,327 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{nrs_map::SubName, validate_nrs_name, NrsMap, ProcessedEntries, VersionHash};
+use crate::{ContentType, Error, Result, Safe, XorUrl};
+use futures::{future::BoxFuture, FutureExt};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// Maximum number of NrsMaps including each other followed when resolving a name
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Precedence of the names of an included NrsMap, see `NrsMap::include`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NrsPrecedence {
+    /// The names of the including NrsMap take precedence over the included ones,
+    /// e.g. to compose shared base mappings into many top names
+    Base,
+    /// The included names take precedence over those of the including NrsMap
+    Overlay,
+}
+
+/// An NrsMap included by another one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NrsInclude {
+    /// URL of the NrsMapContainer of the included NrsMap, e.g. `safe://<top name>`,
+    /// whose latest version is included unless the URL specifies a version
+    pub url: String,
+    pub precedence: NrsPrecedence,
+}
+
+impl NrsMap {
+    /// # Include another published NrsMap
+    ///
+    /// Names are resolved looking them up in the NrsMaps included with `Overlay` precedence
+    /// first, then in this NrsMap's own names, then in the NrsMaps included with `Base`
+    /// precedence, the NrsMaps of each precedence in the order they were included, and
+    /// the first NrsMap resolving the name wins. Included NrsMaps may include others in turn.
+    /// Including an NrsMap again moves it last with the new precedence. Only the includes
+    /// of top level NrsMaps, i.e. of top names, are followed.
+    pub fn include(&mut self, url: &str, precedence: NrsPrecedence) -> Result<()> {
+        let safe_url = Safe::parse_url(url)?;
+        let is_container = if safe_url.is_xorurl() {
+            safe_url.content_type() == ContentType::NrsMapContainer
+        } else {
+            safe_url.sub_names_vec().is_empty()
+        };
+        if !is_container || !safe_url.path().is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "Only NrsMapContainers can be included, which \"{}\" is not",
+                url
+            )));
+        }
+
+        let _ = self.exclude(url);
+        self.includes.push(NrsInclude {
+            url: url.to_string(),
+            precedence,
+        });
+        Ok(())
+    }
+
+    /// Stop including an NrsMap, returning whether it was included
+    pub fn exclude(&mut self, url: &str) -> bool {
+        let included = self.includes.len();
+        self.includes.retain(|include| include.url != url);
+        self.includes.len() != included
+    }
+}
+
+impl Safe {
+    /// # Include a published NrsMap in the NrsMap of a top name
+    ///
+    /// The NrsMap of the given top name includes the NrsMap at the given URL from its new
+    /// version on, see `NrsMap::include`, which is returned along with the new NrsMap.
+    pub async fn nrs_include(
+        &self,
+        top_name: &str,
+        url: &str,
+        precedence: NrsPrecedence,
+    ) -> Result<(VersionHash, XorUrl, NrsMap)> {
+        info!("Including {} in the NRS map of {}", url, top_name);
+        self.update_nrs_includes(top_name, |nrs_map| nrs_map.include(url, precedence))
+            .await
+    }
+
+    /// Stop including a published NrsMap in the NrsMap of a top name, see `nrs_include`
+    pub async fn nrs_exclude(
+        &self,
+        top_name: &str,
+        url: &str,
+    ) -> Result<(VersionHash, XorUrl, NrsMap)> {
+        info!("Excluding {} from the NRS map of {}", url, top_name);
+        self.update_nrs_includes(top_name, |nrs_map| {
+            if nrs_map.exclude(url) {
+                Ok(())
+            } else {
+                Err(Error::InvalidInput(format!(
+                    "\"{}\" is not included in the NRS map of {}",
+                    url, top_name
+                )))
+            }
+        })
+        .await
+    }
+
+    // Resolve the link of the sub names in an NrsMap, looking them up in the NrsMaps it
+    // includes as per their precedence. An NrsMap included which can't be fetched fails
+    // the resolution, as the name could resolve differently otherwise.
+    pub(crate) async fn nrs_resolve_link(
+        &self,
+        nrs_map: &NrsMap,
+        sub_names: &[SubName],
+    ) -> Result<(XorUrl, bool)> {
+        if nrs_map.includes.is_empty() {
+            return nrs_map.resolve_link_for_subnames(sub_names);
+        }
+
+        let layers = self
+            .nrs_map_layers(nrs_map.clone(), BTreeSet::new(), 0)
+            .await?;
+        for (index, layer) in layers.iter().enumerate() {
+            if let Ok(link) = layer.resolve_link_for_subnames(sub_names) {
+                debug!("NRS sub names resolved by NRS map layer {}", index);
+                return Ok(link);
+            }
+        }
+        // none resolves it, the failure of the NrsMap's own names tells why
+        nrs_map.resolve_link_for_subnames(sub_names)
+    }
+
+    // The NrsMap and the NrsMaps it includes, recursively, in their order of precedence,
+    // the URLs of the NrsMaps including it being given to detect cycles
+    fn nrs_map_layers(
+        &self,
+        nrs_map: NrsMap,
+        including: BTreeSet<String>,
+        depth: usize,
+    ) -> BoxFuture<'_, Result<Vec<NrsMap>>> {
+        async move {
+            let mut overlays = vec![];
+            let mut bases = vec![];
+            for include in nrs_map.includes.iter() {
+                if including.contains(&include.url) {
+                    return Err(Error::ContentError(format!(
+                        "NRS map at \"{}\" includes itself",
+                        include.url
+                    )));
+                }
+                if depth >= MAX_INCLUDE_DEPTH {
+                    return Err(Error::ContentError(format!(
+                        "NRS maps include each other beyond the maximum depth of {}",
+                        MAX_INCLUDE_DEPTH
+                    )));
+                }
+
+                let (_, included) = self.nrs_map_container_get(&include.url).await?;
+                let mut path = including.clone();
+                let _ = path.insert(include.url.clone());
+                let layers = self.nrs_map_layers(included, path, depth + 1).await?;
+                match include.precedence {
+                    NrsPrecedence::Overlay => overlays.extend(layers),
+                    NrsPrecedence::Base => bases.extend(layers),
+                }
+            }
+
+            overlays.push(nrs_map);
+            overlays.extend(bases);
+            Ok(overlays)
+        }
+        .boxed()
+    }
+
+    // Private helper to update the includes of the NrsMap of a top name in a new version
+    async fn update_nrs_includes(
+        &self,
+        top_name: &str,
+        update: impl FnOnce(&mut NrsMap) -> Result<()>,
+    ) -> Result<(VersionHash, XorUrl, NrsMap)> {
+        let (safe_url, _) = validate_nrs_name(top_name)?;
+        if !safe_url.sub_names_vec().is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "'{}' is not a top name",
+                top_name
+            )));
+        }
+        let xorurl = safe_url.to_string();
+        let (version, mut nrs_map) = self.nrs_map_container_get(&xorurl).await?;
+        update(&mut nrs_map)?;
+
+        let new_version = self
+            .write_nrs_map_version(
+                &xorurl,
+                safe_url.top_name(),
+                version,
+                &nrs_map,
+                &ProcessedEntries::new(),
+            )
+            .await?;
+        Ok((new_version, xorurl, nrs_map))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::test_helpers::{new_safe_instance, random_nrs_name},
+        retry_loop, retry_loop_for_pattern,
+    };
+    use anyhow::Result;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_nrs_map_include() -> Result<()> {
+        let mut nrs_map = NrsMap::default();
+        nrs_map.include("safe://base", NrsPrecedence::Base)?;
+        nrs_map.include("safe://overlay", NrsPrecedence::Overlay)?;
+        nrs_map.include("safe://base", NrsPrecedence::Overlay)?;
+        assert_eq!(
+            nrs_map.includes,
+            vec![
+                NrsInclude {
+                    url: "safe://overlay".to_string(),
+                    precedence: NrsPrecedence::Overlay
+                },
+                NrsInclude {
+                    url: "safe://base".to_string(),
+                    precedence: NrsPrecedence::Overlay
+                }
+            ]
+        );
+        assert!(nrs_map
+            .include("safe://a.base", NrsPrecedence::Base)
+            .is_err());
+        assert!(nrs_map
+            .include("safe://base/path", NrsPrecedence::Base)
+            .is_err());
+
+        assert!(nrs_map.exclude("safe://base"));
+        assert!(!nrs_map.exclude("safe://base"));
+        assert_eq!(nrs_map.includes.len(), 1);
+
+        // NrsMaps without includes are serialised as they were before includes existed
+        let serialised = serde_json::to_string(&NrsMap::default())?;
+        assert!(!serialised.contains("includes"));
+        let deserialised: NrsMap = serde_json::from_str(&serialised)?;
+        assert_eq!(deserialised, NrsMap::default());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nrs_include() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let (site, base, overlay) = (random_nrs_name(), random_nrs_name(), random_nrs_name());
+        let mut links = vec![];
+        for content in ["site", "base", "overlay"].iter() {
+            links.push(
+                safe.store_public_bytes(Bytes::from(*content), None, false)
+                    .await?,
+            );
+        }
+
+        // the base and the overlay define the same names as the site
+        for (top_name, link) in [&site, &base, &overlay].iter().zip(links.iter()) {
+            let _ = retry_loop!(safe.nrs_map_container_create(
+                &format!("www.{}", top_name),
+                link,
+                false,
+                false,
+                false
+            ));
+        }
+        let _ = retry_loop!(safe.nrs_map_container_add(
+            &format!("shared.{}", base),
+            &links[1],
+            false,
+            false,
+            false
+        ));
+
+        let (_, _, _) = safe
+            .nrs_include(&site, &format!("safe://{}", base), NrsPrecedence::Base)
+            .await?;
+        let (version, _, _) = safe
+            .nrs_include(
+                &site,
+                &format!("safe://{}", overlay),
+                NrsPrecedence::Overlay,
+            )
+            .await?;
+        let (_, nrs_map) = retry_loop_for_pattern!(safe.nrs_map_container_get(&format!("safe://{}", site)), Ok((v, _)) if *v == version)?;
+
+        let (www, _) = safe
+            .nrs_resolve_link(&nrs_map, &["www".to_string()])
+            .await?;
+        assert_eq!(www, links[2]);
+        let (shared, _) = safe
+            .nrs_resolve_link(&nrs_map, &["shared".to_string()])
+            .await?;
+        assert_eq!(shared, links[1]);
+        assert!(safe
+            .nrs_resolve_link(&nrs_map, &["missing".to_string()])
+            .await
+            .is_err());
+
+        // without the overlay, the site's own names take precedence over the base
+        let (version, _, nrs_map) = safe
+            .nrs_exclude(&site, &format!("safe://{}", overlay))
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.nrs_map_container_get(&format!("safe://{}", site)), Ok((v, _)) if *v == version)?;
+        let (www, _) = safe
+            .nrs_resolve_link(&nrs_map, &["www".to_string()])
+            .await?;
+        assert_eq!(www, links[0]);
+
+        Ok(())
+    }
+}
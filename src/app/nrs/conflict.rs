@@ -0,0 +1,396 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{nrs_map::DefaultRdf, validate_nrs_name, NrsMap, NrsVersionRequirement, VersionHash};
+use crate::{
+    app::consts::{PREDICATE_FOLLOW_LATEST, PREDICATE_LINK},
+    Error, Result, Safe,
+};
+use log::info;
+use std::{collections::BTreeSet, fmt};
+
+/// A version of an NrsMap written concurrently with other versions
+#[derive(Debug, Clone, PartialEq)]
+pub struct NrsBranch {
+    pub version: VersionHash,
+    pub nrs_map: NrsMap,
+}
+
+/// A name defined differently across the branches of a conflict
+#[derive(Debug, Clone, PartialEq)]
+pub struct NrsDivergence {
+    /// The sub name, e.g. `a.b` for `a.b.<top name>`, or empty for the top name itself
+    pub subname: String,
+    /// Link of the name in each branch, in the same order as the branches,
+    /// or none if the name is not defined in the branch
+    pub links: Vec<Option<String>>,
+}
+
+/// Concurrent updates of an NrsMap, e.g. made from two devices at the same time, which
+/// left the NrsMapContainer with more than one current NrsMap. Until it's resolved with
+/// `Safe::nrs_resolve_conflict`, resolving the names fails with `Error::NrsConflict`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NrsConflict {
+    /// URL of the NrsMapContainer
+    pub url: String,
+    pub branches: Vec<NrsBranch>,
+    /// The names whose links differ across the branches
+    pub diverging: Vec<NrsDivergence>,
+}
+
+impl NrsConflict {
+    pub(crate) fn new(url: String, branches: Vec<NrsBranch>) -> Self {
+        let diverging = diverging_names(&branches);
+        Self {
+            url,
+            branches,
+            diverging,
+        }
+    }
+}
+
+impl fmt::Display for NrsConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NRS map at \"{}\" was updated concurrently, it has {} versions diverging on {} names",
+            self.url,
+            self.branches.len(),
+            self.diverging.len()
+        )
+    }
+}
+
+/// How to resolve a conflict of concurrent updates of an NrsMap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NrsConflictStrategy {
+    /// Keep the NrsMap of the branch with the given version, discarding the others
+    KeepBranch(VersionHash),
+    /// Merge the NrsMaps of all the branches: the names defined in any of them are kept,
+    /// and those with different links take the link of the branch with the given version
+    MergePreferring(VersionHash),
+}
+
+impl Safe {
+    /// Conflict of concurrent updates of the NrsMap of a name's top name, if there's any
+    pub async fn nrs_conflict(&self, name: &str) -> Result<Option<NrsConflict>> {
+        let (safe_url, _) = validate_nrs_name(name)?;
+        match self.nrs_map_container_get(&safe_url.to_string()).await {
+            Ok(_) => Ok(None),
+            Err(Error::NrsConflict(conflict)) => Ok(Some(*conflict)),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// # Resolve a conflict of concurrent updates of an NrsMap
+    ///
+    /// The NrsMap resulting from the strategy is written superseding all the branches of
+    /// the conflict of the name's top name, and returned with its version. Since the history
+    /// of the branches is not known, a name removed in one branch but still defined in
+    /// another one is kept when merging. If there's no conflict, nothing is written and the
+    /// current NrsMap is returned.
+    pub async fn nrs_resolve_conflict(
+        &self,
+        name: &str,
+        strategy: NrsConflictStrategy,
+    ) -> Result<(VersionHash, NrsMap)> {
+        let (safe_url, _) = validate_nrs_name(name)?;
+        let xorurl = safe_url.to_string();
+        let conflict = match self.nrs_map_container_get(&xorurl).await {
+            Ok(current) => return Ok(current),
+            Err(Error::NrsConflict(conflict)) => conflict,
+            Err(err) => return Err(err),
+        };
+        info!(
+            "Resolving conflict of {} NRS map versions with {:?}",
+            conflict.branches.len(),
+            strategy
+        );
+
+        let top_name = safe_url.top_name().to_string();
+        let nrs_map = resolve_branches(&conflict.branches, strategy, &top_name)?;
+        let nrs_map_xorurl = self.store_nrs_map(&nrs_map).await?;
+        let superseded = conflict
+            .branches
+            .iter()
+            .map(|branch| branch.version.entry_hash())
+            .collect();
+        let entry = (
+            top_name.as_bytes().to_owned(),
+            nrs_map_xorurl.as_bytes().to_owned(),
+        );
+        let entry_hash = self.multimap_insert(&xorurl, entry, superseded).await?;
+
+        Ok(((&entry_hash).into(), nrs_map))
+    }
+}
+
+// The names whose links differ across the branches
+fn diverging_names(branches: &[NrsBranch]) -> Vec<NrsDivergence> {
+    let mut diverging = vec![];
+    let defaults: Vec<Option<String>> = branches
+        .iter()
+        .map(|branch| branch.nrs_map.get_default_link().ok())
+        .collect();
+    if defaults.iter().any(|link| *link != defaults[0]) {
+        diverging.push(NrsDivergence {
+            subname: String::new(),
+            links: defaults,
+        });
+    }
+
+    let subnames: Vec<_> = branches
+        .iter()
+        .map(|branch| branch.nrs_map.subnames())
+        .collect();
+    let names: BTreeSet<&String> = subnames.iter().flat_map(|names| names.keys()).collect();
+    for name in names {
+        let links: Vec<Option<String>> = subnames
+            .iter()
+            .map(|names| {
+                names
+                    .get(name)
+                    .and_then(|definition| definition.get(PREDICATE_LINK).cloned())
+            })
+            .collect();
+        if links.iter().any(|link| *link != links[0]) {
+            diverging.push(NrsDivergence {
+                subname: name.clone(),
+                links,
+            });
+        }
+    }
+
+    diverging
+}
+
+// The NrsMap resulting from resolving the branches with the strategy
+fn resolve_branches(
+    branches: &[NrsBranch],
+    strategy: NrsConflictStrategy,
+    top_name: &str,
+) -> Result<NrsMap> {
+    let (version, merge) = match strategy {
+        NrsConflictStrategy::KeepBranch(version) => (version, false),
+        NrsConflictStrategy::MergePreferring(version) => (version, true),
+    };
+    let preferred = branches
+        .iter()
+        .find(|branch| branch.version == version)
+        .ok_or_else(|| {
+            Error::InvalidInput(format!(
+                "Version {} is not one of the branches of the NRS map conflict",
+                version
+            ))
+        })?;
+
+    let mut nrs_map = preferred.nrs_map.clone();
+    if !merge {
+        return Ok(nrs_map);
+    }
+
+    for branch in branches.iter().filter(|branch| branch.version != version) {
+        let defined = nrs_map.subnames();
+        for (subname, definition) in branch.nrs_map.subnames() {
+            if subname.is_empty() || defined.contains_key(&subname) {
+                continue;
+            }
+            let link = match definition.get(PREDICATE_LINK) {
+                Some(link) => link,
+                None => continue,
+            };
+            let requirement = if definition
+                .get(PREDICATE_FOLLOW_LATEST)
+                .map_or(false, |value| value == "true")
+            {
+                NrsVersionRequirement::FollowLatest
+            } else {
+                NrsVersionRequirement::Strict
+            };
+            let _ = nrs_map.update_with(
+                &format!("{}.{}", subname, top_name),
+                link,
+                false,
+                false,
+                requirement,
+            )?;
+        }
+
+        if nrs_map.default == DefaultRdf::NotSet {
+            nrs_map.default = branch.nrs_map.default.clone();
+        }
+
+        for include in branch.nrs_map.includes.iter() {
+            if !nrs_map.includes.iter().any(|own| own.url == include.url) {
+                nrs_map.includes.push(include.clone());
+            }
+        }
+    }
+
+    Ok(nrs_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::test_helpers::{new_safe_instance, random_nrs_name},
+        register::EntryHash,
+        retry_loop, retry_loop_for_pattern, BytesAddress, ContentType, Url, XorName,
+        DEFAULT_XORURL_BASE,
+    };
+    use anyhow::{anyhow, Result};
+    use bytes::Bytes;
+
+    fn version(byte: u8) -> VersionHash {
+        let hash: EntryHash = [byte; 32];
+        (&hash).into()
+    }
+
+    fn link_of(nrs_map: &NrsMap, subname: &str) -> Option<String> {
+        nrs_map
+            .subnames()
+            .get(subname)
+            .and_then(|definition| definition.get(PREDICATE_LINK).cloned())
+    }
+
+    fn blob_link() -> Result<String> {
+        Ok(Url::encode_bytes(
+            BytesAddress::Public(XorName::random()),
+            ContentType::Raw,
+            DEFAULT_XORURL_BASE,
+        )?)
+    }
+
+    #[test]
+    fn test_nrs_conflict_diverging_and_merge() -> Result<()> {
+        let (shared, first, second, ours, theirs) = (
+            blob_link()?,
+            blob_link()?,
+            blob_link()?,
+            blob_link()?,
+            blob_link()?,
+        );
+        let mut base = NrsMap::default();
+        let _ = base.update("shared.top", &shared, true, false)?;
+
+        let mut ours_map = base.clone();
+        let _ = ours_map.update("first.top", &first, false, false)?;
+        let _ = ours_map.update("both.top", &ours, false, false)?;
+        let mut theirs_map = base;
+        let _ = theirs_map.update("second.top", &second, false, false)?;
+        let _ = theirs_map.update("both.top", &theirs, false, false)?;
+
+        let ours_version = version(1);
+        let theirs_version = version(2);
+        let conflict = NrsConflict::new(
+            "safe://top".to_string(),
+            vec![
+                NrsBranch {
+                    version: ours_version,
+                    nrs_map: ours_map.clone(),
+                },
+                NrsBranch {
+                    version: theirs_version,
+                    nrs_map: theirs_map,
+                },
+            ],
+        );
+        let diverging: Vec<&str> = conflict
+            .diverging
+            .iter()
+            .map(|divergence| divergence.subname.as_str())
+            .collect();
+        assert_eq!(diverging, vec!["both", "first", "second"]);
+        assert_eq!(
+            conflict.diverging[0].links,
+            vec![Some(ours.clone()), Some(theirs)]
+        );
+
+        let kept = resolve_branches(
+            &conflict.branches,
+            NrsConflictStrategy::KeepBranch(ours_version),
+            "top",
+        )?;
+        assert_eq!(kept, ours_map);
+
+        let merged = resolve_branches(
+            &conflict.branches,
+            NrsConflictStrategy::MergePreferring(ours_version),
+            "top",
+        )?;
+        assert_eq!(link_of(&merged, "both"), Some(ours));
+        assert_eq!(link_of(&merged, "first"), Some(first));
+        assert_eq!(link_of(&merged, "second"), Some(second));
+        assert_eq!(merged.get_default_link()?, shared);
+
+        assert!(resolve_branches(
+            &conflict.branches,
+            NrsConflictStrategy::KeepBranch(version(3)),
+            "top",
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nrs_resolve_conflict() -> Result<()> {
+        let site_name = random_nrs_name();
+        let url = format!("safe://{}", site_name);
+        let mut safe = new_safe_instance().await?;
+        let link = safe
+            .store_public_bytes(Bytes::from("linked"), None, false)
+            .await?;
+        let (_, _, _) =
+            retry_loop!(safe.nrs_map_container_create(&site_name, &link, true, false, false));
+        let (version, nrs_map) = retry_loop!(safe.nrs_map_container_get(&url));
+
+        // two devices update the NrsMap from the same version
+        for subname in ["a", "b"].iter() {
+            let mut updated = nrs_map.clone();
+            let _ = updated.update(&format!("{}.{}", subname, site_name), &link, false, false)?;
+            let nrs_map_xorurl = safe.store_nrs_map(&updated).await?;
+            let _ = safe
+                .multimap_insert(
+                    &url,
+                    (
+                        site_name.as_bytes().to_owned(),
+                        nrs_map_xorurl.as_bytes().to_owned(),
+                    ),
+                    vec![version.entry_hash()].into_iter().collect(),
+                )
+                .await?;
+        }
+
+        let conflict = retry_loop_for_pattern!(safe.nrs_conflict(&site_name), Ok(Some(_)))?
+            .ok_or_else(|| anyhow!("The concurrent updates weren't detected"))?;
+        assert_eq!(conflict.branches.len(), 2);
+        let diverging: Vec<&str> = conflict
+            .diverging
+            .iter()
+            .map(|divergence| divergence.subname.as_str())
+            .collect();
+        assert_eq!(diverging, vec!["a", "b"]);
+        assert!(matches!(
+            safe.nrs_map_container_get(&url).await,
+            Err(Error::NrsConflict(_))
+        ));
+
+        let preferred = conflict.branches[0].version;
+        let (_, merged) = safe
+            .nrs_resolve_conflict(&site_name, NrsConflictStrategy::MergePreferring(preferred))
+            .await?;
+        assert!(link_of(&merged, "a").is_some());
+        assert!(link_of(&merged, "b").is_some());
+        let _ = retry_loop_for_pattern!(safe.nrs_conflict(&site_name), Ok(None))?;
+
+        Ok(())
+    }
+}
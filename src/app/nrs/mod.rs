@@ -7,30 +7,75 @@
 // specific language governing permissions and limitations relating to use of the SAFE Network
 // Software.
 
+mod compose;
+mod conflict;
 mod nrs_map;
-
-pub use nrs_map::{DefaultRdf, NrsMap};
+mod receipts;
+mod zone;
+
+pub use compose::{NrsInclude, NrsPrecedence};
+pub use conflict::{NrsBranch, NrsConflict, NrsConflictStrategy, NrsDivergence};
+pub(crate) use nrs_map::validate_nrs_link;
+pub use nrs_map::{DefaultRdf, NrsMap, NrsVersionRequirement};
+pub use receipts::{NrsRegistrationProof, NrsRegistrationReceipt};
 pub use safe_network::url::{ContentType, VersionHash};
+pub use zone::{NrsZone, NrsZoneRecord};
 
 use crate::{
     app::{
-        consts::{CONTENT_ADDED_SIGN, CONTENT_DELETED_SIGN},
+        consts::{CONTENT_ADDED_SIGN, CONTENT_DELETED_SIGN, CONTENT_UPDATED_SIGN},
+        register::QuarantineKind,
         Safe,
     },
-    Error, Result, Url, XorUrl,
+    DataType, Error, IndexedKind, Result, Scope, Url, XorUrl,
 };
 use bytes::{Buf, Bytes};
+use futures::{stream, StreamExt};
 use log::{debug, info, warn};
 use std::collections::{BTreeMap, BTreeSet};
 
 // Type tag to use for the NrsMapContainer stored on Register
 pub(crate) const NRS_MAP_TYPE_TAG: u64 = 1_500;
 
+// Default maximum number of top names registered concurrently by `nrs_create_batch`
+const DEFAULT_BATCH_CONCURRENCY: usize = 8;
+
 const ERROR_MSG_NO_NRS_MAP_FOUND: &str = "No NRS Map found at this address";
 
 // List of public names uploaded with details if they were added, updated or deleted from NrsMaps
 pub type ProcessedEntries = BTreeMap<String, (String, String)>;
 
+/// Outcome of the registration of each top name by `nrs_create_batch`
+#[derive(Debug, Clone, PartialEq)]
+pub enum NrsBatchOutcome {
+    /// The name was registered, with the versioned XOR-URL of its NrsMapContainer
+    Created(XorUrl),
+    /// The name was already registered with this instance's keypair, e.g. by a previous run
+    AlreadyRegistered(XorUrl),
+    /// The name couldn't be registered, with the reason
+    Failed(String),
+}
+
+/// An operation on the names of a top name, see `Safe::nrs_batch`
+#[derive(Debug, Clone, PartialEq)]
+pub enum NrsOp {
+    /// Link a sub name which has no link yet
+    Add { subname: String, link: String },
+    /// Change the link of a sub name which has one
+    Update { subname: String, link: String },
+    /// Remove the link of a sub name
+    Remove { subname: String },
+}
+
+/// A page of the sub names of a top name, see `Safe::nrs_list_subnames`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NrsSubnamesPage {
+    /// The NRS names, e.g. `a.b.<top name>`, with their definition
+    pub subnames: Vec<(String, BTreeMap<String, String>)>,
+    /// Cursor to list the next page from, if there are more sub names
+    pub cursor: Option<String>,
+}
+
 impl Safe {
     pub fn parse_url(url: &str) -> Result<Url> {
         let safe_url = Url::from_url(&sanitised_url(url))?;
@@ -85,11 +130,15 @@ impl Safe {
         info!("Adding to NRS map...");
         // GET current NRS map from name's TLD
         let (safe_url, _) = validate_nrs_name(name)?;
+        if self.verify_nrs_links {
+            self.verify_nrs_link(link).await?;
+        }
         let xorurl = safe_url.to_string();
         let (version, mut nrs_map) = self.nrs_map_container_get(&xorurl).await?;
         debug!("NRS, Existing data: {:?}", nrs_map);
 
-        let link = nrs_map.update(name, link, default, hard_link)?;
+        let link =
+            nrs_map.update_with(name, link, default, hard_link, self.nrs_version_requirement)?;
         let mut processed_entries = ProcessedEntries::new();
         processed_entries.insert(name.to_string(), (CONTENT_ADDED_SIGN.to_string(), link));
         debug!("The new NRS Map: {:?}", nrs_map);
@@ -109,6 +158,8 @@ impl Safe {
         );
         let entry_hash = &self.multimap_insert(&xorurl, entry, old_values).await?;
         let new_version: VersionHash = entry_hash.into();
+        self.local_index
+            .insert(IndexedKind::NrsName, name, &format!("safe://{}", name));
 
         Ok((new_version, xorurl, processed_entries, nrs_map))
     }
@@ -141,16 +192,20 @@ impl Safe {
         dry_run: bool,
     ) -> Result<(XorUrl, ProcessedEntries, NrsMap)> {
         info!("Creating an NRS map");
-        let (_, nrs_url) = validate_nrs_name(name)?;
+        let (safe_url, nrs_url) = validate_nrs_name(name)?;
         if self.nrs_map_container_get(&nrs_url).await.is_ok() {
             return Err(Error::ContentError(
                 "NRS name already exists. Please use 'nrs add' command to add sub names to it"
                     .to_string(),
             ));
         }
+        if self.verify_nrs_links {
+            self.verify_nrs_link(link).await?;
+        }
 
         let mut nrs_map = NrsMap::default();
-        let link = nrs_map.update(name, link, default, hard_link)?;
+        let link =
+            nrs_map.update_with(name, link, default, hard_link, self.nrs_version_requirement)?;
         let mut processed_entries = ProcessedEntries::new();
         processed_entries.insert(name.to_string(), (CONTENT_ADDED_SIGN.to_string(), link));
         debug!("The new NRS Map: {:?}", nrs_map);
@@ -184,6 +239,16 @@ impl Safe {
         tmp_url.set_content_type(ContentType::NrsMapContainer)?;
         let new_xor_url = format!("{}", &tmp_url);
 
+        // The receipt is only a record of the registration, the name is registered already
+        if let Err(err) = self
+            .store_nrs_registration_receipt(safe_url.top_name(), &tmp_url)
+            .await
+        {
+            warn!("Failed to store registration receipt of {}: {}", name, err);
+        }
+        self.local_index
+            .insert(IndexedKind::NrsName, name, &format!("safe://{}", name));
+
         Ok((new_xor_url, processed_entries, nrs_map))
     }
 
@@ -226,10 +291,215 @@ impl Safe {
         );
         let entry_hash = &self.multimap_insert(&xorurl, entry, old_values).await?;
         let new_version: VersionHash = entry_hash.into();
+        self.local_index
+            .remove(IndexedKind::NrsName, &format!("safe://{}", name));
+
+        Ok((new_version, xorurl, processed_entries, nrs_map))
+    }
+
+    /// # Remove many names from a NrsMapContainer at once
+    ///
+    /// All the names need to be sub names of the same top name, they are all removed in a
+    /// single new version of the NrsMapContainer. If `dry_run` is set, nothing is written
+    /// and the current version is returned along with the NRS map the removals would result in.
+    pub async fn nrs_remove_batch(
+        &self,
+        names: &[&str],
+        dry_run: bool,
+    ) -> Result<(VersionHash, XorUrl, ProcessedEntries, NrsMap)> {
+        info!("Removing {} names from NRS map...", names.len());
+        let first_name = names.first().ok_or_else(|| {
+            Error::InvalidInput("No NRS names provided to be removed".to_string())
+        })?;
+        let (safe_url, _) = validate_nrs_name(first_name)?;
+        for name in names.iter() {
+            let (name_url, _) = validate_nrs_name(name)?;
+            if name_url.top_name() != safe_url.top_name() {
+                return Err(Error::InvalidInput(format!(
+                    "All NRS names to be removed need to share the same top name, '{}' doesn't belong to '{}'",
+                    name,
+                    safe_url.top_name()
+                )));
+            }
+        }
+
+        let xorurl = safe_url.to_string();
+        let (version, mut nrs_map) = self.nrs_map_container_get(&xorurl).await?;
+        debug!("NRS, Existing data: {:?}", nrs_map);
+
+        let mut processed_entries = ProcessedEntries::new();
+        for name in names.iter() {
+            let removed_link = nrs_map.nrs_map_remove_subname(name)?;
+            processed_entries.insert(
+                name.to_string(),
+                (CONTENT_DELETED_SIGN.to_string(), removed_link),
+            );
+        }
+
+        if dry_run {
+            return Ok((version, xorurl, processed_entries, nrs_map));
+        }
+
+        let mut old_values = BTreeSet::new();
+        old_values.insert(version.entry_hash());
+        let nrs_map_xorurl = self.store_nrs_map(&nrs_map).await?;
+        let entry = (
+            safe_url.top_name().as_bytes().to_owned(),
+            nrs_map_xorurl.as_bytes().to_owned(),
+        );
+        let entry_hash = &self.multimap_insert(&xorurl, entry, old_values).await?;
+        let new_version: VersionHash = entry_hash.into();
+        for name in names.iter() {
+            self.local_index
+                .remove(IndexedKind::NrsName, &format!("safe://{}", name));
+        }
 
         Ok((new_version, xorurl, processed_entries, nrs_map))
     }
 
+    /// # Apply many operations to the names of a top name at once
+    ///
+    /// The sub names of the operations are relative to the top name, e.g. `a.b` for
+    /// `a.b.<top name>`, an empty one being the top name itself. The operations are applied
+    /// in order to the current NrsMap, and written in a single new version of the
+    /// NrsMapContainer, thus if any of them fails, e.g. adding a sub name which has a link
+    /// already, none of them is written. If `dry_run` is set, nothing is written and the
+    /// current version is returned along with the NrsMap the operations would result in.
+    pub async fn nrs_batch(
+        &self,
+        top_name: &str,
+        ops: Vec<NrsOp>,
+        dry_run: bool,
+    ) -> Result<(VersionHash, XorUrl, ProcessedEntries, NrsMap)> {
+        info!("Applying {} operations to NRS map...", ops.len());
+        let (safe_url, _) = validate_nrs_name(top_name)?;
+        if !safe_url.sub_names_vec().is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "'{}' is not a top name",
+                top_name
+            )));
+        }
+        let top_name = safe_url.top_name().to_string();
+        let xorurl = safe_url.to_string();
+        let (version, mut nrs_map) = self.nrs_map_container_get(&xorurl).await?;
+        debug!("NRS, Existing data: {:?}", nrs_map);
+
+        let mut processed_entries = ProcessedEntries::new();
+        for op in ops.iter() {
+            let subname = match op {
+                NrsOp::Add { subname, .. }
+                | NrsOp::Update { subname, .. }
+                | NrsOp::Remove { subname } => subname,
+            };
+            let name = if subname.is_empty() {
+                top_name.clone()
+            } else {
+                format!("{}.{}", subname, top_name)
+            };
+            let _ = validate_nrs_name(&name)?;
+            let has_link = if subname.is_empty() {
+                nrs_map.default != DefaultRdf::NotSet
+            } else {
+                nrs_map.subnames().contains_key(subname)
+            };
+
+            let (sign, link) = match op {
+                NrsOp::Add { .. } if has_link => {
+                    return Err(Error::ContentError(format!(
+                        "NRS name '{}' already has a link",
+                        name
+                    )))
+                }
+                NrsOp::Update { .. } | NrsOp::Remove { .. } if !has_link => {
+                    return Err(Error::ContentError(format!(
+                        "NRS name '{}' has no link",
+                        name
+                    )))
+                }
+                NrsOp::Add { link, .. } | NrsOp::Update { link, .. } => {
+                    if self.verify_nrs_links {
+                        self.verify_nrs_link(link).await?;
+                    }
+                    let link = nrs_map.update_with(
+                        &name,
+                        link,
+                        false,
+                        false,
+                        self.nrs_version_requirement,
+                    )?;
+                    let sign = match (op, processed_entries.get(&name)) {
+                        (NrsOp::Update { .. }, _) | (_, Some(_)) => CONTENT_UPDATED_SIGN,
+                        _ => CONTENT_ADDED_SIGN,
+                    };
+                    (sign, link)
+                }
+                NrsOp::Remove { .. } => {
+                    (CONTENT_DELETED_SIGN, nrs_map.nrs_map_remove_subname(&name)?)
+                }
+            };
+            let _ = processed_entries.insert(name, (sign.to_string(), link));
+        }
+
+        if dry_run || processed_entries.is_empty() {
+            return Ok((version, xorurl, processed_entries, nrs_map));
+        }
+
+        let new_version = self
+            .write_nrs_map_version(&xorurl, &top_name, version, &nrs_map, &processed_entries)
+            .await?;
+        Ok((new_version, xorurl, processed_entries, nrs_map))
+    }
+
+    /// # Register many top names at once
+    ///
+    /// Each name is registered with an empty NRS map, so links can be added to it later
+    /// with `nrs_map_container_add`. The outcome of each name is reported, thus the failure
+    /// to register some of the names doesn't prevent the rest from being registered.
+    /// Names already registered with this instance's keypair are reported as such, so
+    /// the same batch can be safely run again, e.g. to retry the names which failed.
+    pub async fn nrs_create_batch(
+        &self,
+        names: Vec<String>,
+    ) -> Result<BTreeMap<String, NrsBatchOutcome>> {
+        self.nrs_create_batch_with(names, DEFAULT_BATCH_CONCURRENCY)
+            .await
+    }
+
+    /// # Register many top names at once, with up to `max_concurrent` of them concurrently
+    ///
+    /// See `nrs_create_batch`.
+    pub async fn nrs_create_batch_with(
+        &self,
+        names: Vec<String>,
+        max_concurrent: usize,
+    ) -> Result<BTreeMap<String, NrsBatchOutcome>> {
+        if max_concurrent == 0 {
+            return Err(Error::InvalidInput(
+                "The maximum number of names to register concurrently must be greater than zero"
+                    .to_string(),
+            ));
+        }
+        let names: BTreeSet<String> = names.into_iter().collect();
+        info!("Registering a batch of {} NRS top names...", names.len());
+
+        let outcomes = stream::iter(names)
+            .map(|name| async move {
+                let outcome = match self.nrs_create_empty(&name).await {
+                    Ok(outcome) => outcome,
+                    Err(err) => {
+                        warn!("Failed to register NRS top name {}: {}", name, err);
+                        NrsBatchOutcome::Failed(err.to_string())
+                    }
+                };
+                (name, outcome)
+            })
+            .buffer_unordered(max_concurrent)
+            .collect()
+            .await;
+
+        Ok(outcomes)
+    }
+
     /// # Fetch an existing NrsMapContainer.
     ///
     /// ## Example
@@ -265,23 +535,51 @@ impl Safe {
                 err => Error::NetDataError(format!("Failed to get current version: {}", err)),
             })?;
 
-        // take the 1st entry (TODO Multiple entries)
-        if entries.len() > 1 {
-            return Err(Error::MultimapFork("Multiple NRS map entries not managed, this happends when 2 clients write concurrently to a NRS map".to_string()));
+        // decode the NrsMap of each entry, quarantining those which can't be,
+        // so a corrupt entry doesn't make the name unresolvable
+        let mut nrs_maps = Vec::with_capacity(entries.len());
+        let mut quarantined = 0;
+        for (hash, (_name, nrs_map_xorurl_bytes)) in entries.iter() {
+            match self.fetch_nrs_map(nrs_map_xorurl_bytes).await {
+                Ok(nrs_map) => nrs_maps.push((VersionHash::from(hash), nrs_map)),
+                Err(err) => {
+                    self.quarantine_entry(&safe_url, *hash, QuarantineKind::NrsMap, err)?;
+                    quarantined += 1;
+                }
+            }
         }
-        let first_entry = entries.iter().next();
-        let (version, nrs_map_xorurl_bytes);
-        if let Some((v, (_name, m))) = first_entry {
-            version = v.into();
-            nrs_map_xorurl_bytes = m.to_owned();
-        } else {
-            warn!(
-                "NRS map Register found at XOR name \"{:?}\" was empty",
-                safe_url.xorname()
-            );
-            return Ok((VersionHash::default(), NrsMap::default()));
+
+        // concurrent updates are surfaced for the user to resolve them
+        if nrs_maps.len() > 1 {
+            let branches = nrs_maps
+                .into_iter()
+                .map(|(version, nrs_map)| NrsBranch { version, nrs_map })
+                .collect();
+            let conflict = NrsConflict::new(safe_url.to_string(), branches);
+            warn!("{}", conflict);
+            return Err(Error::NrsConflict(Box::new(conflict)));
+        }
+        match nrs_maps.pop() {
+            Some((version, nrs_map)) => {
+                debug!("Nrs map v{} retrieved", version);
+                Ok((version, nrs_map))
+            }
+            None if quarantined > 0 => Err(Error::ContentError(format!(
+                "None of the {} NRS map entries found at \"{}\" could be decoded, they were quarantined",
+                quarantined, safe_url
+            ))),
+            None => {
+                warn!(
+                    "NRS map Register found at XOR name \"{:?}\" was empty",
+                    safe_url.xorname()
+                );
+                Ok((VersionHash::default(), NrsMap::default()))
+            }
         }
+    }
 
+    // Fetch and deserialise the NrsMap linked from an entry of an NrsMapContainer
+    async fn fetch_nrs_map(&self, nrs_map_xorurl_bytes: &[u8]) -> Result<NrsMap> {
         // get the xorurl from nrs container
         let url = String::from_utf8(nrs_map_xorurl_bytes.to_owned()).map_err(|err| {
             Error::ContentError(format!(
@@ -290,21 +588,206 @@ impl Safe {
             ))
         })?;
         debug!("Deserialised NrsMap XOR-URL: {}", url);
-        let nrs_map_xorurl = Url::from_url(&url)?;
+        let nrs_map_xorurl = Url::from_url(&url).map_err(|err| {
+            Error::ContentError(format!(
+                "Invalid NrsMap link stored in the NrsMapContainer: {}",
+                err
+            ))
+        })?;
 
         // Using the NrsMap XOR-URL we can now fetch the NrsMap and deserialise it
         let serialised_nrs_map = self.fetch_public_data(&nrs_map_xorurl, None).await?;
+        serde_json::from_str(&String::from_utf8_lossy(serialised_nrs_map.chunk())).map_err(|err| {
+            Error::ContentError(format!(
+                "Couldn't deserialise the NrsMap stored in the NrsContainer: {:?}",
+                err
+            ))
+        })
+    }
 
-        debug!("Nrs map v{} retrieved: {:?} ", version, &serialised_nrs_map);
-        let nrs_map = serde_json::from_str(&String::from_utf8_lossy(serialised_nrs_map.chunk()))
-            .map_err(|err| {
-                Error::ContentError(format!(
-                    "Couldn't deserialise the NrsMap stored in the NrsContainer: {:?}",
-                    err
-                ))
-            })?;
+    /// # List the sub names of a top name
+    ///
+    /// Returns the sub names which have a definition, sorted by name, only those starting
+    /// with the prefix if provided, e.g. `a.b` lists `a.b.<top name>` and `a.bc.<top name>`.
+    /// Up to `limit` names are returned, from after the `cursor` of the previous page if
+    /// provided, so large maps can be browsed page by page. The NrsMap is stored in a single
+    /// Blob, thus it's fetched whole, but only the page requested is returned.
+    pub async fn nrs_list_subnames(
+        &self,
+        name: &str,
+        prefix_filter: Option<&str>,
+        limit: Option<usize>,
+        cursor: Option<&str>,
+    ) -> Result<NrsSubnamesPage> {
+        let (safe_url, _) = validate_nrs_name(name)?;
+        let top_name = safe_url.top_name().to_string();
+        let (_, nrs_map) = self.nrs_map_container_get(&safe_url.to_string()).await?;
+
+        let mut remaining = nrs_map
+            .subnames()
+            .into_iter()
+            .filter(|(subname, _)| prefix_filter.map_or(true, |prefix| subname.starts_with(prefix)))
+            .filter(|(subname, _)| cursor.map_or(true, |cursor| subname.as_str() > cursor));
+        let page: Vec<(String, BTreeMap<String, String>)> = remaining
+            .by_ref()
+            .take(limit.unwrap_or(usize::MAX))
+            .collect();
+        let cursor = if remaining.next().is_some() {
+            page.last().map(|(subname, _)| subname.clone())
+        } else {
+            None
+        };
+
+        Ok(NrsSubnamesPage {
+            subnames: page
+                .into_iter()
+                .map(|(subname, definition)| (format!("{}.{}", subname, top_name), definition))
+                .collect(),
+            cursor,
+        })
+    }
+
+    /// # Verify a link to be associated to an NRS name
+    ///
+    /// Besides the checks made on any link associated to an NRS name, i.e. that it specifies
+    /// a version if the content is versionable, the target is fetched to verify that it
+    /// exists, that it's of the type the link declares, and that the version the link
+    /// specifies is valid for it. Only the first byte of Blobs is fetched.
+    /// This is done upon associating links if enabled with `set_verify_nrs_links`.
+    pub async fn verify_nrs_link(&self, link: &str) -> Result<()> {
+        validate_nrs_link(link)?;
+        let mut link_url = Safe::parse_url(link)?;
+        link_url.set_path("");
+        debug!("Verifying NRS link target: {}", link_url);
+
+        let verification = if !link_url.is_xorurl() {
+            self.parse_and_resolve_url(link).await.map(|_| ())
+        } else {
+            match (link_url.content_type(), link_url.data_type()) {
+                (ContentType::FilesContainer, _) => {
+                    self.fetch_files_container(&link_url).await.map(|_| ())
+                }
+                (ContentType::NrsMapContainer, _) => self
+                    .nrs_map_container_get(&link_url.to_string())
+                    .await
+                    .map(|_| ()),
+                (ContentType::Multimap, _) => {
+                    self.fetch_multimap_values(&link_url).await.map(|_| ())
+                }
+                (_, DataType::Register) => self.fetch_register_entries(&link_url).await.map(|_| ()),
+                (_, DataType::Bytes) => self
+                    .fetch_public_data(&link_url, Some((Some(0), Some(1))))
+                    .await
+                    .map(|_| ()),
+                (_, DataType::SafeKey) => Ok(()),
+            }
+        };
+
+        verification.map_err(|err| {
+            Error::InvalidInput(format!(
+                "The target of the link \"{}\" couldn't be verified: {}",
+                link, err
+            ))
+        })
+    }
+
+    // Private helper to register a top name with an empty NrsMap, unless it's
+    // already registered with this instance's keypair
+    async fn nrs_create_empty(&self, name: &str) -> Result<NrsBatchOutcome> {
+        let (safe_url, nrs_url) = validate_nrs_name(name)?;
+        if !safe_url.sub_names_vec().is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "Only top names can be registered, '{}' has sub names",
+                name
+            )));
+        }
+
+        let nrs_xorname = safe_url.xorname();
+        let xorurl = match self.nrs_map_container_get(&nrs_url).await {
+            Ok((version, _)) if version != VersionHash::default() => {
+                let bundle = self.nrs_registration_proof(name).await.map_err(|_| {
+                    Error::ContentError(format!("NRS name '{}' is already registered", name))
+                })?;
+                if bundle.receipt.registrant != self.signer_public_key()? {
+                    return Err(Error::ContentError(format!(
+                        "NRS name '{}' is already registered by another key",
+                        name
+                    )));
+                }
+                return Ok(NrsBatchOutcome::AlreadyRegistered(
+                    bundle.receipt.creation_point,
+                ));
+            }
+            Ok(_) => {
+                // a previous attempt created the container but failed to write the
+                // NrsMap, which only succeeds now if it was created with our keypair
+                debug!("Resuming registration of NRS name '{}'", name);
+                Url::encode_register(
+                    nrs_xorname,
+                    NRS_MAP_TYPE_TAG,
+                    Scope::Public,
+                    ContentType::Multimap,
+                    self.xorurl_base,
+                )?
+            }
+            Err(_) => {
+                self.multimap_create(Some(nrs_xorname), NRS_MAP_TYPE_TAG, false)
+                    .await?
+            }
+        };
+
+        let nrs_map_xorurl = self.store_nrs_map(&NrsMap::default()).await?;
+        let entry = (
+            safe_url.top_name().as_bytes().to_owned(),
+            nrs_map_xorurl.as_bytes().to_owned(),
+        );
+        let entry_hash = &self
+            .multimap_insert(&xorurl, entry, BTreeSet::new())
+            .await?;
+
+        let mut versioned_url = Url::from_xorurl(&xorurl)?;
+        versioned_url.set_content_version(Some(VersionHash::from(entry_hash)));
+        versioned_url.set_content_type(ContentType::NrsMapContainer)?;
+        if let Err(err) = self
+            .store_nrs_registration_receipt(safe_url.top_name(), &versioned_url)
+            .await
+        {
+            warn!("Failed to store registration receipt of {}: {}", name, err);
+        }
+        self.local_index
+            .insert(IndexedKind::NrsName, name, &format!("safe://{}", name));
+
+        Ok(NrsBatchOutcome::Created(versioned_url.to_string()))
+    }
+
+    // Private helper to write an NrsMap as the new version of an NrsMapContainer,
+    // superseding the given version, and index the names added and removed
+    async fn write_nrs_map_version(
+        &self,
+        xorurl: &str,
+        top_name: &str,
+        version: VersionHash,
+        nrs_map: &NrsMap,
+        processed_entries: &ProcessedEntries,
+    ) -> Result<VersionHash> {
+        let nrs_map_xorurl = self.store_nrs_map(nrs_map).await?;
+        let entry = (
+            top_name.as_bytes().to_owned(),
+            nrs_map_xorurl.as_bytes().to_owned(),
+        );
+        let old_values = vec![version.entry_hash()].into_iter().collect();
+        let entry_hash = &self.multimap_insert(xorurl, entry, old_values).await?;
+        for (name, (sign, _)) in processed_entries.iter() {
+            let name_url = format!("safe://{}", name);
+            if sign == CONTENT_DELETED_SIGN {
+                self.local_index.remove(IndexedKind::NrsName, &name_url);
+            } else {
+                self.local_index
+                    .insert(IndexedKind::NrsName, name, &name_url);
+            }
+        }
 
-        Ok((version, nrs_map))
+        Ok(entry_hash.into())
     }
 
     // Private helper to serialise an NrsMap and store it in a Public Blob
@@ -327,7 +810,7 @@ impl Safe {
     }
 }
 
-fn validate_nrs_name(name: &str) -> Result<(Url, String)> {
+pub(crate) fn validate_nrs_name(name: &str) -> Result<(Url, String)> {
     // validate no slashes in name.
     if name.find('/').is_some() {
         let msg = "The NRS name/subname cannot contain a slash".to_string();
@@ -359,6 +842,7 @@ mod tests {
             consts::PREDICATE_LINK,
             test_helpers::{new_safe_instance, random_nrs_name},
         },
+        fetch::SafeData,
         retry_loop, retry_loop_for_pattern,
     };
     use anyhow::{anyhow, bail, Result};
@@ -446,6 +930,95 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_nrs_map_container_quarantines_corrupt_entry() -> Result<()> {
+        let site_name = random_nrs_name();
+        let mut safe = new_safe_instance().await?;
+        let (link, _, _) = safe
+            .files_container_create(None, None, true, true, false)
+            .await?;
+        let (version0, _) = retry_loop!(safe.files_container_get(&link));
+        let link_v0 = format!("{}?v={}", link, version0);
+        let (xorurl, _, _) =
+            retry_loop!(safe.nrs_map_container_create(&site_name, &link_v0, true, false, false));
+        let mut container_url = Url::from_url(&xorurl)?;
+        container_url.set_content_version(None);
+        let container = container_url.to_string();
+
+        // a concurrent write of garbage doesn't make the name unresolvable
+        let garbage_xorurl = safe
+            .store_public_bytes(Bytes::from("garbage"), None, false)
+            .await?;
+        let garbage = safe
+            .write_to_register(&container, Url::from_url(&garbage_xorurl)?, BTreeSet::new())
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_read(&container), Ok(entries) if entries.len() == 2)?;
+
+        let (_, nrs_map) = safe.nrs_map_container_get(&container).await?;
+        assert_eq!(nrs_map.get_default_link()?, link_v0);
+        let quarantined = safe.register_quarantined(&container).await?;
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].hash, garbage);
+        assert_eq!(quarantined[0].kind, QuarantineKind::MultimapEntry);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nrs_list_subnames() -> Result<()> {
+        let site_name = random_nrs_name();
+        let mut safe = new_safe_instance().await?;
+        let link = format!("safe://linked-from-site_name?v={}", VersionHash::default());
+
+        let _ = retry_loop!(safe.nrs_map_container_create(
+            &format!("b.{}", site_name),
+            &link,
+            true,
+            false,
+            false
+        ));
+        for subname in ["a.b", "c", "bc"].iter() {
+            let _ = retry_loop!(safe.nrs_map_container_add(
+                &format!("{}.{}", subname, site_name),
+                &link,
+                false,
+                false,
+                false
+            ));
+        }
+
+        let listed = |page: &NrsSubnamesPage| -> Vec<String> {
+            page.subnames.iter().map(|(name, _)| name.clone()).collect()
+        };
+        let all = safe.nrs_list_subnames(&site_name, None, None, None).await?;
+        assert_eq!(
+            listed(&all),
+            vec![
+                format!("a.b.{}", site_name),
+                format!("b.{}", site_name),
+                format!("bc.{}", site_name),
+                format!("c.{}", site_name),
+            ]
+        );
+        assert_eq!(all.cursor, None);
+        assert!(all
+            .subnames
+            .iter()
+            .all(|(_, definition)| definition.get(PREDICATE_LINK) == Some(&link)));
+
+        let first_page = safe
+            .nrs_list_subnames(&site_name, Some("b"), Some(1), None)
+            .await?;
+        assert_eq!(listed(&first_page), vec![format!("b.{}", site_name)]);
+        let second_page = safe
+            .nrs_list_subnames(&site_name, Some("b"), Some(1), first_page.cursor.as_deref())
+            .await?;
+        assert_eq!(listed(&second_page), vec![format!("bc.{}", site_name)]);
+        assert_eq!(second_page.cursor, None);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_nrs_map_container_add_or_remove_with_versioned_target() -> Result<()> {
         let site_name = random_nrs_name();
@@ -565,6 +1138,269 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_nrs_remove_batch() -> Result<()> {
+        let site_name = random_nrs_name();
+        let mut safe = new_safe_instance().await?;
+
+        let (link, _, _) = safe
+            .files_container_create(None, None, true, true, false)
+            .await?;
+        let (version0, _) = retry_loop!(safe.files_container_get(&link));
+        let link_v0 = format!("{}?v={}", link, version0);
+
+        let (xorurl, _, _) = retry_loop!(safe.nrs_map_container_create(
+            &format!("a.{}", site_name),
+            &link_v0,
+            true,
+            false,
+            false,
+        ));
+        let _ = retry_loop!(safe.fetch(&xorurl, None));
+        let mut version = version0;
+        for sub_name in ["b", "c"].iter() {
+            let (new_version, _, _, _) = retry_loop!(safe.nrs_map_container_add(
+                &format!("{}.{}", sub_name, site_name),
+                &link_v0,
+                false,
+                false,
+                false
+            ));
+            let _ = retry_loop_for_pattern!(safe.nrs_map_container_get(&xorurl), Ok((v, _)) if *v == new_version)?;
+            version = new_version;
+        }
+
+        let names = vec![format!("a.{}", site_name), format!("b.{}", site_name)];
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+
+        // a dry run doesn't create a new version
+        let (dry_run_version, _, processed_entries, nrs_map) =
+            safe.nrs_remove_batch(&names, true).await?;
+        assert_eq!(dry_run_version, version);
+        assert_eq!(processed_entries.len(), 2);
+        assert_eq!(nrs_map.sub_names_map.len(), 1);
+
+        let (new_version, _, _, _) = safe.nrs_remove_batch(&names, false).await?;
+        assert_ne!(new_version, version);
+        let (_, nrs_map) = retry_loop_for_pattern!(safe.nrs_map_container_get(&xorurl), Ok((v, _)) if *v == new_version)?;
+        assert_eq!(nrs_map.sub_names_map.len(), 1);
+
+        let other_site = format!("a.{}", random_nrs_name());
+        assert!(safe
+            .nrs_remove_batch(&[names[0], &other_site], true)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nrs_batch() -> Result<()> {
+        let site_name = random_nrs_name();
+        let mut safe = new_safe_instance().await?;
+
+        let (link, _, _) = safe
+            .files_container_create(None, None, true, true, false)
+            .await?;
+        let (version0, _) = retry_loop!(safe.files_container_get(&link));
+        let link_v0 = format!("{}?v={}", link, version0);
+
+        let (xorurl, _, _) = retry_loop!(safe.nrs_map_container_create(
+            &format!("a.{}", site_name),
+            &link_v0,
+            true,
+            false,
+            false,
+        ));
+        let (version, _) = retry_loop!(safe.nrs_map_container_get(&xorurl));
+
+        let ops: Vec<NrsOp> = (0..10)
+            .map(|i| NrsOp::Add {
+                subname: format!("sub{}", i),
+                link: link_v0.clone(),
+            })
+            .chain(vec![
+                NrsOp::Update {
+                    subname: "a".to_string(),
+                    link: link_v0.clone(),
+                },
+                NrsOp::Remove {
+                    subname: "sub9".to_string(),
+                },
+            ])
+            .collect();
+
+        // a dry run doesn't create a new version
+        let (dry_run_version, _, processed_entries, _) =
+            safe.nrs_batch(&site_name, ops.clone(), true).await?;
+        assert_eq!(dry_run_version, version);
+        assert_eq!(processed_entries.len(), 11);
+
+        let (new_version, _, _, _) = safe.nrs_batch(&site_name, ops, false).await?;
+        assert_ne!(new_version, version);
+        let (_, nrs_map) = retry_loop_for_pattern!(safe.nrs_map_container_get(&xorurl), Ok((v, _)) if *v == new_version)?;
+        let subnames = nrs_map.subnames();
+        assert_eq!(subnames.len(), 10);
+        assert!(subnames.contains_key("sub0"));
+        assert!(!subnames.contains_key("sub9"));
+
+        // none of the operations is written if any of them fails
+        let failing = vec![
+            NrsOp::Remove {
+                subname: "sub0".to_string(),
+            },
+            NrsOp::Add {
+                subname: "sub1".to_string(),
+                link: link_v0.clone(),
+            },
+        ];
+        match safe.nrs_batch(&site_name, failing, false).await {
+            Err(Error::ContentError(_)) => {}
+            other => bail!("Unexpected result: {:?}", other),
+        }
+        let (version, _) = safe.nrs_map_container_get(&xorurl).await?;
+        assert_eq!(version, new_version);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nrs_create_batch() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let first = random_nrs_name();
+        let second = random_nrs_name();
+        let with_sub_name = format!("sub.{}", random_nrs_name());
+
+        let outcomes = safe
+            .nrs_create_batch(vec![first.clone(), second.clone(), with_sub_name.clone()])
+            .await?;
+        assert_eq!(outcomes.len(), 3);
+        assert!(matches!(outcomes[&first], NrsBatchOutcome::Created(_)));
+        assert!(matches!(outcomes[&second], NrsBatchOutcome::Created(_)));
+        assert!(matches!(
+            outcomes[&with_sub_name],
+            NrsBatchOutcome::Failed(_)
+        ));
+
+        let (version, nrs_map) = retry_loop!(safe.nrs_map_container_get(&first));
+        assert_ne!(version, VersionHash::default());
+        assert_eq!(nrs_map, NrsMap::default());
+
+        // running the batch again doesn't register the names again
+        let rerun = retry_loop_for_pattern!(
+            safe.nrs_create_batch(vec![first.clone(), second.clone()]),
+            Ok(outcomes) if outcomes.values().all(|o| matches!(o, NrsBatchOutcome::AlreadyRegistered(_)))
+        )?;
+        assert_eq!(
+            rerun[&first],
+            NrsBatchOutcome::AlreadyRegistered(match &outcomes[&first] {
+                NrsBatchOutcome::Created(xorurl) => xorurl.clone(),
+                other => bail!("Unexpected outcome: {:?}", other),
+            })
+        );
+
+        // names registered by others are reported as failures
+        let other = new_safe_instance().await?;
+        let outcomes = other.nrs_create_batch(vec![first.clone()]).await?;
+        assert!(matches!(outcomes[&first], NrsBatchOutcome::Failed(_)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nrs_verify_link() -> Result<()> {
+        let site_name = random_nrs_name();
+        let mut safe = new_safe_instance().await?;
+        safe.set_verify_nrs_links(true);
+
+        // a dry run gives us the XOR-URL of a Blob which doesn't exist
+        let missing = safe
+            .store_public_bytes(Bytes::from("never stored"), None, true)
+            .await?;
+        assert!(safe.verify_nrs_link(&missing).await.is_err());
+        match safe
+            .nrs_map_container_create(&site_name, &missing, true, false, false)
+            .await
+        {
+            Err(Error::InvalidInput(_)) => {}
+            other => bail!("Unexpected result: {:?}", other),
+        }
+
+        let (container_xorurl, _, _) = safe
+            .files_container_create(None, None, true, true, false)
+            .await?;
+        let _ = retry_loop!(safe.verify_nrs_link(&container_xorurl));
+
+        // a version which is not valid for the FilesContainer
+        let mut invalid_version = Url::from_url(&container_xorurl)?;
+        invalid_version.set_content_version(Some(VersionHash::default()));
+        assert!(safe
+            .verify_nrs_link(&invalid_version.to_string())
+            .await
+            .is_err());
+
+        let (xorurl, _, _) = safe
+            .nrs_map_container_create(&site_name, &container_xorurl, true, false, false)
+            .await?;
+        assert!(xorurl.contains("safe://"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nrs_follow_latest_version() -> Result<()> {
+        let site_name = random_nrs_name();
+        let mut safe = new_safe_instance().await?;
+        let (container_xorurl, _, _) = safe
+            .files_container_create(Some("./testdata/test.md"), None, false, false, false)
+            .await?;
+        let mut unversioned = Url::from_url(&container_xorurl)?;
+        unversioned.set_content_version(None);
+        let unversioned = unversioned.to_string();
+
+        // unversioned links to versionable content are rejected by default
+        assert!(safe
+            .nrs_map_container_create(&site_name, &unversioned, true, false, false)
+            .await
+            .is_err());
+
+        safe.set_nrs_version_requirement(NrsVersionRequirement::FollowLatest);
+        let (_, _, nrs_map) = safe
+            .nrs_map_container_create(&site_name, &unversioned, true, false, false)
+            .await?;
+        assert_eq!(nrs_map.get_default_link()?, unversioned);
+        assert_eq!(
+            nrs_map.resolve_link_for_subnames(&[])?,
+            (unversioned.clone(), true)
+        );
+
+        // the name follows the updates of the FilesContainer
+        let _ = retry_loop!(safe.fetch(&container_xorurl, None));
+        let (version, _, _) = safe
+            .files_container_add(
+                "./testdata/another.md",
+                &format!("{}/another.md", unversioned),
+                false,
+                false,
+                false,
+                false,
+            )
+            .await?;
+        let chain = retry_loop_for_pattern!(
+            safe.inspect(&format!("safe://{}", site_name)),
+            Ok(chain) if matches!(chain.last(), Some(SafeData::FilesContainer { version: v, .. }) if *v == version)
+        )?;
+        assert!(matches!(
+            chain[0],
+            SafeData::NrsMapContainer {
+                follows_latest: true,
+                ..
+            }
+        ));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_nrs_map_container_remove_default_soft_link() -> Result<()> {
         let site_name = random_nrs_name();
@@ -7,9 +7,10 @@
 // specific language governing permissions and limitations relating to use of the SAFE Network
 // Software.
 
+use super::compose::NrsInclude;
 use crate::{
     app::{
-        consts::{PREDICATE_CREATED, PREDICATE_LINK, PREDICATE_MODIFIED},
+        consts::{PREDICATE_CREATED, PREDICATE_FOLLOW_LATEST, PREDICATE_LINK, PREDICATE_MODIFIED},
         fetch::{ContentType, DataType},
         helpers::gen_timestamp_secs,
         Safe,
@@ -65,11 +66,31 @@ impl std::default::Default for DefaultRdf {
 // Each PublicName contains metadata and the link to the target's XOR-URL
 pub type SubNamesMap = BTreeMap<SubName, SubNameRdf>;
 
+/// Whether NRS requires the links to versionable content to specify a version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NrsVersionRequirement {
+    /// Links to versionable content must specify a version
+    Strict,
+    /// Links to versionable content may not specify a version, in which case the name
+    /// follows the updates of the content, as its latest version is resolved at fetch time
+    FollowLatest,
+}
+
+impl Default for NrsVersionRequirement {
+    fn default() -> Self {
+        Self::Strict
+    }
+}
+
 // To use for mapping sub names to PublicNames
 #[derive(Debug, PartialEq, Default, Serialize, Deserialize, Clone)]
 pub struct NrsMap {
     pub sub_names_map: SubNamesMap,
     pub default: DefaultRdf,
+    // Other NrsMaps the names are looked up in, see `NrsMap::include`. It's left out when
+    // empty, so NrsMaps not including others are serialised as they were before.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub includes: Vec<NrsInclude>,
 }
 
 impl NrsMap {
@@ -78,10 +99,17 @@ impl NrsMap {
     }
 
     pub fn resolve_for_subnames(&self, sub_names: &[SubName]) -> Result<XorUrl> {
+        self.resolve_link_for_subnames(sub_names)
+            .map(|(link, _)| link)
+    }
+
+    /// Resolve the link for the sub names, together with a flag which is set if the link
+    /// follows the latest version of versionable content, i.e. it doesn't specify a version
+    /// as it was associated with `NrsVersionRequirement::FollowLatest`
+    pub fn resolve_link_for_subnames(&self, sub_names: &[SubName]) -> Result<(XorUrl, bool)> {
         debug!("NRS: Attempting to resolve for subnames {:?}", sub_names);
 
         let mut nrs_map = self;
-        let dereferenced_link: String;
         let sub_names_str = sub_names_vec_to_str(sub_names);
         let mut link = if sub_names.is_empty() {
             match &self.default {
@@ -90,12 +118,11 @@ impl NrsMap {
                         "NRS subname resolution done from default. Located: \"{:?}\"",
                         def_data
                     );
-                    def_data.get(PREDICATE_LINK)
+                    definition_link(def_data)
                 }
                 DefaultRdf::ExistingRdf(sub_name) => {
                     let sub_names = sub_name.split('.').map(String::from).collect::<Vec<_>>();
-                    dereferenced_link = self.resolve_for_subnames(&sub_names)?;
-                    Some(&dereferenced_link)
+                    Some(self.resolve_link_for_subnames(&sub_names)?)
                 }
                 DefaultRdf::NotSet => None,
             }
@@ -111,7 +138,7 @@ impl NrsMap {
                         // we need default one then
                         if let DefaultRdf::OtherRdf(def_data) = &nrs_sub_map.default {
                             debug!("NRS subname resolution done. Located: \"{:?}\"", def_data);
-                            link = def_data.get(PREDICATE_LINK);
+                            link = definition_link(def_data);
                         } else {
                             return Err(Error::ContentError(
                                 "Sub name not found in NRS Map Container".to_string(),
@@ -124,7 +151,7 @@ impl NrsMap {
                     debug!("NRS subname resolution done. Located: \"{:?}\"", def_data);
                     if sub_names.is_empty() {
                         // cool, we've gone through all subnames and we found a Definition (tree leaf)
-                        link = def_data.get(PREDICATE_LINK);
+                        link = definition_link(def_data);
                     } else {
                         // oops...we haven't gone through all subnames and we reached a Definition (tree leaf)
                         return Err(Error::ContentError(
@@ -141,10 +168,12 @@ impl NrsMap {
         }
 
         match link {
-            Some(the_link) => {
-                // Let's make sure it's a versioned link
-                validate_nrs_link(the_link)?;
-                Ok(the_link.to_string())
+            Some((the_link, follows_latest)) => {
+                // Let's make sure it's a versioned link, unless it follows the latest version
+                if !follows_latest {
+                    validate_nrs_link(&the_link)?;
+                }
+                Ok((the_link, follows_latest))
             }
             None => Err(Error::ContentError(format!(
                 "No link found for subname/s \"{}\"",
@@ -155,20 +184,19 @@ impl NrsMap {
 
     pub fn get_default_link(&self) -> Result<XorUrl> {
         debug!("Attempting to get default link vis NRS....");
-        let dereferenced_link: String;
         let link = match &self.default {
             DefaultRdf::NotSet => {
                 return Err(Error::ContentError(
                     "No default found for resolvable map.".to_string(),
                 ))
             }
-            DefaultRdf::OtherRdf(def_data) => def_data.get(PREDICATE_LINK),
+            DefaultRdf::OtherRdf(def_data) => definition_link(def_data),
             DefaultRdf::ExistingRdf(sub_name) => {
                 let sub_names = sub_name.split('.').map(String::from).collect::<Vec<_>>();
-                dereferenced_link = self.resolve_for_subnames(&sub_names).map_err(|_| Error::ContentError(
+                let dereferenced_link = self.resolve_link_for_subnames(&sub_names).map_err(|_| Error::ContentError(
                     format!("Default found for resolvable map (set to sub names '{}') cannot be resolved.", sub_name),
                 ))?;
-                Some(&dereferenced_link)
+                Some(dereferenced_link)
             }
         }
         .ok_or_else(|| {
@@ -178,10 +206,12 @@ impl NrsMap {
             ))
         })?;
 
-        debug!("Default link retrieved: \"{}\"", link);
-        // Let's make sure it's a versioned link
-        validate_nrs_link(link)?;
-        Ok(link.to_string())
+        debug!("Default link retrieved: \"{}\"", link.0);
+        // Let's make sure it's a versioned link, unless it follows the latest version
+        if !link.1 {
+            validate_nrs_link(&link.0)?;
+        }
+        Ok(link.0)
     }
 
     pub fn nrs_map_remove_subname(&mut self, name: &str) -> Result<String> {
@@ -203,21 +233,47 @@ impl NrsMap {
         link: &str,
         default: bool,
         hard_link: bool,
+    ) -> Result<String> {
+        self.update_with(
+            name,
+            link,
+            default,
+            hard_link,
+            NrsVersionRequirement::Strict,
+        )
+    }
+
+    /// Same as `update`, but links to versionable content are only required to specify
+    /// a version if the requirement is `NrsVersionRequirement::Strict`
+    pub fn update_with(
+        &mut self,
+        name: &str,
+        link: &str,
+        default: bool,
+        hard_link: bool,
+        requirement: NrsVersionRequirement,
     ) -> Result<String> {
         info!("Updating NRS map for: {}", name);
 
-        // NRS resolver doesn't allow unversioned links
-        validate_nrs_link(link)?;
+        // NRS resolver doesn't allow unversioned links, unless they are flagged as
+        // following the latest version of the content
+        let follows_latest = match requirement {
+            NrsVersionRequirement::Strict => {
+                validate_nrs_link(link)?;
+                false
+            }
+            NrsVersionRequirement::FollowLatest => unversioned_versionable_kind(link)?.is_some(),
+        };
 
         // Update NRS Map with new names
         let sub_names: Vec<String> = parse_nrs_name(name)?;
-        let updated_nrs_map = setup_nrs_tree(self, sub_names.clone(), link)?;
+        let updated_nrs_map = setup_nrs_tree(self, sub_names.clone(), link, follows_latest)?;
         self.sub_names_map = updated_nrs_map.sub_names_map;
 
         // Set (top level) default if was requested
         if default {
             debug!("Setting {:?} as default for NrsMap", &name);
-            let definition_data = create_nrs_name_metadata(link);
+            let definition_data = create_nrs_name_metadata(link, follows_latest);
             if hard_link || sub_names.is_empty() {
                 self.default = DefaultRdf::OtherRdf(definition_data);
             } else {
@@ -251,6 +307,14 @@ impl NrsMap {
         }
     }
 
+    /// All the sub names which have a definition, e.g. `a.b` for `a.b.<top name>`,
+    /// together with their definition
+    pub fn subnames(&self) -> BTreeMap<String, BTreeMap<String, String>> {
+        let mut subnames = BTreeMap::new();
+        gen_subnames(self, "", &mut subnames);
+        subnames
+    }
+
     pub fn get_map_summary(&self) -> BTreeMap<String, BTreeMap<String, String>> {
         let mut nrs_map_summary = BTreeMap::new();
         gen_nrs_map_summary(self, "", &mut nrs_map_summary);
@@ -258,16 +322,29 @@ impl NrsMap {
     }
 }
 
-fn create_nrs_name_metadata(link: &str) -> DefinitionData {
+fn create_nrs_name_metadata(link: &str, follows_latest: bool) -> DefinitionData {
     let now = gen_timestamp_secs();
     let mut public_name = DefinitionData::new();
     public_name.insert(PREDICATE_LINK.to_string(), link.to_string());
     public_name.insert(PREDICATE_MODIFIED.to_string(), now.clone());
     public_name.insert(PREDICATE_CREATED.to_string(), now);
+    if follows_latest {
+        public_name.insert(PREDICATE_FOLLOW_LATEST.to_string(), true.to_string());
+    }
 
     public_name
 }
 
+// The link of a definition, and whether it follows the latest version of its target
+fn definition_link(def_data: &DefinitionData) -> Option<(String, bool)> {
+    let follows_latest = def_data
+        .get(PREDICATE_FOLLOW_LATEST)
+        .map_or(false, |value| value == "true");
+    def_data
+        .get(PREDICATE_LINK)
+        .map(|link| (link.clone(), follows_latest))
+}
+
 fn sub_names_vec_to_str(sub_names: &[SubName]) -> String {
     if !sub_names.is_empty() {
         let length = sub_names.len() - 1;
@@ -301,6 +378,17 @@ fn parse_nrs_name(name: &str) -> Result<Vec<String>> {
 }
 
 pub(crate) fn validate_nrs_link(link: &str) -> Result<()> {
+    match unversioned_versionable_kind(link)? {
+        Some(kind) => Err(Error::InvalidInput(format!(
+            "The linked content ({}) is versionable, therefore NRS requires the link to specify a hash: \"{}\"",
+            kind, link
+        ))),
+        None => Ok(()),
+    }
+}
+
+// The kind of content the link targets if it's versionable but the link doesn't specify a version
+fn unversioned_versionable_kind(link: &str) -> Result<Option<String>> {
     let link_encoder = Safe::parse_url(link)?;
     if link_encoder.content_version().is_none() {
         let content_type = link_encoder.content_type();
@@ -308,34 +396,33 @@ pub(crate) fn validate_nrs_link(link: &str) -> Result<()> {
         if content_type == ContentType::FilesContainer
             || content_type == ContentType::NrsMapContainer
         {
-            return Err(Error::InvalidInput(format!(
-                "The linked content ({}) is versionable, therefore NRS requires the link to specify a hash: \"{}\"",
-                content_type, link
-            )));
+            return Ok(Some(content_type.to_string()));
         } else if data_type == DataType::Register {
-            return Err(Error::InvalidInput(format!(
-                "The linked content ({}) is versionable, therefore NRS requires the link to specify a hash: \"{}\"",
-                data_type, link
-            )));
+            return Ok(Some(data_type.to_string()));
         }
     }
 
-    Ok(())
+    Ok(None)
 }
 
-fn setup_nrs_tree(nrs_map: &NrsMap, mut sub_names: Vec<String>, link: &str) -> Result<NrsMap> {
+fn setup_nrs_tree(
+    nrs_map: &NrsMap,
+    mut sub_names: Vec<String>,
+    link: &str,
+    follows_latest: bool,
+) -> Result<NrsMap> {
     let mut updated_nrs_map = nrs_map.clone();
     let curr_sub_name = if let Some(sub_name) = sub_names.pop() {
         sub_name
     } else {
-        let definition_data = create_nrs_name_metadata(link);
+        let definition_data = create_nrs_name_metadata(link, follows_latest);
         updated_nrs_map.default = DefaultRdf::OtherRdf(definition_data);
         return Ok(updated_nrs_map);
     };
 
     match nrs_map.sub_names_map.get(&curr_sub_name) {
         Some(SubNameRdf::SubName(nrs_sub_map)) => {
-            let updated_sub_map = setup_nrs_tree(nrs_sub_map, sub_names, link)?;
+            let updated_sub_map = setup_nrs_tree(nrs_sub_map, sub_names, link, follows_latest)?;
             updated_nrs_map
                 .sub_names_map
                 .insert(curr_sub_name, SubNameRdf::SubName(updated_sub_map));
@@ -347,7 +434,8 @@ fn setup_nrs_tree(nrs_map: &NrsMap, mut sub_names: Vec<String>, link: &str) -> R
                 default: DefaultRdf::OtherRdf(def_data.clone()),
                 ..Default::default()
             };
-            let updated_new_nrs_map = setup_nrs_tree(&new_nrs_map, sub_names, link)?;
+            let updated_new_nrs_map =
+                setup_nrs_tree(&new_nrs_map, sub_names, link, follows_latest)?;
             updated_nrs_map
                 .sub_names_map
                 .insert(curr_sub_name, SubNameRdf::SubName(updated_new_nrs_map));
@@ -357,7 +445,8 @@ fn setup_nrs_tree(nrs_map: &NrsMap, mut sub_names: Vec<String>, link: &str) -> R
             // Sub name not found in NRS Map Container
             // we need to add the new sub nrs tree
             let new_nrs_map = NrsMap::default();
-            let updated_new_nrs_map = setup_nrs_tree(&new_nrs_map, sub_names, link)?;
+            let updated_new_nrs_map =
+                setup_nrs_tree(&new_nrs_map, sub_names, link, follows_latest)?;
             updated_nrs_map
                 .sub_names_map
                 .insert(curr_sub_name, SubNameRdf::SubName(updated_new_nrs_map));
@@ -431,6 +520,31 @@ fn remove_nrs_sub_tree(nrs_map: &NrsMap, mut sub_names: Vec<String>) -> Result<(
     }
 }
 
+fn gen_subnames(
+    nrs_map: &NrsMap,
+    parent_sub_names: &str,
+    subnames: &mut BTreeMap<String, DefinitionData>,
+) {
+    for (subname, subname_rdf) in &nrs_map.sub_names_map {
+        let full_subname = if parent_sub_names.is_empty() {
+            subname.clone()
+        } else {
+            format!("{}.{}", subname, parent_sub_names)
+        };
+        match subname_rdf {
+            SubNameRdf::Definition(def_data) => {
+                let _ = subnames.insert(full_subname, def_data.clone());
+            }
+            SubNameRdf::SubName(nrs_sub_map) => {
+                if let DefaultRdf::OtherRdf(def_data) = &nrs_sub_map.default {
+                    let _ = subnames.insert(full_subname.clone(), def_data.clone());
+                }
+                gen_subnames(nrs_sub_map, &full_subname, subnames);
+            }
+        }
+    }
+}
+
 fn gen_nrs_map_summary(
     nrs_map: &NrsMap,
     sub_names_str: &str,
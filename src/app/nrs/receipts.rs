@@ -0,0 +1,267 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::validate_nrs_name;
+use crate::{
+    app::{encryption::keyed_hash, proofs::Proof},
+    ContentType, Error, PublicKey, Result, Safe, Scope, Url, XorName, XorUrl,
+};
+use bytes::Bytes;
+use chrono::Utc;
+use log::{debug, info};
+use safe_network::types::Signature;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// Type tag to use for the Registers holding the registration receipts of NRS top names
+const NRS_RECEIPTS_TYPE_TAG: u64 = 2_400;
+
+// Context used to derive the location of the receipts Register of a top name
+const NRS_RECEIPTS_CONTEXT: &[u8] = b"sn_api-nrs-receipts";
+
+/// Receipt of the registration of an NRS top name, signed by the registering key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NrsRegistrationReceipt {
+    /// Top name registered
+    pub name: String,
+    /// XOR-URL of the NrsMapContainer, versioned with the entry it was created with
+    pub creation_point: XorUrl,
+    /// Public key of whom registered the name
+    pub registrant: PublicKey,
+    /// Time the name was registered, as seconds since the Unix epoch
+    pub registered_at: i64,
+    signature: Signature,
+}
+
+impl NrsRegistrationReceipt {
+    /// Check the receipt was signed by its registrant
+    pub fn verify(&self) -> Result<()> {
+        let signed_bytes = serialise_statement(
+            &self.name,
+            &self.creation_point,
+            &self.registrant,
+            self.registered_at,
+        )?;
+        self.registrant
+            .verify(&self.signature, &signed_bytes)
+            .map_err(|err| {
+                Error::ContentError(format!(
+                    "Invalid signature found on registration receipt of \"{}\": {:?}",
+                    self.name, err
+                ))
+            })
+    }
+}
+
+/// Bundle for dispute-resolution tooling to demonstrate who registered an NRS top name
+/// first: the registrant's receipt, together with a proof that the NrsMapContainer's
+/// creation entry the receipt points to exists on the network.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NrsRegistrationProof {
+    pub receipt: NrsRegistrationReceipt,
+    /// Proof of the creation entry, to be verified with `verify_proof`
+    /// against the entry's URL, i.e. `creation_entry.to_string()`
+    pub creation_proof: Proof,
+    pub creation_entry: Url,
+}
+
+impl Safe {
+    /// # Retrieve the registration receipt of an NRS top name
+    ///
+    /// Returns the receipt signed by the registrant when the top name was created, along
+    /// with a proof of the NrsMapContainer's creation entry it points to. If more than one
+    /// valid receipt is found, the earliest one is returned.
+    pub async fn nrs_registration_proof(&self, name: &str) -> Result<NrsRegistrationProof> {
+        let (safe_url, _) = validate_nrs_name(name)?;
+        let top_name = safe_url.top_name().to_string();
+        let (_, receipts_url) = self.nrs_receipts_location(safe_url.xorname())?;
+        let entries = self.fetch_register_entries(&receipts_url).await?;
+
+        let mut receipts = vec![];
+        for (_, entry) in entries.iter() {
+            match self.fetch_registration_receipt(entry).await {
+                Ok(receipt) if receipt.name == top_name => receipts.push(receipt),
+                Ok(_) => debug!("Skipping receipt of another name found at {}", entry),
+                Err(err) => debug!("Skipping invalid receipt found at {}: {}", entry, err),
+            }
+        }
+        let receipt = receipts
+            .into_iter()
+            .min_by_key(|receipt| receipt.registered_at)
+            .ok_or_else(|| {
+                Error::ContentNotFound(format!(
+                    "No registration receipt found for \"{}\"",
+                    top_name
+                ))
+            })?;
+
+        let creation_point = Url::from_url(&receipt.creation_point)?;
+        let hash = creation_point
+            .content_version()
+            .ok_or_else(|| {
+                Error::ContentError(format!(
+                    "Registration receipt of \"{}\" has no creation entry",
+                    top_name
+                ))
+            })?
+            .entry_hash();
+        let mut container_url = creation_point.clone();
+        container_url.set_content_version(None);
+        let creation_entry = self.fetch_register_entry(&container_url, hash).await?;
+        let creation_proof = self
+            .create_proof(&creation_point, creation_entry.to_string().as_bytes())
+            .await?;
+
+        Ok(NrsRegistrationProof {
+            receipt,
+            creation_proof,
+            creation_entry,
+        })
+    }
+
+    // Sign and store the registration receipt of a top name just created
+    pub(crate) async fn store_nrs_registration_receipt(
+        &self,
+        top_name: &str,
+        creation_point: &Url,
+    ) -> Result<NrsRegistrationReceipt> {
+        info!("Storing registration receipt of \"{}\"", top_name);
+        let signer = self.signer()?;
+        let registrant = signer.public_key();
+        let registered_at = Utc::now().timestamp();
+        let signature = signer
+            .sign(&serialise_statement(
+                top_name,
+                &creation_point.to_string(),
+                &registrant,
+                registered_at,
+            )?)
+            .await?;
+        let receipt = NrsRegistrationReceipt {
+            name: top_name.to_string(),
+            creation_point: creation_point.to_string(),
+            registrant,
+            registered_at,
+            signature,
+        };
+
+        let serialised_receipt = rmp_serde::to_vec_named(&receipt).map_err(|err| {
+            Error::Serialisation(format!(
+                "Couldn't serialise registration receipt: {:?}",
+                err
+            ))
+        })?;
+        let receipt_xorurl = self
+            .store_public_bytes(Bytes::from(serialised_receipt), None, false)
+            .await?;
+
+        let (receipts_xorname, receipts_url) =
+            self.nrs_receipts_location(creation_point.xorname())?;
+        let _ = self
+            .register_create(Some(receipts_xorname), NRS_RECEIPTS_TYPE_TAG, false)
+            .await?;
+        let _ = self
+            .write_to_register(
+                &receipts_url.to_string(),
+                Url::from_xorurl(&receipt_xorurl)?,
+                BTreeSet::new(),
+            )
+            .await?;
+
+        Ok(receipt)
+    }
+
+    // Private helper to fetch and verify a registration receipt
+    async fn fetch_registration_receipt(&self, url: &Url) -> Result<NrsRegistrationReceipt> {
+        let serialised_receipt = self.fetch_public_data(url, None).await?;
+        let receipt: NrsRegistrationReceipt =
+            rmp_serde::from_slice(&serialised_receipt).map_err(|err| {
+                Error::ContentError(format!("Couldn't parse registration receipt: {:?}", err))
+            })?;
+        receipt.verify()?;
+        Ok(receipt)
+    }
+
+    // Private helper to obtain the location of the receipts Register of a top name
+    fn nrs_receipts_location(&self, nrs_xorname: XorName) -> Result<(XorName, Url)> {
+        let xorname = XorName(keyed_hash(NRS_RECEIPTS_CONTEXT, &nrs_xorname.0));
+        let xorurl = Url::encode_register(
+            xorname,
+            NRS_RECEIPTS_TYPE_TAG,
+            Scope::Public,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?;
+
+        Ok((xorname, Url::from_xorurl(&xorurl)?))
+    }
+}
+
+fn serialise_statement(
+    name: &str,
+    creation_point: &str,
+    registrant: &PublicKey,
+    registered_at: i64,
+) -> Result<Vec<u8>> {
+    rmp_serde::to_vec_named(&(name, creation_point, registrant, registered_at)).map_err(|err| {
+        Error::Serialisation(format!(
+            "Couldn't serialise registration receipt: {:?}",
+            err
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::{
+            proofs::verify_proof,
+            test_helpers::{new_safe_instance, random_nrs_name},
+        },
+        retry_loop,
+    };
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_nrs_registration_proof() -> Result<()> {
+        let site_name = random_nrs_name();
+        let mut safe = new_safe_instance().await?;
+        let link = safe
+            .store_public_bytes(Bytes::from("registered"), None, false)
+            .await?;
+
+        let (xorurl, _, _) =
+            retry_loop!(safe.nrs_map_container_create(&site_name, &link, true, false, false));
+
+        let other = new_safe_instance().await?;
+        let bundle = retry_loop!(other.nrs_registration_proof(&format!("sub.{}", site_name)));
+        bundle.receipt.verify()?;
+        assert_eq!(bundle.receipt.name, site_name);
+        assert_eq!(bundle.receipt.creation_point, xorurl);
+        assert_eq!(
+            bundle.receipt.registrant,
+            safe.get_my_keypair()?.public_key()
+        );
+        verify_proof(
+            &bundle.creation_proof,
+            &[other.get_my_keypair()?.public_key()],
+            bundle.creation_entry.to_string().as_bytes(),
+        )?;
+
+        let mut tampered = bundle.receipt.clone();
+        tampered.registered_at -= 1;
+        assert!(tampered.verify().is_err());
+
+        let unregistered = random_nrs_name();
+        assert!(other.nrs_registration_proof(&unregistered).await.is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,551 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    nrs_map::DefaultRdf, validate_nrs_name, NrsMap, NrsVersionRequirement, ProcessedEntries,
+    VersionHash,
+};
+use crate::{
+    app::consts::{
+        CONTENT_ADDED_SIGN, CONTENT_DELETED_SIGN, CONTENT_UPDATED_SIGN, PREDICATE_FOLLOW_LATEST,
+        PREDICATE_LINK,
+    },
+    Error, Result, Safe, XorUrl,
+};
+use log::{debug, info};
+use std::{collections::BTreeMap, fmt};
+
+// Name of the records of the top name itself
+const TOP_NAME: &str = "@";
+
+const ORIGIN_DIRECTIVE: &str = "$ORIGIN";
+const LINK_RECORD: &str = "LINK";
+const ALIAS_RECORD: &str = "ALIAS";
+const LATEST_FLAG: &str = "LATEST";
+
+/// Where an NRS name links to, as a record of an `NrsZone`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NrsZoneRecord {
+    pub link: String,
+    /// Whether the link follows the latest version of versionable content,
+    /// see `NrsVersionRequirement::FollowLatest`
+    pub follows_latest: bool,
+}
+
+/// # The NRS names of a top name, in a text format akin to DNS zone files
+///
+/// Each line holds a directive or a record, and anything after a `;` is a comment:
+///
+/// ```text
+/// $ORIGIN mysite            ; the top name, required before any record
+/// @      LINK  safe://...   ; the top name links to it
+/// www    LINK  safe://...
+/// a.blog LINK  safe://...  LATEST
+/// ```
+///
+/// Records are made of the sub name, relative to the top name, i.e. `a.blog` for
+/// `a.blog.mysite`, or `@` for the top name itself, the type of record and its value.
+/// `LINK` records set the link of a name, flagged with `LATEST` if they follow the latest
+/// version of the linked content. An `@ ALIAS <sub name>` record makes the top name
+/// resolve as the given sub name, instead of having its own link. Each name can only
+/// have one record.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NrsZone {
+    pub top_name: String,
+    /// The records, by sub name, `@` being the top name itself
+    pub records: BTreeMap<String, NrsZoneRecord>,
+    /// Sub name the top name is an alias of, if any
+    pub alias: Option<String>,
+}
+
+impl NrsZone {
+    /// Parse and validate a zone text, reporting the line of the first error found
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut zone: Option<NrsZone> = None;
+        for (index, line) in text.lines().enumerate() {
+            let line_error =
+                |msg: String| Error::InvalidInput(format!("Line {}: {}", index + 1, msg));
+            let tokens: Vec<&str> = line
+                .split_whitespace()
+                .take_while(|token| !token.starts_with(';'))
+                .collect();
+            if tokens.is_empty() {
+                continue;
+            }
+
+            if tokens[0] == ORIGIN_DIRECTIVE {
+                if zone.is_some() {
+                    return Err(line_error(format!(
+                        "Duplicate {} directive",
+                        ORIGIN_DIRECTIVE
+                    )));
+                }
+                let top_name = match tokens.as_slice() {
+                    [_, top_name] => top_name.to_string(),
+                    _ => {
+                        return Err(line_error(format!(
+                            "{} expects the top name only",
+                            ORIGIN_DIRECTIVE
+                        )))
+                    }
+                };
+                let (url, _) =
+                    validate_nrs_name(&top_name).map_err(|err| line_error(err.to_string()))?;
+                if !url.sub_names_vec().is_empty() {
+                    return Err(line_error(format!("'{}' is not a top name", top_name)));
+                }
+                zone = Some(NrsZone {
+                    top_name,
+                    ..NrsZone::default()
+                });
+                continue;
+            }
+
+            let zone = zone.as_mut().ok_or_else(|| {
+                line_error(format!(
+                    "Records need to follow the {} directive",
+                    ORIGIN_DIRECTIVE
+                ))
+            })?;
+            let (name, record_type, value, flag) = match tokens.as_slice() {
+                [name, record_type, value] => (*name, *record_type, *value, None),
+                [name, record_type, value, flag] => (*name, *record_type, *value, Some(*flag)),
+                _ => {
+                    return Err(line_error(
+                        "Records are made of a name, a type, a value and an optional flag"
+                            .to_string(),
+                    ))
+                }
+            };
+            if zone.records.contains_key(name) || (name == TOP_NAME && zone.alias.is_some()) {
+                return Err(line_error(format!("Duplicate record for '{}'", name)));
+            }
+            if name != TOP_NAME {
+                zone.validate_subname(name)
+                    .map_err(|err| line_error(err.to_string()))?;
+            }
+
+            match record_type.to_ascii_uppercase().as_str() {
+                LINK_RECORD => {
+                    let follows_latest = match flag {
+                        None => false,
+                        Some(flag) if flag.eq_ignore_ascii_case(LATEST_FLAG) => true,
+                        Some(other) => return Err(line_error(format!("Unknown flag '{}'", other))),
+                    };
+                    let _ = Safe::parse_url(value).map_err(|err| line_error(err.to_string()))?;
+                    let _ = zone.records.insert(
+                        name.to_string(),
+                        NrsZoneRecord {
+                            link: value.to_string(),
+                            follows_latest,
+                        },
+                    );
+                }
+                ALIAS_RECORD => {
+                    if name != TOP_NAME {
+                        return Err(line_error(format!(
+                            "Only the top name, i.e. '{}', can be an alias",
+                            TOP_NAME
+                        )));
+                    }
+                    if flag.is_some() {
+                        return Err(line_error("Aliases take no flag".to_string()));
+                    }
+                    zone.alias = Some(value.to_string());
+                }
+                other => return Err(line_error(format!("Unknown record type '{}'", other))),
+            }
+        }
+
+        let zone = zone.ok_or_else(|| {
+            Error::InvalidInput(format!("No {} directive found", ORIGIN_DIRECTIVE))
+        })?;
+        if let Some(alias) = &zone.alias {
+            if alias == TOP_NAME || !zone.records.contains_key(alias) {
+                return Err(Error::InvalidInput(format!(
+                    "The top name is an alias of '{}', which has no link",
+                    alias
+                )));
+            }
+        }
+        Ok(zone)
+    }
+
+    /// The zone of the names an NrsMap holds
+    pub fn from_nrs_map(top_name: &str, nrs_map: &NrsMap) -> Self {
+        let mut records: BTreeMap<String, NrsZoneRecord> = nrs_map
+            .subnames()
+            .iter()
+            .filter_map(|(subname, definition)| {
+                zone_record(definition).map(|record| (subname.clone(), record))
+            })
+            .collect();
+        let alias = match &nrs_map.default {
+            DefaultRdf::NotSet => None,
+            DefaultRdf::ExistingRdf(subname) => Some(subname.clone()),
+            DefaultRdf::OtherRdf(definition) => {
+                if let Some(record) = zone_record(definition) {
+                    let _ = records.insert(TOP_NAME.to_string(), record);
+                }
+                None
+            }
+        };
+
+        Self {
+            top_name: top_name.to_string(),
+            records,
+            alias,
+        }
+    }
+
+    // The NRS name of a sub name, e.g. `a.b.<top name>` for `a.b`
+    fn full_name(&self, subname: &str) -> String {
+        if subname == TOP_NAME {
+            self.top_name.clone()
+        } else {
+            format!("{}.{}", subname, self.top_name)
+        }
+    }
+
+    fn validate_subname(&self, subname: &str) -> Result<()> {
+        if subname.split('.').any(str::is_empty) {
+            return Err(Error::InvalidInput(format!(
+                "'{}' is not a valid sub name",
+                subname
+            )));
+        }
+        let _ = validate_nrs_name(&self.full_name(subname))?;
+        Ok(())
+    }
+
+    // Update an NrsMap of the zone's top name so it holds the zone's names, leaving the
+    // definitions of the names whose link doesn't change as they are, and returning
+    // the names added, updated and removed
+    fn apply_to(
+        &self,
+        nrs_map: &mut NrsMap,
+        requirement: NrsVersionRequirement,
+    ) -> Result<ProcessedEntries> {
+        let current = NrsZone::from_nrs_map(&self.top_name, nrs_map);
+        let mut processed_entries = ProcessedEntries::new();
+
+        for (subname, record) in &current.records {
+            // the top name's own link is unset along with its alias below
+            if subname == TOP_NAME || self.records.contains_key(subname) {
+                continue;
+            }
+            let name = self.full_name(subname);
+            let _ = nrs_map.nrs_map_remove_subname(&name)?;
+            let _ = processed_entries.insert(
+                name,
+                (CONTENT_DELETED_SIGN.to_string(), record.link.clone()),
+            );
+        }
+
+        for (subname, record) in &self.records {
+            let sign = match current.records.get(subname) {
+                Some(existing) if existing == record => continue,
+                Some(_) => CONTENT_UPDATED_SIGN,
+                None => CONTENT_ADDED_SIGN,
+            };
+            let requirement = if record.follows_latest {
+                NrsVersionRequirement::FollowLatest
+            } else {
+                requirement
+            };
+            let name = self.full_name(subname);
+            let link = nrs_map.update_with(&name, &record.link, false, false, requirement)?;
+            let _ = processed_entries.insert(name, (sign.to_string(), link));
+        }
+
+        // the top name's own link was set along with the other records, unless it's an alias
+        let had_top_link = current.alias.is_some() || current.records.contains_key(TOP_NAME);
+        match &self.alias {
+            Some(alias) if current.alias.as_ref() != Some(alias) => {
+                nrs_map.default = DefaultRdf::ExistingRdf(alias.clone());
+                let sign = if had_top_link {
+                    CONTENT_UPDATED_SIGN
+                } else {
+                    CONTENT_ADDED_SIGN
+                };
+                let link = self
+                    .records
+                    .get(alias)
+                    .map_or_else(String::new, |record| record.link.clone());
+                let _ = processed_entries.insert(self.top_name.clone(), (sign.to_string(), link));
+            }
+            None if had_top_link && !self.records.contains_key(TOP_NAME) => {
+                nrs_map.default = DefaultRdf::NotSet;
+                let linked_name = current.alias.as_ref().map_or(TOP_NAME, String::as_str);
+                let link = current
+                    .records
+                    .get(linked_name)
+                    .map_or_else(String::new, |record| record.link.clone());
+                let _ = processed_entries.insert(
+                    self.top_name.clone(),
+                    (CONTENT_DELETED_SIGN.to_string(), link),
+                );
+            }
+            _ => {}
+        }
+
+        Ok(processed_entries)
+    }
+}
+
+impl fmt::Display for NrsZone {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "{} {}", ORIGIN_DIRECTIVE, self.top_name)?;
+        let width = self
+            .records
+            .keys()
+            .map(String::len)
+            .chain(std::iter::once(TOP_NAME.len()))
+            .max()
+            .unwrap_or_default();
+        if let Some(alias) = &self.alias {
+            writeln!(
+                fmt,
+                "{:width$} {:5} {}",
+                TOP_NAME,
+                ALIAS_RECORD,
+                alias,
+                width = width
+            )?;
+        }
+        for (subname, record) in &self.records {
+            write!(
+                fmt,
+                "{:width$} {:5} {}",
+                subname,
+                LINK_RECORD,
+                record.link,
+                width = width
+            )?;
+            if record.follows_latest {
+                write!(fmt, " {}", LATEST_FLAG)?;
+            }
+            writeln!(fmt)?;
+        }
+        Ok(())
+    }
+}
+
+// The record of a definition of an NrsMap, unless it has no link
+fn zone_record(definition: &BTreeMap<String, String>) -> Option<NrsZoneRecord> {
+    definition.get(PREDICATE_LINK).map(|link| NrsZoneRecord {
+        link: link.clone(),
+        follows_latest: definition
+            .get(PREDICATE_FOLLOW_LATEST)
+            .map_or(false, |value| value == "true"),
+    })
+}
+
+impl Safe {
+    /// # Export the names of a top name as a zone text
+    ///
+    /// The current NrsMap of the top name of the given NRS name is exported in the
+    /// format described by `NrsZone`, e.g. to keep it under version control, edit it,
+    /// and apply it back with `nrs_apply`.
+    pub async fn nrs_export(&self, name: &str) -> Result<String> {
+        let (safe_url, _) = validate_nrs_name(name)?;
+        let top_name = safe_url.top_name().to_string();
+        let (_, nrs_map) = self.nrs_map_container_get(&safe_url.to_string()).await?;
+        Ok(NrsZone::from_nrs_map(&top_name, &nrs_map).to_string())
+    }
+
+    /// # Apply a zone text to its top name
+    ///
+    /// The zone text, in the format described by `NrsZone`, is validated, and the NrsMap of
+    /// its top name, which needs to be registered already, is updated to hold exactly the
+    /// names of the zone. Only the names which are added, removed, or whose link changes are
+    /// touched, all of them in a single new version of the NrsMapContainer, which isn't
+    /// written if nothing changes, nor if `dry_run` is set. Records not flagged with
+    /// `LATEST` are validated as per this instance's `NrsVersionRequirement`.
+    pub async fn nrs_apply(
+        &self,
+        zone_text: &str,
+        dry_run: bool,
+    ) -> Result<(VersionHash, XorUrl, ProcessedEntries, NrsMap)> {
+        let zone = NrsZone::parse(zone_text)?;
+        info!("Applying zone of NRS top name {}", zone.top_name);
+        let (safe_url, _) = validate_nrs_name(&zone.top_name)?;
+        let xorurl = safe_url.to_string();
+        let (version, mut nrs_map) = self.nrs_map_container_get(&xorurl).await?;
+
+        let processed_entries = zone.apply_to(&mut nrs_map, self.nrs_version_requirement)?;
+        if self.verify_nrs_links {
+            for (sign, link) in processed_entries.values() {
+                if sign != CONTENT_DELETED_SIGN && !link.is_empty() {
+                    self.verify_nrs_link(link).await?;
+                }
+            }
+        }
+        if processed_entries.is_empty() || dry_run {
+            debug!(
+                "Zone of {} applied with {} changes, not written",
+                zone.top_name,
+                processed_entries.len()
+            );
+            return Ok((version, xorurl, processed_entries, nrs_map));
+        }
+
+        let new_version = self
+            .write_nrs_map_version(
+                &xorurl,
+                &zone.top_name,
+                version,
+                &nrs_map,
+                &processed_entries,
+            )
+            .await?;
+        Ok((new_version, xorurl, processed_entries, nrs_map))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::test_helpers::{new_safe_instance, random_nrs_name},
+        retry_loop, retry_loop_for_pattern, BytesAddress, ContentType, Url, XorName,
+        DEFAULT_XORURL_BASE,
+    };
+    use anyhow::{anyhow, Result};
+    use bytes::Bytes;
+
+    fn blob_link() -> Result<String> {
+        Ok(Url::encode_bytes(
+            BytesAddress::Public(XorName::random()),
+            ContentType::Raw,
+            DEFAULT_XORURL_BASE,
+        )?)
+    }
+
+    #[test]
+    fn test_nrs_zone_parse() -> Result<()> {
+        let (link_a, link_b) = (blob_link()?, blob_link()?);
+        let text = format!(
+            "; my site\n$ORIGIN mysite\n\n@ ALIAS www\nwww LINK {} ; the main site\nblog.www link {} latest\n",
+            link_a, link_b
+        );
+        let zone = NrsZone::parse(&text)?;
+        assert_eq!(zone.top_name, "mysite");
+        assert_eq!(zone.alias, Some("www".to_string()));
+        assert_eq!(zone.records.len(), 2);
+        assert_eq!(
+            zone.records.get("blog.www"),
+            Some(&NrsZoneRecord {
+                link: link_b,
+                follows_latest: true
+            })
+        );
+
+        // the text exported parses back to the same zone
+        assert_eq!(NrsZone::parse(&zone.to_string())?, zone);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nrs_zone_parse_errors() -> Result<()> {
+        let (link_a, link_b) = (blob_link()?, blob_link()?);
+        let invalid = vec![
+            format!("www LINK {}", link_a),
+            "$ORIGIN a.mysite".to_string(),
+            format!("$ORIGIN mysite\nwww LINK {}\nwww LINK {}", link_a, link_b),
+            format!("$ORIGIN mysite\nwww CNAME {}", link_a),
+            format!("$ORIGIN mysite\na..b LINK {}", link_a),
+            format!("$ORIGIN mysite\nwww LINK {} STALE", link_a),
+            format!("$ORIGIN mysite\nwww ALIAS {}", link_a),
+            format!("$ORIGIN mysite\n@ ALIAS www\nblog LINK {}", link_a),
+            "$ORIGIN mysite\nwww LINK".to_string(),
+        ];
+        for text in invalid {
+            assert!(
+                matches!(NrsZone::parse(&text), Err(Error::InvalidInput(_))),
+                "Zone text was not rejected: {}",
+                text
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_nrs_zone_apply_to() -> Result<()> {
+        let (link_a, link_b) = (blob_link()?, blob_link()?);
+        let mut nrs_map = NrsMap::default();
+        let _ = nrs_map.update("mysite", &link_a, false, false)?;
+        let _ = nrs_map.update("www.mysite", &link_a, false, false)?;
+        let _ = nrs_map.update("old.mysite", &link_a, false, false)?;
+        let unchanged = nrs_map.subnames()["www"].clone();
+
+        let zone = NrsZone::parse(&format!(
+            "$ORIGIN mysite\n@ ALIAS www\nwww LINK {}\nblog LINK {}",
+            link_a, link_b
+        ))?;
+        let processed = zone.apply_to(&mut nrs_map, NrsVersionRequirement::Strict)?;
+        let signs: Vec<(&str, &str)> = processed
+            .iter()
+            .map(|(name, (sign, _))| (name.as_str(), sign.as_str()))
+            .collect();
+        assert_eq!(
+            signs,
+            vec![
+                ("blog.mysite", CONTENT_ADDED_SIGN),
+                ("mysite", CONTENT_UPDATED_SIGN),
+                ("old.mysite", CONTENT_DELETED_SIGN)
+            ]
+        );
+        assert_eq!(NrsZone::from_nrs_map("mysite", &nrs_map), zone);
+        // the definition of the name left as is keeps its timestamps
+        assert_eq!(nrs_map.subnames()["www"], unchanged);
+
+        // applying it again changes nothing
+        assert!(zone
+            .apply_to(&mut nrs_map, NrsVersionRequirement::Strict)?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nrs_export_and_apply() -> Result<()> {
+        let site_name = random_nrs_name();
+        let mut safe = new_safe_instance().await?;
+        let link = safe
+            .store_public_bytes(Bytes::from("linked"), None, false)
+            .await?;
+        let (_, _, _) =
+            retry_loop!(safe.nrs_map_container_create(&site_name, &link, true, false, false));
+
+        let exported = retry_loop!(safe.nrs_export(&site_name));
+        let mut zone = NrsZone::parse(&exported)?;
+        assert_eq!(zone.records.len(), 1);
+
+        let _ = zone.records.insert(
+            "www".to_string(),
+            NrsZoneRecord {
+                link: link.clone(),
+                follows_latest: false,
+            },
+        );
+        let (version, _, processed, _) = safe.nrs_apply(&zone.to_string(), false).await?;
+        assert_eq!(processed.len(), 1);
+
+        let (_, nrs_map) = retry_loop_for_pattern!(safe.nrs_map_container_get(&format!("safe://{}", site_name)), Ok((v, _)) if *v == version)?;
+        assert_eq!(NrsZone::from_nrs_map(&site_name, &nrs_map), zone);
+        let (unchanged_version, _, processed, _) = safe.nrs_apply(&zone.to_string(), false).await?;
+        if !processed.is_empty() || unchanged_version != version {
+            return Err(anyhow!("Applying the same zone wrote a new version"));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{Result, Safe};
+use async_trait::async_trait;
+use chrono::Utc;
+use log::{debug, warn};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Kind of content which needs to be refreshed before a due time not to lapse
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ObligationKind {
+    /// A lease held on a Register, see `Safe::lease_acquire`
+    Lease,
+    /// Any other kind of content, named by the application
+    Custom(String),
+}
+
+/// Content the user needs to refresh, e.g. renew, before a due time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Obligation {
+    pub url: String,
+    pub kind: ObligationKind,
+    /// Time the content lapses if not refreshed, as seconds since the Unix epoch
+    pub due_at: i64,
+}
+
+/// Hook called to renew the obligations which are due
+#[async_trait]
+pub trait RenewalHook: Send + Sync {
+    /// Renew an obligation, returning the new time it's due at,
+    /// or `None` if it doesn't need to be renewed anymore
+    async fn renew(&self, safe: Safe, obligation: &Obligation) -> Result<Option<i64>>;
+}
+
+// Local tracking of the obligations of the content published with this instance.
+// Clones of an instance share the same obligations and renewal hook.
+#[derive(Clone, Default)]
+pub(crate) struct Obligations {
+    tracked: Arc<Mutex<BTreeMap<(String, ObligationKind), i64>>>,
+    hook: Arc<Mutex<Option<Arc<dyn RenewalHook>>>>,
+}
+
+impl Obligations {
+    pub(crate) fn track(&self, url: &str, kind: ObligationKind, due_at: i64) {
+        if let Ok(mut tracked) = self.tracked.lock() {
+            let _ = tracked.insert((url.to_string(), kind), due_at);
+        }
+    }
+
+    pub(crate) fn forget(&self, url: &str, kind: &ObligationKind) {
+        if let Ok(mut tracked) = self.tracked.lock() {
+            let _ = tracked.remove(&(url.to_string(), kind.clone()));
+        }
+    }
+
+    fn list(&self) -> Vec<Obligation> {
+        let mut obligations: Vec<Obligation> = match self.tracked.lock() {
+            Ok(tracked) => tracked
+                .iter()
+                .map(|((url, kind), due_at)| Obligation {
+                    url: url.clone(),
+                    kind: kind.clone(),
+                    due_at: *due_at,
+                })
+                .collect(),
+            Err(_) => vec![],
+        };
+        obligations.sort_by_key(|obligation| obligation.due_at);
+        obligations
+    }
+
+    fn hook(&self) -> Option<Arc<dyn RenewalHook>> {
+        self.hook.lock().ok().and_then(|hook| hook.clone())
+    }
+}
+
+impl Safe {
+    /// # List the obligations tracked
+    ///
+    /// Obligations are the content published with this instance which lapses if not
+    /// refreshed before a due time, e.g. leases, returned with the earliest due first.
+    pub fn obligations(&self) -> Vec<Obligation> {
+        self.obligations.list()
+    }
+
+    /// Track an obligation to refresh some content before the given due time, as seconds
+    /// since the Unix epoch, replacing the due time if it was already tracked
+    pub fn obligation_track(&self, url: &str, kind: ObligationKind, due_at: i64) {
+        self.obligations.track(url, kind, due_at);
+    }
+
+    /// Stop tracking an obligation
+    pub fn obligation_forget(&self, url: &str, kind: &ObligationKind) {
+        self.obligations.forget(url, kind);
+    }
+
+    /// Set the hook called by `obligations_renew` to renew the obligations which are due
+    pub fn set_renewal_hook(&self, hook: Arc<dyn RenewalHook>) {
+        if let Ok(mut current) = self.obligations.hook.lock() {
+            *current = Some(hook);
+        }
+    }
+
+    /// Renew, with the renewal hook set, the obligations which are due within the given
+    /// margin, returning the number of obligations renewed. Obligations which fail to be
+    /// renewed are kept to be retried. Nothing is renewed if there is no hook set.
+    pub async fn obligations_renew(&self, margin: Duration) -> Result<usize> {
+        let hook = match self.obligations.hook() {
+            Some(hook) => hook,
+            None => return Ok(0),
+        };
+
+        let deadline = Utc::now().timestamp() + margin.as_secs() as i64;
+        let mut renewed = 0;
+        for obligation in self
+            .obligations()
+            .into_iter()
+            .filter(|obligation| obligation.due_at <= deadline)
+        {
+            debug!("Renewing obligation: {:?}", obligation);
+            match hook.renew(self.clone(), &obligation).await {
+                Ok(Some(due_at)) => {
+                    self.obligation_track(&obligation.url, obligation.kind.clone(), due_at);
+                    renewed += 1;
+                }
+                Ok(None) => {
+                    self.obligation_forget(&obligation.url, &obligation.kind);
+                    renewed += 1;
+                }
+                Err(err) => warn!("Failed to renew {:?}: {}", obligation, err),
+            }
+        }
+
+        Ok(renewed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    struct TestHook;
+
+    #[async_trait]
+    impl RenewalHook for TestHook {
+        async fn renew(&self, _safe: Safe, obligation: &Obligation) -> Result<Option<i64>> {
+            match &obligation.kind {
+                ObligationKind::Custom(kind) if kind == "failing" => {
+                    Err(Error::ContentError("cannot renew".to_string()))
+                }
+                ObligationKind::Custom(kind) if kind == "once" => Ok(None),
+                _ => Ok(Some(obligation.due_at + 3600)),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_obligations_renew() -> anyhow::Result<()> {
+        let safe = Safe::default();
+        let now = Utc::now().timestamp();
+        safe.obligation_track("safe://later", ObligationKind::Lease, now + 7200);
+        safe.obligation_track("safe://soon", ObligationKind::Lease, now + 10);
+        safe.obligation_track(
+            "safe://once",
+            ObligationKind::Custom("once".to_string()),
+            now,
+        );
+        safe.obligation_track(
+            "safe://failing",
+            ObligationKind::Custom("failing".to_string()),
+            now,
+        );
+
+        let urls: Vec<String> = safe.obligations().into_iter().map(|o| o.url).collect();
+        assert_eq!(urls[3], "safe://later");
+
+        // nothing is renewed without a hook
+        assert_eq!(safe.obligations_renew(Duration::from_secs(60)).await?, 0);
+
+        safe.set_renewal_hook(Arc::new(TestHook));
+        assert_eq!(safe.obligations_renew(Duration::from_secs(60)).await?, 2);
+
+        let obligations = safe.obligations();
+        assert_eq!(obligations.len(), 3);
+        assert_eq!(obligations[0].url, "safe://failing");
+        assert!(obligations
+            .iter()
+            .any(|o| o.url == "safe://soon" && o.due_at == now + 10 + 3600));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,314 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::fetch::Range;
+use crate::Safe;
+use rand::{seq::SliceRandom, Rng};
+use safe_network::types::{BytesAddress, RegisterAddress};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// Maximum number of addresses kept to pick decoy fetches from
+const DECOY_POOL_SIZE: usize = 64;
+
+/// Settings of the privacy mode, see `Safe::set_privacy_mode`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrivacyMode {
+    /// Fetches are held until the next boundary of windows of this duration, so the
+    /// fetches made within a window are sent together, no batching if zero
+    pub batch_window: Duration,
+    /// Maximum random delay added to each fetch, on top of the batching
+    pub max_jitter: Duration,
+    /// The ranges of the Blobs fetched are extended to multiples of this many bytes, so
+    /// the sizes fetched don't reveal the ranges read, no padding if zero
+    pub range_padding: u64,
+    /// Number of decoy fetches sent along each fetch
+    pub decoys_per_fetch: usize,
+    /// Maximum number of decoy fetches sent in total
+    pub decoy_budget: u64,
+}
+
+impl Default for PrivacyMode {
+    fn default() -> Self {
+        Self {
+            batch_window: Duration::from_millis(250),
+            max_jitter: Duration::from_millis(100),
+            range_padding: 64 * 1024,
+            decoys_per_fetch: 1,
+            decoy_budget: 1_000,
+        }
+    }
+}
+
+// Content a decoy fetch can be sent for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FetchTarget {
+    Bytes(BytesAddress),
+    Register(RegisterAddress),
+}
+
+#[derive(Default)]
+struct PrivacyState {
+    mode: Option<PrivacyMode>,
+    decoys_sent: u64,
+    // Targets of the fetches made so far, decoys are picked from them as they're
+    // content which exists, thus the decoys look like any other fetch
+    pool: Vec<FetchTarget>,
+}
+
+// Privacy mode of an instance and the state of its decoys. Clones of an instance share it.
+#[derive(Clone, Default)]
+pub(crate) struct Privacy {
+    state: Arc<Mutex<PrivacyState>>,
+}
+
+impl Privacy {
+    pub(crate) fn set_mode(&self, mode: Option<PrivacyMode>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.mode = mode;
+            state.decoys_sent = 0;
+            state.pool.clear();
+        }
+    }
+
+    pub(crate) fn mode(&self) -> Option<PrivacyMode> {
+        self.state.lock().ok().and_then(|state| state.mode.clone())
+    }
+
+    pub(crate) fn decoys_sent(&self) -> u64 {
+        self.state.lock().map_or(0, |state| state.decoys_sent)
+    }
+
+    // Plan a fetch of the target, returning how long to hold it back, and the
+    // decoy fetches to send along with it, or nothing if privacy mode is off
+    pub(crate) fn plan_fetch(&self, target: FetchTarget) -> Option<(Duration, Vec<FetchTarget>)> {
+        let mut state = self.state.lock().ok()?;
+        let mode = state.mode.clone()?;
+        let mut rng = rand::thread_rng();
+
+        let available = mode.decoy_budget.saturating_sub(state.decoys_sent);
+        let decoys: Vec<FetchTarget> = state
+            .pool
+            .iter()
+            .filter(|decoy| **decoy != target)
+            .copied()
+            .collect::<Vec<_>>()
+            .choose_multiple(&mut rng, mode.decoys_per_fetch.min(available as usize))
+            .copied()
+            .collect();
+        state.decoys_sent += decoys.len() as u64;
+
+        if !state.pool.contains(&target) {
+            if state.pool.len() < DECOY_POOL_SIZE {
+                state.pool.push(target);
+            } else {
+                let replaced = rng.gen_range(0, DECOY_POOL_SIZE);
+                state.pool[replaced] = target;
+            }
+        }
+
+        let jitter = if mode.max_jitter > Duration::from_millis(0) {
+            rng.gen_range(Duration::from_millis(0), mode.max_jitter)
+        } else {
+            Duration::from_millis(0)
+        };
+        Some((until_next_window(mode.batch_window) + jitter, decoys))
+    }
+
+    // Range to fetch instead of the one requested, padded as per the privacy mode
+    pub(crate) fn padded_range(&self, range: Range) -> Range {
+        match self.mode() {
+            Some(mode) => pad_range(range, mode.range_padding),
+            None => range,
+        }
+    }
+}
+
+// Time left until the next boundary of the windows of the given duration
+fn until_next_window(window: Duration) -> Duration {
+    let window_ms = window.as_millis();
+    if window_ms == 0 {
+        return Duration::from_millis(0);
+    }
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_millis());
+    Duration::from_millis((window_ms - now_ms % window_ms) as u64)
+}
+
+// Extend a range, if it has a start or an end, to multiples of the padding
+fn pad_range(range: Range, padding: u64) -> Range {
+    if padding == 0 {
+        return range;
+    }
+    range.map(|(start, end)| {
+        (
+            start.map(|start| start - start % padding),
+            end.map(|end| match end % padding {
+                0 => end,
+                rem => end + padding - rem,
+            }),
+        )
+    })
+}
+
+// Cut the data fetched for a padded range down to the range requested
+pub(crate) fn trim_to_range(data: bytes::Bytes, requested: Range, padded: Range) -> bytes::Bytes {
+    let (requested_start, requested_end) = match requested {
+        Some(range) => range,
+        None => return data,
+    };
+    let padded_start = padded.and_then(|(start, _)| start).unwrap_or(0);
+    let offset = (requested_start.unwrap_or(0) - padded_start) as usize;
+    let start = offset.min(data.len());
+    let end = match requested_end {
+        Some(end) => ((end - padded_start) as usize).min(data.len()),
+        None => data.len(),
+    };
+    data.slice(start..end.max(start))
+}
+
+impl Safe {
+    /// # Set the privacy mode
+    ///
+    /// In privacy mode the fetches are made harder to correlate by anyone observing the
+    /// traffic of this instance: they're held until the next boundary of a time window, so
+    /// those made within the window are sent together, and delayed by a random jitter; the
+    /// ranges of the Blobs read are padded so their sizes don't reveal the ranges actually
+    /// read; and decoy fetches, of content fetched earlier, are sent along each fetch until
+    /// the decoy budget is spent. This adds latency and traffic to every fetch, thus it's
+    /// meant for high-risk users. It's off by default, and `None` turns it off.
+    pub fn set_privacy_mode(&mut self, mode: Option<PrivacyMode>) {
+        self.safe_client.privacy().set_mode(mode);
+    }
+
+    /// The privacy mode, if it's on
+    pub fn privacy_mode(&self) -> Option<PrivacyMode> {
+        self.safe_client.privacy().mode()
+    }
+
+    /// Number of decoy fetches sent since the privacy mode was set
+    pub fn privacy_decoys_sent(&self) -> u64 {
+        self.safe_client.privacy().decoys_sent()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_privacy_range_padding() {
+        assert_eq!(
+            pad_range(Some((Some(10), Some(20))), 16),
+            Some((Some(0), Some(32)))
+        );
+        assert_eq!(
+            pad_range(Some((Some(16), None)), 16),
+            Some((Some(16), None))
+        );
+        assert_eq!(pad_range(None, 16), None);
+        assert_eq!(
+            pad_range(Some((Some(10), Some(20))), 0),
+            Some((Some(10), Some(20)))
+        );
+
+        let data = Bytes::from((0..32).collect::<Vec<u8>>());
+        let requested = Some((Some(10), Some(20)));
+        let trimmed = trim_to_range(data, requested, pad_range(requested, 16));
+        assert_eq!(trimmed, Bytes::from((10..20).collect::<Vec<u8>>()));
+
+        // the content can end before the padded range does
+        let data = Bytes::from((0..15).collect::<Vec<u8>>());
+        let trimmed = trim_to_range(data, requested, pad_range(requested, 16));
+        assert_eq!(trimmed, Bytes::from((10..15).collect::<Vec<u8>>()));
+    }
+
+    #[test]
+    fn test_privacy_decoy_budget() {
+        let privacy = Privacy::default();
+        let target =
+            |name: u8| FetchTarget::Bytes(BytesAddress::Public(xor_name::XorName([name; 32])));
+        assert!(privacy.plan_fetch(target(0)).is_none());
+
+        privacy.set_mode(Some(PrivacyMode {
+            batch_window: Duration::from_millis(50),
+            max_jitter: Duration::from_millis(10),
+            range_padding: 0,
+            decoys_per_fetch: 2,
+            decoy_budget: 4,
+        }));
+
+        // nothing to pick decoys from yet
+        let (delay, decoys) = privacy.plan_fetch(target(1)).unwrap_or_default();
+        assert!(delay <= Duration::from_millis(60));
+        assert!(decoys.is_empty());
+
+        let (_, decoys) = privacy.plan_fetch(target(2)).unwrap_or_default();
+        assert_eq!(decoys, vec![target(1)]);
+        let (_, decoys) = privacy.plan_fetch(target(3)).unwrap_or_default();
+        assert_eq!(decoys.len(), 2);
+        assert!(!decoys.contains(&target(3)));
+        assert_eq!(privacy.decoys_sent(), 3);
+
+        // the budget is spent
+        let (_, decoys) = privacy.plan_fetch(target(4)).unwrap_or_default();
+        assert_eq!(decoys.len(), 1);
+        let (_, decoys) = privacy.plan_fetch(target(5)).unwrap_or_default();
+        assert!(decoys.is_empty());
+        assert_eq!(privacy.decoys_sent(), 4);
+    }
+
+    #[cfg(feature = "sim")]
+    #[tokio::test]
+    async fn test_privacy_mode_fetch_sim() -> anyhow::Result<()> {
+        use crate::{
+            app::sim::{SimConfig, SimNetwork},
+            Url,
+        };
+
+        let sim = SimNetwork::new(SimConfig::default());
+        let mut safe = Safe::default();
+        safe.connect_sim(&sim, None);
+        let first = safe
+            .store_public_bytes(Bytes::from("first content"), None, false)
+            .await?;
+        let second = safe
+            .store_public_bytes(
+                Bytes::from("0123456789abcdefghijklmnopqrstuvwxyz"),
+                None,
+                false,
+            )
+            .await?;
+
+        safe.set_privacy_mode(Some(PrivacyMode {
+            batch_window: Duration::from_millis(20),
+            max_jitter: Duration::from_millis(5),
+            range_padding: 16,
+            decoys_per_fetch: 1,
+            decoy_budget: 10,
+        }));
+        let data = safe
+            .fetch_public_data(&Url::from_url(&first)?, None)
+            .await?;
+        assert_eq!(data, Bytes::from("first content"));
+
+        // the range requested is returned, although a padded one is fetched
+        let data = safe
+            .fetch_public_data(&Url::from_url(&second)?, Some((Some(10), Some(20))))
+            .await?;
+        assert_eq!(data, Bytes::from("abcdefghij"));
+        assert!(safe.privacy_decoys_sent() > 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{BytesAddress, ContentType, DataType, Error, Result, Safe, Url, XorName, XorUrl};
+use bytes::Bytes;
+use log::debug;
+
+/// Handle to a Private Blob, which doesn't retrieve nor decrypt any of its content until
+/// it's actually read, and which allows to read (thus decrypt) only a range of its bytes
+#[derive(Clone)]
+pub struct PrivateBlobHandle {
+    safe: Safe,
+    safe_url: Url,
+}
+
+impl PrivateBlobHandle {
+    /// XOR-URL of the Private Blob
+    pub fn xorurl(&self) -> XorUrl {
+        self.safe_url.to_xorurl_string()
+    }
+
+    /// XorName of the Private Blob
+    pub fn xorname(&self) -> XorName {
+        self.safe_url.xorname()
+    }
+
+    /// Media type of the Private Blob, if one was set when storing it
+    pub fn media_type(&self) -> Option<String> {
+        match self.safe_url.content_type() {
+            ContentType::MediaType(media_type) => Some(media_type),
+            _ => None,
+        }
+    }
+
+    /// Retrieve and decrypt the whole content of the Private Blob
+    pub async fn read(&self) -> Result<Bytes> {
+        self.read_range(None, None).await
+    }
+
+    /// Retrieve and decrypt only the bytes within the given range of the Private Blob,
+    /// thus only the chunks containing such bytes are fetched and decrypted
+    pub async fn read_range(&self, start: Option<u64>, end: Option<u64>) -> Result<Bytes> {
+        debug!(
+            "Reading range {:?}-{:?} of Private Blob at {}",
+            start, end, self.safe_url
+        );
+        let range = if start.is_none() && end.is_none() {
+            None
+        } else {
+            Some((start, end))
+        };
+
+        self.safe
+            .safe_client
+            .get_bytes(BytesAddress::Private(self.safe_url.xorname()), range)
+            .await
+    }
+}
+
+impl Safe {
+    /// # Store a Private Blob
+    /// The content is encrypted such that it can only be read with the keypair
+    /// this instance is connected with.
+    pub async fn store_private_bytes(
+        &self,
+        bytes: Bytes,
+        media_type: Option<&str>,
+    ) -> Result<XorUrl> {
+        let content_type = match media_type {
+            Some(media_type_str) if Url::is_media_type_supported(media_type_str) => {
+                ContentType::MediaType(media_type_str.to_string())
+            }
+            Some(media_type_str) => {
+                return Err(Error::InvalidMediaType(format!(
+                    "Media-type '{}' not supported. You can pass 'None' as the 'media_type' for this content to be treated as raw",
+                    media_type_str
+                )))
+            }
+            None => ContentType::Raw,
+        };
+
+        let xorname = self.safe_client.store_private_bytes(bytes).await?;
+        Ok(Url::encode_bytes(
+            BytesAddress::Private(xorname),
+            content_type,
+            self.xorurl_base,
+        )?)
+    }
+
+    /// # Get a handle to a Private Blob
+    /// No content is retrieved nor decrypted until the handle is read from.
+    pub fn private_blob_handle(&self, url: &str) -> Result<PrivateBlobHandle> {
+        let safe_url = Safe::parse_url(url)?;
+        if safe_url.data_type() != DataType::Bytes {
+            return Err(Error::InvalidInput(format!(
+                "The URL provided doesn't target a Blob but a '{}'",
+                safe_url.data_type()
+            )));
+        }
+
+        Ok(PrivateBlobHandle {
+            safe: self.clone(),
+            safe_url,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_private_blob_handle_read_range() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let data = Bytes::from("Something super private");
+        let xorurl = safe
+            .store_private_bytes(data.clone(), Some("text/plain"))
+            .await?;
+
+        let handle = safe.private_blob_handle(&xorurl)?;
+        assert_eq!(handle.xorurl(), xorurl);
+        assert_eq!(handle.media_type(), Some("text/plain".to_string()));
+
+        let received = retry_loop!(handle.read());
+        assert_eq!(received, data);
+
+        let partial = handle.read_range(Some(10), Some(15)).await?;
+        assert_eq!(partial, data.slice(10..15));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{helpers::sha3_256_hex, register::EntryHash};
+use crate::{Error, PublicKey, Result, Safe, Url, VersionHash, XorUrl};
+use bytes::Bytes;
+use chrono::Utc;
+use log::debug;
+use safe_network::types::Signature;
+use serde::{Deserialize, Serialize};
+
+/// What a proof attests, i.e. that some content was found at a URL at a given time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProofStatement {
+    /// URL of the content, including the entry hash as version for Register entries
+    pub url: XorUrl,
+    /// SHA3-256 hash of the content, hex encoded
+    pub content_hash: String,
+    /// Time the content was fetched from the network, as seconds since the Unix epoch
+    pub fetched_at: i64,
+}
+
+/// Proof that some content was retrieved from the network, which can be shared with
+/// third parties for them to verify it offline with `verify_proof`.
+///
+/// The statement is signed by the witness which retrieved the content. The client doesn't
+/// currently obtain section signed responses from the network, so the witness is the
+/// signer of the instance which created the proof, and verifiers decide which witnesses
+/// they trust. Proofs signed with section keys can be verified the same way.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Proof {
+    pub statement: ProofStatement,
+    pub witness: PublicKey,
+    signature: Signature,
+}
+
+impl Safe {
+    /// # Get a Register entry along with a proof of it
+    pub async fn get_entry_with_proof(&self, url: &str, hash: EntryHash) -> Result<(Url, Proof)> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let entry = self.fetch_register_entry(&safe_url, hash).await?;
+
+        safe_url.set_content_version(Some(VersionHash::from(&hash)));
+        let proof = self
+            .create_proof(&safe_url, entry.to_string().as_bytes())
+            .await?;
+        Ok((entry, proof))
+    }
+
+    /// # Get the content of a public Blob along with a proof of it
+    pub async fn get_blob_with_proof(&self, url: &str) -> Result<(Bytes, Proof)> {
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let data = self.fetch_public_data(&safe_url, None).await?;
+        let proof = self.create_proof(&safe_url, &data).await?;
+        Ok((data, proof))
+    }
+
+    // Sign a statement about the content found at a URL with this instance's signer
+    pub(crate) async fn create_proof(&self, safe_url: &Url, content: &[u8]) -> Result<Proof> {
+        let statement = ProofStatement {
+            url: safe_url.to_string(),
+            content_hash: sha3_256_hex(content),
+            fetched_at: Utc::now().timestamp(),
+        };
+        debug!("Creating proof of: {:?}", statement);
+
+        let signer = self.signer()?;
+        let signature = signer.sign(&serialise_statement(&statement)?).await?;
+        Ok(Proof {
+            statement,
+            witness: signer.public_key(),
+            signature,
+        })
+    }
+}
+
+/// # Verify a proof offline
+///
+/// Checks the proof was signed by one of the trusted keys, and that it attests the given
+/// content. For Register entries the content is the entry's URL, e.g. `entry.to_string()`.
+pub fn verify_proof(proof: &Proof, trusted_keys: &[PublicKey], content: &[u8]) -> Result<()> {
+    if !trusted_keys.contains(&proof.witness) {
+        return Err(Error::InvalidInput(format!(
+            "Proof of \"{}\" is signed by an untrusted key: {:?}",
+            proof.statement.url, proof.witness
+        )));
+    }
+
+    let signed_bytes = serialise_statement(&proof.statement)?;
+    proof
+        .witness
+        .verify(&proof.signature, &signed_bytes)
+        .map_err(|err| {
+            Error::ContentError(format!(
+                "Invalid signature found on proof of \"{}\": {:?}",
+                proof.statement.url, err
+            ))
+        })?;
+
+    if sha3_256_hex(content) != proof.statement.content_hash {
+        return Err(Error::ContentError(format!(
+            "Content doesn't match the proof of \"{}\"",
+            proof.statement.url
+        )));
+    }
+
+    Ok(())
+}
+
+fn serialise_statement(statement: &ProofStatement) -> Result<Vec<u8>> {
+    rmp_serde::to_vec_named(statement)
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise proof: {:?}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_blob_and_entry_proofs() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let trusted = vec![safe.get_my_keypair()?.public_key()];
+
+        let xorurl = safe
+            .store_public_bytes(Bytes::from("proven content"), None, false)
+            .await?;
+        let (data, proof) = retry_loop!(safe.get_blob_with_proof(&xorurl));
+        verify_proof(&proof, &trusted, &data)?;
+        assert!(verify_proof(&proof, &trusted, b"tampered content").is_err());
+        assert!(verify_proof(&proof, &[safe.keypair().public_key()], &data).is_err());
+
+        let register = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&register));
+        let hash = safe
+            .write_to_register(&register, Url::from_xorurl(&xorurl)?, Default::default())
+            .await?;
+        let (entry, proof) = retry_loop!(safe.get_entry_with_proof(&register, hash));
+        verify_proof(&proof, &trusted, entry.to_string().as_bytes())?;
+
+        let mut tampered = proof.clone();
+        tampered.statement.fetched_at += 1;
+        assert!(verify_proof(&tampered, &trusted, entry.to_string().as_bytes()).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,270 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    consts::PREDICATE_LINK,
+    encryption::{derive_symmetric_key, keyed_hash, SymmetricKey},
+    files::{FilesMap, FILES_CONTAINER_TYPE_TAG},
+    register::EntryHash,
+    UrlAddressExt,
+};
+use crate::{ContentType, DataType, Error, Result, Safe, Scope, Url, XorName, XorUrl};
+use log::{debug, info};
+use safe_network::types::{BytesAddress, DataAddress};
+use std::collections::BTreeSet;
+
+// Type tag to use for the private Multimap mapping private content to its published copy
+const PUBLISHED_TYPE_TAG: u64 = 2_500;
+
+// Context used to derive the location and lookup keys of the published content mapping
+const PUBLISHED_CONTEXT: &[u8] = b"sn_api-published";
+
+impl Safe {
+    /// # Publish private content
+    ///
+    /// Republishes a Private Blob, or the latest version of a private FilesContainer
+    /// together with its private files, as public content, returning its public XOR-URL.
+    /// A record mapping the private content to its public copy is kept in a private
+    /// Multimap, thus publishing the same Blob again returns the same public XOR-URL,
+    /// and publishing a FilesContainer again adds a new version to the same public
+    /// FilesContainer. The mapping can be looked up with `published_url`.
+    pub async fn make_public(&self, url: &str) -> Result<XorUrl> {
+        info!("Publishing private content: {}", url);
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+
+        match safe_url.data_type() {
+            DataType::Bytes => self.make_blob_public(&safe_url).await,
+            DataType::Register if safe_url.content_type() == ContentType::FilesContainer => {
+                self.make_files_container_public(&safe_url).await
+            }
+            other => Err(Error::InvalidInput(format!(
+                "Only Blobs and FilesContainers can be published, the URL targets a '{}'",
+                other
+            ))),
+        }
+    }
+
+    /// # Look up the public copy of private content published with `make_public`
+    pub async fn published_url(&self, url: &str) -> Result<Option<XorUrl>> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let (mapping_xorurl, key) = self.published_mapping_location()?;
+        let lookup_key = keyed_hash(&key, safe_url.to_string().as_bytes());
+
+        let entries = match self
+            .fetch_multimap_value_by_key(&Url::from_xorurl(&mapping_xorurl)?, &lookup_key)
+            .await
+        {
+            Ok(entries) => entries,
+            Err(err) => {
+                debug!("No published content mapping found: {:?}", err);
+                return Ok(None);
+            }
+        };
+
+        entries
+            .into_iter()
+            .next()
+            .map(|(_, (_, value))| {
+                String::from_utf8(value).map_err(|err| {
+                    Error::ContentError(format!("Couldn't parse published URL: {:?}", err))
+                })
+            })
+            .transpose()
+    }
+
+    // Private helper to publish a Private Blob, unless it was already published
+    async fn make_blob_public(&self, safe_url: &Url) -> Result<XorUrl> {
+        if let BytesAddress::Public(_) = safe_url.bytes_address()? {
+            return Err(Error::InvalidInput(format!(
+                "The Blob at \"{}\" is already public",
+                safe_url
+            )));
+        }
+        if let Some(public_xorurl) = self.published_url(&safe_url.to_string()).await? {
+            return Ok(public_xorurl);
+        }
+
+        let handle = self.private_blob_handle(&safe_url.to_string())?;
+        let data = handle.read().await?;
+        let public_xorurl = self
+            .store_public_bytes(data, handle.media_type().as_deref(), false)
+            .await?;
+
+        self.record_published(safe_url, &public_xorurl).await?;
+        Ok(public_xorurl)
+    }
+
+    // Private helper to publish the latest version of a private FilesContainer,
+    // as a new version of its public copy if it was already published
+    async fn make_files_container_public(&self, safe_url: &Url) -> Result<XorUrl> {
+        if let (_, _, Scope::Public) = safe_url.register_parts()? {
+            return Err(Error::InvalidInput(format!(
+                "The FilesContainer at \"{}\" is already public",
+                safe_url
+            )));
+        }
+        let (_, mut files_map) = self.fetch_files_container(safe_url).await?;
+        self.make_files_public(&mut files_map).await?;
+        let files_map_xorurl = self.store_files_map(&files_map, Scope::Public).await?;
+
+        let (public_url, parents) = match self.published_url(&safe_url.to_string()).await? {
+            Some(public_xorurl) => {
+                let mut public_url = Url::from_url(&public_xorurl)?;
+                public_url.set_content_version(None);
+                let parents: BTreeSet<EntryHash> = self
+                    .fetch_register_entries(&public_url)
+                    .await?
+                    .into_iter()
+                    .map(|(hash, _)| hash)
+                    .collect();
+                (public_url, parents)
+            }
+            None => {
+                let xorname = self
+                    .safe_client
+                    .store_register(None, FILES_CONTAINER_TYPE_TAG, None, false)
+                    .await?;
+                let public_xorurl = Url::encode_register(
+                    xorname,
+                    FILES_CONTAINER_TYPE_TAG,
+                    Scope::Public,
+                    ContentType::FilesContainer,
+                    self.xorurl_base,
+                )?;
+                self.record_published(safe_url, &public_xorurl).await?;
+                (Url::from_xorurl(&public_xorurl)?, BTreeSet::new())
+            }
+        };
+
+        let entry_hash = self
+            .write_to_register(
+                &public_url.to_string(),
+                Url::from_xorurl(&files_map_xorurl)?,
+                parents,
+            )
+            .await?;
+        let mut versioned_url = public_url;
+        versioned_url.set_content_version(Some((&entry_hash).into()));
+        Ok(versioned_url.to_string())
+    }
+
+    // Private helper to replace the links to Private Blobs in a FilesMap with links to
+    // their published copies, publishing them if they weren't already
+    async fn make_files_public(&self, files_map: &mut FilesMap) -> Result<()> {
+        for (path, file_item) in files_map.iter_mut() {
+            let link = match file_item.get(PREDICATE_LINK) {
+                Some(link) => Url::from_url(link)?,
+                None => continue,
+            };
+            match link.address() {
+                DataAddress::Bytes(BytesAddress::Private(_)) => {}
+                _ => continue,
+            }
+
+            debug!("Publishing file at path {}", path);
+            let public_link = self.make_blob_public(&link).await?;
+            let _ = file_item.insert(PREDICATE_LINK.to_string(), public_link);
+        }
+        Ok(())
+    }
+
+    // Private helper to record the public copy of private content
+    async fn record_published(&self, safe_url: &Url, public_xorurl: &str) -> Result<()> {
+        let (mapping_xorurl, key) = self.published_mapping_location()?;
+        let mapping_url = Url::from_xorurl(&mapping_xorurl)?;
+        match self.fetch_multimap_values(&mapping_url).await {
+            Ok(_) | Err(Error::EmptyContent(_)) => {}
+            Err(Error::ContentNotFound(_)) => {
+                debug!("Published content mapping not found, creating it");
+                let (xorname, _, _) = mapping_url.register_parts()?;
+                let _ = self
+                    .multimap_create(Some(xorname), PUBLISHED_TYPE_TAG, true)
+                    .await?;
+            }
+            Err(err) => return Err(err),
+        }
+
+        let lookup_key = keyed_hash(&key, safe_url.to_string().as_bytes());
+        let entry = (lookup_key.to_vec(), public_xorurl.as_bytes().to_vec());
+        let _ = self
+            .multimap_insert(&mapping_xorurl, entry, BTreeSet::new())
+            .await?;
+        Ok(())
+    }
+
+    // Private helper to obtain the location of the published content mapping,
+    // and the key to derive its lookup keys from
+    fn published_mapping_location(&self) -> Result<(XorUrl, SymmetricKey)> {
+        let keypair = self.get_my_keypair()?;
+        let key = derive_symmetric_key(&keypair, PUBLISHED_CONTEXT)?;
+        let xorname = XorName(keyed_hash(&key, PUBLISHED_CONTEXT));
+        let xorurl = Url::encode_register(
+            xorname,
+            PUBLISHED_TYPE_TAG,
+            Scope::Private,
+            ContentType::Multimap,
+            self.xorurl_base,
+        )?;
+
+        Ok((xorurl, key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::test_helpers::new_safe_instance, fetch::SafeData, retry_loop, retry_loop_for_pattern,
+    };
+    use anyhow::Result;
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn test_make_public() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        safe.set_private_by_default(true);
+        let data = Bytes::from("Something not to be published by accident");
+
+        let private_xorurl = safe.store_bytes(data.clone(), None, false).await?;
+        let _ = retry_loop!(safe.private_blob_handle(&private_xorurl)?.read());
+        assert!(safe.published_url(&private_xorurl).await?.is_none());
+
+        let public_xorurl = safe.make_public(&private_xorurl).await?;
+        let mut other = new_safe_instance().await?;
+        let published = retry_loop!(other.fetch(&public_xorurl, None));
+        assert!(matches!(published, SafeData::PublicBlob { data: d, .. } if d == data));
+
+        // publishing it again returns the same public copy
+        let _ = retry_loop_for_pattern!(safe.published_url(&private_xorurl), Ok(Some(url)) if *url == public_xorurl)?;
+        assert_eq!(safe.make_public(&private_xorurl).await?, public_xorurl);
+
+        // public content cannot be published again
+        assert!(safe.make_public(&public_xorurl).await.is_err());
+
+        // FilesContainers are published together with their private files
+        let (container_xorurl, _, _) = safe
+            .files_container_create(Some("./testdata/test.md"), None, false, false, false)
+            .await?;
+        let (_, _, scope) = Url::from_url(&container_xorurl)?.register_parts()?;
+        assert_eq!(scope, Scope::Private);
+        let _ = retry_loop!(safe.fetch(&container_xorurl, None));
+
+        let public_container = safe.make_public(&container_xorurl).await?;
+        let (_, files_map) = retry_loop!(other.files_container_get(&public_container));
+        let link = Url::from_url(&files_map["/test.md"][PREDICATE_LINK])?;
+        assert!(matches!(link.bytes_address()?, BytesAddress::Public(_)));
+        let mut file_url = Url::from_url(&public_container)?;
+        file_url.set_path("/test.md");
+        let _ = retry_loop!(other.fetch(&file_url.to_string(), None));
+
+        Ok(())
+    }
+}
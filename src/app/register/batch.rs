@@ -0,0 +1,206 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{Entry, EntryHash};
+use crate::{app::whois::RegisterWriters, Error, MissingPermission, Result, Safe, UrlAddressExt};
+use futures::future::join_all;
+use log::debug;
+use std::collections::BTreeSet;
+
+/// Collects writes to several Registers to be committed together, see `RegisterBatch::commit`
+pub struct RegisterBatch {
+    safe: Safe,
+    writes: Vec<(String, Entry, Option<BTreeSet<EntryHash>>)>,
+}
+
+/// Outcome of committing a `RegisterBatch`, with the result of each of its writes
+/// in the order they were added to the batch
+#[derive(Debug)]
+pub struct RegisterBatchReport {
+    results: Vec<(String, Result<EntryHash>)>,
+}
+
+impl RegisterBatch {
+    /// Create an empty batch of writes
+    pub fn new(safe: &Safe) -> Self {
+        Self {
+            safe: safe.clone(),
+            writes: Vec::new(),
+        }
+    }
+
+    /// Add a write of an entry to the Register at the given URL, superseding the given parents
+    pub fn write(&mut self, url: &str, entry: Entry, parents: BTreeSet<EntryHash>) -> &mut Self {
+        self.writes.push((url.to_string(), entry, Some(parents)));
+        self
+    }
+
+    /// Add a write of an entry to the Register at the given URL, superseding the entries
+    /// which are current when the batch is committed, as `Safe::register_append` does
+    pub fn append(&mut self, url: &str, entry: Entry) -> &mut Self {
+        self.writes.push((url.to_string(), entry, None));
+        self
+    }
+
+    /// Number of writes in the batch
+    pub fn len(&self) -> usize {
+        self.writes.len()
+    }
+
+    /// Whether the batch has no writes
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// # Commit all the writes of the batch
+    ///
+    /// The writes are first prepared: the URLs are resolved, the current entries of the
+    /// Registers being appended to are read, and the keypair in use is checked to be allowed
+    /// to write to every Register. If any of this fails nothing is written and the error
+    /// is returned. Otherwise all the writes are submitted at once, and their results are
+    /// returned in a report.
+    ///
+    /// The network doesn't support transactions spanning several Registers, thus the batch
+    /// isn't atomic: a write can still fail after being submitted, e.g. due to a network
+    /// error, while the rest of them succeed. The report tells which ones did, so they can
+    /// be retried. Entries of the batch can't be parents of each other, since their hashes
+    /// are only known once written.
+    pub async fn commit(self) -> Result<RegisterBatchReport> {
+        debug!("Preparing batch of {} Register writes", self.writes.len());
+        let my_pk = self.safe.get_my_keypair()?.public_key();
+        let mut prepared = Vec::with_capacity(self.writes.len());
+        for (url, entry, parents) in self.writes {
+            let (mut safe_url, _) = self.safe.parse_and_resolve_url(&url).await?;
+            safe_url.set_content_version(None);
+            let address = safe_url.register_address()?;
+
+            if let (_, RegisterWriters::Keys(writers)) =
+                self.safe.safe_client.get_register_policy(address).await?
+            {
+                if !writers.contains(&my_pk) {
+                    return Err(Error::PermissionDenied {
+                        missing: MissingPermission::Writer,
+                        address: url,
+                    });
+                }
+            }
+
+            let parents = match parents {
+                Some(parents) => parents,
+                None => match self.safe.fetch_register_entries(&safe_url).await {
+                    Ok(entries) => entries.into_iter().map(|(hash, _)| hash).collect(),
+                    Err(Error::EmptyContent(_)) => BTreeSet::new(),
+                    Err(err) => return Err(err),
+                },
+            };
+            let entry = self.safe.seal_register_entry(&safe_url, entry).await?;
+            prepared.push((url, address, entry, parents));
+        }
+
+        debug!("Submitting batch of {} Register writes", prepared.len());
+        let safe = &self.safe;
+        let results = join_all(prepared.into_iter().map(
+            |(url, address, entry, parents)| async move {
+                let result = safe
+                    .safe_client
+                    .write_to_register(address, entry, parents)
+                    .await;
+                safe.emit_outcome("write_to_register", &url, &result);
+                (url, result)
+            },
+        ))
+        .await;
+
+        Ok(RegisterBatchReport { results })
+    }
+}
+
+impl RegisterBatchReport {
+    /// The URL of the Register and the result of each write
+    pub fn results(&self) -> &[(String, Result<EntryHash>)] {
+        &self.results
+    }
+
+    /// Whether all the writes succeeded
+    pub fn is_complete(&self) -> bool {
+        self.results.iter().all(|(_, result)| result.is_ok())
+    }
+
+    /// The URLs of the Registers whose writes failed
+    pub fn failed(&self) -> Vec<&str> {
+        self.results
+            .iter()
+            .filter(|(_, result)| result.is_err())
+            .map(|(url, _)| url.as_str())
+            .collect()
+    }
+
+    /// The hashes of the entries written, in the order the writes were added to the batch,
+    /// or the error of the first write which failed
+    pub fn into_hashes(self) -> Result<Vec<EntryHash>> {
+        self.results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern, Url};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_register_batch_commit() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let data_xorurl = safe.register_create(None, 25_000, false).await?;
+        let index_xorurl = safe.register_create(None, 25_000, true).await?;
+        let _ = retry_loop!(safe.register_read(&data_xorurl));
+        let _ = retry_loop!(safe.register_read(&index_xorurl));
+
+        let data = Url::from_url("safe://data")?;
+        let index = Url::from_url("safe://index")?;
+        let mut batch = RegisterBatch::new(&safe);
+        let _ = batch
+            .write(&data_xorurl, data.clone(), BTreeSet::new())
+            .append(&index_xorurl, index.clone());
+        assert_eq!(batch.len(), 2);
+
+        let report = batch.commit().await?;
+        assert!(report.is_complete());
+        assert!(report.failed().is_empty());
+        let hashes = report.into_hashes()?;
+
+        let data_entries =
+            retry_loop_for_pattern!(safe.register_read(&data_xorurl), Ok(v) if !v.is_empty())?;
+        assert_eq!(data_entries.into_iter().next(), Some((hashes[0], data)));
+        let index_entries =
+            retry_loop_for_pattern!(safe.register_read(&index_xorurl), Ok(v) if !v.is_empty())?;
+        assert_eq!(index_entries.into_iter().next(), Some((hashes[1], index)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_batch_nothing_written_if_preparation_fails() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+        let blob_xorurl = safe
+            .store_public_bytes(bytes::Bytes::from("not a Register"), None, false)
+            .await?;
+
+        let mut batch = RegisterBatch::new(&safe);
+        let _ = batch
+            .append(&xorurl, Url::from_url("safe://data")?)
+            .append(&blob_xorurl, Url::from_url("safe://index")?);
+        assert!(batch.commit().await.is_err());
+        assert!(safe.register_read(&xorurl).await?.is_empty());
+
+        Ok(())
+    }
+}
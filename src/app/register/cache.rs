@@ -0,0 +1,252 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{Entry, EntryHash};
+use crate::Safe;
+use log::debug;
+use safe_network::types::RegisterAddress;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+type RegisterEntries = BTreeSet<(EntryHash, Entry)>;
+
+#[derive(Default)]
+struct CacheState {
+    ttl: Option<Duration>,
+    entries: BTreeMap<RegisterAddress, (Instant, RegisterEntries)>,
+    // Hashes of the entries written by the client which weren't read back yet, given up
+    // on after the TTL as they could have been superseded by other clients meanwhile
+    pending: BTreeMap<RegisterAddress, (Instant, BTreeSet<EntryHash>)>,
+}
+
+// Current entries of the Registers read recently, kept for a TTL. The entries written by
+// the client replace their parents as the entries cached, and reads which don't include
+// them yet, since the network is eventually consistent, are not cached, so the client
+// never reads stale entries from the cache after its own writes. Clones of an instance
+// share the same cache.
+#[derive(Clone, Default)]
+pub(crate) struct RegisterCache {
+    state: Arc<Mutex<CacheState>>,
+}
+
+impl RegisterCache {
+    pub(crate) fn set_ttl(&self, ttl: Option<Duration>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.ttl = ttl;
+            if ttl.is_none() {
+                state.entries.clear();
+                state.pending.clear();
+            }
+        }
+    }
+
+    pub(crate) fn ttl(&self) -> Option<Duration> {
+        self.state.lock().ok().and_then(|state| state.ttl)
+    }
+
+    // Entries of the Register cached within the TTL, if any
+    pub(crate) fn get(&self, address: &RegisterAddress) -> Option<RegisterEntries> {
+        let mut state = self.state.lock().ok()?;
+        let ttl = state.ttl?;
+        let (cached_at, entries) = state.entries.get(address)?;
+        if cached_at.elapsed() < ttl {
+            debug!("Register entries at {:?} found in the cache", address);
+            return Some(entries.clone());
+        }
+        let _ = state.entries.remove(address);
+        None
+    }
+
+    // Cache the entries read from the network, unless they miss entries written by the client
+    pub(crate) fn insert(&self, address: RegisterAddress, entries: &RegisterEntries) {
+        if let Ok(mut state) = self.state.lock() {
+            let ttl = match state.ttl {
+                Some(ttl) => ttl,
+                None => return,
+            };
+            if let Some((written_at, pending)) = state.pending.get_mut(&address) {
+                // the entries we superseded with later writes are not pending anymore,
+                // thus all the pending ones must be among the entries read
+                pending.retain(|hash| !entries.iter().any(|(h, _)| h == hash));
+                if !pending.is_empty() && written_at.elapsed() < ttl {
+                    debug!(
+                        "Register read at {:?} misses our writes, not cached",
+                        address
+                    );
+                    return;
+                }
+                let _ = state.pending.remove(&address);
+            }
+            let _ = state
+                .entries
+                .insert(address, (Instant::now(), entries.clone()));
+        }
+    }
+
+    // Apply an entry written by the client to the entries cached, replacing its parents
+    pub(crate) fn written(
+        &self,
+        address: RegisterAddress,
+        hash: EntryHash,
+        entry: &Entry,
+        parents: &BTreeSet<EntryHash>,
+    ) {
+        if let Ok(mut state) = self.state.lock() {
+            if state.ttl.is_none() {
+                return;
+            }
+            if let Some((_, entries)) = state.entries.get_mut(&address) {
+                entries.retain(|(h, _)| !parents.contains(h));
+                let _ = entries.insert((hash, entry.clone()));
+            }
+            let (written_at, pending) = state
+                .pending
+                .entry(address)
+                .or_insert_with(|| (Instant::now(), BTreeSet::new()));
+            *written_at = Instant::now();
+            pending.retain(|h| !parents.contains(h));
+            let _ = pending.insert(hash);
+        }
+    }
+
+    pub(crate) fn invalidate(&self, address: &RegisterAddress) {
+        if let Ok(mut state) = self.state.lock() {
+            let _ = state.entries.remove(address);
+            let _ = state.pending.remove(address);
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.entries.clear();
+            state.pending.clear();
+        }
+    }
+}
+
+impl Safe {
+    /// # Cache the current entries of the Registers read
+    ///
+    /// With a TTL set, the current entries of a Register read, e.g. with `register_read`
+    /// or when resolving an NRS name, are kept in memory and returned by further reads of
+    /// the same Register within the TTL, without querying the network. The writes made
+    /// by this instance, or any of its clones, are applied to the entries cached right
+    /// away, but the writes made by other clients are not seen until the TTL elapses.
+    /// Without a TTL, which is the default, nothing is cached.
+    pub fn set_register_cache_ttl(&mut self, ttl: Option<Duration>) {
+        self.safe_client.register_cache().set_ttl(ttl);
+    }
+
+    /// TTL of the cache of Register entries, if it's enabled
+    pub fn register_cache_ttl(&self) -> Option<Duration> {
+        self.safe_client.register_cache().ttl()
+    }
+
+    /// Drop all the Register entries cached, so the next reads query the network
+    pub fn register_cache_clear(&self) {
+        self.safe_client.register_cache().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::test_helpers::new_safe_instance, retry_loop_for_pattern, Url, UrlAddressExt,
+        DEFAULT_XORURL_BASE,
+    };
+    use anyhow::Result;
+    use safe_network::url::{ContentType, Scope};
+    use xor_name::XorName;
+
+    #[test]
+    fn test_register_cache_skips_reads_missing_our_writes() -> Result<()> {
+        let cache = RegisterCache::default();
+        cache.set_ttl(Some(Duration::from_secs(60)));
+        let address = Url::from_xorurl(&Url::encode_register(
+            XorName::random(),
+            25_000,
+            Scope::Public,
+            ContentType::Raw,
+            DEFAULT_XORURL_BASE,
+        )?)?
+        .register_address()?;
+
+        let first = ([1; 32], Url::from_url("safe://first")?);
+        let second = ([2; 32], Url::from_url("safe://second")?);
+        let stale: RegisterEntries = vec![first.clone()].into_iter().collect();
+        cache.written(
+            address,
+            second.0,
+            &second.1,
+            &vec![first.0].into_iter().collect(),
+        );
+
+        // a read not including our write yet is not cached
+        cache.insert(address, &stale);
+        assert_eq!(cache.get(&address), None);
+
+        let current: RegisterEntries = vec![second.clone()].into_iter().collect();
+        cache.insert(address, &current);
+        assert_eq!(cache.get(&address), Some(current.clone()));
+
+        // further writes are applied to the entries cached
+        let third = ([3; 32], Url::from_url("safe://third")?);
+        cache.written(
+            address,
+            third.0,
+            &third.1,
+            &vec![second.0].into_iter().collect(),
+        );
+        assert_eq!(cache.get(&address), Some(vec![third].into_iter().collect()));
+
+        cache.invalidate(&address);
+        assert_eq!(cache.get(&address), None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_cache() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let entry = Url::from_url("safe://cached")?;
+        let hash = safe
+            .write_to_register(&xorurl, entry.clone(), BTreeSet::new())
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(v) if !v.is_empty())?;
+
+        safe.set_register_cache_ttl(Some(Duration::from_secs(60)));
+        let (first, first_diagnostics) = safe.with_diagnostics(safe.register_read(&xorurl)).await;
+        assert!(first_diagnostics.queries > 0);
+        let (second, second_diagnostics) = safe.with_diagnostics(safe.register_read(&xorurl)).await;
+        assert_eq!(second_diagnostics.queries, 0);
+        assert_eq!(first?, second?);
+
+        // our own writes are applied to the entries cached
+        let new_entry = Url::from_url("safe://updated")?;
+        let new_hash = safe
+            .write_to_register(&xorurl, new_entry.clone(), vec![hash].into_iter().collect())
+            .await?;
+        let (entries, diagnostics) = safe.with_diagnostics(safe.register_read(&xorurl)).await;
+        assert_eq!(diagnostics.queries, 0);
+        let entries = entries?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries.into_iter().next(), Some((new_hash, new_entry)));
+
+        safe.set_register_cache_ttl(None);
+        let (_, diagnostics) = safe.with_diagnostics(safe.register_read(&xorurl)).await;
+        assert!(diagnostics.queries > 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{Entry, EntryHash};
+use crate::{Error, Result, Safe, Url, UrlAddressExt};
+use bytes::Bytes;
+use log::debug;
+use std::{
+    collections::BTreeSet,
+    time::{Duration, Instant},
+};
+
+/// How the writes queued within a coalescing window are turned into Register entries
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoalesceMode {
+    /// Only the last entry queued within the window is written to the Register
+    LatestWins,
+    /// All entries queued within the window are stored together in a Blob, with the same
+    /// scope as the Register, and a single entry linking to such Blob is written to the Register
+    Combine,
+}
+
+/// Batches rapid successive writes to the same Register within a time window,
+/// so chatty applications (e.g. editors) generate fewer Register entries.
+///
+/// Entries are only written when the window expires upon a `write`, or when `flush`
+/// is called, thus any pending entry is discarded if the coalescer is dropped without
+/// flushing it first.
+pub struct RegisterWriteCoalescer {
+    safe: Safe,
+    url: String,
+    window: Duration,
+    mode: CoalesceMode,
+    // whether the Register is public, the Blobs of combined entries having the same scope
+    public: bool,
+    parents: BTreeSet<EntryHash>,
+    pending: Vec<Entry>,
+    window_start: Option<Instant>,
+}
+
+impl RegisterWriteCoalescer {
+    /// Create a coalescer for the Register at the given URL, the current
+    /// entries of the Register are used as parents for the first write.
+    pub async fn new(safe: &Safe, url: &str, window: Duration, mode: CoalesceMode) -> Result<Self> {
+        let (safe_url, _) = safe.parse_and_resolve_url(url).await?;
+        let public = safe_url.register_address()?.is_public();
+        let parents = safe
+            .register_read(url)
+            .await?
+            .into_iter()
+            .map(|(hash, _)| hash)
+            .collect();
+
+        Ok(Self {
+            safe: safe.clone(),
+            url: url.to_string(),
+            window,
+            mode,
+            public,
+            parents,
+            pending: Vec::new(),
+            window_start: None,
+        })
+    }
+
+    /// Queue an entry to be written to the Register. If the coalescing window has expired
+    /// all pending entries are flushed, returning the hash of the entry written.
+    pub async fn write(&mut self, entry: Entry) -> Result<Option<EntryHash>> {
+        let now = Instant::now();
+        let window_start = *self.window_start.get_or_insert(now);
+        self.pending.push(entry);
+
+        if now.duration_since(window_start) >= self.window {
+            self.flush().await
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Number of entries queued and not yet written to the Register
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Write all pending entries to the Register, returning the hash of the entry written,
+    /// or `None` if there was nothing pending.
+    pub async fn flush(&mut self) -> Result<Option<EntryHash>> {
+        self.window_start = None;
+        if self.pending.is_empty() {
+            return Ok(None);
+        }
+
+        debug!(
+            "Flushing {} coalesced entries to Register at {}",
+            self.pending.len(),
+            self.url
+        );
+        let entry = match self.mode {
+            CoalesceMode::LatestWins => self.pending[self.pending.len() - 1].clone(),
+            CoalesceMode::Combine => {
+                let serialised_entries = rmp_serde::to_vec_named(&self.pending).map_err(|err| {
+                    Error::Serialisation(format!(
+                        "Couldn't serialise the coalesced entries: {:?}",
+                        err
+                    ))
+                })?;
+                let bytes = Bytes::from(serialised_entries);
+                let xorurl = if self.public {
+                    self.safe.store_public_bytes(bytes, None, false).await?
+                } else {
+                    self.safe.store_private_bytes(bytes, None).await?
+                };
+                Url::from_xorurl(&xorurl)?
+            }
+        };
+
+        let hash = self
+            .safe
+            .write_to_register(&self.url, entry, self.parents.clone())
+            .await?;
+        self.pending.clear();
+        self.parents = vec![hash].into_iter().collect();
+
+        Ok(Some(hash))
+    }
+}
+
+impl Safe {
+    /// Decode a Register entry written by a `RegisterWriteCoalescer` in
+    /// `CoalesceMode::Combine` mode, returning the entries it combined
+    pub async fn register_read_coalesced(&self, entry: &Entry) -> Result<Vec<Entry>> {
+        let serialised_entries = self.fetch_public_data(entry, None).await?;
+        rmp_serde::from_slice(&serialised_entries).map_err(|err| {
+            Error::ContentError(format!(
+                "Couldn't parse coalesced Register entry: {:?}",
+                err
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_register_write_coalescer_combine() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let mut coalescer = RegisterWriteCoalescer::new(
+            &safe,
+            &xorurl,
+            Duration::from_secs(3600),
+            CoalesceMode::Combine,
+        )
+        .await?;
+
+        let entries = vec![
+            Url::from_url("safe://test1")?,
+            Url::from_url("safe://test2")?,
+            Url::from_url("safe://test3")?,
+        ];
+        for entry in entries.iter() {
+            assert_eq!(coalescer.write(entry.clone()).await?, None);
+        }
+        assert_eq!(coalescer.pending(), 3);
+
+        let hash = coalescer.flush().await?;
+        assert!(hash.is_some());
+        assert_eq!(coalescer.pending(), 0);
+
+        let received =
+            retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(v) if !v.is_empty())?;
+        assert_eq!(received.len(), 1);
+        let (received_hash, received_entry) = received.into_iter().next().unwrap();
+        assert_eq!(Some(received_hash), hash);
+        assert_eq!(
+            safe.register_read_coalesced(&received_entry).await?,
+            entries
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_write_coalescer_combine_private() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, true).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let mut coalescer = RegisterWriteCoalescer::new(
+            &safe,
+            &xorurl,
+            Duration::from_secs(3600),
+            CoalesceMode::Combine,
+        )
+        .await?;
+        let entry = Url::from_url("safe://test1")?;
+        let _ = coalescer.write(entry.clone()).await?;
+        let hash = coalescer.flush().await?;
+
+        // the combined entries are stored in a private Blob as the Register is private
+        let received =
+            retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(v) if !v.is_empty())?;
+        let (received_hash, received_entry) = received.into_iter().next().unwrap();
+        assert_eq!(Some(received_hash), hash);
+        assert!(!received_entry.bytes_address()?.is_public());
+        assert_eq!(
+            safe.register_read_coalesced(&received_entry).await?,
+            vec![entry]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_write_coalescer_latest_wins() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let mut coalescer = RegisterWriteCoalescer::new(
+            &safe,
+            &xorurl,
+            Duration::from_secs(3600),
+            CoalesceMode::LatestWins,
+        )
+        .await?;
+
+        let _ = coalescer.write(Url::from_url("safe://test1")?).await?;
+        let latest = Url::from_url("safe://test2")?;
+        let _ = coalescer.write(latest.clone()).await?;
+        let _ = coalescer.flush().await?;
+
+        let received =
+            retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(v) if !v.is_empty())?;
+        assert_eq!(received.len(), 1);
+        assert!(received.iter().all(|(_, entry)| *entry == latest));
+
+        Ok(())
+    }
+}
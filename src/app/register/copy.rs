@@ -0,0 +1,165 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{Entry, EntryHash};
+use crate::{Result, Safe, Url, XorName, XorUrl};
+use log::{debug, info};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Entries of a Register along with their parents, in topological order
+pub(super) type RegisterNodes = Vec<(EntryHash, Entry, BTreeSet<EntryHash>)>;
+
+impl Safe {
+    /// # Copy a Register to a new Register owned by the caller
+    ///
+    /// All the entries of the source Register are written to a new Register, each of them
+    /// superseding the copies of its parents, thus the copy has the same history, although
+    /// its entries have different hashes. This allows to fork a Register which the caller
+    /// can't write to, e.g. a shared public Register into a writable private copy.
+    /// It fails if the history of the source Register can't be retrieved. The content the
+    /// entries link to is not copied.
+    pub async fn register_copy(
+        &self,
+        src_url: &str,
+        dst_name: Option<XorName>,
+        type_tag: u64,
+        private: bool,
+    ) -> Result<XorUrl> {
+        info!("Copying Register at {}", src_url);
+        let (mut safe_url, _) = self.parse_and_resolve_url(src_url).await?;
+        safe_url.set_content_version(None);
+        let nodes = self.read_register_nodes(&safe_url).await?;
+
+        let dst_xorurl = self.register_create(dst_name, type_tag, private).await?;
+        self.write_register_nodes(&dst_xorurl, nodes).await?;
+
+        Ok(dst_xorurl)
+    }
+
+    // Read the entries of a Register, opened, with their parents in topological order
+    pub(super) async fn read_register_nodes(&self, safe_url: &Url) -> Result<RegisterNodes> {
+        let history = self.register_history(&safe_url.to_string()).await?;
+        let mut nodes = vec![];
+        for hash in history.topological_order() {
+            if let Some(node) = history.get(&hash) {
+                let entry = self
+                    .open_register_entry(safe_url, node.entry.clone())
+                    .await?;
+                nodes.push((hash, entry, node.parents.clone()));
+            }
+        }
+
+        Ok(nodes)
+    }
+
+    // Write entries, in topological order, to a Register, each of them superseding
+    // the entries written for its parents, returning the hash each entry was written with
+    pub(super) async fn write_register_nodes(
+        &self,
+        dst_xorurl: &str,
+        nodes: RegisterNodes,
+    ) -> Result<BTreeMap<EntryHash, EntryHash>> {
+        let mut copies = BTreeMap::<EntryHash, EntryHash>::new();
+        for (hash, entry, parents) in nodes {
+            let parents = parents
+                .iter()
+                .filter_map(|parent| copies.get(parent).copied())
+                .collect();
+            let copy = self.write_to_register(dst_xorurl, entry, parents).await?;
+            let _ = copies.insert(hash, copy);
+        }
+        debug!(
+            "Wrote {} entries to Register at {}",
+            copies.len(),
+            dst_xorurl
+        );
+
+        Ok(copies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop_for_pattern, UrlAddressExt};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_register_copy() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        // root <- entry
+        let src_xorurl = safe.register_create(None, 25_000, false).await?;
+        let root = safe
+            .write_to_register(&src_xorurl, Url::from_url("safe://root")?, BTreeSet::new())
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_read(&src_xorurl), Ok(entries) if !entries.is_empty())?;
+        let entry = Url::from_url("safe://shared-entry")?;
+        let _ = safe
+            .write_to_register(&src_xorurl, entry.clone(), vec![root].into_iter().collect())
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_history(&src_xorurl), Ok(history) if history.nodes().len() == 2)?;
+
+        let dst_xorurl = safe.register_copy(&src_xorurl, None, 25_001, true).await?;
+        assert!(!Url::from_url(&dst_xorurl)?.register_address()?.is_public());
+        let copied = retry_loop_for_pattern!(safe.register_read(&dst_xorurl), Ok(entries) if !entries.is_empty())?;
+        let copied_entries: Vec<&Url> = copied.iter().map(|(_, entry)| entry).collect();
+        assert_eq!(copied_entries, vec![&entry]);
+
+        let dst_history = retry_loop_for_pattern!(safe.register_history(&dst_xorurl), Ok(history) if history.nodes().len() == 2)?;
+        assert_eq!(dst_history.roots().len(), 1);
+        assert_eq!(dst_history.tips().len(), 1);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "sim")]
+    #[tokio::test]
+    async fn test_register_copy_history_sim() -> Result<()> {
+        use crate::app::sim::{SimConfig, SimNetwork};
+
+        let sim = SimNetwork::new(SimConfig::default());
+        let mut safe = Safe::default();
+        safe.connect_sim(&sim, None);
+
+        // root <- a
+        //      <- b
+        let src_xorurl = safe.register_create(None, 25_000, false).await?;
+        let root = safe
+            .write_to_register(&src_xorurl, Url::from_url("safe://root")?, BTreeSet::new())
+            .await?;
+        for name in ["safe://a", "safe://b"].iter() {
+            let _ = safe
+                .write_to_register(
+                    &src_xorurl,
+                    Url::from_url(name)?,
+                    vec![root].into_iter().collect(),
+                )
+                .await?;
+        }
+
+        let dst_xorurl = safe.register_copy(&src_xorurl, None, 25_000, true).await?;
+        let src_history = safe.register_history(&src_xorurl).await?;
+        let dst_history = safe.register_history(&dst_xorurl).await?;
+        assert_eq!(dst_history.nodes().len(), 3);
+        assert_eq!(dst_history.roots().len(), 1);
+        assert_eq!(dst_history.forks().len(), 1);
+
+        let entries = |history: &super::super::RegisterHistory| {
+            history
+                .tips()
+                .iter()
+                .filter_map(|hash| history.get(hash))
+                .map(|node| node.entry.to_string())
+                .collect::<BTreeSet<_>>()
+        };
+        assert_eq!(entries(&dst_history), entries(&src_history));
+
+        Ok(())
+    }
+}
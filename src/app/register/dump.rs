@@ -0,0 +1,276 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{copy::RegisterNodes, EntryHash};
+use crate::{Error, Result, Safe, Url, UrlAddressExt, XorName, XorUrl};
+use chrono::Utc;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// Version of the format of the Register dumps created
+const REGISTER_DUMP_FORMAT_VERSION: u32 = 1;
+
+/// Contents of a Register exported with `Safe::register_export`, which can be serialised,
+/// e.g. to JSON, to be kept offline and restored with `Safe::register_import`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegisterDump {
+    /// Version of the format of the dump
+    pub format_version: u32,
+    /// URL of the Register exported
+    pub source: String,
+    pub type_tag: u64,
+    pub private: bool,
+    /// Time the Register was exported at, as seconds since the Unix epoch
+    pub exported_at: i64,
+    /// Entries of the Register, each of them after its parents
+    pub entries: Vec<RegisterDumpEntry>,
+}
+
+/// An entry of an exported Register
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegisterDumpEntry {
+    /// Hash of the entry in the Register exported, hex encoded
+    pub hash: String,
+    /// The entry, decrypted if it was stored encrypted
+    pub entry: String,
+    /// Hashes of the entries it superseded, hex encoded
+    pub parents: Vec<String>,
+}
+
+impl Safe {
+    /// # Export the contents of a Register
+    ///
+    /// The entries of the Register are returned in a `RegisterDump`, decrypted if they
+    /// were stored encrypted, thus it must be kept as safe as the Register's content.
+    /// The whole history of the Register is exported, each entry with its parents. The
+    /// content the entries link to is not exported.
+    pub async fn register_export(&self, url: &str) -> Result<RegisterDump> {
+        info!("Exporting Register at {}", url);
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let address = safe_url.register_address()?;
+        let nodes = self.read_register_nodes(&safe_url).await?;
+
+        Ok(RegisterDump {
+            format_version: REGISTER_DUMP_FORMAT_VERSION,
+            source: safe_url.to_string(),
+            type_tag: address.tag(),
+            private: !address.is_public(),
+            exported_at: Utc::now().timestamp(),
+            entries: nodes
+                .into_iter()
+                .map(|(hash, entry, parents)| RegisterDumpEntry {
+                    hash: hex::encode(hash),
+                    entry: entry.to_string(),
+                    parents: parents.iter().map(hex::encode).collect(),
+                })
+                .collect(),
+        })
+    }
+
+    /// # Import the contents of a Register into a new Register
+    ///
+    /// A new Register is created, on the network this instance is connected to, with the
+    /// given name, type tag and scope, or the ones of the exported Register when not given,
+    /// and the entries of the dump are written to it, each of them superseding the entries
+    /// written for its parents. Thus the new Register has the same history as the exported
+    /// one, although its entries have different hashes. It fails before creating anything
+    /// if the dump is invalid, e.g. if an entry comes before any of its parents.
+    pub async fn register_import(
+        &self,
+        dump: &RegisterDump,
+        dst_name: Option<XorName>,
+        type_tag: Option<u64>,
+        private: Option<bool>,
+    ) -> Result<XorUrl> {
+        info!("Importing Register exported from {}", dump.source);
+        let nodes = decode_dump_entries(dump)?;
+
+        let dst_xorurl = self
+            .register_create(
+                dst_name,
+                type_tag.unwrap_or(dump.type_tag),
+                private.unwrap_or(dump.private),
+            )
+            .await?;
+        self.write_register_nodes(&dst_xorurl, nodes).await?;
+
+        Ok(dst_xorurl)
+    }
+}
+
+// Decode and validate the entries of a dump
+fn decode_dump_entries(dump: &RegisterDump) -> Result<RegisterNodes> {
+    if dump.format_version > REGISTER_DUMP_FORMAT_VERSION {
+        return Err(Error::InvalidInput(format!(
+            "Register dump format version {} is not supported, the latest supported is {}",
+            dump.format_version, REGISTER_DUMP_FORMAT_VERSION
+        )));
+    }
+
+    let mut seen = BTreeSet::new();
+    let mut nodes = Vec::with_capacity(dump.entries.len());
+    for dump_entry in dump.entries.iter() {
+        let hash = decode_entry_hash(&dump_entry.hash)?;
+        let entry = Url::from_url(&dump_entry.entry).map_err(|err| {
+            Error::InvalidInput(format!(
+                "Register dump has an invalid entry '{}': {}",
+                dump_entry.entry, err
+            ))
+        })?;
+        let mut parents = BTreeSet::new();
+        for parent in dump_entry.parents.iter() {
+            let parent = decode_entry_hash(parent)?;
+            if !seen.contains(&parent) {
+                return Err(Error::InvalidInput(format!(
+                    "Register dump has entry {} before its parent {}",
+                    dump_entry.hash,
+                    hex::encode(parent)
+                )));
+            }
+            let _ = parents.insert(parent);
+        }
+        let _ = seen.insert(hash);
+        nodes.push((hash, entry, parents));
+    }
+
+    Ok(nodes)
+}
+
+fn decode_entry_hash(hash: &str) -> Result<EntryHash> {
+    let mut entry_hash = EntryHash::default();
+    hex::decode_to_slice(hash, &mut entry_hash).map_err(|err| {
+        Error::InvalidInput(format!(
+            "Register dump has an invalid entry hash '{}': {}",
+            hash, err
+        ))
+    })?;
+    Ok(entry_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop_for_pattern};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_register_export_import() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        // root <- entry
+        let src_xorurl = safe.register_create(None, 25_000, true).await?;
+        let root = safe
+            .write_to_register(&src_xorurl, Url::from_url("safe://root")?, BTreeSet::new())
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_read(&src_xorurl), Ok(entries) if !entries.is_empty())?;
+        let entry = Url::from_url("safe://exported-entry")?;
+        let _ = safe
+            .write_to_register(&src_xorurl, entry.clone(), vec![root].into_iter().collect())
+            .await?;
+
+        let dump = retry_loop_for_pattern!(safe.register_export(&src_xorurl), Ok(dump) if dump.entries.len() == 2)?;
+        assert_eq!(dump.type_tag, 25_000);
+        assert!(dump.private);
+        assert_eq!(dump.entries[0].entry, "safe://root");
+        assert_eq!(dump.entries[1].parents, vec![hex::encode(root)]);
+
+        // the dump survives being kept offline
+        let serialised = serde_json::to_string(&dump)?;
+        let restored: RegisterDump = serde_json::from_str(&serialised)?;
+        assert_eq!(restored, dump);
+
+        let dst_xorurl = safe
+            .register_import(&restored, None, Some(25_001), None)
+            .await?;
+        let dst_address = Url::from_url(&dst_xorurl)?.register_address()?;
+        assert_eq!(dst_address.tag(), 25_001);
+        assert!(!dst_address.is_public());
+        let imported = retry_loop_for_pattern!(safe.register_read(&dst_xorurl), Ok(entries) if !entries.is_empty())?;
+        let imported_entries: Vec<&Url> = imported.iter().map(|(_, entry)| entry).collect();
+        assert_eq!(imported_entries, vec![&entry]);
+        let history = retry_loop_for_pattern!(safe.register_history(&dst_xorurl), Ok(history) if history.nodes().len() == 2)?;
+        assert_eq!(history.roots().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_dump_validation() -> Result<()> {
+        let entry = |hash: &str, parents: &[&str]| RegisterDumpEntry {
+            hash: hash.repeat(64),
+            entry: "safe://entry".to_string(),
+            parents: parents.iter().map(|parent| parent.repeat(64)).collect(),
+        };
+        let mut dump = RegisterDump {
+            format_version: REGISTER_DUMP_FORMAT_VERSION,
+            source: "safe://source".to_string(),
+            type_tag: 25_000,
+            private: false,
+            exported_at: 0,
+            entries: vec![entry("a", &[]), entry("b", &["a"]), entry("c", &["a"])],
+        };
+        let nodes = decode_dump_entries(&dump)?;
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(
+            nodes[1].2,
+            vec![[0xaa; 32]].into_iter().collect::<BTreeSet<_>>()
+        );
+
+        // entries must come after their parents
+        dump.entries.reverse();
+        assert!(decode_dump_entries(&dump).is_err());
+
+        dump.entries = vec![entry("z", &[])];
+        assert!(decode_dump_entries(&dump).is_err());
+
+        dump.entries = vec![];
+        dump.format_version = REGISTER_DUMP_FORMAT_VERSION + 1;
+        assert!(decode_dump_entries(&dump).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "sim")]
+    #[tokio::test]
+    async fn test_register_export_import_history_sim() -> Result<()> {
+        use crate::app::sim::{SimConfig, SimNetwork};
+
+        let sim = SimNetwork::new(SimConfig::default());
+        let mut safe = Safe::default();
+        safe.connect_sim(&sim, None);
+
+        let src_xorurl = safe.register_create(None, 25_000, false).await?;
+        let root = safe
+            .write_to_register(&src_xorurl, Url::from_url("safe://root")?, BTreeSet::new())
+            .await?;
+        let _ = safe
+            .write_to_register(
+                &src_xorurl,
+                Url::from_url("safe://child")?,
+                vec![root].into_iter().collect(),
+            )
+            .await?;
+
+        let dump = safe.register_export(&src_xorurl).await?;
+        assert_eq!(dump.entries.len(), 2);
+
+        // restored on a new network
+        let other_sim = SimNetwork::new(SimConfig::default());
+        let mut other = Safe::default();
+        other.connect_sim(&other_sim, None);
+        let dst_xorurl = other.register_import(&dump, None, None, None).await?;
+        let history = other.register_history(&dst_xorurl).await?;
+        assert_eq!(history.nodes().len(), 2);
+        assert_eq!(history.roots().len(), 1);
+        assert_eq!(history.tips().len(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{large::is_large_entry, Entry, EntryHash};
+use crate::{
+    app::encryption::{
+        decrypt_payload, derive_symmetric_key, encrypt_payload, payload_allowed, SymmetricKey,
+    },
+    DataType, Error, MissingPermission, Result, Safe, Url, UrlAddressExt,
+};
+use bytes::Bytes;
+use safe_network::types::BytesAddress;
+use std::collections::BTreeSet;
+
+// Context used to derive the key the entries of private Registers are encrypted with
+const REGISTER_ENTRIES_CONTEXT: &[u8] = b"sn_api-register-entries";
+
+// Prefix of the Blobs holding encrypted entries, which tells them apart
+// from any other private Blob an entry may link to
+const ENCRYPTED_ENTRY_PREFIX: &[u8] = b"sn_api-encrypted-entry:";
+
+impl Safe {
+    /// Set whether the entries written to private Registers are encrypted with a key
+    /// derived from the keypair this instance is connected with. Each entry is then stored
+    /// encrypted in a private Blob, which the entry written to the Register links to, and
+    /// it's decrypted when read, thus it's transparent to the readers which have it enabled.
+    /// It's disabled by default, as it requires fetching a Blob to read each entry.
+    pub fn set_encrypt_private_registers(&mut self, encrypt_private_registers: bool) {
+        self.encrypt_private_registers = encrypt_private_registers;
+    }
+
+    /// Whether the entries written to private Registers are encrypted
+    pub fn is_encrypting_private_registers(&self) -> bool {
+        self.encrypt_private_registers
+    }
+
+    // Encrypt an entry to be written to the Register, if it's private and
+    // encryption is enabled, returning the entry to be written in its place.
+    // Entries too large to be written as is are stored in a Blob beforehand.
+    pub(crate) async fn seal_register_entry(&self, url: &Url, entry: Entry) -> Result<Entry> {
+        let entry = self.store_large_register_entry(url, entry).await?;
+        if !self.encrypts_entries_of(url)? {
+            return Ok(entry);
+        }
+
+        let mut sealed_entry = ENCRYPTED_ENTRY_PREFIX.to_vec();
+        sealed_entry.extend(encrypt_payload(
+            &self.encryption_policy,
+            &self.register_entries_key()?,
+            entry.to_string().as_bytes(),
+        )?);
+        let xorurl = self
+            .store_private_bytes(Bytes::from(sealed_entry), None)
+            .await?;
+
+        Ok(Url::from_xorurl(&xorurl)?)
+    }
+
+    // Decrypt the entries read from a Register which were written encrypted
+    pub(crate) async fn open_register_entries(
+        &self,
+        url: &Url,
+        entries: BTreeSet<(EntryHash, Entry)>,
+    ) -> Result<BTreeSet<(EntryHash, Entry)>> {
+        if !self.encrypts_entries_of(url)? && !entries.iter().any(|(_, e)| is_large_entry(e)) {
+            return Ok(entries);
+        }
+
+        let mut opened_entries = BTreeSet::new();
+        for (hash, entry) in entries {
+            let _ = opened_entries.insert((hash, self.open_register_entry(url, entry).await?));
+        }
+        Ok(opened_entries)
+    }
+
+    // Decrypt an entry read from a Register if it was written encrypted, and
+    // fetch the entry it stands for if it was too large to be written as is
+    pub(crate) async fn open_register_entry(&self, url: &Url, entry: Entry) -> Result<Entry> {
+        let entry = self.decrypt_register_entry(url, entry).await?;
+        self.resolve_large_register_entry(entry).await
+    }
+
+    async fn decrypt_register_entry(&self, url: &Url, entry: Entry) -> Result<Entry> {
+        if !self.encrypts_entries_of(url)?
+            || entry.data_type() != DataType::Bytes
+            || !matches!(entry.bytes_address()?, BytesAddress::Private(_))
+        {
+            return Ok(entry);
+        }
+
+        let content = self.fetch_public_data(&entry, None).await?;
+        let sealed_entry = match content.strip_prefix(ENCRYPTED_ENTRY_PREFIX) {
+            Some(sealed_entry) => sealed_entry,
+            None => return Ok(entry),
+        };
+        // Entries are encrypted with a key derived from the keypair of the writer, thus
+        // failing to decrypt an entry with an allowed algorithm means the keypair differs
+        let opened_entry = decrypt_payload(
+            &self.encryption_policy,
+            &self.register_entries_key()?,
+            sealed_entry,
+        )
+        .map_err(|err| match err {
+            Error::AccessDenied(_) if payload_allowed(&self.encryption_policy, sealed_entry) => {
+                Error::PermissionDenied {
+                    missing: MissingPermission::Keypair,
+                    address: url.to_string(),
+                }
+            }
+            err => err,
+        })?;
+        let opened_entry = String::from_utf8(opened_entry).map_err(|err| {
+            Error::ContentError(format!("Encrypted Register entry is invalid: {}", err))
+        })?;
+
+        Ok(Url::from_url(&opened_entry)?)
+    }
+
+    fn encrypts_entries_of(&self, url: &Url) -> Result<bool> {
+        Ok(self.encrypt_private_registers && !url.register_address()?.is_public())
+    }
+
+    fn register_entries_key(&self) -> Result<SymmetricKey> {
+        derive_symmetric_key(&self.get_my_keypair()?, REGISTER_ENTRIES_CONTEXT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern};
+    use anyhow::{anyhow, Result};
+
+    #[tokio::test]
+    async fn test_register_encrypted_entries() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        safe.set_encrypt_private_registers(true);
+        let xorurl = safe.register_create(None, 25_000, true).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let entry = Url::from_url("safe://a-private-entry")?;
+        let hash = safe
+            .write_to_register(&xorurl, entry.clone(), BTreeSet::new())
+            .await?;
+
+        // the entry written to the Register links to the encrypted entry
+        let safe_url = Url::from_url(&xorurl)?;
+        let sealed = retry_loop_for_pattern!(
+            safe.safe_client.read_register(safe_url.register_address()?),
+            Ok(entries) if !entries.is_empty()
+        )?;
+        let (_, sealed_entry) = sealed
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No entries found"))?;
+        assert_ne!(sealed_entry, entry);
+        let content = safe.fetch_public_data(&sealed_entry, None).await?;
+        assert!(content.starts_with(ENCRYPTED_ENTRY_PREFIX));
+        assert!(!String::from_utf8_lossy(&content).contains("a-private-entry"));
+
+        // and it's decrypted transparently when read
+        let entries = safe.register_read(&xorurl).await?;
+        assert_eq!(entries, vec![(hash, entry.clone())].into_iter().collect());
+        assert_eq!(safe.register_read_entry(&xorurl, hash).await?, entry);
+
+        // readers without encryption enabled get the entry as it was written
+        safe.set_encrypt_private_registers(false);
+        let (_, raw_entry) = safe
+            .register_read(&xorurl)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No entries found"))?;
+        assert_eq!(raw_entry, sealed_entry);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,611 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{Entry, EntryHash};
+use crate::{Error, Result, Safe, UrlAddressExt};
+use safe_network::types::register::Register;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// An entry of a Register together with the hashes of the entries it supersedes
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegisterNode {
+    pub entry: Entry,
+    pub parents: BTreeSet<EntryHash>,
+}
+
+/// The DAG of all the entries ever written to a Register, see `Safe::register_history`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegisterHistory {
+    nodes: BTreeMap<EntryHash, RegisterNode>,
+}
+
+impl RegisterHistory {
+    /// All the entries, keyed by their hash
+    pub fn nodes(&self) -> &BTreeMap<EntryHash, RegisterNode> {
+        &self.nodes
+    }
+
+    /// Get an entry with its parents
+    pub fn get(&self, hash: &EntryHash) -> Option<&RegisterNode> {
+        self.nodes.get(hash)
+    }
+
+    /// The entries written without parents
+    pub fn roots(&self) -> BTreeSet<EntryHash> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.parents.is_empty())
+            .map(|(hash, _)| *hash)
+            .collect()
+    }
+
+    /// The current entries, i.e. those which are not parents of any other entry,
+    /// which are the entries `register_read` returns
+    pub fn tips(&self) -> BTreeSet<EntryHash> {
+        let parents: BTreeSet<&EntryHash> = self
+            .nodes
+            .values()
+            .flat_map(|node| node.parents.iter())
+            .collect();
+        self.nodes
+            .keys()
+            .filter(|hash| !parents.contains(hash))
+            .copied()
+            .collect()
+    }
+
+    /// The entries which have the given entry as parent
+    pub fn children(&self, hash: &EntryHash) -> BTreeSet<EntryHash> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.parents.contains(hash))
+            .map(|(child, _)| *child)
+            .collect()
+    }
+
+    /// All the entries the given entry descends from, i.e. its parents, their parents, etc.
+    pub fn ancestors(&self, hash: &EntryHash) -> BTreeSet<EntryHash> {
+        let mut ancestors = BTreeSet::new();
+        let mut pending: Vec<EntryHash> = self
+            .get(hash)
+            .map(|node| node.parents.iter().copied().collect())
+            .unwrap_or_default();
+        while let Some(parent) = pending.pop() {
+            if ancestors.insert(parent) {
+                if let Some(node) = self.get(&parent) {
+                    pending.extend(node.parents.iter().copied());
+                }
+            }
+        }
+        ancestors
+    }
+
+    /// The entries where the history forked, i.e. which are parents of more than one entry,
+    /// as happens when different writers write concurrently
+    pub fn forks(&self) -> BTreeSet<EntryHash> {
+        let mut children_count = BTreeMap::<EntryHash, usize>::new();
+        self.nodes
+            .values()
+            .flat_map(|node| node.parents.iter())
+            .for_each(|parent| *children_count.entry(*parent).or_default() += 1);
+        children_count
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(hash, _)| hash)
+            .collect()
+    }
+
+    /// All the entries in an order where every entry comes after its parents,
+    /// with entries not related to each other sorted by their hash
+    pub fn topological_order(&self) -> Vec<EntryHash> {
+        let mut pending_parents: BTreeMap<EntryHash, usize> = self
+            .nodes
+            .iter()
+            .map(|(hash, node)| {
+                let known_parents = node
+                    .parents
+                    .iter()
+                    .filter(|parent| self.nodes.contains_key(*parent))
+                    .count();
+                (*hash, known_parents)
+            })
+            .collect();
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut ready: BTreeSet<EntryHash> = pending_parents
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(hash, _)| *hash)
+            .collect();
+        while let Some(hash) = ready.iter().next().copied() {
+            let _ = ready.remove(&hash);
+            order.push(hash);
+            for child in self.children(&hash) {
+                if let Some(count) = pending_parents.get_mut(&child) {
+                    *count -= 1;
+                    if *count == 0 {
+                        let _ = ready.insert(child);
+                    }
+                }
+            }
+        }
+        order
+    }
+
+    /// # The part of the history from the current entries back to the given boundaries
+    ///
+    /// Walks from the current entries towards the roots, keeping the entries visited, but
+    /// not walking past the entries for which `stop` returns true, which are kept too.
+    /// The entries those boundaries descend from are left out even when they are reached
+    /// through other paths, e.g. from an entry written concurrently with a boundary.
+    /// As with `generations`, the entries at the edge keep the hashes of their parents.
+    pub fn until(&self, stop: impl Fn(&EntryHash, &RegisterNode) -> bool) -> RegisterHistory {
+        let mut nodes = BTreeMap::new();
+        let mut boundaries = vec![];
+        let mut pending: Vec<EntryHash> = self.tips().into_iter().collect();
+        while let Some(hash) = pending.pop() {
+            if nodes.contains_key(&hash) {
+                continue;
+            }
+            if let Some(node) = self.get(&hash) {
+                if stop(&hash, node) {
+                    boundaries.push(hash);
+                } else {
+                    pending.extend(node.parents.iter().copied());
+                }
+                let _ = nodes.insert(hash, node.clone());
+            }
+        }
+
+        for boundary in boundaries {
+            for ancestor in self.ancestors(&boundary) {
+                let _ = nodes.remove(&ancestor);
+            }
+        }
+        RegisterHistory { nodes }
+    }
+
+    /// # The entries on the paths from one entry to another
+    ///
+    /// The entries which descend from `from` and which `to` descends from, plus `to` itself,
+    /// i.e. all the entries written since `from` which lead to `to`, following any of the
+    /// paths connecting them when the history forked and merged in between. `from` is not
+    /// included, although the entries right after it keep it among their parents. It's empty
+    /// if `to` doesn't descend from `from`, or if they are the same entry.
+    pub fn between(&self, from: &EntryHash, to: &EntryHash) -> RegisterHistory {
+        let mut candidates = self.ancestors(to);
+        if !candidates.contains(from) {
+            return RegisterHistory::default();
+        }
+        let _ = candidates.insert(*to);
+
+        // in topological order, an entry is on a path from `from` if any of its parents is
+        let mut reached = BTreeSet::new();
+        let mut nodes = BTreeMap::new();
+        for hash in self.topological_order() {
+            if !candidates.contains(&hash) {
+                continue;
+            }
+            if let Some(node) = self.get(&hash) {
+                if hash == *from {
+                    let _ = reached.insert(hash);
+                } else if node.parents.iter().any(|parent| reached.contains(parent)) {
+                    let _ = reached.insert(hash);
+                    let _ = nodes.insert(hash, node.clone());
+                }
+            }
+        }
+        RegisterHistory { nodes }
+    }
+
+    /// # The last generations of the history
+    ///
+    /// The current entries are the first generation, their parents the second one, and so
+    /// on, thus a `depth` of one keeps only the current entries. The entries at the edge of
+    /// the returned history keep the hashes of their parents, even if these are not part of it.
+    pub fn generations(&self, depth: usize) -> RegisterHistory {
+        RegisterHistory {
+            nodes: last_generations(&self.nodes, |node| &node.parents, depth),
+        }
+    }
+}
+
+impl Safe {
+    /// # Read the history of a Register
+    ///
+    /// Returns all the entries ever written to the Register with the hashes of their
+    /// parents, as opposed to `register_read` which only returns its current entries,
+    /// so the ancestry of the entries can be walked and forks detected.
+    pub async fn register_history(&self, url: &str) -> Result<RegisterHistory> {
+        self.read_register_history(url, None).await
+    }
+
+    /// # Read the last generations of the history of a Register
+    ///
+    /// As `register_history`, but only the current entries of the Register and their
+    /// ancestors up to `depth` generations are returned, see `RegisterHistory::generations`,
+    /// which keeps processing long-lived Registers, e.g. used as logs, cheap. Note the
+    /// Register is still fetched as a whole.
+    pub async fn register_history_depth(&self, url: &str, depth: usize) -> Result<RegisterHistory> {
+        self.read_register_history(url, Some(depth)).await
+    }
+
+    /// # Read the entries written to a Register between two of its entries
+    ///
+    /// Returns the entries on the paths connecting the entry with hash `from` to the entry
+    /// with hash `to`, see `RegisterHistory::between`, e.g. to find what changed since a
+    /// known entry when syncing with the Register, walking them in `topological_order`.
+    /// It fails if either entry is not found, or if `to` doesn't descend from `from`.
+    pub async fn register_entries_between(
+        &self,
+        url: &str,
+        from: EntryHash,
+        to: EntryHash,
+    ) -> Result<RegisterHistory> {
+        let history = self.read_register_history(url, None).await?;
+        for hash in &[from, to] {
+            if history.get(hash).is_none() {
+                return Err(Error::HashNotFound(*hash));
+            }
+        }
+
+        let between = history.between(&from, &to);
+        if between.nodes().is_empty() && from != to {
+            return Err(Error::InvalidInput(format!(
+                "Entry {} doesn't descend from entry {}",
+                hex::encode(to),
+                hex::encode(from)
+            )));
+        }
+        Ok(between)
+    }
+
+    // Private helper to read the whole history of a Register, or its last generations
+    async fn read_register_history(
+        &self,
+        url: &str,
+        depth: Option<usize>,
+    ) -> Result<RegisterHistory> {
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let address = safe_url.register_address()?;
+        let dag = self
+            .safe_client
+            .read_register_history(address, depth)
+            .await?;
+
+        Ok(history_from_dag(dag))
+    }
+}
+
+// Build the history of a Register out of its entries keyed by hash, with their parents
+pub(super) fn history_from_dag(
+    dag: BTreeMap<EntryHash, (Entry, BTreeSet<EntryHash>)>,
+) -> RegisterHistory {
+    let nodes = dag
+        .into_iter()
+        .map(|(hash, (entry, parents))| (hash, RegisterNode { entry, parents }))
+        .collect();
+
+    RegisterHistory { nodes }
+}
+
+// The parts of a Register needed to walk its history, as it's serialised by the network
+// client. The Register doesn't expose the parents of its entries, but they are held by
+// the Merkle DAG of its CRDT, where the parents of a node are named its children.
+#[derive(Deserialize)]
+struct RegisterParts {
+    crdt: RegisterCrdtParts,
+}
+
+#[derive(Deserialize)]
+struct RegisterCrdtParts {
+    data: MerkleRegParts,
+}
+
+#[derive(Deserialize)]
+struct MerkleRegParts {
+    dag: BTreeMap<EntryHash, MerkleNodeParts>,
+}
+
+#[derive(Deserialize)]
+struct MerkleNodeParts {
+    children: BTreeSet<EntryHash>,
+    value: Entry,
+}
+
+// All the entries of a Register with the hashes of their parents. Orphan entries, i.e.
+// those whose parents haven't been received by the replica yet, are not included.
+pub(crate) fn register_dag(
+    register: &Register,
+) -> Result<BTreeMap<EntryHash, (Entry, BTreeSet<EntryHash>)>> {
+    let serialised = rmp_serde::to_vec_named(register)
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise Register: {:?}", err)))?;
+    let parts: RegisterParts = rmp_serde::from_slice(&serialised).map_err(|err| {
+        Error::ContentError(format!(
+            "Couldn't read the entries of the Register: {:?}",
+            err
+        ))
+    })?;
+
+    Ok(parts
+        .crdt
+        .data
+        .dag
+        .into_iter()
+        .map(|(hash, node)| (hash, (node.value, node.children)))
+        .collect())
+}
+
+// The nodes of a DAG of entries within the given number of generations from its tips,
+// walking from the tips towards the roots so older generations aren't visited
+pub(crate) fn last_generations<V: Clone>(
+    nodes: &BTreeMap<EntryHash, V>,
+    parents: impl Fn(&V) -> &BTreeSet<EntryHash>,
+    depth: usize,
+) -> BTreeMap<EntryHash, V> {
+    let all_parents: BTreeSet<&EntryHash> = nodes.values().flat_map(&parents).collect();
+    let mut generation: BTreeSet<EntryHash> = nodes
+        .keys()
+        .filter(|hash| !all_parents.contains(hash))
+        .copied()
+        .collect();
+
+    let mut selected = BTreeMap::new();
+    for _ in 0..depth {
+        let mut next_generation = BTreeSet::new();
+        for hash in generation {
+            if selected.contains_key(&hash) {
+                continue;
+            }
+            if let Some(node) = nodes.get(&hash) {
+                next_generation.extend(parents(node).iter().copied());
+                let _ = selected.insert(hash, node.clone());
+            }
+        }
+        if next_generation.is_empty() {
+            break;
+        }
+        generation = next_generation;
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop_for_pattern, Url};
+    use anyhow::Result;
+
+    fn node(entry: &str, parents: &[EntryHash]) -> Result<RegisterNode> {
+        Ok(RegisterNode {
+            entry: Url::from_url(entry)?,
+            parents: parents.iter().copied().collect(),
+        })
+    }
+
+    #[test]
+    fn test_register_dag_from_network_register() -> Result<()> {
+        use safe_network::types::Keypair;
+        use xor_name::XorName;
+
+        let owner = Keypair::new_ed25519(&mut rand::thread_rng()).public_key();
+        let mut register = Register::new_public(owner, XorName::random(), 25_000, None);
+        let (root, _) = register.write(Url::from_url("safe://root")?, BTreeSet::new())?;
+        let (a, _) =
+            register.write(Url::from_url("safe://a")?, vec![root].into_iter().collect())?;
+        let (b, _) =
+            register.write(Url::from_url("safe://b")?, vec![root].into_iter().collect())?;
+        let (c, _) =
+            register.write(Url::from_url("safe://c")?, vec![a, b].into_iter().collect())?;
+
+        let dag = register_dag(&register)?;
+        assert_eq!(dag.len(), 4);
+        assert_eq!(dag[&root], (Url::from_url("safe://root")?, BTreeSet::new()));
+        assert_eq!(dag[&a].1, vec![root].into_iter().collect());
+        assert_eq!(
+            dag[&c],
+            (Url::from_url("safe://c")?, vec![a, b].into_iter().collect())
+        );
+
+        let last = last_generations(&dag, |(_, parents)| parents, 2);
+        assert_eq!(
+            last.keys().copied().collect::<BTreeSet<_>>(),
+            vec![a, b, c].into_iter().collect()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_history_dag() -> Result<()> {
+        // root <- a <- c
+        //      <- b <-/  <- d
+        let (root, a, b, c, d) = ([4; 32], [3; 32], [2; 32], [1; 32], [0; 32]);
+        let nodes = vec![
+            (root, node("safe://root", &[])?),
+            (a, node("safe://a", &[root])?),
+            (b, node("safe://b", &[root])?),
+            (c, node("safe://c", &[a, b])?),
+            (d, node("safe://d", &[b])?),
+        ];
+        let history = RegisterHistory {
+            nodes: nodes.into_iter().collect(),
+        };
+
+        assert_eq!(history.roots(), vec![root].into_iter().collect());
+        assert_eq!(history.tips(), vec![c, d].into_iter().collect());
+        assert_eq!(history.children(&b), vec![c, d].into_iter().collect());
+        assert_eq!(
+            history.ancestors(&c),
+            vec![root, a, b].into_iter().collect()
+        );
+        assert_eq!(history.ancestors(&root), BTreeSet::new());
+        assert_eq!(history.forks(), vec![root, b].into_iter().collect());
+        assert_eq!(history.topological_order(), vec![root, b, d, a, c]);
+
+        assert_eq!(history.generations(0), RegisterHistory::default());
+        let last = history.generations(1);
+        assert_eq!(last.nodes().keys().copied().collect::<Vec<_>>(), vec![d, c]);
+        assert_eq!(last.tips(), vec![c, d].into_iter().collect());
+        let last_two = history.generations(2);
+        assert_eq!(last_two.nodes().len(), 4);
+        assert!(last_two.get(&root).is_none());
+        assert_eq!(last_two.topological_order(), vec![b, d, a, c]);
+        assert_eq!(history.generations(10), history);
+
+        let since_b = history.until(|hash, _| *hash == b);
+        assert_eq!(
+            since_b.nodes().keys().copied().collect::<Vec<_>>(),
+            vec![d, c, b, a]
+        );
+        assert_eq!(since_b.until(|_, _| false), since_b);
+        assert_eq!(history.until(|_, _| true), last);
+
+        let root_to_c = history.between(&root, &c);
+        assert_eq!(
+            root_to_c.nodes().keys().copied().collect::<Vec<_>>(),
+            vec![c, b, a]
+        );
+        assert_eq!(root_to_c.topological_order(), vec![b, a, c]);
+        assert_eq!(
+            history
+                .between(&b, &c)
+                .nodes()
+                .keys()
+                .copied()
+                .collect::<Vec<_>>(),
+            vec![c]
+        );
+        assert_eq!(history.between(&a, &d), RegisterHistory::default());
+        assert_eq!(history.between(&c, &root), RegisterHistory::default());
+        assert_eq!(history.between(&c, &c), RegisterHistory::default());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_history_depth() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let first = safe
+            .write_to_register(&xorurl, Url::from_url("safe://first")?, BTreeSet::new())
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(v) if v.len() == 1)?;
+        let second = safe
+            .write_to_register(
+                &xorurl,
+                Url::from_url("safe://second")?,
+                vec![first].into_iter().collect(),
+            )
+            .await?;
+
+        let history =
+            retry_loop_for_pattern!(safe.register_history(&xorurl), Ok(h) if h.nodes().len() == 2)?;
+        assert_eq!(history.tips(), vec![second].into_iter().collect());
+        assert_eq!(history.topological_order(), vec![first, second]);
+
+        let last = safe.register_history_depth(&xorurl, 1).await?;
+        assert_eq!(
+            last.nodes().keys().copied().collect::<Vec<_>>(),
+            vec![second]
+        );
+        assert_eq!(safe.register_history_depth(&xorurl, 2).await?, history);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_entries_between() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let first = safe
+            .write_to_register(&xorurl, Url::from_url("safe://first")?, BTreeSet::new())
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(v) if v.len() == 1)?;
+        let second = safe
+            .write_to_register(
+                &xorurl,
+                Url::from_url("safe://second")?,
+                vec![first].into_iter().collect(),
+            )
+            .await?;
+
+        let between = retry_loop_for_pattern!(safe.register_entries_between(&xorurl, first, second), Ok(b) if b.nodes().len() == 1)?;
+        assert_eq!(
+            between.nodes().keys().copied().collect::<Vec<_>>(),
+            vec![second]
+        );
+        assert!(matches!(
+            safe.register_entries_between(&xorurl, second, first).await,
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            safe.register_entries_between(&xorurl, first, [0; 32]).await,
+            Err(Error::HashNotFound(_))
+        ));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "sim")]
+    #[tokio::test]
+    async fn test_register_history_sim() -> Result<()> {
+        use crate::app::sim::{SimConfig, SimNetwork};
+
+        let sim = SimNetwork::new(SimConfig::default());
+        let mut safe = Safe::default();
+        safe.connect_sim(&sim, None);
+
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let first = safe
+            .write_to_register(&xorurl, Url::from_url("safe://first")?, BTreeSet::new())
+            .await?;
+        let second = safe
+            .write_to_register(
+                &xorurl,
+                Url::from_url("safe://second")?,
+                vec![first].into_iter().collect(),
+            )
+            .await?;
+
+        let history = safe.register_history(&xorurl).await?;
+        assert_eq!(history.nodes().len(), 2);
+        assert_eq!(history.tips(), vec![second].into_iter().collect());
+        assert_eq!(
+            history.ancestors(&second),
+            vec![first].into_iter().collect()
+        );
+        assert_eq!(history.topological_order(), vec![first, second]);
+
+        let between = safe
+            .register_entries_between(&xorurl, first, second)
+            .await?;
+        assert_eq!(
+            between.nodes().keys().copied().collect::<Vec<_>>(),
+            vec![second]
+        );
+        assert!(matches!(
+            safe.register_entries_between(&xorurl, second, first).await,
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            safe.register_entries_between(&xorurl, first, [0; 32]).await,
+            Err(Error::HashNotFound(_))
+        ));
+
+        let last = safe.register_history_depth(&xorurl, 1).await?;
+        assert_eq!(
+            last.nodes().keys().copied().collect::<Vec<_>>(),
+            vec![second]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::Entry;
+use crate::{ContentType, DataType, Error, Result, Safe, Url, UrlAddressExt};
+use bytes::Bytes;
+use log::debug;
+use safe_network::types::BytesAddress;
+
+// Default maximum length of an entry, as a URL, written to a Register as is. The network
+// doesn't limit the size of Register entries, but every read fetches the whole Register,
+// with all the entries ever written to it, thus this leaves room for a XOR-URL with a
+// path and version while keeping arbitrary content out of Registers.
+pub(crate) const DEFAULT_MAX_REGISTER_ENTRY_LEN: usize = 1_024;
+
+// Path of the links to the Blobs holding large entries, which tells them apart
+// from any other entry linking to a Blob without reading the Blob
+const LARGE_ENTRY_PATH: &str = "/sn-api-large-entry";
+
+// Prefix of the Blobs holding large entries
+const LARGE_ENTRY_PREFIX: &[u8] = b"sn_api-large-entry:";
+
+impl Safe {
+    /// Set whether the entries too large to be written to a Register as is are stored in
+    /// a Blob, with the same scope as the Register, and the entry written to the Register
+    /// links to such Blob instead. The entries are resolved when read, e.g. by
+    /// `register_read_entry`, regardless of this setting. It's disabled by default.
+    pub fn set_large_register_entries(&mut self, large_register_entries: bool) {
+        self.large_register_entries = large_register_entries;
+    }
+
+    /// Whether the entries too large to be written to a Register are stored in a Blob
+    pub fn is_storing_large_register_entries(&self) -> bool {
+        self.large_register_entries
+    }
+
+    /// Set the maximum length of an entry, as a URL, written to a Register as is when
+    /// large entries are stored in Blobs, see `set_large_register_entries`. The network
+    /// doesn't enforce any limit, but each read of a Register fetches all the entries ever
+    /// written to it, thus this bounds how much a single entry adds to every read.
+    /// It's 1024 bytes by default.
+    pub fn set_large_register_entry_max_len(&mut self, max_len: usize) {
+        self.large_register_entry_max_len = max_len;
+    }
+
+    /// Maximum length of an entry written to a Register as is when large entries are stored
+    /// in Blobs
+    pub fn large_register_entry_max_len(&self) -> usize {
+        self.large_register_entry_max_len
+    }
+
+    // Store an entry in a Blob if it's too large to be written to the Register as is,
+    // returning the entry linking to the Blob to be written in its place
+    pub(crate) async fn store_large_register_entry(
+        &self,
+        url: &Url,
+        entry: Entry,
+    ) -> Result<Entry> {
+        let serialised_entry = entry.to_string();
+        if !self.large_register_entries
+            || serialised_entry.len() <= self.large_register_entry_max_len
+        {
+            return Ok(entry);
+        }
+
+        debug!(
+            "Storing entry of {} bytes in a Blob for Register at {}",
+            serialised_entry.len(),
+            url
+        );
+        let mut content = LARGE_ENTRY_PREFIX.to_vec();
+        content.extend(serialised_entry.as_bytes());
+        let address = if url.register_address()?.is_public() {
+            BytesAddress::Public(
+                self.safe_client
+                    .store_bytes(Bytes::from(content), false)
+                    .await?,
+            )
+        } else {
+            BytesAddress::Private(
+                self.safe_client
+                    .store_private_bytes(Bytes::from(content))
+                    .await?,
+            )
+        };
+
+        let mut link = Url::from_xorurl(&Url::encode_bytes(
+            address,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?)?;
+        link.set_path(LARGE_ENTRY_PATH);
+        Ok(link)
+    }
+
+    // Fetch the entry a Register entry stands for if it was stored in a Blob
+    pub(crate) async fn resolve_large_register_entry(&self, entry: Entry) -> Result<Entry> {
+        if !is_large_entry(&entry) {
+            return Ok(entry);
+        }
+
+        let mut blob_url = entry.clone();
+        blob_url.set_path("");
+        let content = self.fetch_public_data(&blob_url, None).await?;
+        let serialised_entry = content.strip_prefix(LARGE_ENTRY_PREFIX).ok_or_else(|| {
+            Error::ContentError(format!(
+                "The Blob linked from the Register entry {} doesn't hold an entry",
+                entry
+            ))
+        })?;
+        let serialised_entry = String::from_utf8(serialised_entry.to_vec()).map_err(|err| {
+            Error::ContentError(format!("Large Register entry is invalid: {}", err))
+        })?;
+
+        Ok(Url::from_url(&serialised_entry)?)
+    }
+}
+
+// Whether the Register entry links to a Blob holding the actual entry
+pub(crate) fn is_large_entry(entry: &Entry) -> bool {
+    entry.data_type() == DataType::Bytes && entry.path() == LARGE_ENTRY_PATH
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern};
+    use anyhow::Result;
+    use std::collections::BTreeSet;
+
+    #[tokio::test]
+    async fn test_register_large_entries() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        safe.set_large_register_entries(true);
+        safe.set_large_register_entry_max_len(64);
+        let xorurl = safe.register_create(None, 25_000, true).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let large_entry = Url::from_url(&format!("safe://large/{}", "a".repeat(64)))?;
+        let hash = safe
+            .write_to_register(&xorurl, large_entry.clone(), BTreeSet::new())
+            .await?;
+
+        let entries = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(v) if !v.is_empty())?;
+        assert_eq!(
+            entries.into_iter().next(),
+            Some((hash, large_entry.clone()))
+        );
+        assert_eq!(safe.register_read_entry(&xorurl, hash).await?, large_entry);
+
+        // the entry actually written links to the Blob holding the large entry
+        let address = Url::from_url(&xorurl)?.register_address()?;
+        let written = safe.safe_client.get_register_entry(address, hash).await?;
+        assert!(is_large_entry(&written));
+
+        // small entries are written as is
+        let small_entry = Url::from_url("safe://small")?;
+        let small_hash = safe
+            .write_to_register(&xorurl, small_entry.clone(), BTreeSet::new())
+            .await?;
+        let written = retry_loop!(safe.safe_client.get_register_entry(address, small_hash));
+        assert_eq!(written, small_entry);
+
+        Ok(())
+    }
+}
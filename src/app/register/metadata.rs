@@ -0,0 +1,231 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{
+    app::encryption::keyed_hash, ContentType, Error, PublicKey, Result, Safe, Scope, Url,
+    UrlAddressExt, XorName, XorUrl,
+};
+use bytes::Bytes;
+use log::debug;
+use std::collections::BTreeSet;
+
+// Type tag of the Registers linking to the metadata of other Registers
+const REGISTER_METADATA_TYPE_TAG: u64 = 2_800;
+
+// Context used to derive the location of the metadata of a Register from its address and owner
+const REGISTER_METADATA_CONTEXT: &[u8] = b"sn_api-register-metadata";
+
+impl Safe {
+    /// # Create a Register on the network with a content type and metadata
+    ///
+    /// The content type is encoded in the XOR-URL returned, e.g. a `ContentType::MediaType`
+    /// telling what the entries of the Register link to, and the metadata, if any, is stored
+    /// in a Blob with the same scope as the Register, linked from a Register at a location
+    /// derived from the Register's address and owner. Thus `fetch` and `inspect` can describe
+    /// what the Register holds without reading its entries, and its metadata can be
+    /// retrieved with `register_metadata`.
+    pub async fn register_create_with_metadata(
+        &self,
+        name: Option<XorName>,
+        type_tag: u64,
+        private: bool,
+        content_type: ContentType,
+        metadata: Option<Bytes>,
+    ) -> Result<XorUrl> {
+        let xorname = self
+            .safe_client
+            .store_register(name, type_tag, None, private)
+            .await?;
+        let scope = if private {
+            Scope::Private
+        } else {
+            Scope::Public
+        };
+
+        if let Some(metadata) = metadata {
+            let metadata_xorurl = match scope {
+                Scope::Public => self.store_public_bytes(metadata, None, false).await?,
+                Scope::Private => self.store_private_bytes(metadata, None).await?,
+            };
+            let owner = self.get_my_keypair()?.public_key();
+            let (metadata_xorname, metadata_url) =
+                self.register_metadata_location(xorname, type_tag, scope, &owner)?;
+            let _ = self
+                .safe_client
+                .store_register(
+                    Some(metadata_xorname),
+                    REGISTER_METADATA_TYPE_TAG,
+                    None,
+                    private,
+                )
+                .await?;
+            let _ = self
+                .write_to_register(
+                    &metadata_url.to_string(),
+                    Url::from_xorurl(&metadata_xorurl)?,
+                    BTreeSet::new(),
+                )
+                .await?;
+        }
+
+        Ok(Url::encode_register(
+            xorname,
+            type_tag,
+            scope,
+            content_type,
+            self.xorurl_base,
+        )?)
+    }
+
+    /// # Fetch the metadata a Register was created with
+    ///
+    /// Returns `None` if the Register was created without metadata. Metadata is only
+    /// returned if it was stored by the owner of the Register.
+    pub async fn register_metadata(&self, url: &str) -> Result<Option<Bytes>> {
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let (xorname, type_tag, scope) = safe_url.register_parts()?;
+        let (owner, _) = self
+            .safe_client
+            .get_register_policy(safe_url.register_address()?)
+            .await?;
+        let (_, metadata_url) =
+            self.register_metadata_location(xorname, type_tag, scope, &owner)?;
+        let entries = match self.fetch_register_entries(&metadata_url).await {
+            Ok(entries) => entries,
+            // Registers created without metadata have no Register linking to it
+            Err(Error::EmptyContent(_)) | Err(Error::ContentNotFound(_)) => {
+                debug!("No metadata found for Register at {}", safe_url);
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        };
+
+        // Anyone could create a Register at the location of the metadata of someone
+        // else's Register, thus it's only trusted if it has the same owner
+        let (metadata_owner, _) = self
+            .safe_client
+            .get_register_policy(metadata_url.register_address()?)
+            .await?;
+        if metadata_owner != owner {
+            return Err(Error::ContentError(format!(
+                "Metadata found for Register at {} is not owned by the Register's owner",
+                safe_url
+            )));
+        }
+
+        match entries.into_iter().next() {
+            Some((_, metadata_xorurl)) => {
+                Ok(Some(self.fetch_public_data(&metadata_xorurl, None).await?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Private helper to obtain the location of the Register linking to a Register's metadata
+    fn register_metadata_location(
+        &self,
+        xorname: XorName,
+        type_tag: u64,
+        scope: Scope,
+        owner: &PublicKey,
+    ) -> Result<(XorName, Url)> {
+        let mut address = xorname.0.to_vec();
+        address.extend_from_slice(&type_tag.to_be_bytes());
+        address.push(match scope {
+            Scope::Public => 0,
+            Scope::Private => 1,
+        });
+        address.extend(rmp_serde::to_vec(owner).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise public key: {:?}", err))
+        })?);
+        let metadata_xorname = XorName(keyed_hash(REGISTER_METADATA_CONTEXT, &address));
+        let metadata_xorurl = Url::encode_register(
+            metadata_xorname,
+            REGISTER_METADATA_TYPE_TAG,
+            scope,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?;
+
+        Ok((metadata_xorname, Url::from_xorurl(&metadata_xorurl)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop_for_pattern};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_register_create_with_metadata() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let metadata = Bytes::from("{\"title\":\"photos\"}");
+        let xorurl = safe
+            .register_create_with_metadata(
+                None,
+                25_000,
+                false,
+                ContentType::MediaType("image/jpeg".to_string()),
+                Some(metadata.clone()),
+            )
+            .await?;
+
+        let safe_url = Url::from_url(&xorurl)?;
+        assert_eq!(
+            safe_url.content_type(),
+            ContentType::MediaType("image/jpeg".to_string())
+        );
+        let fetched = retry_loop_for_pattern!(safe.register_metadata(&xorurl), Ok(Some(_)))?;
+        assert_eq!(fetched, Some(metadata));
+
+        let plain_xorurl = safe.register_create(None, 25_000, false).await?;
+        assert_eq!(safe.register_metadata(&plain_xorurl).await?, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_metadata_not_owned_by_register_owner() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let (xorname, type_tag, scope) = Url::from_url(&xorurl)?.register_parts()?;
+
+        // someone else links to some metadata from the location of the Register's metadata
+        let other = new_safe_instance().await?;
+        let owner = safe.get_my_keypair()?.public_key();
+        let (metadata_xorname, metadata_url) =
+            other.register_metadata_location(xorname, type_tag, scope, &owner)?;
+        let _ = other
+            .safe_client
+            .store_register(
+                Some(metadata_xorname),
+                REGISTER_METADATA_TYPE_TAG,
+                None,
+                false,
+            )
+            .await?;
+        let spoofed_xorurl = other
+            .store_public_bytes(Bytes::from("spoofed"), None, false)
+            .await?;
+        let _ = other
+            .write_to_register(
+                &metadata_url.to_string(),
+                Url::from_xorurl(&spoofed_xorurl)?,
+                BTreeSet::new(),
+            )
+            .await?;
+
+        let result =
+            retry_loop_for_pattern!(safe.register_metadata(&xorurl), Err(Error::ContentError(_)));
+        assert!(result.is_err());
+
+        Ok(())
+    }
+}
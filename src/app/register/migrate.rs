@@ -0,0 +1,226 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::Entry;
+use crate::{
+    app::whois::RegisterWriters, DataType, Error, PublicKey, Result, Safe, Url, UrlAddressExt,
+    XorUrl,
+};
+use log::{debug, info};
+use std::collections::BTreeSet;
+
+// Path of the links to the Registers a Register was migrated to, which tells them
+// apart from any other entry linking to a Register
+const MIGRATED_PATH: &str = "/sn-api-migrated";
+
+// Maximum number of migrations followed when looking up the current Register,
+// which bounds the lookup if Registers were migrated back and forth
+const MAX_MIGRATIONS_FOLLOWED: usize = 32;
+
+impl Safe {
+    /// # Allow a key to write to a Register
+    ///
+    /// The writers are part of the policy of a Register, which the network doesn't allow
+    /// to change, thus the Register is migrated: a new Register, with the same type tag and
+    /// scope, is created with the key added to the writers, the whole history is copied to
+    /// it, and the Register is superseded with an entry pointing to the new one, which is
+    /// followed by `register_current`. Returns the URL of the new Register. Only the owner
+    /// of the Register can migrate it, and the entries written to it meanwhile are not
+    /// copied. It fails if anyone can already write to the Register.
+    pub async fn register_grant_write(&self, url: &str, key: PublicKey) -> Result<XorUrl> {
+        info!("Granting write access to {:?} on Register at {}", key, url);
+        self.migrate_register_writers(url, |writers| {
+            let _ = writers.insert(key);
+        })
+        .await
+    }
+
+    /// # Revoke the write access of a key to a Register
+    ///
+    /// As `register_grant_write`, the Register is migrated to a new one which the key is
+    /// not allowed to write to. The key can still write to the original Register, but such
+    /// entries are not copied, nor returned by the readers following the migration.
+    pub async fn register_revoke_write(&self, url: &str, key: PublicKey) -> Result<XorUrl> {
+        info!("Revoking write access of {:?} on Register at {}", key, url);
+        self.migrate_register_writers(url, |writers| {
+            let _ = writers.remove(&key);
+        })
+        .await
+    }
+
+    /// # Find the Register a Register was migrated to
+    ///
+    /// Follows the migrations of a Register, see `register_grant_write`, returning the URL
+    /// of the Register it was last migrated to, or its own URL if it was never migrated.
+    /// Migrations are only followed if the Register pointing to the new one and the new
+    /// one have the same owner.
+    pub async fn register_current(&self, url: &str) -> Result<XorUrl> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        for _ in 0..MAX_MIGRATIONS_FOLLOWED {
+            let entries = match self.fetch_register_entries(&safe_url).await {
+                Ok(entries) => entries,
+                Err(Error::EmptyContent(_)) => return Ok(safe_url.to_string()),
+                Err(err) => return Err(err),
+            };
+            let target = match entries
+                .into_iter()
+                .map(|(_, entry)| entry)
+                .find(is_migration)
+            {
+                Some(mut target) => {
+                    target.set_path("");
+                    target
+                }
+                None => return Ok(safe_url.to_string()),
+            };
+
+            let (owner, _) = self
+                .safe_client
+                .get_register_policy(safe_url.register_address()?)
+                .await?;
+            let (target_owner, _) = self
+                .safe_client
+                .get_register_policy(target.register_address()?)
+                .await?;
+            if target_owner != owner {
+                return Err(Error::ContentError(format!(
+                    "Register at \"{}\" was migrated to a Register with another owner",
+                    safe_url
+                )));
+            }
+            debug!("Register at {} was migrated to {}", safe_url, target);
+            safe_url = target;
+        }
+
+        Err(Error::ContentError(format!(
+            "Register at \"{}\" was migrated more than {} times",
+            url, MAX_MIGRATIONS_FOLLOWED
+        )))
+    }
+
+    // Private helper to migrate a Register to a new one with the writers updated
+    async fn migrate_register_writers(
+        &self,
+        url: &str,
+        update: impl FnOnce(&mut BTreeSet<PublicKey>),
+    ) -> Result<XorUrl> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let address = safe_url.register_address()?;
+        let (owner, writers) = self.safe_client.get_register_policy(address).await?;
+        if owner != self.get_my_keypair()?.public_key() {
+            return Err(Error::AccessDenied(format!(
+                "Only the owner of the Register at \"{}\" can change its writers",
+                safe_url
+            )));
+        }
+        let mut keys = match writers {
+            RegisterWriters::Keys(keys) => keys,
+            RegisterWriters::Anyone => {
+                return Err(Error::InvalidInput(format!(
+                    "Anyone can write to the Register at \"{}\"",
+                    safe_url
+                )))
+            }
+        };
+        let _ = keys.remove(&owner);
+        update(&mut keys);
+
+        let nodes = self.read_register_nodes(&safe_url).await?;
+        let dst_xorurl = self
+            .register_create_with_writers(None, address.tag(), !address.is_public(), keys)
+            .await?;
+        self.write_register_nodes(&dst_xorurl, nodes).await?;
+
+        let mut pointer = Url::from_xorurl(&dst_xorurl)?;
+        pointer.set_path(MIGRATED_PATH);
+        let parents = match self.fetch_register_entries(&safe_url).await {
+            Ok(entries) => entries.into_iter().map(|(hash, _)| hash).collect(),
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+        let _ = self
+            .write_to_register(&safe_url.to_string(), pointer, parents)
+            .await?;
+
+        Ok(dst_xorurl)
+    }
+}
+
+// Whether the Register entry points to the Register it was migrated to
+fn is_migration(entry: &Entry) -> bool {
+    entry.data_type() == DataType::Register && entry.path() == MIGRATED_PATH
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_register_grant_revoke_write() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let writer = new_safe_instance().await?;
+        let owner_pk = safe.get_my_keypair()?.public_key();
+        let writer_pk = writer.get_my_keypair()?.public_key();
+
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let entry = Url::from_url("safe://before-migration")?;
+        let _ = safe
+            .write_to_register(&xorurl, entry.clone(), BTreeSet::new())
+            .await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        // only the owner can change the writers
+        assert!(matches!(
+            writer.register_grant_write(&xorurl, writer_pk).await,
+            Err(Error::AccessDenied(_))
+        ));
+
+        let granted_xorurl = safe.register_grant_write(&xorurl, writer_pk).await?;
+        assert_eq!(
+            safe.register_permissions(&granted_xorurl).await?,
+            RegisterWriters::Keys(vec![owner_pk, writer_pk].into_iter().collect())
+        );
+        let copied = retry_loop!(safe.register_read(&granted_xorurl));
+        assert_eq!(
+            copied.into_iter().map(|(_, e)| e).collect::<Vec<_>>(),
+            vec![entry]
+        );
+        let _ = retry_loop_for_pattern!(writer.register_current(&xorurl), Ok(current) if *current == granted_xorurl)?;
+        let _ = writer
+            .write_to_register(
+                &granted_xorurl,
+                Url::from_url("safe://granted")?,
+                BTreeSet::new(),
+            )
+            .await?;
+
+        let revoked_xorurl = safe
+            .register_revoke_write(&granted_xorurl, writer_pk)
+            .await?;
+        assert_eq!(
+            safe.register_permissions(&revoked_xorurl).await?,
+            RegisterWriters::Keys(vec![owner_pk].into_iter().collect())
+        );
+        let _ = retry_loop_for_pattern!(safe.register_current(&xorurl), Ok(current) if *current == revoked_xorurl)?;
+        assert!(writer
+            .write_to_register(
+                &revoked_xorurl,
+                Url::from_url("safe://denied")?,
+                BTreeSet::new()
+            )
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
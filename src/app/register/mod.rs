@@ -0,0 +1,730 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+mod batch;
+mod cache;
+mod coalescer;
+mod copy;
+mod dump;
+mod encrypted;
+mod history;
+mod large;
+mod metadata;
+mod migrate;
+mod pages;
+mod quarantine;
+mod resolve;
+mod signed;
+mod snapshot;
+mod sorted;
+mod stats;
+mod typed;
+mod watch;
+
+pub use batch::{RegisterBatch, RegisterBatchReport};
+pub(crate) use cache::RegisterCache;
+pub use coalescer::{CoalesceMode, RegisterWriteCoalescer};
+pub use dump::{RegisterDump, RegisterDumpEntry};
+pub(crate) use history::{last_generations, register_dag};
+pub use history::{RegisterHistory, RegisterNode};
+pub(crate) use large::DEFAULT_MAX_REGISTER_ENTRY_LEN;
+pub use pages::RegisterPage;
+pub(crate) use quarantine::{is_tombstone, Quarantine};
+pub use quarantine::{QuarantineKind, QuarantinedEntry};
+pub use resolve::{MergeFn, MergePolicy};
+pub use safe_network::types::register::{Entry, EntryHash};
+pub use signed::{EntryWriter, VerifiedEntry};
+pub use snapshot::CompactedHistory;
+pub(crate) use sorted::EnvelopeIndex;
+pub use sorted::{SortBy, TimeRange};
+pub use stats::RegisterStats;
+pub use typed::TypedRegister;
+pub use watch::{EntryEnvelope, RegisterWatch, WatchFilter};
+
+use crate::{
+    app::whois::RegisterWriters, Error, MissingPermission, PublicKey, Result, Safe, UrlAddressExt,
+};
+use log::{debug, warn};
+use safe_network::url::{ContentType, Scope, Url, XorUrl};
+use std::collections::BTreeSet;
+use xor_name::XorName;
+
+// Separator of the hash of an entry appended to a Register URL as its fragment
+const ENTRY_HASH_SEPARATOR: char = '#';
+
+impl Safe {
+    /// URL pinning an entry of a Register, i.e. the URL of the Register with the hash of
+    /// the entry, hex encoded, as its fragment, e.g. `safe://mysafeurl#<entry hash>`.
+    /// It can be read with `register_read`, and written to with `write_to_register`
+    /// to supersede the entry.
+    pub fn register_entry_url(url: &str, hash: EntryHash) -> String {
+        let (url, _) = url.split_once(ENTRY_HASH_SEPARATOR).unwrap_or((url, ""));
+        format!("{}{}{}", url, ENTRY_HASH_SEPARATOR, hex::encode(hash))
+    }
+
+    /// Create a Register on the network
+    pub async fn register_create(
+        &self,
+        name: Option<XorName>,
+        type_tag: u64,
+        private: bool,
+    ) -> Result<XorUrl> {
+        self.register_create_with_writers(name, type_tag, private, BTreeSet::new())
+            .await
+    }
+
+    /// # Create a Register on the network which other keys can write to
+    ///
+    /// Besides its owner, i.e. the key this instance is connected with, the given keys are
+    /// allowed to write to the Register, and to read it if it's private, so collaborative
+    /// data structures can be built on it. The writers are part of the Register's policy,
+    /// which is set upon creation and can't be changed afterwards, thus granting or revoking
+    /// writers migrates the Register to a new one, see `register_grant_write`.
+    pub async fn register_create_with_writers(
+        &self,
+        name: Option<XorName>,
+        type_tag: u64,
+        private: bool,
+        writers: BTreeSet<PublicKey>,
+    ) -> Result<XorUrl> {
+        let xorname = self
+            .safe_client
+            .store_register_with_writers(name, type_tag, private, writers)
+            .await?;
+
+        let scope = if private {
+            Scope::Private
+        } else {
+            Scope::Public
+        };
+        let xorurl =
+            Url::encode_register(xorname, type_tag, scope, ContentType::Raw, self.xorurl_base)?;
+
+        Ok(xorurl)
+    }
+
+    /// # Create a Register on the network with initial entries
+    ///
+    /// The entries are written without parents, thus they are all current entries of the
+    /// Register, before its URL is returned, so it doesn't resolve to an empty Register once
+    /// handed out. Their hashes are returned in the same order. Unless a name is given, the
+    /// Register is created at a random location nobody knows about until then. The network
+    /// doesn't support creating a Register together with entries though, thus it's not
+    /// atomic: if any entry fails to be written, the error is returned, and the Register
+    /// deleted if it's private, or left as is if it's public.
+    pub async fn register_create_with_entries(
+        &self,
+        name: Option<XorName>,
+        type_tag: u64,
+        private: bool,
+        entries: Vec<Entry>,
+    ) -> Result<(XorUrl, Vec<EntryHash>)> {
+        let xorurl = self.register_create(name, type_tag, private).await?;
+        if entries.is_empty() {
+            return Ok((xorurl, vec![]));
+        }
+
+        let entries = entries
+            .into_iter()
+            .map(|entry| (entry, BTreeSet::new()))
+            .collect();
+        let written = self
+            .write_entries_to_register(&xorurl, entries)
+            .await
+            .and_then(|results| results.into_iter().collect::<Result<Vec<_>>>());
+        match written {
+            Ok(hashes) => Ok((xorurl, hashes)),
+            Err(err) => {
+                if private {
+                    if let Err(delete_err) = self.register_delete(&xorurl).await {
+                        warn!(
+                            "Failed to delete Register at {} after failing to write its initial entries: {}",
+                            xorurl, delete_err
+                        );
+                    }
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Read value from a Register on the network. If the URL pins an entry,
+    /// see `register_entry_url`, only such entry is read.
+    pub async fn register_read(&self, url: &str) -> Result<BTreeSet<(EntryHash, Entry)>> {
+        debug!("Getting Public Register data from: {:?}", url);
+        let (url, pinned_hash) = split_entry_hash(url)?;
+        let (safeurl, _) = self.parse_and_resolve_url(url).await?;
+
+        match pinned_hash {
+            Some(hash) => {
+                let entry = self.fetch_register_entry(&safeurl, hash).await?;
+                Ok(vec![(hash, entry)].into_iter().collect())
+            }
+            None => self.fetch_register_entries(&safeurl).await,
+        }
+    }
+
+    /// Read value from a Register on the network by its hash
+    pub async fn register_read_entry(&self, url: &str, hash: EntryHash) -> Result<Entry> {
+        debug!("Getting Public Register data from: {:?}", url);
+        let (url, _) = split_entry_hash(url)?;
+        let (safeurl, _) = self.parse_and_resolve_url(url).await?;
+
+        self.fetch_register_entry(&safeurl, hash).await
+    }
+
+    /// Fetch a Register from a Url without performing any type of URL resolution
+    /// Supports version hashes:
+    /// e.g. safe://mysafeurl?v=ce56a3504c8f27bfeb13bdf9051c2e91409230ea
+    pub(crate) async fn fetch_register_entries(
+        &self,
+        url: &Url,
+    ) -> Result<BTreeSet<(EntryHash, Entry)>> {
+        let result = match url.content_version() {
+            Some(v) => {
+                debug!("Take entry with version hash");
+                let hash = v.entry_hash();
+                self.fetch_register_entry(url, hash)
+                    .await
+                    .map(|entry| vec![(hash, entry)].into_iter().collect())
+            }
+            None => {
+                debug!("No version so take latest entry");
+                let address = url.register_address()?;
+                self.safe_client.read_register(address).await
+            }
+        };
+
+        match result {
+            Ok(data) => {
+                debug!("Register retrieved from {}...", url);
+                self.open_register_entries(url, data).await
+            }
+            Err(Error::EmptyContent(_)) => Err(Error::EmptyContent(format!(
+                "Register found at \"{}\" was empty",
+                url
+            ))),
+            Err(Error::ContentNotFound(_)) => Err(Error::ContentNotFound(format!(
+                "No Register found at \"{}\"",
+                url
+            ))),
+            other_err => other_err,
+        }
+    }
+
+    /// Fetch a Register from a Url without performing any type of URL resolution
+    pub(crate) async fn fetch_register_entry(&self, url: &Url, hash: EntryHash) -> Result<Entry> {
+        let address = url.register_address()?;
+        let entry = self.safe_client.get_register_entry(address, hash).await?;
+        self.open_register_entry(url, entry).await
+    }
+
+    /// # Delete a private Register from the network
+    ///
+    /// Only the owner of a private Register can delete it, public Registers can't be
+    /// deleted. The content its entries link to, e.g. Blobs, is not deleted.
+    pub async fn register_delete(&self, url: &str) -> Result<()> {
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let address = safe_url.register_address()?;
+        if address.is_public() {
+            // Only private Registers can be deleted
+            return Err(Error::PermissionDenied {
+                missing: MissingPermission::Scope,
+                address: url.to_string(),
+            });
+        }
+
+        let result = self.safe_client.delete_register(address).await;
+        self.emit_outcome("register_delete", &safe_url.to_string(), &result);
+        result
+    }
+
+    /// Keys allowed to write to a Register, which include its owner
+    pub async fn register_permissions(&self, url: &str) -> Result<RegisterWriters> {
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let address = safe_url.register_address()?;
+        let (_, writers) = self.safe_client.get_register_policy(address).await?;
+        Ok(writers)
+    }
+
+    /// Write value to a Register on the network. If the URL pins an entry, see
+    /// `register_entry_url`, such entry is superseded too, besides the given parents.
+    pub async fn write_to_register(
+        &self,
+        url: &str,
+        entry: Entry,
+        mut parents: BTreeSet<EntryHash>,
+    ) -> Result<EntryHash> {
+        let (url, pinned_hash) = split_entry_hash(url)?;
+        if let Some(hash) = pinned_hash {
+            let _ = parents.insert(hash);
+        }
+
+        let (url, _) = self.parse_and_resolve_url(url).await?;
+        let address = url.register_address()?;
+        let entry = self.seal_register_entry(&url, entry).await?;
+        let result = self
+            .safe_client
+            .write_to_register(address, entry, parents)
+            .await;
+        self.emit_outcome("write_to_register", &url.to_string(), &result);
+        result
+    }
+
+    /// # Write several entries to a Register on the network
+    ///
+    /// Each entry is written with its own parents, concurrently since the network doesn't
+    /// support writing several entries with a single command, thus they are not written
+    /// atomically. The outcome of each write is returned in the same order, i.e. the hash
+    /// of the entry written or the error it failed with, so the caller knows which entries
+    /// were written. Entries can't be parents of each other, since their hashes are only
+    /// known once written.
+    pub async fn write_entries_to_register(
+        &self,
+        url: &str,
+        entries: Vec<(Entry, BTreeSet<EntryHash>)>,
+    ) -> Result<Vec<Result<EntryHash>>> {
+        let (url, _) = self.parse_and_resolve_url(url).await?;
+        let address = url.register_address()?;
+        let mut sealed_entries = Vec::with_capacity(entries.len());
+        for (entry, parents) in entries {
+            sealed_entries.push((self.seal_register_entry(&url, entry).await?, parents));
+        }
+        let results = self
+            .safe_client
+            .write_entries_to_register(address, sealed_entries)
+            .await?;
+        for result in results.iter() {
+            self.emit_outcome("write_entries_to_register", &url.to_string(), result);
+        }
+        Ok(results)
+    }
+
+    /// Write value to a Register on the network unless an identical entry is already
+    /// among the Register's current entries or the given parents, in which case the hash
+    /// of the existing entry is returned. The returned flag tells if the entry was written.
+    /// Since payloads stored as Blobs are content addressed, entries linking to identical
+    /// payloads are identical.
+    pub async fn write_to_register_dedup(
+        &self,
+        url: &str,
+        entry: Entry,
+        parents: BTreeSet<EntryHash>,
+    ) -> Result<(EntryHash, bool)> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let tips = match self.fetch_register_entries(&safe_url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        if let Some((hash, _)) = tips.iter().find(|(_, tip)| *tip == entry) {
+            debug!("Entry already found among current entries of {}", url);
+            return Ok((*hash, false));
+        }
+        for hash in parents.iter() {
+            if tips.iter().any(|(tip_hash, _)| tip_hash == hash) {
+                continue;
+            }
+            if self.fetch_register_entry(&safe_url, *hash).await? == entry {
+                debug!("Entry already found among parent entries of {}", url);
+                return Ok((*hash, false));
+            }
+        }
+
+        let hash = self.write_to_register(url, entry, parents).await?;
+        Ok((hash, true))
+    }
+
+    /// # Write value to a Register only if its current entries are the expected ones
+    ///
+    /// The entry is written superseding the expected entries, as long as they are exactly
+    /// the Register's current entries, otherwise it fails with `Error::ConcurrentWrite` and
+    /// nothing is written, so the caller can read the Register again, and retry, instead of
+    /// creating a new branch. The current entries are read right before writing, thus a
+    /// write made by another client in between can still go undetected.
+    pub async fn write_to_register_cas(
+        &self,
+        url: &str,
+        entry: Entry,
+        expected_tips: BTreeSet<EntryHash>,
+    ) -> Result<EntryHash> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let tips: BTreeSet<EntryHash> = match self.fetch_register_entries(&safe_url).await {
+            Ok(entries) => entries.into_iter().map(|(hash, _)| hash).collect(),
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        if tips != expected_tips {
+            return Err(Error::ConcurrentWrite(format!(
+                "The current entries of the Register at \"{}\" are not the expected ones, \
+                it has {} current entries: {:?}",
+                url,
+                tips.len(),
+                tips.iter().map(hex::encode).collect::<Vec<_>>()
+            )));
+        }
+
+        self.write_to_register(url, entry, expected_tips).await
+    }
+
+    /// # Append a value to a Register, superseding all its current entries
+    ///
+    /// The current entries of the Register are read and used as the parents of the new
+    /// entry, so it becomes the Register's only current entry, without the caller keeping
+    /// track of entry hashes. As with `write_to_register_cas`, a write made by another client
+    /// between the read and the write isn't superseded, but kept as a separate branch.
+    pub async fn register_append(&self, url: &str, entry: Entry) -> Result<EntryHash> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let parents = match self.fetch_register_entries(&safe_url).await {
+            Ok(entries) => entries.into_iter().map(|(hash, _)| hash).collect(),
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        self.write_to_register(url, entry, parents).await
+    }
+}
+
+// Split the hash of an entry off a Register URL, if the URL pins one as its fragment
+pub(crate) fn split_entry_hash(url: &str) -> Result<(&str, Option<EntryHash>)> {
+    let (url, fragment) = match url.split_once(ENTRY_HASH_SEPARATOR) {
+        Some((url, fragment)) => (url, fragment),
+        None => return Ok((url, None)),
+    };
+
+    let mut hash = EntryHash::default();
+    hex::decode_to_slice(fragment, &mut hash).map_err(|err| {
+        Error::InvalidInput(format!(
+            "The fragment of the URL is not a valid entry hash '{}': {}",
+            fragment, err
+        ))
+    })?;
+    Ok((url, Some(hash)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_entry_hash;
+    use crate::{
+        app::{test_helpers::new_safe_instance, whois::RegisterWriters},
+        retry_loop, retry_loop_for_pattern, Error, MissingPermission, Safe, Url,
+    };
+    use anyhow::Result;
+    use std::collections::BTreeSet;
+
+    #[tokio::test]
+    async fn test_register_create() -> Result<()> {
+        let safe = new_safe_instance().await?;
+
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let xorurl_priv = safe.register_create(None, 25_000, true).await?;
+
+        let received_data = retry_loop!(safe.register_read(&xorurl));
+        let received_data_priv = retry_loop!(safe.register_read(&xorurl_priv));
+
+        assert!(received_data.is_empty());
+        assert!(received_data_priv.is_empty());
+
+        let initial_data = Url::from_url("safe://test")?;
+        let hash = safe
+            .write_to_register(&xorurl, initial_data.clone(), Default::default())
+            .await?;
+        let hash_priv = safe
+            .write_to_register(&xorurl_priv, initial_data.clone(), Default::default())
+            .await?;
+
+        let received_entry = retry_loop!(safe.register_read_entry(&xorurl, hash));
+        let received_entry_priv = retry_loop!(safe.register_read_entry(&xorurl_priv, hash_priv));
+
+        assert_eq!(received_entry, initial_data.clone());
+        assert_eq!(received_entry_priv, initial_data);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_create_with_entries() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let entries = vec![
+            Url::from_url("safe://first")?,
+            Url::from_url("safe://second")?,
+        ];
+        let (xorurl, hashes) = safe
+            .register_create_with_entries(None, 25_000, true, entries.clone())
+            .await?;
+        assert_eq!(hashes.len(), 2);
+
+        let read = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if entries.len() == 2)?;
+        let expected: BTreeSet<_> = hashes.into_iter().zip(entries).collect();
+        assert_eq!(read, expected);
+
+        let (xorurl, hashes) = safe
+            .register_create_with_entries(None, 25_000, false, vec![])
+            .await?;
+        assert!(hashes.is_empty());
+        assert!(retry_loop!(safe.register_read(&xorurl)).is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_write_to_register_dedup() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let first = Url::from_url("safe://first")?;
+        let (hash, written) = safe
+            .write_to_register_dedup(&xorurl, first.clone(), Default::default())
+            .await?;
+        assert!(written);
+        let _ = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if !entries.is_empty())?;
+
+        // identical entry among the current entries
+        let (dup_hash, written) = safe
+            .write_to_register_dedup(&xorurl, first.clone(), Default::default())
+            .await?;
+        assert!(!written);
+        assert_eq!(dup_hash, hash);
+
+        // identical entry among the parents, once superseded
+        let parents = vec![hash].into_iter().collect();
+        let (second_hash, written) = safe
+            .write_to_register_dedup(&xorurl, Url::from_url("safe://second")?, parents)
+            .await?;
+        assert!(written);
+        let _ = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if entries.iter().any(|(h, _)| *h == second_hash))?;
+        let parents = vec![hash, second_hash].into_iter().collect();
+        let (dup_hash, written) = safe
+            .write_to_register_dedup(&xorurl, first, parents)
+            .await?;
+        assert!(!written);
+        assert_eq!(dup_hash, hash);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_write_entries_to_register() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let entries = vec![
+            Url::from_url("safe://first")?,
+            Url::from_url("safe://second")?,
+            Url::from_url("safe://third")?,
+        ];
+        let to_write = entries
+            .iter()
+            .map(|entry| (entry.clone(), Default::default()))
+            .collect();
+        let hashes = safe
+            .write_entries_to_register(&xorurl, to_write)
+            .await?
+            .into_iter()
+            .collect::<crate::Result<Vec<_>>>()?;
+        assert_eq!(hashes.len(), 3);
+
+        let read =
+            retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(read) if read.len() == 3)?;
+        for (hash, entry) in hashes.iter().zip(entries.iter()) {
+            assert!(read.contains(&(*hash, entry.clone())));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_create_with_writers() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let writer = new_safe_instance().await?;
+        let stranger = new_safe_instance().await?;
+        let owner_pk = safe.get_my_keypair()?.public_key();
+        let writer_pk = writer.get_my_keypair()?.public_key();
+
+        let writers = vec![writer_pk].into_iter().collect();
+        let xorurl = safe
+            .register_create_with_writers(None, 25_000, false, writers)
+            .await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let permissions = safe.register_permissions(&xorurl).await?;
+        assert_eq!(
+            permissions,
+            RegisterWriters::Keys(vec![owner_pk, writer_pk].into_iter().collect())
+        );
+
+        let hash = writer
+            .write_to_register(
+                &xorurl,
+                Url::from_url("safe://granted")?,
+                Default::default(),
+            )
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if entries.iter().any(|(h, _)| *h == hash))?;
+        assert!(stranger
+            .write_to_register(&xorurl, Url::from_url("safe://denied")?, Default::default())
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_write_to_register_cas() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let first = safe
+            .write_to_register_cas(&xorurl, Url::from_url("safe://first")?, BTreeSet::new())
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if !entries.is_empty())?;
+
+        // a stale view of the Register is rejected
+        assert!(matches!(
+            safe.write_to_register_cas(&xorurl, Url::from_url("safe://stale")?, BTreeSet::new())
+                .await,
+            Err(Error::ConcurrentWrite(_))
+        ));
+
+        let second = safe
+            .write_to_register_cas(
+                &xorurl,
+                Url::from_url("safe://second")?,
+                vec![first].into_iter().collect(),
+            )
+            .await?;
+        let entries = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if entries.iter().all(|(hash, _)| *hash == second))?;
+        assert_eq!(entries.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_append() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        // two branches are merged by the appended entry
+        for name in ["safe://a", "safe://b"].iter() {
+            let _ = safe
+                .write_to_register(&xorurl, Url::from_url(name)?, BTreeSet::new())
+                .await?;
+        }
+        let _ = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if entries.len() == 2)?;
+
+        let appended = Url::from_url("safe://appended")?;
+        let hash = safe.register_append(&xorurl, appended.clone()).await?;
+        let entries = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if entries.len() == 1)?;
+        assert_eq!(entries.into_iter().next(), Some((hash, appended)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_delete() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, true).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+        let _ = safe
+            .write_to_register(
+                &xorurl,
+                Url::from_url("safe://session")?,
+                Default::default(),
+            )
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if !entries.is_empty())?;
+
+        safe.register_delete(&xorurl).await?;
+        let deleted = retry_loop_for_pattern!(
+            safe.register_read(&xorurl),
+            Err(Error::ContentNotFound(_) | Error::NetDataError(_))
+        );
+        assert!(deleted.is_err());
+
+        let public_xorurl = safe.register_create(None, 25_000, false).await?;
+        assert!(matches!(
+            safe.register_delete(&public_xorurl).await,
+            Err(Error::PermissionDenied {
+                missing: MissingPermission::Scope,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_entry_url() -> Result<()> {
+        let hash = [0xab; 32];
+        let entry_url = Safe::register_entry_url("safe://mysafeurl", hash);
+        assert_eq!(entry_url, format!("safe://mysafeurl#{}", "ab".repeat(32)));
+        assert_eq!(
+            split_entry_hash(&entry_url)?,
+            ("safe://mysafeurl", Some(hash))
+        );
+        // a pinned entry is replaced
+        assert_eq!(
+            Safe::register_entry_url(&entry_url, [0xcd; 32]),
+            format!("safe://mysafeurl#{}", "cd".repeat(32))
+        );
+
+        assert_eq!(
+            split_entry_hash("safe://mysafeurl")?,
+            ("safe://mysafeurl", None)
+        );
+        assert!(split_entry_hash("safe://mysafeurl#not-a-hash").is_err());
+        assert!(split_entry_hash("safe://mysafeurl#abcd").is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_entry_url_read_and_write() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let root = Url::from_url("safe://root")?;
+        let root_hash = safe
+            .write_to_register(&xorurl, root.clone(), Default::default())
+            .await?;
+        let root_url = Safe::register_entry_url(&xorurl, root_hash);
+
+        // the entry pinned by the URL is superseded by the write
+        let child = Url::from_url("safe://child")?;
+        let child_hash = safe
+            .write_to_register(&root_url, child.clone(), Default::default())
+            .await?;
+        let current = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if entries.iter().any(|(hash, _)| *hash == child_hash))?;
+        assert_eq!(
+            current,
+            vec![(child_hash, child)]
+                .into_iter()
+                .collect::<BTreeSet<_>>()
+        );
+
+        // the entry pinned by the URL is read even if it's not current anymore
+        let pinned = retry_loop!(safe.register_read(&root_url));
+        assert_eq!(
+            pinned,
+            vec![(root_hash, root)].into_iter().collect::<BTreeSet<_>>()
+        );
+
+        Ok(())
+    }
+}
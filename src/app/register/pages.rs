@@ -0,0 +1,154 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{Entry, EntryHash};
+use crate::{Error, Result, Safe};
+use futures::{stream, Stream};
+use std::collections::BTreeSet;
+
+/// A page of the entries of a Register, see `Safe::register_read_page`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegisterPage {
+    /// Entries of the page, sorted by their hash
+    pub entries: Vec<(EntryHash, Entry)>,
+    /// Hash to read the next page after, if there are more entries
+    pub next: Option<EntryHash>,
+}
+
+impl Safe {
+    /// # Read a page of the entries of a Register
+    ///
+    /// Returns up to `page_size` entries, sorted by their hash, starting after the entry
+    /// with the `after` hash, or from the first entry if not provided. The `next` hash of
+    /// the page returned is to be provided to read the following page. Since pages are read
+    /// independently, entries written in between reading two pages may be missed.
+    ///
+    /// The network returns all the entries of a Register at once, thus each page is taken
+    /// from them after they are fetched, so only a page of entries is held by the caller.
+    pub async fn register_read_page(
+        &self,
+        url: &str,
+        after: Option<EntryHash>,
+        page_size: usize,
+    ) -> Result<RegisterPage> {
+        if page_size == 0 {
+            return Err(Error::InvalidInput(
+                "The size of the page must be greater than zero".to_string(),
+            ));
+        }
+
+        let entries = match self.register_read(url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+        let mut remaining = entries
+            .into_iter()
+            .filter(|(hash, _)| after.map_or(true, |after| *hash > after));
+        let entries: Vec<(EntryHash, Entry)> = remaining.by_ref().take(page_size).collect();
+        let next = if remaining.next().is_some() {
+            entries.last().map(|(hash, _)| *hash)
+        } else {
+            None
+        };
+
+        Ok(RegisterPage { entries, next })
+    }
+
+    /// # Stream the entries of a Register in batches
+    ///
+    /// Returns a stream which yields the entries of a Register, sorted by their hash,
+    /// in batches of up to `batch_size` entries, so they can be processed incrementally.
+    /// The entries are fetched once, and each batch is released as it's yielded.
+    pub async fn register_read_stream(
+        &self,
+        url: &str,
+        batch_size: usize,
+    ) -> Result<impl Stream<Item = Vec<(EntryHash, Entry)>>> {
+        if batch_size == 0 {
+            return Err(Error::InvalidInput(
+                "The size of the batches must be greater than zero".to_string(),
+            ));
+        }
+
+        let entries = match self.register_read(url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        Ok(stream::unfold(
+            entries.into_iter(),
+            move |mut entries| async move {
+                let batch: Vec<(EntryHash, Entry)> = entries.by_ref().take(batch_size).collect();
+                if batch.is_empty() {
+                    None
+                } else {
+                    Some((batch, entries))
+                }
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern, Url};
+    use anyhow::Result;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_register_read_page_and_stream() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let empty = retry_loop!(safe.register_read_page(&xorurl, None, 2));
+        assert_eq!(
+            empty,
+            RegisterPage {
+                entries: vec![],
+                next: None
+            }
+        );
+
+        // five concurrent entries, thus all of them are current entries
+        for i in 0..5 {
+            let entry = Url::from_url(&format!("safe://entry-{}", i))?;
+            let _ = safe
+                .write_to_register(&xorurl, entry, BTreeSet::new())
+                .await?;
+        }
+        let all = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if entries.len() == 5)?;
+
+        let mut paged = Vec::new();
+        let mut after = None;
+        loop {
+            let page = safe.register_read_page(&xorurl, after, 2).await?;
+            assert!(page.entries.len() <= 2);
+            paged.extend(page.entries);
+            after = page.next;
+            if after.is_none() {
+                break;
+            }
+        }
+        assert_eq!(paged, all.iter().cloned().collect::<Vec<_>>());
+
+        let batches: Vec<Vec<(EntryHash, Entry)>> =
+            safe.register_read_stream(&xorurl, 2).await?.collect().await;
+        assert_eq!(
+            batches.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+        assert_eq!(batches.concat(), paged);
+
+        assert!(safe.register_read_page(&xorurl, None, 0).await.is_err());
+
+        Ok(())
+    }
+}
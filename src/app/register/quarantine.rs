@@ -0,0 +1,245 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{Entry, EntryHash};
+use crate::{app::helpers::gen_timestamp_secs, Error, Result, Safe, Url, UrlAddressExt};
+use log::{info, warn};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+};
+
+// Path of the entries written to supersede quarantined entries
+const TOMBSTONE_PATH: &str = "/sn-api-tombstone";
+
+/// Kind of content a quarantined Register entry was expected to hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuarantineKind {
+    /// A key-value pair of a Multimap, e.g. of an NRS map container
+    MultimapEntry,
+    /// An NrsMap of an NRS map container
+    NrsMap,
+    /// A FilesMap of a FilesContainer
+    FilesMap,
+    /// An envelope, see `Safe::register_write_envelope`
+    Envelope,
+    /// A value of a `TypedRegister`
+    TypedValue,
+}
+
+/// A Register entry which couldn't be decoded as the content it was expected to hold,
+/// thus it was left out when reading the Register, see `Safe::register_quarantined`
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuarantinedEntry {
+    /// URL of the Register
+    pub url: String,
+    pub hash: EntryHash,
+    pub kind: QuarantineKind,
+    /// Why the entry couldn't be decoded
+    pub reason: String,
+    /// Time the entry was first quarantined at, as an RFC3339 timestamp
+    pub quarantined_at: String,
+}
+
+// Entries quarantined by an instance, keyed by the URL of their Register and their
+// hash. It's only kept locally. Clones of an instance share the same quarantine.
+#[derive(Clone, Default)]
+pub(crate) struct Quarantine {
+    entries: Arc<Mutex<BTreeMap<(String, EntryHash), QuarantinedEntry>>>,
+}
+
+impl Quarantine {
+    fn insert(&self, url: String, hash: EntryHash, kind: QuarantineKind, reason: String) {
+        if let Ok(mut entries) = self.entries.lock() {
+            let _ = entries
+                .entry((url.clone(), hash))
+                .or_insert_with(|| QuarantinedEntry {
+                    url,
+                    hash,
+                    kind,
+                    reason,
+                    quarantined_at: gen_timestamp_secs(),
+                });
+        }
+    }
+
+    fn remove(&self, url: &str, hash: &EntryHash) {
+        if let Ok(mut entries) = self.entries.lock() {
+            let _ = entries.remove(&(url.to_string(), *hash));
+        }
+    }
+
+    fn list(&self, url: Option<&str>) -> Vec<QuarantinedEntry> {
+        self.entries.lock().map_or_else(
+            |_| vec![],
+            |entries| {
+                entries
+                    .values()
+                    .filter(|entry| url.map_or(true, |url| entry.url == url))
+                    .cloned()
+                    .collect()
+            },
+        )
+    }
+}
+
+impl Safe {
+    /// # List the entries of a Register which were quarantined
+    ///
+    /// When reading typed content from a Register, e.g. resolving an NRS name, reading a
+    /// FilesContainer, or the values of a `TypedRegister`, the entries which can't be
+    /// decoded as such content, e.g. garbage written by a faulty or malicious writer, are
+    /// quarantined and left out of the read rather than failing it. This lists the entries
+    /// quarantined so far by this instance for the Register, which can be read as they
+    /// are with `register_read_entry`, and superseded with `register_tombstone`.
+    pub async fn register_quarantined(&self, url: &str) -> Result<Vec<QuarantinedEntry>> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        Ok(self.quarantine.list(Some(&safe_url.to_string())))
+    }
+
+    /// List all the entries quarantined so far by this instance, of any Register
+    pub fn quarantined_entries(&self) -> Vec<QuarantinedEntry> {
+        self.quarantine.list(None)
+    }
+
+    /// # Supersede an entry of a Register with a tombstone
+    ///
+    /// A tombstone entry is written to the Register superseding the entry with the given
+    /// hash, e.g. a quarantined one, thus it's no longer among the Register's current
+    /// entries. Tombstones are skipped when reading typed content from Registers.
+    /// The entry remains in the Register's history.
+    pub async fn register_tombstone(&self, url: &str, hash: EntryHash) -> Result<EntryHash> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let _ = safe_url.register_address()?;
+        info!(
+            "Writing tombstone for entry {} of Register at {}",
+            hex::encode(hash),
+            safe_url
+        );
+
+        let mut tombstone = safe_url.clone();
+        tombstone.set_path(TOMBSTONE_PATH);
+        let parents = vec![hash].into_iter().collect::<BTreeSet<_>>();
+        let tombstone_hash = self
+            .write_to_register(&safe_url.to_string(), tombstone, parents)
+            .await?;
+        self.quarantine.remove(&safe_url.to_string(), &hash);
+
+        Ok(tombstone_hash)
+    }
+
+    // Quarantine an entry of a Register if it failed to be decoded as the expected
+    // content, or return the error otherwise, e.g. if its content failed to be fetched
+    pub(crate) fn quarantine_entry(
+        &self,
+        url: &Url,
+        hash: EntryHash,
+        kind: QuarantineKind,
+        err: Error,
+    ) -> Result<()> {
+        match err {
+            Error::ContentError(reason) | Error::DecodeError(reason) => {
+                let mut register_url = url.clone();
+                register_url.set_content_version(None);
+                warn!(
+                    "Quarantining entry {} of Register at {} which is not a valid {:?}: {}",
+                    hex::encode(hash),
+                    register_url,
+                    kind,
+                    reason
+                );
+                self.quarantine
+                    .insert(register_url.to_string(), hash, kind, reason);
+                Ok(())
+            }
+            other => Err(other),
+        }
+    }
+}
+
+// Whether the entry is a tombstone superseding another entry
+pub(crate) fn is_tombstone(entry: &Entry) -> bool {
+    entry.path() == TOMBSTONE_PATH
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::{register::TypedRegister, test_helpers::new_safe_instance},
+        retry_loop_for_pattern,
+    };
+    use anyhow::Result;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_quarantine_entry() -> Result<()> {
+        let safe = Safe::default();
+        let url = Url::from_url("safe://register")?;
+
+        safe.quarantine_entry(
+            &url,
+            [1; 32],
+            QuarantineKind::NrsMap,
+            Error::ContentError("garbage".to_string()),
+        )?;
+        // other failures are not quarantined
+        assert!(safe
+            .quarantine_entry(
+                &url,
+                [2; 32],
+                QuarantineKind::NrsMap,
+                Error::NetDataError("timeout".to_string()),
+            )
+            .is_err());
+
+        let quarantined = safe.quarantined_entries();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].url, "safe://register");
+        assert_eq!(quarantined[0].hash, [1; 32]);
+        assert_eq!(quarantined[0].kind, QuarantineKind::NrsMap);
+        assert_eq!(quarantined[0].reason, "garbage");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_quarantine_and_tombstone() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let register = TypedRegister::<u64>::create(&safe, None, 25_000, false).await?;
+        let url = register.url().to_string();
+        let good = register.write(&42, BTreeSet::new()).await?;
+
+        // a garbage entry written concurrently doesn't make the read fail
+        let garbage_xorurl = safe
+            .store_public_bytes(Bytes::from("garbage"), None, false)
+            .await?;
+        let garbage = safe
+            .write_to_register(&url, Url::from_url(&garbage_xorurl)?, BTreeSet::new())
+            .await?;
+
+        let values = retry_loop_for_pattern!(register.read(), Ok(v) if !v.is_empty())?;
+        assert_eq!(values, vec![(good, 42)]);
+        let quarantined = safe.register_quarantined(&url).await?;
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].hash, garbage);
+        assert_eq!(quarantined[0].kind, QuarantineKind::TypedValue);
+
+        let _ = safe.register_tombstone(&url, garbage).await?;
+        assert!(safe.register_quarantined(&url).await?.is_empty());
+        let entries = retry_loop_for_pattern!(safe.register_read(&url), Ok(v) if v.iter().all(|(hash, _)| *hash != garbage))?;
+        assert!(entries.iter().any(|(_, entry)| is_tombstone(entry)));
+        assert_eq!(register.read().await?, vec![(good, 42)]);
+        assert!(safe.register_quarantined(&url).await?.is_empty());
+
+        Ok(())
+    }
+}
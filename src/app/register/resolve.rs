@@ -0,0 +1,152 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{Entry, EntryHash};
+use crate::{Error, Result, Safe, Url};
+use log::debug;
+use std::{collections::BTreeSet, fmt, sync::Arc};
+
+/// Function which collapses the current entries of a Register into a single one
+pub type MergeFn =
+    dyn Fn(&BTreeSet<(EntryHash, Entry)>) -> Result<(EntryHash, Entry)> + Send + Sync;
+
+/// How the concurrent entries of a Register are collapsed into a single current entry
+#[derive(Clone)]
+pub enum MergePolicy {
+    /// The entry whose envelope has the latest time wins. Entries which are not
+    /// envelopes lose against those which are, ties are won by the greatest hash.
+    LastWriteWins,
+    /// The entry whose link is the greatest when compared as strings wins,
+    /// ties are won by the greatest hash
+    Lexicographic,
+    /// The entry returned by the function wins
+    Custom(Arc<MergeFn>),
+}
+
+impl fmt::Debug for MergePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::LastWriteWins => write!(f, "LastWriteWins"),
+            Self::Lexicographic => write!(f, "Lexicographic"),
+            Self::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl Safe {
+    /// # Read the single current entry of a Register
+    ///
+    /// Registers have more than one current entry when written concurrently, those are
+    /// collapsed into one according to the policy. The policy is not applied if the
+    /// Register has a single current entry. It fails with `Error::EmptyContent` if the
+    /// Register has no entries.
+    pub async fn register_read_resolved(
+        &self,
+        url: &str,
+        policy: &MergePolicy,
+    ) -> Result<(EntryHash, Entry)> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let entries = self.fetch_register_entries(&safe_url).await?;
+        if entries.len() == 1 {
+            return greatest_entry(entries, |_| ());
+        }
+
+        match policy {
+            MergePolicy::LastWriteWins => self.last_written_entry(&safe_url, entries).await,
+            MergePolicy::Lexicographic => {
+                greatest_entry(entries, |(hash, entry)| (entry.to_string(), *hash))
+            }
+            MergePolicy::Custom(merge) => merge(&entries),
+        }
+    }
+
+    // Private helper to pick the entry whose envelope has the latest time
+    async fn last_written_entry(
+        &self,
+        url: &Url,
+        entries: BTreeSet<(EntryHash, Entry)>,
+    ) -> Result<(EntryHash, Entry)> {
+        for (hash, entry) in entries.iter() {
+            if self.envelope_index.timestamp(url, hash).is_some() {
+                continue;
+            }
+            match self.fetch_envelope(entry).await {
+                Ok(envelope) => self.envelope_index.insert(url, *hash, envelope),
+                Err(err) => debug!("Entry {} of {} has no time: {}", entry, url, err),
+            }
+        }
+
+        greatest_entry(entries, |(hash, _)| {
+            (self.envelope_index.timestamp(url, hash), *hash)
+        })
+    }
+}
+
+fn greatest_entry<K: Ord>(
+    entries: BTreeSet<(EntryHash, Entry)>,
+    key: impl Fn(&(EntryHash, Entry)) -> K,
+) -> Result<(EntryHash, Entry)> {
+    entries
+        .into_iter()
+        .max_by_key(|entry| key(entry))
+        .ok_or_else(|| Error::EmptyContent("Register has no entries".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop_for_pattern};
+    use anyhow::Result;
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn test_register_read_resolved() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+
+        // two concurrent entries, the second one written later
+        let older = safe
+            .register_write_envelope(&xorurl, "text/plain", Bytes::from("b"), BTreeSet::new())
+            .await?;
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        let newer = safe
+            .register_write_envelope(&xorurl, "text/plain", Bytes::from("a"), BTreeSet::new())
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if entries.len() == 2)?;
+
+        let (hash, _) = safe
+            .register_read_resolved(&xorurl, &MergePolicy::LastWriteWins)
+            .await?;
+        assert_eq!(hash, newer);
+
+        let entries = safe.register_read(&xorurl).await?;
+        let (greatest_hash, _) = entries
+            .iter()
+            .max_by_key(|(hash, entry)| (entry.to_string(), *hash))
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No entries"))?;
+        let (hash, _) = safe
+            .register_read_resolved(&xorurl, &MergePolicy::Lexicographic)
+            .await?;
+        assert_eq!(hash, greatest_hash);
+
+        let first_written = MergePolicy::Custom(Arc::new(move |entries| {
+            entries
+                .iter()
+                .find(|(hash, _)| *hash == older)
+                .cloned()
+                .ok_or_else(|| Error::EntryNotFound("No older entry".to_string()))
+        }));
+        let (hash, _) = safe.register_read_resolved(&xorurl, &first_written).await?;
+        assert_eq!(hash, older);
+
+        Ok(())
+    }
+}
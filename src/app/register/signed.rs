@@ -0,0 +1,285 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{Entry, EntryHash};
+use crate::{ContentType, DataType, Error, PublicKey, Result, Safe, Url, UrlAddressExt};
+use bytes::Bytes;
+use log::debug;
+use safe_network::types::{BytesAddress, RegisterAddress, Signature};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// Path of the links to the Blobs holding signed entries, which tells them apart
+// from any other entry linking to a Blob without reading the Blob
+const SIGNED_ENTRY_PATH: &str = "/sn-api-signed-entry";
+
+/// Writer of a Register entry, as found by `Safe::register_read_verified`
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryWriter {
+    /// The entry was signed by the writer with this public key, for this Register
+    Verified(PublicKey),
+    /// The entry was not signed
+    Unsigned,
+    /// The entry was signed, but the signature couldn't be verified, e.g. it's invalid
+    /// or it was made for another Register, thus the writer is unknown
+    Invalid(String),
+}
+
+/// An entry of a Register together with its writer
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedEntry {
+    pub hash: EntryHash,
+    /// The entry as signed, or as it was written if unsigned
+    pub entry: Entry,
+    pub writer: EntryWriter,
+}
+
+// What the writer of an entry signs, binding the entry to the Register it's written to,
+// so a signed entry can't be replayed onto another Register
+#[derive(Serialize)]
+struct EntrySignedContent<'a> {
+    register: &'a RegisterAddress,
+    entry: &'a str,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SignedEntry {
+    register: RegisterAddress,
+    entry: String,
+    writer: PublicKey,
+    signature: Signature,
+}
+
+impl Safe {
+    /// # Sign an entry to be written to a Register
+    ///
+    /// The entry is signed with the signer of this instance, see `set_signer`, for the
+    /// given Register only, and stored in a Blob, with the same scope as the Register.
+    /// The entry returned links to such Blob, and it's to be written to the Register in
+    /// place of the given one, e.g. with `write_to_register`, so readers can tell who
+    /// wrote it with `register_read_verified`.
+    pub async fn register_sign_entry(&self, url: &str, entry: Entry) -> Result<Entry> {
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let address = safe_url.register_address()?;
+        let signer = self.signer()?;
+        let serialised_entry = entry.to_string();
+        let signature = signer
+            .sign(&serialise_signed_content(&address, &serialised_entry)?)
+            .await?;
+        let content = rmp_serde::to_vec_named(&SignedEntry {
+            register: address,
+            entry: serialised_entry,
+            writer: signer.public_key(),
+            signature,
+        })
+        .map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise signed entry: {:?}", err))
+        })?;
+
+        let blob_address = if address.is_public() {
+            BytesAddress::Public(
+                self.safe_client
+                    .store_bytes(Bytes::from(content), false)
+                    .await?,
+            )
+        } else {
+            BytesAddress::Private(
+                self.safe_client
+                    .store_private_bytes(Bytes::from(content))
+                    .await?,
+            )
+        };
+
+        let mut link = Url::from_xorurl(&Url::encode_bytes(
+            blob_address,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?)?;
+        link.set_path(SIGNED_ENTRY_PATH);
+        Ok(link)
+    }
+
+    /// Sign an entry, see `register_sign_entry`, and write it to a Register
+    pub async fn register_write_signed(
+        &self,
+        url: &str,
+        entry: Entry,
+        parents: BTreeSet<EntryHash>,
+    ) -> Result<EntryHash> {
+        let signed_entry = self.register_sign_entry(url, entry).await?;
+        self.write_to_register(url, signed_entry, parents).await
+    }
+
+    /// # Read the current entries of a Register with their writers
+    ///
+    /// Each entry signed with `register_sign_entry` is returned as signed, together with
+    /// the public key of the writer who signed it once its signature is verified. Entries
+    /// which are not signed, or whose signature can't be verified, are returned too, as
+    /// they were written, but without a writer, thus callers can decide whether to trust them.
+    pub async fn register_read_verified(&self, url: &str) -> Result<Vec<VerifiedEntry>> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let address = safe_url.register_address()?;
+        let entries = match self.fetch_register_entries(&safe_url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut verified = Vec::with_capacity(entries.len());
+        for (hash, entry) in entries {
+            if !is_signed_entry(&entry) {
+                verified.push(VerifiedEntry {
+                    hash,
+                    entry,
+                    writer: EntryWriter::Unsigned,
+                });
+                continue;
+            }
+
+            let mut blob_url = entry.clone();
+            blob_url.set_path("");
+            let content = self.fetch_public_data(&blob_url, None).await?;
+            let (entry, writer) = match verify_signed_entry(&content, &address) {
+                Ok((signed_entry, writer)) => (signed_entry, EntryWriter::Verified(writer)),
+                Err(err) => {
+                    debug!(
+                        "Entry {} of {} failed verification: {}",
+                        entry, safe_url, err
+                    );
+                    (entry, EntryWriter::Invalid(err.to_string()))
+                }
+            };
+            verified.push(VerifiedEntry {
+                hash,
+                entry,
+                writer,
+            });
+        }
+
+        Ok(verified)
+    }
+}
+
+// Whether the Register entry links to a Blob holding a signed entry
+fn is_signed_entry(entry: &Entry) -> bool {
+    entry.data_type() == DataType::Bytes && entry.path() == SIGNED_ENTRY_PATH
+}
+
+fn serialise_signed_content(register: &RegisterAddress, entry: &str) -> Result<Vec<u8>> {
+    rmp_serde::to_vec_named(&EntrySignedContent { register, entry })
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise entry to sign: {:?}", err)))
+}
+
+// Decode a signed entry and verify it was signed for the Register,
+// returning the entry and the public key of its writer
+fn verify_signed_entry(content: &[u8], address: &RegisterAddress) -> Result<(Entry, PublicKey)> {
+    let signed: SignedEntry = rmp_serde::from_slice(content)
+        .map_err(|err| Error::ContentError(format!("Couldn't parse signed entry: {:?}", err)))?;
+    if signed.register != *address {
+        return Err(Error::ContentError(
+            "Entry was signed for another Register".to_string(),
+        ));
+    }
+
+    let signed_bytes = serialise_signed_content(&signed.register, &signed.entry)?;
+    signed
+        .writer
+        .verify(&signed.signature, &signed_bytes)
+        .map_err(|err| {
+            Error::ContentError(format!("Invalid signature found on entry: {:?}", err))
+        })?;
+
+    Ok((Url::from_url(&signed.entry)?, signed.writer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::test_helpers::new_safe_instance, retry_loop_for_pattern, DEFAULT_XORURL_BASE,
+    };
+    use anyhow::{anyhow, Result};
+    use safe_network::{types::Keypair, url::Scope};
+    use xor_name::XorName;
+
+    fn register_address() -> Result<RegisterAddress> {
+        let address = Url::from_xorurl(&Url::encode_register(
+            XorName::random(),
+            25_000,
+            Scope::Public,
+            ContentType::Raw,
+            DEFAULT_XORURL_BASE,
+        )?)?
+        .register_address()?;
+        Ok(address)
+    }
+
+    #[test]
+    fn test_register_verify_signed_entry() -> Result<()> {
+        let keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        let address = register_address()?;
+        let entry = "safe://signed-entry".to_string();
+        let signature = keypair.sign(&serialise_signed_content(&address, &entry)?);
+        let mut signed = SignedEntry {
+            register: address,
+            entry,
+            writer: keypair.public_key(),
+            signature,
+        };
+
+        let content = rmp_serde::to_vec_named(&signed)?;
+        let (entry, writer) = verify_signed_entry(&content, &address)?;
+        assert_eq!(entry, Url::from_url("safe://signed-entry")?);
+        assert_eq!(writer, keypair.public_key());
+
+        // replayed onto another Register
+        assert!(verify_signed_entry(&content, &register_address()?).is_err());
+
+        // tampered with
+        signed.entry = "safe://tampered-entry".to_string();
+        let content = rmp_serde::to_vec_named(&signed)?;
+        assert!(verify_signed_entry(&content, &address).is_err());
+
+        assert!(verify_signed_entry(b"garbage", &address).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_read_verified() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let signed_entry = Url::from_url("safe://signed")?;
+        let signed_hash = safe
+            .register_write_signed(&xorurl, signed_entry.clone(), BTreeSet::new())
+            .await?;
+        let unsigned_hash = safe
+            .write_to_register(&xorurl, Url::from_url("safe://unsigned")?, BTreeSet::new())
+            .await?;
+
+        let entries =
+            retry_loop_for_pattern!(safe.register_read_verified(&xorurl), Ok(v) if v.len() == 2)?;
+        for verified in entries {
+            if verified.hash == signed_hash {
+                assert_eq!(verified.entry, signed_entry);
+                assert_eq!(
+                    verified.writer,
+                    EntryWriter::Verified(safe.signer_public_key()?)
+                );
+            } else if verified.hash == unsigned_hash {
+                assert_eq!(verified.writer, EntryWriter::Unsigned);
+            } else {
+                return Err(anyhow!("Unexpected entry: {:?}", verified));
+            }
+        }
+
+        Ok(())
+    }
+}
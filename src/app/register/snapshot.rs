@@ -0,0 +1,192 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{Entry, EntryHash, RegisterHistory};
+use crate::{ContentType, DataType, Error, Result, Safe, Url, UrlAddressExt};
+use bytes::Bytes;
+use log::debug;
+use safe_network::types::BytesAddress;
+use std::collections::{BTreeMap, BTreeSet};
+
+// Path of the links to the Blobs holding snapshots, which tells them apart
+// from any other entry linking to a Blob without reading the Blob
+const SNAPSHOT_PATH: &str = "/sn-api-snapshot";
+
+/// The history of a Register since its latest snapshots, see `Safe::register_history_compacted`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactedHistory {
+    /// The state held by each of the latest snapshots, keyed by the hash of its entry, i.e.
+    /// the snapshots reached walking back from the current entries. There is more than one
+    /// when the history forked around snapshots, e.g. when they were written concurrently,
+    /// and none when no snapshot was ever written to the Register.
+    pub snapshots: BTreeMap<EntryHash, Bytes>,
+    /// The entries written since the latest snapshots, including the snapshot entries,
+    /// or the whole history if no snapshot was ever written
+    pub history: RegisterHistory,
+}
+
+impl Safe {
+    /// # Write a snapshot to a Register
+    ///
+    /// A snapshot holds the state the history of the Register amounts to so far, e.g. the
+    /// state built by applying the operations of a Register used as an op-log, as encoded by
+    /// the caller. It's stored in a Blob, with the same scope as the Register, and the entry
+    /// written links to such Blob, superseding all the current entries of the Register, thus
+    /// readers can start from the snapshot with `register_history_compacted` rather than
+    /// replaying the whole history. The history remains in the Register though.
+    pub async fn register_write_snapshot(&self, url: &str, state: Bytes) -> Result<EntryHash> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let blob_address = if safe_url.register_address()?.is_public() {
+            BytesAddress::Public(self.safe_client.store_bytes(state, false).await?)
+        } else {
+            BytesAddress::Private(self.safe_client.store_private_bytes(state).await?)
+        };
+
+        let mut snapshot = Url::from_xorurl(&Url::encode_bytes(
+            blob_address,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?)?;
+        snapshot.set_path(SNAPSHOT_PATH);
+
+        let parents = match self.fetch_register_entries(&safe_url).await {
+            Ok(entries) => entries.into_iter().map(|(hash, _)| hash).collect(),
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+        debug!(
+            "Writing snapshot to Register at {}, superseding {} entries",
+            safe_url,
+            parents.len()
+        );
+        self.write_to_register(&safe_url.to_string(), snapshot, parents)
+            .await
+    }
+
+    /// # Read the history of a Register since its latest snapshots
+    ///
+    /// As `register_history`, but the ancestry of the current entries is walked back only
+    /// as far as the latest snapshots written with `register_write_snapshot`, whose state is
+    /// returned together with the entries written since, thus the older history doesn't need
+    /// to be processed. Snapshots written concurrently are all returned, to be merged by the
+    /// caller. Note the Register is still fetched as a whole.
+    pub async fn register_history_compacted(&self, url: &str) -> Result<CompactedHistory> {
+        let history = self
+            .register_history(url)
+            .await?
+            .until(|_, node| is_snapshot(&node.entry));
+
+        let mut snapshots = BTreeMap::new();
+        for (hash, node) in history.nodes() {
+            if is_snapshot(&node.entry) {
+                let mut blob_url = node.entry.clone();
+                blob_url.set_path("");
+                let state = self.fetch_public_data(&blob_url, None).await?;
+                let _ = snapshots.insert(*hash, state);
+            }
+        }
+
+        Ok(CompactedHistory { snapshots, history })
+    }
+}
+
+// Whether the Register entry links to a Blob holding a snapshot
+fn is_snapshot(entry: &Entry) -> bool {
+    entry.data_type() == DataType::Bytes && entry.path() == SNAPSHOT_PATH
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::{
+            register::history::{history_from_dag, register_dag},
+            test_helpers::new_safe_instance,
+        },
+        retry_loop_for_pattern, XorUrlBase,
+    };
+    use anyhow::Result;
+
+    #[test]
+    fn test_compacted_history_from_register() -> Result<()> {
+        use safe_network::types::{register::Register, Keypair};
+        use xor_name::XorName;
+
+        // op-1 <- snapshot <- op-2, as read from a Register fetched from the network
+        let owner = Keypair::new_ed25519(&mut rand::thread_rng()).public_key();
+        let mut register = Register::new_public(owner, XorName::random(), 25_000, None);
+        let (first, _) = register.write(Url::from_url("safe://op-1")?, BTreeSet::new())?;
+        let mut snapshot_url = Url::from_xorurl(&Url::encode_bytes(
+            BytesAddress::Public(XorName::random()),
+            ContentType::Raw,
+            XorUrlBase::Base32z,
+        )?)?;
+        snapshot_url.set_path(SNAPSHOT_PATH);
+        let (snapshot, _) =
+            register.write(snapshot_url.clone(), vec![first].into_iter().collect())?;
+        let (after, _) = register.write(
+            Url::from_url("safe://op-2")?,
+            vec![snapshot].into_iter().collect(),
+        )?;
+
+        let history = history_from_dag(register_dag(&register)?);
+        assert_eq!(history.nodes().len(), 3);
+        let compacted = history.until(|_, node| is_snapshot(&node.entry));
+        assert_eq!(
+            compacted.nodes().keys().copied().collect::<BTreeSet<_>>(),
+            vec![snapshot, after].into_iter().collect()
+        );
+        assert!(is_snapshot(&compacted.get(&snapshot).unwrap().entry));
+        assert!(!is_snapshot(&compacted.get(&after).unwrap().entry));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_snapshot() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+
+        let first = safe
+            .write_to_register(&xorurl, Url::from_url("safe://op-1")?, BTreeSet::new())
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(v) if v.len() == 1)?;
+
+        // no snapshot written yet, the whole history is returned
+        let compacted = safe.register_history_compacted(&xorurl).await?;
+        assert!(compacted.snapshots.is_empty());
+        assert_eq!(compacted.history.tips(), vec![first].into_iter().collect());
+
+        let snapshot = safe
+            .register_write_snapshot(&xorurl, Bytes::from("state-1"))
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(v) if v.iter().any(|(hash, _)| *hash == snapshot))?;
+        let after = safe
+            .write_to_register(
+                &xorurl,
+                Url::from_url("safe://op-2")?,
+                vec![snapshot].into_iter().collect(),
+            )
+            .await?;
+
+        let compacted = retry_loop_for_pattern!(safe.register_history_compacted(&xorurl), Ok(c) if c.history.nodes().contains_key(&after))?;
+        assert_eq!(
+            compacted.snapshots,
+            vec![(snapshot, Bytes::from("state-1"))]
+                .into_iter()
+                .collect()
+        );
+        // the entries before the snapshot are not walked
+        assert_eq!(compacted.history.nodes().len(), 2);
+        assert!(compacted.history.get(&first).is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{is_tombstone, EntryEnvelope, EntryHash, QuarantineKind};
+use crate::{Error, Result, Safe, Url, XorName};
+use chrono::DateTime;
+use log::debug;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+};
+
+/// Window of time of the entries to read, as seconds since the Unix epoch,
+/// where the start is inclusive and the end exclusive. Either of them can be omitted.
+pub type TimeRange = Option<(Option<i64>, Option<i64>)>;
+
+/// Order of the entries returned by `register_read_sorted`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    /// By the time set on the entries' envelopes, oldest first, with entries
+    /// written within the same second in the order this instance saw them
+    Timestamp,
+}
+
+// Envelopes keyed by entry hash, with their time and the order they were indexed in
+type IndexedEnvelopes = BTreeMap<EntryHash, (i64, usize, EntryEnvelope)>;
+
+// Local index of the envelopes seen on each Register, keyed by its name and type tag.
+// Envelopes are immutable, thus once seen they don't need to be fetched again, and
+// entries superseded since they were seen can still be returned from the index.
+// Clones of an instance share the same index.
+#[derive(Clone, Default)]
+pub(crate) struct EnvelopeIndex {
+    registers: Arc<Mutex<BTreeMap<(XorName, u64), IndexedEnvelopes>>>,
+}
+
+impl EnvelopeIndex {
+    pub(crate) fn insert(&self, url: &Url, hash: EntryHash, envelope: EntryEnvelope) {
+        let timestamp = match DateTime::parse_from_rfc3339(&envelope.timestamp) {
+            Ok(datetime) => datetime.timestamp(),
+            Err(err) => {
+                debug!("Not indexing envelope with invalid timestamp: {}", err);
+                return;
+            }
+        };
+
+        if let Ok(mut registers) = self.registers.lock() {
+            let envelopes = registers
+                .entry((url.xorname(), url.type_tag()))
+                .or_default();
+            let seq = envelopes.len();
+            let _ = envelopes.entry(hash).or_insert((timestamp, seq, envelope));
+        }
+    }
+
+    // Approximate bytes held by the index, i.e. the size of the envelopes' payloads
+    pub(crate) fn bytes(&self) -> usize {
+        self.registers.lock().map_or(0, |registers| {
+            registers
+                .values()
+                .flat_map(|envelopes| envelopes.values())
+                .map(|(_, _, envelope)| envelope.payload.len() + envelope.content_type.len())
+                .sum()
+        })
+    }
+
+    // Time of the envelope indexed for an entry, as seconds since the Unix epoch
+    pub(crate) fn timestamp(&self, url: &Url, hash: &EntryHash) -> Option<i64> {
+        self.registers.lock().ok().and_then(|registers| {
+            registers
+                .get(&(url.xorname(), url.type_tag()))
+                .and_then(|envelopes| envelopes.get(hash))
+                .map(|(timestamp, _, _)| *timestamp)
+        })
+    }
+
+    fn contains(&self, url: &Url, hash: &EntryHash) -> bool {
+        self.registers.lock().map_or(false, |registers| {
+            registers
+                .get(&(url.xorname(), url.type_tag()))
+                .map_or(false, |envelopes| envelopes.contains_key(hash))
+        })
+    }
+
+    fn sorted(
+        &self,
+        url: &Url,
+        range: TimeRange,
+        limit: Option<usize>,
+    ) -> Vec<(EntryHash, EntryEnvelope)> {
+        let (start, end) = range.unwrap_or((None, None));
+        let mut sorted: Vec<(i64, usize, EntryHash, EntryEnvelope)> = match self.registers.lock() {
+            Ok(registers) => registers
+                .get(&(url.xorname(), url.type_tag()))
+                .map(|envelopes| {
+                    envelopes
+                        .iter()
+                        .filter(|(_, (timestamp, _, _))| {
+                            start.map_or(true, |start| *timestamp >= start)
+                                && end.map_or(true, |end| *timestamp < end)
+                        })
+                        .map(|(hash, (timestamp, seq, envelope))| {
+                            (*timestamp, *seq, *hash, envelope.clone())
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            Err(_) => vec![],
+        };
+        sorted.sort_by_key(|(timestamp, seq, _, _)| (*timestamp, *seq));
+
+        sorted
+            .into_iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|(_, _, hash, envelope)| (hash, envelope))
+            .collect()
+    }
+}
+
+impl Safe {
+    /// # Read the entries of a Register sorted by their envelopes' time
+    ///
+    /// Returns the entries within the time range, in the order requested, up to `limit`
+    /// entries. Entries which are not envelopes are skipped, those which can't be decoded
+    /// as such are quarantined, see `register_quarantined`.
+    ///
+    /// The Register's latest entries are merged into an index kept locally, together
+    /// with the envelopes written with this instance, and the entries are read from such
+    /// index. Entries superseded before this instance ever saw them are thus not returned.
+    pub async fn register_read_sorted(
+        &self,
+        url: &str,
+        sort_by: SortBy,
+        range: TimeRange,
+        limit: Option<usize>,
+    ) -> Result<Vec<(EntryHash, EntryEnvelope)>> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let entries = match self.fetch_register_entries(&safe_url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        for (hash, entry) in entries.into_iter() {
+            if self.envelope_index.contains(&safe_url, &hash) || is_tombstone(&entry) {
+                continue;
+            }
+            match self.fetch_envelope(&entry).await {
+                Ok(envelope) => self.envelope_index.insert(&safe_url, hash, envelope),
+                Err(err) => {
+                    if let Err(err) =
+                        self.quarantine_entry(&safe_url, hash, QuarantineKind::Envelope, err)
+                    {
+                        debug!("Skipping entry {} of {}: {}", entry, safe_url, err)
+                    }
+                }
+            }
+        }
+
+        match sort_by {
+            SortBy::Timestamp => Ok(self.envelope_index.sorted(&safe_url, range, limit)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern};
+    use anyhow::Result;
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn test_register_read_sorted() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let mut parents = BTreeSet::new();
+        for message in ["first", "second", "third"].iter() {
+            let hash = safe
+                .register_write_envelope(&xorurl, "text/plain", Bytes::from(*message), parents)
+                .await?;
+            parents = vec![hash].into_iter().collect();
+        }
+
+        let sorted = retry_loop_for_pattern!(
+            safe.register_read_sorted(&xorurl, SortBy::Timestamp, None, None),
+            Ok(sorted) if sorted.len() == 3
+        )?;
+        let payloads: Vec<&[u8]> = sorted
+            .iter()
+            .map(|(_, envelope)| envelope.payload.as_slice())
+            .collect();
+        assert_eq!(
+            payloads,
+            vec![b"first".as_ref(), b"second".as_ref(), b"third".as_ref()]
+        );
+
+        let limited = safe
+            .register_read_sorted(&xorurl, SortBy::Timestamp, None, Some(2))
+            .await?;
+        assert_eq!(limited.len(), 2);
+
+        let none = safe
+            .register_read_sorted(&xorurl, SortBy::Timestamp, Some((Some(0), Some(1))), None)
+            .await?;
+        assert!(none.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{Entry, EntryHash};
+use crate::{Error, Result, Safe, UrlAddressExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// Size of a hash of an entry, which identifies it and its parents
+const ENTRY_HASH_LEN: u64 = 32;
+
+/// Statistics of a Register, see `Safe::register_stats`, serialised as described by
+/// `schemas::REGISTER_STATS_SCHEMA`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RegisterStats {
+    /// Number of entries written to the Register
+    pub entries: usize,
+    /// Number of current entries, i.e. branches, which is one unless it was written
+    /// to concurrently, or its entries were written without superseding each other
+    pub tips: usize,
+    /// Estimated number of bytes stored for the entries, excluding the content they link to
+    pub estimated_bytes: u64,
+    /// Whether the statistics cover all the entries ever written, or only the current ones,
+    /// in which case `entries` and `estimated_bytes` are lower bounds
+    pub complete: bool,
+}
+
+impl Safe {
+    /// # Obtain statistics of a Register
+    ///
+    /// The statistics are computed from the entries and their parents, not from the
+    /// content the entries link to, which is not fetched. Where the history of the Register
+    /// can't be retrieved, as on the network for now, they only cover its current entries.
+    pub async fn register_stats(&self, url: &str) -> Result<RegisterStats> {
+        let (mut safe_url, _) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_content_version(None);
+        let _ = safe_url.register_address()?;
+
+        match self.register_history(&safe_url.to_string()).await {
+            Ok(history) => Ok(RegisterStats {
+                entries: history.nodes().len(),
+                tips: history.tips().len(),
+                estimated_bytes: history
+                    .nodes()
+                    .values()
+                    .map(|node| estimated_entry_size(&node.entry, &node.parents))
+                    .sum(),
+                complete: true,
+            }),
+            Err(Error::NotImplementedError(_)) => {
+                let tips = match self.fetch_register_entries(&safe_url).await {
+                    Ok(entries) => entries,
+                    Err(Error::EmptyContent(_)) => BTreeSet::new(),
+                    Err(err) => return Err(err),
+                };
+                Ok(RegisterStats {
+                    entries: tips.len(),
+                    tips: tips.len(),
+                    estimated_bytes: tips
+                        .iter()
+                        .map(|(_, entry)| estimated_entry_size(entry, &BTreeSet::new()))
+                        .sum(),
+                    complete: false,
+                })
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+// Estimated size of an entry as stored: its content, its hash and the hashes of its parents
+fn estimated_entry_size(entry: &Entry, parents: &BTreeSet<EntryHash>) -> u64 {
+    entry.to_string().len() as u64 + ENTRY_HASH_LEN * (1 + parents.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern, Url};
+    use anyhow::Result;
+
+    #[test]
+    fn test_estimated_entry_size() -> Result<()> {
+        let entry = Url::from_url("safe://entry")?;
+        let parents = vec![[0; 32], [1; 32]].into_iter().collect();
+        assert_eq!(
+            estimated_entry_size(&entry, &parents),
+            entry.to_string().len() as u64 + 96
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_stats() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let empty = retry_loop!(safe.register_stats(&xorurl));
+        assert_eq!(empty.entries, 0);
+        assert_eq!(empty.tips, 0);
+        assert_eq!(empty.estimated_bytes, 0);
+
+        for i in 0..2 {
+            let entry = Url::from_url(&format!("safe://branch-{}", i))?;
+            let _ = safe
+                .write_to_register(&xorurl, entry, BTreeSet::new())
+                .await?;
+        }
+        let stats =
+            retry_loop_for_pattern!(safe.register_stats(&xorurl), Ok(stats) if stats.tips == 2)?;
+        assert_eq!(stats.entries, 2);
+        assert!(stats.estimated_bytes > 2 * ENTRY_HASH_LEN);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{is_tombstone, Entry, EntryHash, QuarantineKind};
+use crate::{Error, Result, Safe, Url, UrlAddressExt, XorName};
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::BTreeSet, marker::PhantomData};
+
+/// A Register whose entries are values of type `T`.
+///
+/// Each value is serialised with MessagePack and stored in a Blob, with the same scope as
+/// the Register's, which the Register entry links to. Values which can't be decoded as `T`,
+/// e.g. written by a different version of an application, fail with `Error::DecodeError`
+/// when read with `read_entry`, and are quarantined and left out by `read`, see
+/// `Safe::register_quarantined`, while failures to fetch them are surfaced as they are.
+#[derive(Clone)]
+pub struct TypedRegister<T> {
+    safe: Safe,
+    url: Url,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> TypedRegister<T> {
+    /// Create a Register on the network to hold values of type `T`
+    pub async fn create(
+        safe: &Safe,
+        name: Option<XorName>,
+        type_tag: u64,
+        private: bool,
+    ) -> Result<Self> {
+        let xorurl = safe.register_create(name, type_tag, private).await?;
+        Self::open(safe, &xorurl).await
+    }
+
+    /// Use the existing Register at the given URL as a Register of values of type `T`
+    pub async fn open(safe: &Safe, url: &str) -> Result<Self> {
+        let (url, _) = safe.parse_and_resolve_url(url).await?;
+        let _ = url.register_address()?;
+
+        Ok(Self {
+            safe: safe.clone(),
+            url,
+            _value: PhantomData,
+        })
+    }
+
+    /// URL of the underlying Register
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Write a value to the Register, superseding the entries with the given hashes
+    pub async fn write(&self, value: &T, parents: BTreeSet<EntryHash>) -> Result<EntryHash> {
+        let serialised_value = rmp_serde::to_vec_named(value).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise Register value: {:?}", err))
+        })?;
+
+        let bytes = Bytes::from(serialised_value);
+        let xorurl = if self.url.register_address()?.is_public() {
+            self.safe.store_public_bytes(bytes, None, false).await?
+        } else {
+            self.safe.store_private_bytes(bytes, None).await?
+        };
+
+        self.safe
+            .write_to_register(&self.url.to_string(), Url::from_xorurl(&xorurl)?, parents)
+            .await
+    }
+
+    /// Read the current values of the Register, with the hashes of their entries
+    pub async fn read(&self) -> Result<Vec<(EntryHash, T)>> {
+        let entries = match self.safe.fetch_register_entries(&self.url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut values = Vec::with_capacity(entries.len());
+        for (hash, entry) in entries {
+            if is_tombstone(&entry) {
+                continue;
+            }
+            match self.fetch_value(hash, &entry).await {
+                Ok(value) => values.push((hash, value)),
+                Err(err) => {
+                    self.safe
+                        .quarantine_entry(&self.url, hash, QuarantineKind::TypedValue, err)?
+                }
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Read the value of the entry with the given hash
+    pub async fn read_entry(&self, hash: EntryHash) -> Result<T> {
+        let entry = self.safe.fetch_register_entry(&self.url, hash).await?;
+        self.fetch_value(hash, &entry).await
+    }
+
+    // Private helper to fetch and decode the value an entry links to
+    async fn fetch_value(&self, hash: EntryHash, entry: &Entry) -> Result<T> {
+        let serialised_value = self.safe.fetch_public_data(entry, None).await?;
+        decode_value(hash, &serialised_value)
+    }
+}
+
+fn decode_value<T: DeserializeOwned>(hash: EntryHash, serialised_value: &[u8]) -> Result<T> {
+    rmp_serde::from_slice(serialised_value).map_err(|err| {
+        Error::DecodeError(format!(
+            "Couldn't decode value of Register entry {}: {:?}",
+            hex::encode(hash),
+            err
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_helpers::new_safe_instance;
+    use anyhow::{anyhow, Result};
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Note {
+        title: String,
+        stars: u8,
+    }
+
+    #[test]
+    fn test_typed_register_decode_error() -> Result<()> {
+        let serialised = rmp_serde::to_vec_named(&"not a note")?;
+        match decode_value::<Note>([0; 32], &serialised) {
+            Err(Error::DecodeError(msg)) => {
+                assert!(msg.contains(&hex::encode([0; 32])));
+                Ok(())
+            }
+            other => Err(anyhow!("Unexpected result: {:?}", other)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_typed_register_write_and_read() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let register = TypedRegister::<Note>::create(&safe, None, 25_000, true).await?;
+        assert!(register.read().await?.is_empty());
+
+        let note = Note {
+            title: "groceries".to_string(),
+            stars: 3,
+        };
+        let first = register.write(&note, BTreeSet::new()).await?;
+        let updated = Note { stars: 5, ..note };
+        let second = register
+            .write(&updated, vec![first].into_iter().collect())
+            .await?;
+
+        assert_eq!(register.read().await?, vec![(second, updated)]);
+        assert_eq!(register.read_entry(first).await?.stars, 3);
+
+        // the same Register read as a different type fails to decode its values
+        let other = TypedRegister::<Vec<u64>>::open(&safe, &register.url().to_string()).await?;
+        assert!(matches!(
+            other.read_entry(second).await,
+            Err(Error::DecodeError(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,395 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{Entry, EntryHash};
+use crate::{
+    app::{
+        events::SafeEvent,
+        helpers::{gen_timestamp_secs, glob_match},
+    },
+    Error, PublicKey, Result, Safe, Url, UrlAddressExt,
+};
+use bytes::Bytes;
+use futures::{stream, Stream};
+use log::debug;
+use safe_network::types::Signature;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    time::Duration,
+};
+
+// Default interval at which a watched Register is polled for new entries
+const DEFAULT_WATCH_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Payload of a Register entry wrapped together with its content type,
+/// its author, and the time it was written. Envelopes are signed by their
+/// author, envelopes with an invalid signature are never returned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntryEnvelope {
+    pub content_type: String,
+    pub author: PublicKey,
+    /// Time the entry was written, in RFC3339 format
+    pub timestamp: String,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SignedEnvelope {
+    envelope: EntryEnvelope,
+    signature: Signature,
+}
+
+/// Filter of the entries a `RegisterWatch` wakes up for. An entry matches if its
+/// envelope matches any of the content types, and any of the authors, set on the filter.
+/// A filter without content types, or without authors, doesn't filter on them.
+#[derive(Debug, Clone, Default)]
+pub struct WatchFilter {
+    content_types: Vec<String>,
+    authors: Vec<PublicKey>,
+}
+
+impl WatchFilter {
+    /// A filter matching all entries
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match the entries with the given content type, which can be
+    /// a pattern using `*` as a wildcard, e.g. `text/*`
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_types.push(content_type.to_lowercase());
+        self
+    }
+
+    /// Match the entries written by the given author
+    pub fn author(mut self, author: PublicKey) -> Self {
+        self.authors.push(author);
+        self
+    }
+
+    /// Check if an entry's envelope matches the filter
+    pub fn matches(&self, envelope: &EntryEnvelope) -> bool {
+        let content_type = envelope.content_type.to_lowercase();
+        let content_type_matches = self.content_types.is_empty()
+            || self
+                .content_types
+                .iter()
+                .any(|pattern| glob_match(pattern, &content_type));
+        let author_matches = self.authors.is_empty() || self.authors.contains(&envelope.author);
+
+        content_type_matches && author_matches
+    }
+}
+
+/// A watch on a Register, polling it for new entries and only waking
+/// up the caller when entries matching its filter arrive.
+///
+/// Only the latest entries of the Register are seen on each poll, thus entries
+/// superseded in between two polls are not seen by the watch.
+pub struct RegisterWatch {
+    safe: Safe,
+    url: Url,
+    filter: WatchFilter,
+    poll_interval: Duration,
+    seen: BTreeSet<EntryHash>,
+}
+
+impl RegisterWatch {
+    /// Set the interval at which the Register is polled for new entries
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Wait until new entries matching the filter arrive, returning them. Entries
+    /// which are not envelopes, or whose signature is not valid, are skipped.
+    pub async fn next(&mut self) -> Result<Vec<(EntryHash, EntryEnvelope)>> {
+        loop {
+            let entries = match self.safe.fetch_register_entries(&self.url).await {
+                Ok(entries) => entries,
+                Err(Error::EmptyContent(_)) => BTreeSet::new(),
+                Err(err) => return Err(err),
+            };
+
+            let mut matching = vec![];
+            for (hash, entry) in entries.into_iter() {
+                if !self.seen.insert(hash) {
+                    continue;
+                }
+
+                match self.safe.fetch_envelope(&entry).await {
+                    Ok(envelope) if self.filter.matches(&envelope) => {
+                        matching.push((hash, envelope))
+                    }
+                    Ok(_) => {}
+                    Err(err) => debug!("Skipping entry {} of {}: {}", entry, self.url, err),
+                }
+            }
+
+            if !matching.is_empty() {
+                return Ok(matching);
+            }
+            self.safe.runtime().sleep(self.poll_interval).await;
+        }
+    }
+}
+
+impl Safe {
+    /// # Write an entry wrapped in an envelope to a Register
+    ///
+    /// The payload is stored together with its content type, the public key of the
+    /// signer of this instance as the author, and the current time, signed with
+    /// such signer, see `set_signer`. The envelope is stored in a Blob with the same
+    /// scope as the Register.
+    pub async fn register_write_envelope(
+        &self,
+        url: &str,
+        content_type: &str,
+        payload: Bytes,
+        parents: BTreeSet<EntryHash>,
+    ) -> Result<EntryHash> {
+        let signer = self.signer()?;
+        let envelope = EntryEnvelope {
+            content_type: content_type.to_string(),
+            author: signer.public_key(),
+            timestamp: gen_timestamp_secs(),
+            payload: payload.to_vec(),
+        };
+        let signature = signer.sign(&serialise_envelope(&envelope)?).await?;
+        let serialised_envelope = rmp_serde::to_vec_named(&SignedEnvelope {
+            envelope: envelope.clone(),
+            signature,
+        })
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise envelope: {:?}", err)))?;
+
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        let bytes = Bytes::from(serialised_envelope);
+        let envelope_xorurl = if safe_url.register_address()?.is_public() {
+            self.store_public_bytes(bytes, None, false).await?
+        } else {
+            self.store_private_bytes(bytes, None).await?
+        };
+        let hash = self
+            .write_to_register(url, Url::from_xorurl(&envelope_xorurl)?, parents)
+            .await?;
+
+        // Keep it in the local index so it's returned by sorted reads even once superseded
+        self.envelope_index.insert(&safe_url, hash, envelope);
+
+        Ok(hash)
+    }
+
+    /// Watch a Register for new entries matching a filter, see `RegisterWatch`.
+    /// The entries the Register currently has are not returned by the watch.
+    pub async fn register_watch(&self, url: &str, filter: WatchFilter) -> Result<RegisterWatch> {
+        let (url, _) = self.parse_and_resolve_url(url).await?;
+        let seen = self.register_entry_hashes(&url).await?;
+
+        Ok(RegisterWatch {
+            safe: self.clone(),
+            url,
+            filter,
+            poll_interval: DEFAULT_WATCH_POLL_INTERVAL,
+            seen,
+        })
+    }
+
+    /// # Subscribe to the new entries written to a Register
+    ///
+    /// Returns a stream which yields each new entry once, as they are written by any
+    /// client, polling the Register at the given interval, or every second if not provided.
+    /// The entries the Register currently has are not yielded. Only the latest entries are
+    /// seen on each poll, thus entries superseded in between two polls are not yielded.
+    /// Failures to poll the Register are yielded as errors, without ending the stream.
+    pub async fn register_subscribe(
+        &self,
+        url: &str,
+        poll_interval: Option<Duration>,
+    ) -> Result<impl Stream<Item = Result<(EntryHash, Entry)>>> {
+        let (url, _) = self.parse_and_resolve_url(url).await?;
+        let seen = self.register_entry_hashes(&url).await?;
+        let poll_interval = poll_interval.unwrap_or(DEFAULT_WATCH_POLL_INTERVAL);
+
+        let state = (self.clone(), url, seen, VecDeque::new());
+        Ok(stream::unfold(
+            state,
+            move |(safe, url, mut seen, mut pending)| async move {
+                loop {
+                    if let Some((hash, entry)) = pending.pop_front() {
+                        safe.emit_event(SafeEvent::SubscriptionUpdate {
+                            url: url.to_string(),
+                            entry_hash: hex::encode(hash),
+                            timestamp: gen_timestamp_secs(),
+                        });
+                        return Some((Ok((hash, entry)), (safe, url, seen, pending)));
+                    }
+
+                    safe.runtime().sleep(poll_interval).await;
+                    match safe.fetch_register_entries(&url).await {
+                        Ok(entries) => pending
+                            .extend(entries.into_iter().filter(|(hash, _)| seen.insert(*hash))),
+                        Err(Error::EmptyContent(_)) => {}
+                        Err(err) => return Some((Err(err), (safe, url, seen, pending))),
+                    }
+                }
+            },
+        ))
+    }
+
+    // Private helper to obtain the hashes of the entries a Register currently has
+    async fn register_entry_hashes(&self, url: &Url) -> Result<BTreeSet<EntryHash>> {
+        match self.fetch_register_entries(url).await {
+            Ok(entries) => Ok(entries.into_iter().map(|(hash, _)| hash).collect()),
+            Err(Error::EmptyContent(_)) => Ok(BTreeSet::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Fetch the envelope a Register entry points to, verifying its author's signature
+    pub async fn fetch_envelope(&self, entry: &Url) -> Result<EntryEnvelope> {
+        let serialised_envelope = self.fetch_public_data(entry, None).await?;
+        let signed: SignedEnvelope = rmp_serde::from_slice(&serialised_envelope)
+            .map_err(|err| Error::ContentError(format!("Couldn't parse envelope: {:?}", err)))?;
+
+        let signed_bytes = serialise_envelope(&signed.envelope)?;
+        signed
+            .envelope
+            .author
+            .verify(&signed.signature, &signed_bytes)
+            .map_err(|err| {
+                Error::ContentError(format!("Invalid signature found on envelope: {:?}", err))
+            })?;
+
+        Ok(signed.envelope)
+    }
+}
+
+fn serialise_envelope(envelope: &EntryEnvelope) -> Result<Vec<u8>> {
+    rmp_serde::to_vec_named(envelope)
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise envelope: {:?}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop};
+    use anyhow::{anyhow, Result};
+    use futures::StreamExt;
+    use safe_network::types::Keypair;
+
+    #[test]
+    fn test_watch_filter_matches() {
+        let mut rng = rand::thread_rng();
+        let author = Keypair::new_ed25519(&mut rng).public_key();
+        let other_author = Keypair::new_ed25519(&mut rng).public_key();
+        let envelope = EntryEnvelope {
+            content_type: "text/Markdown".to_string(),
+            author,
+            timestamp: gen_timestamp_secs(),
+            payload: vec![],
+        };
+
+        assert!(WatchFilter::new().matches(&envelope));
+        assert!(WatchFilter::new().content_type("text/*").matches(&envelope));
+        assert!(WatchFilter::new()
+            .content_type("application/json")
+            .content_type("text/markdown")
+            .author(author)
+            .matches(&envelope));
+        assert!(!WatchFilter::new()
+            .content_type("application/json")
+            .matches(&envelope));
+        assert!(!WatchFilter::new()
+            .content_type("text/*")
+            .author(other_author)
+            .matches(&envelope));
+    }
+
+    #[tokio::test]
+    async fn test_register_watch_with_filter() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let filter = WatchFilter::new().content_type("application/json");
+        let mut watch = safe.register_watch(&xorurl, filter).await?;
+        watch.set_poll_interval(Duration::from_millis(200));
+
+        let _ = safe
+            .register_write_envelope(
+                &xorurl,
+                "text/plain",
+                Bytes::from("ignored"),
+                BTreeSet::new(),
+            )
+            .await?;
+        let hash = safe
+            .register_write_envelope(
+                &xorurl,
+                "application/json",
+                Bytes::from("{}"),
+                BTreeSet::new(),
+            )
+            .await?;
+
+        let entries = watch.next().await?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, hash);
+        assert_eq!(entries[0].1.payload, b"{}".to_vec());
+        assert_eq!(entries[0].1.author, safe.get_my_keypair()?.public_key());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_subscribe() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, false).await?;
+        let _ = retry_loop!(safe.register_read(&xorurl));
+
+        let mut entries = Box::pin(
+            safe.register_subscribe(&xorurl, Some(Duration::from_millis(200)))
+                .await?,
+        );
+
+        let entry = Url::from_xorurl(
+            &safe
+                .store_public_bytes(Bytes::from("new entry"), None, false)
+                .await?,
+        )?;
+        let hash = safe
+            .write_to_register(&xorurl, entry.clone(), BTreeSet::new())
+            .await?;
+
+        let (received_hash, received_entry) = entries
+            .next()
+            .await
+            .ok_or_else(|| anyhow!("Subscription ended"))??;
+        assert_eq!(received_hash, hash);
+        assert_eq!(received_entry, entry);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_write_envelope_private() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe.register_create(None, 25_000, true).await?;
+        let _ = safe
+            .register_write_envelope(&xorurl, "text/plain", Bytes::from("hi"), BTreeSet::new())
+            .await?;
+
+        let entries = retry_loop!(safe.register_read(&xorurl));
+        for (_, entry) in entries.iter() {
+            assert!(!entry.bytes_address()?.is_public());
+        }
+
+        Ok(())
+    }
+}
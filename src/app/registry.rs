@@ -0,0 +1,297 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{encryption::keyed_hash, helpers::gen_timestamp_secs};
+use crate::{ContentType, Error, PublicKey, Result, Safe, Scope, Url, XorName};
+use bytes::Bytes;
+use log::{debug, info};
+use safe_network::types::Signature;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// Type tag to use for the Registers holding the releases of each package
+const REGISTRY_TYPE_TAG: u64 = 2_600;
+
+// Context used to derive the location of the Register of a package from its name
+const REGISTRY_CONTEXT: &[u8] = b"sn_api-registry";
+
+/// A release of a package, signed by its publisher
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackageRelease {
+    pub name: String,
+    /// Semantic version of the release, e.g. `1.2.3`
+    pub version: String,
+    /// URL of the content of the release
+    pub content: String,
+    /// Public key of the publisher, who signed the release
+    pub publisher: PublicKey,
+    /// Time the release was published, in RFC3339 format
+    pub published_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SignedRelease {
+    release: PackageRelease,
+    signature: Signature,
+}
+
+impl Safe {
+    /// # Publish a release of a package
+    ///
+    /// Releases are stored on a Register at a location derived from the package name,
+    /// which is created upon the first release, thus the package is owned by whom first
+    /// published it, and only its owner can publish further releases of it. Each release
+    /// is signed with the signer of this instance. It fails if the version
+    /// is not a valid semantic version, or if it was already published.
+    pub async fn registry_publish(
+        &self,
+        name: &str,
+        version: &str,
+        content: &str,
+    ) -> Result<PackageRelease> {
+        info!("Publishing release {} of package {}", version, name);
+        let parsed_version = parse_version(version)?;
+        let _ = Safe::parse_url(content)?;
+        let (package_xorname, package_url) = self.registry_package_location(name)?;
+
+        match self.fetch_register_entries(&package_url).await {
+            Ok(_) | Err(Error::EmptyContent(_)) => {}
+            Err(Error::ContentNotFound(_)) => {
+                debug!("Package Register not found, creating it");
+                let _ = self
+                    .register_create(Some(package_xorname), REGISTRY_TYPE_TAG, false)
+                    .await?;
+            }
+            Err(err) => return Err(err),
+        }
+        if self
+            .registry_releases(name)
+            .await?
+            .iter()
+            .any(|release| parse_version(&release.version).ok() == Some(parsed_version.clone()))
+        {
+            return Err(Error::EntryExists(format!(
+                "Release {} of package '{}' was already published",
+                version, name
+            )));
+        }
+
+        let signer = self.signer()?;
+        let release = PackageRelease {
+            name: name.to_string(),
+            version: version.to_string(),
+            content: content.to_string(),
+            publisher: signer.public_key(),
+            published_at: gen_timestamp_secs(),
+        };
+        let signature = signer.sign(&serialise_release(&release)?).await?;
+        let serialised_release = rmp_serde::to_vec_named(&SignedRelease {
+            release: release.clone(),
+            signature,
+        })
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise release: {:?}", err)))?;
+        let release_xorurl = self
+            .store_public_bytes(Bytes::from(serialised_release), None, false)
+            .await?;
+
+        // Releases don't supersede each other, thus they are all written without parents
+        let _ = self
+            .write_to_register(
+                &package_url.to_string(),
+                Url::from_xorurl(&release_xorurl)?,
+                BTreeSet::new(),
+            )
+            .await?;
+
+        Ok(release)
+    }
+
+    /// # List the releases of a package
+    ///
+    /// Returns the releases sorted by version, lowest first. Releases whose publisher's
+    /// signature is invalid, or whose name or version don't match the package, are skipped.
+    pub async fn registry_releases(&self, name: &str) -> Result<Vec<PackageRelease>> {
+        let (_, package_url) = self.registry_package_location(name)?;
+        let entries = match self.fetch_register_entries(&package_url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) | Err(Error::ContentNotFound(_)) => BTreeSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut releases = vec![];
+        for (_, entry) in entries.iter() {
+            match self.fetch_release(entry, name).await {
+                Ok(release) => releases.push(release),
+                Err(err) => debug!("Skipping entry {} of package {}: {}", entry, name, err),
+            }
+        }
+
+        releases.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(releases.into_iter().map(|(_, release)| release).collect())
+    }
+
+    /// # Resolve a package specification to a release
+    ///
+    /// The specification is the package name, optionally followed by `@` and a semantic
+    /// version requirement, e.g. `my-package@^1.2`, or `my-package` for its latest release.
+    /// The highest release matching the requirement is returned, with the URL of its content.
+    /// Releases of packages not owned by the expected publisher can be told apart by
+    /// checking the `publisher` of the release returned.
+    pub async fn registry_resolve(&self, spec: &str) -> Result<PackageRelease> {
+        let (name, requirement) = parse_package_spec(spec)?;
+        self.registry_releases(name)
+            .await?
+            .into_iter()
+            .rfind(|release| {
+                parse_version(&release.version)
+                    .map_or(false, |version| requirement.matches(&version))
+            })
+            .ok_or_else(|| {
+                Error::ContentNotFound(format!("No release of package found matching '{}'", spec))
+            })
+    }
+
+    // Private helper to fetch a release and verify its publisher's signature
+    async fn fetch_release(&self, entry: &Url, name: &str) -> Result<(Version, PackageRelease)> {
+        let serialised_release = self.fetch_public_data(entry, None).await?;
+        let signed: SignedRelease = rmp_serde::from_slice(&serialised_release)
+            .map_err(|err| Error::ContentError(format!("Couldn't parse release: {:?}", err)))?;
+
+        let signed_bytes = serialise_release(&signed.release)?;
+        signed
+            .release
+            .publisher
+            .verify(&signed.signature, &signed_bytes)
+            .map_err(|err| {
+                Error::ContentError(format!("Invalid signature found on release: {:?}", err))
+            })?;
+        if signed.release.name != name {
+            return Err(Error::ContentError(format!(
+                "Release is of package '{}'",
+                signed.release.name
+            )));
+        }
+
+        Ok((parse_version(&signed.release.version)?, signed.release))
+    }
+
+    // Private helper to obtain the location of the Register of a package
+    fn registry_package_location(&self, name: &str) -> Result<(XorName, Url)> {
+        if name.is_empty() || name.contains('@') {
+            return Err(Error::InvalidInput(format!(
+                "Invalid package name: '{}'",
+                name
+            )));
+        }
+        let package_xorname = XorName(keyed_hash(REGISTRY_CONTEXT, name.as_bytes()));
+        let package_xorurl = Url::encode_register(
+            package_xorname,
+            REGISTRY_TYPE_TAG,
+            Scope::Public,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?;
+
+        Ok((package_xorname, Url::from_xorurl(&package_xorurl)?))
+    }
+}
+
+fn parse_version(version: &str) -> Result<Version> {
+    Version::parse(version).map_err(|err| {
+        Error::InvalidInput(format!("Invalid semantic version '{}': {}", version, err))
+    })
+}
+
+// Split a package specification into the package name and the version requirement
+fn parse_package_spec(spec: &str) -> Result<(&str, VersionReq)> {
+    match spec.split_once('@') {
+        Some((name, requirement)) => {
+            let requirement = VersionReq::parse(requirement).map_err(|err| {
+                Error::InvalidInput(format!(
+                    "Invalid version requirement '{}': {}",
+                    requirement, err
+                ))
+            })?;
+            Ok((name, requirement))
+        }
+        None => Ok((spec, VersionReq::STAR)),
+    }
+}
+
+fn serialise_release(release: &PackageRelease) -> Result<Vec<u8>> {
+    rmp_serde::to_vec_named(release)
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise release: {:?}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::test_helpers::{new_safe_instance, random_nrs_name},
+        retry_loop_for_pattern,
+    };
+    use anyhow::Result;
+
+    #[test]
+    fn test_parse_package_spec() -> Result<()> {
+        let (name, requirement) = parse_package_spec("my-package@^1.2")?;
+        assert_eq!(name, "my-package");
+        assert!(requirement.matches(&Version::parse("1.4.0")?));
+        assert!(!requirement.matches(&Version::parse("2.0.0")?));
+
+        let (name, requirement) = parse_package_spec("my-package")?;
+        assert_eq!(name, "my-package");
+        assert_eq!(requirement, VersionReq::STAR);
+
+        assert!(parse_package_spec("my-package@not-a-version").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_registry_publish_and_resolve() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let name = random_nrs_name();
+        let content = |version: &str| format!("safe://{}-{}", name, version);
+
+        for version in ["1.2.0", "1.10.1", "2.0.0"].iter() {
+            let _ = safe
+                .registry_publish(&name, version, &content(version))
+                .await?;
+        }
+        assert!(safe
+            .registry_publish(&name, "1.2", "safe://x")
+            .await
+            .is_err());
+
+        let releases = retry_loop_for_pattern!(safe.registry_releases(&name), Ok(releases) if releases.len() == 3)?;
+        let versions: Vec<&str> = releases.iter().map(|r| r.version.as_str()).collect();
+        assert_eq!(versions, vec!["1.2.0", "1.10.1", "2.0.0"]);
+
+        let release = safe.registry_resolve(&format!("{}@^1.2", name)).await?;
+        assert_eq!(release.version, "1.10.1");
+        assert_eq!(release.content, content("1.10.1"));
+        assert_eq!(release.publisher, safe.get_my_keypair()?.public_key());
+
+        let latest = safe.registry_resolve(&name).await?;
+        assert_eq!(latest.version, "2.0.0");
+
+        assert!(matches!(
+            safe.registry_resolve(&format!("{}@^3", name)).await,
+            Err(Error::ContentNotFound(_))
+        ));
+        assert!(matches!(
+            safe.registry_publish(&name, "2.0.0", &content("2.0.0"))
+                .await,
+            Err(Error::EntryExists(_))
+        ));
+
+        Ok(())
+    }
+}
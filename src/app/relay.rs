@@ -0,0 +1,431 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    encryption::keyed_hash, helpers::gen_timestamp_secs, register::EntryHash,
+    whois::RegisterWriters,
+};
+use crate::{
+    ContentType, Error, PublicKey, Result, Safe, Scope, Url, UrlAddressExt, XorName, XorUrl,
+};
+use bytes::Bytes;
+use log::{debug, info};
+use safe_network::types::Signature;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// Type tag to use for the drop Registers of the relay convention
+const RELAY_TYPE_TAG: u64 = 2_200;
+
+// Context used to derive the location of the drop Register of a recipient
+const RELAY_CONTEXT: &[u8] = b"sn_api-relay";
+
+/// Data deposited by a sender on the drop Register of a recipient
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Deposit {
+    /// Public key of the sender, who signed the deposit
+    pub sender: PublicKey,
+    /// Public key of the recipient the deposit was made for
+    pub recipient: bls::PublicKey,
+    /// Time the deposit was made, in RFC3339 format
+    pub deposited_at: String,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SignedDeposit {
+    deposit: Deposit,
+    signature: Signature,
+}
+
+// Entry a recipient writes upon draining its drop Register, superseding the
+// deposits drained so they are not returned again. It's signed with the
+// recipient's key so nobody else can mark its deposits as drained.
+#[derive(Debug, Serialize, Deserialize)]
+struct DrainedMarker {
+    recipient: bls::PublicKey,
+    drained: BTreeSet<EntryHash>,
+    drained_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SignedDrainedMarker {
+    marker: DrainedMarker,
+    signature: bls::Signature,
+}
+
+impl Safe {
+    /// # Create the drop Register of a recipient
+    ///
+    /// The drop Register is created upon the first deposit otherwise, thus recipients can
+    /// create theirs in advance so nobody else can create it with a policy which doesn't
+    /// let senders write to it. It fails if the drop Register already exists and such
+    /// is its policy, i.e. it was squatted. Returns the URL of the drop Register.
+    pub async fn relay_create_drop(&self, recipient: &bls::PublicKey) -> Result<XorUrl> {
+        info!("Creating drop Register for recipient");
+        let drop_url = self.fetch_or_create_drop(recipient).await?;
+        Ok(drop_url.to_string())
+    }
+
+    /// # Deposit data for a recipient
+    ///
+    /// Deposits are stored, following the relay convention, on a Public Register anyone
+    /// can write to, at a location derived from the recipient's public key, for the
+    /// recipient to drain them whenever it comes online. Each deposit is signed with the
+    /// signer of this instance, and encrypted to the recipient's key. It fails with an
+    /// `AccessDenied` error if the drop Register doesn't let anyone write to it, i.e. it
+    /// was squatted, see `relay_create_drop`.
+    pub async fn relay_deposit(
+        &self,
+        recipient: &bls::PublicKey,
+        payload: Bytes,
+    ) -> Result<EntryHash> {
+        info!("Depositing {} bytes for recipient", payload.len());
+        let drop_url = self.fetch_or_create_drop(recipient).await?;
+
+        let signer = self.signer()?;
+        let deposit = Deposit {
+            sender: signer.public_key(),
+            recipient: *recipient,
+            deposited_at: gen_timestamp_secs(),
+            payload: payload.to_vec(),
+        };
+        let signature = signer.sign(&serialise_deposit(&deposit)?).await?;
+        let serialised_deposit = rmp_serde::to_vec_named(&SignedDeposit { deposit, signature })
+            .map_err(|err| {
+                Error::Serialisation(format!("Couldn't serialise deposit: {:?}", err))
+            })?;
+
+        let ciphertext = recipient.encrypt(&serialised_deposit);
+        let serialised_ciphertext = rmp_serde::to_vec_named(&ciphertext).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise encrypted deposit: {:?}", err))
+        })?;
+        let deposit_xorurl = self
+            .store_public_bytes(Bytes::from(serialised_ciphertext), None, false)
+            .await?;
+
+        // Deposits don't supersede each other, thus they are all written without parents
+        self.write_to_register(
+            &drop_url.to_string(),
+            Url::from_xorurl(&deposit_xorurl)?,
+            BTreeSet::new(),
+        )
+        .await
+    }
+
+    /// # Drain the deposits made for a recipient
+    ///
+    /// Returns the deposits found on the recipient's drop Register, oldest first, and
+    /// marks them as drained, with a marker signed with the recipient's key, so they are
+    /// not returned again. Entries which cannot be decrypted with the recipient's key,
+    /// which were made for another recipient, or whose sender's signature is invalid,
+    /// are skipped and drained as well. It fails with an `AccessDenied` error if the drop
+    /// Register doesn't let anyone write to it, i.e. it was squatted.
+    pub async fn relay_drain(&self, recipient: &bls::SecretKey) -> Result<Vec<Deposit>> {
+        let (_, drop_url) = self.relay_drop_location(&recipient.public_key())?;
+        let entries = match self.fetch_register_entries(&drop_url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) => BTreeSet::new(),
+            Err(Error::ContentNotFound(_)) => {
+                debug!("No drop Register found");
+                return Ok(vec![]);
+            }
+            Err(err) => return Err(err),
+        };
+        self.check_drop_open(&drop_url).await?;
+        if entries.is_empty() {
+            debug!("No deposits found on drop Register");
+            return Ok(vec![]);
+        }
+
+        let mut deposits = vec![];
+        let mut pending = false;
+        for (_, entry) in entries.iter() {
+            match self.fetch_drop_entry(entry, recipient).await {
+                Ok(None) => {}
+                Ok(Some(deposit)) => {
+                    deposits.push(deposit);
+                    pending = true;
+                }
+                Err(err) => {
+                    debug!("Skipping entry {} of drop Register: {}", entry, err);
+                    pending = true;
+                }
+            }
+        }
+
+        if !pending {
+            // nothing was deposited since the last time it was drained
+            return Ok(vec![]);
+        }
+
+        let marker = DrainedMarker {
+            recipient: recipient.public_key(),
+            drained: entries.into_iter().map(|(hash, _)| hash).collect(),
+            drained_at: gen_timestamp_secs(),
+        };
+        let drained = marker.drained.clone();
+        let signature = recipient.sign(&serialise_marker(&marker)?);
+        let serialised_marker = rmp_serde::to_vec_named(&SignedDrainedMarker { signature, marker })
+            .map_err(|err| {
+                Error::Serialisation(format!("Couldn't serialise drained marker: {:?}", err))
+            })?;
+        let marker_xorurl = self
+            .store_public_bytes(Bytes::from(serialised_marker), None, false)
+            .await?;
+
+        let _ = self
+            .write_to_register(
+                &drop_url.to_string(),
+                Url::from_xorurl(&marker_xorurl)?,
+                drained,
+            )
+            .await?;
+
+        deposits.sort_by(|a, b| a.deposited_at.cmp(&b.deposited_at));
+        Ok(deposits)
+    }
+
+    // Private helper to fetch an entry of a drop Register, returning None if it's a drained
+    // marker signed by the recipient, or the deposit after decrypting and verifying it
+    async fn fetch_drop_entry(
+        &self,
+        entry: &Url,
+        recipient: &bls::SecretKey,
+    ) -> Result<Option<Deposit>> {
+        let recipient_pk = recipient.public_key();
+        let serialised_entry = self.fetch_public_data(entry, None).await?;
+        if let Ok(signed) = rmp_serde::from_slice::<SignedDrainedMarker>(&serialised_entry) {
+            let signed_bytes = serialise_marker(&signed.marker)?;
+            if signed.marker.recipient != recipient_pk
+                || !recipient_pk.verify(&signed.signature, &signed_bytes)
+            {
+                return Err(Error::ContentError(
+                    "Drained marker is not signed by the recipient".to_string(),
+                ));
+            }
+            return Ok(None);
+        }
+
+        let ciphertext: bls::Ciphertext = rmp_serde::from_slice(&serialised_entry)
+            .map_err(|err| Error::ContentError(format!("Couldn't parse deposit: {:?}", err)))?;
+        let serialised_deposit = recipient.decrypt(&ciphertext).ok_or_else(|| {
+            Error::ContentError("Deposit is not encrypted to the recipient's key".to_string())
+        })?;
+
+        let signed: SignedDeposit = rmp_serde::from_slice(&serialised_deposit)
+            .map_err(|err| Error::ContentError(format!("Couldn't parse deposit: {:?}", err)))?;
+        let signed_bytes = serialise_deposit(&signed.deposit)?;
+        signed
+            .deposit
+            .sender
+            .verify(&signed.signature, &signed_bytes)
+            .map_err(|err| {
+                Error::ContentError(format!("Invalid signature found on deposit: {:?}", err))
+            })?;
+        if signed.deposit.recipient != recipient_pk {
+            return Err(Error::ContentError(
+                "Deposit was made for another recipient".to_string(),
+            ));
+        }
+
+        Ok(Some(signed.deposit))
+    }
+
+    // Private helper to fetch the drop Register of a recipient, creating it if not found,
+    // and checking anyone can write to it
+    async fn fetch_or_create_drop(&self, recipient: &bls::PublicKey) -> Result<Url> {
+        let (drop_xorname, drop_url) = self.relay_drop_location(recipient)?;
+        match self.fetch_register_entries(&drop_url).await {
+            Ok(_) | Err(Error::EmptyContent(_)) => {}
+            Err(Error::ContentNotFound(_)) => {
+                debug!("Drop Register not found, creating it");
+                let _ = self
+                    .safe_client
+                    .store_open_register(drop_xorname, RELAY_TYPE_TAG)
+                    .await?;
+                return Ok(drop_url);
+            }
+            Err(err) => return Err(err),
+        }
+
+        self.check_drop_open(&drop_url).await?;
+        Ok(drop_url)
+    }
+
+    // Private helper to check anyone can write to a drop Register. Anyone can create the
+    // drop Register of a recipient, since its location is derived from its public key,
+    // thus one not letting senders write to it was created to block the recipient's
+    // deposits, which is reported rather than failing to write deposits to it.
+    async fn check_drop_open(&self, drop_url: &Url) -> Result<()> {
+        let (owner, writers) = self
+            .safe_client
+            .get_register_policy(drop_url.register_address()?)
+            .await?;
+        if writers != RegisterWriters::Anyone {
+            return Err(Error::AccessDenied(format!(
+                "Drop Register at \"{}\", owned by {:?}, doesn't let anyone write to it",
+                drop_url, owner
+            )));
+        }
+        Ok(())
+    }
+
+    // Private helper to obtain the location of the drop Register of a recipient
+    fn relay_drop_location(&self, recipient: &bls::PublicKey) -> Result<(XorName, Url)> {
+        let drop_xorname = XorName(keyed_hash(RELAY_CONTEXT, &recipient.to_bytes()));
+        let drop_xorurl = Url::encode_register(
+            drop_xorname,
+            RELAY_TYPE_TAG,
+            Scope::Public,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?;
+
+        Ok((drop_xorname, Url::from_xorurl(&drop_xorurl)?))
+    }
+}
+
+fn serialise_deposit(deposit: &Deposit) -> Result<Vec<u8>> {
+    rmp_serde::to_vec_named(deposit)
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise deposit: {:?}", err)))
+}
+
+fn serialise_marker(marker: &DrainedMarker) -> Result<Vec<u8>> {
+    rmp_serde::to_vec_named(marker).map_err(|err| {
+        Error::Serialisation(format!("Couldn't serialise drained marker: {:?}", err))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop_for_pattern};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_relay_deposit_and_drain() -> Result<()> {
+        let sender = new_safe_instance().await?;
+        let recipient = new_safe_instance().await?;
+        let recipient_sk = bls::SecretKey::random();
+        let _ = recipient
+            .relay_create_drop(&recipient_sk.public_key())
+            .await?;
+
+        let _ = sender
+            .relay_deposit(&recipient_sk.public_key(), Bytes::from("first"))
+            .await?;
+        let _ = sender
+            .relay_deposit(&recipient_sk.public_key(), Bytes::from("second"))
+            .await?;
+
+        let deposits = retry_loop_for_pattern!(recipient.relay_drain(&recipient_sk), Ok(deposits) if deposits.len() == 2)?;
+        let sender_pk = sender.get_my_keypair()?.public_key();
+        assert!(deposits.iter().all(|deposit| deposit.sender == sender_pk));
+        let mut payloads: Vec<Vec<u8>> = deposits.into_iter().map(|d| d.payload).collect();
+        payloads.sort();
+        assert_eq!(payloads, vec![b"first".to_vec(), b"second".to_vec()]);
+
+        // drained deposits are not returned again, and deposits for others are not readable
+        let _ = retry_loop_for_pattern!(recipient.relay_drain(&recipient_sk), Ok(deposits) if deposits.is_empty())?;
+        let other_sk = bls::SecretKey::random();
+        assert!(recipient.relay_drain(&other_sk).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_relay_drain_rejects_forwarded_deposit() -> Result<()> {
+        let sender = new_safe_instance().await?;
+        let forwarder = new_safe_instance().await?;
+        let recipient = new_safe_instance().await?;
+        let forwarder_sk = bls::SecretKey::random();
+        let recipient_sk = bls::SecretKey::random();
+
+        // a deposit made for someone else, re-encrypted and forwarded to the recipient
+        let signer = sender.signer()?;
+        let deposit = Deposit {
+            sender: signer.public_key(),
+            recipient: forwarder_sk.public_key(),
+            deposited_at: gen_timestamp_secs(),
+            payload: b"not for you".to_vec(),
+        };
+        let signature = signer.sign(&serialise_deposit(&deposit)?).await?;
+        let serialised_deposit = rmp_serde::to_vec_named(&SignedDeposit { deposit, signature })?;
+        let ciphertext = recipient_sk.public_key().encrypt(&serialised_deposit);
+        let deposit_xorurl = forwarder
+            .store_public_bytes(
+                Bytes::from(rmp_serde::to_vec_named(&ciphertext)?),
+                None,
+                false,
+            )
+            .await?;
+        let (drop_xorname, drop_url) = forwarder.relay_drop_location(&recipient_sk.public_key())?;
+        let _ = forwarder
+            .safe_client
+            .store_open_register(drop_xorname, RELAY_TYPE_TAG)
+            .await?;
+        let _ = forwarder
+            .write_to_register(
+                &drop_url.to_string(),
+                Url::from_xorurl(&deposit_xorurl)?,
+                BTreeSet::new(),
+            )
+            .await?;
+        let _ = retry_loop_for_pattern!(recipient.fetch_register_entries(&drop_url), Ok(entries) if !entries.is_empty())?;
+
+        // the forwarded deposit is skipped, while deposits made for the recipient are not
+        let _ = sender
+            .relay_deposit(&recipient_sk.public_key(), Bytes::from("for you"))
+            .await?;
+        let deposits = retry_loop_for_pattern!(recipient.relay_drain(&recipient_sk), Ok(deposits) if !deposits.is_empty())?;
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].payload, b"for you".to_vec());
+        assert_eq!(deposits[0].recipient, recipient_sk.public_key());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_relay_squatted_drop() -> Result<()> {
+        let sender = new_safe_instance().await?;
+        let squatter = new_safe_instance().await?;
+        let recipient = new_safe_instance().await?;
+        let recipient_sk = bls::SecretKey::random();
+
+        // someone creates the recipient's drop Register letting only itself write to it
+        let (drop_xorname, drop_url) = squatter.relay_drop_location(&recipient_sk.public_key())?;
+        let _ = squatter
+            .register_create(Some(drop_xorname), RELAY_TYPE_TAG, false)
+            .await?;
+        let _ = retry_loop_for_pattern!(
+            squatter.fetch_register_entries(&drop_url),
+            Err(Error::EmptyContent(_))
+        );
+
+        assert!(matches!(
+            sender
+                .relay_deposit(&recipient_sk.public_key(), Bytes::from("blocked"))
+                .await,
+            Err(Error::AccessDenied(_))
+        ));
+        assert!(matches!(
+            recipient
+                .relay_create_drop(&recipient_sk.public_key())
+                .await,
+            Err(Error::AccessDenied(_))
+        ));
+        assert!(matches!(
+            recipient.relay_drain(&recipient_sk).await,
+            Err(Error::AccessDenied(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{encryption::keyed_hash, helpers::gen_timestamp_secs, register::EntryHash};
+use crate::{ContentType, Error, PublicKey, Result, Safe, Scope, Url, XorName};
+use bytes::Bytes;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// Type tag to use for the Registers holding the reports made about a content
+const REPORTS_TYPE_TAG: u64 = 1_700;
+
+// Context used to derive the location of the Register holding the reports made about a content
+const REPORTS_CONTEXT: &[u8] = b"sn_api-reports";
+
+/// Reason for reporting a content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReportReason {
+    Spam,
+    Malware,
+    Abuse,
+    Illegal,
+    Copyright,
+    Other(String),
+}
+
+/// A report made about a content, as stored following the content reports convention
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentReport {
+    /// XOR-URL of the content reported, i.e. the URL provided once resolved
+    pub target: String,
+    pub reason: ReportReason,
+    pub comment: Option<String>,
+    /// Public key of the reporter
+    pub reporter: PublicKey,
+    /// Time the report was made, in RFC3339 format
+    pub reported_at: String,
+}
+
+impl Safe {
+    /// # Report a content found at the provided URL.
+    ///
+    /// Reports are stored, following a standard convention, on a Public Register anyone
+    /// can write to, at a location derived from the XOR-URL of the reported content.
+    /// This allows moderation tools in gateways and browsers to find and share them.
+    pub async fn report(
+        &self,
+        url: &str,
+        reason: ReportReason,
+        comment: Option<&str>,
+    ) -> Result<EntryHash> {
+        info!("Reporting content at {}", url);
+        let (target, reports_xorname, reports_url) = self.reports_location(url).await?;
+
+        match self.fetch_register_entries(&reports_url).await {
+            Ok(_) | Err(Error::EmptyContent(_)) => {}
+            Err(Error::ContentNotFound(_)) => {
+                debug!("Reports Register not found, creating it");
+                let _ = self
+                    .safe_client
+                    .store_open_register(reports_xorname, REPORTS_TYPE_TAG)
+                    .await?;
+            }
+            Err(err) => return Err(err),
+        }
+
+        let report = ContentReport {
+            target,
+            reason,
+            comment: comment.map(String::from),
+            reporter: self.signer_public_key()?,
+            reported_at: gen_timestamp_secs(),
+        };
+        let serialised_report = rmp_serde::to_vec_named(&report).map_err(|err| {
+            Error::Serialisation(format!(
+                "Couldn't serialise the report '{:?}': {:?}",
+                report, err
+            ))
+        })?;
+        let report_xorurl = self
+            .store_public_bytes(Bytes::from(serialised_report), None, false)
+            .await?;
+
+        // Reports don't supersede each other, thus they are all written without parents
+        self.write_to_register(
+            &reports_url.to_string(),
+            Url::from_xorurl(&report_xorurl)?,
+            BTreeSet::new(),
+        )
+        .await
+    }
+
+    /// Retrieve all the reports made about the content found at the provided URL
+    pub async fn reports_for(&self, url: &str) -> Result<Vec<ContentReport>> {
+        let (target, _, reports_url) = self.reports_location(url).await?;
+        let entries = match self.fetch_register_entries(&reports_url).await {
+            Ok(entries) => entries,
+            Err(err) => {
+                debug!("No reports found for {}: {:?}", url, err);
+                return Ok(vec![]);
+            }
+        };
+
+        let mut reports = vec![];
+        for (_, entry) in entries.iter() {
+            let serialised_report = self.fetch_public_data(entry, None).await?;
+            let report: ContentReport = rmp_serde::from_slice(&serialised_report)
+                .map_err(|err| Error::ContentError(format!("Couldn't parse report: {:?}", err)))?;
+            // Disregard any entry which doesn't belong to this content
+            if report.target == target {
+                reports.push(report);
+            }
+        }
+        reports.sort_by(|a, b| a.reported_at.cmp(&b.reported_at));
+
+        Ok(reports)
+    }
+
+    // Private helper to resolve the URL of the content and obtain
+    // the location of the Register holding the reports about it
+    async fn reports_location(&self, url: &str) -> Result<(String, XorName, Url)> {
+        let (mut target_url, _) = self.parse_and_resolve_url(url).await?;
+        target_url.set_content_version(None);
+        let target = target_url.to_xorurl_string();
+
+        let reports_xorname = XorName(keyed_hash(REPORTS_CONTEXT, target.as_bytes()));
+        let reports_xorurl = Url::encode_register(
+            reports_xorname,
+            REPORTS_TYPE_TAG,
+            Scope::Public,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?;
+
+        Ok((target, reports_xorname, Url::from_xorurl(&reports_xorurl)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_report_and_reports_for() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let xorurl = safe
+            .store_public_bytes(Bytes::from("some spam"), None, false)
+            .await?;
+        let _ = retry_loop!(safe.fetch(&xorurl, None));
+
+        assert!(safe.reports_for(&xorurl).await?.is_empty());
+
+        let _ = safe
+            .report(&xorurl, ReportReason::Spam, Some("unsolicited ads"))
+            .await?;
+        let reports = retry_loop_for_pattern!(safe.reports_for(&xorurl), Ok(v) if !v.is_empty())?;
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].reason, ReportReason::Spam);
+        assert_eq!(reports[0].comment, Some("unsolicited ads".to_string()));
+        assert_eq!(reports[0].reporter, safe.get_my_keypair()?.public_key());
+
+        Ok(())
+    }
+}
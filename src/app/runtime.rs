@@ -0,0 +1,145 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::Safe;
+use futures::future::BoxFuture;
+use std::{sync::Arc, time::Duration};
+use tokio::runtime::Handle;
+
+/// Executor the API spawns its background tasks on, e.g. scheduled publications,
+/// and which provides the timers the API waits on, e.g. between retries or polls.
+///
+/// Embeddings driving their own executor, e.g. on mobile platforms, can implement it
+/// to avoid the API assuming a tokio runtime is running on the calling thread.
+pub trait AsyncRuntime: Send + Sync {
+    /// Spawn a task to run in the background
+    fn spawn(&self, task: BoxFuture<'static, ()>);
+
+    /// A future which completes after the given duration
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+/// Runtime backed by tokio, either multi-thread or current-thread
+#[derive(Debug, Clone, Default)]
+pub struct TokioRuntime {
+    handle: Option<Handle>,
+}
+
+impl TokioRuntime {
+    /// Runtime using the tokio runtime of the given handle, thus it can be used from
+    /// threads which are not driven by such runtime, e.g. by a different executor.
+    /// Without a handle, the runtime driving the calling thread is used.
+    pub fn new(handle: Handle) -> Self {
+        Self {
+            handle: Some(handle),
+        }
+    }
+}
+
+impl AsyncRuntime for TokioRuntime {
+    fn spawn(&self, task: BoxFuture<'static, ()>) {
+        // the task is detached, thus its join handle is dropped
+        match &self.handle {
+            Some(handle) => drop(handle.spawn(task)),
+            None => drop(tokio::spawn(task)),
+        }
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        // The timer is bound to the runtime entered when it's created
+        let _guard = self.handle.as_ref().map(|handle| handle.enter());
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+// Runtime shared by an instance and all its clones
+#[derive(Clone)]
+pub(crate) struct SharedRuntime(Arc<dyn AsyncRuntime>);
+
+impl Default for SharedRuntime {
+    fn default() -> Self {
+        Self(Arc::new(TokioRuntime::default()))
+    }
+}
+
+impl SharedRuntime {
+    pub(crate) fn new(runtime: Arc<dyn AsyncRuntime>) -> Self {
+        Self(runtime)
+    }
+
+    pub(crate) fn spawn(&self, task: BoxFuture<'static, ()>) {
+        self.0.spawn(task)
+    }
+
+    pub(crate) fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        self.0.sleep(duration)
+    }
+}
+
+impl Safe {
+    /// Set the runtime this instance spawns its background tasks on and waits with.
+    /// By default the tokio runtime driving the calling thread is used.
+    pub fn set_runtime(&mut self, runtime: Arc<dyn AsyncRuntime>) {
+        self.safe_client.set_runtime(SharedRuntime::new(runtime));
+    }
+
+    /// Set the handle of the tokio runtime this instance spawns its background
+    /// tasks on and waits with, which can also be a current-thread runtime
+    pub fn set_runtime_handle(&mut self, handle: Handle) {
+        self.set_runtime(Arc::new(TokioRuntime::new(handle)));
+    }
+
+    // Runtime this instance spawns its background tasks on and waits with
+    pub(crate) fn runtime(&self) -> SharedRuntime {
+        self.safe_client.runtime()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use futures::channel::oneshot;
+
+    #[test]
+    fn test_tokio_runtime_from_foreign_executor() -> Result<()> {
+        // a current-thread runtime, driven on its own thread
+        let tokio_runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()?;
+        let handle = tokio_runtime.handle().clone();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let driver = std::thread::spawn(move || {
+            tokio_runtime.block_on(async {
+                let _ = shutdown_rx.await;
+            })
+        });
+
+        let mut safe = Safe::default();
+        safe.set_runtime_handle(handle);
+        let runtime = safe.runtime();
+
+        // the API is driven by an executor other than tokio
+        let received = futures::executor::block_on(async {
+            runtime.sleep(Duration::from_millis(10)).await;
+            let (tx, rx) = oneshot::channel();
+            runtime.spawn(Box::pin(async move {
+                let _ = tx.send("spawned");
+            }));
+            rx.await
+        })?;
+        assert_eq!(received, "spawned");
+
+        let _ = shutdown_tx.send(());
+        driver
+            .join()
+            .map_err(|_| anyhow::anyhow!("Runtime thread panicked"))?;
+        Ok(())
+    }
+}
@@ -7,19 +7,33 @@
 // specific language governing permissions and limitations relating to use of the SAFE Network
 // Software.
 
-use super::fetch::Range;
-use crate::{ipc::NodeConfig, Error, Result};
+#[cfg(feature = "sim")]
+use super::sim::SimNetwork;
+use super::{
+    diagnostics::DiagnosticsCounters,
+    ephemeral::{EphemeralPolicy, EphemeralWrite},
+    fetch::Range,
+    memory::MemoryBudget,
+    privacy::{trim_to_range, FetchTarget, Privacy},
+    register::{last_generations, register_dag, RegisterCache},
+    runtime::SharedRuntime,
+    whois::RegisterWriters,
+    workers::WorkerPool,
+};
+use crate::{ipc::NodeConfig, Error, MissingPermission, Result};
 use bytes::Bytes;
+use futures::future::join_all;
 use hex::encode;
 use log::{debug, info};
-use safe_network::client::{Client, Config, Error as ClientError};
+use safe_network::client::{Client, Config, Error as ClientError, ErrorMessage};
 use safe_network::types::{
-    register::{Entry, EntryHash, PrivatePermissions, PublicPermissions, User},
-    BytesAddress, Error as SafeNdError, Keypair, RegisterAddress,
+    register::{Action, Entry, EntryHash, Policy, PrivatePermissions, PublicPermissions, User},
+    BytesAddress, Error as SafeNdError, Keypair, PublicKey, RegisterAddress,
 };
 use safe_network::url::Scope;
 use std::{
     collections::{BTreeMap, BTreeSet},
+    future::Future,
     path::{Path, PathBuf},
     time::Duration,
 };
@@ -27,11 +41,32 @@ use xor_name::XorName;
 
 const APP_NOT_CONNECTED: &str = "Application is not connected to the network";
 
+// Maximum number of times a query for data is sent before failing
+const MAX_QUERY_ATTEMPTS: usize = 3;
+
+// Time to wait before retrying a failed query, multiplied by the attempts made so far
+const QUERY_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+// Payloads from this size on are self-encrypted on the worker threads, smaller
+// ones are not worth the hop to another thread
+const MIN_OFFLOADED_UPLOAD_SIZE: usize = 1024 * 1024;
+
 #[derive(Default, Clone)]
 pub struct SafeAppClient {
     safe_client: Option<Client>,
     config_path: Option<PathBuf>,
+    node_config: Option<NodeConfig>,
     timeout: Duration,
+    diagnostics: DiagnosticsCounters,
+    runtime: SharedRuntime,
+    workers: WorkerPool,
+    memory: MemoryBudget,
+    register_cache: RegisterCache,
+    privacy: Privacy,
+    ephemeral_policy: Option<EphemeralPolicy>,
+    trusted_genesis_keys: BTreeSet<bls::PublicKey>,
+    #[cfg(feature = "sim")]
+    sim: Option<(SimNetwork, Keypair)>,
 }
 
 impl SafeAppClient {
@@ -43,14 +78,177 @@ impl SafeAppClient {
         }
     }
 
+    // Send the same query again, after a linear backoff, until it succeeds or the attempts
+    // are exhausted. The client doesn't let us choose which holders of the data serve a
+    // query, thus a retry may be sent to the same holders which failed. Upon failure the
+    // last error is returned together with the trail of the errors of each attempt.
+    async fn retry_query<T, F, Fut>(
+        &self,
+        mut query: F,
+    ) -> std::result::Result<T, (ClientError, String)>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = std::result::Result<T, ClientError>>,
+    {
+        let mut trail = vec![];
+        loop {
+            match query().await {
+                Ok(value) => return Ok(value),
+                Err(err) => {
+                    trail.push(format!("attempt #{}: {:?}", trail.len() + 1, err));
+                    if trail.len() >= MAX_QUERY_ATTEMPTS {
+                        return Err((err, trail.join("; ")));
+                    }
+                    debug!("Query failed ({:?}), retrying", err);
+                    self.diagnostics.retry();
+                    self.runtime
+                        .sleep(QUERY_RETRY_BACKOFF * trail.len() as u32)
+                        .await;
+                }
+            }
+        }
+    }
+
     pub fn new(timeout: Duration) -> Self {
         Self {
             safe_client: None,
             config_path: None,
+            node_config: None,
             timeout,
+            diagnostics: DiagnosticsCounters::default(),
+            runtime: SharedRuntime::default(),
+            workers: WorkerPool::default(),
+            memory: MemoryBudget::default(),
+            register_cache: RegisterCache::default(),
+            privacy: Privacy::default(),
+            ephemeral_policy: None,
+            trusted_genesis_keys: BTreeSet::new(),
+            #[cfg(feature = "sim")]
+            sim: None,
+        }
+    }
+
+    // Runtime background tasks are spawned on, and timers are created with
+    pub(crate) fn runtime(&self) -> SharedRuntime {
+        self.runtime.clone()
+    }
+
+    pub(crate) fn set_runtime(&mut self, runtime: SharedRuntime) {
+        self.runtime = runtime;
+    }
+
+    // Number of threads large payloads are self-encrypted on
+    pub(crate) fn workers(&self) -> usize {
+        self.workers.parallelism()
+    }
+
+    pub(crate) fn set_workers(&mut self, parallelism: usize) {
+        self.workers = WorkerPool::new(parallelism);
+    }
+
+    // Budget of the memory in-flight fetches can hold
+    pub(crate) fn memory(&self) -> MemoryBudget {
+        self.memory.clone()
+    }
+
+    // Cache of the current entries of the Registers read
+    pub(crate) fn register_cache(&self) -> RegisterCache {
+        self.register_cache.clone()
+    }
+
+    // Privacy mode the fetches are sent with
+    pub(crate) fn privacy(&self) -> Privacy {
+        self.privacy.clone()
+    }
+
+    // Hold a fetch back, and send decoy fetches along with it, as per the privacy mode
+    async fn hold_fetch(&self, target: FetchTarget) {
+        if let Some((delay, decoys)) = self.privacy.plan_fetch(target) {
+            for decoy in decoys {
+                let client = self.clone();
+                let runtime = self.runtime.clone();
+                self.runtime.spawn(Box::pin(async move {
+                    runtime.sleep(delay).await;
+                    client.decoy_fetch(decoy).await;
+                }));
+            }
+            self.runtime.sleep(delay).await;
+        }
+    }
+
+    // Fetch content only to disguise the actual fetches, discarding it
+    async fn decoy_fetch(&self, target: FetchTarget) {
+        debug!("Sending decoy fetch");
+        let result = match target {
+            FetchTarget::Bytes(address) => {
+                let range = self.privacy.padded_range(Some((Some(0), Some(1))));
+                self.fetch_bytes(address, range).await.map(|_| ())
+            }
+            FetchTarget::Register(address) => {
+                self.read_register_from_network(address).await.map(|_| ())
+            }
+        };
+        if let Err(err) = result {
+            debug!("Decoy fetch failed: {:?}", err);
+        }
+    }
+
+    // Timeout of the queries sent to the network
+    pub(crate) fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    // Bootstrap config of the network the client is connected to
+    pub(crate) fn node_config(&self) -> Result<NodeConfig> {
+        self.node_config
+            .clone()
+            .ok_or_else(|| Error::ConnectionError(APP_NOT_CONNECTED.to_string()))
+    }
+
+    // Policy of the writes allowed to the client if it's an ephemeral identity
+    pub(crate) fn ephemeral_policy(&self) -> Option<EphemeralPolicy> {
+        self.ephemeral_policy
+    }
+
+    pub(crate) fn set_ephemeral_policy(&mut self, policy: EphemeralPolicy) {
+        self.ephemeral_policy = Some(policy);
+    }
+
+    // Fail if the client is an ephemeral identity not allowed to make the write
+    fn check_ephemeral_write(&self, write: EphemeralWrite) -> Result<()> {
+        match &self.ephemeral_policy {
+            Some(policy) => policy.check(write),
+            None => Ok(()),
         }
     }
 
+    // Genesis keys of the networks the client is allowed to connect to, any if empty
+    pub(crate) fn trusted_genesis_keys(&self) -> &BTreeSet<bls::PublicKey> {
+        &self.trusted_genesis_keys
+    }
+
+    pub(crate) fn set_trusted_genesis_keys(&mut self, keys: BTreeSet<bls::PublicKey>) {
+        self.trusted_genesis_keys = keys;
+    }
+
+    // Counters of the operations sent to the network by this client
+    pub(crate) fn diagnostics(&self) -> DiagnosticsCounters {
+        self.diagnostics.clone()
+    }
+
+    // Use a simulated network instead of connecting to the SAFE Network
+    #[cfg(feature = "sim")]
+    pub fn connect_sim(&mut self, sim: SimNetwork, keypair: Keypair) {
+        debug!("Using a simulated network instead of the SAFE Network");
+        self.sim = Some((sim, keypair));
+    }
+
+    // Simulated network used instead of the SAFE Network, if any
+    #[cfg(feature = "sim")]
+    pub(crate) fn sim(&self) -> Option<SimNetwork> {
+        self.sim.as_ref().map(|(sim, _)| sim.clone())
+    }
+
     // Connect to the SAFE Network using the keypair if provided. Contacts list
     // are overriden if a 'bootstrap_config' is provided.
     pub async fn connect(
@@ -69,6 +267,17 @@ impl SafeAppClient {
         );
         debug!("Bootstrap contacts list set to: {:?}", node_config);
 
+        // The sections of the network are verified by the client against the genesis key
+        // of the bootstrap config, thus a compromised config could lead to an impostor network
+        if !self.trusted_genesis_keys.is_empty()
+            && !self.trusted_genesis_keys.contains(&node_config.0)
+        {
+            return Err(Error::UntrustedNetwork(format!(
+                "The genesis key of the network to connect to ({}) is not among the trusted ones",
+                encode(node_config.0.to_bytes())
+            )));
+        }
+
         let config = Config::new(
             None,
             None,
@@ -77,19 +286,35 @@ impl SafeAppClient {
             Some(self.timeout),
         )
         .await;
-        let client = Client::new(config, node_config.1, app_keypair)
+        let client = Client::new(config, node_config.1.clone(), app_keypair)
             .await
             .map_err(|err| {
                 Error::ConnectionError(format!("Failed to connect to the SAFE Network: {:?}", err))
             })?;
 
         self.safe_client = Some(client);
+        self.node_config = Some(node_config);
 
         debug!("Successfully connected to the Network!!!");
         Ok(())
     }
 
+    // Whether the client is connected to the network, or to a simulated one
+    pub fn is_connected(&self) -> bool {
+        #[cfg(feature = "sim")]
+        if self.sim.is_some() {
+            return true;
+        }
+
+        self.safe_client.is_some()
+    }
+
     pub fn keypair(&self) -> Result<Keypair> {
+        #[cfg(feature = "sim")]
+        if let Some((_, keypair)) = &self.sim {
+            return Ok(keypair.clone());
+        }
+
         let client = self.get_safe_client()?;
         Ok(client.keypair())
     }
@@ -104,39 +329,125 @@ impl SafeAppClient {
             XorName::default()
         } else {
             debug!("Storing {} bytes of data", bytes.len());
-            let client = self.get_safe_client()?;
-            let address = client.upload(bytes, Scope::Public).await?;
-            *address.name()
+            self.check_ephemeral_write(EphemeralWrite::Blob(bytes.len()))?;
+            self.diagnostics.command(bytes.len());
+            #[cfg(feature = "sim")]
+            if let Some((sim, _)) = &self.sim {
+                return sim.store_bytes(bytes).await;
+            }
+
+            self.upload(bytes, Scope::Public).await?
         };
         Ok(xorname)
     }
 
+    pub async fn store_private_bytes(&self, bytes: Bytes) -> Result<XorName> {
+        debug!("Storing {} bytes of private data", bytes.len());
+        self.check_ephemeral_write(EphemeralWrite::Blob(bytes.len()))?;
+        self.diagnostics.command(bytes.len());
+        #[cfg(feature = "sim")]
+        if let Some((sim, _)) = &self.sim {
+            return sim.store_bytes(bytes).await;
+        }
+
+        self.upload(bytes, Scope::Private).await
+    }
+
+    // Upload the bytes, self-encrypting them on the worker threads when they're large, so
+    // the async runtime isn't stalled. The upload is driven by the tokio runtime of the
+    // calling task, thus it's only offloaded when called from within such runtime.
+    async fn upload(&self, bytes: Bytes, scope: Scope) -> Result<XorName> {
+        let client = self.get_safe_client()?;
+        let handle = match tokio::runtime::Handle::try_current() {
+            Ok(handle) if bytes.len() >= MIN_OFFLOADED_UPLOAD_SIZE => handle,
+            _ => {
+                let address = client.upload(bytes, scope).await?;
+                return Ok(*address.name());
+            }
+        };
+
+        let address = self
+            .workers
+            .run(move || {
+                let _guard = handle.enter();
+                futures::executor::block_on(client.upload(bytes, scope))
+            })
+            .await??;
+        Ok(*address.name())
+    }
+
     pub async fn get_bytes(&self, address: BytesAddress, range: Range) -> Result<Bytes> {
+        self.hold_fetch(FetchTarget::Bytes(address)).await;
+        let padded_range = self.privacy.padded_range(range);
+        let data = self.fetch_bytes(address, padded_range).await?;
+        Ok(trim_to_range(data, range, padded_range))
+    }
+
+    async fn fetch_bytes(&self, address: BytesAddress, range: Range) -> Result<Bytes> {
         debug!("Attempting to fetch data from {:?}", address.name());
-        let client = self.get_safe_client()?;
+        let expected_len = range.and_then(|(start, end)| {
+            end.map(|end| end.saturating_sub(start.unwrap_or(0)) as usize)
+        });
+        let mut reservation = self.memory.reserve(expected_len).await;
+        self.diagnostics.query();
+        #[cfg(feature = "sim")]
+        if let Some((sim, _)) = &self.sim {
+            let data = sim.get_bytes(address, range).await?;
+            reservation.resize(data.len());
+            self.diagnostics.received(data.len());
+            return Ok(data);
+        }
+
+        let client = &self.get_safe_client()?;
         let data = if let Some((start, end)) = range {
             let len = end
                 .map(|end_index| end_index - start.unwrap_or(0))
                 .unwrap_or(0);
-            client
-                .read_from(
-                    address,
-                    start.map(|val| val as usize).unwrap_or(0),
-                    len as usize,
-                )
+            let start = start.map(|val| val as usize).unwrap_or(0);
+            self.retry_query(move || client.read_from(address, start, len as usize))
                 .await
         } else {
-            client.read_bytes(address).await
+            self.retry_query(move || client.read_bytes(address)).await
         }
-        .map_err(|e| Error::NetDataError(format!("Failed to GET Blob: {:?}", e)))?;
+        .map_err(|(_, trail)| Error::NetDataError(format!("Failed to GET Blob: {}", trail)))?;
         debug!(
             "{} bytes of data successfully retrieved from: {:?}",
             data.len(),
             address.name()
         );
+        reservation.resize(data.len());
+        self.diagnostics.received(data.len());
         Ok(data)
     }
 
+    //
+    // Chunk operations
+    //
+    #[cfg(feature = "advanced")]
+    pub async fn store_chunk(&self, bytes: Bytes) -> Result<XorName> {
+        debug!("Storing chunk of {} bytes", bytes.len());
+        self.check_ephemeral_write(EphemeralWrite::Blob(bytes.len()))?;
+        self.diagnostics.command(bytes.len());
+        #[cfg(feature = "sim")]
+        if let Some((sim, _)) = &self.sim {
+            return sim.store_bytes(bytes).await;
+        }
+
+        let client = self.get_safe_client()?;
+        // the client only stores chunks as part of an upload, which is a single
+        // chunk as long as the bytes are too few to be self-encrypted
+        let (_, chunks) = client.chunk_bytes(bytes.clone(), Scope::Public)?;
+        if chunks.len() != 1 {
+            return Err(Error::InvalidInput(format!(
+                "{} bytes don't fit in a single chunk",
+                bytes.len()
+            )));
+        }
+        let address = client.upload(bytes, Scope::Public).await?;
+
+        Ok(*address.name())
+    }
+
     // === Register data operations ===
     pub async fn store_register(
         &self,
@@ -144,17 +455,41 @@ impl SafeAppClient {
         tag: u64,
         _permissions: Option<String>,
         private: bool,
+    ) -> Result<XorName> {
+        self.store_register_with_writers(name, tag, private, BTreeSet::new())
+            .await
+    }
+
+    // Store a Register which the given keys can write to, besides its owner.
+    // The keys are also allowed to read it if it's private.
+    pub async fn store_register_with_writers(
+        &self,
+        name: Option<XorName>,
+        tag: u64,
+        private: bool,
+        writers: BTreeSet<PublicKey>,
     ) -> Result<XorName> {
         debug!(
-            "Storing {} Register data with tag type: {}, xorname: {:?}",
+            "Storing {} Register data with tag type: {}, xorname: {:?}, additional writers: {:?}",
             if private { "Private" } else { "Public" },
             tag,
-            name
+            name,
+            writers
         );
+        self.check_ephemeral_write(EphemeralWrite::RegisterCreation)?;
 
-        let client = self.get_safe_client()?;
         let xorname = name.unwrap_or_else(rand::random);
         info!("Xorname for new Register storage: {:?}", &xorname);
+        self.diagnostics.command(0);
+
+        #[cfg(feature = "sim")]
+        if let Some((sim, keypair)) = &self.sim {
+            return sim
+                .store_register(xorname, tag, private, keypair.public_key(), false, writers)
+                .await;
+        }
+
+        let client = self.get_safe_client()?;
 
         // The Register's owner will be the client's public key
         let my_pk = client.public_key();
@@ -164,6 +499,9 @@ impl SafeAppClient {
             // Set read and write  permissions to this application
             let mut perms = BTreeMap::default();
             let _ = perms.insert(my_pk, PrivatePermissions::new(true, true));
+            for writer in writers.into_iter() {
+                let _ = perms.insert(writer, PrivatePermissions::new(true, true));
+            }
 
             client
                 .store_private_register(xorname, tag, my_pk, perms)
@@ -176,6 +514,9 @@ impl SafeAppClient {
             let user_app = User::Key(my_pk);
             let mut perms = BTreeMap::default();
             let _ = perms.insert(user_app, PublicPermissions::new(true));
+            for writer in writers.into_iter() {
+                let _ = perms.insert(User::Key(writer), PublicPermissions::new(true));
+            }
 
             client
                 .store_public_register(xorname, tag, my_pk, perms)
@@ -188,11 +529,71 @@ impl SafeAppClient {
         Ok(xorname)
     }
 
+    // Store a Public Register which anyone can write to, e.g. to be used by
+    // conventions where many users append entries to a shared Register
+    pub async fn store_open_register(&self, name: XorName, tag: u64) -> Result<XorName> {
+        debug!(
+            "Storing Public Register writable by anyone with tag type: {}, xorname: {:?}",
+            tag, name
+        );
+        self.check_ephemeral_write(EphemeralWrite::RegisterCreation)?;
+        self.diagnostics.command(0);
+
+        #[cfg(feature = "sim")]
+        if let Some((sim, keypair)) = &self.sim {
+            return sim
+                .store_register(
+                    name,
+                    tag,
+                    false,
+                    keypair.public_key(),
+                    true,
+                    BTreeSet::new(),
+                )
+                .await;
+        }
+
+        let client = self.get_safe_client()?;
+        let my_pk = client.public_key();
+
+        let mut perms = BTreeMap::default();
+        let _ = perms.insert(User::Anyone, PublicPermissions::new(true));
+
+        let _ = client
+            .store_public_register(name, tag, my_pk, perms)
+            .await
+            .map_err(|e| {
+                Error::NetDataError(format!("Failed to store Public Register data: {:?}", e))
+            })?;
+
+        Ok(name)
+    }
+
     pub async fn read_register(
         &self,
         address: RegisterAddress,
+    ) -> Result<BTreeSet<(EntryHash, Entry)>> {
+        if let Some(entries) = self.register_cache.get(&address) {
+            return Ok(entries);
+        }
+
+        self.hold_fetch(FetchTarget::Register(address)).await;
+        let entries = self.read_register_from_network(address).await?;
+        self.register_cache.insert(address, &entries);
+        Ok(entries)
+    }
+
+    async fn read_register_from_network(
+        &self,
+        address: RegisterAddress,
     ) -> Result<BTreeSet<(EntryHash, Entry)>> {
         debug!("Fetching Register data at {:?}", address);
+        self.diagnostics.query();
+
+        #[cfg(feature = "sim")]
+        if let Some((sim, keypair)) = &self.sim {
+            return sim.read_register(address, keypair.public_key()).await;
+        }
 
         let client = self.get_safe_client()?;
 
@@ -200,10 +601,12 @@ impl SafeAppClient {
             if let ClientError::NetworkDataError(SafeNdError::NoSuchEntry) = err {
                 Error::EmptyContent(format!("Empty Register found at {:?}", address))
             } else {
-                Error::NetDataError(format!(
-                    "Failed to read current value from Register data: {:?}",
-                    err
-                ))
+                register_error(
+                    err,
+                    address,
+                    MissingPermission::Reader,
+                    "Failed to read current value from Register data",
+                )
             }
         })
     }
@@ -214,6 +617,15 @@ impl SafeAppClient {
         hash: EntryHash,
     ) -> Result<Entry> {
         debug!("Fetching Register hash {:?} at {:?}", hash, address);
+        self.hold_fetch(FetchTarget::Register(address)).await;
+        self.diagnostics.query();
+
+        #[cfg(feature = "sim")]
+        if let Some((sim, keypair)) = &self.sim {
+            return sim
+                .get_register_entry(address, hash, keypair.public_key())
+                .await;
+        }
 
         let client = self.get_safe_client()?;
         let entry = client
@@ -223,17 +635,126 @@ impl SafeAppClient {
                 if let ClientError::NetworkDataError(SafeNdError::NoSuchEntry) = err {
                     Error::HashNotFound(hash)
                 } else {
-                    Error::NetDataError(format!(
-                        "Failed to retrieve entry with hash '{}' from Register data: {:?}",
-                        encode(hash),
-                        err
-                    ))
+                    register_error(
+                        err,
+                        address,
+                        MissingPermission::Reader,
+                        &format!(
+                            "Failed to retrieve entry with hash '{}' from Register data",
+                            encode(hash)
+                        ),
+                    )
                 }
             })?;
 
         Ok(entry)
     }
 
+    // All the entries of a Register, with the hashes of their parents, read from the
+    // whole Register as fetched from the network. If a depth is given, only the entries
+    // within that many generations from the current entries are returned.
+    pub async fn read_register_history(
+        &self,
+        address: RegisterAddress,
+        depth: Option<usize>,
+    ) -> Result<BTreeMap<EntryHash, (Entry, BTreeSet<EntryHash>)>> {
+        debug!("Fetching history of Register at {:?}", address);
+        self.diagnostics.query();
+
+        #[cfg(feature = "sim")]
+        if let Some((sim, keypair)) = &self.sim {
+            return sim
+                .register_history(address, keypair.public_key(), depth)
+                .await;
+        }
+
+        let client = &self.get_safe_client()?;
+        let register = self
+            .retry_query(move || client.get_register(address))
+            .await
+            .map_err(|(err, trail)| {
+                register_error(
+                    err,
+                    address,
+                    MissingPermission::Reader,
+                    &format!("Failed to read history of Register: {}", trail),
+                )
+            })?;
+
+        let entries = register_dag(&register)?;
+        match depth {
+            Some(depth) => Ok(last_generations(&entries, |(_, parents)| parents, depth)),
+            None => Ok(entries),
+        }
+    }
+
+    // Owner of a Register and the keys allowed to write to it
+    pub async fn get_register_policy(
+        &self,
+        address: RegisterAddress,
+    ) -> Result<(PublicKey, RegisterWriters)> {
+        debug!("Fetching policy of Register at {:?}", address);
+        self.diagnostics.query();
+
+        #[cfg(feature = "sim")]
+        if let Some((sim, keypair)) = &self.sim {
+            let (owner, open, mut keys) =
+                sim.register_policy(address, keypair.public_key()).await?;
+            let writers = if open {
+                RegisterWriters::Anyone
+            } else {
+                let _ = keys.insert(owner);
+                RegisterWriters::Keys(keys)
+            };
+            return Ok((owner, writers));
+        }
+
+        let client = &self.get_safe_client()?;
+        let policy = self
+            .retry_query(move || client.get_register_policy(address))
+            .await
+            .map_err(|(_, trail)| {
+                Error::NetDataError(format!("Failed to get Register policy: {}", trail))
+            })?;
+
+        let (owner, writers) = match policy {
+            Policy::Public(policy) => {
+                let mut keys = BTreeSet::new();
+                let mut anyone = false;
+                for (user, permissions) in policy.permissions.iter() {
+                    if permissions.is_allowed(Action::Write) != Some(true) {
+                        continue;
+                    }
+                    match user {
+                        User::Anyone => anyone = true,
+                        User::Key(key) => {
+                            let _ = keys.insert(*key);
+                        }
+                    }
+                }
+                let _ = keys.insert(policy.owner);
+                let writers = if anyone {
+                    RegisterWriters::Anyone
+                } else {
+                    RegisterWriters::Keys(keys)
+                };
+                (policy.owner, writers)
+            }
+            Policy::Private(policy) => {
+                let mut keys: BTreeSet<PublicKey> = policy
+                    .permissions
+                    .iter()
+                    .filter(|(_, permissions)| permissions.is_allowed(Action::Write))
+                    .map(|(key, _)| *key)
+                    .collect();
+                let _ = keys.insert(policy.owner);
+                (policy.owner, RegisterWriters::Keys(keys))
+            }
+        };
+
+        Ok((owner, writers))
+    }
+
     pub async fn write_to_register(
         &self,
         address: RegisterAddress,
@@ -241,11 +762,156 @@ impl SafeAppClient {
         parents: BTreeSet<EntryHash>,
     ) -> Result<EntryHash> {
         debug!("Writing to Register at {:?}", address);
+        self.check_ephemeral_write(EphemeralWrite::RegisterEntry)?;
+        self.diagnostics.command(0);
+
+        let result = self
+            .write_to_register_on_network(address, entry.clone(), parents.clone())
+            .await;
+        match &result {
+            Ok(hash) => self
+                .register_cache
+                .written(address, *hash, &entry, &parents),
+            Err(_) => self.register_cache.invalidate(&address),
+        }
+        result
+    }
+
+    async fn write_to_register_on_network(
+        &self,
+        address: RegisterAddress,
+        entry: Entry,
+        parents: BTreeSet<EntryHash>,
+    ) -> Result<EntryHash> {
+        #[cfg(feature = "sim")]
+        if let Some((sim, keypair)) = &self.sim {
+            return sim
+                .write_to_register(address, entry, parents, keypair.public_key())
+                .await;
+        }
+
         let client = self.get_safe_client()?;
 
         client
             .write_to_register(address, entry, parents)
             .await
-            .map_err(|e| Error::NetDataError(format!("Failed to write to Register: {:?}", e)))
+            .map_err(|err| {
+                register_error(
+                    err,
+                    address,
+                    MissingPermission::Writer,
+                    "Failed to write to Register",
+                )
+            })
+    }
+
+    // Only private Registers can be deleted, and only by their owner
+    pub async fn delete_register(&self, address: RegisterAddress) -> Result<()> {
+        debug!("Deleting Register at {:?}", address);
+        self.check_ephemeral_write(EphemeralWrite::RegisterDeletion)?;
+        self.diagnostics.command(0);
+        self.register_cache.invalidate(&address);
+
+        #[cfg(feature = "sim")]
+        if let Some((sim, keypair)) = &self.sim {
+            return sim.delete_register(address, keypair.public_key()).await;
+        }
+
+        let client = self.get_safe_client()?;
+        client.delete_register(address).await.map_err(|err| {
+            register_error(
+                err,
+                address,
+                MissingPermission::Owner,
+                "Failed to delete Register",
+            )
+        })
+    }
+
+    // The network doesn't support writing several entries with a single Register command,
+    // thus the entries are written concurrently so the round trips overlap rather than adding
+    // up, returning the outcome of each write
+    pub async fn write_entries_to_register(
+        &self,
+        address: RegisterAddress,
+        entries: Vec<(Entry, BTreeSet<EntryHash>)>,
+    ) -> Result<Vec<Result<EntryHash>>> {
+        debug!(
+            "Writing {} entries to Register at {:?}",
+            entries.len(),
+            address
+        );
+        self.check_ephemeral_write(EphemeralWrite::RegisterEntry)?;
+
+        let written = entries.clone();
+        let results = self
+            .write_entries_to_register_on_network(address, entries)
+            .await?;
+        for (result, (entry, parents)) in results.iter().zip(written.iter()) {
+            match result {
+                Ok(hash) => self.register_cache.written(address, *hash, entry, parents),
+                Err(_) => self.register_cache.invalidate(&address),
+            }
+        }
+        Ok(results)
+    }
+
+    async fn write_entries_to_register_on_network(
+        &self,
+        address: RegisterAddress,
+        entries: Vec<(Entry, BTreeSet<EntryHash>)>,
+    ) -> Result<Vec<Result<EntryHash>>> {
+        #[cfg(feature = "sim")]
+        if let Some((sim, keypair)) = &self.sim {
+            let mut results = vec![];
+            for (entry, parents) in entries.into_iter() {
+                self.diagnostics.command(0);
+                results.push(
+                    sim.write_to_register(address, entry, parents, keypair.public_key())
+                        .await,
+                );
+            }
+            return Ok(results);
+        }
+
+        let client = self.get_safe_client()?;
+        let writes = entries.into_iter().map(|(entry, parents)| {
+            self.diagnostics.command(0);
+            client.write_to_register(address, entry, parents)
+        });
+
+        Ok(join_all(writes)
+            .await
+            .into_iter()
+            .map(|result| {
+                result.map_err(|err| {
+                    register_error(
+                        err,
+                        address,
+                        MissingPermission::Writer,
+                        "Failed to write entry to Register",
+                    )
+                })
+            })
+            .collect())
+    }
+}
+
+fn register_error(
+    err: ClientError,
+    address: RegisterAddress,
+    missing: MissingPermission,
+    failure: &str,
+) -> Error {
+    match err {
+        ClientError::NetworkDataError(SafeNdError::AccessDenied(_)) => Error::PermissionDenied {
+            missing,
+            address: format!("{:?}", address),
+        },
+        ClientError::ErrorMessage {
+            source: ErrorMessage::DataNotFound(_),
+            ..
+        } => Error::ContentNotFound(format!("No Register found at {:?}", address)),
+        err => Error::NetDataError(format!("{}: {:?}", failure, err)),
     }
 }
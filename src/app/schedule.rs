@@ -0,0 +1,208 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    encryption::{decrypt_payload, encrypt_payload, SymmetricKey},
+    nrs::validate_nrs_name,
+};
+use crate::{Error, Result, Safe, Url, XorUrl};
+use bytes::Bytes;
+use chrono::Utc;
+use futures::{
+    channel::oneshot,
+    future::{abortable, AbortHandle},
+};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Content uploaded encrypted, whose decrypted version is to be published,
+/// and linked from an NRS name, at a future time.
+///
+/// The record can be serialised and persisted, e.g. for an external cron job to call
+/// `Safe::release_publication` once it's due. Note it contains the decryption key,
+/// thus it must be kept secret until the release time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledPublication {
+    /// NRS name to link to the content upon release
+    pub nrs_name: String,
+    /// XOR-URL of the encrypted content
+    pub encrypted_xorurl: XorUrl,
+    /// Release time, as seconds since the Unix epoch
+    pub release_at: i64,
+    key: SymmetricKey,
+}
+
+impl ScheduledPublication {
+    /// Check if the release time has been reached
+    pub fn is_due(&self) -> bool {
+        Utc::now().timestamp() >= self.release_at
+    }
+}
+
+/// A background task releasing a scheduled publication at its release time
+pub struct PublicationTask {
+    abort_handle: AbortHandle,
+    outcome: oneshot::Receiver<Result<XorUrl>>,
+}
+
+impl PublicationTask {
+    /// Cancel the publication, the content is never released if it wasn't yet
+    pub fn cancel(self) {
+        self.abort_handle.abort();
+    }
+
+    /// Wait for the content to be released, returning the XOR-URL of the published content
+    pub async fn join(self) -> Result<XorUrl> {
+        self.outcome
+            .await
+            .map_err(|_| Error::ContentError("Scheduled publication didn't complete".to_string()))?
+    }
+}
+
+impl Safe {
+    /// # Schedule the publication of some content
+    ///
+    /// The content is encrypted with a new random key and uploaded right away, while
+    /// publishing it is deferred until the release time, see `release_publication`.
+    /// The NRS name must already exist and be owned by the user, its link is
+    /// flipped to the published content upon release.
+    pub async fn schedule_publication(
+        &self,
+        nrs_name: &str,
+        content: Bytes,
+        release_at: i64,
+    ) -> Result<ScheduledPublication> {
+        info!("Scheduling publication on '{}' at {}", nrs_name, release_at);
+        let _ = validate_nrs_name(nrs_name)?;
+
+        let key: SymmetricKey = rand::random();
+        let encrypted_content = encrypt_payload(&self.encryption_policy, &key, &content)?;
+        let encrypted_xorurl = self
+            .store_public_bytes(Bytes::from(encrypted_content), None, false)
+            .await?;
+
+        Ok(ScheduledPublication {
+            nrs_name: nrs_name.to_string(),
+            encrypted_xorurl,
+            release_at,
+            key,
+        })
+    }
+
+    /// Release a scheduled publication, decrypting and publishing the content and linking
+    /// the NRS name to it, returning the XOR-URL of the published content.
+    /// It fails with `Error::InvalidInput` if the release time has not been reached yet.
+    pub async fn release_publication(&self, publication: &ScheduledPublication) -> Result<XorUrl> {
+        if !publication.is_due() {
+            return Err(Error::InvalidInput(format!(
+                "Publication on '{}' is not due until {}",
+                publication.nrs_name, publication.release_at
+            )));
+        }
+
+        info!("Releasing publication on '{}'", publication.nrs_name);
+        let encrypted_content = self
+            .fetch_public_data(&Url::from_xorurl(&publication.encrypted_xorurl)?, None)
+            .await?;
+        let content = decrypt_payload(
+            &self.encryption_policy,
+            &publication.key,
+            &encrypted_content,
+        )?;
+        let xorurl = self
+            .store_public_bytes(Bytes::from(content), None, false)
+            .await?;
+
+        let _ = self
+            .nrs_map_container_add(&publication.nrs_name, &xorurl, false, false, false)
+            .await?;
+
+        Ok(xorurl)
+    }
+
+    /// Spawn a background task, on the runtime set for this instance, which waits until
+    /// the release time of the publication to release it, and which can be cancelled until then
+    pub fn spawn_publication(&self, publication: ScheduledPublication) -> PublicationTask {
+        let safe = self.clone();
+        let runtime = self.runtime();
+        let (outcome_sender, outcome) = oneshot::channel();
+        let (task, abort_handle) = abortable(async move {
+            let wait = publication.release_at - Utc::now().timestamp();
+            if wait > 0 {
+                debug!(
+                    "Waiting {} secs to release publication on '{}'",
+                    wait, publication.nrs_name
+                );
+                safe.runtime().sleep(Duration::from_secs(wait as u64)).await;
+            }
+            let _ = outcome_sender.send(safe.release_publication(&publication).await);
+        });
+        runtime.spawn(Box::pin(async move {
+            let _ = task.await;
+        }));
+
+        PublicationTask {
+            abort_handle,
+            outcome,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::test_helpers::{new_safe_instance, random_nrs_name},
+        retry_loop,
+    };
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_schedule_and_release_publication() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let site_name = random_nrs_name();
+        let placeholder_xorurl = safe
+            .store_public_bytes(Bytes::from("coming soon"), None, false)
+            .await?;
+        let _ = retry_loop!(safe.nrs_map_container_create(
+            &site_name,
+            &placeholder_xorurl,
+            true,
+            false,
+            false
+        ));
+
+        let content = Bytes::from("the story");
+        let mut publication = safe
+            .schedule_publication(&site_name, content.clone(), Utc::now().timestamp() + 3600)
+            .await?;
+        assert!(!publication.is_due());
+        assert!(safe.release_publication(&publication).await.is_err());
+
+        // the content uploaded is not readable before its release
+        let encrypted = retry_loop!(
+            safe.fetch_public_data(&Url::from_xorurl(&publication.encrypted_xorurl)?, None)
+        );
+        assert_ne!(encrypted, content);
+
+        publication.release_at = Utc::now().timestamp();
+        let task = safe.spawn_publication(publication);
+        let xorurl = task.join().await?;
+
+        let released = retry_loop!(safe.fetch_public_data(&Url::from_xorurl(&xorurl)?, None));
+        assert_eq!(released, content);
+        let (resolved_url, _) = safe
+            .parse_and_resolve_url(&format!("safe://{}", site_name))
+            .await?;
+        assert_eq!(resolved_url.xorname(), Url::from_xorurl(&xorurl)?.xorname());
+
+        Ok(())
+    }
+}
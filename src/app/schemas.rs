@@ -0,0 +1,96 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! JSON Schema documents of the outputs of the inspection APIs once serialised as JSON,
+//! e.g. with `serde_json`, for monitoring systems and scripts consuming them.
+//!
+//! The outputs are stable for a given `SCHEMA_VERSION`, which is only bumped along with
+//! the major version of this crate: the properties described are neither removed, renamed,
+//! nor changed in type, although new properties may be added, thus consumers should ignore
+//! the properties they don't know about. Only `Safe::inspect` and `Safe::register_stats`
+//! are covered for now, as the only inspection APIs there are.
+
+/// Version of the schemas, part of the `$id` of each of them
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Schema of the output of `Safe::inspect`, i.e. a list of `SafeData`
+pub const INSPECT_SCHEMA: &str = include_str!("schemas/inspect.json");
+
+/// Schema of the output of `Safe::register_stats`, i.e. a `RegisterStats`
+pub const REGISTER_STATS_SCHEMA: &str = include_str!("schemas/register_stats.json");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fetch::SafeData, register::RegisterStats, XorName};
+    use anyhow::{anyhow, Result};
+    use serde_json::Value;
+
+    // Check the object has the properties the schema requires it to have
+    fn check_required(schema: &Value, value: &Value) -> Result<()> {
+        let required = schema["required"]
+            .as_array()
+            .ok_or_else(|| anyhow!("Schema without required properties: {}", schema))?;
+        for property in required {
+            let property = property
+                .as_str()
+                .ok_or_else(|| anyhow!("Invalid required property: {}", property))?;
+            if value.get(property).is_none() {
+                return Err(anyhow!("Missing property {} in {}", property, value));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_schemas_versioned() -> Result<()> {
+        for schema in &[INSPECT_SCHEMA, REGISTER_STATS_SCHEMA] {
+            let schema: Value = serde_json::from_str(schema)?;
+            let id = schema["$id"]
+                .as_str()
+                .ok_or_else(|| anyhow!("Schema without $id"))?;
+            assert!(id.contains(&format!(":v{}:", SCHEMA_VERSION)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_stats_schema() -> Result<()> {
+        let schema: Value = serde_json::from_str(REGISTER_STATS_SCHEMA)?;
+        let stats = serde_json::to_value(RegisterStats {
+            entries: 3,
+            tips: 1,
+            estimated_bytes: 300,
+            complete: true,
+        })?;
+        check_required(&schema, &stats)
+    }
+
+    #[test]
+    fn test_inspect_schema() -> Result<()> {
+        let schema: Value = serde_json::from_str(INSPECT_SCHEMA)?;
+        let output = serde_json::to_value(vec![SafeData::SafeKey {
+            xorurl: "safe://key".to_string(),
+            xorname: XorName([7; 32]),
+            resolved_from: "safe://key".to_string(),
+        }])?;
+
+        let item = &output[0];
+        let definition = &schema["definitions"]["SafeKey"];
+        check_required(definition, item)?;
+        check_required(&definition["properties"]["SafeKey"], &item["SafeKey"])?;
+        assert_eq!(
+            item["SafeKey"]["xorname"]
+                .as_array()
+                .map(|bytes| bytes.len()),
+            Some(32)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::helpers::glob_match;
+use crate::{Result, Safe};
+use log::debug;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+/// Kind of the content indexed in the local search index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IndexedKind {
+    NrsName,
+    Bookmark,
+    FilesContainer,
+}
+
+/// An item of the local search index matching a query
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub kind: IndexedKind,
+    /// The name the item is known by, e.g. the NRS name, or the bookmark's title
+    pub name: String,
+    pub url: String,
+    /// How well the item matches the query, the higher the better
+    pub score: u32,
+}
+
+// Scores given to each type of match of a query
+const EXACT_MATCH_SCORE: u32 = 100;
+const PREFIX_MATCH_SCORE: u32 = 75;
+const SUBSTRING_MATCH_SCORE: u32 = 50;
+const PATTERN_MATCH_SCORE: u32 = 25;
+
+// Local index of the content created by the user with this instance, which is updated
+// incrementally as NRS names, bookmarks and FilesContainers are created or removed.
+// Clones of an instance share the same index.
+#[derive(Clone, Default)]
+pub(crate) struct LocalIndex {
+    items: Arc<Mutex<BTreeMap<(IndexedKind, String), String>>>,
+}
+
+impl LocalIndex {
+    // Add or replace an item, keyed by its kind and URL
+    pub(crate) fn insert(&self, kind: IndexedKind, name: &str, url: &str) {
+        if let Ok(mut items) = self.items.lock() {
+            let _ = items.insert((kind, url.to_string()), name.to_string());
+        }
+    }
+
+    pub(crate) fn remove(&self, kind: IndexedKind, url: &str) {
+        if let Ok(mut items) = self.items.lock() {
+            let _ = items.remove(&(kind, url.to_string()));
+        }
+    }
+
+    fn search(&self, query: &str) -> Vec<SearchResult> {
+        let query = query.to_lowercase();
+        let mut results: Vec<SearchResult> = match self.items.lock() {
+            Ok(items) => items
+                .iter()
+                .filter_map(|((kind, url), name)| {
+                    let score = std::cmp::max(score(&query, name), score(&query, url));
+                    if score > 0 {
+                        Some(SearchResult {
+                            kind: *kind,
+                            name: name.clone(),
+                            url: url.clone(),
+                            score,
+                        })
+                    } else {
+                        None
+                    }
+                })
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        results.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.name.cmp(&b.name)));
+        results
+    }
+}
+
+// Score how well a text matches a (lowercase) query, zero meaning it doesn't match
+fn score(query: &str, text: &str) -> u32 {
+    let text = text.to_lowercase();
+    if query.contains('*') {
+        if glob_match(query, &text) {
+            PATTERN_MATCH_SCORE
+        } else {
+            0
+        }
+    } else if text == query {
+        EXACT_MATCH_SCORE
+    } else if text.starts_with(query) {
+        PREFIX_MATCH_SCORE
+    } else if text.contains(query) {
+        SUBSTRING_MATCH_SCORE
+    } else {
+        0
+    }
+}
+
+impl Safe {
+    /// # Search the content created by the user
+    ///
+    /// Search the NRS names, bookmarks, and FilesContainers created with this instance
+    /// whose name or URL match the query, ignoring case, ranking exact matches first,
+    /// followed by prefix and substring matches. The query can also be a pattern using
+    /// `*` as a wildcard, e.g. `blog*`.
+    pub fn search_local(&self, query: &str) -> Vec<SearchResult> {
+        self.local_index.search(query)
+    }
+
+    /// Add to the local search index the bookmarks found in the user's bookmarks
+    /// collection, e.g. those created from another device
+    pub async fn search_local_reindex(&self) -> Result<()> {
+        let bookmarks = self.bookmarks_list().await?;
+        debug!("Indexing {} bookmarks", bookmarks.len());
+        for bookmark in bookmarks.iter() {
+            self.local_index
+                .insert(IndexedKind::Bookmark, &bookmark.title, &bookmark.url);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_local_ranking() {
+        let safe = Safe::default();
+        safe.local_index
+            .insert(IndexedKind::NrsName, "myblog", "safe://myblog");
+        safe.local_index
+            .insert(IndexedKind::NrsName, "blog", "safe://blog");
+        safe.local_index
+            .insert(IndexedKind::Bookmark, "A friend's Blog", "safe://friend");
+        safe.local_index
+            .insert(IndexedKind::FilesContainer, "./photos", "safe://hyfktce");
+
+        let results = safe.search_local("BLOG");
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["blog", "A friend's Blog", "myblog"]);
+        assert_eq!(results[0].score, EXACT_MATCH_SCORE);
+
+        let results = safe.search_local("safe://*blog");
+        assert_eq!(results.len(), 2);
+
+        safe.local_index.remove(IndexedKind::NrsName, "safe://blog");
+        let results = safe.search_local("blog");
+        assert_eq!(results.len(), 2);
+        assert!(safe.search_local("photos")[0].kind == IndexedKind::FilesContainer);
+    }
+}
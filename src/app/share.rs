@@ -0,0 +1,511 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    capability::{CapabilityAccess, CapabilityGrant},
+    consts::{PREDICATE_LINK, PREDICATE_VARIANTS},
+    encryption::{decrypt_payload, encrypt_payload, keyed_hash, SymmetricKey, SYMMETRIC_KEY_LEN},
+    files::FilesMap,
+};
+use crate::{ContentType, Error, PublicKey, Result, Safe, Scope, Url, UrlAddressExt, XorName};
+use bytes::Bytes;
+use chrono::Utc;
+use log::{debug, info};
+use safe_network::types::Signature;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// Type tag to use for the Registers holding the share links revoked by each owner
+const SHARE_REVOCATIONS_TYPE_TAG: u64 = 2_300;
+
+// Context used to derive the location of the revocation list of an owner
+const SHARE_REVOCATIONS_CONTEXT: &[u8] = b"sn_api-share-revocations";
+
+// Query parameter of a share link carrying the key its content key is wrapped with
+const SHARE_LINK_KEY_PARAM: &str = "?key=";
+
+// Each entry of a revocation list links to a Blob holding the name of the capability
+// revoked, signed by the owner who issued it
+#[derive(Debug, Serialize, Deserialize)]
+struct SignedRevocation {
+    capability: XorName,
+    signature: Signature,
+}
+
+impl Safe {
+    /// # Create a time-limited share link for a Private Blob
+    ///
+    /// The content is re-encrypted with a new random key, and stored together with a
+    /// capability, signed with the signer of this instance, which
+    /// carries such key wrapped, and the expiry time (as seconds since the Unix epoch).
+    /// The key to unwrap it is only found on the returned link, thus anyone having the
+    /// link can read the content with `open_share_link` until it expires, or until the
+    /// link is revoked with `revoke_share_link`.
+    pub async fn create_share_link(&self, url: &str, expires_at: i64) -> Result<String> {
+        info!("Creating share link for {} until {}", url, expires_at);
+        let content = self.private_blob_handle(url)?.read().await?;
+        self.store_shared_content(&content, vec![], Some(expires_at))
+            .await
+    }
+
+    /// # Read the content shared with a share link
+    ///
+    /// It fails with an `AccessDenied` error if the link has expired, or if it was
+    /// revoked by the owner who created it.
+    pub async fn open_share_link(&self, link: &str) -> Result<Bytes> {
+        debug!("Opening share link: {}", link);
+        let content = self.fetch_shared_content(link).await?;
+        Ok(Bytes::from(content))
+    }
+
+    /// # Create a link revealing only the metadata of a private FilesContainer
+    ///
+    /// The link discloses the paths of the files and their metadata, e.g. their type,
+    /// size and times, but not the links to their content, so others can browse the
+    /// FilesContainer before being granted access to its content. It can be restricted to
+    /// the files under a path, as well as to expire at a time (as seconds since the Unix
+    /// epoch). The FilesMap at the version the URL targets, or its latest version otherwise,
+    /// is disclosed, thus later versions are not revealed by the link. It's opened with
+    /// `open_metadata_link`, and can be revoked with `revoke_share_link`.
+    pub async fn create_metadata_link(
+        &self,
+        url: &str,
+        subtree: Option<&str>,
+        expires_at: Option<i64>,
+    ) -> Result<String> {
+        info!("Creating metadata link for {}", url);
+        let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+        if safe_url.register_parts()?.2 != Scope::Private {
+            return Err(Error::InvalidInput(format!(
+                "Metadata links can only be created for private FilesContainers: {}",
+                url
+            )));
+        }
+        let (_, files_map) = self.fetch_files_container(&safe_url).await?;
+
+        let metadata = metadata_only(files_map, subtree);
+        let serialised_metadata = serde_json::to_vec(&metadata).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise the metadata: {:?}", err))
+        })?;
+        let paths = subtree
+            .map(|path| vec![path.to_string()])
+            .unwrap_or_default();
+
+        self.store_shared_content(&serialised_metadata, paths, expires_at)
+            .await
+    }
+
+    /// # Browse the metadata shared with a metadata link
+    ///
+    /// Returns the FilesMap disclosed by the link, without the links to the files' content.
+    /// It fails with an `AccessDenied` error if the link has expired, or if it was revoked
+    /// by the owner who created it.
+    pub async fn open_metadata_link(&self, link: &str) -> Result<FilesMap> {
+        debug!("Opening metadata link: {}", link);
+        let serialised_metadata = self.fetch_shared_content(link).await?;
+        serde_json::from_slice(&serialised_metadata)
+            .map_err(|_| Error::ContentError(format!("\"{}\" is not a metadata link", link)))
+    }
+
+    // Private helper to encrypt some content with a new random key and store it together
+    // with a capability carrying such key wrapped, returning the link to the capability
+    async fn store_shared_content(
+        &self,
+        content: &[u8],
+        paths: Vec<String>,
+        expires_at: Option<i64>,
+    ) -> Result<String> {
+        let content_key: SymmetricKey = rand::random();
+        let encrypted_content = encrypt_payload(&self.encryption_policy, &content_key, content)?;
+        let target = self
+            .store_public_bytes(Bytes::from(encrypted_content), None, false)
+            .await?;
+
+        let link_key: SymmetricKey = rand::random();
+        let wrapped_key = encrypt_payload(&self.encryption_policy, &link_key, &content_key)?;
+        let grant = CapabilityGrant {
+            target,
+            paths,
+            access: CapabilityAccess::Read,
+            expires_at,
+            issuer: self.signer_public_key()?,
+            wrapped_key: Some(wrapped_key),
+        };
+        let capability_xorurl = self.store_capability(grant).await?;
+
+        Ok(format!(
+            "{}{}{}",
+            capability_xorurl,
+            SHARE_LINK_KEY_PARAM,
+            hex::encode(link_key)
+        ))
+    }
+
+    // Private helper to fetch and decrypt the content shared with a link,
+    // checking it has not expired nor been revoked
+    async fn fetch_shared_content(&self, link: &str) -> Result<Vec<u8>> {
+        let (capability_url, link_key) = parse_share_link(link)?;
+        let grant = self.fetch_capability(capability_url).await?;
+
+        let wrapped_key = grant.wrapped_key.as_ref().ok_or_else(|| {
+            Error::InvalidInput(format!("\"{}\" is not a share link", capability_url))
+        })?;
+        if grant
+            .expires_at
+            .map_or(false, |expires_at| expires_at <= Utc::now().timestamp())
+        {
+            return Err(Error::AccessDenied("Share link has expired".to_string()));
+        }
+        let capability_xorname = Url::from_url(capability_url)?.xorname();
+        if self
+            .share_links_revoked(&grant.issuer)
+            .await?
+            .contains(&capability_xorname)
+        {
+            return Err(Error::AccessDenied(
+                "Share link has been revoked by its owner".to_string(),
+            ));
+        }
+
+        let content_key = decrypt_payload(&self.encryption_policy, &link_key, wrapped_key)?;
+        if content_key.len() != SYMMETRIC_KEY_LEN {
+            return Err(Error::ContentError(
+                "Key wrapped in share link is invalid".to_string(),
+            ));
+        }
+        let mut key = [0; SYMMETRIC_KEY_LEN];
+        key.copy_from_slice(&content_key);
+
+        let encrypted_content = self
+            .fetch_public_data(&Url::from_url(&grant.target)?, None)
+            .await?;
+        let content = decrypt_payload(&self.encryption_policy, &key, &encrypted_content)?;
+        Ok(content)
+    }
+
+    /// # Revoke a share link
+    ///
+    /// The link is added to the revocation list of the owner, signed with the signer of
+    /// this instance, which is checked upon opening any share link. Only the owner who
+    /// created the link can revoke it, and it fails if someone else owns the Register at
+    /// the location of the owner's revocation list, e.g. if it was squatted, or if the
+    /// signer is not the keypair this instance is connected with, as such Register must
+    /// be owned by the key the links are signed with.
+    pub async fn revoke_share_link(&self, link: &str) -> Result<()> {
+        let (capability_url, _) = parse_share_link(link)?;
+        let grant = self.fetch_capability(capability_url).await?;
+        let owner = self.signer_public_key()?;
+        if grant.issuer != owner {
+            return Err(Error::AccessDenied(
+                "Only the owner who created a share link can revoke it".to_string(),
+            ));
+        }
+        if self.get_my_keypair()?.public_key() != owner {
+            return Err(Error::InvalidInput(
+                "Share links can only be revoked when signing with the keypair this instance is connected with".to_string(),
+            ));
+        }
+
+        let (xorname, revocations_url) = self.share_revocations_location(&owner)?;
+        match self.fetch_register_entries(&revocations_url).await {
+            Ok(_) | Err(Error::EmptyContent(_)) => {}
+            Err(Error::ContentNotFound(_)) => {
+                debug!("Revocation list not found, creating it");
+                let _ = self
+                    .register_create(Some(xorname), SHARE_REVOCATIONS_TYPE_TAG, false)
+                    .await?;
+            }
+            Err(err) => return Err(err),
+        }
+        if !self.revocations_owned_by(&revocations_url, &owner).await? {
+            return Err(Error::AccessDenied(format!(
+                "The revocation list at \"{}\" is owned by someone else",
+                revocations_url
+            )));
+        }
+
+        let capability = Url::from_xorurl(capability_url)?.xorname();
+        let signature = self
+            .signer()?
+            .sign(&revocation_signed_bytes(&capability))
+            .await?;
+        let serialised_revocation = rmp_serde::to_vec_named(&SignedRevocation {
+            capability,
+            signature,
+        })
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise revocation: {:?}", err)))?;
+        let revocation_xorurl = self
+            .store_public_bytes(Bytes::from(serialised_revocation), None, false)
+            .await?;
+
+        // Revocations don't supersede each other, thus they are all written without parents
+        let _ = self
+            .write_to_register(
+                &revocations_url.to_string(),
+                Url::from_xorurl(&revocation_xorurl)?,
+                BTreeSet::new(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    // Private helper to read the names of the capabilities revoked by an owner. Anyone could
+    // create a Register at the location of someone else's revocation list, thus it's only
+    // trusted if it's owned by the owner, and only the revocations signed by it are read.
+    async fn share_links_revoked(&self, owner: &PublicKey) -> Result<BTreeSet<XorName>> {
+        let (_, revocations_url) = self.share_revocations_location(owner)?;
+        let entries = match self.fetch_register_entries(&revocations_url).await {
+            Ok(entries) => entries,
+            Err(Error::EmptyContent(_)) | Err(Error::ContentNotFound(_)) => {
+                return Ok(BTreeSet::new())
+            }
+            Err(err) => return Err(err),
+        };
+        if !self.revocations_owned_by(&revocations_url, owner).await? {
+            debug!(
+                "Ignoring revocation list at {} not owned by {:?}",
+                revocations_url, owner
+            );
+            return Ok(BTreeSet::new());
+        }
+
+        let mut revoked = BTreeSet::new();
+        for (_, entry) in entries {
+            let revocation = match self.fetch_public_data(&entry, None).await {
+                Ok(serialised) => rmp_serde::from_slice::<SignedRevocation>(&serialised).ok(),
+                Err(Error::ContentNotFound(_)) => None,
+                Err(err) => return Err(err),
+            };
+            match revocation {
+                Some(revocation)
+                    if owner
+                        .verify(
+                            &revocation.signature,
+                            &revocation_signed_bytes(&revocation.capability),
+                        )
+                        .is_ok() =>
+                {
+                    let _ = revoked.insert(revocation.capability);
+                }
+                _ => debug!("Ignoring revocation not signed by {:?}: {}", owner, entry),
+            }
+        }
+
+        Ok(revoked)
+    }
+
+    // Private helper to check the Register of a revocation list is owned by the given key
+    async fn revocations_owned_by(&self, revocations_url: &Url, owner: &PublicKey) -> Result<bool> {
+        let (register_owner, _) = self
+            .safe_client
+            .get_register_policy(revocations_url.register_address()?)
+            .await?;
+        Ok(register_owner == *owner)
+    }
+
+    // Private helper to obtain the location of the revocation list of an owner
+    fn share_revocations_location(&self, owner: &PublicKey) -> Result<(XorName, Url)> {
+        let xorname = XorName(keyed_hash(
+            SHARE_REVOCATIONS_CONTEXT,
+            &XorName::from(*owner).0,
+        ));
+        let xorurl = Url::encode_register(
+            xorname,
+            SHARE_REVOCATIONS_TYPE_TAG,
+            Scope::Public,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?;
+
+        Ok((xorname, Url::from_xorurl(&xorurl)?))
+    }
+}
+
+// Keep the files under the subtree, if any, without the links to their content
+fn metadata_only(files_map: FilesMap, subtree: Option<&str>) -> FilesMap {
+    let subtree = subtree.map(|path| format!("/{}", path.trim_matches('/')));
+    files_map
+        .into_iter()
+        .filter(|(path, _)| {
+            subtree.as_ref().map_or(true, |subtree| {
+                subtree == "/" || path == subtree || path.starts_with(&format!("{}/", subtree))
+            })
+        })
+        .map(|(path, mut file_item)| {
+            let _ = file_item.remove(PREDICATE_LINK);
+            let _ = file_item.remove(PREDICATE_VARIANTS);
+            (path, file_item)
+        })
+        .collect()
+}
+
+// Bytes signed by the owner revoking a capability
+fn revocation_signed_bytes(capability: &XorName) -> Vec<u8> {
+    let mut bytes = SHARE_REVOCATIONS_CONTEXT.to_vec();
+    bytes.extend_from_slice(&capability.0);
+    bytes
+}
+
+// Split a share link into its capability URL and the key its content key is wrapped with
+fn parse_share_link(link: &str) -> Result<(&str, SymmetricKey)> {
+    let invalid_link = || Error::InvalidInput(format!("\"{}\" is not a valid share link", link));
+    let index = link.rfind(SHARE_LINK_KEY_PARAM).ok_or_else(invalid_link)?;
+    let (capability_url, key_hex) = link.split_at(index);
+
+    let bytes = hex::decode(&key_hex[SHARE_LINK_KEY_PARAM.len()..]).map_err(|_| invalid_link())?;
+    if bytes.len() != SYMMETRIC_KEY_LEN {
+        return Err(invalid_link());
+    }
+    let mut key = [0; SYMMETRIC_KEY_LEN];
+    key.copy_from_slice(&bytes);
+
+    Ok((capability_url, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop, retry_loop_for_pattern};
+    use anyhow::{anyhow, Result};
+
+    #[test]
+    fn test_parse_share_link() -> Result<()> {
+        let key: SymmetricKey = rand::random();
+        let link = format!("safe://hnyynyz{}{}", SHARE_LINK_KEY_PARAM, hex::encode(key));
+        assert_eq!(parse_share_link(&link)?, ("safe://hnyynyz", key));
+
+        assert!(parse_share_link("safe://hnyynyz").is_err());
+        assert!(parse_share_link("safe://hnyynyz?key=abcd").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_only() {
+        let file_item = |link: &str| -> crate::files::FileItem {
+            vec![
+                (PREDICATE_LINK.to_string(), link.to_string()),
+                ("size".to_string(), "42".to_string()),
+            ]
+            .into_iter()
+            .collect()
+        };
+        let files_map: FilesMap = vec![
+            ("/docs/a.md".to_string(), file_item("safe://a")),
+            ("/docs-old/b.md".to_string(), file_item("safe://b")),
+            ("/c.md".to_string(), file_item("safe://c")),
+        ]
+        .into_iter()
+        .collect();
+
+        let all = metadata_only(files_map.clone(), None);
+        assert_eq!(all.len(), 3);
+        assert!(all.values().all(|item| !item.contains_key(PREDICATE_LINK)
+            && item.get("size") == Some(&"42".to_string())));
+
+        let docs = metadata_only(files_map, Some("docs/"));
+        assert_eq!(docs.keys().collect::<Vec<_>>(), vec!["/docs/a.md"]);
+    }
+
+    #[tokio::test]
+    async fn test_metadata_link() -> Result<()> {
+        let mut owner = new_safe_instance().await?;
+        owner.set_private_by_default(true);
+        let reader = new_safe_instance().await?;
+        let (xorurl, _, files_map) = owner
+            .files_container_create(Some("./testdata/"), None, true, false, false)
+            .await?;
+        let _ = retry_loop!(owner.files_container_get(&xorurl));
+
+        let link = owner
+            .create_metadata_link(&xorurl, Some("/subfolder"), None)
+            .await?;
+        let metadata = retry_loop!(reader.open_metadata_link(&link));
+        assert!(!metadata.is_empty());
+        for (path, item) in metadata.iter() {
+            assert!(path.starts_with("/subfolder/"));
+            assert!(!item.contains_key(PREDICATE_LINK));
+            assert_eq!(item.get("size"), files_map[path].get("size"));
+        }
+
+        owner.revoke_share_link(&link).await?;
+        let _ = retry_loop_for_pattern!(
+            reader.open_metadata_link(&link),
+            Err(Error::AccessDenied(_))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_share_link_expiry_and_revocation() -> Result<()> {
+        let owner = new_safe_instance().await?;
+        let reader = new_safe_instance().await?;
+        let data = Bytes::from("Something to share for a while");
+        let xorurl = owner.store_private_bytes(data.clone(), None).await?;
+        let _ = retry_loop!(owner.private_blob_handle(&xorurl)?.read());
+
+        let link = owner
+            .create_share_link(&xorurl, Utc::now().timestamp() + 3600)
+            .await?;
+        let shared = retry_loop!(reader.open_share_link(&link));
+        assert_eq!(shared, data);
+
+        let expired_link = owner.create_share_link(&xorurl, 0).await?;
+        let _ = retry_loop!(reader.preview_capability(parse_share_link(&expired_link)?.0));
+        match reader.open_share_link(&expired_link).await {
+            Err(Error::AccessDenied(_)) => {}
+            other => return Err(anyhow!("Unexpected result: {:?}", other)),
+        }
+
+        // only the owner can revoke the link
+        assert!(reader.revoke_share_link(&link).await.is_err());
+        owner.revoke_share_link(&link).await?;
+        let _ = retry_loop_for_pattern!(reader.open_share_link(&link), Err(Error::AccessDenied(_)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_share_link_squatted_revocations() -> Result<()> {
+        let owner = new_safe_instance().await?;
+        let squatter = new_safe_instance().await?;
+        let xorurl = owner
+            .store_private_bytes(Bytes::from("Shared"), None)
+            .await?;
+        let _ = retry_loop!(owner.private_blob_handle(&xorurl)?.read());
+        let link = owner
+            .create_share_link(&xorurl, Utc::now().timestamp() + 3600)
+            .await?;
+
+        // someone else creates a Register at the location of the owner's revocation list,
+        // listing the link as revoked
+        let (xorname, revocations_url) =
+            squatter.share_revocations_location(&owner.signer_public_key()?)?;
+        let _ = squatter
+            .register_create(Some(xorname), SHARE_REVOCATIONS_TYPE_TAG, false)
+            .await?;
+        let _ = squatter
+            .write_to_register(
+                &revocations_url.to_string(),
+                Url::from_url(parse_share_link(&link)?.0)?,
+                BTreeSet::new(),
+            )
+            .await?;
+        let _ = retry_loop!(squatter.register_read(&revocations_url.to_string()));
+
+        let shared = retry_loop!(squatter.open_share_link(&link));
+        assert_eq!(shared, Bytes::from("Shared"));
+        assert!(matches!(
+            owner.revoke_share_link(&link).await,
+            Err(Error::AccessDenied(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::Safe;
+use crate::{PublicKey, Result};
+use async_trait::async_trait;
+use safe_network::types::{Keypair, Signature};
+use std::sync::Arc;
+
+/// Signs the content published by the API on behalf of the user, e.g. NRS registration
+/// receipts, capabilities, proofs, channel updates, Register envelopes, or signed Register
+/// entries, see `Safe::set_signer`. It can be implemented to keep the private key out of
+/// the process memory, e.g. in an HSM, a hardware token, or a remote signing service.
+#[async_trait]
+pub trait Signer: Send + Sync {
+    /// Public key the signatures are verified with
+    fn public_key(&self) -> PublicKey;
+
+    /// Sign the data, failing with `Error::SignerError` if the signer can't, e.g.
+    /// because the device isn't available or the user declined the request
+    async fn sign(&self, data: &[u8]) -> Result<Signature>;
+}
+
+/// Signer holding the keypair in memory, which is the default one using the
+/// keypair the instance is connected with
+#[derive(Debug, Clone)]
+pub struct KeypairSigner(Keypair);
+
+impl KeypairSigner {
+    pub fn new(keypair: Keypair) -> Self {
+        Self(keypair)
+    }
+}
+
+#[async_trait]
+impl Signer for KeypairSigner {
+    fn public_key(&self) -> PublicKey {
+        self.0.public_key()
+    }
+
+    async fn sign(&self, data: &[u8]) -> Result<Signature> {
+        Ok(self.0.sign(data))
+    }
+}
+
+impl Safe {
+    /// # Set the signer of the content published by this instance
+    ///
+    /// All the content the API signs on behalf of the user is signed with it, and the
+    /// signer's public key is the one recorded as author, issuer, or registrant of such
+    /// content. By default the keypair this instance is connected with is used.
+    ///
+    /// The messages sent to the network, e.g. to store data, are still signed by the
+    /// client with the keypair it's connected with, since the network client needs to
+    /// hold such keypair, thus an ephemeral keypair can be used to connect with. The keys
+    /// derived from such keypair, e.g. to encrypt the user's private collections, are
+    /// still derived from it too. Content written to Registers only writable by the user,
+    /// e.g. channels, must be written with the same connection keypair every time.
+    pub fn set_signer(&mut self, signer: Arc<dyn Signer>) {
+        self.signer = Some(signer);
+    }
+
+    /// Stop using the signer set, signing with the keypair this instance is connected with
+    pub fn clear_signer(&mut self) {
+        self.signer = None;
+    }
+
+    // Signer of the content published by this instance
+    pub(crate) fn signer(&self) -> Result<Arc<dyn Signer>> {
+        match &self.signer {
+            Some(signer) => Ok(Arc::clone(signer)),
+            None => Ok(Arc::new(KeypairSigner::new(self.get_my_keypair()?))),
+        }
+    }
+
+    // Public key of the signer of the content published by this instance
+    pub(crate) fn signer_public_key(&self) -> Result<PublicKey> {
+        Ok(self.signer()?.public_key())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, Error};
+    use anyhow::{anyhow, Result};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Signer standing for an external device, counting the requests it gets
+    struct CountingSigner {
+        keypair: Keypair,
+        requests: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Signer for CountingSigner {
+        fn public_key(&self) -> PublicKey {
+            self.keypair.public_key()
+        }
+
+        async fn sign(&self, data: &[u8]) -> crate::Result<Signature> {
+            let _ = self.requests.fetch_add(1, Ordering::SeqCst);
+            Ok(self.keypair.sign(data))
+        }
+    }
+
+    struct UnavailableSigner(PublicKey);
+
+    #[async_trait]
+    impl Signer for UnavailableSigner {
+        fn public_key(&self) -> PublicKey {
+            self.0
+        }
+
+        async fn sign(&self, _data: &[u8]) -> crate::Result<Signature> {
+            Err(Error::SignerError("Device not connected".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signer_used_for_proofs() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let signer = Arc::new(CountingSigner {
+            keypair: safe.keypair(),
+            requests: AtomicUsize::new(0),
+        });
+        safe.set_signer(signer.clone());
+
+        let url = crate::Url::from_url("safe://signed")?;
+        let proof = safe.create_proof(&url, b"content").await?;
+        assert_eq!(proof.witness, signer.public_key());
+        assert_ne!(proof.witness, safe.get_my_keypair()?.public_key());
+        assert_eq!(signer.requests.load(Ordering::SeqCst), 1);
+
+        safe.clear_signer();
+        let proof = safe.create_proof(&url, b"content").await?;
+        assert_eq!(proof.witness, safe.get_my_keypair()?.public_key());
+        assert_eq!(signer.requests.load(Ordering::SeqCst), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_signer_failure() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let public_key = safe.keypair().public_key();
+        safe.set_signer(Arc::new(UnavailableSigner(public_key)));
+
+        let url = crate::Url::from_url("safe://signed")?;
+        match safe.create_proof(&url, b"content").await {
+            Err(Error::SignerError(_)) => Ok(()),
+            other => Err(anyhow!("Unexpected result: {:?}", other)),
+        }
+    }
+}
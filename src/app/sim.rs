@@ -0,0 +1,524 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{encryption::keyed_hash, fetch::Range, register::last_generations, Safe};
+use crate::{Error, MissingPermission, Result};
+use bytes::Bytes;
+use log::debug;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use safe_network::types::{
+    register::{Entry, EntryHash},
+    BytesAddress, Keypair, PublicKey, RegisterAddress,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use xor_name::XorName;
+
+/// Distribution the latency of each simulated operation is sampled from
+#[derive(Debug, Clone, PartialEq)]
+pub enum LatencyDistribution {
+    /// Every operation takes the same time
+    Fixed(Duration),
+    /// Latency uniformly distributed between a minimum and a maximum
+    Uniform { min: Duration, max: Duration },
+    /// Latency exponentially distributed with the given mean
+    Exponential { mean: Duration },
+}
+
+/// Cost charged for each type of simulated operation, in nanos
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimCosts {
+    pub per_byte_stored: u64,
+    pub per_register_created: u64,
+    pub per_register_write: u64,
+}
+
+impl Default for SimCosts {
+    fn default() -> Self {
+        Self {
+            per_byte_stored: 1,
+            per_register_created: 1_000,
+            per_register_write: 100,
+        }
+    }
+}
+
+/// Configuration of a simulated network
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimConfig {
+    /// Seed for the random generator, the same seed always produces
+    /// the same sequence of latencies and failures
+    pub seed: u64,
+    pub latency: LatencyDistribution,
+    /// Probability, between 0.0 and 1.0, of any operation failing
+    pub failure_rate: f64,
+    pub costs: SimCosts,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            latency: LatencyDistribution::Fixed(Duration::from_millis(0)),
+            failure_rate: 0.0,
+            costs: SimCosts::default(),
+        }
+    }
+}
+
+/// Statistics of the operations performed on a simulated network
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SimStats {
+    pub operations: u64,
+    pub failures: u64,
+    pub bytes_stored: u64,
+    pub total_cost: u64,
+    pub total_latency: Duration,
+}
+
+struct SimRegister {
+    owner: PublicKey,
+    open: bool,
+    // Keys allowed to write to it besides its owner
+    writers: BTreeSet<PublicKey>,
+    // Entries with the hashes of their parents
+    entries: BTreeMap<EntryHash, (Entry, BTreeSet<EntryHash>)>,
+}
+
+struct SimState {
+    config: SimConfig,
+    rng: StdRng,
+    stats: SimStats,
+    blobs: HashMap<XorName, Bytes>,
+    registers: HashMap<(XorName, u64, bool), SimRegister>,
+}
+
+/// In-memory network which can be shared by several `Safe` instances, simulating
+/// latencies, failures and costs, to deterministically test the behaviour
+/// of applications under degraded network conditions.
+#[derive(Clone)]
+pub struct SimNetwork {
+    state: Arc<Mutex<SimState>>,
+}
+
+impl SimNetwork {
+    pub fn new(config: SimConfig) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SimState {
+                rng: StdRng::seed_from_u64(config.seed),
+                config,
+                stats: SimStats::default(),
+                blobs: HashMap::default(),
+                registers: HashMap::default(),
+            })),
+        }
+    }
+
+    /// Statistics of all the operations performed so far on this network
+    pub fn stats(&self) -> SimStats {
+        self.lock()
+            .map(|state| state.stats.clone())
+            .unwrap_or_default()
+    }
+
+    /// Change the failure rate, e.g. to simulate a network outage mid-test
+    pub fn set_failure_rate(&self, failure_rate: f64) -> Result<()> {
+        self.lock()?.config.failure_rate = failure_rate;
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, SimState>> {
+        self.state
+            .lock()
+            .map_err(|_| Error::NetDataError("Simulated network state is poisoned".to_string()))
+    }
+
+    // Sample the latency and outcome of an operation, sleeping for such latency,
+    // and failing if the operation was chosen to fail
+    async fn simulate(&self, operation: &str) -> Result<()> {
+        let (latency, fail) = {
+            let mut state = self.lock()?;
+            let latency = match state.config.latency.clone() {
+                LatencyDistribution::Fixed(latency) => latency,
+                LatencyDistribution::Uniform { min, max } => {
+                    min + (max.saturating_sub(min)).mul_f64(state.rng.gen::<f64>())
+                }
+                LatencyDistribution::Exponential { mean } => {
+                    mean.mul_f64(-(1.0 - state.rng.gen::<f64>()).ln())
+                }
+            };
+            let fail = state.rng.gen::<f64>() < state.config.failure_rate;
+            state.stats.operations += 1;
+            state.stats.total_latency += latency;
+            if fail {
+                state.stats.failures += 1;
+            }
+            (latency, fail)
+        };
+
+        debug!("Simulating '{}' with latency {:?}", operation, latency);
+        tokio::time::sleep(latency).await;
+        if fail {
+            Err(Error::NetDataError(format!(
+                "Simulated network failure on '{}'",
+                operation
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(crate) async fn store_bytes(&self, bytes: Bytes) -> Result<XorName> {
+        self.simulate("store_bytes").await?;
+        let mut state = self.lock()?;
+        let xorname = XorName(keyed_hash(&[], &bytes));
+        state.stats.bytes_stored += bytes.len() as u64;
+        state.stats.total_cost += bytes.len() as u64 * state.config.costs.per_byte_stored;
+        let _ = state.blobs.insert(xorname, bytes);
+        Ok(xorname)
+    }
+
+    pub(crate) async fn get_bytes(&self, address: BytesAddress, range: Range) -> Result<Bytes> {
+        self.simulate("get_bytes").await?;
+        let state = self.lock()?;
+        let data = state.blobs.get(address.name()).ok_or_else(|| {
+            Error::NetDataError(format!("Failed to GET Blob: {:?}", address.name()))
+        })?;
+
+        match range {
+            Some((start, end)) => {
+                let start = start.unwrap_or(0) as usize;
+                let end = end.map_or(data.len(), |end| end as usize).min(data.len());
+                Ok(data.slice(start.min(end)..end))
+            }
+            None => Ok(data.clone()),
+        }
+    }
+
+    pub(crate) async fn store_register(
+        &self,
+        name: XorName,
+        tag: u64,
+        private: bool,
+        owner: PublicKey,
+        open: bool,
+        writers: BTreeSet<PublicKey>,
+    ) -> Result<XorName> {
+        self.simulate("store_register").await?;
+        let mut state = self.lock()?;
+        if state.registers.contains_key(&(name, tag, private)) {
+            return Err(Error::NetDataError(format!(
+                "Failed to store Register data: a Register already exists at {:?}",
+                name
+            )));
+        }
+
+        state.stats.total_cost += state.config.costs.per_register_created;
+        let _ = state.registers.insert(
+            (name, tag, private),
+            SimRegister {
+                owner,
+                open,
+                writers,
+                entries: BTreeMap::default(),
+            },
+        );
+        Ok(name)
+    }
+
+    pub(crate) async fn read_register(
+        &self,
+        address: RegisterAddress,
+        requester: PublicKey,
+    ) -> Result<BTreeSet<(EntryHash, Entry)>> {
+        self.simulate("read_register").await?;
+        let state = self.lock()?;
+        let register = get_register(&state, address, requester, false)?;
+
+        // Current entries are those which are not parents of any other entry
+        let parents: BTreeSet<&EntryHash> = register
+            .entries
+            .values()
+            .flat_map(|(_, parents)| parents)
+            .collect();
+        let current: BTreeSet<(EntryHash, Entry)> = register
+            .entries
+            .iter()
+            .filter(|(hash, _)| !parents.contains(hash))
+            .map(|(hash, (entry, _))| (*hash, entry.clone()))
+            .collect();
+
+        if current.is_empty() {
+            Err(Error::EmptyContent(format!(
+                "Empty Register found at {:?}",
+                address
+            )))
+        } else {
+            Ok(current)
+        }
+    }
+
+    pub(crate) async fn get_register_entry(
+        &self,
+        address: RegisterAddress,
+        hash: EntryHash,
+        requester: PublicKey,
+    ) -> Result<Entry> {
+        self.simulate("get_register_entry").await?;
+        let state = self.lock()?;
+        let register = get_register(&state, address, requester, false)?;
+        register
+            .entries
+            .get(&hash)
+            .map(|(entry, _)| entry.clone())
+            .ok_or(Error::HashNotFound(hash))
+    }
+
+    pub(crate) async fn delete_register(
+        &self,
+        address: RegisterAddress,
+        requester: PublicKey,
+    ) -> Result<()> {
+        self.simulate("delete_register").await?;
+        let mut state = self.lock()?;
+        if address.is_public() {
+            return Err(Error::PermissionDenied {
+                missing: MissingPermission::Scope,
+                address: format!("{:?}", address),
+            });
+        }
+        if find_register(&state, address)?.owner != requester {
+            return Err(Error::PermissionDenied {
+                missing: MissingPermission::Owner,
+                address: format!("{:?}", address),
+            });
+        }
+
+        let _ = state
+            .registers
+            .remove(&(*address.name(), address.tag(), true));
+        Ok(())
+    }
+
+    // All the entries of a Register, with the hashes of their parents
+    pub(crate) async fn register_history(
+        &self,
+        address: RegisterAddress,
+        requester: PublicKey,
+        depth: Option<usize>,
+    ) -> Result<BTreeMap<EntryHash, (Entry, BTreeSet<EntryHash>)>> {
+        self.simulate("register_history").await?;
+        let state = self.lock()?;
+        let register = get_register(&state, address, requester, false)?;
+        match depth {
+            Some(depth) => Ok(last_generations(
+                &register.entries,
+                |(_, parents)| parents,
+                depth,
+            )),
+            None => Ok(register.entries.clone()),
+        }
+    }
+
+    // Owner of a Register, whether anyone can write to it, and the keys allowed to
+    // write to it besides its owner
+    pub(crate) async fn register_policy(
+        &self,
+        address: RegisterAddress,
+        requester: PublicKey,
+    ) -> Result<(PublicKey, bool, BTreeSet<PublicKey>)> {
+        self.simulate("register_policy").await?;
+        let state = self.lock()?;
+        let register = get_register(&state, address, requester, false)?;
+        Ok((register.owner, register.open, register.writers.clone()))
+    }
+
+    pub(crate) async fn write_to_register(
+        &self,
+        address: RegisterAddress,
+        entry: Entry,
+        parents: BTreeSet<EntryHash>,
+        requester: PublicKey,
+    ) -> Result<EntryHash> {
+        self.simulate("write_to_register").await?;
+        let mut state = self.lock()?;
+        let _ = get_register(&state, address, requester, true)?;
+
+        let mut content = entry.to_string().into_bytes();
+        parents.iter().for_each(|parent| content.extend(parent));
+        let hash = keyed_hash(&address.name().0, &content);
+
+        state.stats.total_cost += state.config.costs.per_register_write;
+        let key = (*address.name(), address.tag(), !address.is_public());
+        if let Some(register) = state.registers.get_mut(&key) {
+            let _ = register.entries.insert(hash, (entry, parents));
+        }
+        Ok(hash)
+    }
+}
+
+// Find a Register in the simulated network, checking the requester's permissions
+fn find_register(state: &SimState, address: RegisterAddress) -> Result<&SimRegister> {
+    state
+        .registers
+        .get(&(*address.name(), address.tag(), !address.is_public()))
+        .ok_or_else(|| Error::ContentNotFound(format!("No Register found at {:?}", address.name())))
+}
+
+fn get_register(
+    state: &SimState,
+    address: RegisterAddress,
+    requester: PublicKey,
+    write: bool,
+) -> Result<&SimRegister> {
+    let register = find_register(state, address)?;
+    let granted = register.owner == requester || register.writers.contains(&requester);
+    let allowed = granted || (address.is_public() && (!write || register.open));
+    if allowed {
+        Ok(register)
+    } else {
+        Err(Error::PermissionDenied {
+            missing: if write {
+                MissingPermission::Writer
+            } else {
+                MissingPermission::Reader
+            },
+            address: format!("{:?}", address),
+        })
+    }
+}
+
+impl Safe {
+    /// Connect this instance to a simulated network instead of the SAFE Network,
+    /// using the provided keypair or a randomly generated one
+    pub fn connect_sim(&mut self, sim: &SimNetwork, app_keypair: Option<Keypair>) {
+        let keypair = app_keypair.unwrap_or_else(|| self.keypair());
+        self.safe_client.connect_sim(sim.clone(), keypair);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Url;
+    use anyhow::{anyhow, Result};
+
+    #[tokio::test]
+    async fn test_sim_network_store_and_fetch() -> Result<()> {
+        let sim = SimNetwork::new(SimConfig::default());
+        let mut safe = Safe::default();
+        safe.connect_sim(&sim, None);
+
+        let xorurl = safe
+            .store_public_bytes(Bytes::from("simulated"), None, false)
+            .await?;
+        assert_eq!(
+            safe.fetch_public_data(&Url::from_url(&xorurl)?, None)
+                .await?,
+            Bytes::from("simulated")
+        );
+
+        let register_xorurl = safe.register_create(None, 25_000, false).await?;
+        let entry = Url::from_url("safe://simulated")?;
+        let _ = safe
+            .write_to_register(&register_xorurl, entry.clone(), BTreeSet::new())
+            .await?;
+        let current = safe.register_read(&register_xorurl).await?;
+        assert!(current.iter().all(|(_, e)| *e == entry));
+
+        let stats = sim.stats();
+        assert_eq!(stats.failures, 0);
+        assert_eq!(stats.bytes_stored, 9);
+        assert!(stats.total_cost > 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sim_network_permission_errors() -> Result<()> {
+        fn missing<T>(result: crate::Result<T>) -> Option<MissingPermission> {
+            match result {
+                Err(Error::PermissionDenied { missing, .. }) => Some(missing),
+                _ => None,
+            }
+        }
+
+        let sim = SimNetwork::new(SimConfig::default());
+        let mut owner = Safe::default();
+        owner.connect_sim(&sim, None);
+        let mut stranger = Safe::default();
+        stranger.connect_sim(&sim, None);
+
+        let private_xorurl = owner.register_create(None, 25_000, true).await?;
+        let public_xorurl = owner.register_create(None, 25_000, false).await?;
+        let entry = Url::from_url("safe://denied")?;
+
+        assert_eq!(
+            missing(stranger.register_read(&private_xorurl).await),
+            Some(MissingPermission::Reader)
+        );
+        assert_eq!(
+            missing(
+                stranger
+                    .write_to_register(&public_xorurl, entry, BTreeSet::new())
+                    .await
+            ),
+            Some(MissingPermission::Writer)
+        );
+        assert_eq!(
+            missing(stranger.register_delete(&private_xorurl).await),
+            Some(MissingPermission::Owner)
+        );
+        assert_eq!(
+            missing(owner.register_delete(&public_xorurl).await),
+            Some(MissingPermission::Scope)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sim_network_deterministic_failures() -> Result<()> {
+        let config = SimConfig {
+            seed: 42,
+            latency: LatencyDistribution::Exponential {
+                mean: Duration::from_millis(1),
+            },
+            failure_rate: 0.5,
+            ..SimConfig::default()
+        };
+
+        let mut outcomes = vec![];
+        for _ in 0..2 {
+            let sim = SimNetwork::new(config.clone());
+            let mut safe = Safe::default();
+            safe.connect_sim(&sim, None);
+            let mut results = vec![];
+            for i in 0..10 {
+                results.push(
+                    safe.store_public_bytes(Bytes::from(vec![i]), None, false)
+                        .await
+                        .is_ok(),
+                );
+            }
+            outcomes.push((results, sim.stats()));
+        }
+
+        if outcomes[0] != outcomes[1] {
+            return Err(anyhow!("Simulation with same seed wasn't deterministic"));
+        }
+        assert!(outcomes[0].1.failures > 0);
+        assert_eq!(outcomes[0].1.operations, 10);
+
+        Ok(())
+    }
+}
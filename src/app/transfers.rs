@@ -0,0 +1,496 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::helpers::gen_timestamp_secs;
+use crate::{Error, Result, Safe, XorUrl};
+use bytes::Bytes;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Identifier of a transfer within its queue
+pub type TransferId = u64;
+
+/// What a transfer moves, and where to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TransferKind {
+    /// Store a local file as a Blob, with the default scope of the instance
+    Upload { path: PathBuf },
+    /// Fetch a Blob and write it to a local file
+    Download { url: String, path: PathBuf },
+}
+
+/// State of a transfer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TransferState {
+    /// Waiting for its turn, or for the transfers it depends on to complete
+    Queued,
+    /// Kept in the queue without being started until resumed
+    Paused,
+    Completed {
+        /// XOR-URL of the Blob uploaded or downloaded
+        xorurl: XorUrl,
+        completed_at: String,
+    },
+    Failed {
+        error: String,
+    },
+    Cancelled,
+}
+
+/// A transfer enqueued in a `TransferQueue`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transfer {
+    pub id: TransferId,
+    pub kind: TransferKind,
+    /// Transfers with higher priority are started first
+    pub priority: i32,
+    /// Transfers which need to complete before this one is started
+    pub depends_on: BTreeSet<TransferId>,
+    pub state: TransferState,
+    /// Time the transfer was enqueued, in RFC3339 format
+    pub enqueued_at: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct PersistedQueue {
+    next_id: TransferId,
+    transfers: Vec<Transfer>,
+}
+
+/// Queue of uploads and downloads which are run in order of priority, once the transfers
+/// they depend on have completed. Transfers with the same priority are run in the order
+/// they were enqueued. If the queue has a path, it's persisted to it upon every change, so
+/// the transfers not completed are resumed when the queue is opened again, e.g. upon restart.
+pub struct TransferQueue {
+    safe: Safe,
+    path: Option<PathBuf>,
+    queue: PersistedQueue,
+}
+
+impl TransferQueue {
+    /// Enqueue a transfer, returning its id. It fails if any of the transfers it
+    /// depends on is not in the queue, which also prevents circular dependencies.
+    pub fn enqueue(
+        &mut self,
+        kind: TransferKind,
+        priority: i32,
+        depends_on: BTreeSet<TransferId>,
+    ) -> Result<TransferId> {
+        if let Some(id) = depends_on.iter().find(|id| self.get(**id).is_none()) {
+            return Err(Error::InvalidInput(format!(
+                "Transfer {} to depend on is not in the queue",
+                id
+            )));
+        }
+
+        let id = self.queue.next_id;
+        debug!("Enqueueing transfer {}: {:?}", id, kind);
+        self.queue.next_id += 1;
+        self.queue.transfers.push(Transfer {
+            id,
+            kind,
+            priority,
+            depends_on,
+            state: TransferState::Queued,
+            enqueued_at: gen_timestamp_secs(),
+        });
+        self.persist()?;
+        Ok(id)
+    }
+
+    /// Get a transfer from the queue
+    pub fn get(&self, id: TransferId) -> Option<&Transfer> {
+        self.queue.transfers.iter().find(|t| t.id == id)
+    }
+
+    /// List all the transfers in the queue, pending ones first in the order they would be run
+    pub fn list(&self) -> Vec<Transfer> {
+        let mut transfers = self.queue.transfers.clone();
+        transfers.sort_by_key(|t| (!is_pending(&t.state), Reverse(t.priority), t.id));
+        transfers
+    }
+
+    /// Pause a transfer which hasn't been run yet
+    pub fn pause(&mut self, id: TransferId) -> Result<()> {
+        self.update(id, |transfer| match transfer.state {
+            TransferState::Queued => {
+                transfer.state = TransferState::Paused;
+                Ok(())
+            }
+            _ => Err(not_pending(transfer)),
+        })
+    }
+
+    /// Resume a paused transfer, also allowing a failed one to be retried
+    pub fn resume(&mut self, id: TransferId) -> Result<()> {
+        self.update(id, |transfer| match transfer.state {
+            TransferState::Paused | TransferState::Failed { .. } => {
+                transfer.state = TransferState::Queued;
+                Ok(())
+            }
+            _ => Err(Error::InvalidInput(format!(
+                "Transfer {} is neither paused nor failed",
+                transfer.id
+            ))),
+        })
+    }
+
+    /// Cancel a transfer which hasn't been run yet, even if paused. The transfers depending
+    /// on it will fail when their turn comes, unless they are cancelled too.
+    pub fn cancel(&mut self, id: TransferId) -> Result<()> {
+        self.update(id, |transfer| match transfer.state {
+            TransferState::Queued | TransferState::Paused => {
+                transfer.state = TransferState::Cancelled;
+                Ok(())
+            }
+            _ => Err(not_pending(transfer)),
+        })
+    }
+
+    /// Change the priority of a transfer, thus reordering the queue
+    pub fn set_priority(&mut self, id: TransferId, priority: i32) -> Result<()> {
+        self.update(id, |transfer| {
+            transfer.priority = priority;
+            Ok(())
+        })
+    }
+
+    /// Move a transfer ahead of all the others, giving it a higher priority than any of them
+    pub fn move_to_front(&mut self, id: TransferId) -> Result<()> {
+        let top_priority = self
+            .queue
+            .transfers
+            .iter()
+            .filter(|t| t.id != id)
+            .map(|t| t.priority)
+            .max()
+            .unwrap_or_default();
+        self.set_priority(id, top_priority.saturating_add(1))
+    }
+
+    /// Remove the completed, failed, and cancelled transfers from the queue
+    pub fn clear_finished(&mut self) -> Result<()> {
+        self.queue
+            .transfers
+            .retain(|t| is_pending(&t.state) || t.state == TransferState::Paused);
+        self.persist()
+    }
+
+    /// Run the next transfer which is ready, returning its id and final state, or None
+    /// if there is none ready. A failed transfer doesn't make this function fail.
+    pub async fn run_next(&mut self) -> Result<Option<(TransferId, TransferState)>> {
+        let transfer = match self.next_ready() {
+            Some(transfer) => transfer,
+            None => return Ok(None),
+        };
+
+        let state = match self.failed_dependency(&transfer) {
+            Some(dependency) => TransferState::Failed {
+                error: format!("Transfer {} it depends on didn't complete", dependency),
+            },
+            None => {
+                info!("Running transfer {}: {:?}", transfer.id, transfer.kind);
+                match self.safe.run_transfer(&transfer.kind).await {
+                    Ok(xorurl) => TransferState::Completed {
+                        xorurl,
+                        completed_at: gen_timestamp_secs(),
+                    },
+                    Err(err) => {
+                        warn!("Transfer {} failed: {}", transfer.id, err);
+                        TransferState::Failed {
+                            error: err.to_string(),
+                        }
+                    }
+                }
+            }
+        };
+
+        let final_state = state.clone();
+        self.update(transfer.id, move |transfer| {
+            transfer.state = state;
+            Ok(())
+        })?;
+        Ok(Some((transfer.id, final_state)))
+    }
+
+    /// Run the transfers until there are none ready, returning the number of transfers run
+    pub async fn run(&mut self) -> Result<usize> {
+        let mut count = 0;
+        while self.run_next().await?.is_some() {
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    // The queued transfer to run next, whose dependencies are all finished
+    fn next_ready(&self) -> Option<Transfer> {
+        self.queue
+            .transfers
+            .iter()
+            .filter(|t| t.state == TransferState::Queued)
+            .filter(|t| {
+                t.depends_on.iter().all(|id| {
+                    self.get(*id)
+                        .map_or(true, |dependency| is_finished(&dependency.state))
+                })
+            })
+            .min_by_key(|t| (Reverse(t.priority), t.id))
+            .cloned()
+    }
+
+    // A dependency of the transfer which failed or was cancelled, if any. Dependencies
+    // no longer in the queue were cleared once finished, they're taken as completed.
+    fn failed_dependency(&self, transfer: &Transfer) -> Option<TransferId> {
+        transfer.depends_on.iter().copied().find(|id| {
+            matches!(
+                self.get(*id).map(|t| &t.state),
+                Some(TransferState::Failed { .. }) | Some(TransferState::Cancelled)
+            )
+        })
+    }
+
+    fn update(
+        &mut self,
+        id: TransferId,
+        f: impl FnOnce(&mut Transfer) -> Result<()>,
+    ) -> Result<()> {
+        let transfer = self
+            .queue
+            .transfers
+            .iter_mut()
+            .find(|t| t.id == id)
+            .ok_or_else(|| Error::EntryNotFound(format!("No transfer {} in the queue", id)))?;
+        f(transfer)?;
+        self.persist()
+    }
+
+    fn persist(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            let serialised_queue = serde_json::to_string(&self.queue).map_err(|err| {
+                Error::Serialisation(format!("Couldn't serialise transfer queue: {:?}", err))
+            })?;
+            fs::write(path, serialised_queue).map_err(|err| {
+                Error::FileSystemError(format!(
+                    "Couldn't write transfer queue to '{}': {}",
+                    path.display(),
+                    err
+                ))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl Safe {
+    /// # Open a queue of uploads and downloads
+    ///
+    /// If a path is provided the queue is persisted to that file, loading any transfers
+    /// previously stored in it, otherwise it's kept in memory. See `TransferQueue`.
+    pub fn transfer_queue(&self, path: Option<&Path>) -> Result<TransferQueue> {
+        let queue = match path {
+            Some(path) if path.exists() => {
+                let serialised_queue = fs::read(path).map_err(|err| {
+                    Error::FileSystemError(format!(
+                        "Couldn't read transfer queue from '{}': {}",
+                        path.display(),
+                        err
+                    ))
+                })?;
+                serde_json::from_slice(&serialised_queue).map_err(|err| {
+                    Error::ContentError(format!("Couldn't parse transfer queue: {:?}", err))
+                })?
+            }
+            _ => PersistedQueue::default(),
+        };
+
+        Ok(TransferQueue {
+            safe: self.clone(),
+            path: path.map(|p| p.to_path_buf()),
+            queue,
+        })
+    }
+
+    // Private helper to run a transfer, returning the XOR-URL of the Blob transferred
+    async fn run_transfer(&self, kind: &TransferKind) -> Result<XorUrl> {
+        match kind {
+            TransferKind::Upload { path } => {
+                let data = fs::read(path).map_err(|err| {
+                    Error::FileSystemError(format!(
+                        "Couldn't read file '{}': {}",
+                        path.display(),
+                        err
+                    ))
+                })?;
+                let media_type = mime_guess::from_path(path);
+                self.store_bytes(Bytes::from(data), media_type.first_raw(), false)
+                    .await
+            }
+            TransferKind::Download { url, path } => {
+                let (safe_url, _) = self.parse_and_resolve_url(url).await?;
+                let data = self.fetch_public_data(&safe_url, None).await?;
+                fs::write(path, data).map_err(|err| {
+                    Error::FileSystemError(format!(
+                        "Couldn't write file '{}': {}",
+                        path.display(),
+                        err
+                    ))
+                })?;
+                Ok(safe_url.to_xorurl_string())
+            }
+        }
+    }
+}
+
+// Transfers which haven't been run, nor cancelled, and aren't paused
+fn is_pending(state: &TransferState) -> bool {
+    matches!(state, TransferState::Queued)
+}
+
+// Transfers which won't be run unless retried
+fn is_finished(state: &TransferState) -> bool {
+    matches!(
+        state,
+        TransferState::Completed { .. } | TransferState::Failed { .. } | TransferState::Cancelled
+    )
+}
+
+fn not_pending(transfer: &Transfer) -> Error {
+    Error::InvalidInput(format!(
+        "Transfer {} is not pending, it's {:?}",
+        transfer.id, transfer.state
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop};
+    use anyhow::{anyhow, Result};
+
+    fn temp_dir() -> Result<PathBuf> {
+        let dir = std::env::temp_dir().join(format!("sn_api-transfers-{}", rand::random::<u64>()));
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    #[test]
+    fn test_transfer_queue_order_and_persistence() -> Result<()> {
+        let safe = Safe::default();
+        let dir = temp_dir()?;
+        let path = dir.join("queue.json");
+        let mut queue = safe.transfer_queue(Some(&path))?;
+
+        let upload = |name: &str| TransferKind::Upload {
+            path: PathBuf::from(name),
+        };
+        let first = queue.enqueue(upload("first"), 0, BTreeSet::new())?;
+        let urgent = queue.enqueue(upload("urgent"), 10, BTreeSet::new())?;
+        let dependent =
+            queue.enqueue(upload("dependent"), 20, vec![first].into_iter().collect())?;
+        let last = queue.enqueue(upload("last"), 0, BTreeSet::new())?;
+        assert!(queue
+            .enqueue(upload("orphan"), 0, vec![100].into_iter().collect())
+            .is_err());
+
+        // the dependent transfer is not ready until the one it depends on completes
+        assert_eq!(queue.next_ready().map(|t| t.id), Some(urgent));
+        queue.pause(urgent)?;
+        assert_eq!(queue.next_ready().map(|t| t.id), Some(first));
+        queue.move_to_front(last)?;
+        assert_eq!(queue.next_ready().map(|t| t.id), Some(last));
+        queue.cancel(last)?;
+        assert!(queue.cancel(last).is_err());
+
+        let ids: Vec<TransferId> = queue.list().iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![dependent, first, last, urgent]);
+
+        // transfers are resumed when the queue is opened again
+        let mut reopened = safe.transfer_queue(Some(&path))?;
+        assert_eq!(reopened.list(), queue.list());
+        reopened.resume(urgent)?;
+        assert_eq!(reopened.next_ready().map(|t| t.id), Some(urgent));
+        assert_eq!(
+            reopened.get(last).map(|t| t.state.clone()),
+            Some(TransferState::Cancelled)
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transfer_queue_run() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let dir = temp_dir()?;
+        let mut queue = safe.transfer_queue(None)?;
+
+        let upload = queue.enqueue(
+            TransferKind::Upload {
+                path: PathBuf::from("./testdata/test.md"),
+            },
+            0,
+            BTreeSet::new(),
+        )?;
+        let missing = queue.enqueue(
+            TransferKind::Upload {
+                path: dir.join("missing.md"),
+            },
+            0,
+            BTreeSet::new(),
+        )?;
+        let blocked = queue.enqueue(
+            TransferKind::Upload {
+                path: PathBuf::from("./testdata/another.md"),
+            },
+            0,
+            vec![missing].into_iter().collect(),
+        )?;
+        assert_eq!(queue.run().await?, 3);
+
+        let xorurl = match queue.get(upload).map(|t| t.state.clone()) {
+            Some(TransferState::Completed { xorurl, .. }) => xorurl,
+            other => return Err(anyhow!("Unexpected state of upload: {:?}", other)),
+        };
+        assert!(matches!(
+            queue.get(missing).map(|t| &t.state),
+            Some(TransferState::Failed { .. })
+        ));
+        assert!(matches!(
+            queue.get(blocked).map(|t| &t.state),
+            Some(TransferState::Failed { .. })
+        ));
+
+        let downloaded = dir.join("downloaded.md");
+        let download = queue.enqueue(
+            TransferKind::Download {
+                url: xorurl,
+                path: downloaded.clone(),
+            },
+            0,
+            vec![upload].into_iter().collect(),
+        )?;
+        let _ = retry_loop!(async {
+            let _ = queue.resume(download);
+            match queue.run_next().await? {
+                Some((_, TransferState::Completed { .. })) => Ok(()),
+                other => Err(anyhow!("Download not completed: {:?}", other)),
+            }
+        });
+        assert_eq!(fs::read(&downloaded)?, fs::read("./testdata/test.md")?);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,378 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    encryption::{
+        decrypt_payload, derive_symmetric_key, encrypt_payload, keyed_hash, SymmetricKey,
+    },
+    helpers::gen_timestamp_secs,
+    keyed_register::KEYED_REGISTER_TYPE_TAG,
+    register::EntryHash,
+};
+use crate::{ContentType, Error, Result, Safe, Scope, Url, XorName, XorUrl};
+use bytes::Bytes;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+// Context used to derive the location and encryption key of the vault
+const VAULT_CONTEXT: &[u8] = b"sn_api-vault";
+
+// Size of the random salt the encryption key of a vault export is derived with
+const VAULT_EXPORT_SALT_LEN: usize = 16;
+
+// Parameters of the scrypt KDF the encryption key of vault exports is derived from a
+// passphrase with, i.e. N = 2^15 and r = 8, which requires 32MiB of memory
+const VAULT_EXPORT_SCRYPT_LOG_N: u8 = 15;
+const VAULT_EXPORT_SCRYPT_R: u32 = 8;
+const VAULT_EXPORT_SCRYPT_P: u32 = 1;
+
+/// Kind of secret stored in the vault
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VaultItemKind {
+    Password,
+    Token,
+    Note,
+}
+
+/// A secret stored in the user's vault
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VaultItem {
+    pub label: String,
+    pub kind: VaultItemKind,
+    pub secret: String,
+    pub updated_at: String,
+}
+
+// A vault export, with the parameters its encryption key is derived from the passphrase
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultExport {
+    salt: Vec<u8>,
+    log_n: u8,
+    r: u32,
+    p: u32,
+    ciphertext: Vec<u8>,
+}
+
+impl Safe {
+    /// # Store a secret in the user's vault
+    ///
+    /// The vault is a private keyed Register, at a location derived from the keypair this
+    /// instance is connected with, thus it's shared by all the applications using the same
+    /// keypair. Items are encrypted before being stored, in private Blobs, and their labels
+    /// are only stored as keyed hashes. Storing a secret with an existing label keeps the previous one in
+    /// the item's history.
+    pub async fn vault_put(
+        &self,
+        label: &str,
+        kind: VaultItemKind,
+        secret: &str,
+    ) -> Result<VaultItem> {
+        info!("Storing secret '{}' in vault", label);
+        let item = VaultItem {
+            label: label.to_string(),
+            kind,
+            secret: secret.to_string(),
+            updated_at: gen_timestamp_secs(),
+        };
+        let serialised_item = rmp_serde::to_vec_named(&item).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise vault item: {:?}", err))
+        })?;
+
+        let (xorurl, key) = self.fetch_vault().await?;
+        let value = encrypt_payload(&self.encryption_policy, &key, &serialised_item)?;
+        let _ = self
+            .kr_put(&xorurl, &vault_lookup_key(&key, label), Bytes::from(value))
+            .await?;
+
+        Ok(item)
+    }
+
+    /// Get the latest value of a secret from the user's vault,
+    /// failing with `Error::EntryNotFound` if there is no secret with such label
+    pub async fn vault_get(&self, label: &str) -> Result<VaultItem> {
+        let (xorurl, key) = self.fetch_vault().await?;
+        let value = self.kr_get(&xorurl, &vault_lookup_key(&key, label)).await?;
+        self.decrypt_vault_item(&key, &value)?.ok_or_else(|| {
+            Error::EntryNotFound(format!("No secret found in vault for '{}'", label))
+        })
+    }
+
+    /// Get all the values a secret had in the user's vault, most recent first
+    pub async fn vault_history(&self, label: &str) -> Result<Vec<(EntryHash, VaultItem)>> {
+        let (xorurl, key) = self.fetch_vault().await?;
+        let history = self
+            .kr_history(&xorurl, &vault_lookup_key(&key, label))
+            .await?;
+
+        let mut items = vec![];
+        for (hash, value) in history.into_iter() {
+            if let Some(item) = self.decrypt_vault_item(&key, &value)? {
+                items.push((hash, item));
+            }
+        }
+        Ok(items)
+    }
+
+    /// Remove a secret from the user's vault, its previous values are kept in its history
+    pub async fn vault_remove(&self, label: &str) -> Result<()> {
+        info!("Removing secret '{}' from vault", label);
+        let _ = self.vault_get(label).await?;
+        let (xorurl, key) = self.fetch_vault().await?;
+
+        // An empty value is used as a tombstone for a removed secret
+        let _ = self
+            .kr_put(&xorurl, &vault_lookup_key(&key, label), Bytes::new())
+            .await?;
+        Ok(())
+    }
+
+    /// List the latest value of all the secrets in the user's vault, sorted by label
+    pub async fn vault_list(&self) -> Result<Vec<VaultItem>> {
+        let (xorurl, key) = self.fetch_vault().await?;
+        let mut items = vec![];
+        for lookup_key in self.kr_keys(&xorurl).await?.keys() {
+            let value = self.kr_get(&xorurl, lookup_key).await?;
+            if let Some(item) = self.decrypt_vault_item(&key, &value)? {
+                items.push(item);
+            }
+        }
+
+        items.sort_by(|a, b| a.label.cmp(&b.label));
+        Ok(items)
+    }
+
+    /// Search the secrets in the user's vault whose label contains the query, ignoring case
+    pub async fn vault_search(&self, query: &str) -> Result<Vec<VaultItem>> {
+        let query = query.to_lowercase();
+        Ok(self
+            .vault_list()
+            .await?
+            .into_iter()
+            .filter(|item| item.label.to_lowercase().contains(&query))
+            .collect())
+    }
+
+    /// # Export the user's vault
+    ///
+    /// The latest value of all the secrets is exported, encrypted with a key derived from
+    /// the passphrase, to be imported with `vault_import`, e.g. into another user's vault.
+    /// The key is derived with the scrypt KDF and a random salt, which is stored in the export.
+    pub async fn vault_export(&self, passphrase: &str) -> Result<Vec<u8>> {
+        let items = self.vault_list().await?;
+        let serialised_items = rmp_serde::to_vec_named(&items).map_err(|err| {
+            Error::Serialisation(format!("Couldn't serialise vault items: {:?}", err))
+        })?;
+
+        let mut salt = vec![0; VAULT_EXPORT_SALT_LEN];
+        self.entropy.fill_bytes(&mut salt)?;
+        let (log_n, r, p) = (
+            VAULT_EXPORT_SCRYPT_LOG_N,
+            VAULT_EXPORT_SCRYPT_R,
+            VAULT_EXPORT_SCRYPT_P,
+        );
+        let key = vault_export_key(passphrase, &salt, log_n, r, p)?;
+        let ciphertext = encrypt_payload(&self.encryption_policy, &key, &serialised_items)?;
+
+        rmp_serde::to_vec_named(&VaultExport {
+            salt,
+            log_n,
+            r,
+            p,
+            ciphertext,
+        })
+        .map_err(|err| Error::Serialisation(format!("Couldn't serialise vault export: {:?}", err)))
+    }
+
+    /// # Import the secrets exported with `vault_export` into the user's vault
+    ///
+    /// Secrets already found in the vault with the same value are not imported again,
+    /// the rest replace any secret with the same label. Returns the number of secrets imported.
+    pub async fn vault_import(&self, export: &[u8], passphrase: &str) -> Result<usize> {
+        let export: VaultExport = rmp_serde::from_slice(export).map_err(|err| {
+            Error::ContentError(format!("Couldn't parse vault export: {:?}", err))
+        })?;
+        let key = vault_export_key(passphrase, &export.salt, export.log_n, export.r, export.p)?;
+        let serialised_items = decrypt_payload(&self.encryption_policy, &key, &export.ciphertext)?;
+        let items: Vec<VaultItem> = rmp_serde::from_slice(&serialised_items).map_err(|err| {
+            Error::ContentError(format!("Couldn't parse vault export: {:?}", err))
+        })?;
+
+        let mut imported = 0;
+        for item in items.into_iter() {
+            match self.vault_get(&item.label).await {
+                Ok(existing) if existing.kind == item.kind && existing.secret == item.secret => {
+                    debug!("Secret '{}' already found in vault", item.label);
+                }
+                _ => {
+                    let _ = self.vault_put(&item.label, item.kind, &item.secret).await?;
+                    imported += 1;
+                }
+            }
+        }
+
+        Ok(imported)
+    }
+
+    // Private helper to fetch the location and encryption key of the vault,
+    // creating it upon first use
+    async fn fetch_vault(&self) -> Result<(XorUrl, SymmetricKey)> {
+        let keypair = self.get_my_keypair()?;
+        let key = derive_symmetric_key(&keypair, VAULT_CONTEXT)?;
+        let xorname = XorName(keyed_hash(&key, VAULT_CONTEXT));
+        let xorurl = Url::encode_register(
+            xorname,
+            KEYED_REGISTER_TYPE_TAG,
+            Scope::Private,
+            ContentType::Raw,
+            self.xorurl_base,
+        )?;
+
+        match self
+            .fetch_register_entries(&Url::from_xorurl(&xorurl)?)
+            .await
+        {
+            Ok(_) | Err(Error::EmptyContent(_)) => {}
+            Err(Error::ContentNotFound(_)) => {
+                debug!("Vault not found, attempting to create it");
+                let _ = self.kr_create(Some(xorname), true).await?;
+            }
+            Err(err) => return Err(err),
+        }
+
+        Ok((xorurl, key))
+    }
+
+    // Private helper to decrypt a vault item, returning None for tombstones of removed items
+    fn decrypt_vault_item(&self, key: &SymmetricKey, value: &[u8]) -> Result<Option<VaultItem>> {
+        if value.is_empty() {
+            return Ok(None);
+        }
+
+        let serialised_item = decrypt_payload(&self.encryption_policy, key, value)?;
+        let item = rmp_serde::from_slice(&serialised_item)
+            .map_err(|err| Error::ContentError(format!("Couldn't parse vault item: {:?}", err)))?;
+        Ok(Some(item))
+    }
+}
+
+// Key the items are stored with in the keyed Register, which doesn't reveal their label
+fn vault_lookup_key(key: &SymmetricKey, label: &str) -> String {
+    hex::encode(keyed_hash(key, label.as_bytes()))
+}
+
+fn vault_export_key(
+    passphrase: &str,
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<SymmetricKey> {
+    let params = scrypt::Params::new(log_n, r, p).map_err(|err| {
+        Error::ContentError(format!("Invalid key derivation parameters: {:?}", err))
+    })?;
+    let mut key = SymmetricKey::default();
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key).map_err(|err| {
+        Error::InvalidInput(format!("Failed to derive key from passphrase: {:?}", err))
+    })?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::test_helpers::new_safe_instance, retry_loop_for_pattern, UrlAddressExt};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_vault_records_private() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let _ = safe
+            .vault_put("Bank", VaultItemKind::Password, "pa55word")
+            .await?;
+
+        let (xorurl, _) = safe.fetch_vault().await?;
+        let entries = retry_loop_for_pattern!(safe.register_read(&xorurl), Ok(entries) if entries.len() == 1)?;
+        for (_, record_url) in entries.iter() {
+            assert!(!record_url.bytes_address()?.is_public());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_vault_put_search_history() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let _ = safe
+            .vault_put("Email account", VaultItemKind::Password, "hunter2")
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.vault_list(), Ok(items) if items.len() == 1)?;
+        let _ = safe
+            .vault_put("API token", VaultItemKind::Token, "t0k3n")
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.vault_list(), Ok(items) if items.len() == 2)?;
+        let _ = safe
+            .vault_put("Email account", VaultItemKind::Password, "correct horse")
+            .await?;
+
+        let item = retry_loop_for_pattern!(safe.vault_get("Email account"), Ok(item) if item.secret == "correct horse")?;
+        assert_eq!(item.kind, VaultItemKind::Password);
+
+        let history = safe.vault_history("Email account").await?;
+        let secrets: Vec<&str> = history.iter().map(|(_, i)| i.secret.as_str()).collect();
+        assert_eq!(secrets, vec!["correct horse", "hunter2"]);
+
+        let found = safe.vault_search("email").await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].label, "Email account");
+
+        safe.vault_remove("API token").await?;
+        let _ = retry_loop_for_pattern!(safe.vault_list(), Ok(items) if items.len() == 1)?;
+        assert!(matches!(
+            safe.vault_get("API token").await,
+            Err(Error::EntryNotFound(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_vault_export_import() -> Result<()> {
+        let safe = new_safe_instance().await?;
+        let _ = safe
+            .vault_put("Wifi", VaultItemKind::Password, "s3cr3t")
+            .await?;
+        let _ = retry_loop_for_pattern!(safe.vault_list(), Ok(items) if items.len() == 1)?;
+
+        let export = safe.vault_export("a strong passphrase").await?;
+
+        // each export is encrypted with a key derived with a different salt
+        let another_export = safe.vault_export("a strong passphrase").await?;
+        let parsed: VaultExport = rmp_serde::from_slice(&export)?;
+        let another_parsed: VaultExport = rmp_serde::from_slice(&another_export)?;
+        assert_eq!(parsed.salt.len(), VAULT_EXPORT_SALT_LEN);
+        assert_ne!(parsed.salt, another_parsed.salt);
+        assert_ne!(parsed.ciphertext, another_parsed.ciphertext);
+
+        let other = new_safe_instance().await?;
+        assert!(other
+            .vault_import(&export, "wrong passphrase")
+            .await
+            .is_err());
+        assert_eq!(other.vault_import(&export, "a strong passphrase").await?, 1);
+
+        let item =
+            retry_loop_for_pattern!(other.vault_get("Wifi"), Ok(item) if item.secret == "s3cr3t")?;
+        assert_eq!(item.kind, VaultItemKind::Password);
+
+        // importing it again doesn't store the same secrets again
+        assert_eq!(other.vault_import(&export, "a strong passphrase").await?, 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{nrs::NrsRegistrationReceipt, UrlAddressExt};
+use crate::{
+    BytesAddress, ContentType, DataAddress, DataType, PublicKey, Result, Safe, Scope, XorUrl,
+};
+use log::{debug, info};
+use std::collections::BTreeSet;
+
+/// Keys allowed to write to a Register
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegisterWriters {
+    /// Anyone can write to the Register
+    Anyone,
+    /// Only these keys can write to the Register, which includes its owner
+    Keys(BTreeSet<PublicKey>),
+}
+
+/// Who controls the content a URL resolves to, see `Safe::whois`
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhoisInfo {
+    /// XOR-URL of the content the URL resolves to
+    pub xorurl: XorUrl,
+    pub data_type: DataType,
+    pub content_type: ContentType,
+    /// Scope of the content, none for content without one, e.g. SafeKeys
+    pub scope: Option<Scope>,
+    /// Owner of the content, none for content without one, e.g. Blobs
+    pub owner: Option<PublicKey>,
+    /// Keys allowed to write to the content, only for Registers
+    pub writers: Option<RegisterWriters>,
+    /// Registration receipt of the NRS top name the URL was resolved through, if any
+    pub registration: Option<NrsRegistrationReceipt>,
+}
+
+impl Safe {
+    /// # Find out who controls the content a URL resolves to
+    ///
+    /// Returns the owner and the keys allowed to write to Registers (e.g. FilesContainers
+    /// and NrsMapContainers), and the scope of any content. If the URL is an NRS-URL, the
+    /// registration receipt of its top name is included as creation info, when found.
+    /// Blobs are immutable and have no owner, thus only their scope is returned.
+    pub async fn whois(&self, url: &str) -> Result<WhoisInfo> {
+        info!("Looking up who controls {}", url);
+        let (mut safe_url, nrs_url) = self.parse_and_resolve_url(url).await?;
+        safe_url.set_path("");
+
+        let (scope, owner, writers) = match safe_url.address() {
+            DataAddress::Register(address) => {
+                let (_, _, scope) = safe_url.register_parts()?;
+                let (owner, writers) = self.safe_client.get_register_policy(address).await?;
+                (Some(scope), Some(owner), Some(writers))
+            }
+            DataAddress::Bytes(BytesAddress::Public(_)) => (Some(Scope::Public), None, None),
+            DataAddress::Bytes(BytesAddress::Private(_)) => (Some(Scope::Private), None, None),
+            _ => (None, None, None),
+        };
+
+        let registration = match nrs_url {
+            Some(nrs_url) if !nrs_url.is_xorurl() => {
+                match self.nrs_registration_proof(nrs_url.top_name()).await {
+                    Ok(proof) => Some(proof.receipt),
+                    Err(err) => {
+                        debug!("No registration receipt found for {}: {}", nrs_url, err);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        Ok(WhoisInfo {
+            xorurl: safe_url.to_xorurl_string(),
+            data_type: safe_url.data_type(),
+            content_type: safe_url.content_type(),
+            scope,
+            owner,
+            writers,
+            registration,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::test_helpers::{new_safe_instance, random_nrs_name},
+        retry_loop,
+    };
+    use anyhow::Result;
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn test_whois() -> Result<()> {
+        let mut safe = new_safe_instance().await?;
+        let my_pk = safe.get_my_keypair()?.public_key();
+        let blob_xorurl = safe
+            .store_public_bytes(Bytes::from("who owns this?"), None, false)
+            .await?;
+
+        let other = new_safe_instance().await?;
+        let info = retry_loop!(other.whois(&blob_xorurl));
+        assert_eq!(info.scope, Some(Scope::Public));
+        assert_eq!(info.owner, None);
+        assert_eq!(info.writers, None);
+
+        let site_name = random_nrs_name();
+        let (container_xorurl, _, _) = safe
+            .files_container_create(None, None, true, true, false)
+            .await?;
+        let _ = retry_loop!(safe.nrs_map_container_create(
+            &site_name,
+            &container_xorurl,
+            true,
+            false,
+            false
+        ));
+
+        let info = retry_loop!(other.whois(&format!("safe://{}", site_name)));
+        assert_eq!(info.content_type, ContentType::FilesContainer);
+        assert_eq!(info.scope, Some(Scope::Public));
+        assert_eq!(info.owner, Some(my_pk));
+        assert_eq!(
+            info.writers,
+            Some(RegisterWriters::Keys(vec![my_pk].into_iter().collect()))
+        );
+        assert_eq!(
+            info.registration.map(|receipt| receipt.registrant),
+            Some(my_pk)
+        );
+
+        Ok(())
+    }
+}
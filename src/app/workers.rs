@@ -0,0 +1,145 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{Error, Result};
+use futures::channel::oneshot;
+use log::{debug, trace};
+use std::{
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+// Number of worker threads used unless explicitly set
+const DEFAULT_WORKERS: usize = 4;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// Pool of threads CPU intensive work is offloaded to, e.g. self-encrypting large payloads,
+// so it doesn't stall the tasks driven by the async runtime. The threads are only spawned
+// upon the first job, and they terminate once the pool and all its clones are dropped.
+#[derive(Clone)]
+pub(crate) struct WorkerPool {
+    parallelism: usize,
+    sender: Arc<Mutex<Option<Sender<Job>>>>,
+}
+
+impl Default for WorkerPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_WORKERS)
+    }
+}
+
+impl WorkerPool {
+    // A pool with no threads runs the jobs on the calling task
+    pub(crate) fn new(parallelism: usize) -> Self {
+        Self {
+            parallelism,
+            sender: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub(crate) fn parallelism(&self) -> usize {
+        self.parallelism
+    }
+
+    // Run a job on one of the worker threads, waiting for its outcome
+    pub(crate) async fn run<T: Send + 'static>(
+        &self,
+        job: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T> {
+        if self.parallelism == 0 {
+            return Ok(job());
+        }
+
+        let (tx, rx) = oneshot::channel();
+        self.sender()?
+            .send(Box::new(move || {
+                let _ = tx.send(job());
+            }))
+            .map_err(|_| Error::NetDataError("Worker threads are not running".to_string()))?;
+
+        rx.await
+            .map_err(|_| Error::NetDataError("Worker thread failed to complete job".to_string()))
+    }
+
+    // Sender of jobs to the worker threads, spawning them upon first use
+    fn sender(&self) -> Result<Sender<Job>> {
+        let mut sender = self
+            .sender
+            .lock()
+            .map_err(|err| Error::NetDataError(format!("Worker pool unavailable: {}", err)))?;
+
+        if let Some(sender) = sender.as_ref() {
+            return Ok(sender.clone());
+        }
+
+        debug!("Spawning {} worker threads", self.parallelism);
+        let (tx, rx) = channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        for i in 0..self.parallelism {
+            let rx = rx.clone();
+            let _ = thread::Builder::new()
+                .name(format!("sn_api-worker-{}", i))
+                .spawn(move || worker_loop(&rx))
+                .map_err(|err| {
+                    Error::NetDataError(format!("Failed to spawn worker thread: {}", err))
+                })?;
+        }
+
+        *sender = Some(tx.clone());
+        Ok(tx)
+    }
+}
+
+// Run the jobs received until all the senders are dropped
+fn worker_loop(jobs: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = match jobs.lock() {
+            Ok(jobs) => jobs.recv(),
+            Err(_) => return,
+        };
+        match job {
+            Ok(job) => job(),
+            Err(_) => {
+                trace!("Worker thread terminating");
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_worker_pool_runs_jobs_off_thread() -> Result<()> {
+        let caller = thread::current().id();
+        let pool = WorkerPool::new(2);
+        let results =
+            futures::executor::block_on(futures::future::try_join_all((0..8).map(|i| {
+                pool.run(move || {
+                    assert_ne!(thread::current().id(), caller);
+                    i * 2
+                })
+            })))?;
+        assert_eq!(results, vec![0, 2, 4, 6, 8, 10, 12, 14]);
+
+        // without threads the jobs are run by the caller
+        let inline = WorkerPool::new(0);
+        let id = futures::executor::block_on(inline.run(|| thread::current().id()))?;
+        assert_eq!(id, caller);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use lazy_static::lazy_static;
+use log::warn;
+use std::sync::RwLock;
+
+/// Hook to provide translated user messages for errors, e.g. in the user's language
+pub trait ErrorLocaliser: Send + Sync {
+    /// Return the message to display for the given error code, or `None` to fall back
+    /// to the default message from the catalog, which is also provided for reference.
+    fn localise(&self, code: &str, default_message: &str) -> Option<String>;
+}
+
+lazy_static! {
+    static ref LOCALISER: RwLock<Option<Box<dyn ErrorLocaliser>>> = RwLock::new(None);
+}
+
+/// Set the localiser to be used by `Error::user_message`, replacing any previously set
+pub fn set_error_localiser(localiser: Box<dyn ErrorLocaliser>) {
+    match LOCALISER.write() {
+        Ok(mut current) => *current = Some(localiser),
+        Err(_) => warn!("Failed to set error localiser, lock is poisoned"),
+    }
+}
+
+/// Remove any localiser previously set, thus using the default catalog messages
+pub fn reset_error_localiser() {
+    if let Ok(mut current) = LOCALISER.write() {
+        *current = None;
+    }
+}
+
+/// Default (english) user message for the given error code
+pub fn default_error_message(code: &str) -> &'static str {
+    match code {
+        "auth_error" | "auth_ipc_error" => "The application could not be authorised.",
+        "authd_client_error" | "authd_error" => {
+            "There was a problem communicating with the authenticator service."
+        }
+        "authd_already_started" => "The authenticator service is already running.",
+        "authenticator_error" => "The authenticator could not complete the operation.",
+        "connection_error" => "Could not connect to the network.",
+        "net_data_error" | "client_error" => "The network could not complete the operation.",
+        "content_not_found" => "The content could not be found.",
+        "content_error" => "The content is invalid or corrupted.",
+        "empty_content" => "The content is empty.",
+        "access_denied" => "You don't have permission to access this content.",
+        "not_owner" => "Only the owner of this content can perform this operation.",
+        "not_writer" => "You don't have permission to modify this content.",
+        "not_reader" => "This content is private, you don't have permission to read it.",
+        "scope_mismatch" => {
+            "This operation is not supported on content that is public, or private, like this one."
+        }
+        "wrong_keypair" => "This content belongs to a different keypair than the one in use.",
+        "version_not_found" => "The requested version of the content could not be found.",
+        "hash_not_found" | "entry_not_found" => "The requested entry could not be found.",
+        "entry_exists" => "The entry already exists.",
+        "invalid_amount" => "The amount is invalid.",
+        "invalid_xorurl" | "url_error" => "The URL is invalid.",
+        "invalid_input" => "The input provided is invalid.",
+        "invalid_media_type" => "The media type is invalid.",
+        "not_enough_balance" => "There is not enough balance to complete the operation.",
+        "serialisation" => "The data could not be encoded or decoded.",
+        "file_system_error" => "There was a problem accessing the local file system.",
+        "not_implemented" => "This operation is not supported yet.",
+        "multimap_fork" => "The content was modified concurrently and needs to be merged.",
+        "lease_unavailable" => "The resource is currently locked by someone else.",
+        "decode_error" => "The content is not in the format that was expected.",
+        "concurrent_write" => "The content was modified by someone else, please try again.",
+        "untrusted_network" => "The network could not be verified to be a trusted one.",
+        "insufficient_entropy" => "Keys could not be generated securely on this device.",
+        "signer_error" => "The content could not be signed, please check your signing device.",
+        "nrs_conflict" => "The name was updated concurrently and needs to be reconciled.",
+        _ => "An unexpected error occurred.",
+    }
+}
+
+// User message for the given error code, as provided by the localiser if there is one set
+pub(crate) fn user_message(code: &str) -> String {
+    let default_message = default_error_message(code);
+    LOCALISER
+        .read()
+        .ok()
+        .and_then(|localiser| {
+            localiser
+                .as_ref()
+                .and_then(|localiser| localiser.localise(code, default_message))
+        })
+        .unwrap_or_else(|| default_message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    struct SpanishLocaliser;
+
+    impl ErrorLocaliser for SpanishLocaliser {
+        fn localise(&self, code: &str, _default_message: &str) -> Option<String> {
+            match code {
+                "access_denied" => Some("No tienes permiso para acceder a este contenido.".into()),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_catalog_localiser() {
+        let access_denied = Error::AccessDenied("technical details".to_string());
+        let not_found = Error::ContentNotFound("technical details".to_string());
+        assert_eq!(access_denied.code(), "access_denied");
+        assert_eq!(
+            access_denied.user_message(),
+            default_error_message("access_denied")
+        );
+
+        set_error_localiser(Box::new(SpanishLocaliser));
+        assert_eq!(
+            access_denied.user_message(),
+            "No tienes permiso para acceder a este contenido."
+        );
+        assert_eq!(
+            not_found.user_message(),
+            default_error_message("content_not_found")
+        );
+
+        reset_error_localiser();
+        assert_eq!(
+            access_denied.user_message(),
+            default_error_message("access_denied")
+        );
+    }
+}
@@ -7,13 +7,42 @@
 // specific language governing permissions and limitations relating to use of the SAFE Network
 // Software.
 
-use super::ipc::IpcError;
+use super::{error_catalog, ipc::IpcError};
 use safe_network::client::Error as ClientError;
 use safe_network::url::Error as UrlError;
+use std::fmt;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Permission which was missing for an operation to be allowed, see `Error::PermissionDenied`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingPermission {
+    /// The operation can only be performed by the owner of the content
+    Owner,
+    /// The operation can only be performed by the writers of the content
+    Writer,
+    /// The content is private, and can only be read by its owner and its writers
+    Reader,
+    /// The operation is not supported on content with this scope, e.g. deleting public content
+    Scope,
+    /// The content was encrypted with a key derived from a different keypair than the one in use
+    Keypair,
+}
+
+impl fmt::Display for MissingPermission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            Self::Owner => "the requester is not the owner",
+            Self::Writer => "the requester is not among the writers",
+            Self::Reader => "the content is private and the requester is not allowed to read it",
+            Self::Scope => "the operation is not supported on content with this scope",
+            Self::Keypair => "the content belongs to a different keypair than the one in use",
+        };
+        write!(f, "{}", description)
+    }
+}
+
 /// Error type returned by the API
 #[derive(Debug, Error)]
 #[allow(clippy::large_enum_variant)]
@@ -58,6 +87,12 @@ pub enum Error {
     /// AccessDenied
     #[error("AccessDenied: {0}")]
     AccessDenied(String),
+    /// PermissionDenied, telling which permission was missing on which address
+    #[error("PermissionDenied: {missing} (address: {address})")]
+    PermissionDenied {
+        missing: MissingPermission,
+        address: String,
+    },
     /// VersionNotFound
     #[error("VersionNotFound: {0}")]
     VersionNotFound(String),
@@ -101,4 +136,85 @@ pub enum Error {
     /// MultimapFork
     #[error("MultimapFork: {0}")]
     MultimapFork(String),
+    /// LeaseUnavailable
+    #[error("LeaseUnavailable: {0}")]
+    LeaseUnavailable(String),
+    /// DecodeError
+    #[error("DecodeError: {0}")]
+    DecodeError(String),
+    /// ConcurrentWrite
+    #[error("ConcurrentWrite: {0}")]
+    ConcurrentWrite(String),
+    /// UntrustedNetwork
+    #[error("UntrustedNetwork: {0}")]
+    UntrustedNetwork(String),
+    /// InsufficientEntropy
+    #[error("InsufficientEntropy: {0}")]
+    InsufficientEntropy(String),
+    /// SignerError
+    #[error("SignerError: {0}")]
+    SignerError(String),
+    /// NrsConflict
+    #[cfg(feature = "app")]
+    #[error("NrsConflict: {0}")]
+    NrsConflict(Box<crate::nrs::NrsConflict>),
+}
+
+impl Error {
+    /// Stable code identifying the kind of error, which unlike the error's message
+    /// is not meant to change across versions, e.g. to be used as key for localisation.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::AuthError(_) => "auth_error",
+            Self::AuthIpcError(_) => "auth_ipc_error",
+            Self::AuthdClientError(_) => "authd_client_error",
+            Self::AuthdError(_) => "authd_error",
+            Self::AuthdAlreadyStarted(_) => "authd_already_started",
+            Self::AuthenticatorError(_) => "authenticator_error",
+            Self::ConnectionError(_) => "connection_error",
+            Self::NetDataError(_) => "net_data_error",
+            Self::ContentNotFound(_) => "content_not_found",
+            Self::ContentError(_) => "content_error",
+            Self::ClientError(_) => "client_error",
+            Self::EmptyContent(_) => "empty_content",
+            Self::AccessDenied(_) => "access_denied",
+            Self::PermissionDenied { missing, .. } => match missing {
+                MissingPermission::Owner => "not_owner",
+                MissingPermission::Writer => "not_writer",
+                MissingPermission::Reader => "not_reader",
+                MissingPermission::Scope => "scope_mismatch",
+                MissingPermission::Keypair => "wrong_keypair",
+            },
+            Self::VersionNotFound(_) => "version_not_found",
+            #[cfg(feature = "app")]
+            Self::HashNotFound(_) => "hash_not_found",
+            Self::EntryNotFound(_) => "entry_not_found",
+            Self::EntryExists(_) => "entry_exists",
+            Self::InvalidAmount(_) => "invalid_amount",
+            Self::InvalidXorUrl(_) => "invalid_xorurl",
+            Self::InvalidInput(_) => "invalid_input",
+            Self::InvalidMediaType(_) => "invalid_media_type",
+            Self::NotEnoughBalance(_) => "not_enough_balance",
+            Self::Serialisation(_) => "serialisation",
+            Self::FileSystemError(_) => "file_system_error",
+            Self::UrlError(_) => "url_error",
+            Self::NotImplementedError(_) => "not_implemented",
+            Self::MultimapFork(_) => "multimap_fork",
+            Self::LeaseUnavailable(_) => "lease_unavailable",
+            Self::DecodeError(_) => "decode_error",
+            Self::ConcurrentWrite(_) => "concurrent_write",
+            Self::UntrustedNetwork(_) => "untrusted_network",
+            Self::InsufficientEntropy(_) => "insufficient_entropy",
+            Self::SignerError(_) => "signer_error",
+            #[cfg(feature = "app")]
+            Self::NrsConflict(_) => "nrs_conflict",
+        }
+    }
+
+    /// Message meant to be displayed to end users, as provided by the localiser set
+    /// with `set_error_localiser`, or taken from the default (english) catalog otherwise.
+    /// The technical details of the error are only available through its `Display` impl.
+    pub fn user_message(&self) -> String {
+        error_catalog::user_message(self.code())
+    }
 }
@@ -18,7 +18,10 @@ mod authd_client;
 mod authenticator;
 mod common;
 mod constants;
+mod error_catalog;
 mod errors;
+#[cfg(feature = "app")]
+pub mod v1;
 
 // re-export these useful types from sn_data_types
 pub use safe_network::types::{
@@ -44,4 +47,7 @@ pub use common::auth_types::*;
 
 pub use common::{ed_sk_from_hex, sk_to_hex};
 
-pub use errors::{Error, Result};
+pub use error_catalog::{
+    default_error_message, reset_error_localiser, set_error_localiser, ErrorLocaliser,
+};
+pub use errors::{Error, MissingPermission, Result};
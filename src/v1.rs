@@ -0,0 +1,439 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! Version 1 of the stable API.
+//!
+//! The types of this module are not changed in a breaking way without bumping the major
+//! version of this crate, as opposed to the types the API re-exports from safe_network,
+//! e.g. Register entries and their hashes, or URLs, which change with it. Applications
+//! can depend on them, and convert from and to the types the API takes and returns with
+//! the adapters provided, to survive the changes of the underlying types:
+//!
+//! ```no_run
+//! use sn_api::v1::*;
+//! # let rt = tokio::runtime::Runtime::new().unwrap();
+//! # rt.block_on(async {
+//! #   let mut safe = Safe::default();
+//! #   safe.connect(None, None, None).await.unwrap();
+//! #   let xorurl = safe.register_create(None, 25_000, false).await.unwrap();
+//!     let entry = Entry::new("safe://my-entry").unwrap();
+//!     let hash = safe
+//!         .write_to_register(&xorurl, entry.to_url().unwrap(), Default::default())
+//!         .await
+//!         .unwrap();
+//!     println!("Entry written with hash: {}", EntryHash::from(hash));
+//! # });
+//! ```
+//!
+//! The addresses of content on the network, and their scope, are also provided as types
+//! owned by this crate, with conversions from and to the safe_network types for the
+//! advanced users which need to work with the latter.
+
+use crate::{app::register, Error, Url, VersionHash, XorName};
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display},
+};
+
+pub use crate::{Result, Safe, XorUrl};
+
+/// Hash of a Register entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct EntryHash([u8; 32]);
+
+impl EntryHash {
+    /// Parse a hash from its hex encoded form, as it's displayed
+    pub fn from_hex(hex_str: &str) -> Result<Self> {
+        let bytes = hex::decode(hex_str)
+            .map_err(|err| Error::InvalidInput(format!("Invalid entry hash: {}", err)))?;
+        let hash = <[u8; 32]>::try_from(bytes.as_slice()).map_err(|_| {
+            Error::InvalidInput(format!(
+                "Invalid entry hash length, expected 32 bytes but got {}",
+                bytes.len()
+            ))
+        })?;
+        Ok(Self(hash))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl Display for EntryHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+impl From<register::EntryHash> for EntryHash {
+    fn from(hash: register::EntryHash) -> Self {
+        Self(hash)
+    }
+}
+
+impl From<EntryHash> for register::EntryHash {
+    fn from(hash: EntryHash) -> Self {
+        hash.0
+    }
+}
+
+/// URL of any content on the network, either a XOR-URL or an NRS-URL
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct SafeUrl(String);
+
+impl SafeUrl {
+    /// Validate a URL, failing if it's not a valid safe:// URL
+    pub fn parse(url: &str) -> Result<Self> {
+        let _ = Url::from_url(url)?;
+        Ok(Self(url.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Convert it to the URL type the API takes
+    pub fn to_url(&self) -> Result<Url> {
+        Ok(Url::from_url(&self.0)?)
+    }
+}
+
+impl Display for SafeUrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<&Url> for SafeUrl {
+    fn from(url: &Url) -> Self {
+        Self(url.to_string())
+    }
+}
+
+impl From<Url> for SafeUrl {
+    fn from(url: Url) -> Self {
+        Self::from(&url)
+    }
+}
+
+impl TryFrom<SafeUrl> for Url {
+    type Error = Error;
+
+    fn try_from(url: SafeUrl) -> Result<Self> {
+        url.to_url()
+    }
+}
+
+/// A Register entry, which is a link to the content the entry holds
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Entry(SafeUrl);
+
+impl Entry {
+    /// An entry linking to the URL, failing if it's not a valid safe:// URL
+    pub fn new(url: &str) -> Result<Self> {
+        SafeUrl::parse(url).map(Self)
+    }
+
+    /// The URL the entry links to
+    pub fn link(&self) -> &SafeUrl {
+        &self.0
+    }
+
+    /// Convert it to the entry type the API takes
+    pub fn to_url(&self) -> Result<register::Entry> {
+        self.0.to_url()
+    }
+}
+
+impl From<&register::Entry> for Entry {
+    fn from(entry: &register::Entry) -> Self {
+        Self(SafeUrl::from(entry))
+    }
+}
+
+impl From<register::Entry> for Entry {
+    fn from(entry: register::Entry) -> Self {
+        Self::from(&entry)
+    }
+}
+
+/// Version of versioned content, e.g. FilesContainers and NrsMapContainers
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Version(String);
+
+impl Version {
+    /// Parse a version from the form it's displayed, as in the `v` query param of URLs
+    pub fn parse(version: &str) -> Result<Self> {
+        let _ = parse_version_hash(version)?;
+        Ok(Self(version.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<VersionHash> for Version {
+    fn from(version: VersionHash) -> Self {
+        Self(version.to_string())
+    }
+}
+
+impl From<EntryHash> for Version {
+    fn from(hash: EntryHash) -> Self {
+        Self::from(VersionHash::from(&hash.0))
+    }
+}
+
+impl TryFrom<Version> for VersionHash {
+    type Error = Error;
+
+    fn try_from(version: Version) -> Result<Self> {
+        parse_version_hash(&version.0)
+    }
+}
+
+fn parse_version_hash(version: &str) -> Result<VersionHash> {
+    version
+        .parse::<VersionHash>()
+        .map_err(|err| Error::InvalidInput(format!("Invalid version '{}': {:?}", version, err)))
+}
+
+/// Whether content is readable by anyone or only by its owner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Scope {
+    Public,
+    Private,
+}
+
+impl From<crate::Scope> for Scope {
+    fn from(scope: crate::Scope) -> Self {
+        match scope {
+            crate::Scope::Public => Self::Public,
+            crate::Scope::Private => Self::Private,
+        }
+    }
+}
+
+impl From<Scope> for crate::Scope {
+    fn from(scope: Scope) -> Self {
+        match scope {
+            Scope::Public => Self::Public,
+            Scope::Private => Self::Private,
+        }
+    }
+}
+
+/// Address of a Register, i.e. its name, type tag and scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct RegisterAddress {
+    pub name: [u8; 32],
+    pub tag: u64,
+    pub scope: Scope,
+}
+
+impl From<&crate::RegisterAddress> for RegisterAddress {
+    fn from(address: &crate::RegisterAddress) -> Self {
+        let scope = if address.is_public() {
+            Scope::Public
+        } else {
+            Scope::Private
+        };
+        Self {
+            name: address.name().0,
+            tag: address.tag(),
+            scope,
+        }
+    }
+}
+
+impl From<crate::RegisterAddress> for RegisterAddress {
+    fn from(address: crate::RegisterAddress) -> Self {
+        Self::from(&address)
+    }
+}
+
+impl From<RegisterAddress> for crate::RegisterAddress {
+    fn from(address: RegisterAddress) -> Self {
+        match DataAddress::from(address).into() {
+            crate::DataAddress::Register(address) => address,
+            // a Register's DataAddress is always built from a RegisterAddress
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Address of immutable content, i.e. its name and scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum BytesAddress {
+    Public([u8; 32]),
+    Private([u8; 32]),
+}
+
+impl BytesAddress {
+    pub fn name(&self) -> &[u8; 32] {
+        match self {
+            Self::Public(name) | Self::Private(name) => name,
+        }
+    }
+
+    pub fn scope(&self) -> Scope {
+        match self {
+            Self::Public(_) => Scope::Public,
+            Self::Private(_) => Scope::Private,
+        }
+    }
+}
+
+impl From<crate::BytesAddress> for BytesAddress {
+    fn from(address: crate::BytesAddress) -> Self {
+        match address {
+            crate::BytesAddress::Public(name) => Self::Public(name.0),
+            crate::BytesAddress::Private(name) => Self::Private(name.0),
+        }
+    }
+}
+
+impl From<BytesAddress> for crate::BytesAddress {
+    fn from(address: BytesAddress) -> Self {
+        match address {
+            BytesAddress::Public(name) => Self::Public(XorName(name)),
+            BytesAddress::Private(name) => Self::Private(XorName(name)),
+        }
+    }
+}
+
+/// Address of content on the network. Only the kinds of content the API works
+/// with are supported, converting any other address fails with `Error::InvalidInput`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum DataAddress {
+    Register(RegisterAddress),
+    Bytes(BytesAddress),
+}
+
+impl TryFrom<crate::DataAddress> for DataAddress {
+    type Error = Error;
+
+    fn try_from(address: crate::DataAddress) -> Result<Self> {
+        match address {
+            crate::DataAddress::Register(address) => Ok(Self::Register(address.into())),
+            crate::DataAddress::Bytes(address) => Ok(Self::Bytes(address.into())),
+            other => Err(Error::InvalidInput(format!(
+                "Address not supported by the stable API: {:?}",
+                other
+            ))),
+        }
+    }
+}
+
+impl From<RegisterAddress> for DataAddress {
+    fn from(address: RegisterAddress) -> Self {
+        Self::Register(address)
+    }
+}
+
+impl From<BytesAddress> for DataAddress {
+    fn from(address: BytesAddress) -> Self {
+        Self::Bytes(address)
+    }
+}
+
+impl From<DataAddress> for crate::DataAddress {
+    fn from(address: DataAddress) -> Self {
+        match address {
+            DataAddress::Register(RegisterAddress { name, tag, scope }) => {
+                Self::register(XorName(name), scope.into(), tag)
+            }
+            DataAddress::Bytes(address) => Self::Bytes(address.into()),
+        }
+    }
+}
+
+impl TryFrom<&Url> for DataAddress {
+    type Error = Error;
+
+    fn try_from(url: &Url) -> Result<Self> {
+        Self::try_from(url.address())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UrlAddressExt;
+    use anyhow::Result;
+
+    #[test]
+    fn test_v1_adapters_roundtrip() -> Result<()> {
+        let internal_hash: register::EntryHash = [7; 32];
+        let hash = EntryHash::from(internal_hash);
+        assert_eq!(EntryHash::from_hex(&hash.to_string())?, hash);
+        assert_eq!(register::EntryHash::from(hash), internal_hash);
+        assert!(EntryHash::from_hex("0707").is_err());
+
+        let url = Url::from_url("safe://an-entry")?;
+        let entry = Entry::from(&url);
+        assert_eq!(entry.link().as_str(), url.to_string());
+        assert_eq!(entry.to_url()?, url);
+        assert!(Entry::new("not a url").is_err());
+
+        let version = Version::from(hash);
+        let internal_version = VersionHash::try_from(Version::parse(version.as_str())?)?;
+        assert_eq!(internal_version, VersionHash::from(&internal_hash));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_v1_addresses_roundtrip() -> Result<()> {
+        let register = RegisterAddress {
+            name: [1; 32],
+            tag: 25_000,
+            scope: Scope::Private,
+        };
+        let internal = crate::RegisterAddress::from(register);
+        assert!(!internal.is_public());
+        assert_eq!(internal.tag(), 25_000);
+        assert_eq!(RegisterAddress::from(internal), register);
+
+        let bytes = BytesAddress::Public([2; 32]);
+        assert_eq!(bytes.scope(), Scope::Public);
+        assert_eq!(BytesAddress::from(crate::BytesAddress::from(bytes)), bytes);
+
+        for address in vec![DataAddress::from(register), DataAddress::from(bytes)] {
+            let internal = crate::DataAddress::from(address);
+            assert_eq!(DataAddress::try_from(internal)?, address);
+        }
+
+        let url = Url::from_register_parts(
+            XorName([3; 32]),
+            25_000,
+            crate::Scope::Public,
+            crate::DEFAULT_XORURL_BASE,
+        )?;
+        let address = DataAddress::try_from(&url)?;
+        assert!(matches!(
+            address,
+            DataAddress::Register(RegisterAddress {
+                scope: Scope::Public,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+}